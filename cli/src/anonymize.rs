@@ -0,0 +1,354 @@
+use anchor_syn::idl::{EnumFields, Idl, IdlField, IdlType, IdlTypeDefinition, IdlTypeDefinitionTy};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::hash::{hash, hashv};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+// An account as written by `solana account --output json`, i.e., the format
+// loaded by the test validator's `--account` flag.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountFixture {
+    pub pubkey: String,
+    pub account: FixtureAccount,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixtureAccount {
+    pub lamports: u64,
+    // Encoded data and its encoding, e.g. `["AAEC", "base64"]`.
+    pub data: (String, String),
+    pub owner: String,
+    pub executable: bool,
+    pub rent_epoch: u64,
+    // Any other fields are kept as is.
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+// Consistently remaps public keys to random looking ones, i.e., the same key
+// is always replaced by the same key, so that relations between accounts
+// (owners, authorities, references stored in account data, ...) survive.
+//
+// Replacements are derived from a secret salt, so that they can't be reversed
+// by hashing candidate addresses.
+pub struct Anonymizer {
+    salt: [u8; 32],
+    keep: BTreeSet<Pubkey>,
+    mapping: BTreeMap<Pubkey, Pubkey>,
+}
+
+impl Anonymizer {
+    pub fn new(salt: [u8; 32], keep: impl IntoIterator<Item = Pubkey>) -> Self {
+        // The default key usually stands for "none", e.g. in a `COption`.
+        let mut keep: BTreeSet<Pubkey> = keep.into_iter().collect();
+        keep.insert(Pubkey::default());
+        keep.insert(solana_sdk::system_program::ID);
+        keep.insert(solana_sdk::sysvar::ID);
+        Self {
+            salt,
+            keep,
+            mapping: BTreeMap::new(),
+        }
+    }
+
+    pub fn remap(&mut self, pubkey: &Pubkey) -> Pubkey {
+        if self.keep.contains(pubkey) {
+            return *pubkey;
+        }
+        let salt = &self.salt;
+        *self
+            .mapping
+            .entry(*pubkey)
+            .or_insert_with(|| Pubkey::new_from_array(hashv(&[salt, pubkey.as_ref()]).to_bytes()))
+    }
+
+    // Original to anonymized keys, for everything remapped so far.
+    pub fn mapping(&self) -> &BTreeMap<Pubkey, Pubkey> {
+        &self.mapping
+    }
+
+    // Anonymizes a set of captured accounts.
+    //
+    // Addresses are remapped, while owners and executable accounts (i.e.
+    // programs) are kept, since the fixtures couldn't be loaded otherwise.
+    // Within account data, every occurrence of one of the captured addresses
+    // is remapped. If an IDL is given, all `publicKey` fields of the program's
+    // accounts are remapped as well, wherever they point to.
+    //
+    // Note that PDAs of remapped keys won't match their derivation anymore,
+    // these must be kept explicitly if the program re-derives them.
+    pub fn anonymize(&mut self, fixtures: &mut [AccountFixture], idl: Option<&Idl>) -> Result<()> {
+        let mut known = BTreeSet::new();
+        for fixture in fixtures.iter() {
+            let address = parse_pubkey(&fixture.pubkey)?;
+            self.keep.insert(parse_pubkey(&fixture.account.owner)?);
+            if fixture.account.executable {
+                self.keep.insert(address);
+            }
+            known.insert(address);
+        }
+        let known: Vec<Pubkey> = known
+            .into_iter()
+            .filter(|address| !self.keep.contains(address))
+            .collect();
+
+        for fixture in fixtures.iter_mut() {
+            let address = parse_pubkey(&fixture.pubkey)?;
+            fixture.pubkey = self.remap(&address).to_string();
+
+            let (data, encoding) = &fixture.account.data;
+            if encoding != "base64" {
+                return Err(anyhow!(
+                    "{}: unsupported data encoding {}, expected base64",
+                    address,
+                    encoding
+                ));
+            }
+            let mut data = base64::decode(data)?;
+            if let Some(idl) = idl {
+                for offset in pubkey_offsets(idl, &data) {
+                    self.remap_at(&mut data, offset);
+                }
+            }
+            self.remap_known(&mut data, &known);
+            fixture.account.data.0 = base64::encode(&data);
+        }
+        Ok(())
+    }
+
+    fn remap_at(&mut self, data: &mut [u8], offset: usize) {
+        let slice = &mut data[offset..offset + 32];
+        let pubkey = Pubkey::new(slice);
+        slice.copy_from_slice(self.remap(&pubkey).as_ref());
+    }
+
+    // Remaps every (possibly unaligned) occurrence of a known key.
+    fn remap_known(&mut self, data: &mut [u8], known: &[Pubkey]) {
+        let mut offset = 0;
+        while offset + 32 <= data.len() {
+            let window = &data[offset..offset + 32];
+            if known.iter().any(|pubkey| pubkey.as_ref() == window) {
+                self.remap_at(data, offset);
+                offset += 32;
+            } else {
+                offset += 1;
+            }
+        }
+    }
+}
+
+fn parse_pubkey(s: &str) -> Result<Pubkey> {
+    Pubkey::from_str(s).map_err(|e| anyhow!("Invalid public key {}: {}", s, e))
+}
+
+// Offsets of all `publicKey` fields in the data, if it holds one of the IDL's
+// accounts. Data that doesn't deserialize (e.g. zero copy accounts) yields
+// nothing.
+fn pubkey_offsets(idl: &Idl, data: &[u8]) -> Vec<usize> {
+    if data.len() < 8 {
+        return vec![];
+    }
+    let account = idl.accounts.iter().find(|acc| {
+        let discriminator = hash(format!("account:{}", acc.name).as_bytes());
+        discriminator.to_bytes()[..8] == data[..8]
+    });
+    let account = match account {
+        Some(account) => account,
+        None => return vec![],
+    };
+    let mut walker = Walker {
+        idl,
+        data,
+        offset: 8,
+        pubkeys: vec![],
+    };
+    match walker.type_definition(account) {
+        Some(()) => walker.pubkeys,
+        None => vec![],
+    }
+}
+
+// Walks borsh encoded data along the IDL types, recording where public keys
+// are. Returns `None` once the data doesn't match the types.
+struct Walker<'a> {
+    idl: &'a Idl,
+    data: &'a [u8],
+    offset: usize,
+    pubkeys: Vec<usize>,
+}
+
+impl<'a> Walker<'a> {
+    fn type_definition(&mut self, ty_def: &IdlTypeDefinition) -> Option<()> {
+        match &ty_def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => self.fields(fields),
+            IdlTypeDefinitionTy::Enum { variants } => {
+                let variant = variants.get(self.take(1)?[0] as usize)?;
+                match &variant.fields {
+                    None => Some(()),
+                    Some(EnumFields::Named(fields)) => self.fields(fields),
+                    Some(EnumFields::Tuple(tys)) => tys.iter().try_for_each(|ty| self.ty(ty)),
+                }
+            }
+        }
+    }
+
+    fn fields(&mut self, fields: &[IdlField]) -> Option<()> {
+        fields.iter().try_for_each(|f| self.ty(&f.ty))
+    }
+
+    fn ty(&mut self, ty: &IdlType) -> Option<()> {
+        match ty {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 => self.skip(1),
+            IdlType::U16 | IdlType::I16 => self.skip(2),
+            IdlType::U32 | IdlType::I32 | IdlType::F32 => self.skip(4),
+            IdlType::U64 | IdlType::I64 | IdlType::F64 => self.skip(8),
+            IdlType::U128 | IdlType::I128 => self.skip(16),
+            IdlType::Float(float) => self.skip(float.bits as usize / 8),
+            IdlType::Bytes | IdlType::String => {
+                let len = self.len()?;
+                self.skip(len)
+            }
+            IdlType::PublicKey => {
+                self.pubkeys.push(self.offset);
+                self.skip(32)
+            }
+            IdlType::Defined(name) => {
+                let idl = self.idl;
+                let ty_def = idl
+                    .types
+                    .iter()
+                    .chain(idl.accounts.iter())
+                    .find(|ty_def| &ty_def.name == name)?;
+                self.type_definition(ty_def)
+            }
+            IdlType::Option(ty) => match self.take(1)?[0] {
+                0 => Some(()),
+                1 => self.ty(ty),
+                _ => None,
+            },
+            IdlType::Vec(ty) => {
+                let len = self.len()?;
+                (0..len).try_for_each(|_| self.ty(ty))
+            }
+            IdlType::Array(ty, len) => (0..*len).try_for_each(|_| self.ty(ty)),
+        }
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        let bytes = self.take(4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.offset.checked_add(len)?;
+        let bytes = self.data.get(self.offset..end)?;
+        self.offset = end;
+        Some(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(pubkey: &Pubkey, owner: &Pubkey, data: &[u8]) -> AccountFixture {
+        AccountFixture {
+            pubkey: pubkey.to_string(),
+            account: FixtureAccount {
+                lamports: 1_000_000,
+                data: (base64::encode(data), "base64".to_string()),
+                owner: owner.to_string(),
+                executable: false,
+                rent_epoch: 0,
+                other: Default::default(),
+            },
+        }
+    }
+
+    fn data(fixture: &AccountFixture) -> Vec<u8> {
+        base64::decode(&fixture.account.data.0).unwrap()
+    }
+
+    #[test]
+    fn remaps_consistently() {
+        let program = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let vault = Pubkey::new_unique();
+        // An unknown key (e.g. a counterparty that wasn't captured), followed
+        // by an unaligned reference to a captured account.
+        let counterparty = Pubkey::new_unique();
+        let mut vault_data = vec![0u8; 8];
+        vault_data.extend_from_slice(counterparty.as_ref());
+        vault_data.push(7);
+        vault_data.extend_from_slice(authority.as_ref());
+        let mut fixtures = vec![
+            fixture(&authority, &solana_sdk::system_program::ID, &[]),
+            fixture(&vault, &program, &vault_data),
+        ];
+
+        let mut anonymizer = Anonymizer::new([1; 32], vec![]);
+        anonymizer.anonymize(&mut fixtures, None).unwrap();
+
+        let new_authority = anonymizer.mapping()[&authority];
+        assert_ne!(new_authority, authority);
+        assert_eq!(fixtures[0].pubkey, new_authority.to_string());
+        assert_eq!(fixtures[1].account.owner, program.to_string());
+        let data = data(&fixtures[1]);
+        assert_eq!(&data[8..40], counterparty.as_ref());
+        assert_eq!(data[40], 7);
+        assert_eq!(&data[41..], new_authority.as_ref());
+
+        // Another salt gives other keys.
+        let mut other = Anonymizer::new([2; 32], vec![]);
+        assert_ne!(other.remap(&authority), new_authority);
+    }
+
+    #[test]
+    fn remaps_idl_fields() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "escrow",
+            "instructions": [],
+            "accounts": [{
+                "name": "Escrow",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "memo", "type": "string" },
+                        { "name": "maker", "type": { "option": "publicKey" } },
+                        { "name": "takers", "type": { "vec": "publicKey" } }
+                    ]
+                }
+            }]
+        }))
+        .unwrap();
+        let maker = Pubkey::new_unique();
+        let taker = Pubkey::new_unique();
+        let mut escrow_data = hash(b"account:Escrow").to_bytes()[..8].to_vec();
+        escrow_data.extend_from_slice(&[2, 0, 0, 0, b'h', b'i']);
+        escrow_data.push(1);
+        escrow_data.extend_from_slice(maker.as_ref());
+        escrow_data.extend_from_slice(&[1, 0, 0, 0]);
+        escrow_data.extend_from_slice(taker.as_ref());
+        let mut fixtures = vec![fixture(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &escrow_data,
+        )];
+
+        let mut anonymizer = Anonymizer::new([1; 32], vec![taker]);
+        anonymizer.anonymize(&mut fixtures, Some(&idl)).unwrap();
+
+        let data = data(&fixtures[0]);
+        assert_eq!(&data[..15], &escrow_data[..15]);
+        assert_eq!(&data[15..47], anonymizer.mapping()[&maker].as_ref());
+        assert_eq!(&data[51..], taker.as_ref());
+    }
+}
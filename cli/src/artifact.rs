@@ -0,0 +1,310 @@
+use anchor_syn::idl::Idl;
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use solana_sdk::hash::hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const ARTIFACT_EXTENSION: &str = "anchorpkg";
+
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+const BINARY_FILE: &str = "program.so";
+const IDL_FILE: &str = "idl.json";
+
+// A release of a program as a single file, so that it can be distributed and
+// verified as one unit: a gzipped tarball of the program binary, its IDL and
+// a manifest describing them, optionally signed by the publisher.
+//
+// Layout:
+//
+//   manifest.json  the manifest, with the digests of the other files
+//   program.so     the program binary
+//   idl.json       the IDL, if any, in its canonical serialization
+pub struct Artifact {
+    pub manifest: Manifest,
+    pub binary: Vec<u8>,
+    pub idl: Option<Idl>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub format_version: u32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program_id: Option<String>,
+    pub binary: FileDigest,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idl: Option<FileDigest>,
+    pub toolchain: Toolchain,
+    // Signature of the manifest without it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ManifestSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileDigest {
+    pub size: u64,
+    pub sha256: String,
+}
+
+// The toolchain the program was built with, to reproduce the build.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Toolchain {
+    pub anchor_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solana_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rustc_version: Option<String>,
+    // Image of verifiable builds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docker_image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cargo_lock_sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestSignature {
+    pub signer: String,
+    pub signature: String,
+}
+
+impl FileDigest {
+    pub fn of(bytes: &[u8]) -> Self {
+        Self {
+            size: bytes.len() as u64,
+            // Hex, as given by `sha256sum`.
+            sha256: hash(bytes)
+                .to_bytes()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+        }
+    }
+}
+
+impl Artifact {
+    pub fn new(
+        name: String,
+        program_id: Option<Pubkey>,
+        binary: Vec<u8>,
+        idl: Option<Idl>,
+        toolchain: Toolchain,
+    ) -> Result<Self> {
+        let idl_digest = match &idl {
+            None => None,
+            Some(idl) => Some(FileDigest::of(&canonical_idl(idl)?)),
+        };
+        Ok(Self {
+            manifest: Manifest {
+                format_version: FORMAT_VERSION,
+                name,
+                program_id: program_id.map(|id| id.to_string()),
+                binary: FileDigest::of(&binary),
+                idl: idl_digest,
+                toolchain,
+                signature: None,
+            },
+            binary,
+            idl,
+        })
+    }
+
+    pub fn sign(&mut self, keypair: &Keypair) -> Result<()> {
+        self.manifest.signature = None;
+        let signature = keypair.sign_message(&serde_json::to_vec(&self.manifest)?);
+        self.manifest.signature = Some(ManifestSignature {
+            signer: keypair.pubkey().to_string(),
+            signature: signature.to_string(),
+        });
+        Ok(())
+    }
+
+    // Returns the signer of the manifest, if signed, failing if the signature
+    // doesn't match.
+    pub fn verify_signature(&self) -> Result<Option<Pubkey>> {
+        let signed = match &self.manifest.signature {
+            None => return Ok(None),
+            Some(signed) => signed,
+        };
+        let signer = Pubkey::from_str(&signed.signer)?;
+        let signature = Signature::from_str(&signed.signature)?;
+        let unsigned = Manifest {
+            signature: None,
+            ..self.manifest.clone()
+        };
+        if !signature.verify(signer.as_ref(), &serde_json::to_vec(&unsigned)?) {
+            return Err(anyhow!("Invalid manifest signature of {}", signer));
+        }
+        Ok(Some(signer))
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("Error creating file with path: {}", path.display()))?;
+        let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        let mut files = vec![
+            (MANIFEST_FILE, serde_json::to_vec_pretty(&self.manifest)?),
+            (BINARY_FILE, self.binary.clone()),
+        ];
+        if let Some(idl) = &self.idl {
+            files.push((IDL_FILE, canonical_idl(idl)?));
+        }
+        for (name, bytes) in files {
+            // Headers are fixed, so that the same contents give the same file.
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(0);
+            header.set_cksum();
+            tar.append_data(&mut header, name, bytes.as_slice())?;
+        }
+        tar.into_inner()?.finish()?;
+        Ok(())
+    }
+
+    // Reads an artifact, verifying the digests of its files and its signature.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Error opening file with path: {}", path.display()))?;
+        let mut files = BTreeMap::new();
+        for entry in tar::Archive::new(GzDecoder::new(file)).entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.display().to_string();
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            files.insert(name, bytes);
+        }
+
+        let manifest: Manifest = serde_json::from_slice(
+            files
+                .get(MANIFEST_FILE)
+                .ok_or_else(|| anyhow!("Missing {}", MANIFEST_FILE))?,
+        )?;
+        if manifest.format_version > FORMAT_VERSION {
+            return Err(anyhow!(
+                "Unsupported artifact format version {}",
+                manifest.format_version
+            ));
+        }
+        let binary = verified(&files, BINARY_FILE, Some(&manifest.binary))?.unwrap();
+        let idl = match verified(&files, IDL_FILE, manifest.idl.as_ref())? {
+            None => None,
+            Some(bytes) => Some(serde_json::from_slice(&bytes)?),
+        };
+        let artifact = Self {
+            manifest,
+            binary,
+            idl,
+        };
+        artifact.verify_signature()?;
+        Ok(artifact)
+    }
+
+    // Writes the files of the artifact to `dir`, named after the program.
+    pub fn extract(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let mut paths = Vec::new();
+        let manifest = dir.join(format!("{}.manifest.json", self.manifest.name));
+        fs::write(&manifest, serde_json::to_vec_pretty(&self.manifest)?)?;
+        paths.push(manifest);
+        let binary = dir.join(format!("{}.so", self.manifest.name));
+        fs::write(&binary, &self.binary)?;
+        paths.push(binary);
+        if let Some(idl) = &self.idl {
+            let idl_path = dir.join(format!("{}.json", self.manifest.name));
+            fs::write(&idl_path, canonical_idl(idl)?)?;
+            paths.push(idl_path);
+        }
+        Ok(paths)
+    }
+}
+
+fn canonical_idl(idl: &Idl) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec_pretty(idl)?)
+}
+
+// The file with the given name, if it matches its digest.
+fn verified(
+    files: &BTreeMap<String, Vec<u8>>,
+    name: &str,
+    digest: Option<&FileDigest>,
+) -> Result<Option<Vec<u8>>> {
+    match (files.get(name), digest) {
+        (None, None) => Ok(None),
+        (Some(_), None) => Err(anyhow!("{} isn't in the manifest", name)),
+        (None, Some(_)) => Err(anyhow!("Missing {}", name)),
+        (Some(bytes), Some(digest)) if FileDigest::of(bytes) == *digest => Ok(Some(bytes.clone())),
+        (Some(_), Some(_)) => Err(anyhow!("{} doesn't match its digest", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idl() -> Idl {
+        serde_json::from_str(r#"{"version": "0.1.0", "name": "vault", "instructions": []}"#)
+            .unwrap()
+    }
+
+    #[test]
+    fn write_read_extract() {
+        let dir = std::env::temp_dir().join("anchor_cli_artifact");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("vault.{}", ARTIFACT_EXTENSION));
+
+        let toolchain = Toolchain {
+            anchor_version: "0.25.0".to_string(),
+            ..Toolchain::default()
+        };
+        let mut artifact = Artifact::new(
+            "vault".to_string(),
+            Some(Pubkey::new_unique()),
+            vec![1, 2, 3],
+            Some(idl()),
+            toolchain,
+        )
+        .unwrap();
+        let keypair = Keypair::new();
+        artifact.sign(&keypair).unwrap();
+        artifact.write(&path).unwrap();
+
+        let read = Artifact::read(&path).unwrap();
+        assert_eq!(read.manifest, artifact.manifest);
+        assert_eq!(read.binary, vec![1, 2, 3]);
+        assert_eq!(read.verify_signature().unwrap(), Some(keypair.pubkey()));
+
+        // The same contents give the same file.
+        let bytes = fs::read(&path).unwrap();
+        read.write(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+
+        let paths = read.extract(dir.join("out")).unwrap();
+        assert_eq!(paths.len(), 3);
+        assert_eq!(fs::read(&paths[1]).unwrap(), vec![1, 2, 3]);
+
+        // Tampering is detected.
+        let mut tampered = read;
+        tampered.binary = vec![4];
+        tampered.write(&path).unwrap();
+        assert!(Artifact::read(&path).is_err());
+        tampered.manifest.binary = FileDigest::of(&tampered.binary);
+        tampered.write(&path).unwrap();
+        assert!(Artifact::read(&path).is_err());
+    }
+}
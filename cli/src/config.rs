@@ -1,6 +1,9 @@
 use crate::is_hidden;
 use anchor_client::Cluster;
-use anchor_syn::idl::Idl;
+use anchor_syn::idl::file::{ExternalAccounts, ParseOptions};
+use anchor_syn::idl::mangle::Target;
+use anchor_syn::idl::{DocsPolicy, FloatPolicy, Idl};
+use anchor_syn::parser::safety::SafetyConfig;
 use anyhow::{anyhow, Context, Error, Result};
 use clap::{ArgEnum, Parser};
 use heck::SnakeCase;
@@ -99,11 +102,37 @@ impl Manifest {
         }
     }
 
+    // Whether the program generates its IDL when compiled, i.e., has an
+    // `idl-build` feature, which must enable `anchor-lang/idl-build`.
+    pub fn has_idl_build(&self) -> bool {
+        self.features.contains_key(IDL_BUILD_FEATURE)
+    }
+
     // Climbs each parent directory from the current dir until we find a Cargo.toml
     pub fn discover() -> Result<Option<WithPath<Manifest>>> {
         Manifest::discover_from_path(std::env::current_dir()?)
     }
 
+    // Registers the `Accounts` structs of all dependencies given by a local
+    // `path`, so that composite accounts defined in other crates of the
+    // workspace, e.g. a shared CPI crate, resolve when parsing the IDL.
+    pub fn external_accounts(&self, manifest_dir: &Path) -> ExternalAccounts {
+        let mut external_accounts = ExternalAccounts::default();
+        for dep in self.dependencies.values() {
+            if let cargo_toml::Dependency::Detailed(detail) = dep {
+                if let Some(path) = &detail.path {
+                    let lib = manifest_dir.join(path).join("src/lib.rs");
+                    // Dependencies that can't be parsed simply aren't
+                    // available for resolution.
+                    if lib.exists() && external_accounts.register_source(&lib).is_err() {
+                        println!("WARNING: unable to parse {}", lib.display());
+                    }
+                }
+            }
+        }
+        external_accounts
+    }
+
     // Climbs each parent directory from a given starting directory until we find a Cargo.toml.
     pub fn discover_from_path(start_from: PathBuf) -> Result<Option<WithPath<Manifest>>> {
         let mut cwd_opt = Some(start_from.as_path());
@@ -180,11 +209,16 @@ impl WithPath<Config> {
             let version = cargo.version();
             let idl = anchor_syn::idl::file::parse(
                 path.join("src/lib.rs"),
-                version,
-                self.features.seeds,
-                false,
-                false,
-            )?;
+                &ParseOptions {
+                    version,
+                    seeds_feature: self.features.seeds,
+                    docs_policy: self.features.docs.clone(),
+                    float_policy: self.features.float_policy,
+                    ..Default::default()
+                },
+                &cargo.external_accounts(&path),
+            )?
+            .map(|output| output.idl);
             r.push(Program {
                 lib_name,
                 path,
@@ -275,6 +309,10 @@ pub struct Config {
     pub programs: ProgramsConfig,
     pub scripts: ScriptsConfig,
     pub workspace: WorkspaceConfig,
+    pub clients: ClientsConfig,
+    pub safety_checks: SafetyConfig,
+    pub renames: RenamesConfig,
+    pub interfaces: InterfacesConfig,
     // Separate entry next to test_config because
     // "anchor localnet" only has access to the Anchor.toml,
     // not the Test.toml files
@@ -288,6 +326,18 @@ pub struct FeaturesConfig {
     pub seeds: bool,
     #[serde(default, rename = "skip-lint")]
     pub skip_lint: bool,
+    #[serde(default, rename = "float-policy")]
+    pub float_policy: FloatPolicy,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub docs: DocsPolicy,
+    #[serde(default, rename = "test-vectors")]
+    pub test_vectors: bool,
+    #[serde(default, rename = "budget-report")]
+    pub budget_report: bool,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -313,6 +363,19 @@ pub type ScriptsConfig = BTreeMap<String, String>;
 
 pub type ProgramsConfig = BTreeMap<Cluster, BTreeMap<String, ProgramDeployment>>;
 
+// Client profiles, e.g. "frontend" or "keeper", mapping each program they use
+// to the allowlist of instructions to generate clients for.
+pub type ClientsConfig = BTreeMap<String, BTreeMap<String, Vec<String>>>;
+
+// Renames of names in clients generated for each target, e.g. `type = "kind"`
+// for Python, overriding the default renaming of reserved words.
+pub type RenamesConfig = BTreeMap<Target, BTreeMap<String, String>>;
+
+// Interfaces each program implements, by the program's library name, as paths
+// to the IDL of the interface or to the root of a crate defining `#[interface]`
+// traits, relative to the workspace. Checked by `anchor test`.
+pub type InterfacesConfig = BTreeMap<String, Vec<String>>;
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct WorkspaceConfig {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
@@ -415,6 +478,11 @@ struct _Config {
     provider: Provider,
     workspace: Option<WorkspaceConfig>,
     scripts: Option<ScriptsConfig>,
+    clients: Option<ClientsConfig>,
+    #[serde(rename = "safety-checks")]
+    safety_checks: Option<SafetyConfig>,
+    renames: Option<RenamesConfig>,
+    interfaces: Option<InterfacesConfig>,
     test: Option<_TestValidator>,
 }
 
@@ -451,6 +519,11 @@ impl ToString for Config {
             programs,
             workspace: (!self.workspace.members.is_empty() || !self.workspace.exclude.is_empty())
                 .then(|| self.workspace.clone()),
+            clients: (!self.clients.is_empty()).then(|| self.clients.clone()),
+            safety_checks: (!self.safety_checks.rules.is_empty())
+                .then(|| self.safety_checks.clone()),
+            renames: (!self.renames.is_empty()).then(|| self.renames.clone()),
+            interfaces: (!self.interfaces.is_empty()).then(|| self.interfaces.clone()),
         };
 
         toml::to_string(&cfg).expect("Must be well formed")
@@ -477,6 +550,10 @@ impl FromStr for Config {
             test_config: None,
             programs: cfg.programs.map_or(Ok(BTreeMap::new()), deser_programs)?,
             workspace: cfg.workspace.unwrap_or_default(),
+            clients: cfg.clients.unwrap_or_default(),
+            safety_checks: cfg.safety_checks.unwrap_or_default(),
+            renames: cfg.renames.unwrap_or_default(),
+            interfaces: cfg.interfaces.unwrap_or_default(),
         })
     }
 }
@@ -557,6 +634,7 @@ pub struct TestValidator {
     pub validator: Option<Validator>,
     pub startup_wait: i32,
     pub shutdown_wait: i32,
+    pub backend: Option<TestBackend>,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -569,8 +647,39 @@ pub struct _TestValidator {
     pub startup_wait: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub shutdown_wait: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<TestBackend>,
+}
+
+// Backend the tests execute transactions with, passed to them in the
+// `ANCHOR_TEST_BACKEND` environment variable. See `anchor_client::backend`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TestBackend {
+    // A local test validator, or the cluster's. The default.
+    Validator,
+    // `solana-program-test`, in process.
+    ProgramTest,
+}
+
+impl TestBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TestBackend::Validator => "validator",
+            TestBackend::ProgramTest => "program-test",
+        }
+    }
+
+    // Whether the tests run the programs in process rather than on a
+    // validator.
+    pub fn is_in_process(&self) -> bool {
+        *self != TestBackend::Validator
+    }
 }
 
+// Feature of a program generating its IDL when compiled.
+pub const IDL_BUILD_FEATURE: &str = "idl-build";
+
 pub const STARTUP_WAIT: i32 = 5000;
 pub const SHUTDOWN_WAIT: i32 = 2000;
 
@@ -581,6 +690,7 @@ impl From<_TestValidator> for TestValidator {
             startup_wait: _test_validator.startup_wait.unwrap_or(STARTUP_WAIT),
             genesis: _test_validator.genesis,
             validator: _test_validator.validator.map(Into::into),
+            backend: _test_validator.backend,
         }
     }
 }
@@ -592,6 +702,7 @@ impl From<TestValidator> for _TestValidator {
             startup_wait: Some(test_validator.startup_wait),
             genesis: test_validator.genesis,
             validator: test_validator.validator.map(Into::into),
+            backend: test_validator.backend,
         }
     }
 }
@@ -734,6 +845,9 @@ impl Merge for _TestToml {
                     if let Some(startup_wait) = other_test.startup_wait {
                         my_test.startup_wait = Some(startup_wait);
                     }
+                    if let Some(backend) = other_test.backend {
+                        my_test.backend = Some(backend);
+                    }
                     if let Some(other_genesis) = other_test.genesis {
                         match &mut my_test.genesis {
                             Some(my_genesis) => {
@@ -1155,4 +1269,39 @@ mod tests {
         let config = Config::from_str(&string).unwrap();
         assert!(!config.features.skip_lint);
     }
+
+    #[test]
+    fn parse_float_policy_default() {
+        let config = Config::from_str(BASE_CONFIG).unwrap();
+        assert_eq!(config.features.float_policy, FloatPolicy::Warn);
+    }
+
+    #[test]
+    fn parse_float_policy_allow_with_annotation() {
+        let string =
+            BASE_CONFIG.to_owned() + "[features]\nfloat-policy = \"allow-with-annotation\"";
+        let config = Config::from_str(&string).unwrap();
+        assert_eq!(
+            config.features.float_policy,
+            FloatPolicy::AllowWithAnnotation
+        );
+    }
+
+    #[test]
+    fn parse_docs_policy() {
+        let config = Config::from_str(BASE_CONFIG).unwrap();
+        assert_eq!(config.features.docs, DocsPolicy::default());
+        assert!(!config.to_string().contains("docs"));
+
+        let string = BASE_CONFIG.to_owned() + "[features.docs]\nfields = false\nmax-length = 200";
+        let config = Config::from_str(&string).unwrap();
+        assert_eq!(
+            config.features.docs,
+            DocsPolicy {
+                fields: false,
+                max_length: Some(200),
+                ..DocsPolicy::default()
+            }
+        );
+    }
 }
@@ -0,0 +1,227 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// The dev loop of `anchor dev`.
+//
+// The programs of the workspace run on a local validator as upgradeable
+// programs, with the wallet as their upgrade authority. Each time a source of
+// the programs changes, the loop goes through the stages below, stopping at the
+// first that fails, and reports the status of each of them. A failed stage
+// leaves the validator running the last programs that built, until the next
+// change.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    // Builds the programs, writing their IDL and TypeScript types.
+    Build,
+    // Upgrades the programs whose binary changed on the local validator.
+    Upgrade,
+    // Adds the address of the programs to their IDL.
+    Idl,
+    // Regenerates the client bindings of the programs from their IDL.
+    Clients,
+}
+
+impl std::fmt::Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stage::Build => write!(f, "build"),
+            Stage::Upgrade => write!(f, "upgrade"),
+            Stage::Idl => write!(f, "idl"),
+            Stage::Clients => write!(f, "clients"),
+        }
+    }
+}
+
+// Runs a stage, printing its status and how long it took. Returns whether it
+// succeeded.
+pub fn run_stage(stage: Stage, f: impl FnOnce() -> anyhow::Result<String>) -> bool {
+    println!("[dev] {} ...", stage);
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    match result {
+        Ok(summary) if summary.is_empty() => {
+            println!("[dev] {} ok ({})", stage, format_elapsed(elapsed));
+            true
+        }
+        Ok(summary) => {
+            println!(
+                "[dev] {} ok ({}): {}",
+                stage,
+                format_elapsed(elapsed),
+                summary
+            );
+            true
+        }
+        Err(err) => {
+            println!(
+                "[dev] {} FAILED ({}): {:#}",
+                stage,
+                format_elapsed(elapsed),
+                err
+            );
+            false
+        }
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    format!("{:.1}s", elapsed.as_secs_f64())
+}
+
+// Watches the files under a set of directories for changes by polling their
+// modification time, leaving out hidden files and `target` directories.
+pub struct Watcher {
+    roots: Vec<PathBuf>,
+    stamps: BTreeMap<PathBuf, SystemTime>,
+}
+
+impl Watcher {
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        let mut watcher = Watcher {
+            roots,
+            stamps: BTreeMap::new(),
+        };
+        watcher.stamps = watcher.scan();
+        watcher
+    }
+
+    // The files added, modified or removed since the last call.
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let stamps = self.scan();
+        let mut changed: Vec<PathBuf> = stamps
+            .iter()
+            .filter(|(path, stamp)| self.stamps.get(*path) != Some(stamp))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(
+            self.stamps
+                .keys()
+                .filter(|path| !stamps.contains_key(*path))
+                .cloned(),
+        );
+        changed.sort();
+        self.stamps = stamps;
+        changed
+    }
+
+    fn scan(&self) -> BTreeMap<PathBuf, SystemTime> {
+        self.roots
+            .iter()
+            .flat_map(|root| {
+                walkdir::WalkDir::new(root)
+                    .into_iter()
+                    .filter_entry(|entry| !is_ignored(entry))
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_type().is_file())
+                    .filter_map(|entry| {
+                        let modified = entry.metadata().ok()?.modified().ok()?;
+                        Some((entry.into_path(), modified))
+                    })
+            })
+            .collect()
+    }
+}
+
+fn is_ignored(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() > 0
+        && entry
+            .file_name()
+            .to_str()
+            .map_or(false, |name| name.starts_with('.') || name == "target")
+}
+
+// Turns the `--bpf-program` flags of the given binaries into
+// `--upgradeable-program` flags with the given upgrade authority, so that the
+// programs can be upgraded once the validator is running.
+pub fn upgradeable_program_flags(
+    flags: Vec<String>,
+    binaries: &HashSet<String>,
+    authority: &str,
+) -> Vec<String> {
+    let mut out = Vec::with_capacity(flags.len());
+    let mut flags = flags.into_iter();
+    while let Some(flag) = flags.next() {
+        if flag != "--bpf-program" {
+            out.push(flag);
+            continue;
+        }
+        let address = flags.next().unwrap_or_default();
+        let binary = flags.next().unwrap_or_default();
+        if binaries.contains(&binary) {
+            out.extend([
+                "--upgradeable-program".to_string(),
+                address,
+                binary,
+                authority.to_string(),
+            ]);
+        } else {
+            out.extend([flag, address, binary]);
+        }
+    }
+    out
+}
+
+// The modification time of a file, if it exists.
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata().ok()?.modified().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn watch_changes() {
+        let root = std::env::temp_dir().join(format!("anchor-dev-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+
+        let mut watcher = Watcher::new(vec![root.clone()]);
+        assert!(watcher.changed().is_empty());
+
+        fs::write(root.join("src/state.rs"), "").unwrap();
+        fs::write(root.join("target/program.so"), "").unwrap();
+        fs::write(root.join(".lib.rs.swp"), "").unwrap();
+        assert_eq!(watcher.changed(), vec![root.join("src/state.rs")]);
+
+        fs::remove_file(root.join("src/lib.rs")).unwrap();
+        assert_eq!(watcher.changed(), vec![root.join("src/lib.rs")]);
+        assert!(watcher.changed().is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn upgradeable_programs() {
+        let flags = ["--bpf-program", "A", "target/deploy/a.so"]
+            .iter()
+            .chain(&[
+                "--bpf-program",
+                "B",
+                "genesis/b.so",
+                "--limit-ledger-size",
+                "10",
+            ])
+            .map(|flag| flag.to_string())
+            .collect();
+        let binaries = ["target/deploy/a.so".to_string()].into_iter().collect();
+        assert_eq!(
+            upgradeable_program_flags(flags, &binaries, "W"),
+            vec![
+                "--upgradeable-program",
+                "A",
+                "target/deploy/a.so",
+                "W",
+                "--bpf-program",
+                "B",
+                "genesis/b.so",
+                "--limit-ledger-size",
+                "10",
+            ]
+        );
+    }
+}
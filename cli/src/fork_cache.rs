@@ -0,0 +1,313 @@
+use crate::anonymize::AccountFixture;
+use crate::replay;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::account::Account;
+use solana_sdk::hash::hash;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_FORK_CACHE_DIR: &str = ".anchor/fork-cache";
+
+// Maximum number of accounts fetched by a single request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+// Accounts fetched from a cluster, and the slot they were read at.
+pub type Fetched = (u64, Vec<Option<Account>>);
+
+// A local cache of accounts cloned from a cluster, so that repeated local runs
+// (fork tests, replays, ...) don't download them again.
+//
+// Accounts are stored as test validator fixtures, named after the hash of
+// their content, so that unchanged accounts are only stored once. Each cluster
+// has a snapshot, pinned to the slot it was first fetched at, mapping the
+// cached addresses to their fixture. Snapshots are only ever extended with
+// accounts that weren't cached, until they are cleared.
+//
+// Layout:
+//
+//   <root>/snapshots.json       the snapshots of each cluster
+//   <root>/objects/<hash>.json  the account fixtures
+pub struct ForkCache {
+    root: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub url: String,
+    pub slot: u64,
+    pub accounts: BTreeMap<String, CachedEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedEntry {
+    // Slot the account was fetched at.
+    pub slot: u64,
+    // Hash of the fixture, or `None` if the account didn't exist.
+    pub object: Option<String>,
+}
+
+// An account resolved through the cache.
+#[derive(Debug, Clone)]
+pub struct CachedAccount {
+    // Fixture to load the account from, e.g. with `--account`.
+    pub file: PathBuf,
+    pub account: Account,
+}
+
+impl ForkCache {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn snapshots(&self) -> Result<Vec<Snapshot>> {
+        let path = self.root.join("snapshots.json");
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let snapshots = fs::read(&path)?;
+        serde_json::from_slice(&snapshots)
+            .with_context(|| format!("Error parsing {}", path.display()))
+    }
+
+    fn write_snapshots(&self, snapshots: &[Snapshot]) -> Result<()> {
+        fs::create_dir_all(&self.root)?;
+        fs::write(
+            self.root.join("snapshots.json"),
+            serde_json::to_string_pretty(snapshots)?,
+        )?;
+        Ok(())
+    }
+
+    fn object_path(&self, object: &str) -> PathBuf {
+        self.root.join("objects").join(format!("{}.json", object))
+    }
+
+    // Returns the accounts of the cluster at `url`, in the same order as
+    // `keys`, and `None` for the ones that don't exist.
+    //
+    // Accounts that aren't cached are fetched with `fetch`. Without it, i.e., offline, they
+    // are an error.
+    pub fn resolve<F>(
+        &self,
+        url: &str,
+        keys: &[Pubkey],
+        fetch: Option<F>,
+    ) -> Result<Vec<Option<CachedAccount>>>
+    where
+        F: FnMut(&[Pubkey]) -> Result<Fetched>,
+    {
+        let mut snapshots = self.snapshots()?;
+        let idx = match snapshots.iter().position(|s| s.url == url) {
+            Some(idx) => idx,
+            None => {
+                snapshots.push(Snapshot {
+                    url: url.to_string(),
+                    slot: 0,
+                    accounts: BTreeMap::new(),
+                });
+                snapshots.len() - 1
+            }
+        };
+
+        let mut missing: Vec<Pubkey> = keys
+            .iter()
+            .filter(|key| !snapshots[idx].accounts.contains_key(&key.to_string()))
+            .cloned()
+            .collect();
+        missing.sort();
+        missing.dedup();
+        if !missing.is_empty() {
+            let mut fetch = fetch.ok_or_else(|| {
+                anyhow!(
+                    "{} account(s) of {} aren't cached, run without --offline to fetch them: {}",
+                    missing.len(),
+                    url,
+                    missing
+                        .iter()
+                        .map(|key| key.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?;
+            fs::create_dir_all(self.root.join("objects"))?;
+            for chunk in missing.chunks(MAX_MULTIPLE_ACCOUNTS) {
+                let (slot, accounts) = fetch(chunk)?;
+                let snapshot = &mut snapshots[idx];
+                if snapshot.accounts.is_empty() {
+                    snapshot.slot = slot;
+                }
+                for (key, account) in chunk.iter().zip(accounts) {
+                    let object = account
+                        .map(|account| self.store(key, &account))
+                        .transpose()?;
+                    snapshot
+                        .accounts
+                        .insert(key.to_string(), CachedEntry { slot, object });
+                }
+            }
+            self.write_snapshots(&snapshots)?;
+        }
+
+        let snapshot = &snapshots[idx];
+        keys.iter()
+            .map(|key| match &snapshot.accounts[&key.to_string()].object {
+                None => Ok(None),
+                Some(object) => {
+                    let file = self.object_path(object);
+                    let account = load(&file)
+                        .with_context(|| format!("Error loading cached account {}", key))?;
+                    Ok(Some(CachedAccount { file, account }))
+                }
+            })
+            .collect()
+    }
+
+    fn store(&self, key: &Pubkey, account: &Account) -> Result<String> {
+        let fixture = serde_json::to_string_pretty(&replay::fixture(key, account))?;
+        let object = hash(fixture.as_bytes()).to_string();
+        let path = self.object_path(&object);
+        if !path.exists() {
+            fs::write(path, fixture)?;
+        }
+        Ok(object)
+    }
+
+    // Removes the snapshots pinned at `slot`, or all of them, and the fixtures
+    // only they used. If `keys` are given, only those accounts are removed
+    // from the snapshots, to be fetched again by the next run.
+    //
+    // Returns the number of removed snapshots and accounts.
+    pub fn clear(&self, slot: Option<u64>, keys: &[Pubkey]) -> Result<(usize, usize)> {
+        let keys: HashSet<String> = keys.iter().map(|key| key.to_string()).collect();
+        let mut removed = (0, 0);
+        let mut snapshots = self.snapshots()?;
+        snapshots.retain(|snapshot| {
+            let remove = keys.is_empty() && slot.map(|slot| slot == snapshot.slot).unwrap_or(true);
+            if remove {
+                removed.0 += 1;
+            }
+            !remove
+        });
+        if !keys.is_empty() {
+            for snapshot in snapshots.iter_mut() {
+                if slot.map(|slot| slot != snapshot.slot).unwrap_or(false) {
+                    continue;
+                }
+                let len = snapshot.accounts.len();
+                snapshot.accounts.retain(|key, _| !keys.contains(key));
+                removed.1 += len - snapshot.accounts.len();
+            }
+        }
+        self.write_snapshots(&snapshots)?;
+
+        // Remove the fixtures that aren't referenced anymore.
+        let used: HashSet<String> = snapshots
+            .iter()
+            .flat_map(|snapshot| snapshot.accounts.values())
+            .filter_map(|entry| entry.object.clone())
+            .collect();
+        let objects = self.root.join("objects");
+        if objects.exists() {
+            for entry in fs::read_dir(objects)? {
+                let path = entry?.path();
+                let referenced = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map(|stem| used.contains(stem))
+                    .unwrap_or(false);
+                if !referenced {
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+// Loads an account from a test validator fixture.
+pub fn load(path: &Path) -> Result<Account> {
+    let fixture: AccountFixture = serde_json::from_slice(&fs::read(path)?)?;
+    let snapshot = replay::AccountSnapshot::from_fixture(&fixture)?;
+    Ok(Account {
+        lamports: snapshot.lamports,
+        data: snapshot.data,
+        owner: snapshot.owner,
+        executable: fixture.account.executable,
+        rent_epoch: fixture.account.rent_epoch,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn account(data: Vec<u8>) -> Account {
+        Account {
+            lamports: 1_000_000,
+            data,
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn resolve_and_clear() {
+        let root = std::env::temp_dir().join(format!("fork-cache-{}", Pubkey::new_unique()));
+        let cache = ForkCache::new(&root);
+        let url = "https://api.mainnet-beta.solana.com";
+        let (a, b, missing) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let remote: BTreeMap<Pubkey, Account> = [(a, account(vec![1, 2, 3])), (b, account(vec![]))]
+            .into_iter()
+            .collect();
+        let fetched = Cell::new(0);
+        let slot = Cell::new(100);
+        let mut fetch = |keys: &[Pubkey]| {
+            fetched.set(fetched.get() + keys.len());
+            slot.set(slot.get() + 1);
+            Ok((
+                slot.get(),
+                keys.iter().map(|key| remote.get(key).cloned()).collect(),
+            ))
+        };
+
+        let accounts = cache.resolve(url, &[a, missing], Some(&mut fetch)).unwrap();
+        assert_eq!(accounts[0].as_ref().unwrap().account, remote[&a]);
+        assert!(accounts[1].is_none());
+
+        // Cached accounts aren't fetched again, and the snapshot stays pinned.
+        let accounts = cache.resolve(url, &[a, b], Some(&mut fetch)).unwrap();
+        assert_eq!(accounts[1].as_ref().unwrap().account, remote[&b]);
+        assert_eq!(fetched.get(), 3);
+        let snapshots = cache.snapshots().unwrap();
+        assert_eq!(snapshots[0].slot, 101);
+        assert_eq!(snapshots[0].accounts[&b.to_string()].slot, 102);
+
+        // Offline, only cached accounts resolve.
+        let offline = None::<fn(&[Pubkey]) -> Result<Fetched>>;
+        assert!(cache.resolve(url, &[a, b, missing], offline).is_ok());
+        assert!(cache
+            .resolve(url, &[Pubkey::new_unique()], offline)
+            .is_err());
+
+        // Invalidated accounts are fetched again.
+        assert_eq!(cache.clear(None, &[a]).unwrap(), (0, 1));
+        assert!(cache.resolve(url, &[a], offline).is_err());
+        cache.resolve(url, &[a], Some(&mut fetch)).unwrap();
+        assert_eq!(fetched.get(), 4);
+
+        assert_eq!(cache.clear(Some(1), &[]).unwrap(), (0, 0));
+        assert_eq!(cache.clear(Some(101), &[]).unwrap(), (1, 0));
+        assert!(cache.snapshots().unwrap().is_empty());
+        assert_eq!(fs::read_dir(root.join("objects")).unwrap().count(), 0);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,16 +1,20 @@
 use crate::config::{
     AnchorPackage, BootstrapMode, BuildConfig, Config, ConfigOverride, Manifest, ProgramDeployment,
-    ProgramWorkspace, ScriptsConfig, TestValidator, WithPath, SHUTDOWN_WAIT, STARTUP_WAIT,
+    ProgramWorkspace, ScriptsConfig, TestBackend, TestValidator, WithPath, IDL_BUILD_FEATURE,
+    SHUTDOWN_WAIT, STARTUP_WAIT,
 };
 use anchor_client::Cluster;
 use anchor_lang::idl::{IdlAccount, IdlInstruction, ERASED_AUTHORITY};
-use anchor_lang::{AccountDeserialize, AnchorDeserialize, AnchorSerialize};
-use anchor_syn::idl::Idl;
+use anchor_lang::{AccountDeserialize, AnchorSerialize};
+use anchor_syn::idl::file::ParseOptions;
+use anchor_syn::idl::mangle::Target;
+use anchor_syn::idl::onchain::Compression as IdlCompression;
+use anchor_syn::idl::{DocsPolicy, Idl};
+use anchor_syn::parser::safety::Severity;
 use anyhow::{anyhow, Context, Result};
 use clap::Parser;
 use flate2::read::GzDecoder;
-use flate2::read::ZlibDecoder;
-use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::write::GzEncoder;
 use flate2::Compression;
 use heck::SnakeCase;
 use rand::rngs::OsRng;
@@ -18,19 +22,24 @@ use reqwest::blocking::multipart::{Form, Part};
 use reqwest::blocking::Client;
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
-use solana_client::rpc_client::RpcClient;
-use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::{
+    RpcSendTransactionConfig, RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
+};
 use solana_program::instruction::{AccountMeta, Instruction};
+use solana_sdk::account::Account;
 use solana_sdk::account_utils::StateMut;
 use solana_sdk::bpf_loader;
 use solana_sdk::bpf_loader_deprecated;
 use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::signature::Keypair;
 use solana_sdk::signature::Signer;
+use solana_sdk::signature::{Keypair, Signature};
 use solana_sdk::sysvar;
 use solana_sdk::transaction::Transaction;
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding};
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -43,8 +52,15 @@ use std::str::FromStr;
 use std::string::ToString;
 use tar::Archive;
 
+pub mod anonymize;
+pub mod artifact;
 pub mod config;
+pub mod dev;
+pub mod fork_cache;
 mod path;
+pub mod program_ids;
+pub mod rent;
+pub mod replay;
 pub mod template;
 
 // Version of the docker image.
@@ -181,6 +197,10 @@ pub enum Command {
         /// to be able to check the transactions.
         #[clap(long)]
         detach: bool,
+        /// Flag to only clone accounts from the local fork cache, failing if
+        /// any of them isn't cached.
+        #[clap(long)]
+        offline: bool,
         #[clap(multiple_values = true)]
         args: Vec<String>,
         /// Arguments to pass to the underlying `cargo build-bpf` command.
@@ -276,6 +296,64 @@ pub enum Command {
         #[clap(subcommand)]
         subcmd: KeysCommand,
     },
+    /// Redacts public keys in captured account JSON files (as written by
+    /// `solana account --output json`), so that they can be shared, e.g. as
+    /// reproduction fixtures. Keys are remapped consistently across all files.
+    Anonymize {
+        /// Account JSON files to anonymize.
+        #[clap(required = true)]
+        files: Vec<String>,
+        /// Directory to write the anonymized files to, named after their new
+        /// addresses.
+        #[clap(short, long)]
+        out_dir: String,
+        /// IDL of the accounts' program, to also remap all public key fields
+        /// of its accounts, including keys that weren't captured.
+        #[clap(long)]
+        idl: Option<String>,
+        /// Public keys to leave untouched, e.g. PDAs the program re-derives.
+        /// Owners and programs are always kept.
+        #[clap(long, multiple_occurrences = true)]
+        keep: Vec<Pubkey>,
+        /// Writes the mapping from original to anonymized keys to this file,
+        /// for local use only.
+        #[clap(long)]
+        mapping: Option<String>,
+    },
+    /// Replays a transaction of the provider cluster against the workspace's
+    /// programs on a local validator, showing typed diffs of the writable
+    /// accounts between the state before, after the replay and after the
+    /// original transaction.
+    Replay {
+        /// Signature of the transaction to replay.
+        signature: Signature,
+        /// Account JSON files (as written by `solana account --output json`)
+        /// holding the state to replay against, e.g. captured before the
+        /// transaction. Other accounts are cloned from their current state.
+        #[clap(long, multiple_occurrences = true)]
+        account: Vec<String>,
+        /// Flag to skip building the program in the workspace,
+        /// use this to save time when the program code is not altered.
+        #[clap(long)]
+        skip_build: bool,
+        /// Flag to replay a transaction replayed before, from the local fork
+        /// cache only, without checking which accounts changed since.
+        #[clap(long)]
+        offline: bool,
+    },
+    /// Commands for the local cache of accounts cloned from clusters, e.g. by
+    /// `[test.validator] clone` and `anchor replay`.
+    ForkCache {
+        #[clap(subcommand)]
+        subcmd: ForkCacheCommand,
+    },
+    /// Commands for program packages (`.anchorpkg` files), bundling a
+    /// program's binary, IDL and toolchain into a single, optionally signed,
+    /// file for distribution and verification.
+    Package {
+        #[clap(subcommand)]
+        subcmd: PackageCommand,
+    },
     /// Localnet commands.
     Localnet {
         /// Flag to skip building the program in the workspace,
@@ -290,6 +368,37 @@ pub enum Command {
         /// no "CHECK" comments where normally required
         #[clap(long)]
         skip_lint: bool,
+        /// Flag to only clone accounts from the local fork cache, failing if
+        /// any of them isn't cached.
+        #[clap(long)]
+        offline: bool,
+        /// Arguments to pass to the underlying `cargo build-bpf` command.
+        #[clap(
+            required = false,
+            takes_value = true,
+            multiple_values = true,
+            last = true
+        )]
+        cargo_args: Vec<String>,
+    },
+    /// Runs the programs of the workspace on a local validator, and on each
+    /// change to their sources rebuilds them, upgrades them on the validator,
+    /// refreshes their IDL and regenerates their clients. Press Enter to stop.
+    Dev {
+        /// True if the build should not fail even if there are
+        /// no "CHECK" comments where normally required
+        #[clap(long)]
+        skip_lint: bool,
+        /// Flag to only clone accounts from the local fork cache, failing if
+        /// any of them isn't cached.
+        #[clap(long)]
+        offline: bool,
+        /// Directory to write the TypeScript client of each program to.
+        #[clap(long, default_value = "target/clients")]
+        clients: String,
+        /// Interval, in milliseconds, to check the sources for changes at.
+        #[clap(long, default_value = "500")]
+        interval: u64,
         /// Arguments to pass to the underlying `cargo build-bpf` command.
         #[clap(
             required = false,
@@ -299,11 +408,73 @@ pub enum Command {
         )]
         cargo_args: Vec<String>,
     },
+    /// Checks that the accounts of the workspace's programs on the provider
+    /// cluster are rent-exempt for the size of their type in the IDL, i.e.
+    /// the size they're reallocated to after a layout change, and optionally
+    /// funds the underfunded ones from the wallet.
+    RentCheck {
+        /// Only check the accounts of this program.
+        #[clap(long)]
+        program_name: Option<String>,
+        /// IDL of the new layout of the program (parsed from the program's
+        /// source if not specified).
+        #[clap(long)]
+        idl: Option<String>,
+        /// Transfer the missing lamports to the underfunded accounts.
+        #[clap(long)]
+        fix: bool,
+    },
+}
+
+#[derive(Debug, Parser)]
+pub enum ForkCacheCommand {
+    /// Lists the cached snapshot of each cluster.
+    List,
+    /// Removes cached accounts, so that they are fetched again by the next
+    /// run, pinned to the then current slot.
+    Clear {
+        /// Only clear the snapshots pinned at this slot.
+        #[clap(long)]
+        slot: Option<u64>,
+        /// Only clear these accounts, keeping the rest of the snapshots.
+        #[clap(long, multiple_occurrences = true)]
+        address: Vec<Pubkey>,
+    },
+}
+
+#[derive(Debug, Parser)]
+pub enum PackageCommand {
+    /// Packages a built workspace program.
+    Create {
+        /// The name of the program to package.
+        program_name: String,
+        /// Package the binary of the last verifiable build.
+        #[clap(long)]
+        verifiable: bool,
+        /// Keypair to sign the package with.
+        #[clap(long)]
+        keypair: Option<String>,
+        /// Output file. Defaults to `target/<program>.anchorpkg`.
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Verifies a package and prints its manifest.
+    Inspect { file: String },
+    /// Verifies a package and writes its files to a directory.
+    Extract {
+        file: String,
+        #[clap(short, long)]
+        out_dir: String,
+    },
 }
 
 #[derive(Debug, Parser)]
 pub enum KeysCommand {
     List,
+    /// Cross-references program ids hardcoded in the workspace (e.g. in
+    /// `declare_id!`, constants and CPI targets) against Anchor.toml for the
+    /// provider cluster.
+    Check,
 }
 
 #[derive(Debug, Parser)]
@@ -373,6 +544,169 @@ pub enum IdlCommand {
         /// Suppress doc strings in output
         #[clap(long)]
         no_docs: bool,
+        /// Client profile of Anchor.toml to restrict the IDL to.
+        #[clap(long)]
+        client: Option<String>,
+        /// Language clients are generated in from the IDL, i.e. `typescript`,
+        /// `python` or `kotlin`, to rename the names that are reserved words
+        /// in it.
+        #[clap(long)]
+        target: Option<Target>,
+        /// Output directory to write the IDL to split into a file per
+        /// section, e.g. `instructions.json`, along with an `idl.json` index.
+        #[clap(long, conflicts_with = "out")]
+        split: Option<String>,
+    },
+    /// Generates a JSON Schema for the instruction args and account data
+    /// described by an IDL.
+    Schema {
+        /// Path to the IDL json file, or the directory of a split IDL.
+        #[clap(short, long)]
+        file: String,
+        /// Output file for the JSON Schema (stdout if not specified).
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Converts the IDL of a native program generated by Shank to an Anchor
+    /// IDL.
+    FromShank {
+        /// Path to the Shank IDL json file.
+        #[clap(short, long)]
+        file: String,
+        /// Output file for the IDL (stdout if not specified).
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Converts an IDL imported from Shank back to a Shank IDL.
+    ToShank {
+        /// Path to the IDL json file, or the directory of a split IDL.
+        #[clap(short, long)]
+        file: String,
+        /// Output file for the Shank IDL (stdout if not specified).
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Exports an IDL as a Codama (formerly Kinobi) node tree, for its client
+    /// generators.
+    Codama {
+        /// Path to the IDL json file, or the directory of a split IDL.
+        #[clap(short, long)]
+        file: String,
+        /// Output file for the node tree (stdout if not specified).
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Generates the message bundle of the errors described by an IDL, to
+    /// be translated by clients.
+    Messages {
+        /// Path to the IDL json file, or the directory of a split IDL.
+        #[clap(short, long)]
+        file: String,
+        /// Output file for the message bundle (stdout if not specified).
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Generates the TypeScript module of the program described by an IDL,
+    /// with the types of its accounts, events and instructions, a builder
+    /// per instruction and a decoder per account.
+    Typescript {
+        /// Path to the IDL json file, or the directory of a split IDL.
+        #[clap(short, long)]
+        file: String,
+        /// Output file for the TypeScript module (stdout if not specified).
+        #[clap(short, long)]
+        out: Option<String>,
+        /// Directory of user templates overriding the default one, e.g.
+        /// `typescript.ts.jinja`.
+        #[clap(long)]
+        templates: Option<String>,
+    },
+    /// Generates the Python module of the program described by an IDL, for
+    /// anchorpy, with the layouts of its types, a builder per instruction, a
+    /// decoder per account and a helper per PDA with seeds in the IDL.
+    Python {
+        /// Path to the IDL json file, or the directory of a split IDL.
+        #[clap(short, long)]
+        file: String,
+        /// Output file for the Python module (stdout if not specified).
+        #[clap(short, long)]
+        out: Option<String>,
+        /// Address of the program (the address in the IDL if not specified).
+        #[clap(long)]
+        program_id: Option<String>,
+        /// Directory of user templates overriding the default one, e.g.
+        /// `python.py.jinja`.
+        #[clap(long)]
+        templates: Option<String>,
+    },
+    /// Generates a crate for programs to CPI into the program described by an
+    /// IDL, without depending on the program's crate.
+    RustCpi {
+        /// Path to the IDL json file, or the directory of a split IDL.
+        #[clap(short, long)]
+        file: String,
+        /// Directory to write the crate to.
+        #[clap(short, long)]
+        out: String,
+        /// Address of the program (the address in the IDL if not specified).
+        #[clap(long)]
+        program_id: Option<String>,
+        /// Directory of user templates overriding the default one, e.g.
+        /// `rust_cpi.rs.jinja`.
+        #[clap(long)]
+        templates: Option<String>,
+    },
+    /// Generates a CLI crate for operators to call the `#[admin]`
+    /// instructions of the program described by an IDL with.
+    AdminCli {
+        /// Path to the IDL json file, or the directory of a split IDL.
+        #[clap(short, long)]
+        file: String,
+        /// Directory to write the crate to.
+        #[clap(short, long)]
+        out: String,
+        /// Address of the program (the address in the IDL if not specified).
+        #[clap(long)]
+        program_id: Option<String>,
+    },
+    /// Generates encode/decode test vectors of the instructions, accounts
+    /// and events described by an IDL, for clients to check their
+    /// serialization against.
+    TestVectors {
+        /// Path to the IDL json file, or the directory of a split IDL.
+        #[clap(short, long)]
+        file: String,
+        /// Seed the values of the vectors are generated from.
+        #[clap(long, default_value_t = anchor_syn::idl::vectors::DEFAULT_SEED)]
+        seed: u64,
+        /// Output file for the test vectors (stdout if not specified).
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Renders the lifecycles of the accounts described by an IDL, declared
+    /// with `#[state_machine]`, as Mermaid state diagrams.
+    Graph {
+        /// Path to the IDL json file, or the directory of a split IDL.
+        #[clap(short, long)]
+        file: String,
+        /// Output file for the diagrams (stdout if not specified).
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Checks that the discriminators of the accounts, events and
+    /// instructions of all programs of the workspace don't collide.
+    CheckDiscriminators,
+    /// Checks that a program implements every instruction of an interface,
+    /// with compatible accounts, args and discriminators.
+    Conformance {
+        /// Path to the IDL json file of the program, or the directory of a
+        /// split IDL.
+        #[clap(short, long)]
+        file: String,
+        /// Path to the IDL of the interface, or to the root of a crate
+        /// defining `#[interface]` traits.
+        #[clap(short, long)]
+        interface: String,
     },
     /// Fetches an IDL for the given address from a cluster.
     /// The address can be a program, IDL account, or IDL buffer.
@@ -460,6 +794,7 @@ pub fn entry(opts: Opts) -> Result<()> {
             skip_local_validator,
             skip_build,
             detach,
+            offline,
             args,
             cargo_args,
             skip_lint,
@@ -470,6 +805,7 @@ pub fn entry(opts: Opts) -> Result<()> {
             skip_build,
             skip_lint,
             detach,
+            offline,
             args,
             cargo_args,
         ),
@@ -488,18 +824,54 @@ pub fn entry(opts: Opts) -> Result<()> {
             skip_build,
         } => publish(&opts.cfg_override, program, cargo_args, skip_build),
         Command::Keys { subcmd } => keys(&opts.cfg_override, subcmd),
+        Command::Replay {
+            signature,
+            account,
+            skip_build,
+            offline,
+        } => replay(&opts.cfg_override, signature, account, skip_build, offline),
+        Command::ForkCache { subcmd } => fork_cache(&opts.cfg_override, subcmd),
+        Command::Package { subcmd } => package(&opts.cfg_override, subcmd),
+        Command::Anonymize {
+            files,
+            out_dir,
+            idl,
+            keep,
+            mapping,
+        } => anonymize(files, out_dir, idl, keep, mapping),
         Command::Localnet {
             skip_build,
             skip_deploy,
             skip_lint,
+            offline,
             cargo_args,
         } => localnet(
             &opts.cfg_override,
             skip_build,
             skip_deploy,
             skip_lint,
+            offline,
+            cargo_args,
+        ),
+        Command::Dev {
+            skip_lint,
+            offline,
+            clients,
+            interval,
+            cargo_args,
+        } => dev(
+            &opts.cfg_override,
+            skip_lint,
+            offline,
+            clients,
+            interval,
             cargo_args,
         ),
+        Command::RentCheck {
+            program_name,
+            idl,
+            fix,
+        } => rent_check(&opts.cfg_override, program_name, idl, fix),
     }
 }
 
@@ -963,13 +1335,23 @@ fn build_cwd_verifiable(
                 // Write out the JSON file.
                 println!("Writing the IDL file");
                 let out_file = workspace_dir.join(format!("target/idl/{}.json", idl.name));
-                write_idl(&idl, OutFile::File(out_file))?;
+                write_idl(&idl, OutFile::File(out_file.clone()))?;
+                write_test_vectors(cfg, &idl, &out_file)?;
+                print_budget_report(cfg, "src/lib.rs", &idl)?;
 
                 // Write out the TypeScript type.
                 println!("Writing the .ts file");
                 let ts_file = workspace_dir.join(format!("target/types/{}.ts", idl.name));
                 fs::write(&ts_file, template::idl_ts(&idl)?)?;
 
+                // Write out the client profiles.
+                write_client_profiles(
+                    cfg,
+                    &idl,
+                    &workspace_dir.join("target/idl"),
+                    &workspace_dir.join("target/types"),
+                )?;
+
                 // Copy out the TypeScript type.
                 if !&cfg.workspace.types.is_empty() {
                     fs::copy(
@@ -1247,9 +1629,15 @@ fn _build_cwd(
         };
 
         // Write out the JSON file.
-        write_idl(&idl, OutFile::File(out))?;
+        write_idl(&idl, OutFile::File(out.clone()))?;
+        // Write out the test vectors next to it.
+        write_test_vectors(cfg, &idl, &out)?;
+        // Print the estimated cost of each instruction.
+        print_budget_report(cfg, "src/lib.rs", &idl)?;
         // Write out the TypeScript type.
         fs::write(&ts_out, template::idl_ts(&idl)?)?;
+        // Write out the client profiles.
+        write_client_profiles(cfg, &idl, out.parent().unwrap(), ts_out.parent().unwrap())?;
         // Copy out the TypeScript type.
         let cfg_parent = cfg.path().parent().expect("Invalid Anchor.toml");
         if !&cfg.workspace.types.is_empty() {
@@ -1266,6 +1654,81 @@ fn _build_cwd(
     Ok(())
 }
 
+// Writes the test vectors of the IDL written to `idl_out` next to it, e.g.
+// `target/idl/<program>.vectors.json`, if enabled with `features.test-vectors`.
+fn write_test_vectors(cfg: &WithPath<Config>, idl: &Idl, idl_out: &Path) -> Result<()> {
+    if !cfg.features.test_vectors {
+        return Ok(());
+    }
+    let vectors = idl.test_vectors(anchor_syn::idl::vectors::DEFAULT_SEED)?;
+    fs::write(
+        idl_out.with_extension("vectors.json"),
+        serde_json::to_string_pretty(&vectors)?,
+    )?;
+    Ok(())
+}
+
+// Prints the estimated stack frame and compute units of each instruction of the
+// program at `file`, and of each of its accounts, if enabled with
+// `features.budget-report`.
+fn print_budget_report(cfg: &WithPath<Config>, file: &str, idl: &Idl) -> Result<()> {
+    if !cfg.features.budget_report {
+        return Ok(());
+    }
+    let budgets = anchor_syn::idl::budget::report(file, idl)?;
+    for budget in budgets.iter() {
+        println!("{}", budget);
+        for account in budget.accounts.iter() {
+            println!("    {}", account);
+        }
+        if budget.exceeds_stack_frame() {
+            println!(
+                "WARNING: The accounts of {} exceed the {} byte stack frame, box the largest ones",
+                budget.name,
+                anchor_syn::idl::budget::STACK_FRAME_LIMIT
+            );
+        }
+        if budget.exceeds_compute_units() {
+            println!(
+                "WARNING: {} may exceed its budget of {} compute units",
+                budget.name, budget.compute_unit_limit
+            );
+        }
+    }
+    Ok(())
+}
+
+// Writes the IDL and TypeScript type of each client profile using the program,
+// restricted to the profile's allowlist of instructions, e.g.
+// `target/idl/frontend/<program>.json` and `target/types/frontend/<program>.ts`.
+fn write_client_profiles(
+    cfg: &WithPath<Config>,
+    idl: &Idl,
+    idl_dir: &Path,
+    ts_dir: &Path,
+) -> Result<()> {
+    for (profile, programs) in &cfg.clients {
+        let allowlist = match programs.get(&idl.name) {
+            Some(allowlist) => allowlist,
+            None => continue,
+        };
+        let idl = idl
+            .with_instructions(allowlist)
+            .with_context(|| format!("Invalid client profile {}", profile))?;
+        fs::create_dir_all(idl_dir.join(profile))?;
+        fs::create_dir_all(ts_dir.join(profile))?;
+        write_idl(
+            &idl,
+            OutFile::File(idl_dir.join(profile).join(&idl.name).with_extension("json")),
+        )?;
+        fs::write(
+            ts_dir.join(profile).join(&idl.name).with_extension("ts"),
+            template::idl_ts(&idl)?,
+        )?;
+    }
+    Ok(())
+}
+
 fn verify(
     cfg_override: &ConfigOverride,
     program_id: Pubkey,
@@ -1487,14 +1950,7 @@ fn fetch_idl(cfg_override: &ConfigOverride, idl_addr: Pubkey) -> Result<Idl> {
             .map_or(Err(anyhow!("Account not found")), Ok)?;
     }
 
-    // Cut off account discriminator.
-    let mut d: &[u8] = &account.data[8..];
-    let idl_account: IdlAccount = AnchorDeserialize::deserialize(&mut d)?;
-
-    let mut z = ZlibDecoder::new(&idl_account.data[..]);
-    let mut s = Vec::new();
-    z.read_to_end(&mut s)?;
-    serde_json::from_slice(&s[..]).map_err(Into::into)
+    anchor_syn::idl::onchain::decode_account(&account.data)
 }
 
 fn extract_idl(
@@ -1507,13 +1963,103 @@ fn extract_idl(
     let manifest_from_path = std::env::current_dir()?.join(PathBuf::from(&*file).parent().unwrap());
     let cargo = Manifest::discover_from_path(manifest_from_path)?
         .ok_or_else(|| anyhow!("Cargo.toml not found"))?;
-    anchor_syn::idl::file::parse(
-        &*file,
+    if !(cfg.features.skip_lint || skip_lint) {
+        safety_checks(cfg, &file)?;
+    }
+    let docs_policy = match no_docs {
+        true => DocsPolicy::none(),
+        false => cfg.features.docs.clone(),
+    };
+    let output = match cargo.has_idl_build() {
+        true => build_idl(cfg, &cargo, &file, &docs_policy)?,
+        false => anchor_syn::idl::file::parse(
+            &*file,
+            &ParseOptions {
+                version: cargo.version(),
+                seeds_feature: cfg.features.seeds,
+                docs_policy,
+                float_policy: cfg.features.float_policy,
+                ..Default::default()
+            },
+            &cargo.external_accounts(cargo.path().parent().unwrap()),
+        )?,
+    };
+    Ok(output.map(|output| {
+        for warning in output.warnings.iter() {
+            println!("WARNING: {}", warning);
+        }
+        output.idl
+    }))
+}
+
+// Checks the program's source against the safety rules, with the severities
+// of the `[safety-checks]` section of the config, printing the warnings and
+// failing on the first error.
+fn safety_checks(cfg: &WithPath<Config>, file: &str) -> Result<()> {
+    let ctx = anchor_syn::parser::context::CrateContext::parse(file)?;
+    for diagnostic in ctx.safety_diagnostics(&cfg.safety_checks) {
+        match diagnostic.severity {
+            Severity::Error => return Err(anyhow!("{}", diagnostic)),
+            Severity::Warn => println!("WARNING: {}", diagnostic),
+            Severity::Allow => {}
+        }
+    }
+    Ok(())
+}
+
+// Generates the IDL from the compiled program, by running the tests generated
+// by Anchor's macros with the program's `idl-build` feature, which print the
+// fragments of the IDL.
+fn build_idl(
+    cfg: &WithPath<Config>,
+    cargo: &WithPath<Manifest>,
+    file: &str,
+    docs_policy: &DocsPolicy,
+) -> Result<Option<anchor_syn::idl::file::ParseOutput>> {
+    let exit = std::process::Command::new("cargo")
+        .arg("test")
+        .arg(anchor_syn::idl::build::IDL_BUILD_TEST_PREFIX)
+        .arg("--lib")
+        .arg(format!("--features={}", IDL_BUILD_FEATURE))
+        .args(["--", "--show-output", "--quiet", "--test-threads=1"])
+        .current_dir(cargo.path().parent().unwrap())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| anyhow::format_err!("{}", e.to_string()))?;
+    if !exit.status.success() {
+        return Err(anyhow!(
+            "Failed to generate the IDL with the {} feature:\n{}",
+            IDL_BUILD_FEATURE,
+            String::from_utf8_lossy(&exit.stdout)
+        ));
+    }
+    let fragments = anchor_syn::idl::build::parse_fragments(&String::from_utf8(exit.stdout)?)?;
+    let mut output = match anchor_syn::idl::build::assemble(
+        fragments,
         cargo.version(),
-        cfg.features.seeds,
-        no_docs,
-        !(cfg.features.skip_lint || skip_lint),
-    )
+        docs_policy,
+        cfg.features.float_policy,
+    )? {
+        None => return Ok(None),
+        Some(output) => output,
+    };
+
+    // Types that aren't defined with Anchor's macros are taken from the
+    // source, when it can be parsed.
+    let parsed = anchor_syn::idl::file::parse(
+        file,
+        &ParseOptions {
+            version: cargo.version(),
+            docs_policy: docs_policy.clone(),
+            float_policy: cfg.features.float_policy,
+            ..Default::default()
+        },
+        &cargo.external_accounts(cargo.path().parent().unwrap()),
+    );
+    if let Ok(Some(parsed)) = parsed {
+        anchor_syn::idl::build::define_types_from(&mut output, &parsed.idl);
+    }
+    Ok(Some(output))
 }
 
 fn idl(cfg_override: &ConfigOverride, subcmd: IdlCommand) -> Result<()> {
@@ -1545,7 +2091,50 @@ fn idl(cfg_override: &ConfigOverride, subcmd: IdlCommand) -> Result<()> {
             out,
             out_ts,
             no_docs,
-        } => idl_parse(cfg_override, file, out, out_ts, no_docs),
+            client,
+            target,
+            split,
+        } => idl_parse(
+            cfg_override,
+            file,
+            out,
+            out_ts,
+            no_docs,
+            client,
+            target,
+            split,
+        ),
+        IdlCommand::Schema { file, out } => idl_schema(file, out),
+        IdlCommand::Codama { file, out } => idl_codama(file, out),
+        IdlCommand::FromShank { file, out } => idl_from_shank(file, out),
+        IdlCommand::ToShank { file, out } => idl_to_shank(file, out),
+        IdlCommand::Messages { file, out } => idl_messages(file, out),
+        IdlCommand::Graph { file, out } => idl_graph(file, out),
+        IdlCommand::CheckDiscriminators => idl_check_discriminators(cfg_override),
+        IdlCommand::Conformance { file, interface } => idl_conformance(file, interface),
+        IdlCommand::Typescript {
+            file,
+            out,
+            templates,
+        } => idl_typescript(file, out, templates),
+        IdlCommand::Python {
+            file,
+            out,
+            program_id,
+            templates,
+        } => idl_python(file, out, program_id, templates),
+        IdlCommand::RustCpi {
+            file,
+            out,
+            program_id,
+            templates,
+        } => idl_rust_cpi(file, out, program_id, templates),
+        IdlCommand::AdminCli {
+            file,
+            out,
+            program_id,
+        } => idl_admin_cli(file, out, program_id),
+        IdlCommand::TestVectors { file, seed, out } => idl_test_vectors(file, seed, out),
         IdlCommand::Fetch { address, out } => idl_fetch(cfg_override, address, out),
     }
 }
@@ -1554,8 +2143,7 @@ fn idl_init(cfg_override: &ConfigOverride, program_id: Pubkey, idl_filepath: Str
     with_workspace(cfg_override, |cfg| {
         let keypair = cfg.provider.wallet.to_string();
 
-        let bytes = fs::read(idl_filepath)?;
-        let idl: Idl = serde_json::from_reader(&*bytes)?;
+        let idl = anchor_syn::idl::io::read(idl_filepath)?;
 
         let idl_address = create_idl_account(cfg, &keypair, &program_id, &idl)?;
 
@@ -1572,8 +2160,7 @@ fn idl_write_buffer(
     with_workspace(cfg_override, |cfg| {
         let keypair = cfg.provider.wallet.to_string();
 
-        let bytes = fs::read(idl_filepath)?;
-        let idl: Idl = serde_json::from_reader(&*bytes)?;
+        let idl = anchor_syn::idl::io::read(idl_filepath)?;
 
         let idl_buffer = create_idl_buffer(cfg, &keypair, &program_id, &idl)?;
         idl_write(cfg, &program_id, &idl, idl_buffer)?;
@@ -1752,12 +2339,7 @@ fn idl_write(cfg: &Config, program_id: &Pubkey, idl: &Idl, idl_address: Pubkey)
     let client = RpcClient::new(url);
 
     // Serialize and compress the idl.
-    let idl_data = {
-        let json_bytes = serde_json::to_vec(&idl)?;
-        let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
-        e.write_all(&json_bytes)?;
-        e.finish()?
-    };
+    let idl_data = serialize_idl(&idl)?;
 
     const MAX_WRITE_SIZE: usize = 1000;
     let mut offset = 0;
@@ -1808,16 +2390,37 @@ fn idl_parse(
     out: Option<String>,
     out_ts: Option<String>,
     no_docs: bool,
+    client: Option<String>,
+    target: Option<Target>,
+    split: Option<String>,
 ) -> Result<()> {
     let cfg = Config::discover(cfg_override)?.expect("Not in workspace.");
-    let idl = extract_idl(&cfg, &file, true, no_docs)?.ok_or_else(|| anyhow!("IDL not parsed"))?;
-    let out = match out {
-        None => OutFile::Stdout,
-        Some(out) => OutFile::File(PathBuf::from(out)),
+    let mut idl =
+        extract_idl(&cfg, &file, true, no_docs)?.ok_or_else(|| anyhow!("IDL not parsed"))?;
+    if let Some(client) = client {
+        let allowlist = cfg
+            .clients
+            .get(&client)
+            .ok_or_else(|| anyhow!("Client profile {} not found in Anchor.toml", client))?
+            .get(&idl.name)
+            .ok_or_else(|| anyhow!("Client profile {} doesn't include {}", client, idl.name))?;
+        idl = idl.with_instructions(allowlist)?;
+    }
+    let out = match (out, split) {
+        (_, Some(split)) => OutFile::Split(PathBuf::from(split)),
+        (None, None) => OutFile::Stdout,
+        (Some(out), None) => OutFile::File(PathBuf::from(out)),
     };
-    write_idl(&idl, out)?;
+    match target {
+        None => write_idl(&idl, out)?,
+        Some(target) => {
+            let renames = cfg.renames.get(&target).cloned().unwrap_or_default();
+            write_idl(&idl.mangled(target, &renames), out)?
+        }
+    }
 
-    // Write out the TypeScript IDL.
+    // Write out the TypeScript IDL. Its names are string keys, which don't
+    // need renaming, and the client derives discriminators from them.
     if let Some(out) = out_ts {
         fs::write(out, template::idl_ts(&idl)?)?;
     }
@@ -1825,8 +2428,19 @@ fn idl_parse(
     Ok(())
 }
 
-fn idl_fetch(cfg_override: &ConfigOverride, address: Pubkey, out: Option<String>) -> Result<()> {
-    let idl = fetch_idl(cfg_override, address)?;
+fn idl_schema(file: String, out: Option<String>) -> Result<()> {
+    let idl = anchor_syn::idl::io::read(file)?;
+    let schema = serde_json::to_string_pretty(&idl.json_schema())?;
+    match out {
+        None => println!("{}", schema),
+        Some(out) => fs::write(out, schema)?,
+    };
+    Ok(())
+}
+
+fn idl_from_shank(file: String, out: Option<String>) -> Result<()> {
+    // Shank IDLs are converted when read.
+    let idl = anchor_syn::idl::io::read(file)?;
     let out = match out {
         None => OutFile::Stdout,
         Some(out) => OutFile::File(PathBuf::from(out)),
@@ -1834,42 +2448,287 @@ fn idl_fetch(cfg_override: &ConfigOverride, address: Pubkey, out: Option<String>
     write_idl(&idl, out)
 }
 
-fn write_idl(idl: &Idl, out: OutFile) -> Result<()> {
-    let idl_json = serde_json::to_string_pretty(idl)?;
+fn idl_to_shank(file: String, out: Option<String>) -> Result<()> {
+    let idl = anchor_syn::idl::io::read(file)?;
+    let shank = serde_json::to_string_pretty(&anchor_syn::idl::shank::export(&idl)?)?;
     match out {
-        OutFile::Stdout => println!("{}", idl_json),
-        OutFile::File(out) => fs::write(out, idl_json)?,
+        None => println!("{}", shank),
+        Some(out) => fs::write(out, shank)?,
     };
+    Ok(())
+}
 
+fn idl_codama(file: String, out: Option<String>) -> Result<()> {
+    let idl = anchor_syn::idl::io::read(file)?;
+    let root = serde_json::to_string_pretty(&idl.codama())?;
+    match out {
+        None => println!("{}", root),
+        Some(out) => fs::write(out, root)?,
+    };
     Ok(())
 }
 
-enum OutFile {
-    Stdout,
-    File(PathBuf),
+fn idl_messages(file: String, out: Option<String>) -> Result<()> {
+    let idl = anchor_syn::idl::io::read(file)?;
+    let messages = serde_json::to_string_pretty(&idl.error_messages())?;
+    match out {
+        None => println!("{}", messages),
+        Some(out) => fs::write(out, messages)?,
+    };
+    Ok(())
 }
 
-// Builds, deploys, and tests all workspace programs in a single command.
-#[allow(clippy::too_many_arguments)]
-fn test(
-    cfg_override: &ConfigOverride,
-    skip_deploy: bool,
-    skip_local_validator: bool,
-    skip_build: bool,
-    skip_lint: bool,
-    detach: bool,
-    extra_args: Vec<String>,
-    cargo_args: Vec<String>,
-) -> Result<()> {
-    with_workspace(cfg_override, |cfg| {
-        // Build if needed.
-        if !skip_build {
-            build(
-                cfg_override,
-                None,
-                None,
-                false,
-                skip_lint,
+fn idl_graph(file: String, out: Option<String>) -> Result<()> {
+    let idl = anchor_syn::idl::io::read(file)?;
+    let diagrams = anchor_syn::idl::state_machine::mermaid(&idl);
+    match out {
+        None => print!("{}", diagrams),
+        Some(out) => fs::write(out, diagrams)?,
+    };
+    Ok(())
+}
+
+fn idl_check_discriminators(cfg_override: &ConfigOverride) -> Result<()> {
+    let cfg = Config::discover(cfg_override)?.expect("Not in workspace.");
+    let programs = cfg.read_all_programs()?;
+    let collisions = anchor_syn::idl::discriminators::collisions(
+        programs
+            .iter()
+            .filter_map(|program| Some((program.lib_name.as_str(), program.idl.as_ref()?))),
+    );
+    for collision in collisions.iter() {
+        eprintln!("{}", collision);
+    }
+    if !collisions.is_empty() {
+        return Err(anyhow!(
+            "Found {} discriminator collision(s)",
+            collisions.len()
+        ));
+    }
+    Ok(())
+}
+
+fn idl_conformance(file: String, interface: String) -> Result<()> {
+    let idl = anchor_syn::idl::io::read(file)?;
+    let interfaces = anchor_syn::idl::conformance::read(interface)?;
+    run_conformance_tests(&idl, &interfaces)
+}
+
+// Runs the conformance tests of the program against each interface, printing
+// their outcome, and fails if any of them fails.
+fn run_conformance_tests(
+    idl: &Idl,
+    interfaces: &[anchor_syn::idl::conformance::Interface],
+) -> Result<()> {
+    let mut failed = 0;
+    for interface in interfaces {
+        for test in anchor_syn::idl::conformance::check(idl, interface) {
+            let name = format!("{}::{}::{}", idl.name, test.interface, test.instruction);
+            if test.passed() {
+                println!("conformance {} ... ok", name);
+                continue;
+            }
+            failed += 1;
+            println!("conformance {} ... FAILED", name);
+            for failure in test.failures.iter() {
+                println!("    {}", failure);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} conformance test(s) failed", failed));
+    }
+    Ok(())
+}
+
+// Checks the programs of the workspace against the interfaces they implement,
+// given in `[interfaces]`.
+fn check_interfaces(cfg: &WithPath<Config>) -> Result<()> {
+    if cfg.interfaces.is_empty() {
+        return Ok(());
+    }
+    let root = cfg.path().parent().unwrap();
+    for program in cfg.read_all_programs()? {
+        let paths = match cfg.interfaces.get(&program.lib_name) {
+            Some(paths) => paths,
+            None => continue,
+        };
+        let idl = program
+            .idl
+            .ok_or_else(|| anyhow!("IDL not parsed for {}", program.lib_name))?;
+        let mut interfaces = vec![];
+        for path in paths {
+            interfaces.extend(anchor_syn::idl::conformance::read(root.join(path))?);
+        }
+        run_conformance_tests(&idl, &interfaces)?;
+    }
+    Ok(())
+}
+
+// Warns about the instructions of a client being generated that already fail,
+// being past their `#[sunset(..)]` deadline, unless it's a slot.
+fn warn_sunset(idl: &Idl) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64);
+    for ix in &idl.instructions {
+        if let Some(sunset) = ix.sunset.filter(|sunset| sunset.is_past(now) == Some(true)) {
+            eprintln!(
+                "WARNING: instruction {} fails since {}, its sunset",
+                ix.name, sunset
+            );
+        }
+    }
+}
+
+fn idl_typescript(file: String, out: Option<String>, templates: Option<String>) -> Result<()> {
+    let idl = anchor_syn::idl::io::read(file)?;
+    warn_sunset(&idl);
+    let ts = anchor_syn::idl::codegen::template::render(
+        anchor_syn::idl::codegen::template::Backend::Typescript,
+        &idl,
+        None,
+        anchor_syn::idl::codegen::typescript::generate(&idl)?,
+        templates.as_deref().map(Path::new),
+    )?;
+    match out {
+        None => print!("{}", ts),
+        Some(out) => fs::write(out, ts)?,
+    };
+    Ok(())
+}
+
+fn idl_python(
+    file: String,
+    out: Option<String>,
+    program_id: Option<String>,
+    templates: Option<String>,
+) -> Result<()> {
+    let idl = anchor_syn::idl::io::read(file)?;
+    warn_sunset(&idl);
+    let py = anchor_syn::idl::codegen::template::render(
+        anchor_syn::idl::codegen::template::Backend::Python,
+        &idl,
+        program_id.as_deref(),
+        anchor_syn::idl::codegen::python::generate(&idl, program_id.as_deref())?,
+        templates.as_deref().map(Path::new),
+    )?;
+    match out {
+        None => print!("{}", py),
+        Some(out) => fs::write(out, py)?,
+    };
+    Ok(())
+}
+
+fn idl_rust_cpi(
+    file: String,
+    out: String,
+    program_id: Option<String>,
+    templates: Option<String>,
+) -> Result<()> {
+    let idl = anchor_syn::idl::io::read(file)?;
+    warn_sunset(&idl);
+    let mut cpi = anchor_syn::idl::codegen::rust_cpi::generate(&idl, program_id.as_deref())?;
+    cpi.lib_rs = anchor_syn::idl::codegen::template::render(
+        anchor_syn::idl::codegen::template::Backend::RustCpi,
+        &idl,
+        program_id.as_deref(),
+        cpi.lib_rs,
+        templates.as_deref().map(Path::new),
+    )?;
+    cpi.write(&out)?;
+    // The generated code is on a single line until formatted. It's still valid
+    // if rustfmt isn't installed.
+    let lib_rs = Path::new(&out).join("src").join("lib.rs");
+    if let Err(e) = std::process::Command::new("rustfmt")
+        .args(["--edition", "2021"])
+        .arg(&lib_rs)
+        .stderr(Stdio::null())
+        .status()
+    {
+        println!("Unable to format {}: {}", lib_rs.display(), e);
+    }
+    Ok(())
+}
+
+fn idl_admin_cli(file: String, out: String, program_id: Option<String>) -> Result<()> {
+    let idl = anchor_syn::idl::io::read(file)?;
+    warn_sunset(&idl);
+    let cli = anchor_syn::idl::codegen::admin_cli::generate(&idl, program_id.as_deref())?;
+    cli.write(&out)?;
+    let main_rs = Path::new(&out).join("src").join("main.rs");
+    if let Err(e) = std::process::Command::new("rustfmt")
+        .args(["--edition", "2021"])
+        .arg(&main_rs)
+        .stderr(Stdio::null())
+        .status()
+    {
+        println!("Unable to format {}: {}", main_rs.display(), e);
+    }
+    Ok(())
+}
+
+fn idl_test_vectors(file: String, seed: u64, out: Option<String>) -> Result<()> {
+    let idl = anchor_syn::idl::io::read(file)?;
+    let vectors = serde_json::to_string_pretty(&idl.test_vectors(seed)?)?;
+    match out {
+        None => println!("{}", vectors),
+        Some(out) => fs::write(out, vectors)?,
+    };
+    Ok(())
+}
+
+fn idl_fetch(cfg_override: &ConfigOverride, address: Pubkey, out: Option<String>) -> Result<()> {
+    let idl = fetch_idl(cfg_override, address)?;
+    let out = match out {
+        None => OutFile::Stdout,
+        Some(out) => OutFile::File(PathBuf::from(out)),
+    };
+    write_idl(&idl, out)
+}
+
+fn write_idl(idl: &Idl, out: OutFile) -> Result<()> {
+    match out {
+        OutFile::Stdout => {
+            anchor_syn::idl::io::write(idl, std::io::stdout().lock())?;
+            println!();
+        }
+        OutFile::File(out) => anchor_syn::idl::io::write(idl, File::create(out)?)?,
+        OutFile::Split(dir) => anchor_syn::idl::io::write_split(idl, dir)?,
+    };
+
+    Ok(())
+}
+
+enum OutFile {
+    Stdout,
+    File(PathBuf),
+    // A directory to write the IDL to split into sections.
+    Split(PathBuf),
+}
+
+// Builds, deploys, and tests all workspace programs in a single command.
+#[allow(clippy::too_many_arguments)]
+fn test(
+    cfg_override: &ConfigOverride,
+    skip_deploy: bool,
+    skip_local_validator: bool,
+    skip_build: bool,
+    skip_lint: bool,
+    detach: bool,
+    offline: bool,
+    extra_args: Vec<String>,
+    cargo_args: Vec<String>,
+) -> Result<()> {
+    with_workspace(cfg_override, |cfg| {
+        // Build if needed.
+        if !skip_build {
+            build(
+                cfg_override,
+                None,
+                None,
+                false,
+                skip_lint,
                 None,
                 None,
                 None,
@@ -1881,6 +2740,9 @@ fn test(
             )?;
         }
 
+        // Programs must implement their interfaces before any test suite runs.
+        check_interfaces(cfg)?;
+
         let root = cfg.path().parent().unwrap().to_owned();
         cfg.add_test_config(root)?;
 
@@ -1905,6 +2767,7 @@ fn test(
                 skip_local_validator,
                 skip_deploy,
                 detach,
+                offline,
                 &cfg.test_validator,
                 &cfg.scripts,
                 &extra_args,
@@ -1932,6 +2795,7 @@ fn test(
                     skip_local_validator,
                     skip_deploy,
                     detach,
+                    offline,
                     &test_suite.1.test,
                     &test_suite.1.scripts,
                     &extra_args,
@@ -1950,17 +2814,23 @@ fn run_test_suite(
     skip_local_validator: bool,
     skip_deploy: bool,
     detach: bool,
+    offline: bool,
     test_validator: &Option<TestValidator>,
     scripts: &ScriptsConfig,
     extra_args: &[String],
 ) -> Result<()> {
     println!("\nRunning test suite: {:#?}\n", test_suite_path.as_ref());
+    let backend = test_validator
+        .as_ref()
+        .and_then(|test| test.backend)
+        .unwrap_or(TestBackend::Validator);
+
     // Start local test validator, if needed.
     let mut validator_handle = None;
-    if is_localnet && (!skip_local_validator) {
+    if is_localnet && (!skip_local_validator) && !backend.is_in_process() {
         let flags = match skip_deploy {
             true => None,
-            false => Some(validator_flags(cfg, test_validator)?),
+            false => Some(validator_flags(cfg, test_validator, offline)?),
         };
         validator_handle = Some(start_test_validator(cfg, test_validator, flags, true)?);
     }
@@ -1979,7 +2849,16 @@ fn run_test_suite(
     );
 
     // Setup log reader.
-    let log_streams = stream_logs(cfg, &url);
+    let log_streams = match backend.is_in_process() {
+        true => Ok(vec![]),
+        false => stream_logs(cfg, &url),
+    };
+
+    // Programs loaded by in process backends.
+    let test_programs = match backend.is_in_process() {
+        true => test_programs(cfg, test_validator)?,
+        false => String::new(),
+    };
 
     // Run the tests.
     let test_result: Result<_> = {
@@ -1997,6 +2876,8 @@ fn run_test_suite(
             .args(args)
             .env("ANCHOR_PROVIDER_URL", url)
             .env("ANCHOR_WALLET", cfg.provider.wallet.to_string())
+            .env("ANCHOR_TEST_BACKEND", backend.as_str())
+            .env("ANCHOR_TEST_PROGRAMS", test_programs)
             .env("NODE_OPTIONS", node_options)
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
@@ -2037,12 +2918,38 @@ fn run_test_suite(
 
     Ok(())
 }
+// Returns the programs of the workspace and of the genesis configuration, as
+// the `<program id>=<path>` pairs of `ANCHOR_TEST_PROGRAMS`, for the tests to
+// load in process.
+fn test_programs(cfg: &WithPath<Config>, test_validator: &Option<TestValidator>) -> Result<String> {
+    let programs = cfg.programs.get(&Cluster::Localnet);
+
+    let mut entries = Vec::new();
+    for program in cfg.read_all_programs()? {
+        let address = programs
+            .and_then(|m| m.get(&program.lib_name))
+            .map(|deployment| Ok(deployment.address.to_string()))
+            .unwrap_or_else(|| program.pubkey().map(|p| p.to_string()))?;
+        entries.push(format!("{}={}", address, program.binary_path().display()));
+    }
+    if let Some(genesis) = test_validator
+        .as_ref()
+        .and_then(|test| test.genesis.as_ref())
+    {
+        for entry in genesis {
+            entries.push(format!("{}={}", entry.address, entry.program));
+        }
+    }
+    Ok(entries.join(","))
+}
+
 // Returns the solana-test-validator flags. This will embed the workspace
 // programs in the genesis block so we don't have to deploy every time. It also
 // allows control of other solana-test-validator features.
 fn validator_flags(
     cfg: &WithPath<Config>,
     test_validator: &Option<TestValidator>,
+    offline: bool,
 ) -> Result<Vec<String>> {
     let programs = cfg.programs.get(&Cluster::Localnet);
 
@@ -2104,16 +3011,19 @@ fn validator_flags(
                         flags.push(entry["filename"].as_str().unwrap().to_string());
                     }
                 } else if key == "clone" {
-                    // Client for fetching accounts data
-                    let client = if let Some(url) = entries["url"].as_str() {
-                        RpcClient::new(url.to_string())
-                    } else {
-                        return Err(anyhow!(
-                    "Validator url for Solana's JSON RPC should be provided in order to clone accounts from it"
-                ));
+                    let url = match entries["url"].as_str() {
+                        Some(url) => url,
+                        None => return Err(anyhow!(
+                            "Validator url for Solana's JSON RPC should be provided in order to clone accounts from it"
+                        )),
                     };
+                    // Accounts are cloned through the fork cache, only
+                    // fetching the ones that aren't cached yet.
+                    let cache = fork_cache::ForkCache::new(fork_cache::DEFAULT_FORK_CACHE_DIR);
+                    let client = RpcClient::new(url.to_string());
+                    let fetch = (!offline).then(|| |keys: &[Pubkey]| fetch_accounts(&client, keys));
 
-                    let mut pubkeys = value
+                    let pubkeys = value
                         .as_array()
                         .unwrap()
                         .iter()
@@ -2123,39 +3033,42 @@ fn validator_flags(
                                 .map_err(|_| anyhow!("Invalid pubkey {}", address))
                         })
                         .collect::<Result<HashSet<Pubkey>>>()?;
-
-                    let accounts_keys = pubkeys.iter().cloned().collect::<Vec<_>>();
-                    let accounts = client
-                        .get_multiple_accounts_with_commitment(
-                            &accounts_keys,
-                            CommitmentConfig::default(),
-                        )?
-                        .value;
+                    let mut accounts_keys = pubkeys.iter().cloned().collect::<Vec<_>>();
+                    let mut accounts = cache.resolve(url, &accounts_keys, fetch)?;
 
                     // Check if there are program accounts
-                    for (account, acc_key) in accounts.iter().zip(accounts_keys) {
-                        if let Some(account) = account {
-                            if account.owner == bpf_loader_upgradeable::id() {
-                                let upgradable: UpgradeableLoaderState = account
-                                    .deserialize_data()
-                                    .map_err(|_| anyhow!("Invalid program account {}", acc_key))?;
-
-                                if let UpgradeableLoaderState::Program {
-                                    programdata_address,
-                                } = upgradable
-                                {
-                                    pubkeys.insert(programdata_address);
+                    let mut programdata = vec![];
+                    for (account, acc_key) in accounts.iter().zip(accounts_keys.iter()) {
+                        let account = account
+                            .as_ref()
+                            .ok_or_else(|| anyhow!("Account {} not found", acc_key))?;
+                        if account.account.owner == bpf_loader_upgradeable::id() {
+                            let upgradable: UpgradeableLoaderState = account
+                                .account
+                                .deserialize_data()
+                                .map_err(|_| anyhow!("Invalid program account {}", acc_key))?;
+
+                            if let UpgradeableLoaderState::Program {
+                                programdata_address,
+                            } = upgradable
+                            {
+                                if !pubkeys.contains(&programdata_address) {
+                                    programdata.push(programdata_address);
                                 }
                             }
-                        } else {
-                            return Err(anyhow!("Account {} not found", acc_key));
                         }
                     }
+                    accounts.extend(cache.resolve(url, &programdata, fetch)?);
+                    accounts_keys.extend(programdata);
 
-                    for pubkey in &pubkeys {
-                        // Push the clone flag for each array entry
-                        flags.push("--clone".to_string());
-                        flags.push(pubkey.to_string());
+                    for (account, acc_key) in accounts.iter().zip(accounts_keys.iter()) {
+                        let account = account
+                            .as_ref()
+                            .ok_or_else(|| anyhow!("Account {} not found", acc_key))?;
+                        // Push the account flag for each cloned account
+                        flags.push("--account".to_string());
+                        flags.push(acc_key.to_string());
+                        flags.push(account.file.display().to_string());
                     }
                 } else {
                     // Remaining validator flags are non-array types
@@ -2173,6 +3086,13 @@ fn validator_flags(
     Ok(flags)
 }
 
+// Fetches accounts to add to the fork cache.
+fn fetch_accounts(client: &RpcClient, keys: &[Pubkey]) -> Result<fork_cache::Fetched> {
+    let response =
+        client.get_multiple_accounts_with_commitment(keys, CommitmentConfig::default())?;
+    Ok((response.context.slot, response.value))
+}
+
 fn stream_logs(config: &WithPath<Config>, rpc_url: &str) -> Result<Vec<std::process::Child>> {
     let program_logs_dir = ".anchor/program-logs";
     if Path::new(program_logs_dir).exists() {
@@ -2622,10 +3542,7 @@ fn create_idl_buffer(
 
 // Serialize and compress the idl.
 fn serialize_idl(idl: &Idl) -> Result<Vec<u8>> {
-    let json_bytes = serde_json::to_vec(idl)?;
-    let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
-    e.write_all(&json_bytes)?;
-    e.finish().map_err(Into::into)
+    anchor_syn::idl::onchain::compress(idl, IdlCompression::default())
 }
 
 fn serialize_idl_ix(ix_inner: anchor_lang::idl::IdlInstruction) -> Result<Vec<u8>> {
@@ -3042,9 +3959,450 @@ fn registry_api_token(_cfg_override: &ConfigOverride) -> Result<String> {
 fn keys(cfg_override: &ConfigOverride, cmd: KeysCommand) -> Result<()> {
     match cmd {
         KeysCommand::List => keys_list(cfg_override),
+        KeysCommand::Check => keys_check(cfg_override),
+    }
+}
+
+fn replay(
+    cfg_override: &ConfigOverride,
+    signature: Signature,
+    accounts: Vec<String>,
+    skip_build: bool,
+    offline: bool,
+) -> Result<()> {
+    with_workspace(cfg_override, |cfg| {
+        if cfg.provider.cluster == Cluster::Localnet {
+            return Err(anyhow!(
+                "Transactions are replayed from the provider cluster, set it with --provider.cluster"
+            ));
+        }
+        if !skip_build {
+            build(
+                cfg_override,
+                None,
+                None,
+                false,
+                false,
+                None,
+                None,
+                None,
+                BootstrapMode::None,
+                None,
+                None,
+                vec![],
+                false,
+            )?;
+        }
+
+        let replay_dir = Path::new(".anchor/replay").join(signature.to_string());
+        fs::create_dir_all(&replay_dir)?;
+
+        let url = cfg.provider.cluster.url().to_string();
+        let client = RpcClient::new(url.clone());
+        let transaction_file = replay_dir.join("transaction.json");
+        let confirmed: EncodedConfirmedTransactionWithStatusMeta = match offline {
+            true => serde_json::from_slice(&fs::read(&transaction_file).with_context(|| {
+                format!(
+                    "Transaction {} wasn't replayed before, run without --offline to fetch it",
+                    signature
+                )
+            })?)?,
+            false => {
+                let confirmed =
+                    client.get_transaction(&signature, UiTransactionEncoding::Base64)?;
+                fs::write(&transaction_file, serde_json::to_string_pretty(&confirmed)?)?;
+                confirmed
+            }
+        };
+        let meta = confirmed
+            .transaction
+            .meta
+            .ok_or_else(|| anyhow!("Transaction status not available"))?;
+        let tx = confirmed
+            .transaction
+            .transaction
+            .decode()
+            .and_then(|tx| tx.into_legacy_transaction())
+            .ok_or_else(|| anyhow!("Only legacy transactions can be replayed"))?;
+        let keys = &tx.message.account_keys;
+        let writable: Vec<Pubkey> = keys
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| tx.message.is_writable(*idx))
+            .map(|(_, key)| *key)
+            .collect();
+
+        // Workspace programs are replaced by their local build.
+        let deployments = cfg.programs.get(&cfg.provider.cluster);
+        let mut programs = HashMap::new();
+        for program in cfg.read_all_programs()? {
+            let address = match deployments.and_then(|m| m.get(&program.lib_name)) {
+                Some(deployment) => deployment.address,
+                None => program.pubkey()?,
+            };
+            programs.insert(address, program);
+        }
+
+        // State given explicitly, e.g. captured before the transaction.
+        let mut fixtures = HashMap::new();
+        for file in accounts.iter() {
+            let fixture: anonymize::AccountFixture = serde_json::from_slice(&fs::read(file)?)
+                .with_context(|| format!("Error parsing {}", file))?;
+            fixtures.insert(Pubkey::from_str(&fixture.pubkey)?, (file, fixture));
+        }
+
+        // Other accounts are cloned through the fork cache.
+        let cache = fork_cache::ForkCache::new(fork_cache::DEFAULT_FORK_CACHE_DIR);
+        let fetch = (!offline).then(|| |keys: &[Pubkey]| fetch_accounts(&client, keys));
+
+        let mut flags = vec![];
+        let mut idls = vec![];
+        let mut pre = HashMap::new();
+        let mut expected = HashMap::new();
+        let current = cache.resolve(&url, keys, fetch)?;
+        for (key, cached) in keys.iter().zip(current) {
+            if let Some(program) = programs.get(key) {
+                flags.push("--bpf-program".to_string());
+                flags.push(key.to_string());
+                flags.push(program.binary_path().display().to_string());
+                idls.extend(program.idl.clone());
+                continue;
+            }
+            if let Some((file, fixture)) = fixtures.get(key) {
+                flags.push("--account".to_string());
+                flags.push(key.to_string());
+                flags.push(file.to_string());
+                pre.insert(*key, replay::AccountSnapshot::from_fixture(fixture)?);
+                continue;
+            }
+            // Accounts created by the transaction.
+            let (file, account) = match cached {
+                Some(cached) => (cached.file, cached.account),
+                None => continue,
+            };
+            if account.owner == bpf_loader_upgradeable::id() {
+                flags.push("--account".to_string());
+                flags.push(key.to_string());
+                flags.push(file.display().to_string());
+                if let Ok(UpgradeableLoaderState::Program {
+                    programdata_address,
+                }) = account.deserialize_data()
+                {
+                    let programdata = cache.resolve(&url, &[programdata_address], fetch)?;
+                    if let Some(programdata) = &programdata[0] {
+                        flags.push("--account".to_string());
+                        flags.push(programdata_address.to_string());
+                        flags.push(programdata.file.display().to_string());
+                    }
+                }
+                continue;
+            }
+            // Native programs and sysvars are provided by the validator.
+            if account.executable
+                || account.owner == solana_sdk::native_loader::id()
+                || account.owner == sysvar::id()
+            {
+                continue;
+            }
+
+            // RPC nodes only serve the current state of accounts, which for
+            // writable accounts is at best the state left by the transaction.
+            if writable.contains(key) && !offline {
+                let since = client.get_signatures_for_address_with_config(
+                    key,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before: None,
+                        until: Some(signature),
+                        limit: Some(1),
+                        commitment: None,
+                    },
+                )?;
+                if since.is_empty() {
+                    expected.insert(*key, replay::AccountSnapshot::from(&account));
+                    println!(
+                        "WARNING: {} is replayed against its state after the transaction, pass its prior state with --account",
+                        key
+                    );
+                } else {
+                    println!(
+                        "WARNING: {} changed since the transaction, it is replayed against its current state",
+                        key
+                    );
+                }
+            }
+            flags.push("--account".to_string());
+            flags.push(key.to_string());
+            flags.push(file.display().to_string());
+            pre.insert(*key, replay::AccountSnapshot::from(&account));
+        }
+
+        let validator_handle =
+            &mut start_test_validator(cfg, &cfg.test_validator, Some(flags), true)?;
+        let local = RpcClient::new(test_validator_rpc_url(&cfg.test_validator));
+        let result = local.simulate_transaction_with_config(
+            &tx,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(CommitmentConfig::processed()),
+                accounts: Some(RpcSimulateTransactionAccountsConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    addresses: writable.iter().map(|key| key.to_string()).collect(),
+                }),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        );
+        if let Err(err) = validator_handle.kill() {
+            println!(
+                "Failed to kill subprocess {}: {}",
+                validator_handle.id(),
+                err
+            );
+        }
+        let result = result?.value;
+
+        let post = result.accounts.unwrap_or_default();
+        let reports: Vec<replay::AccountReport> = writable
+            .iter()
+            .zip(post)
+            .map(|(key, account)| {
+                let idx = keys.iter().position(|k| k == key).unwrap();
+                replay::AccountReplay {
+                    address: *key,
+                    pre: pre.get(key).cloned(),
+                    post: account
+                        .and_then(|account| account.decode::<Account>())
+                        .map(|account| replay::AccountSnapshot::from(&account)),
+                    expected: expected.get(key).cloned(),
+                    expected_lamports: meta.post_balances.get(idx).cloned(),
+                }
+                .report(&idls)
+            })
+            .collect();
+
+        let report = replay::ReplayReport {
+            signature: signature.to_string(),
+            slot: confirmed.slot,
+            original_error: meta.err,
+            replay_error: result.err,
+            logs: result.logs.unwrap_or_default(),
+            accounts: reports,
+        };
+        for log in report.logs.iter() {
+            println!("{}", log);
+        }
+        println!();
+        for account in report.accounts.iter() {
+            match account.post.get("type").and_then(|ty| ty.as_str()) {
+                Some(ty) => println!("{} ({})", account.address, ty),
+                None => println!("{}", account.address),
+            }
+            for change in account.changes.iter() {
+                match &change.expected {
+                    Some(expected) if expected != &change.post => println!(
+                        "  {}: {} -> {} (expected {})",
+                        change.path, change.pre, change.post, expected
+                    ),
+                    _ => println!("  {}: {} -> {}", change.path, change.pre, change.post),
+                }
+            }
+            for mismatch in account.mismatches.iter() {
+                if !account.changes.iter().any(|c| c.path == mismatch.path) {
+                    println!(
+                        "  {}: {} (expected {})",
+                        mismatch.path,
+                        mismatch.post,
+                        mismatch.expected.as_ref().unwrap()
+                    );
+                }
+            }
+        }
+        println!();
+        println!("Original result: {:?}", report.original_error);
+        println!("Replay result: {:?}", report.replay_error);
+
+        let report_file = replay_dir.join("report.json");
+        fs::write(&report_file, serde_json::to_string_pretty(&report)?)?;
+        println!("Report written to {}", report_file.display());
+        Ok(())
+    })
+}
+
+fn fork_cache(cfg_override: &ConfigOverride, cmd: ForkCacheCommand) -> Result<()> {
+    with_workspace(cfg_override, |_cfg| {
+        let cache = fork_cache::ForkCache::new(fork_cache::DEFAULT_FORK_CACHE_DIR);
+        match cmd {
+            ForkCacheCommand::List => {
+                for snapshot in cache.snapshots()? {
+                    println!(
+                        "{} (slot {}): {} account(s)",
+                        snapshot.url,
+                        snapshot.slot,
+                        snapshot.accounts.len()
+                    );
+                }
+            }
+            ForkCacheCommand::Clear { slot, address } => {
+                let (snapshots, accounts) = cache.clear(slot, &address)?;
+                match address.is_empty() {
+                    true => println!("Removed {} snapshot(s)", snapshots),
+                    false => println!("Removed {} account(s)", accounts),
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+fn package(cfg_override: &ConfigOverride, cmd: PackageCommand) -> Result<()> {
+    match cmd {
+        PackageCommand::Create {
+            program_name,
+            verifiable,
+            keypair,
+            out,
+        } => package_create(cfg_override, program_name, verifiable, keypair, out),
+        PackageCommand::Inspect { file } => {
+            let artifact = artifact::Artifact::read(&file)?;
+            println!("{}", serde_json::to_string_pretty(&artifact.manifest)?);
+            match artifact.verify_signature()? {
+                Some(signer) => println!("Verified: signed by {}", signer),
+                None => println!("Verified: unsigned"),
+            }
+            Ok(())
+        }
+        PackageCommand::Extract { file, out_dir } => {
+            for path in artifact::Artifact::read(&file)?.extract(&out_dir)? {
+                println!("{}", path.display());
+            }
+            Ok(())
+        }
     }
 }
 
+fn package_create(
+    cfg_override: &ConfigOverride,
+    program_name: String,
+    verifiable: bool,
+    keypair: Option<String>,
+    out: Option<String>,
+) -> Result<()> {
+    with_workspace(cfg_override, |cfg| {
+        let program = cfg
+            .get_program(&program_name)?
+            .ok_or_else(|| anyhow!("Workspace member not found"))?;
+        let binary_path = match verifiable {
+            true => PathBuf::from(format!("target/verifiable/{}.so", program.lib_name)),
+            false => program.binary_path(),
+        };
+        let binary = fs::read(&binary_path).with_context(|| {
+            format!(
+                "Error reading {}, was the program built?",
+                binary_path.display()
+            )
+        })?;
+        let cargo_lock = [
+            program.path().join("Cargo.lock"),
+            PathBuf::from("Cargo.lock"),
+        ]
+        .into_iter()
+        .find(|path| path.exists());
+        let toolchain = artifact::Toolchain {
+            anchor_version: VERSION.to_string(),
+            solana_version: command_version("solana"),
+            rustc_version: command_version("rustc"),
+            docker_image: verifiable.then(|| cfg.docker()),
+            cargo_lock_sha256: match cargo_lock {
+                None => None,
+                Some(path) => Some(artifact::FileDigest::of(&fs::read(path)?).sha256),
+            },
+        };
+
+        // The IDL of the last build, if any.
+        let idl_path = PathBuf::from(format!("target/idl/{}.json", program.lib_name));
+        let idl = match idl_path.exists() {
+            true => Some(serde_json::from_slice(&fs::read(&idl_path)?)?),
+            false => program.idl.clone(),
+        };
+
+        // Without creating the program keypair if it doesn't exist.
+        let program_id = solana_sdk::signature::read_keypair_file(format!(
+            "target/deploy/{}-keypair.json",
+            program.lib_name
+        ))
+        .ok()
+        .map(|keypair| keypair.pubkey());
+
+        let mut artifact =
+            artifact::Artifact::new(program.lib_name.clone(), program_id, binary, idl, toolchain)?;
+        if let Some(keypair) = keypair {
+            let keypair = solana_sdk::signature::read_keypair_file(&keypair)
+                .map_err(|_| anyhow!("Unable to read keypair file"))?;
+            artifact.sign(&keypair)?;
+        }
+        let out = out.unwrap_or_else(|| {
+            format!(
+                "target/{}.{}",
+                program.lib_name,
+                artifact::ARTIFACT_EXTENSION
+            )
+        });
+        artifact.write(&out)?;
+        println!("Package written to {}", out);
+        Ok(())
+    })
+}
+
+// The version printed by `<cmd> --version`, e.g. `1.10.29` for
+// `solana-cli 1.10.29 (src:...)`.
+fn command_version(cmd: &str) -> Option<String> {
+    let output = std::process::Command::new(cmd)
+        .arg("--version")
+        .output()
+        .ok()?;
+    let output = String::from_utf8(output.stdout).ok()?;
+    output.split_whitespace().nth(1).map(|v| v.to_string())
+}
+
+fn anonymize(
+    files: Vec<String>,
+    out_dir: String,
+    idl: Option<String>,
+    keep: Vec<Pubkey>,
+    mapping: Option<String>,
+) -> Result<()> {
+    let mut fixtures = files
+        .iter()
+        .map(|file| {
+            let bytes = fs::read(file).with_context(|| format!("Error reading {}", file))?;
+            serde_json::from_slice(&bytes).with_context(|| format!("Error parsing {}", file))
+        })
+        .collect::<Result<Vec<anonymize::AccountFixture>>>()?;
+    let idl: Option<Idl> = match idl {
+        None => None,
+        Some(idl) => Some(anchor_syn::idl::io::read(idl)?),
+    };
+
+    let mut anonymizer = anonymize::Anonymizer::new(rand::random(), keep);
+    anonymizer.anonymize(&mut fixtures, idl.as_ref())?;
+
+    fs::create_dir_all(&out_dir)?;
+    for fixture in fixtures.iter() {
+        let out = Path::new(&out_dir).join(format!("{}.json", fixture.pubkey));
+        fs::write(&out, serde_json::to_string_pretty(fixture)?)?;
+        println!("{}", out.display());
+    }
+    if let Some(mapping_file) = mapping {
+        let mapping: BTreeMap<String, String> = anonymizer
+            .mapping()
+            .iter()
+            .map(|(original, anonymized)| (original.to_string(), anonymized.to_string()))
+            .collect();
+        fs::write(mapping_file, serde_json::to_string_pretty(&mapping)?)?;
+    }
+    Ok(())
+}
+
 fn keys_list(cfg_override: &ConfigOverride) -> Result<()> {
     let cfg = Config::discover(cfg_override)?.expect("Not in workspace.");
     for program in cfg.read_all_programs()? {
@@ -3054,11 +4412,36 @@ fn keys_list(cfg_override: &ConfigOverride) -> Result<()> {
     Ok(())
 }
 
+fn keys_check(cfg_override: &ConfigOverride) -> Result<()> {
+    let cfg = Config::discover(cfg_override)?.expect("Not in workspace.");
+    let report = program_ids::check(&cfg)?;
+    for (program, deps) in report.graph.iter() {
+        if deps.is_empty() {
+            println!("{}", program);
+        } else {
+            let deps: Vec<&str> = deps.iter().map(|d| d.as_str()).collect();
+            println!("{} -> {}", program, deps.join(", "));
+        }
+    }
+    if !report.mismatches.is_empty() {
+        for mismatch in report.mismatches.iter() {
+            eprintln!("{}", mismatch);
+        }
+        return Err(anyhow!(
+            "Found {} program id mismatch(es) for cluster {}",
+            report.mismatches.len(),
+            cfg.provider.cluster
+        ));
+    }
+    Ok(())
+}
+
 fn localnet(
     cfg_override: &ConfigOverride,
     skip_build: bool,
     skip_deploy: bool,
     skip_lint: bool,
+    offline: bool,
     cargo_args: Vec<String>,
 ) -> Result<()> {
     with_workspace(cfg_override, |cfg| {
@@ -3083,7 +4466,7 @@ fn localnet(
 
         let flags = match skip_deploy {
             true => None,
-            false => Some(validator_flags(cfg, &cfg.test_validator)?),
+            false => Some(validator_flags(cfg, &cfg.test_validator, offline)?),
         };
 
         let validator_handle = &mut start_test_validator(cfg, &cfg.test_validator, flags, false)?;
@@ -3113,6 +4496,312 @@ fn localnet(
     })
 }
 
+fn dev(
+    cfg_override: &ConfigOverride,
+    skip_lint: bool,
+    offline: bool,
+    clients: String,
+    interval: u64,
+    cargo_args: Vec<String>,
+) -> Result<()> {
+    with_workspace(cfg_override, |cfg| {
+        if !dev::run_stage(dev::Stage::Build, || dev_build(skip_lint, &cargo_args)) {
+            return Err(anyhow!("Unable to build the workspace"));
+        }
+
+        // Start the validator with the programs deployed as upgradeable
+        // programs, which also adds their address to their IDL.
+        let programs = cfg.read_all_programs()?;
+        let binaries = programs
+            .iter()
+            .map(|program| program.binary_path().display().to_string())
+            .collect();
+        let flags = dev::upgradeable_program_flags(
+            validator_flags(cfg, &cfg.test_validator, offline)?,
+            &binaries,
+            &cfg.wallet_kp()?.pubkey().to_string(),
+        );
+        let validator_handle =
+            &mut start_test_validator(cfg, &cfg.test_validator, Some(flags), false)?;
+        let mut deployed: BTreeMap<String, Option<std::time::SystemTime>> = programs
+            .iter()
+            .map(|program| {
+                (
+                    program.lib_name.clone(),
+                    dev::modified(&program.binary_path()),
+                )
+            })
+            .collect();
+        dev::run_stage(dev::Stage::Clients, || dev_clients(cfg, &clients));
+
+        // Setup log reader.
+        let url = test_validator_rpc_url(&cfg.test_validator);
+        let log_streams = stream_logs(cfg, &url);
+
+        // Stop on Enter, while checking for changes in between.
+        let (stop, stopped) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = std::io::stdin().lock().lines().next();
+            let _ = stop.send(());
+        });
+        let mut watcher = dev::Watcher::new(programs.into_iter().map(|p| p.path).collect());
+        println!("[dev] Watching for changes, press Enter to stop");
+        while let Err(std::sync::mpsc::RecvTimeoutError::Timeout) =
+            stopped.recv_timeout(std::time::Duration::from_millis(interval))
+        {
+            let changed = watcher.changed();
+            if changed.is_empty() {
+                continue;
+            }
+            println!("[dev] {} file(s) changed", changed.len());
+            let up_to_date =
+                dev::run_stage(dev::Stage::Build, || dev_build(skip_lint, &cargo_args))
+                    && dev::run_stage(dev::Stage::Upgrade, || {
+                        dev_upgrade(cfg, &url, &mut deployed)
+                    })
+                    && dev::run_stage(dev::Stage::Idl, || dev_idl(cfg))
+                    && dev::run_stage(dev::Stage::Clients, || dev_clients(cfg, &clients));
+            if up_to_date {
+                println!("[dev] Up to date, watching for changes");
+            }
+        }
+
+        // Check all errors and shut down.
+        if let Err(err) = validator_handle.kill() {
+            println!(
+                "Failed to kill subprocess {}: {}",
+                validator_handle.id(),
+                err
+            );
+        }
+
+        for mut child in log_streams? {
+            if let Err(err) = child.kill() {
+                println!("Failed to kill subprocess {}: {}", child.id(), err);
+            }
+        }
+
+        Ok(())
+    })
+}
+
+// Builds the workspace in a subprocess, since a failing build exits the
+// process.
+fn dev_build(skip_lint: bool, cargo_args: &[String]) -> Result<String> {
+    let mut cmd = std::process::Command::new(std::env::current_exe()?);
+    cmd.arg("build");
+    if skip_lint {
+        cmd.arg("--skip-lint");
+    }
+    if !cargo_args.is_empty() {
+        cmd.arg("--").args(cargo_args);
+    }
+    let exit = cmd
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !exit.status.success() {
+        return Err(anyhow!("anchor build exited with {}", exit.status));
+    }
+    Ok(String::new())
+}
+
+// Upgrades the programs whose binary changed since they were last deployed.
+fn dev_upgrade(
+    cfg: &WithPath<Config>,
+    url: &str,
+    deployed: &mut BTreeMap<String, Option<std::time::SystemTime>>,
+) -> Result<String> {
+    let programs = cfg.programs.get(&Cluster::Localnet);
+    let mut upgraded = vec![];
+    for program in cfg.read_all_programs()? {
+        let binary_path = program.binary_path();
+        let modified = dev::modified(&binary_path);
+        if deployed.get(&program.lib_name) == Some(&modified) {
+            continue;
+        }
+        let address = programs
+            .and_then(|m| m.get(&program.lib_name))
+            .map(|deployment| Ok(deployment.address.to_string()))
+            .unwrap_or_else(|| program.pubkey().map(|p| p.to_string()))?;
+        let exit = std::process::Command::new("solana")
+            .arg("program")
+            .arg("deploy")
+            .arg("--url")
+            .arg(url)
+            .arg("--keypair")
+            .arg(cfg.provider.wallet.to_string())
+            .arg("--program-id")
+            .arg(&address)
+            .arg(&binary_path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()?;
+        if !exit.status.success() {
+            return Err(anyhow!(
+                "Unable to upgrade {}: {}",
+                program.lib_name,
+                String::from_utf8_lossy(&exit.stderr).trim()
+            ));
+        }
+        deployed.insert(program.lib_name.clone(), modified);
+        upgraded.push(program.lib_name);
+    }
+    Ok(match upgraded.is_empty() {
+        true => "no program changed".to_string(),
+        false => upgraded.join(", "),
+    })
+}
+
+// Writes the IDL of the programs with their address, as `anchor build` leaves
+// it out.
+fn dev_idl(cfg: &WithPath<Config>) -> Result<String> {
+    let programs = cfg.programs.get(&Cluster::Localnet);
+    for mut program in cfg.read_all_programs()? {
+        let address = programs
+            .and_then(|m| m.get(&program.lib_name))
+            .map(|deployment| Ok(deployment.address.to_string()))
+            .unwrap_or_else(|| program.pubkey().map(|p| p.to_string()))?;
+        if let Some(idl) = program.idl.as_mut() {
+            idl.metadata = Some(serde_json::to_value(IdlTestMetadata { address })?);
+            let idl_out = PathBuf::from("target/idl")
+                .join(&idl.name)
+                .with_extension("json");
+            write_idl(idl, OutFile::File(idl_out))?;
+        }
+    }
+    Ok(String::new())
+}
+
+// Regenerates the TypeScript client of each program from its IDL.
+fn dev_clients(cfg: &WithPath<Config>, clients: &str) -> Result<String> {
+    fs::create_dir_all(clients)?;
+    for program in cfg.read_all_programs()? {
+        let idl_path = PathBuf::from("target/idl")
+            .join(&program.lib_name)
+            .with_extension("json");
+        if !idl_path.exists() {
+            continue;
+        }
+        let idl = anchor_syn::idl::io::read(&idl_path)?;
+        let ts = anchor_syn::idl::codegen::typescript::generate(&idl)?;
+        fs::write(
+            Path::new(clients)
+                .join(&program.lib_name)
+                .with_extension("ts"),
+            ts,
+        )?;
+    }
+    Ok(format!("written to {}", clients))
+}
+
+fn rent_check(
+    cfg_override: &ConfigOverride,
+    program_name: Option<String>,
+    idl: Option<String>,
+    fix: bool,
+) -> Result<()> {
+    with_workspace(cfg_override, |cfg| {
+        let url = cluster_url(cfg, &cfg.test_validator);
+        let client = RpcClient::new(url);
+        let deployments = cfg.programs.get(&cfg.provider.cluster);
+
+        // The rent-exemption of each size, fetched once.
+        let mut minimum_balances = BTreeMap::new();
+        let mut minimum_balance = |len: usize| -> Result<u64> {
+            if let Some(lamports) = minimum_balances.get(&len) {
+                return Ok(*lamports);
+            }
+            let lamports = client.get_minimum_balance_for_rent_exemption(len)?;
+            minimum_balances.insert(len, lamports);
+            Ok(lamports)
+        };
+
+        let mut underfunded = vec![];
+        for program in cfg.read_all_programs()? {
+            if let Some(program_name) = &program_name {
+                if program_name != &program.lib_name {
+                    continue;
+                }
+            }
+            let address = match deployments.and_then(|m| m.get(&program.lib_name)) {
+                Some(deployment) => deployment.address,
+                None => program.pubkey()?,
+            };
+            let program_idl = match &idl {
+                Some(idl) => Some(anchor_syn::idl::io::read(idl)?),
+                None => program.idl,
+            };
+
+            let accounts = client.get_program_accounts(&address)?;
+            let statuses = rent::check(&accounts, program_idl.as_ref(), &mut minimum_balance)?;
+            let short: Vec<_> = statuses
+                .into_iter()
+                .filter(|status| status.shortfall() > 0)
+                .collect();
+            println!(
+                "{} ({}): {} accounts, {} not rent-exempt",
+                program.lib_name,
+                address,
+                accounts.len(),
+                short.len()
+            );
+            for status in &short {
+                println!(
+                    "  {} {}: {} -> {} bytes, {} lamports, {} required, {} short",
+                    status.address,
+                    status.account.as_deref().unwrap_or("unknown"),
+                    status.len,
+                    status.planned_len,
+                    status.lamports,
+                    status.required,
+                    status.shortfall()
+                );
+            }
+            underfunded.extend(short);
+        }
+
+        if underfunded.is_empty() {
+            return Ok(());
+        }
+        if !fix {
+            return Err(anyhow!(
+                "{} accounts aren't rent-exempt, run with --fix to fund them",
+                underfunded.len()
+            ));
+        }
+
+        // Fund the accounts from the wallet, a few per transaction.
+        let keypair = cfg.wallet_kp()?;
+        for chunk in underfunded.chunks(10) {
+            let ixs: Vec<Instruction> = chunk
+                .iter()
+                .map(|status| {
+                    solana_sdk::system_instruction::transfer(
+                        &keypair.pubkey(),
+                        &status.address,
+                        status.shortfall(),
+                    )
+                })
+                .collect();
+            let tx = Transaction::new_signed_with_payer(
+                &ixs,
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                client.get_latest_blockhash()?,
+            );
+            client.send_and_confirm_transaction_with_spinner(&tx)?;
+        }
+        println!(
+            "Funded {} accounts with {} lamports",
+            underfunded.len(),
+            underfunded.iter().map(|s| s.shortfall()).sum::<u64>()
+        );
+
+        Ok(())
+    })
+}
+
 // with_workspace ensures the current working directory is always the top level
 // workspace directory, i.e., where the `Anchor.toml` file is located, before
 // and after the closure invocation.
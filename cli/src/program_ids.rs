@@ -0,0 +1,215 @@
+use crate::config::{Config, Manifest, WithPath};
+use anchor_client::Cluster;
+use anyhow::{anyhow, Context, Result};
+use heck::SnakeCase;
+use proc_macro2::{TokenStream, TokenTree};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use walkdir::WalkDir;
+
+// A program id hardcoded somewhere in the source of a workspace crate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdReference {
+    pub file: PathBuf,
+    pub line: usize,
+    pub address: Pubkey,
+    // True if this is the crate's own top level `declare_id!`.
+    pub is_declare_id: bool,
+}
+
+// Edges between workspace programs, keyed by the dependent program's lib name.
+pub type DependencyGraph = BTreeMap<String, BTreeSet<String>>;
+
+#[derive(Debug, Default)]
+pub struct IdCheckReport {
+    pub graph: DependencyGraph,
+    pub mismatches: Vec<String>,
+}
+
+// Scans all workspace programs for hardcoded program ids (`declare_id!`,
+// constants, CPI targets, etc.) and cross-references them against the
+// `[programs.<cluster>]` entries of Anchor.toml for the provider cluster.
+pub fn check(cfg: &WithPath<Config>) -> Result<IdCheckReport> {
+    let cluster = &cfg.provider.cluster;
+    let current = cfg.programs.get(cluster);
+
+    // Every address known to Anchor.toml, across all clusters.
+    let mut known: BTreeMap<Pubkey, Vec<(&Cluster, &String)>> = BTreeMap::new();
+    for (cl, programs) in cfg.programs.iter() {
+        for (name, deployment) in programs.iter() {
+            known
+                .entry(deployment.address)
+                .or_default()
+                .push((cl, name));
+        }
+    }
+
+    let mut report = IdCheckReport::default();
+    let program_paths = cfg.get_program_list()?;
+    let mut crates = Vec::new();
+    for path in program_paths.iter() {
+        let manifest = Manifest::from_path(path.join("Cargo.toml"))?;
+        crates.push((manifest.lib_name()?, manifest));
+    }
+    let lib_names: BTreeSet<String> = crates.iter().map(|(name, _)| name.clone()).collect();
+
+    for (path, (lib_name, manifest)) in program_paths.iter().zip(crates.iter()) {
+        let edges = report.graph.entry(lib_name.clone()).or_default();
+
+        // Cargo dependencies on other workspace programs, e.g. for CPI.
+        for dep in manifest.dependencies.keys() {
+            let dep = dep.to_snake_case();
+            if &dep != lib_name && lib_names.contains(&dep) {
+                edges.insert(dep);
+            }
+        }
+
+        for r in scan_crate(path)? {
+            let location = format!("{}:{}", r.file.display(), r.line);
+            if r.is_declare_id {
+                if let Some(expected) = current.and_then(|c| c.get(lib_name)) {
+                    if expected.address != r.address {
+                        report.mismatches.push(format!(
+                            "{}: declare_id! of {} is {}, but [programs.{}] has {}",
+                            location, lib_name, r.address, cluster, expected.address
+                        ));
+                    }
+                }
+                continue;
+            }
+            for (cl, name) in known.get(&r.address).into_iter().flatten() {
+                if *name != lib_name {
+                    edges.insert(name.to_string());
+                }
+                if let Some(expected) = current.and_then(|c| c.get(*name)) {
+                    if expected.address != r.address {
+                        report.mismatches.push(format!(
+                            "{}: {} references {} at {} from [programs.{}], but [programs.{}] has {}",
+                            location, lib_name, name, r.address, cl, cluster, expected.address
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// Finds all hardcoded program ids in the `src/` directory of a crate.
+pub fn scan_crate(path: &Path) -> Result<Vec<IdReference>> {
+    let src = path.join("src");
+    let root = src.join("lib.rs");
+    let mut refs = Vec::new();
+    for entry in WalkDir::new(&src)
+        .into_iter()
+        .filter_entry(|e| !crate::is_hidden(e))
+    {
+        let entry = entry?;
+        if entry.path().extension().and_then(|e| e.to_str()) != Some("rs") {
+            continue;
+        }
+        let content = fs::read_to_string(entry.path())
+            .with_context(|| format!("Error reading file: {}", entry.path().display()))?;
+        let is_root = entry.path() == root;
+        refs.extend(
+            scan_source(&content)?
+                .into_iter()
+                .map(|(line, address, is_declare_id)| IdReference {
+                    file: entry.path().to_path_buf(),
+                    line,
+                    address,
+                    is_declare_id: is_root && is_declare_id,
+                }),
+        );
+    }
+    Ok(refs)
+}
+
+// Returns the line, address and whether or not the id was declared with a
+// top level `declare_id!` for every pubkey string literal in the source.
+pub fn scan_source(source: &str) -> Result<Vec<(usize, Pubkey, bool)>> {
+    let tokens = TokenStream::from_str(source)
+        .map_err(|e| anyhow!("Unable to tokenize source: {}", e.to_string()))?;
+    let mut refs = Vec::new();
+    scan_tokens(tokens, true, &mut refs);
+    Ok(refs)
+}
+
+fn scan_tokens(tokens: TokenStream, top_level: bool, refs: &mut Vec<(usize, Pubkey, bool)>) {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    for (idx, tt) in tokens.iter().enumerate() {
+        match tt {
+            TokenTree::Group(group) => {
+                // `declare_id ! ( "..." )`
+                let is_declare_id = top_level
+                    && idx >= 2
+                    && matches!(&tokens[idx - 1], TokenTree::Punct(p) if p.as_char() == '!')
+                    && matches!(&tokens[idx - 2], TokenTree::Ident(i) if i == "declare_id");
+                if is_declare_id {
+                    for inner in group.stream() {
+                        if let Some((line, address)) = parse_pubkey_literal(&inner) {
+                            refs.push((line, address, true));
+                        }
+                    }
+                } else {
+                    scan_tokens(group.stream(), false, refs);
+                }
+            }
+            _ => {
+                if let Some((line, address)) = parse_pubkey_literal(tt) {
+                    refs.push((line, address, false));
+                }
+            }
+        }
+    }
+}
+
+fn parse_pubkey_literal(tt: &TokenTree) -> Option<(usize, Pubkey)> {
+    match tt {
+        TokenTree::Literal(lit) => {
+            let s = lit.to_string();
+            let s = s.strip_prefix('"')?.strip_suffix('"')?;
+            Pubkey::from_str(s)
+                .ok()
+                .map(|address| (lit.span().start().line, address))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_declare_id_and_constants() {
+        let source = r#"
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            pub const OTHER: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+            pub mod dex {
+                declare_id!("9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin");
+            }
+
+            const NOT_A_KEY: &str = "hello";
+        "#;
+        let refs = scan_source(source).unwrap();
+        assert_eq!(refs.len(), 3);
+        assert_eq!(
+            refs[0],
+            (
+                2,
+                Pubkey::from_str("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS").unwrap(),
+                true
+            )
+        );
+        assert!(!refs[1].2);
+        // Nested `declare_id!` invocations refer to other programs.
+        assert!(!refs[2].2);
+    }
+}
@@ -0,0 +1,131 @@
+use anchor_syn::idl::Idl;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+// Rent-exemption drift of the accounts of a program.
+//
+// Accounts are rent-exempt when created, for the size they're created with.
+// After a layout change, accounts that are reallocated to the new size of their
+// type must hold the rent-exemption of that size, which accounts created long
+// ago, when rent was different, or funded to the exact minimum, may not. Each
+// account is checked against the rent-exemption of the larger of its current
+// size and the size of its type in the IDL, i.e. the size it will be
+// reallocated to by the new layout.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RentStatus {
+    pub address: Pubkey,
+    // Name of the account type, matched by discriminator, if any.
+    pub account: Option<String>,
+    pub lamports: u64,
+    pub len: usize,
+    // Size of the account once reallocated to the size of its type.
+    pub planned_len: usize,
+    // Rent-exemption of the planned size.
+    pub required: u64,
+}
+
+impl RentStatus {
+    // Lamports missing for the account to be rent-exempt at its planned size.
+    pub fn shortfall(&self) -> u64 {
+        self.required.saturating_sub(self.lamports)
+    }
+}
+
+// The rent status of the accounts of a program described by `idl`, given the
+// rent-exemption of a size, sorted by shortfall.
+pub fn check(
+    accounts: &[(Pubkey, Account)],
+    idl: Option<&Idl>,
+    mut minimum_balance: impl FnMut(usize) -> anyhow::Result<u64>,
+) -> anyhow::Result<Vec<RentStatus>> {
+    let types = idl
+        .map(|idl| {
+            idl.accounts
+                .iter()
+                .filter(|acc| !acc.account_discriminator().is_empty())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let mut statuses = accounts
+        .iter()
+        .map(|(address, account)| {
+            let ty = types
+                .iter()
+                .find(|ty| account.data.starts_with(&ty.account_discriminator()));
+            let len = account.data.len();
+            let planned_len = ty
+                .and_then(|ty| ty.size)
+                .map_or(len, |size| len.max(size as usize));
+            Ok(RentStatus {
+                address: *address,
+                account: ty.map(|ty| ty.name.clone()),
+                lamports: account.lamports,
+                len,
+                planned_len,
+                required: minimum_balance(planned_len)?,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    statuses.sort_by(|a, b| b.shortfall().cmp(&a.shortfall()));
+    Ok(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use solana_sdk::rent::Rent;
+
+    #[test]
+    fn rent_drift() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "instructions": [],
+            "accounts": [
+                { "name": "Vault", "type": { "kind": "struct", "fields": [] }, "size": 72 },
+                { "name": "Config", "type": { "kind": "struct", "fields": [] } }
+            ]
+        }))
+        .unwrap();
+        let rent = Rent::default();
+        let account = |name: &str, len: usize, lamports: u64| {
+            let mut data = anchor_syn::codegen::program::common::sighash("account", name).to_vec();
+            data.resize(len, 0);
+            (
+                Pubkey::new_unique(),
+                Account {
+                    lamports,
+                    data,
+                    owner: Pubkey::new_unique(),
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )
+        };
+        let accounts = vec![
+            account("Vault", 40, rent.minimum_balance(40)),
+            account("Config", 16, rent.minimum_balance(16)),
+            account("Other", 16, rent.minimum_balance(16) - 1),
+        ];
+
+        let statuses = check(&accounts, Some(&idl), |len| Ok(rent.minimum_balance(len))).unwrap();
+        let summary: Vec<_> = statuses
+            .iter()
+            .map(|s| (s.account.as_deref(), s.planned_len, s.shortfall()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    Some("Vault"),
+                    72,
+                    rent.minimum_balance(72) - rent.minimum_balance(40)
+                ),
+                (None, 16, 1),
+                (Some("Config"), 16, 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,435 @@
+use crate::anonymize::{AccountFixture, FixtureAccount};
+use anchor_syn::idl::{EnumFields, Idl, IdlField, IdlType, IdlTypeDefinition, IdlTypeDefinitionTy};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+use solana_sdk::account::Account;
+use solana_sdk::hash::hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::TransactionError;
+use std::str::FromStr;
+
+// The state of an account before or after a transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountSnapshot {
+    pub lamports: u64,
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+}
+
+impl AccountSnapshot {
+    pub fn from_fixture(fixture: &AccountFixture) -> Result<Self> {
+        let (data, encoding) = &fixture.account.data;
+        if encoding != "base64" {
+            return Err(anyhow!(
+                "{}: unsupported data encoding {}, expected base64",
+                fixture.pubkey,
+                encoding
+            ));
+        }
+        Ok(Self {
+            lamports: fixture.account.lamports,
+            owner: Pubkey::from_str(&fixture.account.owner)?,
+            data: base64::decode(data)?,
+        })
+    }
+}
+
+impl From<&Account> for AccountSnapshot {
+    fn from(account: &Account) -> Self {
+        Self {
+            lamports: account.lamports,
+            owner: account.owner,
+            data: account.data.clone(),
+        }
+    }
+}
+
+// The account in the format loaded by the test validator.
+pub fn fixture(address: &Pubkey, account: &Account) -> AccountFixture {
+    AccountFixture {
+        pubkey: address.to_string(),
+        account: FixtureAccount {
+            lamports: account.lamports,
+            data: (base64::encode(&account.data), "base64".to_string()),
+            owner: account.owner.to_string(),
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            other: Default::default(),
+        },
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayReport {
+    pub signature: String,
+    pub slot: u64,
+    pub original_error: Option<TransactionError>,
+    pub replay_error: Option<TransactionError>,
+    pub logs: Vec<String>,
+    pub accounts: Vec<AccountReport>,
+}
+
+// A writable account of a replayed transaction.
+#[derive(Debug, Clone)]
+pub struct AccountReplay {
+    pub address: Pubkey,
+    // State the transaction was replayed against.
+    pub pre: Option<AccountSnapshot>,
+    // State after the replay.
+    pub post: Option<AccountSnapshot>,
+    // State after the original transaction, if known.
+    pub expected: Option<AccountSnapshot>,
+    // Balance after the original transaction, from the transaction status.
+    pub expected_lamports: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    // Path to the changed value, e.g. `data.positions[2].amount`.
+    pub path: String,
+    pub pre: JsonValue,
+    pub post: JsonValue,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<JsonValue>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountReport {
+    pub address: String,
+    pub pre: JsonValue,
+    pub post: JsonValue,
+    pub expected: JsonValue,
+    pub changes: Vec<FieldChange>,
+    // Fields where the replay diverged from the original transaction.
+    pub mismatches: Vec<FieldChange>,
+}
+
+impl AccountReplay {
+    // Typed views of the pre, post and expected states, decoded with the
+    // given IDLs, along with their differences.
+    pub fn report(&self, idls: &[Idl]) -> AccountReport {
+        let pre = view(idls, self.pre.as_ref());
+        let post = view(idls, self.post.as_ref());
+        let mut expected = view(idls, self.expected.as_ref());
+        if let (Some(lamports), JsonValue::Null) = (self.expected_lamports, &expected) {
+            expected = json!({ "lamports": lamports });
+        }
+
+        let changes = diff(&pre, &post)
+            .into_iter()
+            .map(|(path, pre, post)| {
+                let expected = lookup(&expected, &path).cloned();
+                FieldChange {
+                    path,
+                    pre,
+                    post,
+                    expected,
+                }
+            })
+            .collect();
+        // Only compare what is known about the expected state.
+        let mismatches = diff(&expected, &post)
+            .into_iter()
+            .filter(|(_, expected, _)| !expected.is_null())
+            .map(|(path, expected, post)| FieldChange {
+                pre: lookup(&pre, &path).cloned().unwrap_or(JsonValue::Null),
+                path,
+                post,
+                expected: Some(expected),
+            })
+            .collect();
+
+        AccountReport {
+            address: self.address.to_string(),
+            pre,
+            post,
+            expected,
+            changes,
+            mismatches,
+        }
+    }
+}
+
+fn view(idls: &[Idl], snapshot: Option<&AccountSnapshot>) -> JsonValue {
+    let snapshot = match snapshot {
+        Some(snapshot) => snapshot,
+        None => return JsonValue::Null,
+    };
+    let mut view = json!({
+        "lamports": snapshot.lamports,
+        "owner": snapshot.owner.to_string(),
+    });
+    match decode_account(idls, &snapshot.data) {
+        Some((name, data)) => {
+            view["type"] = JsonValue::String(name);
+            view["data"] = data;
+        }
+        None => view["data"] = JsonValue::String(base64::encode(&snapshot.data)),
+    }
+    view
+}
+
+// Decodes account data with the IDL account matching its discriminator,
+// returning the account's name and fields.
+pub fn decode_account(idls: &[Idl], data: &[u8]) -> Option<(String, JsonValue)> {
+    let discriminator = data.get(..8)?;
+    idls.iter().find_map(|idl| {
+        let account = idl.accounts.iter().find(|acc| {
+            hash(format!("account:{}", acc.name).as_bytes()).to_bytes()[..8] == *discriminator
+        })?;
+        let mut decoder = Decoder {
+            idl,
+            data,
+            offset: 8,
+        };
+        let value = decoder.type_definition(account)?;
+        Some((account.name.clone(), value))
+    })
+}
+
+// Decodes borsh encoded data along the IDL types. 128 bit integers are
+// decoded as strings, like everywhere else the IDL embeds values.
+struct Decoder<'a> {
+    idl: &'a Idl,
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn type_definition(&mut self, ty_def: &IdlTypeDefinition) -> Option<JsonValue> {
+        match &ty_def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => self.fields(fields),
+            IdlTypeDefinitionTy::Enum { variants } => {
+                let variant = variants.get(self.take(1)?[0] as usize)?;
+                let fields = match &variant.fields {
+                    None => json!({}),
+                    Some(EnumFields::Named(fields)) => self.fields(fields)?,
+                    Some(EnumFields::Tuple(tys)) => JsonValue::Array(
+                        tys.iter()
+                            .map(|ty| self.ty(ty))
+                            .collect::<Option<Vec<_>>>()?,
+                    ),
+                };
+                Some(json!({ variant.name.clone(): fields }))
+            }
+        }
+    }
+
+    fn fields(&mut self, fields: &[IdlField]) -> Option<JsonValue> {
+        let mut values = Map::new();
+        for f in fields {
+            values.insert(f.name.clone(), self.ty(&f.ty)?);
+        }
+        Some(JsonValue::Object(values))
+    }
+
+    fn ty(&mut self, ty: &IdlType) -> Option<JsonValue> {
+        let value = match ty {
+            IdlType::Bool => json!(self.take(1)?[0] != 0),
+            IdlType::U8 => json!(self.take(1)?[0]),
+            IdlType::I8 => json!(self.take(1)?[0] as i8),
+            IdlType::U16 => json!(u16::from_le_bytes(self.array()?)),
+            IdlType::I16 => json!(i16::from_le_bytes(self.array()?)),
+            IdlType::U32 => json!(u32::from_le_bytes(self.array()?)),
+            IdlType::I32 => json!(i32::from_le_bytes(self.array()?)),
+            IdlType::U64 => json!(u64::from_le_bytes(self.array()?)),
+            IdlType::I64 => json!(i64::from_le_bytes(self.array()?)),
+            IdlType::U128 => json!(u128::from_le_bytes(self.array()?).to_string()),
+            IdlType::I128 => json!(i128::from_le_bytes(self.array()?).to_string()),
+            IdlType::F32 => json!(f32::from_le_bytes(self.array()?)),
+            IdlType::F64 => json!(f64::from_le_bytes(self.array()?)),
+            IdlType::Float(float) => match float.bits {
+                32 => json!(f32::from_le_bytes(self.array()?)),
+                _ => json!(f64::from_le_bytes(self.array()?)),
+            },
+            IdlType::Bytes => {
+                let len = self.len()?;
+                json!(base64::encode(self.take(len)?))
+            }
+            IdlType::String => {
+                let len = self.len()?;
+                json!(std::str::from_utf8(self.take(len)?).ok()?)
+            }
+            IdlType::PublicKey => json!(Pubkey::new_from_array(self.array()?).to_string()),
+            IdlType::Defined(name) => {
+                let idl = self.idl;
+                let ty_def = idl
+                    .types
+                    .iter()
+                    .chain(idl.accounts.iter())
+                    .find(|ty_def| &ty_def.name == name)?;
+                self.type_definition(ty_def)?
+            }
+            IdlType::Option(ty) => match self.take(1)?[0] {
+                0 => JsonValue::Null,
+                1 => self.ty(ty)?,
+                _ => return None,
+            },
+            IdlType::Vec(ty) => {
+                let len = self.len()?;
+                JsonValue::Array((0..len).map(|_| self.ty(ty)).collect::<Option<_>>()?)
+            }
+            IdlType::Array(ty, len) => {
+                JsonValue::Array((0..*len).map(|_| self.ty(ty)).collect::<Option<_>>()?)
+            }
+        };
+        Some(value)
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        Some(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.offset.checked_add(len)?;
+        let bytes = self.data.get(self.offset..end)?;
+        self.offset = end;
+        Some(bytes)
+    }
+}
+
+// Leaf values that differ between the two views, by path.
+fn diff(a: &JsonValue, b: &JsonValue) -> Vec<(String, JsonValue, JsonValue)> {
+    let mut changes = vec![];
+    diff_at(String::new(), a, b, &mut changes);
+    changes
+}
+
+fn diff_at(
+    path: String,
+    a: &JsonValue,
+    b: &JsonValue,
+    changes: &mut Vec<(String, JsonValue, JsonValue)>,
+) {
+    match (a, b) {
+        (JsonValue::Object(a), JsonValue::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = match path.is_empty() {
+                    true => key.clone(),
+                    false => format!("{}.{}", path, key),
+                };
+                let a = a.get(key).unwrap_or(&JsonValue::Null);
+                let b = b.get(key).unwrap_or(&JsonValue::Null);
+                diff_at(path, a, b, changes);
+            }
+        }
+        (JsonValue::Array(a), JsonValue::Array(b)) if a.len() == b.len() => {
+            for (idx, (a, b)) in a.iter().zip(b).enumerate() {
+                diff_at(format!("{}[{}]", path, idx), a, b, changes);
+            }
+        }
+        (a, b) if a != b => changes.push((path, a.clone(), b.clone())),
+        _ => {}
+    }
+}
+
+// The value at a path produced by `diff`.
+fn lookup<'a>(value: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    path.split('.').try_fold(value, |value, segment| {
+        let mut parts = segment.split('[');
+        let mut value = value.get(parts.next()?)?;
+        for idx in parts {
+            value = value.get(idx.strip_suffix(']')?.parse::<usize>().ok()?)?;
+        }
+        Some(value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idl() -> Idl {
+        serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "counter",
+            "instructions": [],
+            "accounts": [{
+                "name": "Counter",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "authority", "type": "publicKey" },
+                        { "name": "counts", "type": { "vec": "u64" } },
+                        { "name": "total", "type": "u128" }
+                    ]
+                }
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn counter(authority: &Pubkey, counts: &[u64], total: u128) -> Vec<u8> {
+        let mut data = hash(b"account:Counter").to_bytes()[..8].to_vec();
+        data.extend_from_slice(authority.as_ref());
+        data.extend_from_slice(&(counts.len() as u32).to_le_bytes());
+        for count in counts {
+            data.extend_from_slice(&count.to_le_bytes());
+        }
+        data.extend_from_slice(&total.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn decodes_accounts() {
+        let authority = Pubkey::new_unique();
+        let (name, value) =
+            decode_account(&[idl()], &counter(&authority, &[1, 2], u128::MAX)).unwrap();
+        assert_eq!(name, "Counter");
+        assert_eq!(
+            value,
+            json!({
+                "authority": authority.to_string(),
+                "counts": [1, 2],
+                "total": u128::MAX.to_string(),
+            })
+        );
+        assert_eq!(decode_account(&[idl()], &[0; 8]), None);
+    }
+
+    #[test]
+    fn reports_changes_and_mismatches() {
+        let authority = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let snapshot = |lamports, counts: &[u64], total| AccountSnapshot {
+            lamports,
+            owner,
+            data: counter(&authority, counts, total),
+        };
+        let replay = AccountReplay {
+            address: Pubkey::new_unique(),
+            pre: Some(snapshot(10, &[1, 2], 3)),
+            post: Some(snapshot(10, &[1, 3], 4)),
+            expected: Some(snapshot(10, &[1, 3], 5)),
+            expected_lamports: Some(10),
+        };
+        let report = replay.report(&[idl()]);
+
+        let changes: Vec<(&str, &JsonValue, &JsonValue)> = report
+            .changes
+            .iter()
+            .map(|c| (c.path.as_str(), &c.pre, &c.post))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("data.counts[1]", &json!(2), &json!(3)),
+                ("data.total", &json!("3"), &json!("4")),
+            ]
+        );
+        assert_eq!(report.changes[1].expected, Some(json!("5")));
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].path, "data.total");
+    }
+}
@@ -2,7 +2,7 @@ use crate::config::ProgramWorkspace;
 use crate::VERSION;
 use anchor_syn::idl::Idl;
 use anyhow::Result;
-use heck::{CamelCase, MixedCase, SnakeCase};
+use heck::{CamelCase, SnakeCase};
 use solana_sdk::pubkey::Pubkey;
 
 pub fn default_program_id() -> Pubkey {
@@ -38,21 +38,7 @@ token = "{}"
 }
 
 pub fn idl_ts(idl: &Idl) -> Result<String> {
-    let mut idl = idl.clone();
-    for acc in idl.accounts.iter_mut() {
-        acc.name = acc.name.to_mixed_case();
-    }
-    let idl_json = serde_json::to_string_pretty(&idl)?;
-    Ok(format!(
-        r#"export type {} = {};
-
-export const IDL: {} = {};
-"#,
-        idl.name.to_camel_case(),
-        idl_json,
-        idl.name.to_camel_case(),
-        idl_json
-    ))
+    anchor_syn::idl::codegen::typescript::idl(idl)
 }
 
 pub fn cargo_toml(name: &str) -> String {
@@ -0,0 +1,376 @@
+//! Execution backends for tests.
+//!
+//! The same typed tests can run against a [`Backend`] executing transactions
+//! in process, which is fast and needs no validator, or against a test
+//! validator, which is closer to a real cluster:
+//!
+//! - [`RpcBackend`]: a (remote) test validator, over RPC.
+//! - `ProgramTestBackend`: `solana-program-test`, with the `program-test`
+//!   feature.
+//!
+//! [`TestBackend::from_env`] selects the backend configured by `backend` in
+//! the `[test]` section of `Anchor.toml`, which `anchor test` passes to the
+//! tests along with the programs of the workspace.
+//!
+//! ```ignore
+//! let mut backend = TestBackend::from_env(payer.pubkey())?;
+//! backend.airdrop(&authority.pubkey(), LAMPORTS_PER_SOL)?;
+//! backend.send(&[initialize_ix], &payer, &[&authority])?;
+//! let vault: Vault = backend.account(&vault_address)?;
+//! ```
+
+use anchor_lang::solana_program::hash::Hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::AccountDeserialize;
+use solana_client::client_error::ClientError as SolanaClientError;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Environment variable of the backend to run the tests with, one of
+/// `validator` (the default) and `program-test`.
+pub const BACKEND_ENV: &str = "ANCHOR_TEST_BACKEND";
+
+/// Environment variable of the programs loaded by in process backends, as
+/// comma separated `<program id>=<path to the .so>` pairs.
+pub const PROGRAMS_ENV: &str = "ANCHOR_TEST_PROGRAMS";
+
+/// Environment variable of the URL of the test validator.
+pub const PROVIDER_URL_ENV: &str = "ANCHOR_PROVIDER_URL";
+
+#[derive(Debug, Error)]
+pub enum BackendError {
+    #[error("{0}")]
+    SolanaClientError(#[from] SolanaClientError),
+    #[error("Transaction failed: {0}")]
+    TransactionError(String),
+    #[error("Account not found: {0}")]
+    AccountNotFound(Pubkey),
+    #[error("{0}")]
+    AnchorError(#[from] anchor_lang::error::Error),
+    #[error("Unable to load program {0}: {1}")]
+    ProgramLoadError(String, std::io::Error),
+    #[error("Invalid test configuration: {0}")]
+    ConfigError(String),
+}
+
+/// Executes transactions and reads accounts, for tests.
+pub trait Backend {
+    fn latest_blockhash(&mut self) -> Result<Hash, BackendError>;
+
+    /// Executes a signed transaction, returning its signature once processed.
+    fn process_transaction(&mut self, tx: Transaction) -> Result<Signature, BackendError>;
+
+    fn get_account(&mut self, address: &Pubkey) -> Result<Option<Account>, BackendError>;
+
+    /// Credits `lamports` to `address`.
+    fn airdrop(&mut self, address: &Pubkey, lamports: u64) -> Result<(), BackendError>;
+
+    /// Signs the instructions with the payer and signers, and executes them
+    /// in a single transaction.
+    fn send(
+        &mut self,
+        instructions: &[Instruction],
+        payer: &dyn Signer,
+        signers: &[&dyn Signer],
+    ) -> Result<Signature, BackendError> {
+        let mut all_signers = vec![payer];
+        all_signers.extend_from_slice(signers);
+        let tx = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &all_signers,
+            self.latest_blockhash()?,
+        );
+        self.process_transaction(tx)
+    }
+
+    /// Returns the deserialized account at `address`.
+    fn account<T: AccountDeserialize>(&mut self, address: &Pubkey) -> Result<T, BackendError>
+    where
+        Self: Sized,
+    {
+        let account = self
+            .get_account(address)?
+            .ok_or(BackendError::AccountNotFound(*address))?;
+        T::try_deserialize(&mut &account.data[..]).map_err(Into::into)
+    }
+}
+
+/// The kind of backend tests run with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    Validator,
+    ProgramTest,
+}
+
+impl FromStr for BackendKind {
+    type Err = BackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "validator" => Ok(BackendKind::Validator),
+            "program-test" => Ok(BackendKind::ProgramTest),
+            _ => Err(BackendError::ConfigError(format!(
+                "Unknown backend {}, expected validator or program-test",
+                s
+            ))),
+        }
+    }
+}
+
+/// A program loaded by in process backends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestProgram {
+    pub program_id: Pubkey,
+    pub path: String,
+}
+
+impl TestProgram {
+    /// Parses the programs of `ANCHOR_TEST_PROGRAMS`.
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, BackendError> {
+        s.split(',')
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (program_id, path) = entry.split_once('=').ok_or_else(|| {
+                    BackendError::ConfigError(format!("Invalid program entry: {}", entry))
+                })?;
+                let program_id = program_id.parse().map_err(|_| {
+                    BackendError::ConfigError(format!("Invalid program id: {}", program_id))
+                })?;
+                Ok(TestProgram {
+                    program_id,
+                    path: path.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn read(&self) -> Result<Vec<u8>, BackendError> {
+        std::fs::read(&self.path).map_err(|e| BackendError::ProgramLoadError(self.path.clone(), e))
+    }
+}
+
+/// The backend selected by the environment of the tests.
+pub enum TestBackend {
+    Validator(RpcBackend),
+    #[cfg(feature = "program-test")]
+    ProgramTest(program_test::ProgramTestBackend),
+}
+
+impl TestBackend {
+    /// The backend of `ANCHOR_TEST_BACKEND`, loading the programs of
+    /// `ANCHOR_TEST_PROGRAMS` if it runs in process. In process backends
+    /// fund `payer`.
+    pub fn from_env(payer: Pubkey) -> Result<Self, BackendError> {
+        let kind = match std::env::var(BACKEND_ENV) {
+            Ok(kind) => kind.parse()?,
+            Err(_) => BackendKind::Validator,
+        };
+        let programs = match std::env::var(PROGRAMS_ENV) {
+            Ok(programs) => TestProgram::parse_list(&programs)?,
+            Err(_) => vec![],
+        };
+        Self::new(kind, &programs, payer)
+    }
+
+    pub fn new(
+        kind: BackendKind,
+        programs: &[TestProgram],
+        payer: Pubkey,
+    ) -> Result<Self, BackendError> {
+        match kind {
+            BackendKind::Validator => {
+                let url = std::env::var(PROVIDER_URL_ENV)
+                    .unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+                Ok(TestBackend::Validator(RpcBackend::new(url)))
+            }
+            #[cfg(feature = "program-test")]
+            BackendKind::ProgramTest => Ok(TestBackend::ProgramTest(
+                program_test::ProgramTestBackend::new(programs, payer)?,
+            )),
+            #[allow(unreachable_patterns)]
+            kind => {
+                let _ = (programs, payer);
+                Err(BackendError::ConfigError(format!(
+                    "The {:?} backend requires the program-test feature of anchor-client",
+                    kind
+                )))
+            }
+        }
+    }
+
+    fn inner(&mut self) -> &mut dyn Backend {
+        match self {
+            TestBackend::Validator(backend) => backend,
+            #[cfg(feature = "program-test")]
+            TestBackend::ProgramTest(backend) => backend,
+        }
+    }
+}
+
+impl Backend for TestBackend {
+    fn latest_blockhash(&mut self) -> Result<Hash, BackendError> {
+        self.inner().latest_blockhash()
+    }
+
+    fn process_transaction(&mut self, tx: Transaction) -> Result<Signature, BackendError> {
+        self.inner().process_transaction(tx)
+    }
+
+    fn get_account(&mut self, address: &Pubkey) -> Result<Option<Account>, BackendError> {
+        self.inner().get_account(address)
+    }
+
+    fn airdrop(&mut self, address: &Pubkey, lamports: u64) -> Result<(), BackendError> {
+        self.inner().airdrop(address, lamports)
+    }
+}
+
+/// A test validator, local or remote, whose programs are already deployed.
+pub struct RpcBackend {
+    rpc_client: RpcClient,
+}
+
+impl RpcBackend {
+    pub fn new(url: impl ToString) -> Self {
+        Self {
+            rpc_client: RpcClient::new_with_commitment(
+                url.to_string(),
+                CommitmentConfig::confirmed(),
+            ),
+        }
+    }
+}
+
+impl Backend for RpcBackend {
+    fn latest_blockhash(&mut self) -> Result<Hash, BackendError> {
+        self.rpc_client.get_latest_blockhash().map_err(Into::into)
+    }
+
+    fn process_transaction(&mut self, tx: Transaction) -> Result<Signature, BackendError> {
+        self.rpc_client
+            .send_and_confirm_transaction(&tx)
+            .map_err(Into::into)
+    }
+
+    fn get_account(&mut self, address: &Pubkey) -> Result<Option<Account>, BackendError> {
+        Ok(self
+            .rpc_client
+            .get_account_with_commitment(address, self.rpc_client.commitment())?
+            .value)
+    }
+
+    fn airdrop(&mut self, address: &Pubkey, lamports: u64) -> Result<(), BackendError> {
+        let signature = self.rpc_client.request_airdrop(address, lamports)?;
+        let blockhash = self.rpc_client.get_latest_blockhash()?;
+        self.rpc_client
+            .confirm_transaction_with_spinner(&signature, &blockhash, self.rpc_client.commitment())
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(feature = "program-test")]
+mod program_test {
+    use super::*;
+    use anchor_lang::solana_program::{bpf_loader, system_instruction};
+    use solana_program_test::{ProgramTest, ProgramTestContext};
+    use solana_sdk::signature::Keypair;
+    use tokio::runtime::Runtime;
+
+    // Lamports the payer is funded with.
+    const PAYER_LAMPORTS: u64 = 1_000_000_000_000;
+
+    /// `solana-program-test`, with a bank in process.
+    pub struct ProgramTestBackend {
+        runtime: Runtime,
+        context: ProgramTestContext,
+    }
+
+    impl ProgramTestBackend {
+        pub fn new(programs: &[TestProgram], payer: Pubkey) -> Result<Self, BackendError> {
+            let mut program_test = ProgramTest::default();
+            for program in programs {
+                program_test.add_account(
+                    program.program_id,
+                    Account {
+                        lamports: 1,
+                        data: program.read()?,
+                        owner: bpf_loader::id(),
+                        executable: true,
+                        rent_epoch: 0,
+                    },
+                );
+            }
+            program_test.add_account(
+                payer,
+                Account::new(PAYER_LAMPORTS, 0, &anchor_lang::system_program::ID),
+            );
+            let runtime = Runtime::new().map_err(|e| {
+                BackendError::ConfigError(format!("Unable to start a runtime: {}", e))
+            })?;
+            let context = runtime.block_on(program_test.start_with_context());
+            Ok(Self { runtime, context })
+        }
+    }
+
+    impl Backend for ProgramTestBackend {
+        fn latest_blockhash(&mut self) -> Result<Hash, BackendError> {
+            let banks_client = &mut self.context.banks_client;
+            self.runtime
+                .block_on(banks_client.get_latest_blockhash())
+                .map_err(|e| BackendError::TransactionError(e.to_string()))
+        }
+
+        fn process_transaction(&mut self, tx: Transaction) -> Result<Signature, BackendError> {
+            let signature = tx.signatures[0];
+            let banks_client = &mut self.context.banks_client;
+            self.runtime
+                .block_on(banks_client.process_transaction(tx))
+                .map_err(|e| BackendError::TransactionError(e.to_string()))?;
+            Ok(signature)
+        }
+
+        fn get_account(&mut self, address: &Pubkey) -> Result<Option<Account>, BackendError> {
+            let banks_client = &mut self.context.banks_client;
+            self.runtime
+                .block_on(banks_client.get_account(*address))
+                .map_err(|e| BackendError::TransactionError(e.to_string()))
+        }
+
+        fn airdrop(&mut self, address: &Pubkey, lamports: u64) -> Result<(), BackendError> {
+            // The payer funding the genesis of the bank.
+            let payer = Keypair::from_bytes(&self.context.payer.to_bytes()).unwrap();
+            let ix = system_instruction::transfer(&payer.pubkey(), address, lamports);
+            self.send(&[ix], &payer, &[]).map(|_| ())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_programs() {
+        let program_id = Pubkey::new_unique();
+        assert_eq!(
+            TestProgram::parse_list(&format!("{}=target/deploy/vault.so,", program_id)).unwrap(),
+            vec![TestProgram {
+                program_id,
+                path: "target/deploy/vault.so".to_string(),
+            }]
+        );
+        assert!(TestProgram::parse_list("vault.so").is_err());
+        assert_eq!(
+            "program-test".parse::<BackendKind>().unwrap(),
+            BackendKind::ProgramTest
+        );
+        assert!("bankrun".parse::<BackendKind>().is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! Decoding of the instructions of a program in transactions.
+//!
+//! Instructions are found both at the top level of a transaction and in its
+//! inner instructions, i.e. when the program is invoked by another program
+//! through CPI, e.g. by a router or an aggregator.
+//!
+//! ```ignore
+//! use my_program::instruction::ProgramInstruction;
+//!
+//! for decoded in program.decode_transaction::<ProgramInstruction>(&signature)? {
+//!     println!("{:?} via {}", decoded.instruction, decoded.invoked_via);
+//! }
+//! ```
+
+use anchor_lang::solana_program::instruction::{AccountMeta, CompiledInstruction};
+use anchor_lang::solana_program::message::Message;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::AnchorDeserialize;
+use solana_sdk::bs58;
+use solana_sdk::message::VersionedMessage;
+use solana_transaction_status::{
+    EncodedTransactionWithStatusMeta, InnerInstructions, UiInstruction,
+};
+use thiserror::Error;
+
+/// An instruction of the program in a transaction.
+#[derive(Debug, Clone)]
+pub struct DecodedInstruction<T> {
+    pub instruction: T,
+    /// The accounts of the instruction. Their privileges are the ones they
+    /// have in the transaction, which may be higher than the ones they were
+    /// given in a CPI.
+    pub accounts: Vec<AccountMeta>,
+    /// Index of the top level instruction it is or is part of.
+    pub index: usize,
+    /// Index in the inner instructions of the top level instruction, if it
+    /// was invoked through CPI.
+    pub inner_index: Option<usize>,
+    /// Program of the top level instruction, e.g. the router which invoked
+    /// the program.
+    pub invoked_via: Pubkey,
+}
+
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    #[error("The transaction couldn't be decoded")]
+    InvalidTransaction,
+    #[error("Unsupported transaction version")]
+    UnsupportedVersion,
+    #[error("The transaction has no status meta, so its inner instructions are unknown")]
+    MissingMeta,
+    #[error("Inner instruction {0} isn't compiled")]
+    NotCompiled(usize),
+    #[error("Invalid instruction data: {0}")]
+    InvalidData(#[from] bs58::decode::Error),
+}
+
+/// Decodes the instructions of the program in a transaction, in execution
+/// order. Instructions that don't decode as `T` are skipped, e.g. the IDL
+/// instructions.
+pub fn decode_instructions<T: AnchorDeserialize>(
+    program_id: &Pubkey,
+    message: &Message,
+    inner_instructions: &[InnerInstructions],
+) -> Vec<DecodedInstruction<T>> {
+    let mut decoded = Vec::new();
+    for (index, top_level) in message.instructions.iter().enumerate() {
+        let invoked_via = message.account_keys[top_level.program_id_index as usize];
+        let inner = inner_instructions
+            .iter()
+            .filter(|inner| inner.index as usize == index)
+            .flat_map(|inner| inner.instructions.iter().enumerate())
+            .map(|(inner_index, ix)| (Some(inner_index), ix));
+        for (inner_index, ix) in std::iter::once((None, top_level)).chain(inner) {
+            if let Some(instruction) = decode_instruction(program_id, message, ix) {
+                decoded.push(DecodedInstruction {
+                    instruction,
+                    accounts: account_metas(message, ix),
+                    index,
+                    inner_index,
+                    invoked_via,
+                });
+            }
+        }
+    }
+    decoded
+}
+
+/// Decodes the instructions of the program in a transaction fetched with
+/// a binary encoding, e.g. base64.
+pub fn decode_transaction<T: AnchorDeserialize>(
+    program_id: &Pubkey,
+    tx: &EncodedTransactionWithStatusMeta,
+) -> Result<Vec<DecodedInstruction<T>>, DecodeError> {
+    let message = match tx
+        .transaction
+        .decode()
+        .ok_or(DecodeError::InvalidTransaction)?
+        .message
+    {
+        VersionedMessage::Legacy(message) => message,
+        _ => return Err(DecodeError::UnsupportedVersion),
+    };
+    let meta = tx.meta.as_ref().ok_or(DecodeError::MissingMeta)?;
+    let inner_instructions = meta
+        .inner_instructions
+        .iter()
+        .flatten()
+        .map(|inner| {
+            let instructions = inner
+                .instructions
+                .iter()
+                .enumerate()
+                .map(|(inner_index, ix)| match ix {
+                    UiInstruction::Compiled(ix) => Ok(CompiledInstruction {
+                        program_id_index: ix.program_id_index,
+                        accounts: ix.accounts.clone(),
+                        data: bs58::decode(&ix.data).into_vec()?,
+                    }),
+                    UiInstruction::Parsed(_) => Err(DecodeError::NotCompiled(inner_index)),
+                })
+                .collect::<Result<_, DecodeError>>()?;
+            Ok(InnerInstructions {
+                index: inner.index,
+                instructions,
+            })
+        })
+        .collect::<Result<Vec<_>, DecodeError>>()?;
+    Ok(decode_instructions(
+        program_id,
+        &message,
+        &inner_instructions,
+    ))
+}
+
+fn decode_instruction<T: AnchorDeserialize>(
+    program_id: &Pubkey,
+    message: &Message,
+    ix: &CompiledInstruction,
+) -> Option<T> {
+    if message.account_keys.get(ix.program_id_index as usize)? != program_id {
+        return None;
+    }
+    T::deserialize(&mut ix.data.as_slice()).ok()
+}
+
+fn account_metas(message: &Message, ix: &CompiledInstruction) -> Vec<AccountMeta> {
+    ix.accounts
+        .iter()
+        .map(|&index| {
+            let index = index as usize;
+            AccountMeta {
+                pubkey: message.account_keys[index],
+                is_signer: message.is_signer(index),
+                is_writable: message.is_writable(index),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::instruction::Instruction;
+
+    // An instruction of the program, with a discriminator of 1.
+    #[derive(Debug, PartialEq)]
+    struct Swap {
+        amount: u8,
+    }
+
+    impl AnchorDeserialize for Swap {
+        fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+            match **buf {
+                [1, amount, ..] => Ok(Swap { amount }),
+                _ => Err(std::io::ErrorKind::InvalidData.into()),
+            }
+        }
+    }
+
+    #[test]
+    fn decode_inner_instructions() {
+        let program_id = Pubkey::new_unique();
+        let router = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        let message = Message::new(
+            &[
+                Instruction::new_with_bytes(
+                    program_id,
+                    &[1, 10],
+                    vec![AccountMeta::new(pool, false)],
+                ),
+                Instruction::new_with_bytes(
+                    router,
+                    &[0],
+                    vec![
+                        AccountMeta::new_readonly(program_id, false),
+                        AccountMeta::new(pool, false),
+                    ],
+                ),
+            ],
+            Some(&payer),
+        );
+        let index_of =
+            |key: &Pubkey| message.account_keys.iter().position(|k| k == key).unwrap() as u8;
+        let cpi = |data: Vec<u8>| CompiledInstruction {
+            program_id_index: index_of(&program_id),
+            accounts: vec![index_of(&pool)],
+            data,
+        };
+        // The router invokes the program twice, once with an instruction
+        // which doesn't decode.
+        let inner_instructions = vec![InnerInstructions {
+            index: 1,
+            instructions: vec![cpi(vec![1, 20]), cpi(vec![2]), cpi(vec![1, 30])],
+        }];
+
+        let decoded = decode_instructions::<Swap>(&program_id, &message, &inner_instructions);
+        let summary: Vec<(u8, usize, Option<usize>, Pubkey)> = decoded
+            .iter()
+            .map(|d| (d.instruction.amount, d.index, d.inner_index, d.invoked_via))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (10, 0, None, program_id),
+                (20, 1, Some(0), router),
+                (30, 1, Some(2), router),
+            ]
+        );
+        assert_eq!(decoded[1].accounts, vec![AccountMeta::new(pool, false)]);
+    }
+}
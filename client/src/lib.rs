@@ -5,7 +5,13 @@ use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
 use anchor_lang::solana_program::program_error::ProgramError;
 use anchor_lang::solana_program::pubkey::Pubkey;
 use anchor_lang::solana_program::system_program;
-use anchor_lang::{AccountDeserialize, Discriminator, InstructionData, ToAccountMetas};
+use anchor_lang::{
+    AccountDeserialize, AnchorDeserialize, Discriminator, InstructionData, ToAccountMetas,
+};
+use backend::BackendError;
+use decode::{DecodeError, DecodedInstruction};
+use plan::{ExecutionPlan, PlanError};
+use reconcile::{DesiredState, ReconcileError, Reconciliation};
 use regex::Regex;
 use solana_account_decoder::UiAccountEncoding;
 use solana_client::client_error::ClientError as SolanaClientError;
@@ -22,10 +28,13 @@ use solana_sdk::bs58;
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::signature::{Signature, Signer};
 use solana_sdk::transaction::Transaction;
+use solana_transaction_status::UiTransactionEncoding;
 use std::convert::Into;
 use std::iter::Map;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 use std::vec::IntoIter;
+use telemetry::{Telemetry, TelemetryError};
 use thiserror::Error;
 
 pub use anchor_lang;
@@ -33,11 +42,23 @@ pub use cluster::Cluster;
 pub use solana_client;
 pub use solana_sdk;
 
+pub mod backend;
 mod cluster;
+pub mod decode;
+pub mod plan;
+pub mod reconcile;
+pub mod telemetry;
 
 const PROGRAM_LOG: &str = "Program log: ";
 const PROGRAM_DATA: &str = "Program data: ";
 
+// Maximum number of accounts fetched by a `getMultipleAccounts` request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+// How long to wait for a transaction of a plan to be confirmed, which is
+// roughly how long its blockhash is valid for.
+const PLAN_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(90);
+
 /// EventHandle unsubscribes from a program event stream on drop.
 pub type EventHandle = PubsubClientSubscription<RpcResponse<RpcLogsResponse>>;
 
@@ -116,6 +137,16 @@ impl Program {
         )
     }
 
+    /// Returns a request builder for an instruction of the program given as
+    /// raw data and account metas, e.g. an instruction without a type in the
+    /// program's crate. It's signed and sent like any other request.
+    pub fn raw_request(&self, data: Vec<u8>, accounts: Vec<AccountMeta>) -> RequestBuilder {
+        let mut request = self.request();
+        request.instruction_data = Some(data);
+        request.accounts = accounts;
+        request
+    }
+
     /// Returns a request builder for program state.
     pub fn state_request(&self) -> RequestBuilder {
         RequestBuilder::from(
@@ -194,6 +225,79 @@ impl Program {
         self.program_id
     }
 
+    /// Sends the transactions of a plan, stage by stage, with at most
+    /// `concurrency` transactions awaiting confirmation at once. The payer and
+    /// the given signers sign the transactions requiring them.
+    ///
+    /// Returns the signatures of the transactions, in the order of the plan.
+    pub fn send_plan(
+        &self,
+        plan: &ExecutionPlan,
+        signers: &[&dyn Signer],
+        concurrency: usize,
+    ) -> Result<Vec<Signature>, ClientError> {
+        let rpc_client = self.rpc();
+        let mut all_signers = signers.to_vec();
+        all_signers.push(&*self.cfg.payer);
+
+        let mut signatures = Vec::with_capacity(plan.transaction_count());
+        for stage in plan.stages() {
+            for chunk in stage.chunks(concurrency.max(1)) {
+                let latest_hash = rpc_client.get_latest_blockhash()?;
+                let mut pending = Vec::with_capacity(chunk.len());
+                for planned in chunk {
+                    let message = plan.message(planned);
+                    let required =
+                        &message.account_keys[..message.header.num_required_signatures as usize];
+                    let tx_signers = required
+                        .iter()
+                        .map(|key| {
+                            all_signers
+                                .iter()
+                                .find(|signer| signer.pubkey() == *key)
+                                .copied()
+                                .ok_or(PlanError::MissingSigner(*key))
+                        })
+                        .collect::<Result<Vec<&dyn Signer>, _>>()?;
+                    let mut tx = Transaction::new_unsigned(message);
+                    tx.sign(&tx_signers, latest_hash);
+                    pending.push(rpc_client.send_transaction(&tx)?);
+                }
+                for signature in pending {
+                    confirm_transaction(&rpc_client, &signature)?;
+                    signatures.push(signature);
+                }
+            }
+        }
+        Ok(signatures)
+    }
+
+    /// Fetches a transaction and decodes the instructions of the program in
+    /// it, including the ones invoked through CPI, e.g. by routers.
+    pub fn decode_transaction<T: AnchorDeserialize>(
+        &self,
+        signature: &Signature,
+    ) -> Result<Vec<DecodedInstruction<T>>, ClientError> {
+        let tx = self
+            .rpc()
+            .get_transaction(signature, UiTransactionEncoding::Base64)?;
+        Ok(decode::decode_transaction(
+            &self.program_id,
+            &tx.transaction,
+        )?)
+    }
+
+    /// Diffs the desired state against the live accounts, giving the changes
+    /// needed to converge.
+    pub fn reconcile(&self, state: &DesiredState) -> Result<Reconciliation, ClientError> {
+        let rpc_client = self.rpc();
+        let mut accounts = Vec::new();
+        for addresses in state.addresses().chunks(MAX_MULTIPLE_ACCOUNTS) {
+            accounts.extend(rpc_client.get_multiple_accounts(addresses)?);
+        }
+        Ok(state.diff(&accounts)?)
+    }
+
     pub fn on<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
         &self,
         f: impl Fn(&EventContext, T) + Send + 'static,
@@ -276,6 +380,20 @@ impl<T> Iterator for ProgramAccountsIterator<T> {
     }
 }
 
+fn confirm_transaction(rpc_client: &RpcClient, signature: &Signature) -> Result<(), ClientError> {
+    let start = Instant::now();
+    loop {
+        match rpc_client.get_signature_status_with_commitment(signature, rpc_client.commitment())? {
+            Some(Ok(())) => return Ok(()),
+            Some(Err(err)) => return Err(SolanaClientError::from(err).into()),
+            None if start.elapsed() > PLAN_CONFIRMATION_TIMEOUT => {
+                return Err(PlanError::Unconfirmed(*signature).into())
+            }
+            None => std::thread::sleep(Duration::from_millis(500)),
+        }
+    }
+}
+
 fn handle_program_log<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
     self_program_str: &str,
     l: &str,
@@ -389,6 +507,16 @@ pub enum ClientError {
     SolanaClientPubsubError(#[from] PubsubClientError),
     #[error("Unable to parse log: {0}")]
     LogParseError(String),
+    #[error("{0}")]
+    PlanError(#[from] PlanError),
+    #[error("{0}")]
+    ReconcileError(#[from] ReconcileError),
+    #[error("{0}")]
+    DecodeError(#[from] DecodeError),
+    #[error("{0}")]
+    BackendError(#[from] BackendError),
+    #[error("{0}")]
+    TelemetryError(#[from] TelemetryError),
 }
 
 /// `RequestBuilder` provides a builder interface to create and send
@@ -405,6 +533,7 @@ pub struct RequestBuilder<'a> {
     signers: Vec<&'a dyn Signer>,
     // True if the user is sending a state instruction.
     namespace: RequestNamespace,
+    telemetry: Option<Telemetry>,
 }
 
 #[derive(PartialEq)]
@@ -435,6 +564,7 @@ impl<'a> RequestBuilder<'a> {
             instruction_data: None,
             signers: Vec::new(),
             namespace,
+            telemetry: None,
         }
     }
 
@@ -497,6 +627,14 @@ impl<'a> RequestBuilder<'a> {
         self
     }
 
+    /// Records the instructions of the request in `telemetry` once sent, in
+    /// which case expired transactions are sent again with a new blockhash.
+    #[must_use]
+    pub fn telemetry(mut self, telemetry: &Telemetry) -> Self {
+        self.telemetry = Some(telemetry.clone());
+        self
+    }
+
     pub fn instructions(&self) -> Result<Vec<Instruction>, ClientError> {
         let mut accounts = match self.namespace {
             RequestNamespace::State { new } => match new {
@@ -542,6 +680,10 @@ impl<'a> RequestBuilder<'a> {
 
         let rpc_client = RpcClient::new_with_commitment(self.cluster, self.options);
 
+        if let Some(telemetry) = &self.telemetry {
+            return telemetry.send(&rpc_client, &instructions, &self.payer.pubkey(), &signers);
+        }
+
         let tx = {
             let latest_hash = rpc_client.get_latest_blockhash()?;
             Transaction::new_signed_with_payer(
@@ -591,6 +733,27 @@ impl<'a> RequestBuilder<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use solana_sdk::signature::Keypair;
+
+    #[test]
+    fn raw_request_instructions() {
+        let program_id = Pubkey::new_unique();
+        let counter = Pubkey::new_unique();
+        let program = Client::new(Cluster::Localnet, Rc::new(Keypair::new())).program(program_id);
+        let instructions = program
+            .raw_request(vec![7, 1, 2], vec![AccountMeta::new(counter, false)])
+            .instructions()
+            .unwrap();
+        assert_eq!(
+            instructions,
+            vec![Instruction {
+                program_id,
+                accounts: vec![AccountMeta::new(counter, false)],
+                data: vec![7, 1, 2],
+            }]
+        );
+    }
+
     #[test]
     fn new_execution() {
         let mut logs: &[String] =
@@ -0,0 +1,325 @@
+//! Packing of instructions into transactions.
+//!
+//! An [`ExecutionPlan`] packs instructions into as few transactions as it can,
+//! in order, without exceeding the size, compute and account lock limits of a
+//! transaction, and groups the transactions into stages: the transactions of
+//! a stage only depend on the ones of earlier stages, so they can be sent
+//! concurrently, e.g. with [`Program::send_plan`](crate::Program::send_plan).
+//!
+//! ```ignore
+//! let instructions = vec![
+//!     PlannedInstruction::new(create_ix, 10_000),
+//!     PlannedInstruction::new(deposit_ix, 30_000).after(0),
+//!     PlannedInstruction::new(other_ix, 30_000),
+//! ];
+//! let plan = ExecutionPlan::new(&program.payer(), instructions, &PlanConfig::default())?;
+//! let signatures = program.send_plan(&plan, &[&authority], 4)?;
+//! ```
+
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::message::Message;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::signature::Signature;
+use thiserror::Error;
+
+// Maximum number of accounts a transaction can lock.
+const MAX_ACCOUNT_LOCKS: usize = 64;
+
+// Compute units a transaction can use without a compute budget instruction,
+// for a single instruction.
+const DEFAULT_COMPUTE_UNITS: u32 = 200_000;
+
+/// An instruction to plan, along with its estimated compute units and the
+/// instructions it must be executed after.
+#[derive(Debug, Clone)]
+pub struct PlannedInstruction {
+    pub instruction: Instruction,
+    pub compute_units: u32,
+    /// Indices of earlier instructions of the plan.
+    pub depends_on: Vec<usize>,
+}
+
+impl PlannedInstruction {
+    pub fn new(instruction: Instruction, compute_units: u32) -> Self {
+        Self {
+            instruction,
+            compute_units,
+            depends_on: Vec::new(),
+        }
+    }
+
+    /// Executes the instruction after the instruction at `index`.
+    #[must_use]
+    pub fn after(mut self, index: usize) -> Self {
+        self.depends_on.push(index);
+        self
+    }
+}
+
+/// The limits of the planned transactions.
+#[derive(Debug, Clone, Copy)]
+pub struct PlanConfig {
+    /// Maximum size of a signed transaction, in bytes.
+    pub max_size: usize,
+    /// Maximum compute units of a transaction. Above the default limit, the
+    /// transactions need a compute budget instruction.
+    pub max_compute_units: u32,
+    /// Maximum number of accounts of a transaction.
+    pub max_account_locks: usize,
+    /// Maximum number of writable accounts of a transaction, e.g. to reduce
+    /// contention with other transactions.
+    pub max_write_locks: usize,
+}
+
+impl Default for PlanConfig {
+    fn default() -> Self {
+        Self {
+            max_size: PACKET_DATA_SIZE,
+            max_compute_units: DEFAULT_COMPUTE_UNITS,
+            max_account_locks: MAX_ACCOUNT_LOCKS,
+            max_write_locks: MAX_ACCOUNT_LOCKS,
+        }
+    }
+}
+
+/// A transaction of a plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedTransaction {
+    /// Indices of the instructions of the plan, in order.
+    pub instructions: Vec<usize>,
+    pub compute_units: u32,
+    /// Size of the signed transaction, in bytes.
+    pub size: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecutionPlan {
+    payer: Pubkey,
+    instructions: Vec<PlannedInstruction>,
+    stages: Vec<Vec<PlannedTransaction>>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PlanError {
+    #[error("Instruction {0} depends on instruction {1}, which isn't an earlier instruction")]
+    InvalidDependency(usize, usize),
+    #[error("Instruction {0} doesn't fit in a transaction")]
+    InstructionTooLarge(usize),
+    #[error("Missing signer {0}")]
+    MissingSigner(Pubkey),
+    #[error("Transaction {0} wasn't confirmed")]
+    Unconfirmed(Signature),
+}
+
+impl ExecutionPlan {
+    /// Plans the execution of the instructions, with transactions paid by
+    /// `payer`.
+    ///
+    /// Instructions are added to the first transaction they fit in that comes
+    /// after the ones of the instructions they depend on, so the order of
+    /// instructions that don't depend on each other isn't kept.
+    pub fn new(
+        payer: &Pubkey,
+        instructions: Vec<PlannedInstruction>,
+        config: &PlanConfig,
+    ) -> Result<Self, PlanError> {
+        // The transaction of each instruction.
+        let mut placement: Vec<usize> = Vec::with_capacity(instructions.len());
+        let mut transactions: Vec<PlannedTransaction> = Vec::new();
+        for (index, planned) in instructions.iter().enumerate() {
+            let mut first = 0;
+            for &dep in &planned.depends_on {
+                if dep >= index {
+                    return Err(PlanError::InvalidDependency(index, dep));
+                }
+                first = first.max(placement[dep]);
+            }
+            let fitting = (first..transactions.len()).find_map(|tx_index| {
+                let mut tx_instructions = transactions[tx_index].instructions.clone();
+                tx_instructions.push(index);
+                transaction(payer, &instructions, tx_instructions, config).map(|tx| (tx_index, tx))
+            });
+            match fitting {
+                Some((tx_index, tx)) => {
+                    transactions[tx_index] = tx;
+                    placement.push(tx_index);
+                }
+                None => {
+                    let tx = transaction(payer, &instructions, vec![index], config)
+                        .ok_or(PlanError::InstructionTooLarge(index))?;
+                    transactions.push(tx);
+                    placement.push(transactions.len() - 1);
+                }
+            }
+        }
+
+        // Dependencies only go to later transactions, so the stage of each
+        // transaction is known once the ones before it are.
+        let mut tx_stages: Vec<usize> = Vec::with_capacity(transactions.len());
+        for tx in &transactions {
+            let stage = tx
+                .instructions
+                .iter()
+                .flat_map(|&index| &instructions[index].depends_on)
+                .map(|&dep| placement[dep])
+                .filter(|&dep_tx| dep_tx != tx_stages.len())
+                .map(|dep_tx| tx_stages[dep_tx] + 1)
+                .max()
+                .unwrap_or(0);
+            tx_stages.push(stage);
+        }
+        let mut stages = vec![Vec::new(); tx_stages.iter().max().map_or(0, |max| max + 1)];
+        for (tx, stage) in transactions.into_iter().zip(tx_stages) {
+            stages[stage].push(tx);
+        }
+
+        Ok(Self {
+            payer: *payer,
+            instructions,
+            stages,
+        })
+    }
+
+    pub fn payer(&self) -> &Pubkey {
+        &self.payer
+    }
+
+    /// The transactions to execute, by stage. The transactions of a stage may
+    /// be executed concurrently, after the ones of the previous stage.
+    pub fn stages(&self) -> &[Vec<PlannedTransaction>] {
+        &self.stages
+    }
+
+    pub fn transaction_count(&self) -> usize {
+        self.stages.iter().map(Vec::len).sum()
+    }
+
+    /// The message of a planned transaction.
+    pub fn message(&self, tx: &PlannedTransaction) -> Message {
+        message(&self.payer, &self.instructions, &tx.instructions)
+    }
+}
+
+fn message(payer: &Pubkey, instructions: &[PlannedInstruction], indices: &[usize]) -> Message {
+    let instructions: Vec<Instruction> = indices
+        .iter()
+        .map(|&index| instructions[index].instruction.clone())
+        .collect();
+    Message::new(&instructions, Some(payer))
+}
+
+// The transaction of the given instructions, if within the limits.
+fn transaction(
+    payer: &Pubkey,
+    instructions: &[PlannedInstruction],
+    indices: Vec<usize>,
+    config: &PlanConfig,
+) -> Option<PlannedTransaction> {
+    let compute_units = indices
+        .iter()
+        .try_fold(0u32, |total, &index| {
+            total.checked_add(instructions[index].compute_units)
+        })
+        .filter(|&total| total <= config.max_compute_units)?;
+    let message = message(payer, instructions, &indices);
+    let header = &message.header;
+    let signers = header.num_required_signatures as usize;
+    let accounts = message.account_keys.len();
+    let write_locks = (signers - header.num_readonly_signed_accounts as usize)
+        + (accounts - signers - header.num_readonly_unsigned_accounts as usize);
+    let size = short_vec_len(signers) + signers * 64 + message.serialize().len();
+    if size > config.max_size
+        || accounts > config.max_account_locks
+        || write_locks > config.max_write_locks
+    {
+        return None;
+    }
+    Some(PlannedTransaction {
+        instructions: indices,
+        compute_units,
+        size,
+    })
+}
+
+// Size of the length prefix of a serialized vector.
+fn short_vec_len(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::instruction::AccountMeta;
+
+    fn ix(data_len: usize, writable: &[Pubkey]) -> Instruction {
+        Instruction {
+            program_id: Pubkey::new_from_array([1; 32]),
+            accounts: writable
+                .iter()
+                .map(|key| AccountMeta::new(*key, false))
+                .collect(),
+            data: vec![0; data_len],
+        }
+    }
+
+    #[test]
+    fn plan() {
+        let payer = Pubkey::new_unique();
+        let vault = Pubkey::new_unique();
+        let instructions = vec![
+            PlannedInstruction::new(ix(500, &[vault]), 50_000),
+            PlannedInstruction::new(ix(500, &[vault]), 50_000),
+            // Too large for the transaction of the first two.
+            PlannedInstruction::new(ix(500, &[vault]), 50_000).after(0),
+            PlannedInstruction::new(ix(10, &[]), 160_000),
+            PlannedInstruction::new(ix(10, &[]), 10_000).after(2),
+        ];
+        let plan = ExecutionPlan::new(&payer, instructions, &PlanConfig::default()).unwrap();
+        let stages: Vec<Vec<Vec<usize>>> = plan
+            .stages()
+            .iter()
+            .map(|stage| stage.iter().map(|tx| tx.instructions.clone()).collect())
+            .collect();
+        assert_eq!(stages, vec![vec![vec![0, 1], vec![3]], vec![vec![2, 4]]]);
+        assert_eq!(plan.transaction_count(), 3);
+        for tx in plan.stages().iter().flatten() {
+            assert!(tx.size <= PACKET_DATA_SIZE);
+            assert_eq!(tx.size, plan.message(tx).serialize().len() + 1 + 64);
+        }
+
+        let write_locks = PlanConfig {
+            max_write_locks: 2,
+            ..PlanConfig::default()
+        };
+        let instructions = vec![
+            PlannedInstruction::new(ix(10, &[Pubkey::new_unique()]), 1),
+            PlannedInstruction::new(ix(10, &[Pubkey::new_unique()]), 1),
+        ];
+        let plan = ExecutionPlan::new(&payer, instructions, &write_locks).unwrap();
+        assert_eq!(plan.stages()[0].len(), 2);
+
+        assert_eq!(
+            ExecutionPlan::new(
+                &payer,
+                vec![PlannedInstruction::new(ix(2000, &[]), 1)],
+                &PlanConfig::default()
+            )
+            .unwrap_err(),
+            PlanError::InstructionTooLarge(0)
+        );
+        assert_eq!(
+            ExecutionPlan::new(
+                &payer,
+                vec![PlannedInstruction::new(ix(10, &[]), 1).after(0)],
+                &PlanConfig::default()
+            )
+            .unwrap_err(),
+            PlanError::InvalidDependency(0, 0)
+        );
+    }
+}
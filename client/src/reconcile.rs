@@ -0,0 +1,432 @@
+//! Reconciliation of declared on-chain state.
+//!
+//! Instead of imperative setup scripts, the desired state of accounts (e.g.
+//! the fields of a config account, associated token accounts that must
+//! exist, or a program's upgrade authority) is declared as a
+//! [`DesiredState`], which is diffed against the live accounts, e.g. with
+//! [`Program::reconcile`](crate::Program::reconcile), giving only the
+//! instructions needed to converge. Running the same setup again is a no-op.
+//!
+//! ```ignore
+//! let mut state = DesiredState::new();
+//! let vault = state.add(AssociatedTokenAccount::new(payer, authority, mint));
+//! state.add_after(
+//!     DesiredAccount::new(config_address, expected_config)
+//!         .create(vec![init_config_ix])
+//!         .update(|_current| vec![set_config_ix.clone()]),
+//!     &[vault],
+//! );
+//! let reconciliation = program.reconcile(&state)?;
+//! if !reconciliation.is_converged() {
+//!     let plan = reconciliation.plan(&payer, &PlanConfig::default(), 50_000)?;
+//!     program.send_plan(&plan, &[&authority], 4)?;
+//! }
+//! ```
+
+use crate::plan::{ExecutionPlan, PlanConfig, PlanError, PlannedInstruction};
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::solana_program::{system_program, sysvar};
+use anchor_lang::{AccountDeserialize, ProgramData};
+use solana_sdk::account::Account;
+use std::str::FromStr;
+use thiserror::Error;
+
+const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+/// A piece of desired on-chain state, held by a single account.
+pub trait Desired {
+    fn address(&self) -> Pubkey;
+
+    /// A description of the state, for reports.
+    fn describe(&self) -> String;
+
+    /// The instructions converging the live account, if it exists, to the
+    /// desired state.
+    fn diff(&self, account: Option<&Account>) -> Result<Vec<Instruction>, ReconcileError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ReconcileError {
+    #[error("{0} doesn't exist and can't be created")]
+    Missing(String),
+    #[error("{0} differs from the desired state and can't be updated")]
+    Unconvergeable(String),
+    #[error("{0}: {1}")]
+    AnchorError(String, Box<anchor_lang::error::Error>),
+}
+
+type Update<T> = Box<dyn Fn(&T) -> Vec<Instruction>>;
+
+/// An account whose deserialized value must match.
+pub struct DesiredAccount<T> {
+    address: Pubkey,
+    description: String,
+    matches: Box<dyn Fn(&T) -> bool>,
+    create: Vec<Instruction>,
+    update: Option<Update<T>>,
+}
+
+impl<T: AccountDeserialize + PartialEq + 'static> DesiredAccount<T> {
+    /// An account which must be equal to `value`.
+    pub fn new(address: Pubkey, value: T) -> Self {
+        Self::matching(address, move |current| *current == value)
+    }
+}
+
+impl<T: AccountDeserialize> DesiredAccount<T> {
+    /// An account which must satisfy `matches`, e.g. to only compare some of
+    /// its fields.
+    pub fn matching(address: Pubkey, matches: impl Fn(&T) -> bool + 'static) -> Self {
+        Self {
+            address,
+            description: format!("Account {}", address),
+            matches: Box::new(matches),
+            create: Vec::new(),
+            update: None,
+        }
+    }
+
+    #[must_use]
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// The instructions creating the account when it doesn't exist.
+    #[must_use]
+    pub fn create(mut self, instructions: Vec<Instruction>) -> Self {
+        self.create = instructions;
+        self
+    }
+
+    /// The instructions updating the account, given its current value, when
+    /// it doesn't match.
+    #[must_use]
+    pub fn update(mut self, update: impl Fn(&T) -> Vec<Instruction> + 'static) -> Self {
+        self.update = Some(Box::new(update));
+        self
+    }
+}
+
+impl DesiredAccount<ProgramData> {
+    /// The upgrade authority of a program, which is made immutable if `None`.
+    pub fn upgrade_authority(program_id: Pubkey, authority: Option<Pubkey>) -> Self {
+        let (address, _) =
+            Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::ID);
+        Self::matching(address, move |current: &ProgramData| {
+            current.upgrade_authority_address == authority
+        })
+        .description(format!("Upgrade authority of {}", program_id))
+        .update(move |current| match current.upgrade_authority_address {
+            Some(current_authority) => vec![bpf_loader_upgradeable::set_upgrade_authority(
+                &program_id,
+                &current_authority,
+                authority.as_ref(),
+            )],
+            // Immutable programs can't be updated.
+            None => Vec::new(),
+        })
+    }
+}
+
+impl<T: AccountDeserialize> Desired for DesiredAccount<T> {
+    fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    fn describe(&self) -> String {
+        self.description.clone()
+    }
+
+    fn diff(&self, account: Option<&Account>) -> Result<Vec<Instruction>, ReconcileError> {
+        let account = match account {
+            None if self.create.is_empty() => return Err(ReconcileError::Missing(self.describe())),
+            None => return Ok(self.create.clone()),
+            Some(account) => account,
+        };
+        let current = T::try_deserialize(&mut &account.data[..])
+            .map_err(|err| ReconcileError::AnchorError(self.describe(), Box::new(err)))?;
+        if (self.matches)(&current) {
+            return Ok(Vec::new());
+        }
+        match self.update.as_ref().map(|update| update(&current)) {
+            Some(instructions) if !instructions.is_empty() => Ok(instructions),
+            _ => Err(ReconcileError::Unconvergeable(self.describe())),
+        }
+    }
+}
+
+/// An associated token account which must exist.
+pub struct AssociatedTokenAccount {
+    payer: Pubkey,
+    owner: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+}
+
+impl AssociatedTokenAccount {
+    pub fn new(payer: Pubkey, owner: Pubkey, mint: Pubkey) -> Self {
+        Self {
+            payer,
+            owner,
+            mint,
+            token_program: Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap(),
+        }
+    }
+
+    /// Sets the token program of the mint, if not the SPL Token program.
+    #[must_use]
+    pub fn token_program(mut self, token_program: Pubkey) -> Self {
+        self.token_program = token_program;
+        self
+    }
+}
+
+impl Desired for AssociatedTokenAccount {
+    fn address(&self) -> Pubkey {
+        let (address, _) = Pubkey::find_program_address(
+            &[
+                self.owner.as_ref(),
+                self.token_program.as_ref(),
+                self.mint.as_ref(),
+            ],
+            &Pubkey::from_str(ASSOCIATED_TOKEN_PROGRAM_ID).unwrap(),
+        );
+        address
+    }
+
+    fn describe(&self) -> String {
+        format!("Token account of {} for mint {}", self.owner, self.mint)
+    }
+
+    fn diff(&self, account: Option<&Account>) -> Result<Vec<Instruction>, ReconcileError> {
+        if account.is_some() {
+            return Ok(Vec::new());
+        }
+        Ok(vec![Instruction {
+            program_id: Pubkey::from_str(ASSOCIATED_TOKEN_PROGRAM_ID).unwrap(),
+            accounts: vec![
+                AccountMeta::new(self.payer, true),
+                AccountMeta::new(self.address(), false),
+                AccountMeta::new_readonly(self.owner, false),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(self.token_program, false),
+                AccountMeta::new_readonly(sysvar::rent::ID, false),
+            ],
+            data: Vec::new(),
+        }])
+    }
+}
+
+/// The desired state of a set of accounts, converged in order of their
+/// dependencies.
+#[derive(Default)]
+pub struct DesiredState {
+    items: Vec<(Box<dyn Desired>, Vec<usize>)>,
+}
+
+impl DesiredState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a desired state, returning its index.
+    pub fn add(&mut self, desired: impl Desired + 'static) -> usize {
+        self.add_after(desired, &[])
+    }
+
+    /// Adds a desired state converged after the ones at the given indices,
+    /// returning its index.
+    pub fn add_after(&mut self, desired: impl Desired + 'static, after: &[usize]) -> usize {
+        assert!(
+            after.iter().all(|&index| index < self.items.len()),
+            "Dependencies must be added first"
+        );
+        self.items.push((Box::new(desired), after.to_vec()));
+        self.items.len() - 1
+    }
+
+    pub fn addresses(&self) -> Vec<Pubkey> {
+        self.items
+            .iter()
+            .map(|(desired, _)| desired.address())
+            .collect()
+    }
+
+    /// Diffs against the live accounts at `addresses()`.
+    pub fn diff(&self, accounts: &[Option<Account>]) -> Result<Reconciliation, ReconcileError> {
+        assert_eq!(accounts.len(), self.items.len());
+        let changes = self
+            .items
+            .iter()
+            .zip(accounts)
+            .map(|((desired, after), account)| {
+                Ok(Change {
+                    description: desired.describe(),
+                    instructions: desired.diff(account.as_ref())?,
+                    after: after.clone(),
+                })
+            })
+            .collect::<Result<_, ReconcileError>>()?;
+        Ok(Reconciliation { changes })
+    }
+}
+
+/// The change needed by a desired state, if any.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub description: String,
+    pub instructions: Vec<Instruction>,
+    after: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reconciliation {
+    changes: Vec<Change>,
+}
+
+impl Reconciliation {
+    pub fn is_converged(&self) -> bool {
+        self.changes().next().is_none()
+    }
+
+    /// The changes needed to converge, in order.
+    pub fn changes(&self) -> impl Iterator<Item = &Change> {
+        self.changes
+            .iter()
+            .filter(|change| !change.instructions.is_empty())
+    }
+
+    /// Plans the execution of the changes, with each instruction estimated to
+    /// use `compute_units`.
+    pub fn plan(
+        &self,
+        payer: &Pubkey,
+        config: &PlanConfig,
+        compute_units: u32,
+    ) -> Result<ExecutionPlan, PlanError> {
+        let mut instructions: Vec<PlannedInstruction> = Vec::new();
+        // The last instructions each change must run after, including the
+        // ones of the changes its converged dependencies depend on.
+        let mut tails: Vec<Vec<usize>> = Vec::with_capacity(self.changes.len());
+        for change in &self.changes {
+            let mut depends_on: Vec<usize> = change
+                .after
+                .iter()
+                .flat_map(|&index| tails[index].iter().copied())
+                .collect();
+            depends_on.sort_unstable();
+            depends_on.dedup();
+            if change.instructions.is_empty() {
+                tails.push(depends_on);
+                continue;
+            }
+            for instruction in &change.instructions {
+                let mut planned = PlannedInstruction::new(instruction.clone(), compute_units);
+                planned.depends_on = std::mem::replace(&mut depends_on, vec![instructions.len()]);
+                instructions.push(planned);
+            }
+            tails.push(depends_on);
+        }
+        ExecutionPlan::new(payer, instructions, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::error::ErrorCode;
+
+    #[derive(PartialEq)]
+    struct Config {
+        fee: u16,
+    }
+
+    impl Config {
+        fn data(&self) -> Vec<u8> {
+            self.fee.to_le_bytes().to_vec()
+        }
+    }
+
+    impl AccountDeserialize for Config {
+        fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+            match **buf {
+                [a, b] => Ok(Config {
+                    fee: u16::from_le_bytes([a, b]),
+                }),
+                _ => Err(ErrorCode::AccountDidNotDeserialize.into()),
+            }
+        }
+    }
+
+    fn account(data: Vec<u8>) -> Account {
+        Account {
+            data,
+            ..Account::default()
+        }
+    }
+
+    fn ix(tag: u8) -> Instruction {
+        Instruction::new_with_bytes(Pubkey::new_unique(), &[tag], vec![])
+    }
+
+    #[test]
+    fn reconcile() {
+        let payer = Pubkey::new_unique();
+        let mut state = DesiredState::new();
+        let ata = state.add(AssociatedTokenAccount::new(
+            payer,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ));
+        let config = state.add_after(
+            DesiredAccount::new(Pubkey::new_unique(), Config { fee: 5 })
+                .create(vec![ix(0)])
+                .update(|current| vec![ix(current.fee as u8)]),
+            &[ata],
+        );
+        state.add_after(
+            DesiredAccount::matching(Pubkey::new_unique(), |current: &Config| current.fee > 0)
+                .create(vec![ix(1), ix(2)]),
+            &[config],
+        );
+
+        // Nothing exists.
+        let reconciliation = state.diff(&[None, None, None]).unwrap();
+        assert_eq!(reconciliation.changes().count(), 3);
+        let plan = reconciliation
+            .plan(&payer, &PlanConfig::default(), 10_000)
+            .unwrap();
+        assert_eq!(plan.transaction_count(), 1);
+
+        // The token account exists and the config is outdated.
+        let existing = Some(account(vec![]));
+        let outdated = Some(account(Config { fee: 3 }.data()));
+        let reconciliation = state
+            .diff(&[existing.clone(), outdated, Some(account(vec![1, 0]))])
+            .unwrap();
+        let changes: Vec<&Change> = reconciliation.changes().collect();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].instructions[0].data, vec![3]);
+
+        // Converged.
+        let current = Some(account(Config { fee: 5 }.data()));
+        let reconciliation = state.diff(&[existing, current.clone(), current]).unwrap();
+        assert!(reconciliation.is_converged());
+
+        let missing = DesiredAccount::new(Pubkey::new_unique(), Config { fee: 5 });
+        assert!(matches!(
+            missing.diff(None),
+            Err(ReconcileError::Missing(_))
+        ));
+        let stale = account(Config { fee: 1 }.data());
+        assert!(matches!(
+            missing.diff(Some(&stale)),
+            Err(ReconcileError::Unconvergeable(_))
+        ));
+    }
+}
@@ -0,0 +1,491 @@
+//! Opt-in collection of the cost of the program's instructions as they land.
+//!
+//! For each instruction of the program in a landed transaction, the slot it
+//! landed in, the compute units it consumed, the priority fee paid by its
+//! transaction and the number of times it was sent again are recorded under
+//! the instruction's name, and aggregated to tune compute budgets and
+//! priority fees from real traffic.
+//!
+//! ```ignore
+//! let telemetry = Telemetry::new(program.id(), &["deposit", "withdraw"]);
+//! program
+//!     .request()
+//!     .accounts(accounts)
+//!     .args(instruction::Deposit { amount })
+//!     .telemetry(&telemetry)
+//!     .send()?;
+//!
+//! for (name, stats) in telemetry.aggregate() {
+//!     println!("{}: p95 {} CUs", name, stats.compute_units.p95);
+//! }
+//! ```
+
+use crate::ClientError;
+use anchor_lang::solana_program::hash::{hash, Hash};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::message::Message;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::signature::{Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+const COMPUTE_BUDGET_PROGRAM: &str = "ComputeBudget111111111111111111111111111111";
+
+// Compute units available to each instruction, and to a transaction, when
+// its compute unit limit isn't set.
+const DEFAULT_INSTRUCTION_COMPUTE_UNITS: u64 = 200_000;
+const MAX_COMPUTE_UNITS: u64 = 1_400_000;
+
+// Times a transaction is signed with a new blockhash and sent again after the
+// previous one expired.
+const MAX_SEND_RETRIES: u32 = 3;
+
+/// An instruction of the program in a landed transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionSample {
+    /// Name of the instruction, or the hex of its discriminator if it isn't
+    /// one of the names given to the collector.
+    pub instruction: String,
+    pub signature: Signature,
+    pub slot: u64,
+    /// Compute units consumed by the instruction, if logged.
+    pub compute_units: Option<u64>,
+    /// Priority fee paid by the transaction, in lamports.
+    pub priority_fee: u64,
+    /// Fee paid by the transaction, in lamports.
+    pub fee: u64,
+    /// Times the transaction was sent again before landing.
+    pub retries: u32,
+    pub failed: bool,
+}
+
+/// Distribution of a measure over the samples of an instruction.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Distribution {
+    pub min: u64,
+    pub max: u64,
+    pub mean: u64,
+    pub p50: u64,
+    pub p95: u64,
+}
+
+impl Distribution {
+    fn new(mut values: Vec<u64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_unstable();
+        let percentile = |p: usize| values[(values.len() - 1) * p / 100];
+        Self {
+            min: values[0],
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<u64>() / values.len() as u64,
+            p50: percentile(50),
+            p95: percentile(95),
+        }
+    }
+}
+
+/// Aggregated samples of an instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionStats {
+    pub count: usize,
+    pub failed: usize,
+    pub compute_units: Distribution,
+    pub priority_fee: Distribution,
+    pub retries: u64,
+    pub max_retries: u32,
+    /// Slot of the latest sample.
+    pub last_slot: u64,
+}
+
+#[derive(Debug, Error)]
+pub enum TelemetryError {
+    #[error("The transaction couldn't be decoded")]
+    InvalidTransaction,
+    #[error("The transaction has no status meta")]
+    MissingMeta,
+    #[error("Transaction {0} expired before landing")]
+    Expired(Signature),
+}
+
+/// Collects samples of the instructions of a program. Clones share their
+/// samples.
+#[derive(Debug, Clone)]
+pub struct Telemetry {
+    program_id: Pubkey,
+    names: Rc<HashMap<[u8; 8], String>>,
+    samples: Rc<RefCell<Vec<InstructionSample>>>,
+}
+
+impl Telemetry {
+    /// Collects the instructions of the program, naming them after the
+    /// discriminators of the given instruction names, e.g. `deposit`.
+    pub fn new(program_id: Pubkey, instructions: &[&str]) -> Self {
+        let names = instructions
+            .iter()
+            .map(|name| (sighash(name), name.to_string()))
+            .collect();
+        Self {
+            program_id,
+            names: Rc::new(names),
+            samples: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Records the instructions of the program in a transaction fetched with
+    /// a binary encoding, e.g. base64, along with the number of times it was
+    /// sent again.
+    pub fn record(
+        &self,
+        tx: &EncodedConfirmedTransactionWithStatusMeta,
+        retries: u32,
+    ) -> Result<(), TelemetryError> {
+        let versioned = tx
+            .transaction
+            .transaction
+            .decode()
+            .ok_or(TelemetryError::InvalidTransaction)?;
+        let message = match versioned.message {
+            VersionedMessage::Legacy(message) => message,
+            _ => return Err(TelemetryError::InvalidTransaction),
+        };
+        let meta = tx
+            .transaction
+            .meta
+            .as_ref()
+            .ok_or(TelemetryError::MissingMeta)?;
+        let logs = meta.log_messages.clone().unwrap_or_default();
+        let samples = self.samples_of(
+            &message,
+            &logs,
+            versioned.signatures[0],
+            tx.slot,
+            meta.fee,
+            meta.err.is_some(),
+            retries,
+        );
+        self.samples.borrow_mut().extend(samples);
+        Ok(())
+    }
+
+    /// The samples recorded so far.
+    pub fn samples(&self) -> Vec<InstructionSample> {
+        self.samples.borrow().clone()
+    }
+
+    /// Aggregates the samples recorded so far per instruction.
+    pub fn aggregate(&self) -> BTreeMap<String, InstructionStats> {
+        let mut per_instruction: BTreeMap<String, Vec<InstructionSample>> = BTreeMap::new();
+        for sample in self.samples.borrow().iter() {
+            per_instruction
+                .entry(sample.instruction.clone())
+                .or_default()
+                .push(sample.clone());
+        }
+        per_instruction
+            .into_iter()
+            .map(|(name, samples)| {
+                let stats = InstructionStats {
+                    count: samples.len(),
+                    failed: samples.iter().filter(|s| s.failed).count(),
+                    compute_units: Distribution::new(
+                        samples.iter().filter_map(|s| s.compute_units).collect(),
+                    ),
+                    priority_fee: Distribution::new(
+                        samples.iter().map(|s| s.priority_fee).collect(),
+                    ),
+                    retries: samples.iter().map(|s| s.retries as u64).sum(),
+                    max_retries: samples.iter().map(|s| s.retries).max().unwrap_or(0),
+                    last_slot: samples.iter().map(|s| s.slot).max().unwrap_or(0),
+                };
+                (name, stats)
+            })
+            .collect()
+    }
+
+    /// Drops the samples recorded so far.
+    pub fn clear(&self) {
+        self.samples.borrow_mut().clear();
+    }
+
+    // Sends the transaction, with a new blockhash each time the previous
+    // one expired, and records it once landed.
+    pub(crate) fn send(
+        &self,
+        rpc_client: &RpcClient,
+        instructions: &[Instruction],
+        payer: &Pubkey,
+        signers: &[&dyn Signer],
+    ) -> Result<Signature, ClientError> {
+        let commitment = match rpc_client.commitment().is_at_least_confirmed() {
+            true => rpc_client.commitment(),
+            false => CommitmentConfig::confirmed(),
+        };
+        let mut retries = 0;
+        let signature = loop {
+            let latest_hash = rpc_client.get_latest_blockhash()?;
+            let tx =
+                Transaction::new_signed_with_payer(instructions, Some(payer), signers, latest_hash);
+            let signature = rpc_client.send_transaction(&tx)?;
+            if confirm(rpc_client, &signature, &latest_hash, commitment)? {
+                break signature;
+            }
+            if retries == MAX_SEND_RETRIES {
+                return Err(TelemetryError::Expired(signature).into());
+            }
+            retries += 1;
+        };
+
+        let tx = rpc_client.get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(commitment),
+                ..RpcTransactionConfig::default()
+            },
+        )?;
+        self.record(&tx, retries)?;
+        Ok(signature)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn samples_of(
+        &self,
+        message: &Message,
+        logs: &[String],
+        signature: Signature,
+        slot: u64,
+        fee: u64,
+        failed: bool,
+        retries: u32,
+    ) -> Vec<InstructionSample> {
+        let priority_fee = priority_fee(message);
+        let compute_units = compute_units(logs);
+        let mut invocations: HashMap<Pubkey, usize> = HashMap::new();
+        let mut samples = Vec::new();
+        for ix in &message.instructions {
+            let program_id = message.account_keys[ix.program_id_index as usize];
+            let invocation = invocations.entry(program_id).or_default();
+            let consumed = compute_units
+                .get(&program_id)
+                .and_then(|units| units.get(*invocation))
+                .copied()
+                .flatten();
+            *invocation += 1;
+            if program_id != self.program_id {
+                continue;
+            }
+            let mut discriminator = [0; 8];
+            let len = ix.data.len().min(8);
+            discriminator[..len].copy_from_slice(&ix.data[..len]);
+            let instruction = self.names.get(&discriminator).cloned().unwrap_or_else(|| {
+                discriminator
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect()
+            });
+            samples.push(InstructionSample {
+                instruction,
+                signature,
+                slot,
+                compute_units: consumed,
+                priority_fee,
+                fee,
+                retries,
+                failed,
+            });
+        }
+        samples
+    }
+}
+
+// Whether the transaction was confirmed before its blockhash expired.
+fn confirm(
+    rpc_client: &RpcClient,
+    signature: &Signature,
+    blockhash: &Hash,
+    commitment: CommitmentConfig,
+) -> Result<bool, ClientError> {
+    loop {
+        match rpc_client.get_signature_status_with_commitment(signature, commitment)? {
+            // Failed transactions landed too, and are recorded.
+            Some(_) => return Ok(true),
+            None if !rpc_client.is_blockhash_valid(blockhash, CommitmentConfig::processed())? => {
+                return Ok(false)
+            }
+            None => std::thread::sleep(Duration::from_millis(500)),
+        }
+    }
+}
+
+fn sighash(name: &str) -> [u8; 8] {
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+// The compute units consumed by each top level invocation of each program,
+// in order, from the logs of the transaction. Builtin programs, e.g. the
+// compute budget program, don't log them.
+fn compute_units(logs: &[String]) -> HashMap<Pubkey, Vec<Option<u64>>> {
+    let mut units: HashMap<Pubkey, Vec<Option<u64>>> = HashMap::new();
+    let mut depth = 0;
+    for log in logs {
+        let words: Vec<&str> = log.split_whitespace().collect();
+        match words[..] {
+            ["Program", program, "invoke", level] => {
+                depth = level
+                    .trim_matches(|c| c == '[' || c == ']')
+                    .parse()
+                    .unwrap_or(0);
+                if depth == 1 {
+                    if let Ok(program) = Pubkey::from_str(program) {
+                        units.entry(program).or_default().push(None);
+                    }
+                }
+            }
+            ["Program", program, "consumed", consumed, "of", _, "compute", "units"]
+                if depth == 1 =>
+            {
+                if let (Ok(program), Ok(consumed)) = (Pubkey::from_str(program), consumed.parse()) {
+                    if let Some(last) = units.get_mut(&program).and_then(|units| units.last_mut()) {
+                        *last = Some(consumed);
+                    }
+                }
+            }
+            ["Program", _, "success"] | ["Program", _, "failed:", ..] => {
+                depth = depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+    }
+    units
+}
+
+// The priority fee of the transaction set by its compute budget instructions,
+// i.e. its compute unit price times its compute unit limit, or the
+// additional fee of the deprecated request units instruction.
+fn priority_fee(message: &Message) -> u64 {
+    let compute_budget = Pubkey::from_str(COMPUTE_BUDGET_PROGRAM).unwrap();
+    let mut limit = None;
+    let mut price = 0u64;
+    let mut instruction_count = 0;
+    for ix in &message.instructions {
+        if message.account_keys[ix.program_id_index as usize] != compute_budget {
+            instruction_count += 1;
+            continue;
+        }
+        let u32_at = |offset: usize| {
+            ix.data
+                .get(offset..offset + 4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as u64)
+        };
+        match ix.data.first() {
+            // RequestUnitsDeprecated { units, additional_fee }
+            Some(0) => return u32_at(5).unwrap_or(0),
+            // SetComputeUnitLimit(units)
+            Some(2) => limit = u32_at(1),
+            // SetComputeUnitPrice(micro_lamports)
+            Some(3) => {
+                price = ix
+                    .data
+                    .get(1..9)
+                    .map_or(0, |bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            }
+            _ => {}
+        }
+    }
+    let limit = limit
+        .unwrap_or(DEFAULT_INSTRUCTION_COMPUTE_UNITS * instruction_count)
+        .min(MAX_COMPUTE_UNITS);
+    let micro_lamports = price as u128 * limit as u128;
+    ((micro_lamports + 999_999) / 1_000_000) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::instruction::AccountMeta;
+
+    #[test]
+    fn samples_of_transaction() {
+        let program_id = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let compute_budget = Pubkey::from_str(COMPUTE_BUDGET_PROGRAM).unwrap();
+        let ix = |program_id: Pubkey, data: Vec<u8>| {
+            Instruction::new_with_bytes(program_id, &data, vec![AccountMeta::new(payer, true)])
+        };
+        let mut set_price = vec![3];
+        set_price.extend_from_slice(&2_500u64.to_le_bytes());
+        let mut set_limit = vec![2];
+        set_limit.extend_from_slice(&300_000u32.to_le_bytes());
+        let message = Message::new(
+            &[
+                ix(compute_budget, set_limit),
+                ix(compute_budget, set_price),
+                ix(program_id, sighash("deposit").to_vec()),
+                ix(other, vec![0]),
+                ix(program_id, vec![1, 2, 3, 4, 5, 6, 7, 8]),
+            ],
+            Some(&payer),
+        );
+        let logs = vec![
+            format!("Program {} invoke [1]", compute_budget),
+            format!("Program {} success", compute_budget),
+            format!("Program {} invoke [1]", compute_budget),
+            format!("Program {} success", compute_budget),
+            format!("Program {} invoke [1]", program_id),
+            format!("Program {} invoke [2]", other),
+            format!("Program {} consumed 1000 of 290000 compute units", other),
+            format!("Program {} success", other),
+            format!(
+                "Program {} consumed 12000 of 300000 compute units",
+                program_id
+            ),
+            format!("Program {} success", program_id),
+            format!("Program {} invoke [1]", other),
+            format!("Program {} consumed 500 of 288000 compute units", other),
+            format!("Program {} success", other),
+            format!("Program {} invoke [1]", program_id),
+            format!(
+                "Program {} consumed 3000 of 287500 compute units",
+                program_id
+            ),
+            format!("Program {} success", program_id),
+        ];
+
+        let telemetry = Telemetry::new(program_id, &["deposit"]);
+        let samples =
+            telemetry.samples_of(&message, &logs, Signature::default(), 42, 5750, false, 1);
+        telemetry.samples.borrow_mut().extend(samples);
+        let samples = telemetry.samples();
+        assert_eq!(
+            samples
+                .iter()
+                .map(|s| (s.instruction.as_str(), s.compute_units, s.priority_fee))
+                .collect::<Vec<_>>(),
+            vec![
+                ("deposit", Some(12000), 750),
+                ("0102030405060708", Some(3000), 750),
+            ]
+        );
+
+        let stats = &telemetry.aggregate()["deposit"];
+        assert_eq!(stats.count, 1);
+        assert_eq!(stats.compute_units.p95, 12000);
+        assert_eq!(stats.retries, 1);
+        assert_eq!(stats.last_slot, 42);
+    }
+}
@@ -45,6 +45,10 @@ use syn::parse_macro_input;
 /// on the `Accounts` struct, particularly when instruction arguments are
 /// needed. Here, we use the given `bump_seed` to verify it creates a valid
 /// program-derived address.
+///
+/// The calls are recorded in the IDL as the `guards` of the instruction, named
+/// after the called functions, e.g. `is_admin` for `is_admin(&ctx)`, so that
+/// permissioned instructions can be told apart without reading the source.
 #[proc_macro_attribute]
 pub fn access_control(
     args: proc_macro::TokenStream,
@@ -79,3 +83,169 @@ pub fn access_control(
         }
     })
 }
+
+/// Marks an instruction as administrative, e.g. changing the fees or pausing
+/// the program, recorded in the IDL as the `admin` instructions.
+///
+/// This doesn't restrict who may call the instruction, which is still up to
+/// its accounts' constraints, e.g. `has_one = admin`. It lets tooling tell
+/// these instructions apart, e.g. `anchor idl admin-cli` generating a CLI for
+/// operators to call them with.
+///
+/// # Example
+///
+/// ```ignore
+/// #[program]
+/// mod vault {
+///     use super::*;
+///
+///     #[admin]
+///     pub fn set_fee(ctx: Context<SetFee>, fee_bps: u16) -> Result<()> {
+///         ...
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn admin(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if !args.is_empty() {
+        return syn::Error::new(proc_macro2::Span::call_site(), "`admin` takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    input
+}
+
+/// Allows an instruction in the `batch` instruction of the program, generated
+/// when any of its instructions is marked `#[batch]`.
+///
+/// The `batch` instruction takes the data of each instruction to run, and the
+/// number of accounts each one takes, out of the accounts given to the batch,
+/// in order. The instructions run in sequence, each one persisting its
+/// accounts before the next, and the batch fails as a whole if any of them
+/// does. Its instructions are listed in the IDL as the `batch` of the `batch`
+/// instruction.
+///
+/// # Example
+///
+/// ```ignore
+/// #[program]
+/// mod vault {
+///     use super::*;
+///
+///     #[batch]
+///     pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+///         ...
+///     }
+///
+///     #[batch]
+///     pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+///         ...
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn batch(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if !args.is_empty() {
+        return syn::Error::new(proc_macro2::Span::call_site(), "`batch` takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    input
+}
+
+/// Schedules the removal of an instruction, failing it with
+/// `InstructionSunset` from the given slot, or unix timestamp, on.
+///
+/// The deadline is recorded in the IDL as the `sunset` of the instruction, and
+/// the clients generated from it mark the instruction as deprecated, so that
+/// integrators can migrate off it in time.
+///
+/// # Example
+///
+/// ```ignore
+/// #[program]
+/// mod vault {
+///     use super::*;
+///
+///     /// Use `withdraw_v2` instead.
+///     #[sunset(timestamp = 1735689600)]
+///     pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+///         ...
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn sunset(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let (now, deadline) = match anchor_syn::parser::sunset::parse_args(args.into()) {
+        Ok(anchor_syn::parser::sunset::Sunset::Slot(slot)) => {
+            (quote! { clock.slot }, quote! { #slot })
+        }
+        Ok(anchor_syn::parser::sunset::Sunset::Timestamp(timestamp)) => {
+            (quote! { clock.unix_timestamp }, quote! { #timestamp })
+        }
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let item_fn = parse_macro_input!(input as syn::ItemFn);
+
+    let fn_attrs = item_fn.attrs;
+    let fn_vis = item_fn.vis;
+    let fn_sig = item_fn.sig;
+    let fn_stmts = item_fn.block.stmts;
+
+    proc_macro::TokenStream::from(quote! {
+        #(#fn_attrs)*
+        #fn_vis #fn_sig {
+            {
+                let clock = <anchor_lang::solana_program::sysvar::clock::Clock as anchor_lang::solana_program::sysvar::Sysvar>::get()?;
+                if #now >= #deadline {
+                    return Err(anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::InstructionSunset)
+                        .with_values((#now, #deadline)));
+                }
+            }
+
+            #(#fn_stmts)*
+        }
+    })
+}
+
+/// Declares the compute budget an instruction needs, in compute units and
+/// bytes of heap, when it's more than the default.
+///
+/// A program can't raise its own budget, so this only records the budget in
+/// the IDL as the `computeBudget` of the instruction. The TypeScript client
+/// prepends the instructions of the compute budget program requesting it to
+/// the transactions it builds, unless given its own.
+///
+/// # Example
+///
+/// ```ignore
+/// #[program]
+/// mod vault {
+///     use super::*;
+///
+///     #[compute_budget(units = 400_000, heap = 256_000)]
+///     pub fn rebalance(ctx: Context<Rebalance>) -> Result<()> {
+///         ...
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn compute_budget(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if let Err(err) = anchor_syn::parser::compute_budget::parse_args(args.into()) {
+        return err.to_compile_error().into();
+    }
+    input
+}
@@ -17,6 +17,15 @@ mod id;
 /// - [`Discriminator`](./trait.Discriminator.html)
 /// - [`Owner`](./trait.Owner.html)
 ///
+/// With the `serde` feature of `anchor-lang` enabled, `Serialize` and
+/// `Deserialize` are derived as well, with public keys represented as base58
+/// strings and bytes as base64 strings. Any user defined types used as fields
+/// must then implement them, too.
+///
+/// Similarly, with the `arbitrary` feature enabled, `arbitrary::Arbitrary` is
+/// implemented for fuzzing and property testing. `String` and `Vec` fields can
+/// be bounded with `#[max_len(..)]`, giving one length per level of nesting.
+///
 /// When implementing account serialization traits the first 8 bytes are
 /// reserved for a unique account discriminator, self described by the first 8
 /// bytes of the SHA256 of the account's Rust ident.
@@ -25,6 +34,189 @@ mod id;
 /// check this discriminator. If it doesn't match, an invalid account was given,
 /// and the account deserialization will exit with an error.
 ///
+/// # Custom discriminators
+///
+/// The discriminator can be overridden with the `discriminator` argument,
+/// either with its bytes, from 1 to 8 of them, e.g. to keep the layout of the
+/// accounts of a program migrated to Anchor, or with the name to derive it
+/// from instead of the struct's, e.g. to keep the discriminator of a renamed
+/// account. The data of the account starts with those bytes instead, so the
+/// space of the account is the length of the discriminator plus the space of
+/// its fields. The discriminator is recorded in the IDL. Discriminators of
+/// zero copy accounts must be 8 bytes.
+///
+/// The discriminators of the accounts of a program given
+/// `#[program(discriminator_len = ..)]` are shortened to its first bytes,
+/// along with those derived from a custom name, except for zero copy
+/// accounts. Collisions between the accounts of the crate are reported when
+/// its IDL is generated.
+///
+/// ```ignore
+/// #[account(discriminator = [1])]
+/// pub struct Pool {
+///     pub authority: Pubkey,
+/// }
+///
+/// #[account(discriminator = "Vault")]
+/// pub struct Treasury {
+///     pub authority: Pubkey,
+/// }
+/// ```
+///
+/// # Versioning
+///
+/// The layout of an account can be versioned with the `version` argument,
+/// with the fields added by later versions than the first marked by
+/// `#[since(..)]`, along with their previous name if they were renamed. This
+/// only adds metadata to the IDL, for indexers to decode accounts written with
+/// older layouts, migrating the accounts is up to the program.
+///
+/// ```ignore
+/// #[account(version = 3)]
+/// pub struct Vault {
+///     pub authority: Pubkey,
+///     #[since(2)]
+///     pub fee_bps: u16,
+///     #[since(3, renamed_from = "total")]
+///     pub total_deposits: u64,
+/// }
+/// ```
+///
+/// # Permissions
+///
+/// The signer allowed to change a field can be given with
+/// `#[writable_by = ".."]`, naming the account expected to sign the
+/// instructions writing it. This is recorded in the IDL as the account's
+/// `permissions`, and instructions assigning to the field without that
+/// account as a signer are reported when parsing the IDL. The check is best
+/// effort, only covering assignments made directly by the handlers, and
+/// doesn't constrain the program in any way.
+///
+/// ```ignore
+/// #[account]
+/// pub struct Vault {
+///     pub authority: Pubkey,
+///     #[writable_by = "authority"]
+///     pub fee_bps: u16,
+///     pub total_deposits: u64,
+/// }
+/// ```
+///
+/// # Renamed fields
+///
+/// The former names of a renamed field can be given with
+/// `#[idl_name("..")]`, as written in the IDL, i.e. in camel case. They're
+/// recorded in the IDL as the field's `aliases`, which clients accept in place
+/// of its name, so that code written against the former name keeps working.
+/// The arguments of instruction handlers take the same attribute.
+///
+/// ```ignore
+/// #[account]
+/// pub struct Vault {
+///     pub authority: Pubkey,
+///     #[idl_name("feeRate")]
+///     pub fee_bps: u16,
+/// }
+/// ```
+///
+/// # State machines
+///
+/// The lifecycle of an account can be declared with `#[state_machine(..)]`,
+/// given after `#[account]`, naming the enum field holding the account's state
+/// and the transitions allowed between the variants of the enum. A
+/// `transition` method moving the account to another state is generated,
+/// failing unless the transition is allowed from the current state.
+///
+/// In `#[derive(Accounts)]` structs, `in_state = Open | PartiallyFilled`
+/// requires the account to be in one of the given states for the instruction
+/// to run, and `transition_to = Filled` moves it to the given state before the
+/// handler runs, which must be a unit variant. The machine is recorded in the
+/// IDL as the account's `stateMachine`.
+///
+/// ```ignore
+/// #[account]
+/// #[state_machine(field = status, transitions(Open -> Filled, Open -> Canceled))]
+/// pub struct Order {
+///     pub owner: Pubkey,
+///     pub status: OrderStatus,
+/// }
+///
+/// #[derive(Accounts)]
+/// pub struct Cancel<'info> {
+///     #[account(mut, has_one = owner, in_state = Open, transition_to = Canceled)]
+///     pub order: Account<'info, Order>,
+///     pub owner: Signer<'info>,
+/// }
+/// ```
+///
+/// # Checksums
+///
+/// With the `checksum` argument, the data of the account is followed by the
+/// CRC-32 of its serialized fields, written on serialization and verified by
+/// `try_deserialize`, failing with `AccountChecksumMismatch` when the data was
+/// corrupted, e.g. by a faulty reallocation. The checksum takes
+/// [`CHECKSUM_LEN`](./checksum/constant.CHECKSUM_LEN.html) bytes, to add to
+/// the space of the account, and is recorded in the IDL as its `checksum`.
+/// Accounts being initialized are deserialized unchecked, and lazy loads don't
+/// verify it. Zero copy accounts can't have a checksum.
+///
+/// ```ignore
+/// #[account(checksum)]
+/// #[derive(InitSpace)]
+/// pub struct Treasury {
+///     pub authority: Pubkey,
+///     pub balance: u64,
+/// }
+///
+/// #[derive(Accounts)]
+/// pub struct Initialize<'info> {
+///     #[account(
+///         init,
+///         payer = payer,
+///         space = 8 + Treasury::INIT_SPACE + anchor_lang::checksum::CHECKSUM_LEN,
+///     )]
+///     pub treasury: Account<'info, Treasury>,
+///     ...
+/// }
+/// ```
+///
+/// # Enums
+///
+/// Enums can be accounts as well, serialized as the discriminator followed by
+/// the index of the variant and its fields, e.g. for accounts whose fields
+/// depend on their state. They're listed with their variants in the
+/// `accounts` of the IDL. Enums take the `discriminator` and `checksum`
+/// arguments, but can't be zero copy nor versioned.
+///
+/// ```ignore
+/// #[account]
+/// #[derive(InitSpace)]
+/// pub enum Escrow {
+///     Open { maker: Pubkey, amount: u64 },
+///     Settled { taker: Pubkey },
+///     Canceled,
+/// }
+/// ```
+///
+/// # Lazy deserialization
+///
+/// With the `lazy-account` feature of `anchor-lang` enabled, a `Lazy<Name>`
+/// trait is generated for the account, with a `load_<field>` method per field
+/// deserializing only that field of a
+/// [`LazyAccount`](./accounts/lazy_account/struct.LazyAccount.html), which
+/// saves compute units and stack when only a few fields of a large account
+/// are read. The trait of an enum has a `load_variant` method instead,
+/// deserializing only the index of its variant.
+///
+/// ```ignore
+/// use crate::state::LazyPool;
+///
+/// pub fn swap(ctx: Context<Swap>, amount: u64) -> Result<()> {
+///     let fee_bps = ctx.accounts.pool.load_fee_bps()?;
+///     ...
+/// }
+/// ```
+///
 /// # Zero Copy Deserialization
 ///
 /// **WARNING**: Zero copy deserialization is an experimental feature. It's
@@ -56,84 +248,185 @@ mod id;
 ///
 /// To facilitate this, all fields in an account must be constrained to be
 /// "plain old  data", i.e., they must implement
-/// [`Pod`](https://docs.rs/bytemuck/latest/bytemuck/trait.Pod.html). Please review the
+/// [`Pod`](https://docs.rs/bytemuck/latest/bytemuck/trait.Pod.html), which
+/// the account derives along with `#[zero_copy]`, checking at compile time
+/// that it has no padding bytes. For generic accounts, which can't derive it,
+/// use `#[account(zero_copy(unsafe))]` and review the
 /// [`safety`](https://docs.rs/bytemuck/latest/bytemuck/trait.Pod.html#safety)
-/// section before using.
+/// section.
 #[proc_macro_attribute]
 pub fn account(
     args: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let mut namespace = "".to_string();
-    let mut is_zero_copy = false;
-    let args_str = args.to_string();
-    let args: Vec<&str> = args_str.split(',').collect();
-    if args.len() > 2 {
-        panic!("Only two args are allowed to the account attribute.")
+    let (args, zero_copy) = match anchor_syn::parser::zero_copy::split_account_args(args.into()) {
+        Ok(args) => args,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let is_zero_copy = zero_copy.is_some();
+    let version = match anchor_syn::parser::version::account_version(args.clone()) {
+        Ok(version) => version,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let is_checksummed = match anchor_syn::parser::checksum::account_checksum(args.clone()) {
+        Ok(is_checksummed) => is_checksummed,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let custom_discriminator =
+        match anchor_syn::parser::discriminator::account_discriminator(args.clone()) {
+            Ok(discriminator) => discriminator,
+            Err(err) => return err.to_compile_error().into(),
+        };
+    if let Err(err) = anchor_syn::parser::discriminator::reject_account_len(args.clone()) {
+        return err.to_compile_error().into();
     }
-    for arg in args {
-        let ns = arg
-            .to_string()
-            .replace('\"', "")
-            .chars()
-            .filter(|c| !c.is_whitespace())
-            .collect();
-        if ns == "zero_copy" {
-            is_zero_copy = true;
-        } else {
-            namespace = ns;
+    let namespace = match anchor_syn::parser::discriminator::account_namespace(args.clone()) {
+        Ok(namespace) => namespace,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    // Custom discriminator bytes keep their length, others take the one of
+    // the program, see `discriminator_len`.
+    let is_custom_bytes = matches!(
+        custom_discriminator,
+        Some(anchor_syn::parser::discriminator::AccountDiscriminator::Bytes(_))
+    );
+
+    let mut account_strct = match parse_macro_input!(input as syn::Item) {
+        syn::Item::Struct(strct) => strct,
+        syn::Item::Enum(enm) => {
+            let discriminator_bytes = anchor_syn::parser::discriminator::account_bytes(
+                &enm.ident,
+                &namespace,
+                custom_discriminator.as_ref(),
+                anchor_syn::parser::discriminator::MAX_LEN,
+            );
+            let discriminator_len = discriminator_len_expr(&discriminator_bytes, !is_custom_bytes);
+            #[cfg(feature = "idl-build")]
+            let idl_build = {
+                let idl_build_impl = anchor_syn::idl::build::gen_idl_build_impl_enum(&enm);
+                let idl_print = anchor_syn::idl::build::gen_idl_print_account_enum(
+                    &enm,
+                    custom_discriminator.is_some(),
+                    is_checksummed,
+                );
+                quote! {
+                    #idl_build_impl
+                    #idl_print
+                }
+            };
+            #[cfg(not(feature = "idl-build"))]
+            let idl_build = quote! {};
+            return account_enum(
+                enm,
+                &namespace,
+                &discriminator_bytes,
+                &discriminator_len,
+                is_zero_copy,
+                version,
+                is_checksummed,
+                idl_build,
+            )
+            .unwrap_or_else(|err| err.to_compile_error())
+            .into();
+        }
+        item => {
+            return syn::Error::new_spanned(item, "#[account] must be on a struct or an enum")
+                .to_compile_error()
+                .into()
         }
+    };
+    let discriminator_bytes = anchor_syn::parser::discriminator::account_bytes(
+        &account_strct.ident,
+        &namespace,
+        custom_discriminator.as_ref(),
+        anchor_syn::parser::discriminator::MAX_LEN,
+    );
+    // Zero copy accounts keep 8 bytes.
+    let discriminator_len =
+        discriminator_len_expr(&discriminator_bytes, !is_custom_bytes && !is_zero_copy);
+    if is_zero_copy && discriminator_bytes.len() != 8 {
+        return syn::Error::new(
+            account_strct.ident.span(),
+            "Discriminators of zero copy accounts must be 8 bytes",
+        )
+        .to_compile_error()
+        .into();
     }
+    if is_zero_copy && is_checksummed {
+        return syn::Error::new(
+            account_strct.ident.span(),
+            "Zero copy accounts can't have a checksum",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let discriminator: proc_macro2::TokenStream =
+        format!("{:?}", discriminator_bytes).parse().unwrap();
 
-    let account_strct = parse_macro_input!(input as syn::ItemStruct);
-    let account_name = &account_strct.ident;
-    let account_name_str = account_name.to_string();
-    let (impl_gen, type_gen, where_clause) = account_strct.generics.split_for_impl();
-
-    let discriminator: proc_macro2::TokenStream = {
-        // Namespace the discriminator to prevent collisions.
-        let discriminator_preimage = {
-            // For now, zero copy accounts can't be namespaced.
-            if namespace.is_empty() {
-                format!("account:{}", account_name)
-            } else {
-                format!("{}:{}", namespace, account_name)
-            }
+    // Zero copy accounts implement `IdlBuild` through `#[zero_copy]`.
+    #[cfg(feature = "idl-build")]
+    let idl_build = {
+        let idl_build_impl = match is_zero_copy {
+            true => quote! {},
+            false => anchor_syn::idl::build::gen_idl_build_impl_struct(&account_strct, None),
         };
-
-        let mut discriminator = [0u8; 8];
-        discriminator.copy_from_slice(
-            &anchor_syn::hash::hash(discriminator_preimage.as_bytes()).to_bytes()[..8],
+        let idl_print = anchor_syn::idl::build::gen_idl_print_account(
+            &account_strct,
+            version,
+            custom_discriminator.is_some(),
+            is_checksummed,
         );
-        format!("{:?}", discriminator).parse().unwrap()
+        quote! {
+            #idl_build_impl
+            #idl_print
+        }
     };
-
-    let owner_impl = {
-        if namespace.is_empty() {
-            quote! {
-                #[automatically_derived]
-                impl #impl_gen anchor_lang::Owner for #account_name #type_gen #where_clause {
-                    fn owner() -> Pubkey {
-                        crate::ID
-                    }
-                }
-            }
-        } else {
-            quote! {}
+    #[cfg(not(feature = "idl-build"))]
+    let idl_build = quote! {};
+    if let Err(err) = take_since(&mut account_strct, version) {
+        return err.to_compile_error().into();
+    }
+    if let Err(err) = take_writable_by(&mut account_strct) {
+        return err.to_compile_error().into();
+    }
+    if let Err(err) = take_idl_names(&mut account_strct) {
+        return err.to_compile_error().into();
+    }
+    let state_machine_impl = match anchor_syn::codegen::state_machine::generate(&mut account_strct)
+    {
+        Ok(state_machine_impl) => state_machine_impl,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    if cfg!(feature = "serde") {
+        anchor_syn::codegen::serde::add_derives(&mut account_strct);
+    }
+    let arbitrary_impl = match anchor_syn::codegen::arbitrary::generate(&mut account_strct) {
+        arbitrary_impl if cfg!(feature = "arbitrary") => arbitrary_impl,
+        _ => quote! {},
+    };
+    let lazy_account_impl = match is_zero_copy {
+        false if cfg!(feature = "lazy-account") => {
+            anchor_syn::codegen::lazy_account::generate(&account_strct, &discriminator_len)
         }
+        _ => quote! {},
     };
+    let account_name = &account_strct.ident;
+    let account_name_str = account_name.to_string();
+    let (impl_gen, type_gen, where_clause) = account_strct.generics.split_for_impl();
+    let owner_impl = owner_impl(account_name, &account_strct.generics, &namespace);
 
     proc_macro::TokenStream::from({
         if is_zero_copy {
+            let zero_copy_attr = match zero_copy {
+                Some(anchor_syn::parser::zero_copy::ZeroCopy::Unsafe) => {
+                    quote! { #[zero_copy(unsafe)] }
+                }
+                _ => quote! { #[zero_copy] },
+            };
             quote! {
-                #[zero_copy]
+                #zero_copy_attr
                 #account_strct
 
-                #[automatically_derived]
-                unsafe impl #impl_gen anchor_lang::__private::bytemuck::Pod for #account_name #type_gen #where_clause {}
-                #[automatically_derived]
-                unsafe impl #impl_gen anchor_lang::__private::bytemuck::Zeroable for #account_name #type_gen #where_clause {}
-
                 #[automatically_derived]
                 impl #impl_gen anchor_lang::ZeroCopy for #account_name #type_gen #where_clause {}
 
@@ -169,57 +462,244 @@ pub fn account(
                 }
 
                 #owner_impl
+
+                #state_machine_impl
+
+                #arbitrary_impl
+
+                #idl_build
             }
         } else {
+            let account_impls = borsh_account_impls(
+                account_name,
+                &account_strct.generics,
+                &discriminator_bytes,
+                &discriminator_len,
+                is_checksummed,
+            );
             quote! {
                 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
                 #account_strct
 
-                #[automatically_derived]
-                impl #impl_gen anchor_lang::AccountSerialize for #account_name #type_gen #where_clause {
-                    fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {
-                        if writer.write_all(&#discriminator).is_err() {
-                            return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
-                        }
+                #account_impls
 
-                        if AnchorSerialize::serialize(self, writer).is_err() {
-                            return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
-                        }
-                        Ok(())
-                    }
+                #owner_impl
+
+                #state_machine_impl
+
+                #arbitrary_impl
+
+                #lazy_account_impl
+
+                #idl_build
+            }
+        }
+    })
+}
+
+// `#[account]` on an enum, serialized with borsh as the index of the variant
+// followed by its fields, e.g. for the states of a state machine. Only the
+// discriminator and checksum arguments apply.
+#[allow(clippy::too_many_arguments)]
+fn account_enum(
+    mut account_enum: syn::ItemEnum,
+    namespace: &str,
+    discriminator_bytes: &[u8],
+    discriminator_len: &proc_macro2::TokenStream,
+    is_zero_copy: bool,
+    version: Option<u32>,
+    is_checksummed: bool,
+    idl_build: proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    if is_zero_copy {
+        return Err(syn::Error::new_spanned(
+            &account_enum.ident,
+            "Zero copy accounts must be structs",
+        ));
+    }
+    if version.is_some() {
+        return Err(syn::Error::new_spanned(
+            &account_enum.ident,
+            "Versioned accounts must be structs",
+        ));
+    }
+
+    if cfg!(feature = "serde") {
+        anchor_syn::codegen::serde::add_enum_derives(&mut account_enum);
+    }
+    let arbitrary_impl = match anchor_syn::codegen::arbitrary::generate_enum(&mut account_enum) {
+        arbitrary_impl if cfg!(feature = "arbitrary") => arbitrary_impl,
+        _ => quote! {},
+    };
+    let lazy_account_impl = match cfg!(feature = "lazy-account") {
+        true => anchor_syn::codegen::lazy_account::generate_enum(&account_enum, discriminator_len),
+        false => quote! {},
+    };
+    let account_name = &account_enum.ident;
+    let account_impls = borsh_account_impls(
+        account_name,
+        &account_enum.generics,
+        discriminator_bytes,
+        discriminator_len,
+        is_checksummed,
+    );
+    let owner_impl = owner_impl(account_name, &account_enum.generics, namespace);
+    Ok(quote! {
+        #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+        #account_enum
+
+        #account_impls
+
+        #owner_impl
+
+        #arbitrary_impl
+
+        #lazy_account_impl
+
+        #idl_build
+    })
+}
+
+// Accounts of the program, i.e. without a namespace, are owned by it.
+fn owner_impl(
+    account_name: &syn::Ident,
+    generics: &syn::Generics,
+    namespace: &str,
+) -> proc_macro2::TokenStream {
+    let (impl_gen, type_gen, where_clause) = generics.split_for_impl();
+    if namespace.is_empty() {
+        quote! {
+            #[automatically_derived]
+            impl #impl_gen anchor_lang::Owner for #account_name #type_gen #where_clause {
+                fn owner() -> Pubkey {
+                    crate::ID
                 }
+            }
+        }
+    } else {
+        quote! {}
+    }
+}
 
-                #[automatically_derived]
-                impl #impl_gen anchor_lang::AccountDeserialize for #account_name #type_gen #where_clause {
-                    fn try_deserialize(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
-                        if buf.len() < #discriminator.len() {
-                            return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorNotFound.into());
-                        }
-                        let given_disc = &buf[..8];
-                        if &#discriminator != given_disc {
-                            return Err(anchor_lang::error!(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch).with_account_name(#account_name_str));
-                        }
-                        Self::try_deserialize_unchecked(buf)
-                    }
+// The length of the discriminator of an account, either that of its bytes, or
+// the one declared for the accounts of the crate, at its root, which the
+// account inherits.
+fn discriminator_len_expr(discriminator_bytes: &[u8], inherits: bool) -> proc_macro2::TokenStream {
+    match inherits {
+        true => {
+            let len_const =
+                quote::format_ident!("{}", anchor_syn::parser::discriminator::LEN_CONST);
+            quote! { crate::#len_const }
+        }
+        false => {
+            let len = discriminator_bytes.len();
+            quote! { #len }
+        }
+    }
+}
 
-                    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
-                        let mut data: &[u8] = &buf[8..];
-                        AnchorDeserialize::deserialize(&mut data)
-                            .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
-                    }
+// The `AccountSerialize`, `AccountDeserialize` and `Discriminator`
+// implementations of a borsh serialized account, given the bytes of its
+// discriminator and the number of them the data starts with.
+fn borsh_account_impls(
+    account_name: &syn::Ident,
+    generics: &syn::Generics,
+    discriminator_bytes: &[u8],
+    discriminator_len: &proc_macro2::TokenStream,
+    is_checksummed: bool,
+) -> proc_macro2::TokenStream {
+    let account_name_str = account_name.to_string();
+    let (impl_gen, type_gen, where_clause) = generics.split_for_impl();
+    // `Discriminator::discriminator` is 8 bytes, shorter custom
+    // discriminators are padded with zeros.
+    let padded_discriminator: proc_macro2::TokenStream = {
+        let mut padded = [0u8; 8];
+        padded[..discriminator_bytes.len()].copy_from_slice(discriminator_bytes);
+        format!("{:?}", padded).parse().unwrap()
+    };
+
+    // Checksummed accounts serialize their fields first, to hash them, and
+    // check the checksum following them when deserialized.
+    let (serialize_fields, deserialize_checked) = match is_checksummed {
+        true => (
+            quote! {
+                let data = AnchorSerialize::try_to_vec(self)
+                    .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotSerialize)?;
+                if writer.write_all(&data).is_err()
+                    || writer.write_all(&anchor_lang::checksum::checksum(&data)).is_err()
+                {
+                    return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+                }
+            },
+            quote! {
+                let data: &[u8] = &buf[#discriminator_len..];
+                let mut fields = data;
+                let account: Self = AnchorDeserialize::deserialize(&mut fields)
+                    .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize)?;
+                let len = data.len() - fields.len();
+                let checksum = data.get(len..len + anchor_lang::checksum::CHECKSUM_LEN);
+                if checksum != Some(&anchor_lang::checksum::checksum(&data[..len])[..]) {
+                    return Err(anchor_lang::error!(anchor_lang::error::ErrorCode::AccountChecksumMismatch).with_account_name(#account_name_str));
+                }
+                Ok(account)
+            },
+        ),
+        false => (
+            quote! {
+                if AnchorSerialize::serialize(self, writer).is_err() {
+                    return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+                }
+            },
+            quote! {
+                Self::try_deserialize_unchecked(buf)
+            },
+        ),
+    };
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_gen anchor_lang::AccountSerialize for #account_name #type_gen #where_clause {
+            fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {
+                if writer.write_all(&#padded_discriminator[..#discriminator_len]).is_err() {
+                    return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
                 }
 
-                #[automatically_derived]
-                impl #impl_gen anchor_lang::Discriminator for #account_name #type_gen #where_clause {
-                    fn discriminator() -> [u8; 8] {
-                        #discriminator
-                    }
+                #serialize_fields
+                Ok(())
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_gen anchor_lang::AccountDeserialize for #account_name #type_gen #where_clause {
+            fn try_deserialize(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+                if buf.len() < #discriminator_len {
+                    return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorNotFound.into());
                 }
+                let given_disc = &buf[..#discriminator_len];
+                if &#padded_discriminator[..#discriminator_len] != given_disc {
+                    return Err(anchor_lang::error!(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch).with_account_name(#account_name_str));
+                }
+                #deserialize_checked
+            }
 
-                #owner_impl
+            fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+                let mut data: &[u8] = &buf[#discriminator_len..];
+                AnchorDeserialize::deserialize(&mut data)
+                    .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
             }
         }
-    })
+
+        #[automatically_derived]
+        impl #impl_gen anchor_lang::Discriminator for #account_name #type_gen #where_clause {
+            fn discriminator() -> [u8; 8] {
+                #padded_discriminator
+            }
+
+            fn discriminator_len() -> usize {
+                #discriminator_len
+            }
+        }
+    }
 }
 
 #[proc_macro_derive(ZeroCopyAccessor, attributes(accessor))]
@@ -283,16 +763,38 @@ pub fn derive_zero_copy_accessor(item: proc_macro::TokenStream) -> proc_macro::T
 /// This is just a convenient alias for
 ///
 /// ```ignore
-/// #[derive(Copy, Clone)]
-/// #[repr(packed)]
+/// #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+/// #[repr(C)]
 /// struct MyStruct {...}
 /// ```
+///
+/// Deriving [`Pod`](https://docs.rs/bytemuck/latest/bytemuck/trait.Pod.html)
+/// fails to compile if the struct has padding bytes, e.g. a `u8` field
+/// followed by a `u64` one, or fields that aren't `Pod` themselves, so that
+/// reinterpreting account data as the struct is sound. The derives refer to
+/// the `bytemuck` crate, which programs must depend on.
+///
+/// Generic structs, which can't derive `Pod`, are given
+/// `#[zero_copy(unsafe)]`, implementing the traits without these checks. It's
+/// up to the program to uphold their
+/// [`safety`](https://docs.rs/bytemuck/latest/bytemuck/trait.Pod.html#safety)
+/// requirements then. Either way, the struct is recorded in the IDL with the
+/// `serialization` of its data, for clients to decode it accordingly.
 #[proc_macro_attribute]
 pub fn zero_copy(
-    _args: proc_macro::TokenStream,
+    args: proc_macro::TokenStream,
     item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
+    let zero_copy = match anchor_syn::parser::zero_copy::parse(args.into()) {
+        Ok(zero_copy) => zero_copy,
+        Err(err) => return err.to_compile_error().into(),
+    };
     let account_strct = parse_macro_input!(item as syn::ItemStruct);
+    #[cfg(feature = "idl-build")]
+    let idl_build_impl =
+        anchor_syn::idl::build::gen_idl_build_impl_struct(&account_strct, Some(zero_copy));
+    #[cfg(not(feature = "idl-build"))]
+    let idl_build_impl = quote! {};
 
     // Takes the first repr. It's assumed that more than one are not on the
     // struct.
@@ -306,10 +808,37 @@ pub fn zero_copy(
         None => quote! {#[repr(C)]},
     };
 
+    let (pod_derive, pod_impl) = match zero_copy {
+        anchor_syn::parser::zero_copy::ZeroCopy::Safe => (
+            quote! {
+                #[derive(anchor_lang::__private::bytemuck::Pod, anchor_lang::__private::bytemuck::Zeroable)]
+            },
+            quote! {},
+        ),
+        anchor_syn::parser::zero_copy::ZeroCopy::Unsafe => {
+            let ident = &account_strct.ident;
+            let (impl_gen, type_gen, where_clause) = account_strct.generics.split_for_impl();
+            (
+                quote! {},
+                quote! {
+                    #[automatically_derived]
+                    unsafe impl #impl_gen anchor_lang::__private::bytemuck::Pod for #ident #type_gen #where_clause {}
+                    #[automatically_derived]
+                    unsafe impl #impl_gen anchor_lang::__private::bytemuck::Zeroable for #ident #type_gen #where_clause {}
+                },
+            )
+        }
+    };
+
     proc_macro::TokenStream::from(quote! {
         #[derive(anchor_lang::__private::ZeroCopyAccessor, Copy, Clone)]
         #repr
+        #pod_derive
         #account_strct
+
+        #pod_impl
+
+        #idl_build_impl
     })
 }
 
@@ -320,3 +849,62 @@ pub fn declare_id(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let id = parse_macro_input!(input as id::Id);
     proc_macro::TokenStream::from(quote! {#id})
 }
+
+// Removes the `#[since(..)]` attributes of the fields, which are only read by
+// the IDL, checking them against the account's version.
+fn take_since(strct: &mut syn::ItemStruct, version: Option<u32>) -> syn::Result<()> {
+    if version == Some(0) {
+        return Err(syn::Error::new_spanned(
+            &strct.ident,
+            "Account versions start at 1",
+        ));
+    }
+    for field in strct.fields.iter_mut() {
+        let since = match anchor_syn::parser::version::since(&field.attrs)? {
+            Some(since) => since,
+            None => continue,
+        };
+        match version {
+            None => {
+                return Err(syn::Error::new_spanned(
+                    &field,
+                    "#[since] requires a versioned account, e.g. #[account(version = 2)]",
+                ))
+            }
+            Some(version) if since.version < 2 || since.version > version => {
+                return Err(syn::Error::new_spanned(
+                    &field,
+                    format!(
+                        "#[since] must be a version between 2 and the account's version, {}",
+                        version
+                    ),
+                ))
+            }
+            _ => (),
+        }
+        field.attrs.retain(|attr| !attr.path.is_ident("since"));
+    }
+    Ok(())
+}
+
+// Removes the `#[writable_by = ".."]` attributes of the fields, which are only
+// read by the IDL.
+fn take_writable_by(strct: &mut syn::ItemStruct) -> syn::Result<()> {
+    for field in strct.fields.iter_mut() {
+        anchor_syn::parser::permissions::writable_by(&field.attrs)?;
+        field
+            .attrs
+            .retain(|attr| !attr.path.is_ident("writable_by"));
+    }
+    Ok(())
+}
+
+// Removes the `#[idl_name(..)]` attributes of the fields, recorded in the IDL
+// as their aliases.
+fn take_idl_names(strct: &mut syn::ItemStruct) -> syn::Result<()> {
+    for field in strct.fields.iter_mut() {
+        anchor_syn::parser::idl_name::parse(&field.attrs)?;
+        anchor_syn::parser::idl_name::strip(&mut field.attrs);
+    }
+    Ok(())
+}
@@ -2,10 +2,34 @@ extern crate proc_macro;
 
 /// A marker attribute used to mark const values that should be included in the
 /// generated IDL but functionally does nothing.
+///
+/// Only public constants are included, in whichever module they're defined,
+/// and `#[constant(skip_idl)]` leaves a constant out of the IDL.
+///
+/// With the `idl-build` feature, a test printing the constant's IDL is
+/// generated as well.
 #[proc_macro_attribute]
 pub fn constant(
-    _attr: proc_macro::TokenStream,
+    attr: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    input
+    match anchor_syn::parser::constant::parse_args(attr.into()) {
+        Err(err) => err.to_compile_error().into(),
+        #[cfg(feature = "idl-build")]
+        Ok(args) if !args.skip_idl => with_idl_print(input),
+        Ok(_) => input,
+    }
+}
+
+#[cfg(feature = "idl-build")]
+fn with_idl_print(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item: proc_macro2::TokenStream = input.into();
+    let idl_print = match syn::parse2::<syn::ItemConst>(item.clone()) {
+        Ok(item_const) => anchor_syn::idl::build::gen_idl_print_constant(&item_const),
+        Err(_) => proc_macro2::TokenStream::new(),
+    };
+    proc_macro::TokenStream::from(quote::quote! {
+        #item
+        #idl_print
+    })
 }
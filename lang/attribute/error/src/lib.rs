@@ -59,8 +59,16 @@ pub fn error_code(
         false => Some(parse_macro_input!(args as ErrorArgs)),
     };
     let mut error_enum = parse_macro_input!(input as syn::ItemEnum);
-    let error = codegen::error::generate(error_parser::parse(&mut error_enum, args));
-    proc_macro::TokenStream::from(error)
+    let error = error_parser::parse(&mut error_enum, args);
+    #[cfg(feature = "idl-build")]
+    let idl_print = anchor_syn::idl::build::gen_idl_print_errors(&error);
+    #[cfg(not(feature = "idl-build"))]
+    let idl_print = quote! {};
+    let error = codegen::error::generate(error);
+    proc_macro::TokenStream::from(quote! {
+        #error
+        #idl_print
+    })
 }
 
 /// Generates an [`Error::AnchorError`](../../anchor_lang/error/enum.Error.html) that includes file and line information.
@@ -8,15 +8,44 @@ use syn::parse_macro_input;
 /// their programs that clients can subscribe to. Currently, this macro is for
 /// structs only.
 ///
+/// With the `serde` feature of `anchor-lang` enabled, `Serialize` and
+/// `Deserialize` are derived as well, with public keys represented as base58
+/// strings and bytes as base64 strings. With the `arbitrary` feature enabled,
+/// `arbitrary::Arbitrary` is implemented, like for `#[account]`.
+///
+/// With the `no-entrypoint` feature of the program crate, the event is
+/// registered for the `decode_event` generated by `#[program]`, decoding the
+/// events of the program off-chain.
+///
+/// With the `idl-build` feature, a test printing the event's IDL is generated
+/// as well, for `anchor build` to generate the IDL from the compiled program.
+///
 /// See the [`emit!` macro](emit!) for an example.
 #[proc_macro_attribute]
 pub fn event(
     _args: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let event_strct = parse_macro_input!(input as syn::ItemStruct);
+    let mut event_strct = parse_macro_input!(input as syn::ItemStruct);
+    for field in &event_strct.fields {
+        if let Err(err) = anchor_syn::parser::event::index(&field.attrs) {
+            return err.to_compile_error().into();
+        }
+    }
+    #[cfg(feature = "idl-build")]
+    let idl_print = anchor_syn::idl::build::gen_idl_print_event(&event_strct);
+    #[cfg(not(feature = "idl-build"))]
+    let idl_print = quote! {};
+    if cfg!(feature = "serde") {
+        anchor_syn::codegen::serde::add_derives(&mut event_strct);
+    }
+    let arbitrary_impl = match anchor_syn::codegen::arbitrary::generate(&mut event_strct) {
+        arbitrary_impl if cfg!(feature = "arbitrary") => arbitrary_impl,
+        _ => quote! {},
+    };
 
     let event_name = &event_strct.ident;
+    let event_name_str = event_name.to_string();
 
     let discriminator: proc_macro2::TokenStream = {
         let discriminator_preimage = format!("event:{}", event_name);
@@ -44,6 +73,20 @@ pub fn event(
                 #discriminator
             }
         }
+
+        #[cfg(all(feature = "no-entrypoint", not(target_arch = "bpf")))]
+        anchor_lang::__private::inventory::submit! {
+            anchor_lang::event::EventDecoder {
+                program: env!("CARGO_PKG_NAME"),
+                name: #event_name_str,
+                discriminator: #discriminator,
+                decode: anchor_lang::event::decode_boxed::<#event_name>,
+            }
+        }
+
+        #arbitrary_impl
+
+        #idl_print
     })
 }
 
@@ -83,9 +126,146 @@ pub fn emit(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     })
 }
 
+/// Emits an event with a self CPI, i.e. in the data of an instruction the
+/// program invokes itself with, rather than in its logs, which RPC nodes may
+/// truncate. Indexers read the event from the inner instructions of the
+/// transaction, where it's the data of an instruction to the program
+/// starting with `anchor_lang::event::EVENT_IX_TAG_LE`, followed by the
+/// event's discriminator and its borsh encoding. The instruction does
+/// nothing else.
+///
+/// The `Accounts` struct of the instruction must be marked with
+/// [`#[event_cpi]`](event_cpi), and `ctx` must be in scope. Requires the
+/// `event-cpi` feature of `anchor-lang`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use anchor_lang::prelude::*;
+///
+/// // handler function inside #[program]
+/// pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+///     emit_cpi!(MyEvent { data: 5 });
+///     Ok(())
+/// }
+///
+/// #[event_cpi]
+/// #[derive(Accounts)]
+/// pub struct Initialize<'info> {
+///     pub signer: Signer<'info>,
+/// }
+///
+/// #[event]
+/// pub struct MyEvent {
+///     pub data: u64,
+/// }
+/// ```
+#[cfg(feature = "event-cpi")]
+#[proc_macro]
+pub fn emit_cpi(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let data: proc_macro2::TokenStream = input.into();
+    proc_macro::TokenStream::from(quote! {
+        {
+            let authority_info = ctx.accounts.event_authority.to_account_info();
+            let authority_bump = *ctx
+                .bumps
+                .get("event_authority")
+                .expect("The accounts must be marked with #[event_cpi]");
+            let ix_data: Vec<u8> = anchor_lang::event::EVENT_IX_TAG_LE
+                .iter()
+                .copied()
+                .chain(anchor_lang::Event::data(&#data))
+                .collect();
+            let ix = anchor_lang::solana_program::instruction::Instruction::new_with_bytes(
+                crate::ID,
+                &ix_data,
+                vec![anchor_lang::solana_program::instruction::AccountMeta::new_readonly(
+                    *authority_info.key,
+                    true,
+                )],
+            );
+            anchor_lang::solana_program::program::invoke_signed(
+                &ix,
+                &[authority_info],
+                &[&[anchor_lang::event::EVENT_AUTHORITY_SEED, &[authority_bump]]],
+            )
+            .map_err(anchor_lang::error::Error::from)?;
+        }
+    })
+}
+
+/// Adds the accounts [`emit_cpi!`](emit_cpi) needs to an `Accounts` struct,
+/// i.e. `event_authority`, the PDA of the program with the seed
+/// `__event_authority` signing the self CPI, and `program`, the program
+/// itself. Clients pass them as any other account, and they're in the IDL.
+///
+/// Must be placed before `#[derive(Accounts)]`. Requires the `event-cpi`
+/// feature of `anchor-lang`.
+#[cfg(feature = "event-cpi")]
+#[proc_macro_attribute]
+pub fn event_cpi(
+    _args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let mut accounts_strct = parse_macro_input!(input as syn::ItemStruct);
+    if let Err(err) =
+        anchor_syn::parser::accounts::event_cpi::add_event_cpi_accounts(&mut accounts_strct)
+    {
+        return err.to_compile_error().into();
+    }
+    proc_macro::TokenStream::from(quote! {
+        #accounts_strct
+    })
+}
+
+/// A marker attribute describing a payload defined outside of the program and
+/// consumed by its instructions, e.g. the payload of a Wormhole VAA, in the
+/// `externalMessages` of the IDL, so that integrators don't have to read how
+/// the program parses it. Functionally does nothing, verifying the message is
+/// up to the program.
+///
+/// All arguments are optional:
+///
+/// - `format`: the format of the message carrying the payload.
+/// - `emitter` and `emitter_chain`: the sender the program expects messages
+///   from.
+/// - `account`: the account of the instructions holding the verified message,
+///   and `owner`, the program it must be owned by.
+/// - `instructions`: the instructions consuming the message, which must have
+///   the `account`.
+///
+/// The layout of the payload is described by the fields of the struct.
+///
+/// # Example
+///
+/// ```ignore
+/// #[external_message(
+///     format = "wormhole_vaa",
+///     emitter_chain = 2,
+///     emitter = "0x3ee18b2214aff97000d974cf647e7c347e8fa585",
+///     account = posted_vaa,
+///     owner = "worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth",
+///     instructions = [complete_transfer],
+/// )]
+/// pub struct TransferPayload {
+///     pub amount: u64,
+///     pub recipient: Pubkey,
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn external_message(
+    args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    match anchor_syn::parser::external_message::parse_args(args.into()) {
+        Ok(_) => input,
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
 // EventIndex is a marker macro. It functionally does nothing other than
-// allow one to mark fields with the `#[index]` inert attribute, which is
-// used to add metadata to IDLs.
+// allow one to mark fields with the `#[index]` or `#[index(unique)]` inert
+// attributes, which are used to add metadata to IDLs.
 #[proc_macro_derive(EventIndex, attributes(index))]
 pub fn derive_event(_item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     proc_macro::TokenStream::from(quote! {})
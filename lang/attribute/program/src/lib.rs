@@ -1,16 +1,196 @@
 extern crate proc_macro;
 
+use anchor_syn::{Program, ProgramArgs};
 use quote::ToTokens;
 use syn::parse_macro_input;
 
 /// The `#[program]` attribute defines the module containing all instruction
 /// handlers defining all entries into a Solana program.
+///
+/// Optionally, a `prelude` macro can be given, which every instruction data
+/// type generated by the attribute (i.e. the types in the `instruction`
+/// module) is passed through. This allows adding derives or impls to generated code, for
+/// example, for off-chain use.
+///
+/// ```ignore
+/// #[macro_export]
+/// macro_rules! my_prelude {
+///     ($item:item) => {
+///         #[derive(Debug, serde::Serialize)]
+///         $item
+///     };
+/// }
+///
+/// #[program(prelude = crate::my_prelude)]
+/// pub mod my_program {
+///     // ...
+/// }
+/// ```
+///
+/// The arguments of the handlers can be constrained with `#[arg(..)]`:
+/// `range = <min>..=<max>` for integers, with literal bounds, `non_zero`, and
+/// `max_len = <len>` for strings, in bytes, and vectors. The constraints are
+/// checked once the instruction data is deserialized, before the accounts and
+/// the handler, failing the instruction with `InstructionArgOutOfRange`,
+/// `InstructionArgZero` or `InstructionArgTooLong`, and are recorded in the
+/// IDL as the `constraints` of the arguments, which the TypeScript client
+/// checks before sending the instruction.
+///
+/// ```ignore
+/// pub fn deposit(
+///     ctx: Context<Deposit>,
+///     #[arg(non_zero)] amount: u64,
+///     #[arg(range = 1..=10_000)] fee_bps: u16,
+///     #[arg(max_len = 32)] memo: String,
+/// ) -> Result<()> {
+///     ...
+/// }
+/// ```
+///
+/// The discriminator of an instruction is the sighash of its name under the
+/// `global` namespace, i.e. `sha256("global:<name>")[..8]`, unless another
+/// namespace is given with `#[instruction(namespace = "..")]`, e.g. to
+/// implement the instructions of an interface whose names collide with the
+/// program's. The namespace is recorded in the IDL, for clients to compute
+/// the same discriminator. `state` is reserved for the methods of the state.
+///
+/// ```ignore
+/// #[instruction(namespace = "admin")]
+/// pub fn set_fee(ctx: Context<SetFee>, fee_bps: u16) -> Result<()> {
+///     ...
+/// }
+/// ```
+///
+/// With `discriminator_len = <len>`, the discriminators of the instructions,
+/// and those of all the `#[account]`s of the crate, are shortened to their
+/// first bytes, from 1 to 8, saving bytes in every instruction and account at
+/// the cost of a higher chance of collisions. The shortened discriminators
+/// are recorded in the IDL. Instructions with colliding discriminators fail to
+/// compile, as do those whose data could start with the tag of the IDL
+/// instructions or of `emit_cpi!`, and colliding accounts fail the generation
+/// of the IDL.
+///
+/// ```ignore
+/// #[program(discriminator_len = 4)]
+/// pub mod my_program {
+///     // ...
+/// }
+/// ```
+///
+/// The former names of a renamed argument can be given with
+/// `#[idl_name("..")]`, in camel case. They're recorded in the IDL as its
+/// `aliases`, which clients accept in place of its name.
+///
+/// ```ignore
+/// pub fn set_fee(ctx: Context<SetFee>, #[idl_name("fee")] fee_bps: u16) -> Result<()> {
+///     ...
+/// }
+/// ```
+///
+/// With `checked_math`, the `+`, `-`, `*` and `/` operations of the handlers,
+/// and their compound assignments, are rewritten into checked operations,
+/// failing the instruction with `ArithmeticOverflow` on overflow or division
+/// by zero. Only the operations on two integers of the same primitive type
+/// are checked, others, e.g. `a + &b` or `String + &str`, are left as is, as
+/// are closures and items nested in the handlers. The arguments of macro
+/// invocations, e.g. `msg!("{}", a + b)`, are rewritten if they're
+/// expressions.
+///
+/// ```ignore
+/// #[program(checked_math)]
+/// pub mod my_program {
+///     pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+///         // Fails with `ArithmeticOverflow` instead of wrapping.
+///         ctx.accounts.vault.amount += amount;
+///         Ok(())
+///     }
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn program(
-    _args: proc_macro::TokenStream,
+    args: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    parse_macro_input!(input as anchor_syn::Program)
-        .to_token_stream()
+    let args = match args.is_empty() {
+        true => None,
+        false => Some(parse_macro_input!(args as ProgramArgs)),
+    };
+    let mut program = parse_macro_input!(input as Program);
+    program.discriminator_len = args
+        .as_ref()
+        .and_then(|args| args.discriminator_len)
+        .unwrap_or(anchor_syn::parser::discriminator::MAX_LEN);
+    program.args = args;
+    #[cfg(feature = "idl-build")]
+    anchor_syn::idl::build::add_idl_print_program(&mut program);
+    program.to_token_stream().into()
+}
+
+/// Declares a module for the program described by an IDL, for programs to
+/// depend on another program without depending on its crate, or even having
+/// its source.
+///
+/// The path of the IDL is relative to the root of the crate, i.e. the
+/// directory of its `Cargo.toml`, and the module is named after the program.
+/// Like `declare_id!`, it must be invoked at the root of the crate.
+///
+/// The module holds what the `cpi` feature of the program's crate provides:
+/// the constants, types, accounts and events of the program at its root, the
+/// data of its instructions in `instruction`, its CPI functions in `cpi`, with
+/// their accounts in `cpi::accounts`, its `ErrorCode`, and the decoding of its
+/// events in `events`.
+///
+/// ```ignore
+/// declare_program!("idls/vault.json");
+///
+/// pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+///     let cpi_ctx = CpiContext::new(
+///         ctx.accounts.vault_program.to_account_info(),
+///         vault::cpi::accounts::Deposit {
+///             vault: ctx.accounts.vault.to_account_info(),
+///             authority: ctx.accounts.authority.to_account_info(),
+///         },
+///     );
+///     vault::cpi::deposit(cpi_ctx, amount)?;
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "declare-program")]
+#[proc_macro]
+pub fn declare_program(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let path = parse_macro_input!(input as syn::LitStr);
+    declare_program_module(&path)
+        .unwrap_or_else(|e| e.to_compile_error())
         .into()
 }
+
+#[cfg(feature = "declare-program")]
+fn declare_program_module(path: &syn::LitStr) -> syn::Result<proc_macro2::TokenStream> {
+    use anchor_syn::idl::codegen::rust_cpi;
+    use heck::SnakeCase;
+    use quote::{format_ident, quote};
+
+    let error = |e: anyhow::Error| syn::Error::new(path.span(), e.to_string());
+    let dir = std::env::var("CARGO_MANIFEST_DIR").map_err(|e| error(e.into()))?;
+    let file = std::path::Path::new(&dir).join(path.value());
+    let idl = anchor_syn::idl::io::read(&file).map_err(error)?;
+    let name = format_ident!("{}", idl.name.to_snake_case());
+    let items = rust_cpi::module(&idl, None, &quote! { crate::#name }).map_err(error)?;
+    // Rebuilds the crate when the IDL changes.
+    let include = match file.is_file() {
+        true => {
+            let file = file.display().to_string();
+            quote! { const _: &[u8] = include_bytes!(#file); }
+        }
+        false => quote! {},
+    };
+    let doc = format!(" The `{}` program, declared from its IDL.", idl.name);
+    Ok(quote! {
+        #[doc = #doc]
+        pub mod #name {
+            #include
+
+            #items
+        }
+    })
+}
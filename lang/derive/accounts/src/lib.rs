@@ -9,6 +9,8 @@ use syn::parse_macro_input;
 ///
 /// # Table of Contents
 /// - [Instruction Attribute](#instruction-attribute)
+/// - [Remaining Accounts Attribute](#remaining-accounts-attribute)
+/// - [Distinct Mut Attribute](#distinct-mut-attribute)
 /// - [Constraints](#constraints)
 ///
 /// # Instruction Attribute
@@ -34,6 +36,55 @@ use syn::parse_macro_input;
 /// }
 /// ```
 ///
+/// # Remaining Accounts Attribute
+///
+/// The type of the accounts expected after the ones of the struct can be given
+/// with `#[remaining_accounts(ty = <type>)]`, adding `mutable` if they must be
+/// writable. A `remaining_accounts` function is generated, iterating over the
+/// remaining accounts as `Account`s of that type, which checks their owner and
+/// discriminator, in order. The type is recorded in the IDL as the
+/// `remainingAccounts` of the instructions, for clients to know what to
+/// append.
+///
+/// ```ignore
+/// pub fn close_all(ctx: Context<CloseAll>) -> Result<()> {
+///     for vault in CloseAll::remaining_accounts(ctx.remaining_accounts) {
+///         let vault = vault?;
+///         ...
+///     }
+///     Ok(())
+/// }
+/// ...
+/// #[derive(Accounts)]
+/// #[remaining_accounts(ty = TokenAccount, mutable)]
+/// pub struct CloseAll<'info> {
+///     ...
+/// }
+/// ```
+///
+/// # Distinct Mut Attribute
+///
+/// Passing the same account twice, e.g. as both the source and destination of
+/// a transfer, can let a caller bypass the handler's accounting. With
+/// `#[distinct_mut]`, deserialization fails with
+/// `ConstraintDuplicateMutableAccount` if any two mutable accounts of the
+/// struct have the same key. The accounts to compare can be listed instead,
+/// with `#[distinct_mut(<account>, <account>, ..)]`. Omitted optional accounts
+/// are skipped. The pairs are recorded in the IDL as the `distinctAccounts` of
+/// the instructions.
+///
+/// ```ignore
+/// #[derive(Accounts)]
+/// #[distinct_mut(from, to)]
+/// pub struct Transfer<'info> {
+///     #[account(mut)]
+///     pub from: Account<'info, Vault>,
+///     #[account(mut)]
+///     pub to: Account<'info, Vault>,
+///     ...
+/// }
+/// ```
+///
 /// # Constraints
 ///
 /// There are different types of constraints that can be applied with the `#[account(..)]` attribute.
@@ -581,11 +632,109 @@ use syn::parse_macro_input;
 ///                 </pre>
 ///             </td>
 ///         </tr>
+///         <tr>
+///             <td>
+///                 <code>#[account(metadata::mint = &lt;target_account&gt;, metadata::name = &lt;expr&gt;, metadata::symbol = &lt;expr&gt;, metadata::update_authority = &lt;target_account&gt;)]</code>
+///             </td>
+///             <td>
+///                 Checks the Metaplex metadata account of a mint. With <code>metadata::mint</code>,
+///                 the account must be the metadata program derived address of the mint, which is
+///                 recorded in the IDL for clients to derive it. The name, symbol and update authority
+///                 are checked against the data of the account, so they require an
+///                 <code>Account&lt;'info, MetadataAccount&gt;</code>. Can't be used with <code>init</code>.
+///                 <br><br>
+///                 Example:
+///                 <pre>
+/// use anchor_spl::metadata::MetadataAccount;
+/// ...&#10;
+/// #[account(
+///     metadata::mint = nft_mint,
+///     metadata::symbol = "APE",
+///     metadata::update_authority = collection.authority,
+/// )]
+/// pub nft_metadata: Account<'info, MetadataAccount>,
+/// pub nft_mint: Account<'info, Mint>,
+/// pub collection: Account<'info, Collection>,
+///                 </pre>
+///             </td>
+///         </tr>
 ///     <tbody>
 /// </table>
-#[proc_macro_derive(Accounts, attributes(account, instruction))]
+#[proc_macro_derive(
+    Accounts,
+    attributes(account, instruction, from_fragment, remaining_accounts, distinct_mut)
+)]
 pub fn derive_anchor_deserialize(item: TokenStream) -> TokenStream {
-    parse_macro_input!(item as anchor_syn::AccountsStruct)
-        .to_token_stream()
+    let accounts = parse_macro_input!(item as anchor_syn::AccountsStruct);
+    #[allow(unused_mut)]
+    let mut tokens = accounts.to_token_stream();
+    #[cfg(feature = "idl-build")]
+    tokens.extend(anchor_syn::idl::build::gen_idl_build_impl_accounts(
+        &accounts,
+    ));
+    tokens.into()
+}
+
+/// Defines a reusable group of accounts, to embed into `Accounts` structs with
+/// [`with_fragments`](./attr.with_fragments.html).
+///
+/// The fragment isn't a struct of its own, but a macro of the same name
+/// embedding its accounts into other structs, so it must be in scope where
+/// it's embedded, e.g. imported with `use`. Its lifetime must be `'info`.
+///
+/// Constraints may refer to the accounts of the fragment, as well as to the
+/// accounts of the structs it's embedded into, e.g. `authority` below.
+///
+/// # Example
+/// ```ignore
+/// #[accounts_fragment]
+/// pub struct TokenPair<'info> {
+///     pub mint: Account<'info, Mint>,
+///     #[account(mut, token::mint = mint, token::authority = authority)]
+///     pub vault: Account<'info, TokenAccount>,
+///     pub token_program: Program<'info, Token>,
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn accounts_fragment(_args: TokenStream, input: TokenStream) -> TokenStream {
+    let strct = parse_macro_input!(input as syn::ItemStruct);
+    anchor_syn::codegen::fragment::accounts_fragment(&strct).into()
+}
+
+/// Embeds the [`accounts_fragment`](./attr.accounts_fragment.html)s of the
+/// fields marked with `#[fragment]` into an `Accounts` struct. Must come
+/// before `#[derive(Accounts)]`.
+///
+/// The accounts of a fragment replace the field, prefixed with its name, or
+/// with the one given with `#[fragment(prefix = "..")]`, e.g. `base_mint`,
+/// `base_vault` and `base_token_program` below. In the IDL, they're grouped
+/// under the name of the field, like composite accounts.
+///
+/// # Example
+/// ```ignore
+/// #[with_fragments]
+/// #[derive(Accounts)]
+/// pub struct Swap<'info> {
+///     pub authority: Signer<'info>,
+///     #[fragment]
+///     pub base: TokenPair<'info>,
+///     #[fragment(prefix = "quote")]
+///     pub other: TokenPair<'info>,
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn with_fragments(_args: TokenStream, input: TokenStream) -> TokenStream {
+    let strct = parse_macro_input!(input as syn::ItemStruct);
+    anchor_syn::codegen::fragment::expand(&strct)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn __embed_fragment(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as anchor_syn::codegen::fragment::EmbedFragment);
+    anchor_syn::codegen::fragment::embed(&input.fragment, &input.target)
+        .unwrap_or_else(|e| e.to_compile_error())
         .into()
 }
@@ -0,0 +1,42 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use syn::parse_macro_input;
+
+/// Implements [`Space`](./trait.Space.html) for a struct or an enum, i.e. the
+/// `INIT_SPACE` constant, the space its serialization takes at most, to
+/// allocate accounts with. The 8 bytes of the account discriminator aren't
+/// included.
+///
+/// `String` and `Vec` fields must be bounded with `#[max_len(..)]`, giving one
+/// length per level of nesting, and other types must implement `Space`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[account]
+/// #[derive(InitSpace)]
+/// pub struct Profile {
+///     pub authority: Pubkey,
+///     #[max_len(32)]
+///     pub name: String,
+///     #[max_len(10, 16)]
+///     pub tags: Vec<String>,
+/// }
+///
+/// #[derive(Accounts)]
+/// pub struct CreateProfile<'info> {
+///     #[account(init, payer = authority, space = 8 + Profile::INIT_SPACE)]
+///     pub profile: Account<'info, Profile>,
+///     #[account(mut)]
+///     pub authority: Signer<'info>,
+///     pub system_program: Program<'info, System>,
+/// }
+/// ```
+#[proc_macro_derive(InitSpace, attributes(max_len))]
+pub fn derive_init_space(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as syn::DeriveInput);
+    anchor_syn::codegen::space::generate(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
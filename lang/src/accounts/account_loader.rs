@@ -41,6 +41,10 @@ use std::ops::DerefMut;
 /// moving it into its own function.
 /// </p>
 ///
+/// With the `anchor-debug` feature, loads panic with a description of the
+/// issue when the account data is too small or misaligned for the struct, or
+/// when borrows of the data overlap, which makes them easier to find in tests.
+///
 /// # Example
 /// ```ignore
 /// use anchor_lang::prelude::*;
@@ -151,7 +155,12 @@ impl<'info, T: ZeroCopy + Owner> AccountLoader<'info, T> {
 
     /// Returns a Ref to the account data structure for reading.
     pub fn load(&self) -> Result<Ref<T>> {
-        let data = self.acc_info.try_borrow_data()?;
+        let data = self.acc_info.try_borrow_data();
+        #[cfg(feature = "anchor-debug")]
+        if data.is_err() {
+            self.borrow_failed("load");
+        }
+        let data = data?;
         if data.len() < T::discriminator().len() {
             return Err(ErrorCode::AccountDiscriminatorNotFound.into());
         }
@@ -161,6 +170,9 @@ impl<'info, T: ZeroCopy + Owner> AccountLoader<'info, T> {
             return Err(ErrorCode::AccountDiscriminatorMismatch.into());
         }
 
+        #[cfg(feature = "anchor-debug")]
+        self.check_layout(&data);
+
         Ok(Ref::map(data, |data| {
             bytemuck::from_bytes(&data[8..mem::size_of::<T>() + 8])
         }))
@@ -174,7 +186,12 @@ impl<'info, T: ZeroCopy + Owner> AccountLoader<'info, T> {
             return Err(ErrorCode::AccountNotMutable.into());
         }
 
-        let data = self.acc_info.try_borrow_mut_data()?;
+        let data = self.acc_info.try_borrow_mut_data();
+        #[cfg(feature = "anchor-debug")]
+        if data.is_err() {
+            self.borrow_failed("load_mut");
+        }
+        let data = data?;
         if data.len() < T::discriminator().len() {
             return Err(ErrorCode::AccountDiscriminatorNotFound.into());
         }
@@ -184,6 +201,9 @@ impl<'info, T: ZeroCopy + Owner> AccountLoader<'info, T> {
             return Err(ErrorCode::AccountDiscriminatorMismatch.into());
         }
 
+        #[cfg(feature = "anchor-debug")]
+        self.check_layout(&data);
+
         Ok(RefMut::map(data, |data| {
             bytemuck::from_bytes_mut(&mut data.deref_mut()[8..mem::size_of::<T>() + 8])
         }))
@@ -198,7 +218,15 @@ impl<'info, T: ZeroCopy + Owner> AccountLoader<'info, T> {
             return Err(ErrorCode::AccountNotMutable.into());
         }
 
-        let data = self.acc_info.try_borrow_mut_data()?;
+        let data = self.acc_info.try_borrow_mut_data();
+        #[cfg(feature = "anchor-debug")]
+        if data.is_err() {
+            self.borrow_failed("load_init");
+        }
+        let data = data?;
+
+        #[cfg(feature = "anchor-debug")]
+        self.check_layout(&data);
 
         // The discriminator should be zero, since we're initializing.
         let mut disc_bytes = [0u8; 8];
@@ -212,6 +240,51 @@ impl<'info, T: ZeroCopy + Owner> AccountLoader<'info, T> {
             bytemuck::from_bytes_mut(&mut data.deref_mut()[8..mem::size_of::<T>() + 8])
         }))
     }
+
+    // Checks that the account data can be reinterpreted as `T`, panicking
+    // with a description of the issue otherwise, instead of deep inside
+    // bytemuck.
+    #[cfg(feature = "anchor-debug")]
+    fn check_layout(&self, data: &[u8]) {
+        let type_name = std::any::type_name::<T>();
+        let len = T::discriminator().len() + mem::size_of::<T>();
+        if data.len() < len {
+            solana_program::msg!("The given AccountLoader data is too small");
+            panic!(
+                "AccountLoader<{}>: account {} has {} bytes of data, but {} are needed for the discriminator and the struct",
+                type_name,
+                self.acc_info.key,
+                data.len(),
+                len
+            );
+        }
+        let align = mem::align_of::<T>();
+        let addr = data[T::discriminator().len()..].as_ptr() as usize;
+        if addr % align != 0 {
+            solana_program::msg!("The given AccountLoader data is misaligned");
+            panic!(
+                "AccountLoader<{}>: the data of account {} is at an offset of {} bytes from the alignment of {} bytes the struct requires, use #[repr(packed)] or fields with a lower alignment",
+                type_name,
+                self.acc_info.key,
+                addr % align,
+                align
+            );
+        }
+    }
+
+    // Borrows of the account data can't overlap mutable ones, e.g. when the
+    // `Ref` of a `load` is still in scope when calling `load_mut`, or when the
+    // same account is given twice.
+    #[cfg(feature = "anchor-debug")]
+    fn borrow_failed(&self, method: &str) -> ! {
+        solana_program::msg!("The given AccountLoader is already borrowed");
+        panic!(
+            "AccountLoader<{}>: {} of account {} overlaps another borrow of its data, drop the previous Ref or RefMut first, e.g. before a CPI",
+            std::any::type_name::<T>(),
+            method,
+            self.acc_info.key
+        );
+    }
 }
 
 impl<'info, T: ZeroCopy + Owner> Accounts<'info> for AccountLoader<'info, T> {
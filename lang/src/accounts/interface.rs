@@ -0,0 +1,127 @@
+//! Type validating that the account is one of the programs implementing an
+//! interface.
+
+use crate::error::{Error, ErrorCode};
+use crate::{Accounts, AccountsExit, Ids, Key, Result, ToAccountInfos, ToAccountMetas};
+use solana_program::account_info::AccountInfo;
+use solana_program::instruction::AccountMeta;
+use solana_program::pubkey::Pubkey;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+/// Type validating that the account is one of a set of programs, e.g. the
+/// token program or token-2022, which implement the same interface.
+///
+/// Like [`Program`](crate::accounts::program::Program), but checks that the
+/// account's key is one of `T::ids()`, and that it's executable.
+///
+/// # Example
+/// ```ignore
+/// use anchor_spl::token_interface::TokenInterface;
+///
+/// #[derive(Accounts)]
+/// pub struct Transfer<'info> {
+///     pub token_program: Interface<'info, TokenInterface>,
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Interface<'info, T: Ids + Clone> {
+    info: AccountInfo<'info>,
+    _phantom: PhantomData<T>,
+}
+
+impl<'info, T: Ids + Clone + fmt::Debug> fmt::Debug for Interface<'info, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interface")
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
+impl<'a, T: Ids + Clone> Interface<'a, T> {
+    fn new(info: AccountInfo<'a>) -> Interface<'a, T> {
+        Self {
+            info,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Deserializes the given `info` into an `Interface`.
+    #[inline(never)]
+    pub fn try_from(info: &AccountInfo<'a>) -> Result<Interface<'a, T>> {
+        if !T::ids().contains(info.key) {
+            let error = Error::from(ErrorCode::InvalidProgramId);
+            return Err(match T::ids().first() {
+                Some(id) => error.with_pubkeys((*info.key, *id)),
+                None => error,
+            });
+        }
+        if !info.executable {
+            return Err(ErrorCode::InvalidProgramExecutable.into());
+        }
+
+        Ok(Interface::new(info.clone()))
+    }
+}
+
+impl<'info, T> Accounts<'info> for Interface<'info, T>
+where
+    T: Ids + Clone,
+{
+    #[inline(never)]
+    fn try_accounts(
+        _program_id: &Pubkey,
+        accounts: &mut &[AccountInfo<'info>],
+        _ix_data: &[u8],
+        _bumps: &mut BTreeMap<String, u8>,
+        _reallocs: &mut BTreeSet<Pubkey>,
+    ) -> Result<Self> {
+        if accounts.is_empty() {
+            return Err(ErrorCode::AccountNotEnoughKeys.into());
+        }
+        let account = &accounts[0];
+        *accounts = &accounts[1..];
+        Interface::try_from(account)
+    }
+}
+
+impl<'info, T: Ids + Clone> ToAccountMetas for Interface<'info, T> {
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let is_signer = is_signer.unwrap_or(self.info.is_signer);
+        let meta = match self.info.is_writable {
+            false => AccountMeta::new_readonly(*self.info.key, is_signer),
+            true => AccountMeta::new(*self.info.key, is_signer),
+        };
+        vec![meta]
+    }
+}
+
+impl<'info, T: Ids + Clone> ToAccountInfos<'info> for Interface<'info, T> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        vec![self.info.clone()]
+    }
+}
+
+impl<'info, T: Ids + Clone> AsRef<AccountInfo<'info>> for Interface<'info, T> {
+    fn as_ref(&self) -> &AccountInfo<'info> {
+        &self.info
+    }
+}
+
+impl<'info, T: Ids + Clone> Deref for Interface<'info, T> {
+    type Target = AccountInfo<'info>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.info
+    }
+}
+
+impl<'info, T: Ids + Clone> AccountsExit<'info> for Interface<'info, T> {}
+
+impl<'info, T: Ids + Clone> Key for Interface<'info, T> {
+    fn key(&self) -> Pubkey {
+        *self.info.key
+    }
+}
@@ -0,0 +1,232 @@
+//! Account container that checks ownership against a set of programs on
+//! deserialization.
+
+use crate::bpf_writer::BpfWriter;
+use crate::error::{Error, ErrorCode};
+use crate::{
+    AccountDeserialize, AccountSerialize, Accounts, AccountsClose, AccountsExit, Key, Owners,
+    Result, ToAccountInfo, ToAccountInfos, ToAccountMetas,
+};
+use solana_program::account_info::AccountInfo;
+use solana_program::instruction::AccountMeta;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+/// Wrapper around [`AccountInfo`](crate::solana_program::account_info::AccountInfo)
+/// that verifies the account is owned by one of a set of programs, and
+/// deserializes its data into a Rust type.
+///
+/// Like [`Account`](crate::accounts::account::Account), but for account
+/// types shared by several programs implementing the same interface, e.g.
+/// token accounts and mints of both the token program and token-2022. The
+/// owner of the account must be one of `T::owners()`, which the type
+/// implements through the [`Owners`](crate::Owners) trait.
+///
+/// The account is only written back at the end of the instruction when it's
+/// owned by the executing program.
+///
+/// # Example
+/// ```ignore
+/// use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+///
+/// #[derive(Accounts)]
+/// pub struct Deposit<'info> {
+///     #[account(mut, token::mint = mint)]
+///     pub vault: InterfaceAccount<'info, TokenAccount>,
+///     pub mint: InterfaceAccount<'info, Mint>,
+///     pub token_program: Interface<'info, TokenInterface>,
+/// }
+/// ```
+#[derive(Clone)]
+pub struct InterfaceAccount<'info, T: AccountSerialize + AccountDeserialize + Owners + Clone> {
+    account: T,
+    info: AccountInfo<'info>,
+}
+
+impl<'info, T: AccountSerialize + AccountDeserialize + Owners + Clone + fmt::Debug> fmt::Debug
+    for InterfaceAccount<'info, T>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InterfaceAccount")
+            .field("account", &self.account)
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
+impl<'a, T: AccountSerialize + AccountDeserialize + Owners + Clone> InterfaceAccount<'a, T> {
+    fn new(info: AccountInfo<'a>, account: T) -> InterfaceAccount<'a, T> {
+        Self { info, account }
+    }
+
+    fn check_owner(info: &AccountInfo<'a>) -> Result<()> {
+        if info.owner == &system_program::ID && info.lamports() == 0 {
+            return Err(ErrorCode::AccountNotInitialized.into());
+        }
+        if !T::owners().contains(info.owner) {
+            let error = Error::from(ErrorCode::AccountOwnedByWrongProgram);
+            return Err(match T::owners().first() {
+                Some(owner) => error.with_pubkeys((*info.owner, *owner)),
+                None => error,
+            });
+        }
+        Ok(())
+    }
+
+    /// Deserializes the given `info` into an `InterfaceAccount`.
+    #[inline(never)]
+    pub fn try_from(info: &AccountInfo<'a>) -> Result<InterfaceAccount<'a, T>> {
+        Self::check_owner(info)?;
+        let mut data: &[u8] = &info.try_borrow_data()?;
+        Ok(InterfaceAccount::new(
+            info.clone(),
+            T::try_deserialize(&mut data)?,
+        ))
+    }
+
+    /// Deserializes the given `info` into an `InterfaceAccount` without
+    /// checking the account discriminator. Be careful when using this and
+    /// avoid it if possible.
+    #[inline(never)]
+    pub fn try_from_unchecked(info: &AccountInfo<'a>) -> Result<InterfaceAccount<'a, T>> {
+        Self::check_owner(info)?;
+        let mut data: &[u8] = &info.try_borrow_data()?;
+        Ok(InterfaceAccount::new(
+            info.clone(),
+            T::try_deserialize_unchecked(&mut data)?,
+        ))
+    }
+
+    /// Reloads the account from storage. This is useful, for example, when
+    /// observing side effects after CPI.
+    pub fn reload(&mut self) -> Result<()> {
+        Self::check_owner(&self.info)?;
+        let mut data: &[u8] = &self.info.try_borrow_data()?;
+        self.account = T::try_deserialize(&mut data)?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> T {
+        self.account
+    }
+
+    /// Sets the inner account.
+    pub fn set_inner(&mut self, inner: T) {
+        self.account = inner;
+    }
+}
+
+impl<'info, T: AccountSerialize + AccountDeserialize + Owners + Clone> Accounts<'info>
+    for InterfaceAccount<'info, T>
+{
+    #[inline(never)]
+    fn try_accounts(
+        _program_id: &Pubkey,
+        accounts: &mut &[AccountInfo<'info>],
+        _ix_data: &[u8],
+        _bumps: &mut BTreeMap<String, u8>,
+        _reallocs: &mut BTreeSet<Pubkey>,
+    ) -> Result<Self> {
+        if accounts.is_empty() {
+            return Err(ErrorCode::AccountNotEnoughKeys.into());
+        }
+        let account = &accounts[0];
+        *accounts = &accounts[1..];
+        InterfaceAccount::try_from(account)
+    }
+}
+
+impl<'info, T: AccountSerialize + AccountDeserialize + Owners + Clone> AccountsExit<'info>
+    for InterfaceAccount<'info, T>
+{
+    fn exit(&self, program_id: &Pubkey) -> Result<()> {
+        // Only persist if the owner is the current program.
+        if self.info.owner == program_id {
+            let info = self.to_account_info();
+            let mut data = info.try_borrow_mut_data()?;
+            let dst: &mut [u8] = &mut data;
+            let mut writer = BpfWriter::new(dst);
+            self.account.try_serialize(&mut writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'info, T: AccountSerialize + AccountDeserialize + Owners + Clone> AccountsClose<'info>
+    for InterfaceAccount<'info, T>
+{
+    fn close(&self, sol_destination: AccountInfo<'info>) -> Result<()> {
+        crate::common::close(self.to_account_info(), sol_destination)
+    }
+}
+
+impl<'info, T: AccountSerialize + AccountDeserialize + Owners + Clone> ToAccountMetas
+    for InterfaceAccount<'info, T>
+{
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let is_signer = is_signer.unwrap_or(self.info.is_signer);
+        let meta = match self.info.is_writable {
+            false => AccountMeta::new_readonly(*self.info.key, is_signer),
+            true => AccountMeta::new(*self.info.key, is_signer),
+        };
+        vec![meta]
+    }
+}
+
+impl<'info, T: AccountSerialize + AccountDeserialize + Owners + Clone> ToAccountInfos<'info>
+    for InterfaceAccount<'info, T>
+{
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        vec![self.info.clone()]
+    }
+}
+
+impl<'info, T: AccountSerialize + AccountDeserialize + Owners + Clone> AsRef<AccountInfo<'info>>
+    for InterfaceAccount<'info, T>
+{
+    fn as_ref(&self) -> &AccountInfo<'info> {
+        &self.info
+    }
+}
+
+impl<'info, T: AccountSerialize + AccountDeserialize + Owners + Clone> AsRef<T>
+    for InterfaceAccount<'info, T>
+{
+    fn as_ref(&self) -> &T {
+        &self.account
+    }
+}
+
+impl<'a, T: AccountSerialize + AccountDeserialize + Owners + Clone> Deref
+    for InterfaceAccount<'a, T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.account
+    }
+}
+
+impl<'a, T: AccountSerialize + AccountDeserialize + Owners + Clone> DerefMut
+    for InterfaceAccount<'a, T>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        #[cfg(feature = "anchor-debug")]
+        if !self.info.is_writable {
+            solana_program::msg!("The given InterfaceAccount is not mutable");
+            panic!();
+        }
+        &mut self.account
+    }
+}
+
+impl<'info, T: AccountSerialize + AccountDeserialize + Owners + Clone> Key
+    for InterfaceAccount<'info, T>
+{
+    fn key(&self) -> Pubkey {
+        *self.info.key
+    }
+}
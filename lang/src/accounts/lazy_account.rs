@@ -0,0 +1,175 @@
+//! Account container that deserializes the fields of its data on demand.
+
+use crate::error::{Error, ErrorCode};
+use crate::{
+    AccountDeserialize, Accounts, AccountsExit, Discriminator, Key, Owner, Result, ToAccountInfos,
+    ToAccountMetas,
+};
+use solana_program::account_info::AccountInfo;
+use solana_program::instruction::AccountMeta;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Wrapper around [`AccountInfo`](crate::solana_program::account_info::AccountInfo)
+/// that verifies program ownership and the account discriminator like
+/// [`Account`](crate::accounts::account::Account), but doesn't deserialize
+/// the account's data until it's read.
+///
+/// Deserializing a large account to read one or two of its fields costs
+/// compute units and stack for all the others. With the `lazy-account`
+/// feature, `#[account]` generates a `Lazy<Name>` trait for each account,
+/// implemented by `LazyAccount<'info, Name>`, with a `load_<field>` method
+/// per field deserializing only that field. Fields are found at an offset
+/// computed at compile time up to the first field whose size depends on its
+/// value, e.g. a `Vec`, an `Option` or a user defined type, after which the
+/// fields in between are deserialized to be skipped. Fields used the most
+/// should thus come first.
+///
+/// `LazyAccount` is read-only: changes must go through an
+/// [`Account`](crate::accounts::account::Account) of the same type.
+///
+/// # Example
+/// ```ignore
+/// #[account]
+/// pub struct Pool {
+///     pub authority: Pubkey,
+///     pub fee_bps: u16,
+///     pub members: Vec<Pubkey>,
+/// }
+///
+/// #[derive(Accounts)]
+/// pub struct Swap<'info> {
+///     pub pool: LazyAccount<'info, Pool>,
+/// }
+///
+/// pub fn swap(ctx: Context<Swap>, amount: u64) -> Result<()> {
+///     let fee = amount * ctx.accounts.pool.load_fee_bps()? as u64 / 10_000;
+///     ...
+/// }
+/// ```
+#[derive(Clone)]
+pub struct LazyAccount<'info, T: AccountDeserialize + Discriminator + Owner> {
+    info: AccountInfo<'info>,
+    phantom: PhantomData<T>,
+}
+
+impl<'info, T: AccountDeserialize + Discriminator + Owner + fmt::Debug> fmt::Debug
+    for LazyAccount<'info, T>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyAccount")
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
+impl<'info, T: AccountDeserialize + Discriminator + Owner> LazyAccount<'info, T> {
+    /// Checks the owner and the discriminator of the given `info`, without
+    /// deserializing its data.
+    #[inline(never)]
+    pub fn try_from(info: &AccountInfo<'info>) -> Result<LazyAccount<'info, T>> {
+        if info.owner == &system_program::ID && info.lamports() == 0 {
+            return Err(ErrorCode::AccountNotInitialized.into());
+        }
+        if info.owner != &T::owner() {
+            return Err(Error::from(ErrorCode::AccountOwnedByWrongProgram)
+                .with_pubkeys((*info.owner, T::owner())));
+        }
+        let data = info.try_borrow_data()?;
+        let len = T::discriminator_len();
+        if data.len() < len {
+            return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+        }
+        if data[..len] != T::discriminator()[..len] {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        Ok(LazyAccount {
+            info: info.clone(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Deserializes the whole account.
+    pub fn load(&self) -> Result<T> {
+        let mut data: &[u8] = &self.info.try_borrow_data()?;
+        T::try_deserialize(&mut data)
+    }
+
+    /// Deserializes a value from the data of the account, starting at
+    /// `offset`. Used by the `load_<field>` methods generated by `#[account]`.
+    #[doc(hidden)]
+    pub fn __load_at<F>(
+        &self,
+        offset: usize,
+        load: impl FnOnce(&mut &[u8]) -> std::io::Result<F>,
+    ) -> Result<F> {
+        let data = self.info.try_borrow_data()?;
+        let mut buf: &[u8] = data
+            .get(offset..)
+            .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+        load(&mut buf).map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+    }
+}
+
+impl<'info, T: AccountDeserialize + Discriminator + Owner> Accounts<'info>
+    for LazyAccount<'info, T>
+{
+    #[inline(never)]
+    fn try_accounts(
+        _program_id: &Pubkey,
+        accounts: &mut &[AccountInfo<'info>],
+        _ix_data: &[u8],
+        _bumps: &mut BTreeMap<String, u8>,
+        _reallocs: &mut BTreeSet<Pubkey>,
+    ) -> Result<Self> {
+        if accounts.is_empty() {
+            return Err(ErrorCode::AccountNotEnoughKeys.into());
+        }
+        let account = &accounts[0];
+        *accounts = &accounts[1..];
+        LazyAccount::try_from(account)
+    }
+}
+
+impl<'info, T: AccountDeserialize + Discriminator + Owner> AccountsExit<'info>
+    for LazyAccount<'info, T>
+{
+}
+
+impl<'info, T: AccountDeserialize + Discriminator + Owner> ToAccountMetas
+    for LazyAccount<'info, T>
+{
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let is_signer = is_signer.unwrap_or(self.info.is_signer);
+        let meta = match self.info.is_writable {
+            false => AccountMeta::new_readonly(*self.info.key, is_signer),
+            true => AccountMeta::new(*self.info.key, is_signer),
+        };
+        vec![meta]
+    }
+}
+
+impl<'info, T: AccountDeserialize + Discriminator + Owner> ToAccountInfos<'info>
+    for LazyAccount<'info, T>
+{
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        vec![self.info.clone()]
+    }
+}
+
+impl<'info, T: AccountDeserialize + Discriminator + Owner> AsRef<AccountInfo<'info>>
+    for LazyAccount<'info, T>
+{
+    fn as_ref(&self) -> &AccountInfo<'info> {
+        &self.info
+    }
+}
+
+impl<'info, T: AccountDeserialize + Discriminator + Owner> Key for LazyAccount<'info, T> {
+    fn key(&self) -> Pubkey {
+        *self.info.key
+    }
+}
@@ -0,0 +1,72 @@
+//! Option<T> type for optional accounts.
+//!
+//! An optional account that isn't given is passed as the program id, so that
+//! the accounts after it keep their position.
+//!
+//! # Example
+//! ```ignore
+//! #[derive(Accounts)]
+//! pub struct Example<'info> {
+//!     pub config: Option<AccountLoader<'info, Config>>,
+//! }
+//! ```
+
+use crate::error::ErrorCode;
+use crate::{Accounts, AccountsClose, AccountsExit, Result, ToAccountInfos, ToAccountMetas};
+use solana_program::account_info::AccountInfo;
+use solana_program::instruction::AccountMeta;
+use solana_program::pubkey::Pubkey;
+use std::collections::{BTreeMap, BTreeSet};
+
+impl<'info, T: Accounts<'info>> Accounts<'info> for Option<T> {
+    fn try_accounts(
+        program_id: &Pubkey,
+        accounts: &mut &[AccountInfo<'info>],
+        ix_data: &[u8],
+        bumps: &mut BTreeMap<String, u8>,
+        reallocs: &mut BTreeSet<Pubkey>,
+    ) -> Result<Self> {
+        if accounts.is_empty() {
+            return Err(ErrorCode::AccountNotEnoughKeys.into());
+        }
+        if accounts[0].key == program_id {
+            *accounts = &accounts[1..];
+            return Ok(None);
+        }
+        T::try_accounts(program_id, accounts, ix_data, bumps, reallocs).map(Some)
+    }
+}
+
+impl<'info, T: AccountsExit<'info>> AccountsExit<'info> for Option<T> {
+    fn exit(&self, program_id: &Pubkey) -> Result<()> {
+        match self {
+            Some(account) => account.exit(program_id),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'info, T: ToAccountInfos<'info>> ToAccountInfos<'info> for Option<T> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        self.as_ref()
+            .map_or_else(Vec::new, |account| account.to_account_infos())
+    }
+}
+
+// The program id isn't known here, so omitted accounts have no meta. The
+// `Accounts` derive gives the program id for them instead.
+impl<T: ToAccountMetas> ToAccountMetas for Option<T> {
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+        self.as_ref()
+            .map_or_else(Vec::new, |account| account.to_account_metas(is_signer))
+    }
+}
+
+impl<'info, T: AccountsClose<'info>> AccountsClose<'info> for Option<T> {
+    fn close(&self, sol_destination: AccountInfo<'info>) -> Result<()> {
+        match self {
+            Some(account) => account.close(sol_destination),
+            None => Ok(()),
+        }
+    }
+}
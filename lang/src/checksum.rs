@@ -0,0 +1,56 @@
+//! Checksums of the data of `#[account(checksum)]` accounts.
+//!
+//! The data of such accounts is followed by the CRC-32 of their serialized
+//! fields, discriminator excluded, as 4 little endian bytes. It's written on
+//! serialization and verified by `AccountDeserialize::try_deserialize`,
+//! catching data corrupted e.g. by a faulty reallocation.
+
+/// Number of bytes of the checksum following the data of an account, to add
+/// to its space.
+pub const CHECKSUM_LEN: usize = 4;
+
+const TABLE: [u32; 256] = table();
+
+// Lookup table of the reflected CRC-32 (IEEE 802.3) polynomial.
+const fn table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// The CRC-32 (IEEE 802.3) of the given bytes.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| {
+        TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// The checksum of the serialized fields of an account.
+pub fn checksum(data: &[u8]) -> [u8; CHECKSUM_LEN] {
+    crc32(data).to_le_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+}
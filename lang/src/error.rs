@@ -33,6 +33,24 @@ pub enum ErrorCode {
     /// 103 - The program could not serialize the given instruction
     #[msg("The program could not serialize the given instruction")]
     InstructionDidNotSerialize,
+    /// 104 - The instruction is past its sunset
+    #[msg("The instruction is past its sunset")]
+    InstructionSunset,
+    /// 105 - An instruction argument is out of its range
+    #[msg("An instruction argument is out of its range")]
+    InstructionArgOutOfRange,
+    /// 106 - An instruction argument must not be zero
+    #[msg("An instruction argument must not be zero")]
+    InstructionArgZero,
+    /// 107 - An instruction argument exceeds its maximum length
+    #[msg("An instruction argument exceeds its maximum length")]
+    InstructionArgTooLong,
+    /// 108 - The instruction isn't allowed in batches
+    #[msg("The instruction isn't allowed in batches")]
+    InstructionNotBatchable,
+    /// 109 - The batch gives more or fewer account counts than instructions
+    #[msg("The batch gives more or fewer account counts than instructions")]
+    InstructionBatchMismatch,
 
     // IDL instructions
     /// 1000 - The program was compiled without idl instructions
@@ -105,6 +123,18 @@ pub enum ErrorCode {
     /// 2019 - A space constraint was violated
     #[msg("A space constraint was violated")]
     ConstraintSpace,
+    /// 2020 - A state machine constraint was violated
+    #[msg("A state machine constraint was violated")]
+    ConstraintStateMachine,
+    /// 2021 - A duplicate mutable account constraint was violated
+    #[msg("A duplicate mutable account constraint was violated")]
+    ConstraintDuplicateMutableAccount,
+    /// 2022 - A metadata address constraint was violated
+    #[msg("A metadata address constraint was violated")]
+    ConstraintMetadataAddress,
+    /// 2023 - A metadata constraint was violated
+    #[msg("A metadata constraint was violated")]
+    ConstraintMetadata,
 
     // Require
     /// 2500 - A require expression was violated
@@ -184,6 +214,9 @@ pub enum ErrorCode {
     /// 3017 - The account was duplicated for more than one reallocation
     #[msg("The account was duplicated for more than one reallocation")]
     AccountDuplicateReallocs,
+    /// 3018 - The checksum of the account data doesn't match its content
+    #[msg("The checksum of the account data doesn't match its content")]
+    AccountChecksumMismatch,
 
     // State.
     /// 4000 - The given state account does not have the correct address
@@ -194,6 +227,12 @@ pub enum ErrorCode {
     /// 4100 - The declared program id does not match actual program id
     #[msg("The declared program id does not match the actual program id")]
     DeclaredProgramIdMismatch = 4100,
+    /// 4101 - The state machine of the account doesn't allow the transition
+    #[msg("The state machine of the account doesn't allow the transition")]
+    StateMachineTransition,
+    /// 4102 - An arithmetic operation overflowed or divided by zero
+    #[msg("An arithmetic operation overflowed or divided by zero")]
+    ArithmeticOverflow,
 
     // Deprecated
     /// 5000 - The API being used is deprecated and should no longer be used
@@ -0,0 +1,96 @@
+// The first 8 bytes of the instruction of a self CPI emitting an event with
+// `emit_cpi!`, followed by the event's data, i.e. its discriminator and its
+// borsh encoding. Like the IDL instructions, it's handled outside of the
+// program's instructions.
+//
+// Sha256(anchor:event)[..8];
+pub const EVENT_IX_TAG: u64 = 0x1d9acb512ea545e4;
+pub const EVENT_IX_TAG_LE: [u8; 8] = EVENT_IX_TAG.to_le_bytes();
+
+// Seed of the PDA signing the self CPIs emitting events, so that programs only
+// accept events emitted by themselves.
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+// Off-chain decoding of the events of the program crates linked with their
+// `no-entrypoint` feature. `#[event]` registers a decoder per event, under the
+// name of the crate defining it, which the `decode_event` generated by
+// `#[program]` looks the events of its crate up in.
+#[cfg(not(target_arch = "bpf"))]
+mod decode {
+    use crate::AnchorDeserialize;
+    use std::any::Any;
+
+    #[doc(hidden)]
+    pub struct EventDecoder {
+        // `CARGO_PKG_NAME` of the crate defining the event.
+        pub program: &'static str,
+        pub name: &'static str,
+        pub discriminator: [u8; 8],
+        pub decode: fn(&[u8]) -> Option<Box<dyn Any>>,
+    }
+
+    inventory::collect!(EventDecoder);
+
+    #[doc(hidden)]
+    pub fn decode_boxed<T: AnchorDeserialize + 'static>(mut data: &[u8]) -> Option<Box<dyn Any>> {
+        T::deserialize(&mut data)
+            .ok()
+            .map(|event| Box::new(event) as Box<dyn Any>)
+    }
+
+    /// An event of a program, decoded by the `decode_event` of its crate.
+    /// Downcast it to the `#[event]` struct it was decoded as.
+    pub struct ProgramEvent {
+        name: &'static str,
+        event: Box<dyn Any>,
+    }
+
+    impl ProgramEvent {
+        /// The name of the event's struct.
+        pub fn name(&self) -> &'static str {
+            self.name
+        }
+
+        pub fn is<T: Any>(&self) -> bool {
+            self.event.is::<T>()
+        }
+
+        pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+            self.event.downcast_ref()
+        }
+
+        pub fn downcast<T: Any>(self) -> Result<T, Self> {
+            let name = self.name;
+            self.event
+                .downcast()
+                .map(|event| *event)
+                .map_err(|event| ProgramEvent { name, event })
+        }
+    }
+
+    impl std::fmt::Debug for ProgramEvent {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("ProgramEvent")
+                .field("name", &self.name)
+                .finish_non_exhaustive()
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn decode_event(program: &str, discriminator: &[u8], data: &[u8]) -> Option<ProgramEvent> {
+        inventory::iter::<EventDecoder>
+            .into_iter()
+            .filter(|decoder| {
+                decoder.program == program && decoder.discriminator[..] == *discriminator
+            })
+            .find_map(|decoder| {
+                Some(ProgramEvent {
+                    name: decoder.name,
+                    event: (decoder.decode)(data)?,
+                })
+            })
+    }
+}
+
+#[cfg(not(target_arch = "bpf"))]
+pub use decode::*;
@@ -0,0 +1,197 @@
+//! Support for generating the IDL from the compiled program, used by the code
+//! generated with the `idl-build` feature. See `anchor_syn::idl::build`.
+//!
+//! The IDL type of a Rust type is given by its `IdlBuild` implementation,
+//! which Anchor's macros generate for the types they define. Types without one
+//! fall back to the IDL type parsed from the type as written, which relies on
+//! the method resolution of `IdlBuildMarker` to pick `IdlBuildResolved` when
+//! the type implements `IdlBuild`, and `IdlBuildUnresolved` otherwise.
+
+use crate::AnchorSerialize;
+use solana_program::pubkey::Pubkey;
+use std::marker::PhantomData;
+
+pub use anchor_syn::idl as types;
+pub use anchor_syn::idl::build::{
+    account, instruction, print_account, print_constant, print_errors, print_event, print_program,
+    type_definition, Types,
+};
+
+use types::IdlType;
+
+pub trait IdlBuild {
+    fn __anchor_private_idl_type() -> IdlType;
+
+    // The type of a `Vec` of `Self`, as a `Vec<u8>` is `Bytes`.
+    fn __anchor_private_idl_vec_type() -> IdlType {
+        IdlType::Vec(Box::new(Self::__anchor_private_idl_type()))
+    }
+
+    // Inserts the definitions of the types used by `Self`, including itself.
+    fn __anchor_private_insert_idl_defined(_types: &mut Types) {}
+}
+
+/// The accounts of an `Accounts` struct.
+pub trait IdlBuildAccounts {
+    fn __anchor_private_gen_idl_accounts() -> Vec<types::IdlAccountItem>;
+}
+
+macro_rules! impl_idl_build {
+    ($($ty:ty => $idl_ty:expr),* $(,)?) => {
+        $(
+            impl IdlBuild for $ty {
+                fn __anchor_private_idl_type() -> IdlType {
+                    $idl_ty
+                }
+            }
+        )*
+    };
+}
+
+impl_idl_build! {
+    bool => IdlType::Bool,
+    i8 => IdlType::I8,
+    u16 => IdlType::U16,
+    i16 => IdlType::I16,
+    u32 => IdlType::U32,
+    i32 => IdlType::I32,
+    f32 => IdlType::F32,
+    u64 => IdlType::U64,
+    i64 => IdlType::I64,
+    f64 => IdlType::F64,
+    u128 => IdlType::U128,
+    i128 => IdlType::I128,
+    String => IdlType::String,
+    Pubkey => IdlType::PublicKey,
+}
+
+impl IdlBuild for u8 {
+    fn __anchor_private_idl_type() -> IdlType {
+        IdlType::U8
+    }
+
+    fn __anchor_private_idl_vec_type() -> IdlType {
+        IdlType::Bytes
+    }
+}
+
+impl<T: IdlBuild> IdlBuild for Vec<T> {
+    fn __anchor_private_idl_type() -> IdlType {
+        T::__anchor_private_idl_vec_type()
+    }
+
+    fn __anchor_private_insert_idl_defined(types: &mut Types) {
+        T::__anchor_private_insert_idl_defined(types)
+    }
+}
+
+impl<T: IdlBuild> IdlBuild for Option<T> {
+    fn __anchor_private_idl_type() -> IdlType {
+        IdlType::Option(Box::new(T::__anchor_private_idl_type()))
+    }
+
+    fn __anchor_private_insert_idl_defined(types: &mut Types) {
+        T::__anchor_private_insert_idl_defined(types)
+    }
+}
+
+impl<T: IdlBuild> IdlBuild for Box<T> {
+    fn __anchor_private_idl_type() -> IdlType {
+        T::__anchor_private_idl_type()
+    }
+
+    fn __anchor_private_insert_idl_defined(types: &mut Types) {
+        T::__anchor_private_insert_idl_defined(types)
+    }
+}
+
+impl<T: IdlBuild, const N: usize> IdlBuild for [T; N] {
+    fn __anchor_private_idl_type() -> IdlType {
+        IdlType::Array(Box::new(T::__anchor_private_idl_type()), N)
+    }
+
+    fn __anchor_private_insert_idl_defined(types: &mut Types) {
+        T::__anchor_private_insert_idl_defined(types)
+    }
+}
+
+/// The field-level diff of two values, as printed by
+/// [assert_accounts_eq](crate::assert_accounts_eq), colored unless the
+/// `NO_COLOR` environment variable is set.
+pub fn diff<T: IdlBuild + AnchorSerialize>(expected: &T, actual: &T) -> String {
+    let mut types = Types::new();
+    T::__anchor_private_insert_idl_defined(&mut types);
+    let color = std::env::var_os("NO_COLOR").is_none();
+    types::diff::diff(
+        &T::__anchor_private_idl_type(),
+        &types,
+        &expected.try_to_vec().expect("Must serialize"),
+        &actual.try_to_vec().expect("Must serialize"),
+        color,
+    )
+}
+
+#[doc(hidden)]
+pub struct IdlBuildMarker<T: ?Sized>(PhantomData<T>);
+
+impl<T: ?Sized> IdlBuildMarker<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait IdlBuildResolved {
+    fn __anchor_private_idl_type(&self, fallback: &str) -> IdlType;
+    fn __anchor_private_insert_idl_defined(&self, types: &mut Types);
+}
+
+impl<T: IdlBuild> IdlBuildResolved for IdlBuildMarker<T> {
+    fn __anchor_private_idl_type(&self, _fallback: &str) -> IdlType {
+        T::__anchor_private_idl_type()
+    }
+
+    fn __anchor_private_insert_idl_defined(&self, types: &mut Types) {
+        T::__anchor_private_insert_idl_defined(types)
+    }
+}
+
+#[doc(hidden)]
+pub trait IdlBuildUnresolved {
+    fn __anchor_private_idl_type(&self, fallback: &str) -> IdlType;
+    fn __anchor_private_insert_idl_defined(&self, types: &mut Types);
+}
+
+impl<T: ?Sized> IdlBuildUnresolved for &IdlBuildMarker<T> {
+    fn __anchor_private_idl_type(&self, fallback: &str) -> IdlType {
+        serde_json::from_str(fallback).expect("Invalid IDL type")
+    }
+
+    fn __anchor_private_insert_idl_defined(&self, _types: &mut Types) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(dead_code)]
+    struct Foreign;
+
+    #[test]
+    fn idl_types() {
+        let defined = r#"{"defined":"Foreign"}"#;
+        assert_eq!(
+            (&IdlBuildMarker::<Vec<u8>>::new()).__anchor_private_idl_type(defined),
+            IdlType::Bytes
+        );
+        assert_eq!(
+            (&IdlBuildMarker::<Option<[Pubkey; 2]>>::new()).__anchor_private_idl_type(defined),
+            IdlType::Option(Box::new(IdlType::Array(Box::new(IdlType::PublicKey), 2)))
+        );
+        assert_eq!(
+            (&IdlBuildMarker::<Foreign>::new()).__anchor_private_idl_type(defined),
+            IdlType::Defined("Foreign".to_string())
+        );
+    }
+}
@@ -34,25 +34,34 @@ mod account_meta;
 pub mod accounts;
 mod bpf_upgradeable_state;
 mod bpf_writer;
+pub mod checksum;
 mod common;
 pub mod context;
 mod ctor;
 pub mod error;
+pub mod event;
 #[doc(hidden)]
 pub mod idl;
+#[cfg(feature = "serde")]
+pub mod serde_helpers;
 pub mod system_program;
 
 mod vec;
 pub use crate::bpf_upgradeable_state::*;
-pub use anchor_attribute_access_control::access_control;
+pub use anchor_attribute_access_control::{access_control, admin, batch, compute_budget, sunset};
 pub use anchor_attribute_account::{account, declare_id, zero_copy};
 pub use anchor_attribute_constant::constant;
 pub use anchor_attribute_error::*;
-pub use anchor_attribute_event::{emit, event};
+pub use anchor_attribute_event::{emit, event, external_message};
+#[cfg(feature = "event-cpi")]
+pub use anchor_attribute_event::{emit_cpi, event_cpi};
 pub use anchor_attribute_interface::interface;
+#[cfg(feature = "declare-program")]
+pub use anchor_attribute_program::declare_program;
 pub use anchor_attribute_program::program;
 pub use anchor_attribute_state::state;
-pub use anchor_derive_accounts::Accounts;
+pub use anchor_derive_accounts::{accounts_fragment, with_fragments, Accounts};
+pub use anchor_derive_space::InitSpace;
 /// Borsh is the default serialization format for instructions and accounts.
 pub use borsh::{BorshDeserialize as AnchorDeserialize, BorshSerialize as AnchorSerialize};
 pub use solana_program;
@@ -202,6 +211,70 @@ pub trait EventData: AnchorSerialize + Discriminator {
 /// 8 byte unique identifier for a type.
 pub trait Discriminator {
     fn discriminator() -> [u8; 8];
+
+    /// The number of bytes of `discriminator()` the serialized data of the
+    /// type starts with, less than 8 for the custom discriminators given with
+    /// `#[account(discriminator = ..)]`, which are padded with zeros, and for
+    /// the accounts of programs given `#[program(discriminator_len = ..)]`.
+    fn discriminator_len() -> usize {
+        8
+    }
+}
+
+/// Lifecycle of an account, implemented by `#[state_machine(..)]` on an
+/// `#[account]`. States are named after the variants of the enum holding them.
+pub trait StateMachine {
+    /// The name of the current state, if it's one of the machine's.
+    fn state_name(&self) -> Option<&'static str>;
+
+    /// Whether the machine allows moving from the state `from` to `to`.
+    fn can_transition(from: &str, to: &str) -> bool;
+
+    /// Moves to the state named `to`, failing unless the machine allows it
+    /// from the current state.
+    fn transition_to(&mut self, to: &str) -> Result<()>;
+}
+
+/// Space taken by the serialization of a type at most, to allocate accounts
+/// with, e.g. `space = 8 + MyAccount::INIT_SPACE`. Implemented with
+/// [`InitSpace`](./derive.InitSpace.html) for user defined types.
+pub trait Space {
+    const INIT_SPACE: usize;
+}
+
+macro_rules! impl_space {
+    ($($ty:ty => $space:expr),* $(,)?) => {
+        $(
+            impl Space for $ty {
+                const INIT_SPACE: usize = $space;
+            }
+        )*
+    };
+}
+
+impl_space! {
+    bool => 1,
+    u8 => 1,
+    i8 => 1,
+    u16 => 2,
+    i16 => 2,
+    u32 => 4,
+    i32 => 4,
+    f32 => 4,
+    u64 => 8,
+    i64 => 8,
+    f64 => 8,
+    u128 => 16,
+    i128 => 16,
+    Pubkey => 32,
+}
+
+impl<T: Space> Space for Option<T> {
+    const INIT_SPACE: usize = 1 + T::INIT_SPACE;
+}
+
+impl<T: Space, const N: usize> Space for [T; N] {
+    const INIT_SPACE: usize = N * T::INIT_SPACE;
 }
 
 /// Bump seed for program derived addresses.
@@ -219,6 +292,19 @@ pub trait Id {
     fn id() -> Pubkey;
 }
 
+/// Defines the addresses allowed to own an account, for account types shared
+/// by several programs, e.g. the token accounts of the token program and of
+/// token-2022. See [`InterfaceAccount`](accounts::interface_account::InterfaceAccount).
+pub trait Owners {
+    fn owners() -> &'static [Pubkey];
+}
+
+/// Defines the ids of the programs implementing an interface. See
+/// [`Interface`](accounts::interface::Interface).
+pub trait Ids {
+    fn ids() -> &'static [Pubkey];
+}
+
 /// Defines the Pubkey of an account.
 pub trait Key {
     fn key(&self) -> Pubkey;
@@ -233,18 +319,27 @@ impl Key for Pubkey {
 /// The prelude contains all commonly used components of the crate.
 /// All programs should include it via `anchor_lang::prelude::*;`.
 pub mod prelude {
+    #[doc(hidden)]
+    pub use super::__private::__DISCRIMINATOR_LEN;
+    #[cfg(feature = "declare-program")]
+    pub use super::declare_program;
     pub use super::{
         access_control, account, accounts::account::Account,
-        accounts::account_loader::AccountLoader, accounts::program::Program,
-        accounts::signer::Signer, accounts::system_account::SystemAccount,
-        accounts::sysvar::Sysvar, accounts::unchecked_account::UncheckedAccount, constant,
-        context::Context, context::CpiContext, declare_id, emit, err, error, event, interface,
-        program, require, require_eq, require_gt, require_gte, require_keys_eq, require_keys_neq,
-        require_neq, solana_program::bpf_loader_upgradeable::UpgradeableLoaderState, source, state,
-        system_program::System, zero_copy, AccountDeserialize, AccountSerialize, Accounts,
-        AccountsExit, AnchorDeserialize, AnchorSerialize, Id, Key, Owner, ProgramData, Result,
-        ToAccountInfo, ToAccountInfos, ToAccountMetas,
+        accounts::account_loader::AccountLoader, accounts::interface::Interface,
+        accounts::interface_account::InterfaceAccount, accounts::lazy_account::LazyAccount,
+        accounts::program::Program, accounts::signer::Signer,
+        accounts::system_account::SystemAccount, accounts::sysvar::Sysvar,
+        accounts::unchecked_account::UncheckedAccount, accounts_fragment, admin, batch,
+        compute_budget, constant, context::Context, context::CpiContext, declare_id, emit, err,
+        error, event, external_message, interface, program, require, require_eq, require_gt,
+        require_gte, require_keys_eq, require_keys_neq, require_neq,
+        solana_program::bpf_loader_upgradeable::UpgradeableLoaderState, source, state, sunset,
+        system_program::System, with_fragments, zero_copy, AccountDeserialize, AccountSerialize,
+        Accounts, AccountsExit, AnchorDeserialize, AnchorSerialize, Id, Ids, InitSpace, Key, Owner,
+        Owners, ProgramData, Result, Space, ToAccountInfo, ToAccountInfos, ToAccountMetas,
     };
+    #[cfg(feature = "event-cpi")]
+    pub use super::{emit_cpi, event_cpi};
     pub use anchor_attribute_error::*;
     pub use borsh;
     pub use error::*;
@@ -272,22 +367,215 @@ pub mod __private {
     /// The discriminator anchor uses to mark an account as closed.
     pub const CLOSED_ACCOUNT_DISCRIMINATOR: [u8; 8] = [255, 255, 255, 255, 255, 255, 255, 255];
 
+    // Length of the discriminators of `#[account]`s, taken from the root of
+    // their crate, where it's imported with the prelude. `#[program]` declares
+    // its own there, shadowing this one, from its `discriminator_len`.
+    pub const __DISCRIMINATOR_LEN: usize = 8;
+
     pub use crate::ctor::Ctor;
 
     pub use anchor_attribute_account::ZeroCopyAccessor;
 
     pub use anchor_attribute_event::EventIndex;
 
+    pub use anchor_derive_accounts::__embed_fragment;
+
     pub use base64;
 
+    #[cfg(not(target_arch = "bpf"))]
+    pub use inventory;
+
     pub use bytemuck;
 
+    #[cfg(feature = "serde")]
+    pub use serde_dep as serde;
+
+    #[cfg(feature = "arbitrary")]
+    pub use arbitrary_dep as arbitrary;
+
     use solana_program::pubkey::Pubkey;
 
+    // The largest of two spaces, in constant expressions.
+    pub const fn max(a: usize, b: usize) -> usize {
+        if a > b {
+            a
+        } else {
+            b
+        }
+    }
+
     pub mod state {
         pub use crate::accounts::state::*;
     }
 
+    // The checked operations the arithmetic of the handlers is rewritten into
+    // by `#[program(checked_math)]`, returning `None` on overflow or division
+    // by zero.
+    #[doc(hidden)]
+    pub trait CheckedMath: Sized {
+        fn checked_math_add(self, rhs: Self) -> Option<Self>;
+        fn checked_math_sub(self, rhs: Self) -> Option<Self>;
+        fn checked_math_mul(self, rhs: Self) -> Option<Self>;
+        fn checked_math_div(self, rhs: Self) -> Option<Self>;
+    }
+
+    macro_rules! impl_checked_math_int {
+        ($($ty:ty),*) => {
+            $(
+                impl CheckedMath for $ty {
+                    fn checked_math_add(self, rhs: Self) -> Option<Self> {
+                        self.checked_add(rhs)
+                    }
+                    fn checked_math_sub(self, rhs: Self) -> Option<Self> {
+                        self.checked_sub(rhs)
+                    }
+                    fn checked_math_mul(self, rhs: Self) -> Option<Self> {
+                        self.checked_mul(rhs)
+                    }
+                    fn checked_math_div(self, rhs: Self) -> Option<Self> {
+                        self.checked_div(rhs)
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_checked_math_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+    // The operands of an operation rewritten by `#[program(checked_math)]`,
+    // which is only checked if both are the same primitive integer type, and
+    // left as is otherwise, e.g. for `a + &b` or `String + &str`. The types
+    // are only known once the macro is expanded, so
+    // `(&&CheckedOperands::new(a, b)).checked_math_add()` resolves to the
+    // method of `CheckedOp` if it's implemented for the operands, i.e. with
+    // the fewest auto-derefs, or else to the one of `UncheckedAdd`.
+    #[doc(hidden)]
+    pub struct CheckedOperands<L, R>(core::cell::Cell<Option<(L, R)>>);
+
+    impl<L, R> CheckedOperands<L, R> {
+        pub fn new(left: L, right: R) -> Self {
+            CheckedOperands(core::cell::Cell::new(Some((left, right))))
+        }
+
+        fn take(&self) -> (L, R) {
+            self.0.take().expect("The operands are taken once")
+        }
+    }
+
+    #[doc(hidden)]
+    pub trait CheckedOp {
+        type Output;
+        fn checked_math_add(&self) -> Option<Self::Output>;
+        fn checked_math_sub(&self) -> Option<Self::Output>;
+        fn checked_math_mul(&self) -> Option<Self::Output>;
+        fn checked_math_div(&self) -> Option<Self::Output>;
+    }
+
+    impl<T: CheckedMath> CheckedOp for &CheckedOperands<T, T> {
+        type Output = T;
+        fn checked_math_add(&self) -> Option<T> {
+            let (left, right) = self.take();
+            left.checked_math_add(right)
+        }
+        fn checked_math_sub(&self) -> Option<T> {
+            let (left, right) = self.take();
+            left.checked_math_sub(right)
+        }
+        fn checked_math_mul(&self) -> Option<T> {
+            let (left, right) = self.take();
+            left.checked_math_mul(right)
+        }
+        fn checked_math_div(&self) -> Option<T> {
+            let (left, right) = self.take();
+            left.checked_math_div(right)
+        }
+    }
+
+    // Compound assignments, given the assigned place.
+    #[doc(hidden)]
+    pub trait CheckedAssignOp {
+        fn checked_math_add_assign(&self) -> Option<()>;
+        fn checked_math_sub_assign(&self) -> Option<()>;
+        fn checked_math_mul_assign(&self) -> Option<()>;
+        fn checked_math_div_assign(&self) -> Option<()>;
+    }
+
+    impl<T: CheckedMath + Copy> CheckedAssignOp for &CheckedOperands<&mut T, T> {
+        fn checked_math_add_assign(&self) -> Option<()> {
+            let (left, right) = self.take();
+            *left = (*left).checked_math_add(right)?;
+            Some(())
+        }
+        fn checked_math_sub_assign(&self) -> Option<()> {
+            let (left, right) = self.take();
+            *left = (*left).checked_math_sub(right)?;
+            Some(())
+        }
+        fn checked_math_mul_assign(&self) -> Option<()> {
+            let (left, right) = self.take();
+            *left = (*left).checked_math_mul(right)?;
+            Some(())
+        }
+        fn checked_math_div_assign(&self) -> Option<()> {
+            let (left, right) = self.take();
+            *left = (*left).checked_math_div(right)?;
+            Some(())
+        }
+    }
+
+    macro_rules! impl_unchecked_op {
+        ($($op:ident::$op_method:ident, $trait:ident::$method:ident;)*) => {
+            $(
+                #[doc(hidden)]
+                pub trait $trait {
+                    type Output;
+                    fn $method(&self) -> Option<Self::Output>;
+                }
+
+                impl<L: core::ops::$op<R>, R> $trait for CheckedOperands<L, R> {
+                    type Output = L::Output;
+                    fn $method(&self) -> Option<L::Output> {
+                        let (left, right) = self.take();
+                        Some(core::ops::$op::$op_method(left, right))
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_unchecked_op!(
+        Add::add, UncheckedAdd::checked_math_add;
+        Sub::sub, UncheckedSub::checked_math_sub;
+        Mul::mul, UncheckedMul::checked_math_mul;
+        Div::div, UncheckedDiv::checked_math_div;
+    );
+
+    macro_rules! impl_unchecked_assign_op {
+        ($($op:ident::$op_method:ident, $trait:ident::$method:ident;)*) => {
+            $(
+                #[doc(hidden)]
+                pub trait $trait {
+                    fn $method(&self) -> Option<()>;
+                }
+
+                impl<L: core::ops::$op<R>, R> $trait for CheckedOperands<&mut L, R> {
+                    fn $method(&self) -> Option<()> {
+                        let (left, right) = self.take();
+                        core::ops::$op::$op_method(left, right);
+                        Some(())
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_unchecked_assign_op!(
+        AddAssign::add_assign, UncheckedAddAssign::checked_math_add_assign;
+        SubAssign::sub_assign, UncheckedSubAssign::checked_math_sub_assign;
+        MulAssign::mul_assign, UncheckedMulAssign::checked_math_mul_assign;
+        DivAssign::div_assign, UncheckedDivAssign::checked_math_div_assign;
+    );
+
     // Calculates the size of an account, which may be larger than the deserialized
     // data in it. This trait is currently only used for `#[state]` accounts.
     #[doc(hidden)]
@@ -393,6 +681,36 @@ macro_rules! require_eq {
     };
 }
 
+/// Asserts that two accounts, or other values of types defined with Anchor's
+/// macros, are equal, for tests.
+///
+/// On failure, the panic message is a field-level diff of the values laid out
+/// as described by the IDL, rather than their `Debug` output: only the fields
+/// and vec or array items that differ are expanded. Requires the `idl-build`
+/// feature.
+///
+/// # Example
+/// ```rust,ignore
+/// let vault: Vault = program.account(vault_pubkey)?;
+/// assert_accounts_eq!(Vault { balance: 10, ..expected }, vault);
+/// ```
+#[cfg(feature = "idl-build")]
+#[macro_export]
+macro_rules! assert_accounts_eq {
+    ($expected: expr, $actual: expr $(,)?) => {
+        match (&$expected, &$actual) {
+            (expected, actual) => {
+                if *expected != *actual {
+                    panic!(
+                        "assertion failed: `(expected == actual)`\n{}",
+                        anchor_lang::idl::build::diff(expected, actual)
+                    );
+                }
+            }
+        }
+    };
+}
+
 /// Ensures two NON-PUBKEY values are not equal.
 ///
 /// Use [require_keys_neq](crate::prelude::require_keys_neq)
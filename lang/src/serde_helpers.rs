@@ -0,0 +1,84 @@
+//! `#[serde(with = "..")]` modules used by the `serde` feature to give
+//! `#[account]` and `#[event]` types a JSON friendly representation, i.e.,
+//! public keys as base58 strings and bytes as base64 strings.
+//!
+//! These can also be used directly on user defined types nested inside
+//! accounts and events.
+
+use serde_dep::de::Error;
+use serde_dep::{Deserialize, Deserializer, Serializer};
+use solana_program::pubkey::Pubkey;
+use std::str::FromStr;
+
+/// (De)serializes a [`Pubkey`] as a base58 string.
+pub mod pubkey {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&pubkey.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Pubkey::from_str(&s).map_err(D::Error::custom)
+    }
+}
+
+/// (De)serializes an `Option<Pubkey>` as a base58 string or null.
+pub mod option_pubkey {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        pubkey: &Option<Pubkey>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match pubkey {
+            None => serializer.serialize_none(),
+            Some(pubkey) => serializer.serialize_some(&pubkey.to_string()),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Pubkey>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| Pubkey::from_str(&s).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// (De)serializes a `Vec<u8>` as a base64 string.
+pub mod bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        base64::decode(&s).map_err(D::Error::custom)
+    }
+}
+
+/// (De)serializes a `[u8; N]` as a base64 string.
+pub mod byte_array {
+    use super::*;
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        bytes: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        super::bytes::serialize(bytes, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        let bytes = super::bytes::deserialize(deserializer)?;
+        let len = bytes.len();
+        bytes
+            .try_into()
+            .map_err(|_| D::Error::custom(format!("expected {} bytes, found {}", N, len)))
+    }
+}
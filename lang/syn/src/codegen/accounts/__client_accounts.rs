@@ -1,5 +1,6 @@
-use crate::{AccountField, AccountsStruct, Ty};
-use heck::SnakeCase;
+use crate::parser::docs::without_check_comments;
+use crate::{AccountField, AccountsStruct, Field, Ty};
+use heck::{MixedCase, SnakeCase};
 use quote::quote;
 use std::str::FromStr;
 
@@ -21,7 +22,7 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
         .map(|f: &AccountField| match f {
             AccountField::CompositeField(s) => {
                 let name = &s.ident;
-                let docs = if let Some(ref docs) = s.docs {
+                let docs = if let Some(docs) = s.docs.as_deref().and_then(without_check_comments) {
                     docs.iter()
                         .map(|docs_line| {
                             proc_macro2::TokenStream::from_str(&format!(
@@ -48,7 +49,7 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
             }
             AccountField::Field(f) => {
                 let name = &f.ident;
-                let docs = if let Some(ref docs) = f.docs {
+                let docs = if let Some(docs) = f.docs.as_deref().and_then(without_check_comments) {
                     docs.iter()
                         .map(|docs_line| {
                             proc_macro2::TokenStream::from_str(&format!(
@@ -61,9 +62,13 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
                 } else {
                     quote!()
                 };
+                let ty = match f.is_optional {
+                    true => quote! { Option<anchor_lang::solana_program::pubkey::Pubkey> },
+                    false => quote! { anchor_lang::solana_program::pubkey::Pubkey },
+                };
                 quote! {
                     #docs
-                    pub #name: anchor_lang::solana_program::pubkey::Pubkey
+                    pub #name: #ty
                 }
             }
         })
@@ -92,9 +97,47 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
                     false => quote! { anchor_lang::solana_program::instruction::AccountMeta::new_readonly },
                     true => quote! { anchor_lang::solana_program::instruction::AccountMeta::new },
                 };
-                let name = &f.ident;
+                let meta = field_meta(f, meta, is_signer);
                 quote! {
-                    account_metas.push(#meta(self.#name, #is_signer));
+                    account_metas.push(#meta);
+                }
+            }
+        })
+        .collect();
+
+    let account_struct_metas_named: Vec<proc_macro2::TokenStream> = accs
+        .fields
+        .iter()
+        .map(|f: &AccountField| match f {
+            AccountField::CompositeField(s) => {
+                let name = &s.ident;
+                let name_str = name.to_string().to_mixed_case();
+                quote! {
+                    account_metas.extend(
+                        self.#name
+                            .to_account_metas_named()
+                            .into_iter()
+                            .map(|(n, meta)| (format!("{}.{}", #name_str, n), meta))
+                    );
+                }
+            }
+            AccountField::Field(f) => {
+                let is_signer = match f.ty {
+                    Ty::Signer => true,
+                    _ => f.constraints.is_signer(),
+                };
+                let is_signer = match is_signer {
+                    false => quote! {false},
+                    true => quote! {true},
+                };
+                let meta = match f.constraints.is_mutable() {
+                    false => quote! { anchor_lang::solana_program::instruction::AccountMeta::new_readonly },
+                    true => quote! { anchor_lang::solana_program::instruction::AccountMeta::new },
+                };
+                let name_str = super::idl_account_name(f);
+                let meta = field_meta(f, meta, is_signer);
+                quote! {
+                    account_metas.push((#name_str.to_string(), #meta));
                 }
             }
         })
@@ -166,6 +209,40 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
                     account_metas
                 }
             }
+
+            #[automatically_derived]
+            impl #name {
+                /// Same as `to_account_metas`, but with each meta paired with
+                /// the name of its account in the IDL, i.e. its field in
+                /// mixedCase. Fields of composite accounts are prefixed with
+                /// the composite field name, e.g. `"composite.fieldName"`.
+                pub fn to_account_metas_named(&self) -> Vec<(String, anchor_lang::solana_program::instruction::AccountMeta)> {
+                    let mut account_metas = vec![];
+
+                    #(#account_struct_metas_named)*
+
+                    account_metas
+                }
+            }
         }
     }
 }
+
+// The meta of an account field, which is the program id for optional accounts
+// that aren't given.
+fn field_meta(
+    f: &Field,
+    meta: proc_macro2::TokenStream,
+    is_signer: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let name = &f.ident;
+    match f.is_optional {
+        true => quote! {
+            match self.#name {
+                Some(key) => #meta(key, #is_signer),
+                None => anchor_lang::solana_program::instruction::AccountMeta::new_readonly(crate::ID, false),
+            }
+        },
+        false => quote! { #meta(self.#name, #is_signer) },
+    }
+}
@@ -1,5 +1,6 @@
 use std::str::FromStr;
 
+use crate::parser::docs::without_check_comments;
 use crate::{AccountField, AccountsStruct, Ty};
 use heck::SnakeCase;
 use quote::quote;
@@ -22,7 +23,7 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
         .map(|f: &AccountField| match f {
             AccountField::CompositeField(s) => {
                 let name = &s.ident;
-                let docs = if let Some(ref docs) = s.docs {
+                let docs = if let Some(docs) = s.docs.as_deref().and_then(without_check_comments) {
                     docs.iter()
                         .map(|docs_line| {
                             proc_macro2::TokenStream::from_str(&format!(
@@ -49,7 +50,7 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
             }
             AccountField::Field(f) => {
                 let name = &f.ident;
-                let docs = if let Some(ref docs) = f.docs {
+                let docs = if let Some(docs) = f.docs.as_deref().and_then(without_check_comments) {
                     docs.iter()
                         .map(|docs_line| {
                             proc_macro2::TokenStream::from_str(&format!(
@@ -62,9 +63,13 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
                 } else {
                     quote!()
                 };
+                let ty = match f.is_optional {
+                    true => quote! { Option<anchor_lang::solana_program::account_info::AccountInfo<'info>> },
+                    false => quote! { anchor_lang::solana_program::account_info::AccountInfo<'info> },
+                };
                 quote! {
                     #docs
-                    pub #name: anchor_lang::solana_program::account_info::AccountInfo<'info>
+                    pub #name: #ty
                 }
             }
         })
@@ -94,8 +99,17 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
                     true => quote! { anchor_lang::solana_program::instruction::AccountMeta::new },
                 };
                 let name = &f.ident;
-                quote! {
-                    account_metas.push(#meta(anchor_lang::Key::key(&self.#name), #is_signer));
+                match f.is_optional {
+                    // Optional accounts that aren't given are the program id.
+                    true => quote! {
+                        account_metas.push(match &self.#name {
+                            Some(account) => #meta(anchor_lang::Key::key(account), #is_signer),
+                            None => anchor_lang::solana_program::instruction::AccountMeta::new_readonly(crate::ID, false),
+                        });
+                    },
+                    false => quote! {
+                        account_metas.push(#meta(anchor_lang::Key::key(&self.#name), #is_signer));
+                    },
                 }
             }
         })
@@ -113,8 +127,13 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
             }
             AccountField::Field(f) => {
                 let name = &f.ident;
-                quote! {
-                    account_infos.push(anchor_lang::ToAccountInfo::to_account_info(&self.#name));
+                match f.is_optional {
+                    true => quote! {
+                        account_infos.extend(self.#name.clone());
+                    },
+                    false => quote! {
+                        account_infos.push(anchor_lang::ToAccountInfo::to_account_info(&self.#name));
+                    },
                 }
             }
         })
@@ -17,9 +17,31 @@ pub fn generate(f: &Field) -> proc_macro2::TokenStream {
         .map(|c| generate_constraint(f, c))
         .collect();
 
-    quote! {
-        #rent
-        #(#checks)*
+    let transition = f
+        .constraints
+        .state_machine
+        .as_ref()
+        .and_then(|c| c.transition_to.as_ref())
+        .map(|state| generate_transition(f, state))
+        .unwrap_or_else(|| quote! {});
+
+    // The constraints of optional accounts only apply when they're given.
+    match f.is_optional {
+        true => {
+            let ident = &f.ident;
+            quote! {
+                if let Some(#ident) = &#ident {
+                    #rent
+                    #(#checks)*
+                }
+                #transition
+            }
+        }
+        false => quote! {
+            #rent
+            #(#checks)*
+            #transition
+        },
     }
 }
 
@@ -60,6 +82,8 @@ pub fn linearize(c_group: &ConstraintGroup) -> Vec<Constraint> {
         token_account,
         mint,
         realloc,
+        state_machine,
+        metadata,
     } = c_group.clone();
 
     let mut constraints = Vec::new();
@@ -112,6 +136,12 @@ pub fn linearize(c_group: &ConstraintGroup) -> Vec<Constraint> {
     if let Some(c) = mint {
         constraints.push(Constraint::Mint(c));
     }
+    if let Some(c) = state_machine {
+        constraints.push(Constraint::StateMachine(c));
+    }
+    if let Some(c) = metadata {
+        constraints.push(Constraint::Metadata(c));
+    }
     constraints
 }
 
@@ -135,6 +165,8 @@ fn generate_constraint(f: &Field, c: &Constraint) -> proc_macro2::TokenStream {
         Constraint::TokenAccount(c) => generate_constraint_token_account(f, c),
         Constraint::Mint(c) => generate_constraint_mint(f, c),
         Constraint::Realloc(c) => generate_constraint_realloc(f, c),
+        Constraint::StateMachine(c) => generate_constraint_state_machine(f, c),
+        Constraint::Metadata(c) => generate_constraint_metadata(f, c),
     }
 }
 
@@ -216,6 +248,7 @@ pub fn generate_constraint_has_one(f: &Field, c: &ConstraintHasOne) -> proc_macr
     let field = match &f.ty {
         Ty::Loader(_) => quote! {#ident.load()?},
         Ty::AccountLoader(_) => quote! {#ident.load()?},
+        Ty::LazyAccount(_) => quote! {#ident.load()?},
         _ => quote! {#ident},
     };
     let error = generate_custom_error(
@@ -243,6 +276,8 @@ pub fn generate_constraint_signer(f: &Field, c: &ConstraintSigner) -> proc_macro
         Ty::Account(_) => quote! { #ident.to_account_info() },
         Ty::Loader(_) => quote! { #ident.to_account_info() },
         Ty::AccountLoader(_) => quote! { #ident.to_account_info() },
+        Ty::LazyAccount(_) => quote! { #ident.to_account_info() },
+        Ty::InterfaceAccount(_) => quote! { #ident.to_account_info() },
         Ty::CpiAccount(_) => quote! { #ident.to_account_info() },
         _ => panic!("Invalid syntax: signer cannot be specified."),
     };
@@ -710,10 +745,13 @@ fn generate_constraint_seeds(f: &Field, c: &ConstraintSeedsGroup) -> proc_macro2
                 );
                 __bumps.insert(#name_str.to_string(), __bump);
             },
-            // Bump target given. Use it.
+            // Bump target given. Use it. A bump stored in an account, e.g.
+            // `bump = vault.bump`, must be a `u8` field, which the binding
+            // checks where the bump is given.
             Some(b) => quote! {
+                let __bump: u8 = #b;
                 let __pda_address = Pubkey::create_program_address(
-                    &[#maybe_seeds_plus_comma &[#b][..]],
+                    &[#maybe_seeds_plus_comma &[__bump][..]],
                     &#deriving_program_id,
                 ).map_err(|_| anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintSeeds).with_account_name(#name_str))?;
             },
@@ -811,6 +849,58 @@ fn generate_constraint_mint(f: &Field, c: &ConstraintTokenMintGroup) -> proc_mac
     }
 }
 
+fn generate_constraint_metadata(
+    f: &Field,
+    c: &ConstraintMetadataGroup,
+) -> proc_macro2::TokenStream {
+    let name = &f.ident;
+    let name_str = name.to_string();
+
+    let address_check = match &c.mint {
+        Some(mint) => quote! {
+            {
+                let __metadata_address = anchor_spl::metadata::find_metadata_address(&#mint.key());
+                let my_key = #name.key();
+                if my_key != __metadata_address {
+                    return Err(anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintMetadataAddress).with_account_name(#name_str).with_pubkeys((my_key, __metadata_address)));
+                }
+            }
+        },
+        None => quote! {},
+    };
+    // The name and symbol are stored padded with null bytes.
+    let name_check = match &c.name {
+        Some(metadata_name) => quote! {
+            if #name.data.name.trim_end_matches('\0') != AsRef::<str>::as_ref(&#metadata_name) {
+                return Err(anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintMetadata).with_account_name(#name_str));
+            }
+        },
+        None => quote! {},
+    };
+    let symbol_check = match &c.symbol {
+        Some(symbol) => quote! {
+            if #name.data.symbol.trim_end_matches('\0') != AsRef::<str>::as_ref(&#symbol) {
+                return Err(anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintMetadata).with_account_name(#name_str));
+            }
+        },
+        None => quote! {},
+    };
+    let update_authority_check = match &c.update_authority {
+        Some(update_authority) => quote! {
+            if #name.update_authority != anchor_lang::Key::key(&#update_authority) {
+                return Err(anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintMetadata).with_account_name(#name_str).with_pubkeys((#name.update_authority, anchor_lang::Key::key(&#update_authority))));
+            }
+        },
+        None => quote! {},
+    };
+    quote! {
+        #address_check
+        #name_check
+        #symbol_check
+        #update_authority_check
+    }
+}
+
 // Generated code to create an account with with system program with the
 // given `space` amount of data, owned by `owner`.
 //
@@ -867,6 +957,58 @@ pub fn generate_create_account(
     }
 }
 
+// Checks the account is in one of the given states of its state machine.
+// Moving it to the state given by `transition_to` is done once all the
+// constraints are checked, see `generate_transition`.
+fn generate_constraint_state_machine(
+    f: &Field,
+    c: &ConstraintStateMachineGroup,
+) -> proc_macro2::TokenStream {
+    if c.states.is_empty() {
+        return quote! {};
+    }
+    let ident = &f.ident;
+    let name_str = ident.to_string();
+    let states = c.states.iter().map(|state| state.to_string());
+    quote! {
+        {
+            use anchor_lang::StateMachine as _;
+            if !matches!(#ident.state_name(), #(Some(#states))|*) {
+                return Err(anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintStateMachine).with_account_name(#name_str));
+            }
+        }
+    }
+}
+
+// Moves the account to the given state of its state machine, which is
+// written back to the account on exit, as the account is mutable.
+fn generate_transition(f: &Field, state: &Ident) -> proc_macro2::TokenStream {
+    let ident = &f.ident;
+    let name_str = ident.to_string();
+    let state = state.to_string();
+    let transition = quote! {
+        #ident
+            .transition_to(#state)
+            .map_err(|e| e.with_account_name(#name_str))?;
+    };
+    match f.is_optional {
+        true => quote! {
+            let mut #ident = #ident;
+            if let Some(#ident) = #ident.as_mut() {
+                use anchor_lang::StateMachine as _;
+                #transition
+            }
+        },
+        false => quote! {
+            let mut #ident = #ident;
+            {
+                use anchor_lang::StateMachine as _;
+                #transition
+            }
+        },
+    }
+}
+
 pub fn generate_constraint_executable(
     f: &Field,
     _c: &ConstraintExecutable,
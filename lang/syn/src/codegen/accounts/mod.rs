@@ -1,4 +1,5 @@
-use crate::AccountsStruct;
+use crate::{AccountsStruct, Field};
+use heck::MixedCase;
 use quote::quote;
 use std::iter;
 use syn::punctuated::Punctuated;
@@ -8,16 +9,18 @@ use syn::{GenericParam, PredicateLifetime, WhereClause, WherePredicate};
 mod __client_accounts;
 mod __cpi_client_accounts;
 mod constraints;
-mod exit;
+pub(crate) mod exit;
+mod remaining_accounts;
 mod to_account_infos;
 mod to_account_metas;
-mod try_accounts;
+pub(crate) mod try_accounts;
 
 pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
     let impl_try_accounts = try_accounts::generate(accs);
     let impl_to_account_infos = to_account_infos::generate(accs);
     let impl_to_account_metas = to_account_metas::generate(accs);
     let impl_exit = exit::generate(accs);
+    let impl_remaining_accounts = remaining_accounts::generate(accs);
 
     let __client_accounts_mod = __client_accounts::generate(accs);
     let __cpi_client_accounts_mod = __cpi_client_accounts::generate(accs);
@@ -27,12 +30,23 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
         #impl_to_account_infos
         #impl_to_account_metas
         #impl_exit
+        #impl_remaining_accounts
 
         #__client_accounts_mod
         #__cpi_client_accounts_mod
     }
 }
 
+// Name of an account in the IDL, which `to_account_metas_named` pairs its metas
+// with, so that they match `IdlInstruction::name_accounts`. The accounts of
+// fragments are grouped under the field of the fragment, as in the IDL.
+fn idl_account_name(f: &Field) -> String {
+    match &f.fragment {
+        Some((group, name)) => format!("{}.{}", group.to_mixed_case(), name.to_mixed_case()),
+        None => f.ident.to_string().to_mixed_case(),
+    }
+}
+
 fn generics(accs: &AccountsStruct) -> ParsedGenerics {
     let trait_lifetime = accs
         .generics
@@ -0,0 +1,47 @@
+use crate::codegen::accounts::{generics, ParsedGenerics};
+use crate::AccountsStruct;
+use quote::quote;
+
+// Generates the `remaining_accounts` function of the struct, iterating over the
+// remaining accounts as `Account`s of the type given by
+// `#[remaining_accounts(..)]`.
+pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
+    let remaining_accounts = match &accs.remaining_accounts {
+        Some(remaining_accounts) => remaining_accounts,
+        None => return quote! {},
+    };
+    let name = &accs.ident;
+    let ParsedGenerics {
+        combined_generics,
+        trait_generics,
+        struct_generics,
+        where_clause,
+    } = generics(accs);
+    let ty = &remaining_accounts.ty;
+    let mut_check = match remaining_accounts.mutable {
+        true => quote! {
+            if !info.is_writable {
+                return Err(anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintMut)
+                    .with_account_name(format!("remaining_accounts[{}]", index)));
+            }
+        },
+        false => quote! {},
+    };
+    quote! {
+        #[automatically_derived]
+        impl<#combined_generics> #name<#struct_generics> #where_clause {
+            /// Iterates over the remaining accounts, checking that each is an
+            /// account of the type given by `#[remaining_accounts(..)]`.
+            pub fn remaining_accounts<'__a>(
+                remaining_accounts: &'__a [anchor_lang::solana_program::account_info::AccountInfo<#trait_generics>],
+            ) -> impl Iterator<Item = anchor_lang::Result<anchor_lang::accounts::account::Account<#trait_generics, #ty>>> + '__a {
+                remaining_accounts.iter().enumerate().map(|(index, info)| {
+                    #mut_check
+                    anchor_lang::accounts::account::Account::try_from(info).map_err(|err| {
+                        err.with_account_name(format!("remaining_accounts[{}]", index))
+                    })
+                })
+            }
+        }
+    }
+}
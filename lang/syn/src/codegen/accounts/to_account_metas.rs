@@ -1,4 +1,5 @@
 use crate::{AccountField, AccountsStruct};
+use heck::MixedCase;
 use quote::quote;
 
 // Generates the `ToAccountMetas` trait implementation.
@@ -11,6 +12,21 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
         .map(|f: &AccountField| {
             let (name, is_signer) = match f {
                 AccountField::CompositeField(s) => (&s.ident, quote! {None}),
+                AccountField::Field(f) if f.is_optional => {
+                    let name = &f.ident;
+                    let is_signer = match f.constraints.is_signer() {
+                        false => quote! {None},
+                        true => quote! {Some(true)},
+                    };
+                    return quote! {
+                        match &self.#name {
+                            Some(account) => account_metas.extend(account.to_account_metas(#is_signer)),
+                            None => account_metas.push(
+                                anchor_lang::solana_program::instruction::AccountMeta::new_readonly(crate::ID, false)
+                            ),
+                        }
+                    };
+                }
                 AccountField::Field(f) => {
                     let is_signer = match f.constraints.is_signer() {
                         false => quote! {None},
@@ -25,6 +41,53 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
         })
         .collect();
 
+    let to_acc_metas_named: Vec<proc_macro2::TokenStream> = accs
+        .fields
+        .iter()
+        .map(|f: &AccountField| match f {
+            AccountField::CompositeField(s) => {
+                let name = &s.ident;
+                let name_str = name.to_string().to_mixed_case();
+                quote! {
+                    account_metas.extend(
+                        self.#name
+                            .to_account_metas_named()
+                            .into_iter()
+                            .map(|(n, meta)| (format!("{}.{}", #name_str, n), meta))
+                    );
+                }
+            }
+            AccountField::Field(f) => {
+                let name = &f.ident;
+                let name_str = super::idl_account_name(f);
+                let is_signer = match f.constraints.is_signer() {
+                    false => quote! {None},
+                    true => quote! {Some(true)},
+                };
+                let metas = match f.is_optional {
+                    true => quote! {
+                        match &self.#name {
+                            Some(account) => anchor_lang::ToAccountMetas::to_account_metas(account, #is_signer),
+                            None => vec![
+                                anchor_lang::solana_program::instruction::AccountMeta::new_readonly(crate::ID, false)
+                            ],
+                        }
+                    },
+                    false => quote! {
+                        anchor_lang::ToAccountMetas::to_account_metas(&self.#name, #is_signer)
+                    },
+                };
+                quote! {
+                    account_metas.extend(
+                        #metas
+                            .into_iter()
+                            .map(|meta| (#name_str.to_string(), meta))
+                    );
+                }
+            }
+        })
+        .collect();
+
     let (impl_gen, ty_gen, where_clause) = accs.generics.split_for_impl();
 
     quote! {
@@ -38,5 +101,20 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
                 account_metas
             }
         }
+
+        #[automatically_derived]
+        impl #impl_gen #name #ty_gen #where_clause{
+            /// Same as `to_account_metas`, but with each meta paired with the
+            /// name of its account in the IDL, i.e. its field in mixedCase.
+            /// Fields of composite accounts are prefixed with the composite
+            /// field name, e.g. `"composite.fieldName"`.
+            pub fn to_account_metas_named(&self) -> Vec<(String, anchor_lang::solana_program::instruction::AccountMeta)> {
+                let mut account_metas = vec![];
+
+                #(#to_acc_metas_named)*
+
+                account_metas
+            }
+        }
     }
 }
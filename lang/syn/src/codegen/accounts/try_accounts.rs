@@ -57,6 +57,7 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
         .collect();
 
     let constraints = generate_constraints(accs);
+    let distinct_checks = generate_distinct_checks(accs);
     let accounts_instance = generate_accounts_instance(accs);
 
     let ix_de = match &accs.instruction_api {
@@ -106,6 +107,8 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
                 #(#deser_fields)*
                 // Execute accounts constraints.
                 #constraints
+                // Check that the accounts of `#[distinct_mut]` aren't aliased.
+                #distinct_checks
                 // Success. Return the validated accounts.
                 Ok(#accounts_instance)
             }
@@ -147,6 +150,38 @@ pub fn generate_constraints(accs: &AccountsStruct) -> proc_macro2::TokenStream {
     }
 }
 
+// Errors if the same key is given for both accounts of a `#[distinct_mut]`
+// pair, skipping omitted optional accounts.
+fn generate_distinct_checks(accs: &AccountsStruct) -> proc_macro2::TokenStream {
+    let key = |ident: &syn::Ident| {
+        let is_optional = accs.fields.iter().any(|af| match af {
+            AccountField::Field(f) => &f.ident == ident && f.is_optional,
+            AccountField::CompositeField(_) => false,
+        });
+        match is_optional {
+            true => quote! { #ident.as_ref().map(|acc| acc.key()) },
+            false => quote! { Some(#ident.key()) },
+        }
+    };
+    let checks = accs.distinct_mut.iter().map(|(a, b)| {
+        let a_key = key(a);
+        let b_key = key(b);
+        let b_str = b.to_string();
+        quote! {
+            if let (Some(__a), Some(__b)) = (#a_key, #b_key) {
+                if __a == __b {
+                    return Err(anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::ConstraintDuplicateMutableAccount)
+                        .with_account_name(#b_str)
+                        .with_pubkeys((__a, __b)));
+                }
+            }
+        }
+    });
+    quote! {
+        #(#checks)*
+    }
+}
+
 pub fn generate_accounts_instance(accs: &AccountsStruct) -> proc_macro2::TokenStream {
     let name = &accs.ident;
     // Each field in the final deserialized accounts struct.
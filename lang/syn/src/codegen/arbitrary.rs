@@ -0,0 +1,174 @@
+use crate::parser::space;
+use quote::quote;
+use syn::parse_quote;
+
+// Generates an `arbitrary::Arbitrary` implementation for an `#[account]` or
+// `#[event]` struct, for fuzzing and property testing. Requires the
+// `arbitrary` feature of `anchor-lang`.
+//
+// Public keys are built from arbitrary bytes, and `String`/`Vec` fields
+// annotated with `#[max_len(..)]` are bounded accordingly, with one length
+// per level of nesting, e.g. `#[max_len(10, 32)]` for a `Vec<String>`. The
+// `#[max_len]` attributes are removed from the struct, unless it derives
+// `InitSpace` which reads them too, so this must be called even when the
+// implementation isn't emitted.
+pub fn generate(strct: &mut syn::ItemStruct) -> proc_macro2::TokenStream {
+    let keep_max_len = space::derives_init_space(&strct.attrs);
+    let construct = gen_construct(quote! { Self }, &mut strct.fields, keep_max_len);
+    gen_impl(&strct.ident, &strct.generics, quote! { Ok(#construct) })
+}
+
+// Same as `generate`, for an `#[account]` enum, choosing its variant from
+// `u` too.
+pub fn generate_enum(enm: &mut syn::ItemEnum) -> proc_macro2::TokenStream {
+    let keep_max_len = space::derives_init_space(&enm.attrs);
+    let variants: Vec<proc_macro2::TokenStream> = enm
+        .variants
+        .iter_mut()
+        .map(|variant| {
+            let ident = &variant.ident;
+            gen_construct(quote! { Self::#ident }, &mut variant.fields, keep_max_len)
+        })
+        .collect();
+    let body = match variants.len() {
+        0 => quote! { Err(anchor_lang::__private::arbitrary::Error::IncorrectFormat) },
+        len => {
+            let last = len as u32 - 1;
+            let indices = 0..len as u32;
+            quote! {
+                Ok(match u.int_in_range(0..=#last)? {
+                    #(#indices => #variants,)*
+                    _ => unreachable!(),
+                })
+            }
+        }
+    };
+    gen_impl(&enm.ident, &enm.generics, body)
+}
+
+// Expression constructing the struct or variant at `path` from arbitrary
+// values of its fields.
+fn gen_construct(
+    path: proc_macro2::TokenStream,
+    fields: &mut syn::Fields,
+    keep_max_len: bool,
+) -> proc_macro2::TokenStream {
+    let values: Vec<proc_macro2::TokenStream> = fields
+        .iter_mut()
+        .map(|field| {
+            let max_len = take_max_len(field, keep_max_len);
+            let value = gen_value(&field.ty, &max_len);
+            match &field.ident {
+                Some(ident) => quote! { #ident: #value },
+                None => value,
+            }
+        })
+        .collect();
+    match fields {
+        syn::Fields::Named(_) => quote! { #path { #(#values),* } },
+        syn::Fields::Unnamed(_) => quote! { #path(#(#values),*) },
+        syn::Fields::Unit => quote! { #path },
+    }
+}
+
+fn gen_impl(
+    name: &syn::Ident,
+    generics: &syn::Generics,
+    body: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let mut impl_generics = generics.clone();
+    for param in impl_generics.type_params_mut() {
+        param.bounds.push(parse_quote!(
+            anchor_lang::__private::arbitrary::Arbitrary<'arbitrary>
+        ));
+    }
+    impl_generics.params.insert(0, parse_quote!('arbitrary));
+    let (impl_gen, _, where_clause) = impl_generics.split_for_impl();
+    let (_, ty_gen, _) = generics.split_for_impl();
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_gen anchor_lang::__private::arbitrary::Arbitrary<'arbitrary> for #name #ty_gen #where_clause {
+            fn arbitrary(
+                u: &mut anchor_lang::__private::arbitrary::Unstructured<'arbitrary>,
+            ) -> anchor_lang::__private::arbitrary::Result<Self> {
+                #body
+            }
+        }
+    }
+}
+
+// Removes the `#[max_len(..)]` attribute from the field unless `keep` is set,
+// returning its lengths.
+fn take_max_len(field: &mut syn::Field, keep: bool) -> Vec<syn::Expr> {
+    let max_len = space::max_len(&field.attrs).expect("Invalid max_len attribute");
+    if !keep {
+        field.attrs.retain(|attr| !attr.path.is_ident("max_len"));
+    }
+    max_len
+}
+
+// Expression evaluating to an arbitrary value of the given type, using `u`.
+fn gen_value(ty: &syn::Type, lens: &[syn::Expr]) -> proc_macro2::TokenStream {
+    let (max_len, inner_lens) = match lens.split_first() {
+        Some((max_len, rest)) => (Some(max_len), rest),
+        None => (None, lens),
+    };
+    let segment = match ty {
+        syn::Type::Path(ty_path) => ty_path.path.segments.last(),
+        _ => None,
+    };
+    let segment = match segment {
+        Some(segment) => segment,
+        None => return quote! { u.arbitrary()? },
+    };
+    let generic_arg = match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    };
+    match (segment.ident.to_string().as_str(), generic_arg) {
+        ("Pubkey", None) => quote! {
+            anchor_lang::solana_program::pubkey::Pubkey::new_from_array(u.arbitrary()?)
+        },
+        ("Option", Some(inner)) => {
+            // Options don't have a length of their own.
+            let inner = gen_value(inner, lens);
+            quote! {
+                if u.arbitrary()? { Some(#inner) } else { None }
+            }
+        }
+        ("Vec", Some(inner)) => {
+            let len = match max_len {
+                Some(max_len) => quote! { u.int_in_range(0..=(#max_len) as usize)? },
+                None => quote! { u.arbitrary_len::<u8>()? },
+            };
+            let inner = gen_value(inner, inner_lens);
+            quote! {
+                {
+                    let len = #len;
+                    let mut v = Vec::with_capacity(len);
+                    for _ in 0..len {
+                        v.push(#inner);
+                    }
+                    v
+                }
+            }
+        }
+        ("String", None) => match max_len {
+            None => quote! { u.arbitrary()? },
+            Some(max_len) => quote! {
+                {
+                    let mut s: String = u.arbitrary()?;
+                    while s.len() > (#max_len) as usize {
+                        s.pop();
+                    }
+                    s
+                }
+            },
+        },
+        _ => quote! { u.arbitrary()? },
+    }
+}
@@ -21,8 +21,11 @@ pub fn generate(error: Error) -> proc_macro2::TokenStream {
                     }
                 }
                 Some(msg) => {
+                    // Named placeholders, e.g. `{amount}`, are filled in by
+                    // clients, so they are displayed as is.
+                    let msg = msg.replace("{{", "{").replace("}}", "}");
                     quote! {
-                        write!(fmt, #msg)
+                        fmt.write_str(#msg)
                     }
                 }
             };
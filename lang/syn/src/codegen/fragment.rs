@@ -0,0 +1,271 @@
+use proc_macro2::{Spacing, TokenStream, TokenTree};
+use quote::quote;
+use std::collections::HashSet;
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+
+// Accounts fragments, reusable groups of accounts embedded into `Accounts`
+// structs.
+//
+// `#[accounts_fragment]` turns a struct into a `macro_rules!` macro of the same
+// name, holding the struct. `#[with_fragments]` on an `Accounts` struct
+// replaces the struct by a call to the macro of its first field marked with
+// `#[fragment]`, which hands both structs to `__embed_fragment!`. The fields
+// of the fragment replace the marked field, prefixed with its name, e.g.
+// `base_mint` for the `mint` of a `base` field, along with the references to
+// them in their constraints. References to other accounts are left as they
+// are, i.e. to the accounts of the struct the fragment is embedded into. The
+// struct then goes through the macro of its next fragment, until there are
+// none left.
+//
+// The embedded fields are marked with `#[from_fragment(<field>, <name>)]`, for
+// the IDL to group them under the field's name.
+
+// The macro of a fragment.
+pub fn accounts_fragment(strct: &syn::ItemStruct) -> TokenStream {
+    let name = &strct.ident;
+    quote! {
+        #[allow(unused_macros)]
+        macro_rules! #name {
+            ($($target:tt)*) => {
+                anchor_lang::__private::__embed_fragment! { { #strct } $($target)* }
+            };
+        }
+        #[allow(unused_imports)]
+        pub(crate) use #name;
+    }
+}
+
+// The struct with the first field marked with `#[fragment]` replaced by the
+// fields of its fragment, through the macro of the fragment, or the struct as
+// it is once all fragments are embedded.
+pub fn expand(strct: &syn::ItemStruct) -> syn::Result<TokenStream> {
+    let field = match strct.fields.iter().find(|f| fragment_attr(f).is_some()) {
+        Some(field) => field,
+        None => return Ok(quote! { #strct }),
+    };
+    let mut path = match &field.ty {
+        syn::Type::Path(ty_path) if ty_path.qself.is_none() => ty_path.path.clone(),
+        ty => return Err(syn::Error::new(ty.span(), "Expected an accounts fragment")),
+    };
+    if let Some(segment) = path.segments.last_mut() {
+        segment.arguments = syn::PathArguments::None;
+    }
+    Ok(quote! { #path! { #strct } })
+}
+
+// Embeds `fragment` into the first field of `target` marked with `#[fragment]`
+// and expands the struct with the next one.
+pub fn embed(fragment: &syn::ItemStruct, target: &syn::ItemStruct) -> syn::Result<TokenStream> {
+    let fragment_fields = match &fragment.fields {
+        syn::Fields::Named(fields) => &fields.named,
+        _ => {
+            return Err(syn::Error::new(
+                fragment.ident.span(),
+                "Accounts fragments must have named fields",
+            ))
+        }
+    };
+    let names: HashSet<String> = fragment_fields
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap().to_string())
+        .collect();
+
+    let mut target = target.clone();
+    let fields = match &mut target.fields {
+        syn::Fields::Named(fields) => &mut fields.named,
+        _ => return Ok(quote! { #target }),
+    };
+    let mut embedded = syn::punctuated::Punctuated::<syn::Field, syn::Token![,]>::new();
+    let mut found = false;
+    for field in std::mem::take(fields) {
+        let attr = match fragment_attr(&field) {
+            Some(attr) if !found => attr,
+            _ => {
+                embedded.push(field);
+                continue;
+            }
+        };
+        found = true;
+        let field_name = field.ident.as_ref().unwrap();
+        let prefix = match attr.tokens.is_empty() {
+            true => field_name.to_string(),
+            false => attr.parse_args::<FragmentArgs>()?.prefix,
+        };
+        for fragment_field in fragment_fields {
+            let name = fragment_field.ident.as_ref().unwrap();
+            let mut attrs: Vec<syn::Attribute> = fragment_field
+                .attrs
+                .iter()
+                .map(|attr| match attr.path.is_ident("account") {
+                    true => syn::Attribute {
+                        tokens: rename(attr.tokens.clone(), &names, &prefix),
+                        ..attr.clone()
+                    },
+                    false => attr.clone(),
+                })
+                .collect();
+            attrs.push(syn::parse_quote! { #[from_fragment(#field_name, #name)] });
+            embedded.push(syn::Field {
+                attrs,
+                ident: Some(syn::Ident::new(
+                    &format!("{}_{}", prefix, name),
+                    name.span(),
+                )),
+                ..fragment_field.clone()
+            });
+        }
+    }
+    *fields = embedded;
+    expand(&target)
+}
+
+// The input of `__embed_fragment!`, the fragment in braces followed by the
+// struct it's embedded into.
+pub struct EmbedFragment {
+    pub fragment: syn::ItemStruct,
+    pub target: syn::ItemStruct,
+}
+
+impl Parse for EmbedFragment {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content;
+        syn::braced!(content in input);
+        Ok(EmbedFragment {
+            fragment: content.parse()?,
+            target: input.parse()?,
+        })
+    }
+}
+
+fn fragment_attr(field: &syn::Field) -> Option<&syn::Attribute> {
+    field
+        .attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("fragment"))
+}
+
+// `#[fragment(prefix = "..")]`.
+struct FragmentArgs {
+    prefix: String,
+}
+
+impl Parse for FragmentArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key: syn::Ident = input.parse()?;
+        if key != "prefix" {
+            return Err(syn::Error::new(key.span(), "Expected `prefix = \"..\"`"));
+        }
+        input.parse::<syn::Token![=]>()?;
+        let prefix: syn::LitStr = input.parse()?;
+        Ok(FragmentArgs {
+            prefix: prefix.value(),
+        })
+    }
+}
+
+// Prefixes the references to the fields of the fragment in the tokens of a
+// constraint, i.e. their names unless they're a path segment after `::` or a
+// member after `.`, or a namespace before `::`, e.g. `mint` in
+// `token::mint = mint` or `mint::decimals`.
+fn rename(tokens: TokenStream, names: &HashSet<String>, prefix: &str) -> TokenStream {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let is_colon = |token: Option<&TokenTree>| matches!(token, Some(TokenTree::Punct(punct)) if punct.as_char() == ':');
+    let is_dot = |token: Option<&TokenTree>| matches!(token, Some(TokenTree::Punct(punct)) if punct.as_char() == '.' && punct.spacing() == Spacing::Alone);
+    tokens
+        .iter()
+        .enumerate()
+        .map(|(i, token)| match token {
+            TokenTree::Group(group) => {
+                let mut renamed = proc_macro2::Group::new(
+                    group.delimiter(),
+                    rename(group.stream(), names, prefix),
+                );
+                renamed.set_span(group.span());
+                TokenTree::Group(renamed)
+            }
+            TokenTree::Ident(ident)
+                if names.contains(&ident.to_string())
+                    && !(i > 0 && (is_colon(tokens.get(i - 1)) || is_dot(tokens.get(i - 1))))
+                    && !is_colon(tokens.get(i + 1)) =>
+            {
+                TokenTree::Ident(syn::Ident::new(
+                    &format!("{}_{}", prefix, ident),
+                    ident.span(),
+                ))
+            }
+            token => token.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embed_fragments() {
+        let fragment: syn::ItemStruct = syn::parse_quote! {
+            pub struct TokenPair<'info> {
+                pub mint: Account<'info, Mint>,
+                #[account(mut, token::mint = mint, token::authority = pool, constraint = vault.amount > 0)]
+                pub vault: Account<'info, TokenAccount>,
+                pub token_program: Program<'info, Token>,
+            }
+        };
+        let target: syn::ItemStruct = syn::parse_quote! {
+            #[derive(Accounts)]
+            pub struct Swap<'info> {
+                pub pool: Account<'info, Pool>,
+                #[fragment]
+                pub base: TokenPair<'info>,
+                #[fragment(prefix = "q")]
+                pub quote: TokenPair<'info>,
+            }
+        };
+        let normalize = |tokens: TokenStream| tokens.to_string().replace(' ', "");
+
+        // The struct goes through the macro of its first fragment.
+        assert!(normalize(expand(&target).unwrap()).starts_with("TokenPair!{#[derive(Accounts)]"));
+
+        let embedded: syn::Macro = syn::parse2(embed(&fragment, &target).unwrap()).unwrap();
+        let embedded: syn::ItemStruct = syn::parse2(embedded.tokens).unwrap();
+        let fields: Vec<String> = embedded
+            .fields
+            .iter()
+            .map(|f| normalize(quote! { #f }))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                "pubpool:Account<'info,Pool>",
+                "#[from_fragment(base,mint)]pubbase_mint:Account<'info,Mint>",
+                "#[account(mut,token::mint=base_mint,token::authority=pool,constraint=base_vault.amount>0)]#[from_fragment(base,vault)]pubbase_vault:Account<'info,TokenAccount>",
+                "#[from_fragment(base,token_program)]pubbase_token_program:Program<'info,Token>",
+                "#[fragment(prefix=\"q\")]pubquote:TokenPair<'info>",
+            ]
+        );
+
+        // Until all fragments are embedded.
+        let embedded = embed(&fragment, &embedded).unwrap();
+        assert!(normalize(embedded.clone()).starts_with("#[derive(Accounts)]"));
+        let embedded: syn::ItemStruct = syn::parse2(embedded).unwrap();
+        let names: Vec<String> = embedded
+            .fields
+            .iter()
+            .map(|f| f.ident.as_ref().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "pool",
+                "base_mint",
+                "base_vault",
+                "base_token_program",
+                "q_mint",
+                "q_vault",
+                "q_token_program",
+            ]
+        );
+    }
+}
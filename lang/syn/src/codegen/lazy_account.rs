@@ -0,0 +1,184 @@
+use quote::{format_ident, quote};
+
+// Generates the `Lazy<Name>` trait of an `#[account]` struct, implemented by
+// `LazyAccount<'info, Name>`, with a `load_<field>` method per field
+// deserializing only that field, given the length of the account's
+// discriminator, as an expression. Requires the `lazy-account` feature of `anchor-lang`.
+//
+// The offset of each field is a constant expression up to the first field
+// whose size depends on its value, i.e. anything but primitives, public keys
+// and arrays of those. The fields from there on are deserialized to be
+// skipped before the field is read. Structs with generics or unnamed fields
+// get no trait.
+pub fn generate(
+    strct: &syn::ItemStruct,
+    discriminator_len: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let fields = match &strct.fields {
+        syn::Fields::Named(fields) if strct.generics.params.is_empty() => &fields.named,
+        _ => return quote! {},
+    };
+    let name = &strct.ident;
+    let vis = &strct.vis;
+    let trait_name = format_ident!("Lazy{}", name);
+
+    // The discriminator comes first.
+    let mut offset = vec![quote! { #discriminator_len }];
+    let mut skipped: Vec<&syn::Type> = vec![];
+    let mut declarations = vec![];
+    let mut methods = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let load = format_ident!("load_{}", ident);
+        let doc = format!(" Deserializes the `{}` field of the account.", ident);
+        declarations.push(quote! {
+            #[doc = #doc]
+            fn #load(&self) -> anchor_lang::Result<#ty>;
+        });
+        methods.push(quote! {
+            fn #load(&self) -> anchor_lang::Result<#ty> {
+                self.__load_at(#(#offset)+*, |buf| {
+                    #(<#skipped as anchor_lang::AnchorDeserialize>::deserialize(buf)?;)*
+                    <#ty as anchor_lang::AnchorDeserialize>::deserialize(buf)
+                })
+            }
+        });
+        match fixed_size(ty) {
+            Some(size) if skipped.is_empty() => offset.push(size),
+            _ => skipped.push(ty),
+        }
+    }
+
+    let trait_doc = format!(
+        " Deserialization of the fields of a `LazyAccount<{}>` on demand.",
+        name
+    );
+    quote! {
+        #[doc = #trait_doc]
+        #vis trait #trait_name {
+            #(#declarations)*
+        }
+
+        #[automatically_derived]
+        impl<'info> #trait_name for anchor_lang::accounts::lazy_account::LazyAccount<'info, #name> {
+            #(#methods)*
+        }
+    }
+}
+
+// Same as `generate`, for an `#[account]` enum, with a `load_variant` method
+// deserializing only the index of its variant, which borsh writes right after
+// the discriminator. Enums with generics get no trait.
+pub fn generate_enum(
+    enm: &syn::ItemEnum,
+    discriminator_len: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    if !enm.generics.params.is_empty() {
+        return quote! {};
+    }
+    let name = &enm.ident;
+    let vis = &enm.vis;
+    let trait_name = format_ident!("Lazy{}", name);
+    let trait_doc = format!(
+        " Deserialization of the variant of a `LazyAccount<{}>` on demand.",
+        name
+    );
+    quote! {
+        #[doc = #trait_doc]
+        #vis trait #trait_name {
+            /// Deserializes the index of the variant of the account, in the
+            /// order of declaration.
+            fn load_variant(&self) -> anchor_lang::Result<u8>;
+        }
+
+        #[automatically_derived]
+        impl<'info> #trait_name for anchor_lang::accounts::lazy_account::LazyAccount<'info, #name> {
+            fn load_variant(&self) -> anchor_lang::Result<u8> {
+                self.__load_at(#discriminator_len, |buf| {
+                    <u8 as anchor_lang::AnchorDeserialize>::deserialize(buf)
+                })
+            }
+        }
+    }
+}
+
+// Constant expression of the size of the serialization of a type, if it's the
+// same for all values.
+fn fixed_size(ty: &syn::Type) -> Option<proc_macro2::TokenStream> {
+    match ty {
+        syn::Type::Array(array) => {
+            let elem = fixed_size(&array.elem)?;
+            let len = &array.len;
+            Some(quote! { ((#len) as usize * #elem) })
+        }
+        syn::Type::Path(ty_path) if ty_path.qself.is_none() => {
+            let segment = ty_path.path.segments.last()?;
+            if !segment.arguments.is_empty() {
+                return None;
+            }
+            let size: usize = match segment.ident.to_string().as_str() {
+                "bool" | "u8" | "i8" => 1,
+                "u16" | "i16" => 2,
+                "u32" | "i32" | "f32" => 4,
+                "u64" | "i64" | "f64" => 8,
+                "u128" | "i128" => 16,
+                "Pubkey" => 32,
+                _ => return None,
+            };
+            Some(quote! { #size })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_offsets() {
+        let strct: syn::ItemStruct = syn::parse_quote! {
+            pub struct Pool {
+                pub authority: Pubkey,
+                pub fees: [u16; 4],
+                pub members: Vec<Pubkey>,
+                pub bump: u8,
+            }
+        };
+        let generated: syn::File = syn::parse2(generate(&strct, &quote! { 8usize })).unwrap();
+        let methods = match &generated.items[1] {
+            syn::Item::Impl(item_impl) => item_impl
+                .items
+                .iter()
+                .map(|item| quote! { #item }.to_string().replace(' ', ""))
+                .collect::<Vec<_>>(),
+            _ => panic!("Expected the implementation of the trait"),
+        };
+        assert!(methods[0].contains("self.__load_at(8usize,|buf|{<Pubkeyasanchor_lang"));
+        assert!(methods[1].contains("self.__load_at(8usize+32usize,|buf|{<[u16;4]as"));
+        assert!(methods[2]
+            .contains("self.__load_at(8usize+32usize+((4)asusize*2usize),|buf|{<Vec<Pubkey>as"));
+        // Fields after a `Vec` skip it.
+        assert!(methods[3].contains(
+            "self.__load_at(8usize+32usize+((4)asusize*2usize),|buf|{<Vec<Pubkey>asanchor_lang::AnchorDeserialize>::deserialize(buf)?;<u8as"
+        ));
+    }
+
+    #[test]
+    fn enum_variant() {
+        let enm: syn::ItemEnum = syn::parse_quote! {
+            pub enum Position {
+                Open { size: u64 },
+                Closed,
+            }
+        };
+        let generated = generate_enum(&enm, &quote! { crate::__DISCRIMINATOR_LEN })
+            .to_string()
+            .replace(' ', "");
+        assert!(generated.contains("pubtraitLazyPosition{"));
+        assert!(generated.contains(
+            "self.__load_at(crate::__DISCRIMINATOR_LEN,|buf|{<u8asanchor_lang::AnchorDeserialize>::deserialize(buf)"
+        ));
+    }
+}
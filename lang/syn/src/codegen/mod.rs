@@ -1,3 +1,9 @@
 pub mod accounts;
+pub mod arbitrary;
 pub mod error;
+pub mod fragment;
+pub mod lazy_account;
 pub mod program;
+pub mod serde;
+pub mod space;
+pub mod state_machine;
@@ -0,0 +1,251 @@
+use crate::Program;
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::visit_mut::{self, VisitMut};
+
+// The program module, with the `+ - * /` operations of the handlers rewritten
+// into checked ones if `#[program(checked_math)]` was given, e.g.
+//
+// ctx.accounts.vault.amount += amount * 2;
+//
+// becomes
+//
+// {
+//     let __rhs = {
+//         use anchor_lang::__private::{CheckedOp as _, UncheckedMul as _};
+//         (&&anchor_lang::__private::CheckedOperands::new(amount, 2)).checked_math_mul()
+//     }
+//     .ok_or_else(|| anchor_lang::error!(anchor_lang::error::ErrorCode::ArithmeticOverflow))?;
+//     let __lhs = &mut ctx.accounts.vault.amount;
+//     {
+//         use anchor_lang::__private::{CheckedAssignOp as _, UncheckedAddAssign as _};
+//         (&&anchor_lang::__private::CheckedOperands::new(__lhs, __rhs)).checked_math_add_assign()
+//     }
+//     .ok_or_else(|| anchor_lang::error!(anchor_lang::error::ErrorCode::ArithmeticOverflow))?;
+// }
+//
+// The types of the operands are only known by the compiler, which resolves
+// the method to a checked operation if both are the same primitive integer
+// type, and to the operator otherwise, e.g. for `a + &b` or `String + &str`.
+//
+// The arguments of macro invocations, e.g. `msg!("{}", a + b)`, are rewritten
+// if they're expressions. Closures and nested items are left as is, since
+// they can't return the error, as are constants, i.e. array lengths, patterns
+// and const generic arguments.
+pub fn generate(program: &Program) -> syn::ItemMod {
+    let mut program_mod = program.program_mod.clone();
+    if !program
+        .args
+        .as_ref()
+        .map_or(false, |args| args.checked_math)
+    {
+        return program_mod;
+    }
+    let items = program_mod.content.iter_mut().flat_map(|(_, items)| items);
+    for item in items {
+        if let syn::Item::Fn(item_fn) = item {
+            if program.ixs.iter().any(|ix| ix.ident == item_fn.sig.ident) {
+                CheckedMath.visit_block_mut(&mut item_fn.block);
+            }
+        }
+    }
+    program_mod
+}
+
+// The method of `CheckedOperands` an operation is checked with, along with
+// the trait falling back to the operator, if it's an arithmetic one that may
+// overflow.
+pub(crate) fn checked_method(op: &syn::BinOp) -> Option<(&'static str, &'static str)> {
+    match op {
+        syn::BinOp::Add(_) => Some(("checked_math_add", "UncheckedAdd")),
+        syn::BinOp::Sub(_) => Some(("checked_math_sub", "UncheckedSub")),
+        syn::BinOp::Mul(_) => Some(("checked_math_mul", "UncheckedMul")),
+        syn::BinOp::Div(_) => Some(("checked_math_div", "UncheckedDiv")),
+        syn::BinOp::AddEq(_) => Some(("checked_math_add_assign", "UncheckedAddAssign")),
+        syn::BinOp::SubEq(_) => Some(("checked_math_sub_assign", "UncheckedSubAssign")),
+        syn::BinOp::MulEq(_) => Some(("checked_math_mul_assign", "UncheckedMulAssign")),
+        syn::BinOp::DivEq(_) => Some(("checked_math_div_assign", "UncheckedDivAssign")),
+        _ => None,
+    }
+}
+
+// The arguments of a macro invocation, if they're expressions separated by
+// commas, e.g. those of `msg!("{}", a + b)` or `require!(a + b <= max, ..)`.
+pub(crate) fn macro_args(mac: &syn::Macro) -> Option<Punctuated<syn::Expr, syn::Token![,]>> {
+    Punctuated::parse_terminated.parse2(mac.tokens.clone()).ok()
+}
+
+struct CheckedMath;
+
+impl VisitMut for CheckedMath {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        visit_mut::visit_expr_mut(self, expr);
+        *expr = match expr {
+            syn::Expr::Binary(binary) => {
+                let (method, unchecked) = match checked_method(&binary.op) {
+                    Some(method) => method,
+                    None => return,
+                };
+                let (left, right) = (&binary.left, &binary.right);
+                let checked = checked_op(
+                    method,
+                    unchecked,
+                    "CheckedOp",
+                    quote! { #left },
+                    quote! { #right },
+                );
+                syn::parse_quote! { #checked }
+            }
+            // The right operand is evaluated first, as with the compound
+            // assignment of primitive types.
+            syn::Expr::AssignOp(assign) => {
+                let (method, unchecked) = match checked_method(&assign.op) {
+                    Some(method) => method,
+                    None => return,
+                };
+                let (left, right) = (&assign.left, &assign.right);
+                let checked = checked_op(
+                    method,
+                    unchecked,
+                    "CheckedAssignOp",
+                    quote! { __lhs },
+                    quote! { __rhs },
+                );
+                syn::parse_quote! {
+                    {
+                        let __rhs = #right;
+                        let __lhs = &mut #left;
+                        #checked;
+                    }
+                }
+            }
+            _ => return,
+        };
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        if let Some(mut args) = macro_args(mac) {
+            for arg in args.iter_mut() {
+                self.visit_expr_mut(arg);
+            }
+            mac.tokens = quote! { #args };
+        }
+    }
+
+    fn visit_expr_closure_mut(&mut self, _closure: &mut syn::ExprClosure) {}
+
+    fn visit_expr_async_mut(&mut self, _async_block: &mut syn::ExprAsync) {}
+
+    // The length of an array expression is a constant.
+    fn visit_expr_repeat_mut(&mut self, repeat: &mut syn::ExprRepeat) {
+        self.visit_expr_mut(&mut repeat.expr);
+    }
+
+    // Macro invocations in statement position are parsed as items.
+    fn visit_item_mut(&mut self, item: &mut syn::Item) {
+        if let syn::Item::Macro(item) = item {
+            if item.ident.is_none() {
+                self.visit_macro_mut(&mut item.mac);
+            }
+        }
+    }
+
+    fn visit_type_mut(&mut self, _ty: &mut syn::Type) {}
+
+    // As are patterns, including macros expanding to them, e.g. the arm
+    // `bound!(MAX - 1) => ..`.
+    fn visit_pat_mut(&mut self, _pat: &mut syn::Pat) {}
+
+    // And const generic arguments, e.g. `foo::<{ N + 1 }>()`.
+    fn visit_generic_argument_mut(&mut self, _arg: &mut syn::GenericArgument) {}
+
+    fn visit_generic_method_argument_mut(&mut self, _arg: &mut syn::GenericMethodArgument) {}
+}
+
+fn checked_op(
+    method: &str,
+    unchecked: &str,
+    checked: &str,
+    left: proc_macro2::TokenStream,
+    right: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let span = proc_macro2::Span::call_site();
+    let (method, unchecked, checked) = (
+        syn::Ident::new(method, span),
+        syn::Ident::new(unchecked, span),
+        syn::Ident::new(checked, span),
+    );
+    quote! {
+        {
+            // Only one of the traits applies.
+            #[allow(unused_imports)]
+            use anchor_lang::__private::{#checked as _, #unchecked as _};
+            (&&anchor_lang::__private::CheckedOperands::new(#left, #right)).#method()
+        }
+        .ok_or_else(|| anchor_lang::error!(anchor_lang::error::ErrorCode::ArithmeticOverflow))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProgramArgs;
+
+    #[test]
+    fn checked_handlers() {
+        let mut program: Program = syn::parse_quote! {
+            pub mod vault {
+                pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+                    ctx.accounts.vault.amount += amount * 2;
+                    msg!("Deposited {}", amount - 1);
+                    let fees = [0u64; 4 + 4];
+                    let halve = |a: u64| a / 2;
+                    match amount {
+                        bound!(MAX - 1) => {}
+                        _ => {}
+                    }
+                    let limit = limit::<{ N + 1 }>();
+                    let limit = ctx.accounts.vault.limit::<{ N * 2 }>();
+                    Ok(())
+                }
+
+                fn helper(a: u64) -> u64 {
+                    a - 1
+                }
+            }
+        };
+        let (unchecked, item_mod) = (&program.program_mod, generate(&program));
+        assert_eq!(
+            quote! { #item_mod }.to_string(),
+            quote! { #unchecked }.to_string()
+        );
+
+        program.args = Some(ProgramArgs {
+            checked_math: true,
+            ..Default::default()
+        });
+        let item_mod = generate(&program);
+        let checked = quote! { #item_mod }.to_string().replace(' ', "");
+        assert!(checked.contains(
+            "let__rhs={#[allow(unused_imports)]useanchor_lang::__private::{CheckedOpas_,UncheckedMulas_};\
+            (&&anchor_lang::__private::CheckedOperands::new(amount,2)).checked_math_mul()}"
+        ));
+        assert!(checked.contains("let__lhs=&mutctx.accounts.vault.amount;"));
+        assert!(checked.contains(
+            "{#[allow(unused_imports)]useanchor_lang::__private::{CheckedAssignOpas_,UncheckedAddAssignas_};\
+            (&&anchor_lang::__private::CheckedOperands::new(__lhs,__rhs)).checked_math_add_assign()}"
+        ));
+        assert!(checked.contains(
+            "msg!(\"Deposited{}\",{#[allow(unused_imports)]useanchor_lang::__private::{CheckedOpas_,UncheckedSubas_};\
+            (&&anchor_lang::__private::CheckedOperands::new(amount,1)).checked_math_sub()}"
+        ));
+        assert_eq!(checked.matches("ErrorCode::ArithmeticOverflow").count(), 3);
+        assert!(checked.contains("[0u64;4+4]"));
+        assert!(checked.contains("|a:u64|a/2"));
+        assert!(checked.contains("bound!(MAX-1)=>{}"));
+        assert!(checked.contains("letlimit=limit::<{N+1}>();"));
+        assert!(checked.contains("letlimit=ctx.accounts.vault.limit::<{N*2}>();"));
+        assert!(checked.contains("a-1"));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::parser;
-use crate::{IxArg, State};
+use crate::{IxArg, Program, State};
 use heck::CamelCase;
 use quote::quote;
 
@@ -22,6 +22,29 @@ pub fn sighash(namespace: &str, name: &str) -> [u8; 8] {
     sighash
 }
 
+// Passes an instruction data type generated by `#[program]` through the user's prelude macro,
+// if one was given with `#[program(prelude = ..)]`.
+pub fn with_prelude(program: &Program, item: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    match program.args.as_ref().and_then(|args| args.prelude.as_ref()) {
+        None => item,
+        Some(prelude) => {
+            quote! {
+                #prelude! {
+                    #item
+                }
+            }
+        }
+    }
+}
+
+// The discriminator of an instruction, i.e. the first bytes of its sighash,
+// as many as the discriminators of the program have.
+pub fn discriminator(program: &Program, sighash: [u8; 8]) -> proc_macro2::TokenStream {
+    format!("{:?}", &sighash[..program.discriminator_len])
+        .parse()
+        .unwrap()
+}
+
 pub fn sighash_ctor() -> [u8; 8] {
     sighash(SIGHASH_STATE_NAMESPACE, "new")
 }
@@ -1,4 +1,5 @@
 use crate::codegen::program::common::*;
+use crate::parser;
 use crate::Program;
 use quote::quote;
 
@@ -9,9 +10,7 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
         Some(state) => match state.ctor_and_anchor.is_some() {
             false => quote! {},
             true => {
-                let sighash_arr = sighash_ctor();
-                let sighash_tts: proc_macro2::TokenStream =
-                    format!("{:?}", sighash_arr).parse().unwrap();
+                let sighash_tts = discriminator(program, sighash_ctor());
                 quote! {
                     #sighash_tts => {
                         __private::__state::__ctor(
@@ -38,9 +37,8 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                         let name = &ix.raw_method.sig.ident.to_string();
                         let ix_method_name: proc_macro2::TokenStream =
                             { format!("__{}", name).parse().unwrap() };
-                        let sighash_arr = sighash(SIGHASH_STATE_NAMESPACE, name);
-                        let sighash_tts: proc_macro2::TokenStream =
-                            format!("{:?}", sighash_arr).parse().unwrap();
+                        let sighash_tts =
+                            discriminator(program, sighash(SIGHASH_STATE_NAMESPACE, name));
                         quote! {
                             #sighash_tts => {
                                 __private::__state::#ix_method_name(
@@ -70,9 +68,10 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                             .methods
                             .iter()
                             .map(|m: &crate::StateIx| {
-                                let sighash_arr = sighash(&iface.trait_name, &m.ident.to_string());
-                                let sighash_tts: proc_macro2::TokenStream =
-                                    format!("{:?}", sighash_arr).parse().unwrap();
+                                let sighash_tts = discriminator(
+                                    program,
+                                    sighash(&iface.trait_name, &m.ident.to_string()),
+                                );
                                 let name = &m.raw_method.sig.ident.to_string();
                                 let ix_method_name: proc_macro2::TokenStream =
                                     format!("__{}_{}", iface.trait_name, name).parse().unwrap();
@@ -99,9 +98,10 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
         .iter()
         .map(|ix| {
             let ix_method_name = &ix.raw_method.sig.ident;
-            let sighash_arr = sighash(SIGHASH_GLOBAL_NAMESPACE, &ix_method_name.to_string());
-            let sighash_tts: proc_macro2::TokenStream =
-                format!("{:?}", sighash_arr).parse().unwrap();
+            let sighash_tts = discriminator(
+                program,
+                sighash(ix.sighash_namespace(), &ix_method_name.to_string()),
+            );
             quote! {
                 #sighash_tts => {
                     __private::__global::#ix_method_name(
@@ -113,9 +113,42 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
             }
         })
         .collect();
+    // Dispatch the `batch` instruction, if any instruction is marked `#[batch]`.
+    let batch_dispatch_arm = match parser::batch::batched_ixs(&program.ixs).is_empty() {
+        true => quote! {},
+        false => {
+            let sighash_tts = discriminator(
+                program,
+                sighash(SIGHASH_GLOBAL_NAMESPACE, parser::batch::BATCH_IX_NAME),
+            );
+            quote! {
+                #sighash_tts => {
+                    __private::__global::batch(
+                        program_id,
+                        accounts,
+                        ix_data,
+                    )
+                }
+            }
+        }
+    };
     let fallback_fn = gen_fallback(program).unwrap_or(quote! {
         Err(anchor_lang::error::ErrorCode::InstructionFallbackNotFound.into())
     });
+    // Events emitted with `emit_cpi!`, invoking the program itself.
+    let event_cpi_dispatch = match cfg!(feature = "event-cpi") {
+        true => quote! {
+            if data.starts_with(&anchor_lang::event::EVENT_IX_TAG_LE) {
+                return __private::__events::__event_dispatch(
+                    program_id,
+                    accounts,
+                    &data[8..],
+                );
+            }
+        },
+        false => quote! {},
+    };
+    let discriminator_len = program.discriminator_len;
     quote! {
         /// Performs method dispatch.
         ///
@@ -135,38 +168,43 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
         /// With this 8 byte identifier, Anchor performs method dispatch,
         /// matching the given 8 byte identifier to the associated method
         /// handler, which leads to user defined code being eventually invoked.
+        /// Programs given a shorter discriminator length with
+        /// `#[program(discriminator_len = ..)]` only use the first bytes of it.
         fn dispatch(
             program_id: &Pubkey,
             accounts: &[AccountInfo],
             data: &[u8],
         ) -> anchor_lang::Result<()> {
-            // Split the instruction data into the first 8 byte method
-            // identifier (sighash) and the serialized instruction data.
-            let mut ix_data: &[u8] = data;
-            let sighash: [u8; 8] = {
-                let mut sighash: [u8; 8] = [0; 8];
-                sighash.copy_from_slice(&ix_data[..8]);
-                ix_data = &ix_data[8..];
-                sighash
-            };
-
-            // If the method identifier is the IDL tag, then execute an IDL
-            // instruction, injected into all Anchor programs.
+            // If the instruction data starts with the IDL tag, then execute an
+            // IDL instruction, injected into all Anchor programs. The tag is 8
+            // bytes, whatever the length of the program's discriminators.
             if cfg!(not(feature = "no-idl")) {
-                if sighash == anchor_lang::idl::IDL_IX_TAG.to_le_bytes() {
+                if data.starts_with(&anchor_lang::idl::IDL_IX_TAG.to_le_bytes()) {
                     return __private::__idl::__idl_dispatch(
                         program_id,
                         accounts,
-                        &ix_data,
+                        &data[8..],
                     );
                 }
             }
 
+            #event_cpi_dispatch
+
+            // Split the instruction data into the method identifier (sighash)
+            // and the serialized instruction data.
+            let sighash: [u8; #discriminator_len] = {
+                let mut sighash: [u8; #discriminator_len] = [0; #discriminator_len];
+                sighash.copy_from_slice(&data[..#discriminator_len]);
+                sighash
+            };
+            let ix_data: &[u8] = &data[#discriminator_len..];
+
             match sighash {
                 #ctor_state_dispatch_arm
                 #(#state_dispatch_arms)*
                 #(#trait_dispatch_arms)*
                 #(#global_dispatch_arms)*
+                #batch_dispatch_arm
                 _ => {
                     #fallback_fn
                 }
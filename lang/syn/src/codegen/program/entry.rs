@@ -8,6 +8,7 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
     let fallback_maybe = dispatch::gen_fallback(program).unwrap_or(quote! {
         Err(anchor_lang::error::ErrorCode::InstructionMissing.into())
     });
+    let discriminator_len = program.discriminator_len;
     quote! {
         #[cfg(not(feature = "no-entrypoint"))]
         anchor_lang::solana_program::entrypoint!(entry);
@@ -34,7 +35,7 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
         ///
         /// * Start program via the entrypoint.
         /// * Strip method identifier off the first 8 bytes of the instruction
-        ///   data and invoke the identified method. The method identifier
+        ///   data, or as many as given to `#[program(discriminator_len = ..)]`, and invoke the identified method. The method identifier
         ///   is a variant of sighash. See docs.rs for `anchor_lang` for details.
         /// * If the method identifier is an IDL identifier, execute the IDL
         ///   instructions, which are a special set of hardcoded instructions
@@ -66,7 +67,7 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
             if *program_id != ID {
                 return Err(anchor_lang::error::ErrorCode::DeclaredProgramIdMismatch.into());
             }
-            if data.len() < 8 {
+            if data.len() < #discriminator_len {
                 return #fallback_maybe;
             }
 
@@ -0,0 +1,37 @@
+use quote::quote;
+
+// Generates `decode_event` and `decode_log`, decoding the events of the
+// program, so that indexers linking the program with the `no-entrypoint`
+// feature decode its logs without the IDL.
+//
+// The program module doesn't see the events defined in other modules, so each
+// `#[event]` registers itself under the name of its crate, which the decoders
+// look the events of the program up with. See `anchor_lang::event`.
+pub fn generate() -> proc_macro2::TokenStream {
+    quote! {
+        /// An event of the program, decoded by `decode_event`.
+        #[cfg(all(feature = "no-entrypoint", not(target_arch = "bpf")))]
+        pub use anchor_lang::event::ProgramEvent;
+
+        /// Decodes an event of the program from its discriminator and the
+        /// borsh serialized event following it in the logged data.
+        #[cfg(all(feature = "no-entrypoint", not(target_arch = "bpf")))]
+        pub fn decode_event(discriminator: &[u8], data: &[u8]) -> Option<ProgramEvent> {
+            anchor_lang::event::decode_event(env!("CARGO_PKG_NAME"), discriminator, data)
+        }
+
+        /// Decodes an event of the program from a `Program data: ` log line,
+        /// as emitted by `emit!`.
+        #[cfg(all(feature = "no-entrypoint", not(target_arch = "bpf")))]
+        pub fn decode_log(log: &str) -> Option<ProgramEvent> {
+            let data = anchor_lang::__private::base64::decode(
+                log.strip_prefix("Program data: ")?,
+            )
+            .ok()?;
+            if data.len() < 8 {
+                return None;
+            }
+            decode_event(&data[..8], &data[8..])
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use crate::codegen::program::common::*;
-use crate::{Program, State};
+use crate::parser;
+use crate::parser::arg_constraints::ArgConstraint;
+use crate::{IxArg, Program, State};
 use heck::CamelCase;
 use quote::{quote, ToTokens};
 
@@ -8,6 +10,46 @@ use quote::{quote, ToTokens};
 // so.
 pub fn generate(program: &Program) -> proc_macro2::TokenStream {
     let program_name = &program.name;
+    // Entry of the self CPIs emitting events, which only checks that the
+    // program signed them with its event authority. The event itself is only
+    // read by indexers.
+    let non_inlined_event = quote! {
+        #[inline(never)]
+        pub fn __event_dispatch(program_id: &Pubkey, accounts: &[AccountInfo], _event_data: &[u8]) -> anchor_lang::Result<()> {
+            let given_event_authority =
+                anchor_lang::solana_program::account_info::next_account_info(&mut accounts.iter())?;
+            if !given_event_authority.is_signer {
+                return Err(anchor_lang::error::Error::from(
+                    anchor_lang::error::ErrorCode::ConstraintSigner,
+                )
+                .with_account_name("event_authority"));
+            }
+            let (event_authority, _) = Pubkey::find_program_address(
+                &[anchor_lang::event::EVENT_AUTHORITY_SEED],
+                program_id,
+            );
+            if *given_event_authority.key != event_authority {
+                return Err(anchor_lang::error::Error::from(
+                    anchor_lang::error::ErrorCode::ConstraintSeeds,
+                )
+                .with_account_name("event_authority")
+                .with_pubkeys((*given_event_authority.key, event_authority)));
+            }
+            Ok(())
+        }
+    };
+    let events_mod = match cfg!(feature = "event-cpi") {
+        true => quote! {
+            /// __events mod defines the handler of the self CPIs emitting
+            /// events with `emit_cpi!`.
+            pub mod __events {
+                use super::*;
+
+                #non_inlined_event
+            }
+        },
+        false => quote! {},
+    };
     let non_inlined_idl: proc_macro2::TokenStream = {
         quote! {
             // Entry for all IDL related instructions. Use the "no-idl" feature
@@ -718,6 +760,7 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
             let anchor = &ix.anchor_ident;
             let variant_arm = generate_ix_variant(ix.raw_method.sig.ident.to_string(), &ix.args);
             let ix_name_log = format!("Instruction: {}", ix_name);
+            let arg_checks = generate_arg_checks(&ix.args);
             let ret_type = &ix.returns.ty.to_token_stream();
             let maybe_set_return_data = match ret_type.to_string().as_str() {
                 "()" => quote! {},
@@ -740,6 +783,9 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                         .map_err(|_| anchor_lang::error::ErrorCode::InstructionDidNotDeserialize)?;
                     let instruction::#variant_arm = ix;
 
+                    // Check the arguments' `#[arg(..)]` constraints.
+                    #arg_checks
+
                     // Bump collector.
                     let mut __bumps = std::collections::BTreeMap::new();
 
@@ -775,6 +821,7 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
             }
         })
         .collect();
+    let batch_handler = generate_batch_handler(program);
 
     quote! {
         /// Create a private module to not clutter the program's namespace.
@@ -789,6 +836,8 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                 #non_inlined_idl
             }
 
+            #events_mod
+
             /// __state mod defines wrapped handlers for state instructions.
             pub mod __state {
                 use super::*;
@@ -810,11 +859,109 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                 use super::*;
 
                 #(#non_inlined_handlers)*
+
+                #batch_handler
+            }
+        }
+    }
+}
+
+// Runs the instructions given to the `batch` instruction in sequence, each
+// with the next of the batch's accounts, failing on any instruction that isn't
+// marked `#[batch]`, the batch itself included.
+fn generate_batch_handler(program: &Program) -> proc_macro2::TokenStream {
+    let batched_ixs = parser::batch::batched_ixs(&program.ixs);
+    if batched_ixs.is_empty() {
+        return quote! {};
+    }
+    let discriminator_len = program.discriminator_len;
+    let batch_arms = batched_ixs.iter().map(|ix| {
+        let ix_method_name = &ix.raw_method.sig.ident;
+        let sighash_tts = discriminator(
+            program,
+            sighash(ix.sighash_namespace(), &ix_method_name.to_string()),
+        );
+        quote! {
+            #sighash_tts => #ix_method_name(program_id, ix_accounts, &data[#discriminator_len..])?
+        }
+    });
+    quote! {
+        #[inline(never)]
+        pub fn batch(
+            program_id: &Pubkey,
+            accounts: &[AccountInfo],
+            ix_data: &[u8],
+        ) -> anchor_lang::Result<()> {
+            #[cfg(not(feature = "no-log-ix-name"))]
+            anchor_lang::prelude::msg!("Instruction: Batch");
+
+            let instruction::Batch {
+                instructions,
+                account_counts,
+            } = instruction::Batch::deserialize(&mut &ix_data[..])
+                .map_err(|_| anchor_lang::error::ErrorCode::InstructionDidNotDeserialize)?;
+            if instructions.len() != account_counts.len() {
+                return Err(anchor_lang::error::ErrorCode::InstructionBatchMismatch.into());
+            }
+
+            let mut remaining_accounts: &[AccountInfo] = accounts;
+            for (data, count) in instructions.iter().zip(account_counts) {
+                let count = count as usize;
+                if remaining_accounts.len() < count {
+                    return Err(anchor_lang::error::ErrorCode::AccountNotEnoughKeys.into());
+                }
+                let (ix_accounts, rest) = remaining_accounts.split_at(count);
+                remaining_accounts = rest;
+
+                if data.len() < #discriminator_len {
+                    return Err(anchor_lang::error::ErrorCode::InstructionMissing.into());
+                }
+                let mut sighash = [0u8; #discriminator_len];
+                sighash.copy_from_slice(&data[..#discriminator_len]);
+                match sighash {
+                    #(#batch_arms,)*
+                    _ => {
+                        return Err(anchor_lang::error::ErrorCode::InstructionNotBatchable.into());
+                    }
+                }
             }
+            Ok(())
         }
     }
 }
 
+// Fails the instruction if an argument doesn't satisfy its `#[arg(..)]`
+// constraints.
+fn generate_arg_checks(args: &[IxArg]) -> proc_macro2::TokenStream {
+    let checks =
+        args.iter().flat_map(|arg| {
+            let name = &arg.name;
+            arg.constraints.iter().map(move |constraint| match constraint {
+            ArgConstraint::Range { range, .. } => quote! {
+                if !(#range).contains(&#name) {
+                    return Err(anchor_lang::error::ErrorCode::InstructionArgOutOfRange.into());
+                }
+            },
+            ArgConstraint::NonZero => quote! {
+                if #name == 0 {
+                    return Err(anchor_lang::error::ErrorCode::InstructionArgZero.into());
+                }
+            },
+            ArgConstraint::MaxLen(max_len) => {
+                let max_len = *max_len as usize;
+                quote! {
+                    if #name.len() > #max_len {
+                        return Err(anchor_lang::error::ErrorCode::InstructionArgTooLong.into());
+                    }
+                }
+            }
+        })
+        });
+    quote! {
+        #(#checks)*
+    }
+}
+
 fn generate_ix_variant_name(name: String) -> proc_macro2::TokenStream {
     let n = name.to_camel_case();
     n.parse().unwrap()
@@ -19,11 +19,15 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
             let strct = {
                 if ctor_args.is_empty() {
                     quote! {
+                        /// Instruction arguments to the `#[state]`'s `new`
+                        /// constructor.
                         #[derive(AnchorSerialize, AnchorDeserialize)]
                         pub struct New;
                     }
                 } else {
                     quote! {
+                        /// Instruction arguments to the `#[state]`'s `new`
+                        /// constructor.
                         #[derive(AnchorSerialize, AnchorDeserialize)]
                         pub struct New {
                             #(#ctor_args),*
@@ -31,12 +35,9 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                     }
                 }
             };
-            let sighash_arr = sighash_ctor();
-            let sighash_tts: proc_macro2::TokenStream =
-                format!("{:?}", sighash_arr).parse().unwrap();
+            let strct = with_prelude(program, strct);
+            let sighash_tts = discriminator(program, sighash_ctor());
             quote! {
-                /// Instruction arguments to the `#[state]`'s `new`
-                /// constructor.
                 #strct
 
                 impl anchor_lang::InstructionData for New {
@@ -78,9 +79,7 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
 
                         let ix_data_trait = {
                             let name = method.raw_method.sig.ident.to_string();
-                            let sighash_arr = sighash(SIGHASH_STATE_NAMESPACE, &name);
-                            let sighash_tts: proc_macro2::TokenStream =
-                                format!("{:?}", sighash_arr).parse().unwrap();
+                            let sighash_tts = discriminator(program, sighash(SIGHASH_STATE_NAMESPACE, &name));
                             quote! {
                                 impl anchor_lang::InstructionData for #ix_name_camel {
                                     fn data(&self) -> Vec<u8> {
@@ -93,13 +92,11 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                         };
 
                         // If no args, output a "unit" variant instead of a struct variant.
-                        if method.args.is_empty() {
+                        let strct = if method.args.is_empty() {
                             quote! {
                                 /// Anchor generated instruction.
                                 #[derive(AnchorSerialize, AnchorDeserialize)]
                                 pub struct #ix_name_camel;
-
-                                #ix_data_trait
                             }
                         } else {
                             quote! {
@@ -108,9 +105,13 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                                 pub struct #ix_name_camel {
                                     #(#raw_args),*
                                 }
-
-                                #ix_data_trait
                             }
+                        };
+                        let strct = with_prelude(program, strct);
+                        quote! {
+                            #strct
+
+                            #ix_data_trait
                         }
                     })
                     .collect()
@@ -134,9 +135,7 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                 })
                 .collect();
             let ix_data_trait = {
-                let sighash_arr = sighash(SIGHASH_GLOBAL_NAMESPACE, name);
-                let sighash_tts: proc_macro2::TokenStream =
-                    format!("{:?}", sighash_arr).parse().unwrap();
+                let sighash_tts = discriminator(program, sighash(ix.sighash_namespace(), name));
                 quote! {
                     impl anchor_lang::InstructionData for #ix_name_camel {
                         fn data(&self) -> Vec<u8> {
@@ -148,27 +147,32 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                 }
             };
             // If no args, output a "unit" variant instead of a struct variant.
-            if ix.args.is_empty() {
+            let strct = if ix.args.is_empty() {
                 quote! {
                     /// Instruction.
-                    #[derive(AnchorSerialize, AnchorDeserialize)]
+                    #[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
                     pub struct #ix_name_camel;
-
-                    #ix_data_trait
                 }
             } else {
                 quote! {
                     /// Instruction.
-                    #[derive(AnchorSerialize, AnchorDeserialize)]
+                    #[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
                     pub struct #ix_name_camel {
                         #(#raw_args),*
                     }
-
-                    #ix_data_trait
                 }
+            };
+            let strct = with_prelude(program, strct);
+            quote! {
+                #strct
+
+                #ix_data_trait
             }
         })
         .collect();
+    let batch_variant = generate_batch_variant(program);
+
+    let program_instruction = generate_program_instruction(program);
 
     quote! {
         /// An Anchor generated module containing the program's set of
@@ -189,6 +193,225 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
             }
 
             #(#variants)*
+
+            #batch_variant
+
+            #program_instruction
+        }
+    }
+}
+
+// Generates the arguments of the `batch` instruction, if any handler is marked
+// `#[batch]`.
+fn generate_batch_variant(program: &Program) -> proc_macro2::TokenStream {
+    if parser::batch::batched_ixs(&program.ixs).is_empty() {
+        return quote! {};
+    }
+    let sighash_tts = discriminator(
+        program,
+        sighash(SIGHASH_GLOBAL_NAMESPACE, parser::batch::BATCH_IX_NAME),
+    );
+    let strct = with_prelude(
+        program,
+        quote! {
+            /// Instruction running the `#[batch]` instructions in sequence.
+            #[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+            pub struct Batch {
+                /// Data of each instruction, discriminator included.
+                pub instructions: Vec<Vec<u8>>,
+                /// Number of accounts each instruction takes out of the
+                /// batch's, in order.
+                pub account_counts: Vec<u8>,
+            }
+        },
+    );
+    quote! {
+        #strct
+
+        impl anchor_lang::InstructionData for Batch {
+            fn data(&self) -> Vec<u8> {
+                let mut d = #sighash_tts.to_vec();
+                d.append(&mut self.try_to_vec().expect("Should always serialize"));
+                d
+            }
+        }
+    }
+}
+
+// Generates a single enum over all the global instructions of the program,
+// (de)serialized as the sighash discriminator followed by the borsh
+// encoded arguments, i.e., exactly the instruction data the program expects.
+fn generate_program_instruction(program: &Program) -> proc_macro2::TokenStream {
+    if program.ixs.is_empty() {
+        return quote! {};
+    }
+    let mut ixs: Vec<(proc_macro2::Ident, proc_macro2::TokenStream)> = program
+        .ixs
+        .iter()
+        .map(|ix| {
+            let name = ix.raw_method.sig.ident.to_string();
+            let ix_name_camel =
+                proc_macro2::Ident::new(&name.to_camel_case(), ix.raw_method.sig.ident.span());
+            let sighash_tts = discriminator(program, sighash(ix.sighash_namespace(), &name));
+            (ix_name_camel, sighash_tts)
+        })
+        .collect();
+    if !parser::batch::batched_ixs(&program.ixs).is_empty() {
+        let sighash_tts = discriminator(
+            program,
+            sighash(SIGHASH_GLOBAL_NAMESPACE, parser::batch::BATCH_IX_NAME),
+        );
+        ixs.push((
+            proc_macro2::Ident::new("Batch", proc_macro2::Span::call_site()),
+            sighash_tts,
+        ));
+    }
+    let variants = ixs.iter().map(|(name, _)| {
+        quote! {
+            #name(#name)
         }
+    });
+    let discriminator_arms = ixs.iter().map(|(name, sighash)| {
+        quote! {
+            ProgramInstruction::#name(_) => #sighash
+        }
+    });
+    let serialize_arms = ixs.iter().map(|(name, _)| {
+        quote! {
+            ProgramInstruction::#name(ix) => ix.serialize(writer)
+        }
+    });
+    let deserialize_arms = ixs.iter().map(|(name, sighash)| {
+        quote! {
+            #sighash => Ok(ProgramInstruction::#name(AnchorDeserialize::deserialize(buf)?))
+        }
+    });
+    let discriminator_len = program.discriminator_len;
+
+    let program_instruction = with_prelude(
+        program,
+        quote! {
+            /// All of the program's global instructions, keyed by their
+            /// discriminator. Serializing a variant yields the full instruction
+            /// data, so this can be used to build (or decode) instructions for
+            /// the program without depending on a client.
+            #[derive(Debug, Clone)]
+            pub enum ProgramInstruction {
+                #(#variants),*
+            }
+        },
+    );
+
+    quote! {
+        #program_instruction
+
+        impl ProgramInstruction {
+            /// The discriminator prefixing the instruction data.
+            pub fn discriminator(&self) -> [u8; #discriminator_len] {
+                match self {
+                    #(#discriminator_arms),*
+                }
+            }
+        }
+
+        impl AnchorSerialize for ProgramInstruction {
+            fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                writer.write_all(&self.discriminator())?;
+                match self {
+                    #(#serialize_arms),*
+                }
+            }
+        }
+
+        impl AnchorDeserialize for ProgramInstruction {
+            fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+                if buf.len() < #discriminator_len {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Instruction data too short for a discriminator",
+                    ));
+                }
+                let mut sighash = [0u8; #discriminator_len];
+                sighash.copy_from_slice(&buf[..#discriminator_len]);
+                *buf = &buf[#discriminator_len..];
+                match sighash {
+                    #(#deserialize_arms,)*
+                    _ => Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Unknown instruction discriminator",
+                    )),
+                }
+            }
+        }
+
+        impl anchor_lang::InstructionData for ProgramInstruction {
+            fn data(&self) -> Vec<u8> {
+                self.try_to_vec().expect("Should always serialize")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::program::dispatch;
+
+    // The discriminator written by a variant of `ProgramInstruction`, and the
+    // one deserializing back into it, are those the dispatch routes to the
+    // handler.
+    #[test]
+    fn program_instruction_round_trip() {
+        let mut program: Program = syn::parse_quote! {
+            pub mod vault {
+                pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+                    Ok(())
+                }
+
+                #[instruction(namespace = "admin")]
+                pub fn withdraw(ctx: Context<Withdraw>) -> Result<()> {
+                    Ok(())
+                }
+            }
+        };
+        for discriminator_len in [8, 4] {
+            program.discriminator_len = discriminator_len;
+            let ix_mod = generate(&program).to_string().replace(' ', "");
+            let dispatch = dispatch::generate(&program).to_string().replace(' ', "");
+            for (handler, variant) in [("deposit", "Deposit"), ("withdraw", "Withdraw")] {
+                let accepted = dispatch
+                    .split(&format!("=>{{__private::__global::{}(", handler))
+                    .next()
+                    .and_then(|before| before.rsplit('[').next())
+                    .map(|bytes| format!("[{}", bytes))
+                    .unwrap();
+                assert_eq!(accepted.matches(',').count() + 1, discriminator_len);
+                assert!(
+                    ix_mod.contains(&format!("ProgramInstruction::{}(_)=>{}", variant, accepted))
+                );
+                assert!(ix_mod.contains(&format!(
+                    "{}=>Ok(ProgramInstruction::{}(AnchorDeserialize::deserialize(buf)?))",
+                    accepted, variant
+                )));
+            }
+        }
+        assert!(generate(&program)
+            .to_string()
+            .replace(' ', "")
+            .contains("#[derive(Debug,Clone)]pubenumProgramInstruction"));
+    }
+
+    #[test]
+    fn program_instruction_handler() {
+        let err = syn::parse_str::<Program>(
+            "pub mod vault {
+                pub fn program_instruction(ctx: Context<Ix>) -> Result<()> { Ok(()) }
+            }",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The `ProgramInstruction` enum is generated over all the instructions, rename the handler"
+        );
     }
 }
@@ -1,11 +1,14 @@
+use crate::parser::discriminator;
 use crate::Program;
 use quote::quote;
 
 mod accounts;
+pub(crate) mod checked_math;
 pub mod common;
 mod cpi;
 mod dispatch;
 mod entry;
+mod events;
 mod handlers;
 mod instruction;
 
@@ -15,10 +18,19 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
     let entry = entry::generate(program);
     let dispatch = dispatch::generate(program);
     let handlers = handlers::generate(program);
-    let user_defined_program = &program.program_mod;
+    let user_defined_program = checked_math::generate(program);
     let instruction = instruction::generate(program);
     let cpi = cpi::generate(program);
     let accounts = accounts::generate(program);
+    let events = events::generate();
+    // Colliding discriminators are reported along with the rest of the
+    // program, for its other errors not to hide them.
+    let collisions = discriminator::check_collisions(program)
+        .err()
+        .map(|err| err.to_compile_error());
+    // The length the accounts of the crate take their discriminators' from.
+    let len_const = quote::format_ident!("{}", discriminator::LEN_CONST);
+    let discriminator_len = program.discriminator_len;
 
     quote! {
         // TODO: remove once we allow segmented paths in `Accounts` structs.
@@ -31,5 +43,11 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
         #instruction
         #cpi
         #accounts
+        #events
+        #collisions
+
+        #[doc(hidden)]
+        #[allow(dead_code)]
+        const #len_const: usize = #discriminator_len;
     }
 }
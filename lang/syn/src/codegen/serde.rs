@@ -0,0 +1,50 @@
+use syn::parse_quote;
+
+// Adds serde derives to an `#[account]` or `#[event]` struct for off-chain
+// use, representing public keys as base58 strings and bytes as base64
+// strings. Requires the `serde` feature of `anchor-lang`.
+pub fn add_derives(strct: &mut syn::ItemStruct) {
+    add_serde_attrs(&mut strct.attrs, strct.fields.iter_mut());
+}
+
+// As `add_derives`, for `#[account]` enums.
+pub fn add_enum_derives(enm: &mut syn::ItemEnum) {
+    let fields = enm
+        .variants
+        .iter_mut()
+        .flat_map(|variant| variant.fields.iter_mut());
+    add_serde_attrs(&mut enm.attrs, fields);
+}
+
+fn add_serde_attrs<'a>(
+    attrs: &mut Vec<syn::Attribute>,
+    fields: impl Iterator<Item = &'a mut syn::Field>,
+) {
+    attrs.push(parse_quote! {
+        #[derive(anchor_lang::__private::serde::Serialize, anchor_lang::__private::serde::Deserialize)]
+    });
+    attrs.push(parse_quote! {
+        #[serde(crate = "anchor_lang::__private::serde")]
+    });
+    for field in fields {
+        if let Some(helper) = helper(&field.ty) {
+            let with = format!("anchor_lang::serde_helpers::{}", helper);
+            field.attrs.push(parse_quote! {
+                #[serde(with = #with)]
+            });
+        }
+    }
+}
+
+// Returns the `anchor_lang::serde_helpers` module to (de)serialize the type
+// with, if it's not the serde default.
+fn helper(ty: &syn::Type) -> Option<&'static str> {
+    let ty = crate::parser::tts_to_string(ty).replace(' ', "");
+    match ty.as_str() {
+        "Pubkey" => Some("pubkey"),
+        "Option<Pubkey>" => Some("option_pubkey"),
+        "Vec<u8>" => Some("bytes"),
+        _ if ty.starts_with("[u8;") => Some("byte_array"),
+        _ => None,
+    }
+}
@@ -0,0 +1,55 @@
+use crate::parser::space::{self, Space};
+use quote::quote;
+use syn::parse_quote;
+
+// Implements `anchor_lang::Space` for a struct or an enum deriving
+// `InitSpace`, i.e. the `INIT_SPACE` constant, the space taken by the type at
+// most, discriminator excluded.
+pub fn generate(input: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let space = match &input.data {
+        syn::Data::Struct(strct) => space::fields_space(&strct.fields)?,
+        syn::Data::Enum(enm) => space::enum_space(enm.variants.iter())?,
+        syn::Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "InitSpace can't be derived for unions",
+            ))
+        }
+    };
+    let init_space = gen_space(&space);
+
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(anchor_lang::Space));
+    }
+    let (impl_gen, _, where_clause) = generics.split_for_impl();
+    let (_, ty_gen, _) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_gen anchor_lang::Space for #name #ty_gen #where_clause {
+            const INIT_SPACE: usize = #init_space;
+        }
+    })
+}
+
+// Constant expression of the space.
+fn gen_space(space: &Space) -> proc_macro2::TokenStream {
+    match space {
+        Space::Bytes(bytes) => quote! { #bytes },
+        Space::Defined(ty) => quote! { <#ty as anchor_lang::Space>::INIT_SPACE },
+        Space::Repeat(inner, n) => {
+            let inner = gen_space(inner);
+            quote! { ((#n) as usize * #inner) }
+        }
+        Space::Sum(spaces) => {
+            let spaces = spaces.iter().map(gen_space);
+            quote! { (0 #(+ #spaces)*) }
+        }
+        Space::Max(spaces) => spaces.iter().map(gen_space).fold(
+            quote! { 0 },
+            |max, space| quote! { anchor_lang::__private::max(#max, #space) },
+        ),
+    }
+}
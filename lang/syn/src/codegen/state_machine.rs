@@ -0,0 +1,97 @@
+use crate::parser::state_machine::{self, StateMachine};
+use quote::quote;
+
+// Generates the transitions of an `#[account]` struct given
+// `#[state_machine(..)]`, i.e. a `transition` method moving the account to a
+// state, and the `anchor_lang::StateMachine` implementation used by the
+// `in_state` and `transition_to` constraints. The attribute is removed from the
+// struct, so this must be called after the IDL is generated from it.
+//
+// The states are the variants of the enum type of the state field. States
+// moved to by `transition_to` must be unit variants.
+pub fn generate(strct: &mut syn::ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let machine = match state_machine::state_machine(&strct.attrs)? {
+        Some(machine) => machine,
+        None => return Ok(quote! {}),
+    };
+    strct
+        .attrs
+        .retain(|attr| !attr.path.is_ident("state_machine"));
+
+    let StateMachine { field, transitions } = &machine;
+    let ty = match strct
+        .fields
+        .iter()
+        .find(|f| f.ident.as_ref() == Some(field))
+    {
+        Some(f) if matches!(f.ty, syn::Type::Path(_)) => &f.ty,
+        Some(f) => {
+            return Err(syn::Error::new_spanned(
+                &f.ty,
+                "The state of a state machine must be an enum",
+            ))
+        }
+        None => {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!("{} has no field {}", strct.ident, field),
+            ))
+        }
+    };
+
+    let name = &strct.ident;
+    let (impl_gen, type_gen, where_clause) = strct.generics.split_for_impl();
+    let froms: Vec<_> = transitions.iter().map(|t| &t.from).collect();
+    let tos: Vec<_> = transitions.iter().map(|t| &t.to).collect();
+    let from_names: Vec<_> = froms.iter().map(|from| from.to_string()).collect();
+    let to_names: Vec<_> = tos.iter().map(|to| to.to_string()).collect();
+    let states = machine.states();
+    let state_names: Vec<_> = states.iter().map(|state| state.to_string()).collect();
+    let targets: Vec<_> = states.iter().filter(|state| tos.contains(state)).collect();
+    let target_names: Vec<_> = targets.iter().map(|state| state.to_string()).collect();
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_gen #name #type_gen #where_clause {
+            /// Moves the account to the state `to`, failing unless its
+            /// `#[state_machine]` allows it from the current state.
+            pub fn transition(&mut self, to: #ty) -> anchor_lang::Result<()> {
+                let allowed = matches!(
+                    (&self.#field, &to),
+                    #((#ty::#froms { .. }, #ty::#tos { .. }))|*
+                );
+                if !allowed {
+                    return Err(anchor_lang::error!(anchor_lang::error::ErrorCode::StateMachineTransition));
+                }
+                self.#field = to;
+                Ok(())
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_gen anchor_lang::StateMachine for #name #type_gen #where_clause {
+            fn state_name(&self) -> Option<&'static str> {
+                #[allow(unreachable_patterns)]
+                match &self.#field {
+                    #(#ty::#states { .. } => Some(#state_names),)*
+                    _ => None,
+                }
+            }
+
+            fn can_transition(from: &str, to: &str) -> bool {
+                matches!((from, to), #((#from_names, #to_names))|*)
+            }
+
+            fn transition_to(&mut self, to: &str) -> anchor_lang::Result<()> {
+                let allowed = self
+                    .state_name()
+                    .map_or(false, |from| <Self as anchor_lang::StateMachine>::can_transition(from, to));
+                self.#field = match to {
+                    #(#target_names if allowed => #ty::#targets,)*
+                    _ => return Err(anchor_lang::error!(anchor_lang::error::ErrorCode::StateMachineTransition)),
+                };
+                Ok(())
+            }
+        }
+    })
+}
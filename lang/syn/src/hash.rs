@@ -107,7 +107,7 @@ pub fn hashv(vals: &[&[u8]]) -> Hash {
                 &mut hash_result as *mut _ as *mut u8,
             );
         }
-        Hash::new_from_array(hash_result)
+        Hash(hash_result)
     }
 }
 
@@ -0,0 +1,462 @@
+use crate::codegen::accounts::{exit, try_accounts};
+use crate::idl::file::{parse_account_derives, parse_program_mod};
+use crate::idl::*;
+use crate::parser::context::CrateContext;
+use crate::parser::program;
+use crate::{AccountField, AccountsStruct, Field, InitKind, SysvarTy, Ty};
+use anyhow::Result;
+use heck::MixedCase;
+use proc_macro2::{TokenStream, TokenTree};
+use std::collections::HashMap;
+use std::path::Path;
+
+// Compile-time estimates of the cost of each instruction of a program.
+//
+// The accounts of an instruction are deserialized and checked by the
+// `try_accounts` generated by `#[derive(Accounts)]`, which builds the accounts
+// struct in its stack frame, limited to 4KB by the runtime. Accounts hold
+// their deserialized data inline unless boxed, so a few large accounts exceed
+// it, which only shows up as an access violation once deployed. Sizes are the
+// in-memory sizes of the types of the IDL, padding ignored.
+//
+// Compute units are estimated from rough costs of the syscalls and CPIs made
+// by the constraints, and of the data deserialized and serialized. They're
+// meant to compare instructions and find their costly accounts, not to predict
+// the units an instruction consumes.
+
+// Size of the stack frame of a function.
+pub const STACK_FRAME_LIMIT: usize = 4096;
+// Default compute budget of an instruction.
+pub const COMPUTE_UNIT_LIMIT: u64 = 200_000;
+
+// Size of an `AccountInfo`, held by every account type.
+const ACCOUNT_INFO_SIZE: usize = 48;
+// Size of a pointer, e.g. to a boxed account.
+const POINTER_SIZE: usize = 8;
+// Size of a `Vec` or a `String`, whatever their length.
+const VEC_SIZE: usize = 24;
+
+// Matching the discriminator and building the context.
+const DISPATCH_UNITS: u64 = 100;
+// Converting the `AccountInfo` of an account, checking its owner and
+// discriminator.
+const ACCOUNT_UNITS: u64 = 100;
+// Deserializing or serializing a byte of data.
+const DATA_BYTE_UNITS: u64 = 1;
+// A constraint comparing keys or values.
+const CHECK_UNITS: u64 = 25;
+// A `create_program_address`, i.e. seeds with a bump.
+const CREATE_PROGRAM_ADDRESS_UNITS: u64 = 1_500;
+// A `find_program_address`, i.e. seeds without a bump, trying two bumps on
+// average.
+const FIND_PROGRAM_ADDRESS_UNITS: u64 = 3_000;
+// Creating an account through the system program.
+const CREATE_ACCOUNT_UNITS: u64 = 3_000;
+// Initializing a token account or a mint through the token program.
+const INIT_TOKEN_UNITS: u64 = 5_000;
+// Creating an associated token account.
+const INIT_ASSOCIATED_TOKEN_UNITS: u64 = 25_000;
+// Reallocating an account, along with the transfer of its rent.
+const REALLOC_UNITS: u64 = 1_500;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstructionBudget {
+    pub name: String,
+    // Size of the accounts struct, built in the stack frame of `try_accounts`.
+    pub stack_frame: usize,
+    // Estimated compute units of the dispatch and of the account checks.
+    pub compute_units: u64,
+    // Compute units the instruction is given, by `#[compute_budget(..)]` or
+    // by default.
+    pub compute_unit_limit: u64,
+    // Size of the generated account checks, in tokens.
+    pub code_size: usize,
+    // The accounts of the instruction, composite ones flattened.
+    pub accounts: Vec<AccountBudget>,
+}
+
+impl InstructionBudget {
+    pub fn exceeds_stack_frame(&self) -> bool {
+        self.stack_frame > STACK_FRAME_LIMIT
+    }
+
+    pub fn exceeds_compute_units(&self) -> bool {
+        self.compute_units > self.compute_unit_limit
+    }
+}
+
+impl std::fmt::Display for InstructionBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} bytes of stack, ~{} compute units, {} tokens of account checks",
+            self.name, self.stack_frame, self.compute_units, self.code_size
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountBudget {
+    // Path of the account, e.g. `auth.payer` for the account of a composite.
+    pub name: String,
+    pub stack_frame: usize,
+    pub compute_units: u64,
+}
+
+impl std::fmt::Display for AccountBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} bytes of stack, ~{} compute units",
+            self.name, self.stack_frame, self.compute_units
+        )
+    }
+}
+
+// The budget of each instruction of the program at `filename`, with the sizes
+// of its types given by its IDL.
+pub fn report(filename: impl AsRef<Path>, idl: &Idl) -> Result<Vec<InstructionBudget>> {
+    let ctx = CrateContext::parse(filename)?;
+    let program_mod = match parse_program_mod(&ctx) {
+        None => return Ok(vec![]),
+        Some(m) => m,
+    };
+    let p = program::parse(program_mod)?;
+    let accs = parse_account_derives(&ctx);
+    let sizes = TypeSizes::new(idl);
+
+    Ok(p.ixs
+        .iter()
+        .map(|ix| {
+            let name = ix.ident.to_string().to_mixed_case();
+            let idl_ix = idl.instructions.iter().find(|idl_ix| idl_ix.name == name);
+            let args: usize = idl_ix
+                .map(|idl_ix| idl_ix.args.iter().map(|arg| sizes.of(&arg.ty)).sum())
+                .unwrap_or_default();
+            let compute_unit_limit = idl_ix
+                .and_then(|idl_ix| idl_ix.compute_budget)
+                .and_then(|budget| budget.units)
+                .map_or(COMPUTE_UNIT_LIMIT, u64::from);
+            let mut budget = InstructionBudget {
+                name,
+                stack_frame: 0,
+                compute_units: DISPATCH_UNITS + DATA_BYTE_UNITS * args as u64,
+                compute_unit_limit,
+                code_size: 0,
+                accounts: vec![],
+            };
+            if let Some(strct) = accs.get(&ix.anchor_ident.to_string()) {
+                add_accounts(&mut budget, "", strct, &accs, &sizes);
+            }
+            budget
+        })
+        .collect())
+}
+
+// Adds the accounts of `strct`, and their checks, to the budget.
+fn add_accounts(
+    budget: &mut InstructionBudget,
+    prefix: &str,
+    strct: &AccountsStruct,
+    accs: &HashMap<String, AccountsStruct>,
+    sizes: &TypeSizes,
+) {
+    budget.code_size += count_tokens(try_accounts::generate(strct));
+    budget.code_size += count_tokens(exit::generate(strct));
+    for field in &strct.fields {
+        match field {
+            // The composite is built in its own frame and then moved into
+            // this one.
+            AccountField::CompositeField(comp_f) => {
+                if let Some(strct) = accs.get(&comp_f.symbol) {
+                    let prefix = format!("{}{}.", prefix, comp_f.ident.to_string().to_mixed_case());
+                    add_accounts(budget, &prefix, strct, accs, sizes);
+                }
+            }
+            AccountField::Field(field) => {
+                let account = AccountBudget {
+                    name: format!("{}{}", prefix, field.ident.to_string().to_mixed_case()),
+                    stack_frame: field_stack_frame(field, sizes),
+                    compute_units: field_compute_units(field, sizes),
+                };
+                budget.stack_frame += account.stack_frame;
+                budget.compute_units += account.compute_units;
+                budget.accounts.push(account);
+            }
+        }
+    }
+}
+
+// Size of an account in the accounts struct.
+fn field_stack_frame(field: &Field, sizes: &TypeSizes) -> usize {
+    match &field.ty {
+        Ty::Account(ty) if ty.boxed => POINTER_SIZE,
+        Ty::Account(ty) => ACCOUNT_INFO_SIZE + sizes.of_path(&ty.account_type_path),
+        Ty::InterfaceAccount(ty) => ACCOUNT_INFO_SIZE + sizes.of_path(&ty.account_type_path),
+        Ty::Sysvar(ty) => ACCOUNT_INFO_SIZE + sysvar_size(ty),
+        Ty::ProgramState(_) | Ty::CpiState(_) | Ty::ProgramAccount(_) => POINTER_SIZE,
+        Ty::CpiAccount(_) => ACCOUNT_INFO_SIZE + POINTER_SIZE,
+        _ => ACCOUNT_INFO_SIZE,
+    }
+}
+
+// Size of the data deserialized into an account, and serialized back on exit
+// when it's mutable.
+fn field_data_size(field: &Field, sizes: &TypeSizes) -> usize {
+    match &field.ty {
+        Ty::Account(ty) => sizes.of_path(&ty.account_type_path),
+        Ty::InterfaceAccount(ty) => sizes.of_path(&ty.account_type_path),
+        Ty::ProgramAccount(ty) => sizes.of_path(&ty.account_type_path),
+        Ty::CpiAccount(ty) => sizes.of_path(&ty.account_type_path),
+        _ => 0,
+    }
+}
+
+fn field_compute_units(field: &Field, sizes: &TypeSizes) -> u64 {
+    let c = &field.constraints;
+    let data_size = field_data_size(field, sizes) as u64;
+    let mut units = ACCOUNT_UNITS + DATA_BYTE_UNITS * data_size;
+    if c.is_mutable() || c.init.is_some() {
+        units += DATA_BYTE_UNITS * data_size;
+    }
+    if let Some(init) = &c.init {
+        units += match init.kind {
+            InitKind::Program { .. } => CREATE_ACCOUNT_UNITS,
+            InitKind::Token { .. } | InitKind::Mint { .. } => {
+                CREATE_ACCOUNT_UNITS + INIT_TOKEN_UNITS
+            }
+            InitKind::AssociatedToken { .. } => INIT_ASSOCIATED_TOKEN_UNITS,
+        };
+    }
+    if let Some(seeds) = &c.seeds {
+        units += match seeds.bump {
+            None => FIND_PROGRAM_ADDRESS_UNITS,
+            Some(_) => CREATE_PROGRAM_ADDRESS_UNITS,
+        };
+    }
+    if c.realloc.is_some() {
+        units += REALLOC_UNITS;
+    }
+    let checks = c.has_one.len()
+        + c.literal.len()
+        + c.raw.len()
+        + [
+            c.mutable.is_some(),
+            c.signer.is_some(),
+            c.owner.is_some(),
+            c.rent_exempt.is_some(),
+            c.executable.is_some(),
+            c.state.is_some(),
+            c.close.is_some(),
+            c.address.is_some(),
+            c.associated_token.is_some(),
+            c.token_account.is_some(),
+            c.mint.is_some(),
+        ]
+        .iter()
+        .filter(|check| **check)
+        .count();
+    units + CHECK_UNITS * checks as u64
+}
+
+fn sysvar_size(ty: &SysvarTy) -> usize {
+    match ty {
+        SysvarTy::Clock => 40,
+        SysvarTy::Rent => 17,
+        SysvarTy::EpochSchedule => 33,
+        SysvarTy::Fees => 8,
+        SysvarTy::Rewards => 16,
+        SysvarTy::SlotHistory => VEC_SIZE + 16,
+        SysvarTy::RecentBlockhashes | SysvarTy::SlotHashes | SysvarTy::StakeHistory => VEC_SIZE,
+        SysvarTy::Instructions => 0,
+    }
+}
+
+fn count_tokens(tokens: TokenStream) -> usize {
+    tokens
+        .into_iter()
+        .map(|tt| match tt {
+            TokenTree::Group(group) => 1 + count_tokens(group.stream()),
+            _ => 1,
+        })
+        .sum()
+}
+
+// In-memory sizes of the types of an IDL.
+struct TypeSizes<'a> {
+    types: HashMap<&'a str, &'a IdlTypeDefinition>,
+}
+
+impl<'a> TypeSizes<'a> {
+    fn new(idl: &'a Idl) -> Self {
+        Self {
+            types: idl
+                .accounts
+                .iter()
+                .chain(idl.types.iter())
+                .map(|ty_def| (ty_def.name.as_str(), ty_def))
+                .collect(),
+        }
+    }
+
+    // Size of the type of an account, e.g. `Vault` or `crate::state::Vault`.
+    fn of_path(&self, path: &syn::TypePath) -> usize {
+        let name = path.path.segments.last().unwrap().ident.to_string();
+        self.of_defined(&name, &mut vec![])
+    }
+
+    fn of(&self, ty: &IdlType) -> usize {
+        self.of_ty(ty, &mut vec![])
+    }
+
+    // `defining` holds the types being sized, to stop at recursive ones, which
+    // are necessarily behind a pointer.
+    fn of_ty(&self, ty: &IdlType, defining: &mut Vec<String>) -> usize {
+        match ty {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 => 1,
+            IdlType::U16 | IdlType::I16 => 2,
+            IdlType::U32 | IdlType::I32 | IdlType::F32 => 4,
+            IdlType::U64 | IdlType::I64 | IdlType::F64 => 8,
+            IdlType::U128 | IdlType::I128 => 16,
+            IdlType::Float(float) => float.bits as usize / 8,
+            IdlType::PublicKey => 32,
+            IdlType::Bytes | IdlType::String | IdlType::Vec(_) => VEC_SIZE,
+            IdlType::Option(ty) => 1 + self.of_ty(ty, defining),
+            IdlType::Array(ty, len) => len * self.of_ty(ty, defining),
+            IdlType::Defined(name) => self.of_defined(name, defining),
+        }
+    }
+
+    fn of_defined(&self, name: &str, defining: &mut Vec<String>) -> usize {
+        if defining.iter().any(|ty| ty == name) {
+            return POINTER_SIZE;
+        }
+        let ty_def = match self.types.get(name) {
+            Some(ty_def) => ty_def,
+            None => return 0,
+        };
+        defining.push(name.to_string());
+        let size = match &ty_def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => fields
+                .iter()
+                .map(|field| self.of_ty(&field.ty, defining))
+                .sum(),
+            IdlTypeDefinitionTy::Enum { variants } => {
+                1 + variants
+                    .iter()
+                    .map(|variant| match &variant.fields {
+                        None => 0,
+                        Some(EnumFields::Named(fields)) => fields
+                            .iter()
+                            .map(|field| self.of_ty(&field.ty, defining))
+                            .sum(),
+                        Some(EnumFields::Tuple(tys)) => {
+                            tys.iter().map(|ty| self.of_ty(ty, defining)).sum()
+                        }
+                    })
+                    .max()
+                    .unwrap_or_default()
+            }
+        };
+        defining.pop();
+        size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::idl::file::{self, ExternalAccounts};
+    use crate::idl::DocsPolicy;
+
+    #[test]
+    fn instruction_budgets() {
+        let path = std::env::temp_dir().join("anchor_syn_budget.rs");
+        std::fs::write(
+            &path,
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod vault {
+                use super::*;
+                pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+                    Ok(())
+                }
+                #[compute_budget(units = 400_000)]
+                pub fn deposit_boxed(ctx: Context<DepositBoxed>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Deposit<'info> {
+                pub owner: Signer<'info>,
+                #[account(mut, seeds = [b"vault"], bump)]
+                pub vault: Account<'info, Vault>,
+            }
+
+            #[derive(Accounts)]
+            pub struct DepositBoxed<'info> {
+                pub owner: Signer<'info>,
+                #[account(mut, seeds = [b"vault"], bump)]
+                pub vault: Box<Account<'info, Vault>>,
+            }
+
+            #[account]
+            pub struct Vault {
+                pub owner: Pubkey,
+                pub history: [u64; 512],
+            }
+            "#,
+        )
+        .unwrap();
+        let idl = file::parse(
+            &path,
+            &file::ParseOptions {
+                docs_policy: DocsPolicy::none(),
+                ..Default::default()
+            },
+            &ExternalAccounts::default(),
+        )
+        .unwrap()
+        .unwrap()
+        .idl;
+        let budgets = report(&path, &idl).unwrap();
+
+        let vault = 32 + 8 * 512;
+        assert_eq!(budgets[0].name, "deposit");
+        assert_eq!(budgets[0].stack_frame, 2 * ACCOUNT_INFO_SIZE + vault);
+        assert!(budgets[0].exceeds_stack_frame());
+        assert_eq!(
+            budgets[0].accounts[1],
+            AccountBudget {
+                name: "vault".to_string(),
+                stack_frame: ACCOUNT_INFO_SIZE + vault,
+                compute_units: ACCOUNT_UNITS
+                    + 2 * DATA_BYTE_UNITS * vault as u64
+                    + FIND_PROGRAM_ADDRESS_UNITS
+                    + CHECK_UNITS,
+            }
+        );
+        assert_eq!(
+            budgets[0].compute_units,
+            DISPATCH_UNITS
+                + 8 * DATA_BYTE_UNITS
+                + budgets[0]
+                    .accounts
+                    .iter()
+                    .map(|acc| acc.compute_units)
+                    .sum::<u64>()
+        );
+        assert!(budgets[0].code_size > 0);
+        assert_eq!(budgets[0].compute_unit_limit, COMPUTE_UNIT_LIMIT);
+
+        assert_eq!(budgets[1].name, "depositBoxed");
+        assert_eq!(budgets[1].stack_frame, ACCOUNT_INFO_SIZE + POINTER_SIZE);
+        assert!(!budgets[1].exceeds_stack_frame());
+        assert_eq!(budgets[1].compute_unit_limit, 400_000);
+    }
+}
@@ -0,0 +1,1167 @@
+use crate::codegen::program::common::sighash;
+use crate::idl::file::{
+    account_kind, account_programs, apply_float_policy, arg_constraints, batch_enum_variant,
+    batch_instruction, close_destination, compute_budget, field_permissions, guards, is_admin,
+    layout_migrations, metadata_pda, realloc, sunset, token_account, ParseOutput,
+};
+use crate::idl::*;
+use crate::parser::zero_copy::ZeroCopy;
+use crate::parser::{self, docs, idl_name};
+use crate::{AccountField, AccountsStruct, Error, Field, Program, Ty};
+use anyhow::{anyhow, Result};
+use heck::{CamelCase, MixedCase, SnakeCase};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::collections::{BTreeMap, HashSet};
+
+// Generation of the IDL by the compiled program, as an alternative to parsing
+// its source with `file::parse`.
+//
+// With the `idl-build` feature of `anchor-lang`, the macros generate tests,
+// gated by the program's own `idl-build` feature, that print fragments of the
+// IDL: the instructions, each account, event, error enum and constant. The
+// types of fields and arguments are described by the compiler through the
+// `IdlBuild` trait of `anchor-lang`, and only parsed as written for types that
+// don't implement it, i.e., that aren't defined with Anchor's macros.
+// `assemble` puts the fragments printed by `cargo test` back together.
+//
+// PDA seeds, signers, remaining and distinct accounts, external messages,
+// `#[state]` and `#[interface]` implementations aren't described.
+
+// Prefix of the generated tests, to run only them.
+pub const IDL_BUILD_TEST_PREFIX: &str = "__anchor_private_print_idl";
+
+const FRAGMENT_BEGIN: &str = "--- IDL fragment begin ---";
+const FRAGMENT_END: &str = "--- IDL fragment end ---";
+
+// Type definitions by name.
+pub type Types = BTreeMap<String, IdlTypeDefinition>;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlFragment {
+    // Name of the program, for the fragment of the `#[program]` module.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub docs: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub instructions: Vec<IdlInstruction>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub instruction_enum: Option<IdlInstructionEnum>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fallback: Option<IdlFallback>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub accounts: Vec<IdlTypeDefinition>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub types: Vec<IdlTypeDefinition>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub events: Vec<IdlEvent>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub errors: Vec<IdlErrorCode>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub constants: Vec<IdlConst>,
+}
+
+impl IdlFragment {
+    pub fn print(&self) {
+        println!(
+            "{}\n{}\n{}",
+            FRAGMENT_BEGIN,
+            serde_json::to_string(self).expect("Must serialize"),
+            FRAGMENT_END
+        );
+    }
+}
+
+// Parses the fragments printed in the output of the generated tests.
+pub fn parse_fragments(output: &str) -> Result<Vec<IdlFragment>> {
+    let mut fragments = vec![];
+    let mut lines = output.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() != FRAGMENT_BEGIN {
+            continue;
+        }
+        let fragment = lines
+            .next()
+            .ok_or_else(|| anyhow!("Unterminated IDL fragment"))?;
+        fragments.push(serde_json::from_str(fragment)?);
+    }
+    Ok(fragments)
+}
+
+// Assembles the IDL of a program from its fragments, or returns `None` if none
+// is the `#[program]` module's.
+pub fn assemble(
+    fragments: Vec<IdlFragment>,
+    version: String,
+    docs_policy: &DocsPolicy,
+    float_policy: FloatPolicy,
+) -> Result<Option<ParseOutput>> {
+    let mut program = None;
+    let mut accounts = vec![];
+    let mut types = Types::new();
+    let mut events = vec![];
+    let mut errors = vec![];
+    let mut constants = vec![];
+    for fragment in fragments {
+        if let Some(name) = fragment.name {
+            if program.is_some() {
+                return Err(anyhow!("Found more than one #[program] module"));
+            }
+            program = Some((
+                name,
+                fragment.docs,
+                fragment.instructions,
+                fragment.instruction_enum,
+                fragment.fallback,
+            ));
+        }
+        accounts.extend(fragment.accounts);
+        for ty_def in fragment.types {
+            types.entry(ty_def.name.clone()).or_insert(ty_def);
+        }
+        events.extend(fragment.events);
+        errors.extend(fragment.errors);
+        constants.extend(fragment.constants);
+    }
+    let (name, docs, instructions, instruction_enum, fallback) = match program {
+        None => return Ok(None),
+        Some(program) => program,
+    };
+
+    // Fragments are printed in any order.
+    accounts.sort_by(|a: &IdlTypeDefinition, b| a.name.cmp(&b.name));
+    events.sort_by(|a: &IdlEvent, b| a.name.cmp(&b.name));
+    errors.sort_by_key(|error: &IdlErrorCode| error.code);
+    constants.sort_by_key(IdlConst::path);
+
+    // Accounts and events are only in their own sections.
+    let types = types
+        .into_values()
+        .filter(|ty_def| {
+            !accounts.iter().any(|a| a.name == ty_def.name)
+                && !events.iter().any(|e| e.name == ty_def.name)
+        })
+        .collect();
+
+    let mut warnings = vec![];
+    let mut const_paths = HashSet::new();
+    for c in constants.iter() {
+        if !const_paths.insert(c.path()) {
+            warnings.push(IdlWarning::DuplicateConst(c.path()));
+        }
+    }
+
+    let mut idl = Idl {
+        version,
+        name,
+        docs,
+        constants,
+        instructions,
+        instruction_enum,
+        fallback,
+        state: None,
+        accounts,
+        types,
+        events: (!events.is_empty()).then(|| events),
+        external_messages: vec![],
+        errors: (!errors.is_empty()).then(|| errors),
+        metadata: None,
+    };
+    idl.apply_docs_policy(docs_policy);
+    warnings.extend(
+        undefined_types(&idl)
+            .into_iter()
+            .map(IdlWarning::UndefinedType),
+    );
+    apply_float_policy(&mut idl, float_policy, &mut warnings)?;
+    Ok(Some(ParseOutput { idl, warnings }))
+}
+
+// Adds the definitions of the undefined types found in another IDL of the
+// program, e.g. parsed from its source, for types that aren't defined with
+// Anchor's macros.
+pub fn define_types_from(output: &mut ParseOutput, idl: &Idl) {
+    loop {
+        let undefined = undefined_types(&output.idl);
+        let found: Vec<IdlTypeDefinition> = idl
+            .types
+            .iter()
+            .filter(|ty_def| undefined.contains(&ty_def.name))
+            .cloned()
+            .collect();
+        if found.is_empty() {
+            break;
+        }
+        output.idl.types.extend(found);
+    }
+    output.idl.types.sort_by(|a, b| a.name.cmp(&b.name));
+    let undefined = undefined_types(&output.idl);
+    output.warnings.retain(|warning| match warning {
+        IdlWarning::UndefinedType(name) => undefined.contains(name),
+        _ => true,
+    });
+}
+
+// Names of the types used by the IDL but not defined in it, i.e., types with
+// neither an `IdlBuild` implementation nor a definition found in another
+// fragment.
+pub(crate) fn undefined_types(idl: &Idl) -> Vec<String> {
+    fn defined(ty: &IdlType, names: &mut Vec<String>) {
+        match ty {
+            IdlType::Defined(name) => names.push(name.clone()),
+            IdlType::Option(ty) | IdlType::Vec(ty) | IdlType::Array(ty, _) => defined(ty, names),
+            _ => {}
+        }
+    }
+    let mut used = vec![];
+    for ix in &idl.instructions {
+        for arg in &ix.args {
+            defined(&arg.ty, &mut used);
+        }
+        if let Some(ty) = &ix.returns {
+            defined(ty, &mut used);
+        }
+    }
+    for ty_def in idl.accounts.iter().chain(&idl.types) {
+        match &ty_def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                fields.iter().for_each(|f| defined(&f.ty, &mut used))
+            }
+            IdlTypeDefinitionTy::Enum { variants } => {
+                for variant in variants {
+                    match &variant.fields {
+                        Some(EnumFields::Named(fields)) => {
+                            fields.iter().for_each(|f| defined(&f.ty, &mut used))
+                        }
+                        Some(EnumFields::Tuple(tys)) => {
+                            tys.iter().for_each(|ty| defined(ty, &mut used))
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+    for event in idl.events.iter().flatten() {
+        event.fields.iter().for_each(|f| defined(&f.ty, &mut used));
+    }
+    let known: HashSet<&String> = idl
+        .accounts
+        .iter()
+        .chain(&idl.types)
+        .map(|ty_def| &ty_def.name)
+        .collect();
+    let mut undefined: Vec<String> = used
+        .into_iter()
+        .filter(|name| !known.contains(name))
+        .collect();
+    undefined.sort();
+    undefined.dedup();
+    undefined
+}
+
+// Helpers called by the generated code, with the parts of the IDL known when
+// expanding the macros serialized as JSON.
+
+// A type definition, given the types of its fields, those of each variant in
+// order for enums.
+pub fn type_definition(json: &str, tys: Vec<IdlType>) -> IdlTypeDefinition {
+    let mut ty_def: IdlTypeDefinition = serde_json::from_str(json).expect("Invalid IDL");
+    let mut tys = tys.into_iter();
+    match &mut ty_def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            for (field, ty) in fields.iter_mut().zip(tys) {
+                field.ty = ty;
+            }
+        }
+        IdlTypeDefinitionTy::Enum { variants } => {
+            for variant in variants {
+                match &mut variant.fields {
+                    Some(EnumFields::Named(fields)) => {
+                        for (field, ty) in fields.iter_mut().zip(&mut tys) {
+                            field.ty = ty;
+                        }
+                    }
+                    Some(EnumFields::Tuple(fields)) => {
+                        for (field, ty) in fields.iter_mut().zip(&mut tys) {
+                            *field = ty;
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+    ty_def
+}
+
+// An account of an `Accounts` struct, given its address, if it can only be a
+// single one, and the programs it may be or be owned by, for interfaces.
+pub fn account(
+    json: &str,
+    address: Option<String>,
+    programs: Option<Vec<String>>,
+) -> IdlAccountItem {
+    let mut acc: IdlAccount = serde_json::from_str(json).expect("Invalid IDL");
+    acc.address = address.or(acc.address);
+    acc.programs = programs.or(acc.programs);
+    IdlAccountItem::IdlAccount(Box::new(acc))
+}
+
+// An instruction, given its accounts and the types of its args and return
+// value.
+pub fn instruction(
+    json: &str,
+    accounts: Vec<IdlAccountItem>,
+    tys: Vec<IdlType>,
+    returns: Option<IdlType>,
+) -> IdlInstruction {
+    let mut ix: IdlInstruction = serde_json::from_str(json).expect("Invalid IDL");
+    ix.accounts = accounts;
+    for (arg, ty) in ix.args.iter_mut().zip(tys) {
+        arg.ty = ty;
+    }
+    ix.returns = returns;
+    ix
+}
+
+pub fn print_program(json: &str, instructions: Vec<IdlInstruction>, types: Types) {
+    let mut fragment: IdlFragment = serde_json::from_str(json).expect("Invalid IDL");
+    fragment.instructions = instructions;
+    fragment.types = types.into_values().collect();
+    fragment.print();
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn print_account(
+    name: &str,
+    mut types: Types,
+    version: Option<u32>,
+    migrations: &str,
+    permissions: &str,
+    state_machine: &str,
+    size: Option<u64>,
+    discriminator: Option<Vec<u8>>,
+    checksum: &str,
+) {
+    let mut account = types.remove(name).expect("Account must be defined");
+    account.version = version;
+    account.discriminator = discriminator;
+    account.migrations = serde_json::from_str(migrations).expect("Invalid IDL");
+    account.permissions = serde_json::from_str(permissions).expect("Invalid IDL");
+    account.state_machine = serde_json::from_str(state_machine).expect("Invalid IDL");
+    account.size = size;
+    account.checksum = serde_json::from_str(checksum).expect("Invalid IDL");
+    IdlFragment {
+        accounts: vec![account],
+        types: types.into_values().collect(),
+        ..Default::default()
+    }
+    .print();
+}
+
+pub fn print_event(json: &str, tys: Vec<IdlType>, types: Types) {
+    let mut event: IdlEvent = serde_json::from_str(json).expect("Invalid IDL");
+    for (field, ty) in event.fields.iter_mut().zip(tys) {
+        field.ty = ty;
+    }
+    IdlFragment {
+        events: vec![event],
+        types: types.into_values().collect(),
+        ..Default::default()
+    }
+    .print();
+}
+
+pub fn print_errors(json: &str, codes: Vec<u32>) {
+    let mut errors: Vec<IdlErrorCode> = serde_json::from_str(json).expect("Invalid IDL");
+    for (error, code) in errors.iter_mut().zip(codes) {
+        error.code = code;
+    }
+    IdlFragment {
+        errors,
+        ..Default::default()
+    }
+    .print();
+}
+
+// Prints the IDL of a constant defined in the module at `module_path`, as
+// given by `module_path!()`.
+pub fn print_constant(json: &str, module_path: &str, ty: IdlType, types: Types) {
+    let mut constant: IdlConst = serde_json::from_str(json).expect("Invalid IDL");
+    constant.ty = ty;
+    constant.module = module_path
+        .split_once("::")
+        .map(|(_crate, module)| module.to_string());
+    IdlFragment {
+        constants: vec![constant],
+        types: types.into_values().collect(),
+        ..Default::default()
+    }
+    .print();
+}
+
+// Code generation, used by the macros with the `idl-build` feature.
+
+// Only structs without type parameters can be described without knowing their
+// arguments.
+fn is_describable(strct: &syn::ItemStruct) -> bool {
+    strct.generics.type_params().next().is_none()
+        && strct.generics.const_params().next().is_none()
+        && !matches!(strct.fields, syn::Fields::Unnamed(_))
+}
+
+fn is_describable_enum(enm: &syn::ItemEnum) -> bool {
+    enm.generics.type_params().next().is_none() && enm.generics.const_params().next().is_none()
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("Must serialize")
+}
+
+// The IDL type of a field, from the compiler if it implements `IdlBuild` or
+// else as written.
+fn field_type(ty: &syn::Type) -> TokenStream {
+    let fallback = to_json(&constant::idl_type(ty));
+    quote! {
+        (&anchor_lang::idl::build::IdlBuildMarker::<#ty>::new())
+            .__anchor_private_idl_type(#fallback)
+    }
+}
+
+fn insert_defined(ty: &syn::Type) -> TokenStream {
+    quote! {
+        (&anchor_lang::idl::build::IdlBuildMarker::<#ty>::new())
+            .__anchor_private_insert_idl_defined(types);
+    }
+}
+
+fn field_docs(field: &syn::Field) -> Option<Vec<String>> {
+    docs::parse(&field.attrs)
+}
+
+// Implements `IdlBuild` for a struct, i.e., the `Defined` type describing it,
+// given whether it's zero copy.
+pub fn gen_idl_build_impl_struct(
+    strct: &syn::ItemStruct,
+    zero_copy: Option<ZeroCopy>,
+) -> TokenStream {
+    if !is_describable(strct) {
+        return quote! {};
+    }
+    let ident = &strct.ident;
+    let name = ident.to_string();
+    let fields = strct
+        .fields
+        .iter()
+        .map(|f| IdlField {
+            name: f.ident.as_ref().unwrap().to_string().to_mixed_case(),
+            docs: field_docs(f),
+            ty: IdlType::Bool,
+            constraints: vec![],
+            // Invalid names are reported by `#[account]`.
+            aliases: idl_name::parse(&f.attrs).unwrap_or_default(),
+        })
+        .collect();
+    let ty_def = to_json(&IdlTypeDefinition {
+        name: name.clone(),
+        docs: docs::parse(&strct.attrs),
+        ty: IdlTypeDefinitionTy::Struct { fields },
+        version: None,
+        migrations: vec![],
+        permissions: vec![],
+        discriminator: None,
+        state_machine: None,
+        size: None,
+        checksum: None,
+        serialization: zero_copy.into(),
+    });
+    let tys = strct.fields.iter().map(|f| field_type(&f.ty));
+    let inserts = strct.fields.iter().map(|f| insert_defined(&f.ty));
+    let (impl_gen, ty_gen, where_clause) = strct.generics.split_for_impl();
+    quote! {
+        #[cfg(feature = "idl-build")]
+        #[automatically_derived]
+        impl #impl_gen anchor_lang::idl::build::IdlBuild for #ident #ty_gen #where_clause {
+            fn __anchor_private_idl_type() -> anchor_lang::idl::build::types::IdlType {
+                anchor_lang::idl::build::types::IdlType::Defined(#name.to_string())
+            }
+
+            fn __anchor_private_insert_idl_defined(types: &mut anchor_lang::idl::build::Types) {
+                #[allow(unused_imports)]
+                use anchor_lang::idl::build::{IdlBuildResolved as _, IdlBuildUnresolved as _};
+                if types.contains_key(#name) {
+                    return;
+                }
+                let ty_def = anchor_lang::idl::build::type_definition(#ty_def, vec![#(#tys),*]);
+                types.insert(#name.to_string(), ty_def);
+                #(#inserts)*
+            }
+        }
+    }
+}
+
+// Implements `IdlBuild` for an `#[account]` enum.
+pub fn gen_idl_build_impl_enum(enm: &syn::ItemEnum) -> TokenStream {
+    if !is_describable_enum(enm) {
+        return quote! {};
+    }
+    let ident = &enm.ident;
+    let name = ident.to_string();
+    let variants = enm
+        .variants
+        .iter()
+        .map(|variant| IdlEnumVariant {
+            name: variant.ident.to_string(),
+            fields: match &variant.fields {
+                syn::Fields::Unit => None,
+                syn::Fields::Unnamed(fields) => Some(EnumFields::Tuple(
+                    fields.unnamed.iter().map(|_| IdlType::Bool).collect(),
+                )),
+                syn::Fields::Named(fields) => Some(EnumFields::Named(
+                    fields
+                        .named
+                        .iter()
+                        .map(|f| IdlField {
+                            name: f.ident.as_ref().unwrap().to_string(),
+                            docs: field_docs(f),
+                            ty: IdlType::Bool,
+                            constraints: vec![],
+                            aliases: vec![],
+                        })
+                        .collect(),
+                )),
+            },
+        })
+        .collect();
+    let ty_def = to_json(&IdlTypeDefinition {
+        name: name.clone(),
+        docs: docs::parse(&enm.attrs),
+        ty: IdlTypeDefinitionTy::Enum { variants },
+        version: None,
+        migrations: vec![],
+        permissions: vec![],
+        discriminator: None,
+        state_machine: None,
+        size: None,
+        checksum: None,
+        serialization: IdlSerialization::Borsh,
+    });
+    let fields = enm
+        .variants
+        .iter()
+        .flat_map(|variant| variant.fields.iter());
+    let tys = fields.clone().map(|f| field_type(&f.ty));
+    let inserts = fields.map(|f| insert_defined(&f.ty));
+    let (impl_gen, ty_gen, where_clause) = enm.generics.split_for_impl();
+    quote! {
+        #[cfg(feature = "idl-build")]
+        #[automatically_derived]
+        impl #impl_gen anchor_lang::idl::build::IdlBuild for #ident #ty_gen #where_clause {
+            fn __anchor_private_idl_type() -> anchor_lang::idl::build::types::IdlType {
+                anchor_lang::idl::build::types::IdlType::Defined(#name.to_string())
+            }
+
+            fn __anchor_private_insert_idl_defined(types: &mut anchor_lang::idl::build::Types) {
+                #[allow(unused_imports)]
+                use anchor_lang::idl::build::{IdlBuildResolved as _, IdlBuildUnresolved as _};
+                if types.contains_key(#name) {
+                    return;
+                }
+                let ty_def = anchor_lang::idl::build::type_definition(#ty_def, vec![#(#tys),*]);
+                types.insert(#name.to_string(), ty_def);
+                #(#inserts)*
+            }
+        }
+    }
+}
+
+fn test_ident(kind: &str, name: &str) -> syn::Ident {
+    format_ident!(
+        "{}_{}_{}",
+        IDL_BUILD_TEST_PREFIX,
+        kind,
+        name.to_snake_case()
+    )
+}
+
+// Test printing an `#[account]`, given the version of its layout and whether
+// it has a custom discriminator. Its discriminator, which may be shortened by
+// the program, is only known once compiled, so it's recorded when it's either
+// custom or shorter than 8 bytes.
+pub fn gen_idl_print_account(
+    strct: &syn::ItemStruct,
+    version: Option<u32>,
+    custom_discriminator: bool,
+    checksum: bool,
+) -> TokenStream {
+    if !is_describable(strct) {
+        return quote! {};
+    }
+    let ident = &strct.ident;
+    let name = ident.to_string();
+    let test = test_ident("account", &name);
+    // Invalid `#[since]`, `#[writable_by]` and `#[state_machine]` attributes
+    // are reported by the `#[account]` macro.
+    let migrations = to_json(&layout_migrations(&strct.fields).unwrap_or_default());
+    let permissions = to_json(&field_permissions(&strct.fields).unwrap_or_default());
+    let state_machine = to_json(&state_machine::state_machine(&strct.attrs).unwrap_or_default());
+    let version = match version {
+        Some(version) => quote! { Some(#version) },
+        None => quote! { None },
+    };
+    // The same `INIT_SPACE` the program allocates its accounts with.
+    let checksum = checksum.then(|| IdlChecksum::Crc32);
+    let size = account_size(ident, &strct.attrs, checksum);
+    let checksum = to_json(&checksum);
+    let discriminator = account_discriminator(ident, custom_discriminator);
+    quote! {
+        #[cfg(all(test, feature = "idl-build"))]
+        #[test]
+        fn #test() {
+            let mut types = anchor_lang::idl::build::Types::new();
+            <#ident as anchor_lang::idl::build::IdlBuild>::__anchor_private_insert_idl_defined(&mut types);
+            anchor_lang::idl::build::print_account(#name, types, #version, #migrations, #permissions, #state_machine, #size, #discriminator, #checksum);
+        }
+    }
+}
+
+// The space of an account deriving `InitSpace`, if it does, including its
+// discriminator and checksum.
+fn account_size(
+    ident: &syn::Ident,
+    attrs: &[syn::Attribute],
+    checksum: Option<IdlChecksum>,
+) -> TokenStream {
+    let checksum_size = checksum.map_or(0, |c| c.size());
+    match parser::space::derives_init_space(attrs) {
+        true => quote! {
+            Some((
+                <#ident as anchor_lang::Discriminator>::discriminator_len()
+                    + #checksum_size
+                    + <#ident as anchor_lang::Space>::INIT_SPACE
+            ) as u64)
+        },
+        false => quote! { None },
+    }
+}
+
+// The discriminator of an account in the IDL, if it's custom or shortened.
+fn account_discriminator(ident: &syn::Ident, custom: bool) -> TokenStream {
+    quote! {{
+        let len = <#ident as anchor_lang::Discriminator>::discriminator_len();
+        (#custom || len != 8)
+            .then(|| <#ident as anchor_lang::Discriminator>::discriminator()[..len].to_vec())
+    }}
+}
+
+// Test printing an `#[account]` enum, given whether it has a custom
+// discriminator. Enums have neither versions, permissions nor state machines.
+pub fn gen_idl_print_account_enum(
+    enm: &syn::ItemEnum,
+    custom_discriminator: bool,
+    checksum: bool,
+) -> TokenStream {
+    if !is_describable_enum(enm) {
+        return quote! {};
+    }
+    let ident = &enm.ident;
+    let name = ident.to_string();
+    let test = test_ident("account", &name);
+    let checksum = checksum.then(|| IdlChecksum::Crc32);
+    let size = account_size(ident, &enm.attrs, checksum);
+    let checksum = to_json(&checksum);
+    let discriminator = account_discriminator(ident, custom_discriminator);
+    quote! {
+        #[cfg(all(test, feature = "idl-build"))]
+        #[test]
+        fn #test() {
+            let mut types = anchor_lang::idl::build::Types::new();
+            <#ident as anchor_lang::idl::build::IdlBuild>::__anchor_private_insert_idl_defined(&mut types);
+            anchor_lang::idl::build::print_account(#name, types, None, "[]", "[]", "null", #size, #discriminator, #checksum);
+        }
+    }
+}
+
+// Test printing an `#[event]`.
+pub fn gen_idl_print_event(strct: &syn::ItemStruct) -> TokenStream {
+    if !is_describable(strct) {
+        return quote! {};
+    }
+    let name = strct.ident.to_string();
+    let test = test_ident("event", &name);
+    let event = to_json(&IdlEvent {
+        name: name.clone(),
+        fields: strct
+            .fields
+            .iter()
+            .map(|f| {
+                // Invalid attributes are reported by `#[event]` itself.
+                let index = crate::parser::event::index(&f.attrs).unwrap_or_default();
+                IdlEventField {
+                    name: f.ident.as_ref().unwrap().to_string().to_mixed_case(),
+                    ty: IdlType::Bool,
+                    index: index.is_some(),
+                    unique: index.map_or(false, |index| index.unique),
+                }
+            })
+            .collect(),
+        transports: vec![],
+    });
+    let tys = strct.fields.iter().map(|f| field_type(&f.ty));
+    let inserts = strct.fields.iter().map(|f| insert_defined(&f.ty));
+    quote! {
+        #[cfg(all(test, feature = "idl-build"))]
+        #[test]
+        fn #test() {
+            #[allow(unused_imports)]
+            use anchor_lang::idl::build::{IdlBuildResolved as _, IdlBuildUnresolved as _};
+            let types = &mut anchor_lang::idl::build::Types::new();
+            let tys = vec![#(#tys),*];
+            #(#inserts)*
+            anchor_lang::idl::build::print_event(#event, tys, std::mem::take(types));
+        }
+    }
+}
+
+// Test printing an `#[error_code]` enum, with the codes given by the compiled
+// program.
+pub fn gen_idl_print_errors(error: &Error) -> TokenStream {
+    let ident = &error.ident;
+    let test = test_ident("errors", &error.name);
+    let errors: Vec<IdlErrorCode> = error
+        .codes
+        .iter()
+        .zip(&error.raw_enum.variants)
+        .map(|(code, variant)| IdlErrorCode {
+            code: code.id,
+            name: code.ident.to_string(),
+            msg: code.msg.clone(),
+            docs: docs::parse(&variant.attrs),
+        })
+        .collect();
+    let errors = to_json(&errors);
+    let variants = error.codes.iter().map(|code| &code.ident);
+    quote! {
+        #[cfg(all(test, feature = "idl-build"))]
+        #[test]
+        fn #test() {
+            anchor_lang::idl::build::print_errors(#errors, vec![#(u32::from(#ident::#variants)),*]);
+        }
+    }
+}
+
+// Test printing a `#[constant]`.
+pub fn gen_idl_print_constant(item: &syn::ItemConst) -> TokenStream {
+    // Constants that aren't public aren't part of the IDL.
+    if !matches!(item.vis, syn::Visibility::Public(_)) {
+        return TokenStream::new();
+    }
+    let name = item.ident.to_string();
+    let test = test_ident("constant", &name);
+    let constant = to_json(&constant::parse_standalone(item));
+    let ty = &item.ty;
+    let idl_ty = field_type(ty);
+    let insert = insert_defined(ty);
+    quote! {
+        #[cfg(all(test, feature = "idl-build"))]
+        #[test]
+        fn #test() {
+            #[allow(unused_imports)]
+            use anchor_lang::idl::build::{IdlBuildResolved as _, IdlBuildUnresolved as _};
+            let types = &mut anchor_lang::idl::build::Types::new();
+            let ty = #idl_ty;
+            #insert
+            anchor_lang::idl::build::print_constant(
+                #constant,
+                module_path!(),
+                ty,
+                std::mem::take(types),
+            );
+        }
+    }
+}
+
+// Implements `IdlBuildAccounts` for an `Accounts` struct.
+pub fn gen_idl_build_impl_accounts(accs: &AccountsStruct) -> TokenStream {
+    let ident = &accs.ident;
+    let item = |field: &AccountField| match field {
+        AccountField::CompositeField(comp_f) => {
+            let name = comp_f.ident.to_string().to_mixed_case();
+            let ty = &comp_f.raw_field.ty;
+            quote! {
+                anchor_lang::idl::build::types::IdlAccountItem::IdlAccounts(
+                    anchor_lang::idl::build::types::IdlAccounts {
+                        name: #name.to_string(),
+                        accounts: <#ty as anchor_lang::idl::build::IdlBuildAccounts>::__anchor_private_gen_idl_accounts(),
+                    }
+                )
+            }
+        }
+        AccountField::Field(acc) => {
+            let (kind, address) = account_kind(None, &acc.ty);
+            // The accounts of fragments keep their name in the fragment.
+            let name = match &acc.fragment {
+                Some((_, name)) => name.clone(),
+                None => acc.ident.to_string(),
+            };
+            let json = to_json(&IdlAccount {
+                name: name.to_mixed_case(),
+                is_mut: acc.constraints.is_mutable(),
+                is_signer: match acc.ty {
+                    Ty::Signer => true,
+                    _ => acc.constraints.is_signer(),
+                },
+                is_optional: acc.is_optional.then(|| true),
+                is_init_if_needed: acc.constraints.is_init_if_needed().then(|| true),
+                space: None,
+                docs: acc.docs.clone(),
+                token: token_account(&acc.constraints),
+                realloc: realloc(None, &acc.constraints),
+                pda: metadata_pda(&acc.constraints),
+                kind,
+                address,
+                programs: account_programs(&acc.ty),
+                close: close_destination(&acc.constraints),
+            });
+            // The address of any program is known once compiled.
+            let address = match &acc.ty {
+                Ty::Program(program) => {
+                    let program = &program.account_type_path;
+                    quote! { Some(<#program as anchor_lang::Id>::id().to_string()) }
+                }
+                _ => quote! { None },
+            };
+            // As are the programs of any interface.
+            let programs = match &acc.ty {
+                Ty::Interface(interface) => {
+                    let interface = &interface.account_type_path;
+                    quote! { Some(<#interface as anchor_lang::Ids>::ids().iter().map(|id| id.to_string()).collect()) }
+                }
+                Ty::InterfaceAccount(account) => {
+                    let account = &account.account_type_path;
+                    quote! { Some(<#account as anchor_lang::Owners>::owners().iter().map(|id| id.to_string()).collect()) }
+                }
+                _ => quote! { None },
+            };
+            quote! { anchor_lang::idl::build::account(#json, #address, #programs) }
+        }
+    };
+    // The accounts embedded from a fragment are grouped under the name of the
+    // field of the fragment, like composite accounts.
+    let mut items = vec![];
+    let mut fields = accs.fields.iter().peekable();
+    while let Some(field) = fields.next() {
+        let group = match field {
+            AccountField::Field(Field {
+                fragment: Some((group, _)),
+                ..
+            }) => group,
+            _ => {
+                items.push(item(field));
+                continue;
+            }
+        };
+        let mut accounts = vec![item(field)];
+        while let Some(next) = fields.peek() {
+            match next {
+                AccountField::Field(Field {
+                    fragment: Some((next_group, _)),
+                    ..
+                }) if next_group == group => accounts.push(item(fields.next().unwrap())),
+                _ => break,
+            }
+        }
+        let name = group.to_mixed_case();
+        items.push(quote! {
+            anchor_lang::idl::build::types::IdlAccountItem::IdlAccounts(
+                anchor_lang::idl::build::types::IdlAccounts {
+                    name: #name.to_string(),
+                    accounts: vec![#(#accounts),*],
+                }
+            )
+        });
+    }
+    let (impl_gen, ty_gen, where_clause) = accs.generics.split_for_impl();
+    quote! {
+        #[cfg(feature = "idl-build")]
+        #[automatically_derived]
+        impl #impl_gen anchor_lang::idl::build::IdlBuildAccounts for #ident #ty_gen #where_clause {
+            fn __anchor_private_gen_idl_accounts() -> Vec<anchor_lang::idl::build::types::IdlAccountItem> {
+                vec![#(#items),*]
+            }
+        }
+    }
+}
+
+// Adds the test printing the instructions to the `#[program]` module, where
+// the types of the instructions are in scope.
+pub fn add_idl_print_program(program: &mut Program) {
+    let batch = batch_instruction(&program.ixs);
+    let fragment = to_json(&IdlFragment {
+        name: Some(program.name.to_string()),
+        docs: program.docs.clone(),
+        instruction_enum: (!program.ixs.is_empty()).then(|| IdlInstructionEnum {
+            name: "ProgramInstruction".to_string(),
+            variants: program
+                .ixs
+                .iter()
+                .map(|ix| {
+                    let name = ix.ident.to_string();
+                    IdlInstructionEnumVariant {
+                        name: name.to_camel_case(),
+                        instruction: name.to_mixed_case(),
+                        discriminator: sighash(ix.sighash_namespace(), &name)
+                            [..program.discriminator_len]
+                            .to_vec(),
+                    }
+                })
+                .chain(
+                    batch
+                        .as_ref()
+                        .map(|ix| batch_enum_variant(ix, program.discriminator_len)),
+                )
+                .collect(),
+        }),
+        fallback: program.fallback_fn.as_ref().map(IdlFallback::new),
+        ..Default::default()
+    });
+    let mut inserts = vec![];
+    let mut instructions: Vec<TokenStream> = program
+        .ixs
+        .iter()
+        .map(|ix| {
+            let mut idl_ix = IdlInstruction {
+                name: ix.ident.to_string().to_mixed_case(),
+                docs: ix.docs.clone(),
+                accounts: vec![],
+                args: ix
+                    .args
+                    .iter()
+                    .map(|arg| IdlField {
+                        name: arg.name.to_string().to_mixed_case(),
+                        docs: arg.docs.clone(),
+                        ty: IdlType::Bool,
+                        constraints: arg_constraints(&arg.constraints),
+                        aliases: arg.aliases.clone(),
+                    })
+                    .collect(),
+                returns: None,
+                namespace: ix.namespace.clone(),
+                discriminator: None,
+                guards: guards(&ix.raw_method.attrs).unwrap_or_default(),
+                remaining_accounts: None,
+                sunset: sunset(&ix.raw_method.attrs).unwrap_or_default(),
+                compute_budget: compute_budget(&ix.raw_method.attrs).unwrap_or_default(),
+                distinct_accounts: vec![],
+                admin: is_admin(&ix.raw_method.attrs),
+                batch: vec![],
+                signers: IdlSigners::default(),
+            };
+            idl_ix.shorten_discriminator(program.discriminator_len);
+            let json = to_json(&idl_ix);
+            let accounts = &ix.anchor_ident;
+            let tys: Vec<TokenStream> = ix
+                .args
+                .iter()
+                .map(|arg| field_type(&arg.raw_arg.ty))
+                .collect();
+            inserts.extend(ix.args.iter().map(|arg| insert_defined(&arg.raw_arg.ty)));
+            let returns = match parser::tts_to_string(&ix.returns.ty).as_str() {
+                "()" => quote! { None },
+                _ => {
+                    inserts.push(insert_defined(&ix.returns.ty));
+                    let ty = field_type(&ix.returns.ty);
+                    quote! { Some(#ty) }
+                }
+            };
+            quote! {
+                anchor_lang::idl::build::instruction(
+                    #json,
+                    <#accounts as anchor_lang::idl::build::IdlBuildAccounts>::__anchor_private_gen_idl_accounts(),
+                    vec![#(#tys),*],
+                    #returns,
+                )
+            }
+        })
+        .collect();
+    // The arguments of the `batch` instruction are already described.
+    instructions.extend(batch.map(|mut idl_ix| {
+        idl_ix.shorten_discriminator(program.discriminator_len);
+        let json = to_json(&idl_ix);
+        quote! {
+            anchor_lang::idl::build::instruction(#json, vec![], vec![], None)
+        }
+    }));
+    let test = format_ident!("{}_program", IDL_BUILD_TEST_PREFIX);
+    let item: syn::Item = syn::parse_quote! {
+        #[cfg(all(test, feature = "idl-build"))]
+        #[test]
+        fn #test() {
+            #[allow(unused_imports)]
+            use anchor_lang::idl::build::{IdlBuildResolved as _, IdlBuildUnresolved as _};
+            let types = &mut anchor_lang::idl::build::Types::new();
+            let instructions = vec![#(#instructions),*];
+            #(#inserts)*
+            anchor_lang::idl::build::print_program(#fragment, instructions, std::mem::take(types));
+        }
+    };
+    if let Some((_, items)) = &mut program.program_mod.content {
+        items.push(item);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assemble_fragments() {
+        let ty_def = |name: &str| IdlTypeDefinition {
+            name: name.to_string(),
+            docs: Some(vec![format!("The {}.", name)]),
+            ty: IdlTypeDefinitionTy::Struct {
+                fields: vec![IdlField {
+                    name: "value".to_string(),
+                    docs: None,
+                    ty: IdlType::Defined("Inner".to_string()),
+                    constraints: vec![],
+                    aliases: vec![],
+                }],
+            },
+            version: None,
+            migrations: vec![],
+            permissions: vec![],
+            discriminator: None,
+            state_machine: None,
+            size: None,
+            checksum: None,
+            serialization: IdlSerialization::Borsh,
+        };
+        let fragments = [
+            IdlFragment {
+                name: Some("counter".to_string()),
+                instructions: vec![IdlInstruction {
+                    name: "increment".to_string(),
+                    docs: Some(vec!["Increments.".to_string()]),
+                    accounts: vec![],
+                    args: vec![IdlField {
+                        name: "by".to_string(),
+                        docs: None,
+                        ty: IdlType::Defined("Missing".to_string()),
+                        constraints: vec![],
+                        aliases: vec![],
+                    }],
+                    returns: None,
+                    namespace: None,
+                    discriminator: None,
+                    guards: vec![],
+                    remaining_accounts: None,
+                    sunset: None,
+                    compute_budget: None,
+                    distinct_accounts: vec![],
+                    admin: false,
+                    batch: vec![],
+                    signers: IdlSigners::default(),
+                }],
+                ..Default::default()
+            },
+            IdlFragment {
+                accounts: vec![ty_def("Counter")],
+                types: vec![ty_def("Inner")],
+                ..Default::default()
+            },
+            IdlFragment {
+                errors: vec![
+                    IdlErrorCode {
+                        code: 6001,
+                        name: "B".to_string(),
+                        msg: None,
+                        docs: None,
+                    },
+                    IdlErrorCode {
+                        code: 6000,
+                        name: "A".to_string(),
+                        msg: None,
+                        docs: None,
+                    },
+                ],
+                ..Default::default()
+            },
+        ];
+
+        let output: String = fragments
+            .iter()
+            .map(|fragment| {
+                format!(
+                    "running 1 test\n{}\n{}\n{}\n",
+                    FRAGMENT_BEGIN,
+                    serde_json::to_string(fragment).unwrap(),
+                    FRAGMENT_END
+                )
+            })
+            .collect();
+        let fragments = parse_fragments(&output).unwrap();
+        let ParseOutput { idl, warnings } = assemble(
+            fragments.clone(),
+            "0.1.0".to_string(),
+            &DocsPolicy::none(),
+            FloatPolicy::Warn,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(idl.name, "counter");
+        assert_eq!(idl.accounts.len(), 1);
+        assert_eq!(idl.accounts[0].docs, None);
+        assert_eq!(idl.instructions[0].docs, None);
+        assert_eq!(
+            idl.types
+                .iter()
+                .map(|t| t.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Inner"]
+        );
+        assert_eq!(
+            idl.errors
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|e| e.code)
+                .collect::<Vec<_>>(),
+            vec![6000, 6001]
+        );
+        assert_eq!(
+            warnings,
+            vec![IdlWarning::UndefinedType("Missing".to_string())]
+        );
+
+        let mut output = ParseOutput { idl, warnings };
+        let parsed = Idl {
+            types: vec![ty_def("Missing"), ty_def("Unused")],
+            ..output.idl.clone()
+        };
+        define_types_from(&mut output, &parsed);
+        assert_eq!(
+            output
+                .idl
+                .types
+                .iter()
+                .map(|t| t.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Inner", "Missing"]
+        );
+        assert!(output.warnings.is_empty());
+
+        // Without the program's fragment, there's no IDL.
+        assert!(assemble(
+            fragments[1..].to_vec(),
+            "0.1.0".to_string(),
+            &DocsPolicy::default(),
+            FloatPolicy::Warn
+        )
+        .unwrap()
+        .is_none());
+    }
+}
@@ -0,0 +1,556 @@
+use crate::idl::*;
+use heck::MixedCase;
+use serde_json::json;
+
+// Version of the Codama (formerly Kinobi) node tree standard.
+const CODAMA_VERSION: &str = "1.0.0";
+
+impl Idl {
+    // Returns the program as a Codama `rootNode`, the node tree consumed by
+    // the Codama (formerly Kinobi) client generators.
+    //
+    // Names are camel case, and the discriminators of accounts and
+    // instructions are their first field, `discriminator`, omitted from the
+    // generated clients. Composite accounts are flattened, e.g. `vaultMint`
+    // for the `mint` of the `vault` accounts. Each PDA with seeds in the IDL
+    // is a `pdaNode` of the program, named after its instruction and account,
+    // which the instruction account defaults to, with the seeds derived from
+    // the instruction's own arguments and accounts filled in. Seeds derived
+    // from fields of arguments or of account data are left to the caller.
+    //
+    // Events and constants aren't part of the standard, and aren't exported.
+    pub fn codama(&self) -> JsonValue {
+        let mut pdas = vec![];
+        let instructions: Vec<JsonValue> = self
+            .instructions
+            .iter()
+            .map(|ix| instruction_node(ix, &mut pdas))
+            .collect();
+        let accounts: Vec<JsonValue> = self.accounts.iter().map(account_node).collect();
+        let defined_types: Vec<JsonValue> = self
+            .types
+            .iter()
+            .map(|ty_def| {
+                json!({
+                    "kind": "definedTypeNode",
+                    "name": ty_def.name.to_mixed_case(),
+                    "docs": docs(&ty_def.docs),
+                    "type": type_definition_node(&ty_def.ty),
+                })
+            })
+            .collect();
+        let errors: Vec<JsonValue> = self
+            .errors
+            .iter()
+            .flatten()
+            .map(|error| {
+                json!({
+                    "kind": "errorNode",
+                    "name": error.name.to_mixed_case(),
+                    "code": error.code,
+                    "message": error.msg.clone().unwrap_or_default(),
+                    "docs": docs(&error.docs),
+                })
+            })
+            .collect();
+        let public_key = self
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("address"))
+            .and_then(|address| address.as_str())
+            .unwrap_or_default();
+
+        json!({
+            "kind": "rootNode",
+            "standard": "codama",
+            "version": CODAMA_VERSION,
+            "program": {
+                "kind": "programNode",
+                "name": self.name.to_mixed_case(),
+                "publicKey": public_key,
+                "version": self.version,
+                "origin": "anchor",
+                "docs": docs(&self.docs),
+                "accounts": accounts,
+                "instructions": instructions,
+                "definedTypes": defined_types,
+                "pdas": pdas,
+                "errors": errors,
+            },
+            "additionalPrograms": [],
+        })
+    }
+}
+
+fn account_node(acc: &IdlTypeDefinition) -> JsonValue {
+    let discriminator = acc.account_discriminator();
+    let mut fields = discriminator_field("structFieldTypeNode", &discriminator);
+    match &acc.ty {
+        IdlTypeDefinitionTy::Struct { fields: acc_fields } => {
+            fields.extend(acc_fields.iter().map(field_node))
+        }
+        // Laid out the same as a struct with the enum as its only field.
+        ty @ IdlTypeDefinitionTy::Enum { .. } => fields.push(json!({
+            "kind": "structFieldTypeNode",
+            "name": "value",
+            "docs": [],
+            "type": type_definition_node(ty),
+        })),
+    }
+    json!({
+        "kind": "accountNode",
+        "name": acc.name.to_mixed_case(),
+        "docs": docs(&acc.docs),
+        "data": { "kind": "structTypeNode", "fields": fields },
+        "discriminators": discriminator_nodes(&discriminator),
+    })
+}
+
+fn instruction_node(ix: &IdlInstruction, pdas: &mut Vec<JsonValue>) -> JsonValue {
+    let discriminator = ix.discriminator();
+    let mut arguments = discriminator_field("instructionArgumentNode", &discriminator);
+    arguments.extend(ix.args.iter().map(|arg| {
+        json!({
+            "kind": "instructionArgumentNode",
+            "name": arg.name.to_mixed_case(),
+            "docs": docs(&arg.docs),
+            "type": type_node(&arg.ty),
+        })
+    }));
+
+    let mut accounts = vec![];
+    flatten_accounts(&ix.accounts, "", &mut accounts);
+    let account_names: Vec<String> = accounts
+        .iter()
+        .map(|(name, _)| name.to_mixed_case())
+        .collect();
+    let accounts: Vec<JsonValue> = accounts
+        .into_iter()
+        .map(|(name, acc)| {
+            let mut node = json!({
+                "kind": "instructionAccountNode",
+                "name": name.to_mixed_case(),
+                "isWritable": acc.is_mut,
+                "isSigner": acc.is_signer,
+                "isOptional": acc.is_optional.unwrap_or(false),
+                "docs": docs(&acc.docs),
+            });
+            let default = match (&acc.address, &acc.pda) {
+                (Some(address), _) => Some(json!({
+                    "kind": "publicKeyValueNode",
+                    "publicKey": address,
+                })),
+                (None, Some(pda)) => {
+                    let pda_name = format!("{}_{}", ix.name, name).to_mixed_case();
+                    pda_value_node(pda, &pda_name, ix, &account_names).map(|(pda, value)| {
+                        pdas.push(pda);
+                        value
+                    })
+                }
+                (None, None) => None,
+            };
+            if let Some(default) = default {
+                node["defaultValue"] = default;
+            }
+            node
+        })
+        .collect();
+
+    json!({
+        "kind": "instructionNode",
+        "name": ix.name.to_mixed_case(),
+        "docs": docs(&ix.docs),
+        "optionalAccountStrategy": "programId",
+        "accounts": accounts,
+        "arguments": arguments,
+        "discriminators": discriminator_nodes(&discriminator),
+    })
+}
+
+// The accounts of an instruction in order, with the names of the composite
+// accounts they're nested in prepended, e.g. `vault_mint`.
+fn flatten_accounts<'a>(
+    items: &'a [IdlAccountItem],
+    prefix: &str,
+    accounts: &mut Vec<(String, &'a IdlAccount)>,
+) {
+    for item in items {
+        match item {
+            IdlAccountItem::IdlAccount(acc) => {
+                accounts.push((format!("{}{}", prefix, acc.name), acc))
+            }
+            IdlAccountItem::IdlAccounts(accs) => {
+                let prefix = format!("{}{}_", prefix, accs.name);
+                flatten_accounts(&accs.accounts, &prefix, accounts)
+            }
+        }
+    }
+}
+
+// The `pdaNode` of a PDA, named `name`, and the `pdaValueNode` linking to it
+// with the seeds derived from the arguments and accounts of `ix`. `None` if a
+// seed or the program id can't be described, e.g. a constant of a type
+// without a known encoding.
+fn pda_value_node(
+    pda: &IdlPda,
+    name: &str,
+    ix: &IdlInstruction,
+    account_names: &[String],
+) -> Option<(JsonValue, JsonValue)> {
+    let mut seeds = vec![];
+    let mut values = vec![];
+    for seed in &pda.seeds {
+        // Seeds of nested paths, e.g. `params.nonce`, are named after the
+        // whole path, e.g. `paramsNonce`, and aren't derived.
+        let (path, ty) = match seed {
+            IdlSeed::Const(seed) => {
+                let (ty, value) = const_seed_nodes(&seed.value, &seed.ty)?;
+                seeds.push(json!({
+                    "kind": "constantPdaSeedNode",
+                    "type": ty,
+                    "value": value,
+                }));
+                continue;
+            }
+            IdlSeed::Arg(seed) => (&seed.path, &seed.ty),
+            IdlSeed::Account(seed) => (&seed.path, &seed.ty),
+        };
+        let name = path.replace('.', "_").to_mixed_case();
+        seeds.push(json!({
+            "kind": "variablePdaSeedNode",
+            "name": name,
+            "docs": [],
+            "type": seed_type_node(ty),
+        }));
+        let value = match seed {
+            _ if path.contains('.') => None,
+            IdlSeed::Arg(_) if ix.args.iter().any(|arg| arg.name.to_mixed_case() == name) => {
+                Some(json!({ "kind": "argumentValueNode", "name": name }))
+            }
+            IdlSeed::Account(_) if account_names.contains(&name) => {
+                Some(json!({ "kind": "accountValueNode", "name": name }))
+            }
+            _ => None,
+        };
+        if let Some(value) = value {
+            values.push(json!({
+                "kind": "pdaSeedValueNode",
+                "name": name,
+                "value": value,
+            }));
+        }
+    }
+
+    let mut pda_node = json!({
+        "kind": "pdaNode",
+        "name": name,
+        "docs": [],
+        "seeds": seeds,
+    });
+    match &pda.program_id {
+        None => {}
+        Some(IdlSeed::Const(IdlSeedConst {
+            ty: IdlType::PublicKey,
+            value: JsonValue::String(program_id),
+        })) => pda_node["programId"] = json!(program_id),
+        Some(_) => return None,
+    }
+    let value = json!({
+        "kind": "pdaValueNode",
+        "pda": { "kind": "pdaLinkNode", "name": name },
+        "seeds": values,
+    });
+    Some((pda_node, value))
+}
+
+// The type and value nodes of a constant seed, if its encoding is known.
+fn const_seed_nodes(value: &JsonValue, ty: &IdlType) -> Option<(JsonValue, JsonValue)> {
+    let nodes = match (ty, value) {
+        (IdlType::String, JsonValue::String(s)) => (
+            seed_type_node(ty),
+            json!({ "kind": "stringValueNode", "string": s }),
+        ),
+        (IdlType::PublicKey, JsonValue::String(key)) => (
+            seed_type_node(ty),
+            json!({ "kind": "publicKeyValueNode", "publicKey": key }),
+        ),
+        (_, JsonValue::Array(bytes)) if *ty == IdlType::Bytes || is_byte_array(ty) => {
+            let data = bytes
+                .iter()
+                .map(|b| b.as_u64().map(|b| format!("{:02x}", b)))
+                .collect::<Option<String>>()?;
+            (
+                seed_type_node(ty),
+                json!({ "kind": "bytesValueNode", "encoding": "base16", "data": data }),
+            )
+        }
+        (IdlType::Bool, JsonValue::Bool(b)) => (
+            type_node(ty),
+            json!({ "kind": "booleanValueNode", "boolean": b }),
+        ),
+        (
+            IdlType::U8
+            | IdlType::I8
+            | IdlType::U16
+            | IdlType::I16
+            | IdlType::U32
+            | IdlType::I32
+            | IdlType::U64
+            | IdlType::I64,
+            JsonValue::Number(n),
+        ) => (
+            type_node(ty),
+            json!({ "kind": "numberValueNode", "number": n }),
+        ),
+        _ => return None,
+    };
+    Some(nodes)
+}
+
+// The type of a seed, whose bytes are used as is, without the length prefix
+// of strings and bytes.
+fn seed_type_node(ty: &IdlType) -> JsonValue {
+    match ty {
+        IdlType::String => json!({ "kind": "stringTypeNode", "encoding": "utf8" }),
+        IdlType::Bytes => json!({ "kind": "bytesTypeNode" }),
+        IdlType::Array(_, len) if is_byte_array(ty) => json!({
+            "kind": "fixedSizeTypeNode",
+            "size": len,
+            "type": { "kind": "bytesTypeNode" },
+        }),
+        _ => type_node(ty),
+    }
+}
+
+fn is_byte_array(ty: &IdlType) -> bool {
+    matches!(ty, IdlType::Array(ty, _) if **ty == IdlType::U8)
+}
+
+fn type_definition_node(ty: &IdlTypeDefinitionTy) -> JsonValue {
+    match ty {
+        IdlTypeDefinitionTy::Struct { fields } => struct_node(fields),
+        IdlTypeDefinitionTy::Enum { variants } => {
+            let variants: Vec<JsonValue> = variants
+                .iter()
+                .map(|variant| {
+                    let name = variant.name.to_mixed_case();
+                    match &variant.fields {
+                        None => json!({ "kind": "enumEmptyVariantTypeNode", "name": name }),
+                        Some(EnumFields::Named(fields)) => json!({
+                            "kind": "enumStructVariantTypeNode",
+                            "name": name,
+                            "struct": struct_node(fields),
+                        }),
+                        Some(EnumFields::Tuple(tys)) => json!({
+                            "kind": "enumTupleVariantTypeNode",
+                            "name": name,
+                            "tuple": {
+                                "kind": "tupleTypeNode",
+                                "items": tys.iter().map(type_node).collect::<Vec<_>>(),
+                            },
+                        }),
+                    }
+                })
+                .collect();
+            json!({
+                "kind": "enumTypeNode",
+                "variants": variants,
+                "size": number_node("u8"),
+            })
+        }
+    }
+}
+
+fn struct_node(fields: &[IdlField]) -> JsonValue {
+    json!({
+        "kind": "structTypeNode",
+        "fields": fields.iter().map(field_node).collect::<Vec<_>>(),
+    })
+}
+
+fn field_node(field: &IdlField) -> JsonValue {
+    json!({
+        "kind": "structFieldTypeNode",
+        "name": field.name.to_mixed_case(),
+        "docs": docs(&field.docs),
+        "type": type_node(&field.ty),
+    })
+}
+
+fn type_node(ty: &IdlType) -> JsonValue {
+    match ty {
+        IdlType::Bool => json!({ "kind": "booleanTypeNode", "size": number_node("u8") }),
+        IdlType::U8 => number_node("u8"),
+        IdlType::I8 => number_node("i8"),
+        IdlType::U16 => number_node("u16"),
+        IdlType::I16 => number_node("i16"),
+        IdlType::U32 => number_node("u32"),
+        IdlType::I32 => number_node("i32"),
+        IdlType::F32 => number_node("f32"),
+        IdlType::U64 => number_node("u64"),
+        IdlType::I64 => number_node("i64"),
+        IdlType::F64 => number_node("f64"),
+        IdlType::U128 => number_node("u128"),
+        IdlType::I128 => number_node("i128"),
+        IdlType::Float(float) => match float.bits {
+            32 => number_node("f32"),
+            _ => number_node("f64"),
+        },
+        IdlType::Bytes => json!({
+            "kind": "sizePrefixTypeNode",
+            "type": { "kind": "bytesTypeNode" },
+            "prefix": number_node("u32"),
+        }),
+        IdlType::String => json!({
+            "kind": "sizePrefixTypeNode",
+            "type": { "kind": "stringTypeNode", "encoding": "utf8" },
+            "prefix": number_node("u32"),
+        }),
+        IdlType::PublicKey => json!({ "kind": "publicKeyTypeNode" }),
+        IdlType::Defined(name) => json!({
+            "kind": "definedTypeLinkNode",
+            "name": name.to_mixed_case(),
+        }),
+        IdlType::Option(ty) => json!({
+            "kind": "optionTypeNode",
+            "fixed": false,
+            "item": type_node(ty),
+            "prefix": number_node("u8"),
+        }),
+        IdlType::Vec(ty) => json!({
+            "kind": "arrayTypeNode",
+            "item": type_node(ty),
+            "count": { "kind": "prefixedCountNode", "prefix": number_node("u32") },
+        }),
+        IdlType::Array(ty, len) => json!({
+            "kind": "arrayTypeNode",
+            "item": type_node(ty),
+            "count": { "kind": "fixedCountNode", "value": len },
+        }),
+    }
+}
+
+fn number_node(format: &str) -> JsonValue {
+    json!({ "kind": "numberTypeNode", "format": format, "endian": "le" })
+}
+
+// The discriminator as the first field of the data of an account, or argument
+// of an instruction, omitted from the values given by the caller. Nothing if
+// the discriminator is empty.
+fn discriminator_field(kind: &str, discriminator: &[u8]) -> Vec<JsonValue> {
+    if discriminator.is_empty() {
+        return vec![];
+    }
+    let data: String = discriminator.iter().map(|b| format!("{:02x}", b)).collect();
+    vec![json!({
+        "kind": kind,
+        "name": "discriminator",
+        "docs": [],
+        "type": {
+            "kind": "fixedSizeTypeNode",
+            "size": discriminator.len(),
+            "type": { "kind": "bytesTypeNode" },
+        },
+        "defaultValue": { "kind": "bytesValueNode", "encoding": "base16", "data": data },
+        "defaultValueStrategy": "omitted",
+    })]
+}
+
+fn discriminator_nodes(discriminator: &[u8]) -> JsonValue {
+    match discriminator.is_empty() {
+        true => json!([]),
+        false => {
+            json!([{ "kind": "fieldDiscriminatorNode", "name": "discriminator", "offset": 0 }])
+        }
+    }
+}
+
+fn docs(docs: &Option<Vec<String>>) -> JsonValue {
+    json!(docs.clone().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::program::common::sighash;
+
+    #[test]
+    fn codama() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "vault_program",
+            "instructions": [{
+                "name": "deposit",
+                "accounts": [
+                    {
+                        "name": "vault",
+                        "isMut": true,
+                        "isSigner": false,
+                        "pda": {
+                            "seeds": [
+                                { "kind": "const", "type": "string", "value": "vault" },
+                                { "kind": "account", "type": "publicKey", "path": "owner" },
+                                { "kind": "arg", "type": "u64", "path": "params.nonce" }
+                            ]
+                        }
+                    },
+                    { "name": "owner", "isMut": false, "isSigner": true }
+                ],
+                "args": [{ "name": "params", "type": { "defined": "DepositParams" } }]
+            }],
+            "accounts": [{
+                "name": "Vault",
+                "type": {
+                    "kind": "struct",
+                    "fields": [{ "name": "total_amount", "type": "u64" }]
+                }
+            }],
+            "types": [{
+                "name": "DepositParams",
+                "type": {
+                    "kind": "struct",
+                    "fields": [{ "name": "nonce", "type": "u64" }]
+                }
+            }]
+        }))
+        .unwrap();
+        let root = idl.codama();
+        let program = &root["program"];
+
+        assert_eq!(program["name"], "vaultProgram");
+        let vault = &program["accounts"][0];
+        assert_eq!(
+            vault["data"]["fields"][0]["defaultValue"]["data"],
+            sighash("account", "Vault")
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        );
+        assert_eq!(vault["data"]["fields"][1]["name"], "totalAmount");
+
+        let deposit = &program["instructions"][0];
+        assert_eq!(
+            deposit["arguments"][1]["type"],
+            json!({ "kind": "definedTypeLinkNode", "name": "depositParams" })
+        );
+        assert_eq!(
+            deposit["accounts"][0]["defaultValue"],
+            json!({
+                "kind": "pdaValueNode",
+                "pda": { "kind": "pdaLinkNode", "name": "depositVault" },
+                "seeds": [{
+                    "kind": "pdaSeedValueNode",
+                    "name": "owner",
+                    "value": { "kind": "accountValueNode", "name": "owner" },
+                }],
+            })
+        );
+        let seeds = program["pdas"][0]["seeds"].as_array().unwrap();
+        assert_eq!(seeds.len(), 3);
+        assert_eq!(
+            seeds[0]["value"],
+            json!({ "kind": "stringValueNode", "string": "vault" })
+        );
+        assert_eq!(seeds[2]["name"], "paramsNonce");
+    }
+}
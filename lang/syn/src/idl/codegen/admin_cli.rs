@@ -0,0 +1,600 @@
+use super::rust_cpi::{discriminator, docs, ident};
+use crate::idl::*;
+use anyhow::{anyhow, Result};
+use heck::{CamelCase, KebabCase, SnakeCase};
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use std::path::Path;
+
+// A standalone binary for operators to call the `#[admin]` instructions of a
+// program with, generated from its IDL.
+//
+// There's a subcommand per admin instruction, with a flag per argument and
+// per account, e.g. `vault-admin set-fee --fee-bps 30 --config <KEY>`.
+// Accounts nested in composite structs are flagged by their path joined with
+// `-`. Signer accounts default to the signer of the transaction, accounts with
+// a known address to it, and optional accounts to being omitted.
+//
+// The cluster and keypair are read from a TOML config file, `admin.toml` by
+// default, and can be overridden with `--cluster` and `--keypair`. With
+// `--dry-run`, the transaction is simulated instead of sent. With
+// `--export <VAULT>`, the unsigned message of the transaction, with the
+// multisig vault as its payer and signer, is printed in base58 instead, to be
+// proposed to the multisig.
+//
+// Arguments of user defined types, arrays, and nested options or vectors
+// can't be given as flags, and their instructions are rejected.
+
+pub struct AdminCliCrate {
+    pub cargo_toml: String,
+    pub main_rs: String,
+}
+
+impl AdminCliCrate {
+    // Writes the manifest and `src/main.rs` of the crate to `dir`.
+    pub fn write(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir.join("src"))?;
+        std::fs::write(dir.join("Cargo.toml"), &self.cargo_toml)?;
+        std::fs::write(dir.join("src").join("main.rs"), &self.main_rs)?;
+        Ok(())
+    }
+}
+
+// The CLI of the program deployed at `program_id`, defaulting to the address
+// in the IDL's metadata. The code of `main_rs` isn't formatted.
+pub fn generate(idl: &Idl, program_id: Option<&str>) -> Result<AdminCliCrate> {
+    let program_id = match program_id {
+        Some(program_id) => program_id.to_string(),
+        None => idl
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("address"))
+            .and_then(|address| address.as_str())
+            .map(ToString::to_string)
+            .ok_or_else(|| anyhow!("The IDL has no address, the program id must be given"))?,
+    };
+    match bs58::decode(&program_id).into_vec() {
+        Ok(bytes) if bytes.len() == 32 => {}
+        _ => return Err(anyhow!("Invalid program id: {}", program_id)),
+    }
+    let admin_ixs: Vec<&IdlInstruction> = idl.instructions.iter().filter(|ix| ix.admin).collect();
+    if admin_ixs.is_empty() {
+        return Err(anyhow!(
+            "The IDL has no `#[admin]` instructions to generate a CLI for"
+        ));
+    }
+    let commands = admin_ixs
+        .iter()
+        .map(|ix| Command::new(ix))
+        .collect::<Result<Vec<_>>>()?;
+
+    let bin = format!("{}-admin", idl.name.to_kebab_case());
+    let doc = format!(
+        " Admin CLI of the `{}` program, generated from its IDL.",
+        idl.name
+    );
+    let about = format!("Calls the admin instructions of the {} program", idl.name);
+    let variants = commands.iter().map(Command::variant);
+    let arms = commands.iter().map(Command::arm);
+    let main_rs = quote! {
+        #![doc = #doc]
+
+        use anyhow::{anyhow, Result};
+        use clap::Parser;
+        use solana_client::rpc_client::RpcClient;
+        use solana_sdk::commitment_config::CommitmentConfig;
+        use solana_sdk::instruction::{AccountMeta, Instruction};
+        use solana_sdk::message::Message;
+        use solana_sdk::pubkey::Pubkey;
+        use solana_sdk::signature::{read_keypair_file, Signer};
+        use solana_sdk::transaction::Transaction;
+        use std::str::FromStr;
+
+        const PROGRAM_ID: &str = #program_id;
+
+        #[derive(Parser)]
+        #[clap(name = #bin, about = #about)]
+        struct Opts {
+            /// TOML file with the `cluster` and `keypair` to use.
+            #[clap(long, default_value = "admin.toml")]
+            config: String,
+            /// Cluster to send the transaction to, overriding the config's:
+            /// `mainnet`, `devnet`, `testnet`, `localnet` or a URL.
+            #[clap(long)]
+            cluster: Option<String>,
+            /// Keypair paying for and signing the transaction, overriding the
+            /// config's.
+            #[clap(long)]
+            keypair: Option<String>,
+            /// Simulates the transaction instead of sending it.
+            #[clap(long)]
+            dry_run: bool,
+            /// Prints the base58 message of the transaction, paid for and
+            /// signed by the given multisig vault, instead of sending it.
+            #[clap(long)]
+            export: Option<Pubkey>,
+            #[clap(subcommand)]
+            command: Command,
+        }
+
+        #[derive(Default, serde::Deserialize)]
+        struct Config {
+            cluster: Option<String>,
+            keypair: Option<String>,
+        }
+
+        impl Config {
+            fn load(path: &str) -> Result<Self> {
+                match std::fs::read_to_string(path) {
+                    Ok(config) => toml::from_str(&config)
+                        .map_err(|e| anyhow!("Invalid config {}: {}", path, e)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+                    Err(e) => Err(e.into()),
+                }
+            }
+        }
+
+        #[derive(clap::Subcommand)]
+        enum Command {
+            #(#variants),*
+        }
+
+        impl Command {
+            fn instruction(self, program_id: Pubkey, signer: Pubkey) -> Instruction {
+                match self {
+                    #(#arms),*
+                }
+            }
+        }
+
+        fn main() -> Result<()> {
+            let opts = Opts::parse();
+            let config = Config::load(&opts.config)?;
+            let program_id = Pubkey::from_str(PROGRAM_ID)?;
+
+            if let Some(vault) = opts.export {
+                let ix = opts.command.instruction(program_id, vault);
+                let message = Message::new(&[ix], Some(&vault));
+                println!("{}", bs58::encode(message.serialize()).into_string());
+                return Ok(());
+            }
+
+            let keypair = opts
+                .keypair
+                .or(config.keypair)
+                .unwrap_or_else(|| "~/.config/solana/id.json".to_string());
+            let keypair = read_keypair_file(expand_home(&keypair))
+                .map_err(|e| anyhow!("Unable to read keypair {}: {}", keypair, e))?;
+            let cluster = opts
+                .cluster
+                .or(config.cluster)
+                .unwrap_or_else(|| "localnet".to_string());
+            let client = RpcClient::new_with_commitment(
+                cluster_url(&cluster).to_string(),
+                CommitmentConfig::confirmed(),
+            );
+            let ix = opts.command.instruction(program_id, keypair.pubkey());
+            let tx = Transaction::new_signed_with_payer(
+                &[ix],
+                Some(&keypair.pubkey()),
+                &[&keypair],
+                client.get_latest_blockhash()?,
+            );
+
+            if opts.dry_run {
+                let result = client.simulate_transaction(&tx)?.value;
+                for log in result.logs.unwrap_or_default() {
+                    println!("{}", log);
+                }
+                return match result.err {
+                    Some(err) => Err(anyhow!("Simulation failed: {}", err)),
+                    None => Ok(()),
+                };
+            }
+            let signature = client.send_and_confirm_transaction(&tx)?;
+            println!("{}", signature);
+            Ok(())
+        }
+
+        fn cluster_url(cluster: &str) -> &str {
+            match cluster {
+                "mainnet" => "https://api.mainnet-beta.solana.com",
+                "devnet" => "https://api.devnet.solana.com",
+                "testnet" => "https://api.testnet.solana.com",
+                "localnet" => "http://127.0.0.1:8899",
+                url => url,
+            }
+        }
+
+        fn expand_home(path: &str) -> String {
+            match (path.strip_prefix("~/"), std::env::var("HOME")) {
+                (Some(path), Ok(home)) => format!("{}/{}", home, path),
+                _ => path.to_string(),
+            }
+        }
+
+        /// Borsh encoding of the arguments.
+        trait Encode {
+            fn encode(&self, data: &mut Vec<u8>);
+        }
+
+        macro_rules! encode_le {
+            ($($ty:ty),*) => {
+                $(
+                    impl Encode for $ty {
+                        fn encode(&self, data: &mut Vec<u8>) {
+                            data.extend_from_slice(&self.to_le_bytes());
+                        }
+                    }
+                )*
+            };
+        }
+
+        encode_le!(u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, f32, f64);
+
+        impl Encode for bool {
+            fn encode(&self, data: &mut Vec<u8>) {
+                data.push(*self as u8);
+            }
+        }
+
+        impl Encode for String {
+            fn encode(&self, data: &mut Vec<u8>) {
+                (self.len() as u32).encode(data);
+                data.extend_from_slice(self.as_bytes());
+            }
+        }
+
+        impl Encode for Pubkey {
+            fn encode(&self, data: &mut Vec<u8>) {
+                data.extend_from_slice(self.as_ref());
+            }
+        }
+
+        impl<T: Encode> Encode for Option<T> {
+            fn encode(&self, data: &mut Vec<u8>) {
+                match self {
+                    None => data.push(0),
+                    Some(value) => {
+                        data.push(1);
+                        value.encode(data);
+                    }
+                }
+            }
+        }
+
+        impl<T: Encode> Encode for Vec<T> {
+            fn encode(&self, data: &mut Vec<u8>) {
+                (self.len() as u32).encode(data);
+                for value in self {
+                    value.encode(data);
+                }
+            }
+        }
+
+        /// Bytes given in hex.
+        struct Bytes(Vec<u8>);
+
+        impl FromStr for Bytes {
+            type Err = String;
+
+            fn from_str(hex: &str) -> std::result::Result<Self, Self::Err> {
+                let hex = hex.strip_prefix("0x").unwrap_or(hex);
+                if hex.len() % 2 != 0 {
+                    return Err("Expected an even number of hex digits".to_string());
+                }
+                (0..hex.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| e.to_string()))
+                    .collect::<std::result::Result<_, _>>()
+                    .map(Bytes)
+            }
+        }
+
+        impl Encode for Bytes {
+            fn encode(&self, data: &mut Vec<u8>) {
+                self.0.encode(data);
+            }
+        }
+    };
+
+    let cargo_toml = format!(
+        r#"[package]
+name = "{}"
+version = "{}"
+description = "Admin CLI of the {} program, generated from its IDL"
+edition = "2021"
+
+[dependencies]
+anyhow = "1.0"
+bs58 = "0.4"
+clap = {{ version = "3", features = ["derive"] }}
+serde = {{ version = "1.0", features = ["derive"] }}
+solana-client = "~1.10"
+solana-sdk = "~1.10"
+toml = "0.5"
+"#,
+        bin, idl.version, idl.name,
+    );
+    Ok(AdminCliCrate {
+        cargo_toml,
+        main_rs: main_rs.to_string(),
+    })
+}
+
+// The subcommand of an admin instruction.
+struct Command<'a> {
+    ix: &'a IdlInstruction,
+    args: Vec<(Ident, TokenStream)>,
+    // Flattened accounts, named after their path.
+    accounts: Vec<(Ident, &'a IdlAccount)>,
+}
+
+impl<'a> Command<'a> {
+    fn new(ix: &'a IdlInstruction) -> Result<Self> {
+        let args = ix
+            .args
+            .iter()
+            .map(|arg| {
+                let ty = flag_type(&arg.ty).ok_or_else(|| {
+                    anyhow!(
+                        "Argument {} of {} can't be given as a flag",
+                        arg.name,
+                        ix.name
+                    )
+                })?;
+                Ok((ident(&arg.name.to_snake_case()), ty))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut accounts = vec![];
+        flatten(None, &ix.accounts, &mut accounts);
+        for (name, _) in &accounts {
+            if args.iter().any(|(arg, _)| arg == name) {
+                return Err(anyhow!(
+                    "Argument and account {} of {} have the same flag",
+                    name,
+                    ix.name
+                ));
+            }
+        }
+        Ok(Self { ix, args, accounts })
+    }
+
+    fn variant(&self) -> TokenStream {
+        let docs = docs(&self.ix.docs);
+        let name = format_ident!("{}", self.ix.name.to_camel_case());
+        let args = self.args.iter().map(|(name, ty)| {
+            quote! {
+                #[clap(long)]
+                #name: #ty
+            }
+        });
+        let accounts = self.accounts.iter().map(|(name, acc)| {
+            let doc = format!(
+                " Account `{}`{}.",
+                acc.name,
+                match (acc.is_signer, &acc.address, acc.is_optional) {
+                    (true, _, _) => ", the signer of the transaction by default",
+                    (_, Some(_), _) => ", its known address by default",
+                    (_, _, Some(true)) => ", omitted by default",
+                    _ => "",
+                }
+            );
+            let ty = match acc.is_signer || acc.address.is_some() || acc.is_optional == Some(true) {
+                true => quote! { Option<Pubkey> },
+                false => quote! { Pubkey },
+            };
+            quote! {
+                #[doc = #doc]
+                #[clap(long)]
+                #name: #ty
+            }
+        });
+        quote! {
+            #docs
+            #name {
+                #(#args,)*
+                #(#accounts),*
+            }
+        }
+    }
+
+    fn arm(&self) -> TokenStream {
+        let name = format_ident!("{}", self.ix.name.to_camel_case());
+        let fields = self
+            .args
+            .iter()
+            .map(|(name, _)| name)
+            .chain(self.accounts.iter().map(|(name, _)| name));
+        let discriminator = discriminator(&self.ix.discriminator());
+        let args = self.args.iter().map(|(name, _)| name);
+        let metas = self.accounts.iter().map(|(name, acc)| {
+            let is_signer = acc.is_signer;
+            let is_writable = acc.is_mut;
+            let pubkey = match (acc.is_signer, &acc.address, acc.is_optional) {
+                (true, _, _) => quote! { #name.unwrap_or(signer) },
+                (_, Some(address), _) => quote! {
+                    #name.unwrap_or_else(|| Pubkey::from_str(#address).unwrap())
+                },
+                // Omitted optional accounts are given as the program id.
+                (_, _, Some(true)) => {
+                    return quote! {
+                        match #name {
+                            Some(pubkey) => AccountMeta {
+                                pubkey,
+                                is_signer: #is_signer,
+                                is_writable: #is_writable,
+                            },
+                            None => AccountMeta::new_readonly(program_id, false),
+                        }
+                    }
+                }
+                _ => quote! { #name },
+            };
+            quote! {
+                AccountMeta {
+                    pubkey: #pubkey,
+                    is_signer: #is_signer,
+                    is_writable: #is_writable,
+                }
+            }
+        });
+        quote! {
+            Command::#name { #(#fields),* } => {
+                let mut data = #discriminator.to_vec();
+                #(Encode::encode(&#args, &mut data);)*
+                Instruction {
+                    program_id,
+                    accounts: vec![#(#metas),*],
+                    data,
+                }
+            }
+        }
+    }
+}
+
+fn flatten<'a>(
+    prefix: Option<&str>,
+    items: &'a [IdlAccountItem],
+    accounts: &mut Vec<(Ident, &'a IdlAccount)>,
+) {
+    for item in items {
+        match item {
+            IdlAccountItem::IdlAccount(acc) => {
+                let name = match prefix {
+                    None => acc.name.to_snake_case(),
+                    Some(prefix) => format!("{}_{}", prefix, acc.name.to_snake_case()),
+                };
+                accounts.push((ident(&name), acc));
+            }
+            IdlAccountItem::IdlAccounts(accs) => {
+                let name = match prefix {
+                    None => accs.name.to_snake_case(),
+                    Some(prefix) => format!("{}_{}", prefix, accs.name.to_snake_case()),
+                };
+                flatten(Some(&name), &accs.accounts, accounts)
+            }
+        }
+    }
+}
+
+// The type of the flag of an argument, parsed by clap, if it can be given as
+// one.
+fn flag_type(ty: &IdlType) -> Option<TokenStream> {
+    match ty {
+        IdlType::Option(ty) => scalar_type(ty).map(|ty| quote! { Option<#ty> }),
+        IdlType::Vec(ty) => scalar_type(ty).map(|ty| quote! { Vec<#ty> }),
+        ty => scalar_type(ty),
+    }
+}
+
+fn scalar_type(ty: &IdlType) -> Option<TokenStream> {
+    Some(match ty {
+        IdlType::Bool => quote! { bool },
+        IdlType::U8 => quote! { u8 },
+        IdlType::I8 => quote! { i8 },
+        IdlType::U16 => quote! { u16 },
+        IdlType::I16 => quote! { i16 },
+        IdlType::U32 => quote! { u32 },
+        IdlType::I32 => quote! { i32 },
+        IdlType::F32 => quote! { f32 },
+        IdlType::U64 => quote! { u64 },
+        IdlType::I64 => quote! { i64 },
+        IdlType::F64 => quote! { f64 },
+        IdlType::U128 => quote! { u128 },
+        IdlType::I128 => quote! { i128 },
+        IdlType::Float(float) => match float.bits {
+            32 => quote! { f32 },
+            _ => quote! { f64 },
+        },
+        IdlType::Bytes => quote! { Bytes },
+        IdlType::String => quote! { String },
+        IdlType::PublicKey => quote! { Pubkey },
+        IdlType::Defined(_) | IdlType::Option(_) | IdlType::Vec(_) | IdlType::Array(..) => {
+            return None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_cli() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "instructions": [
+                {
+                    "name": "setFee",
+                    "docs": ["Sets the fee of the vault."],
+                    "accounts": [
+                        { "name": "vault", "isMut": true, "isSigner": false },
+                        {
+                            "name": "auth",
+                            "accounts": [
+                                { "name": "admin", "isMut": false, "isSigner": true }
+                            ]
+                        },
+                        {
+                            "name": "systemProgram",
+                            "isMut": false,
+                            "isSigner": false,
+                            "address": "11111111111111111111111111111111"
+                        }
+                    ],
+                    "args": [
+                        { "name": "feeBps", "type": "u16" },
+                        { "name": "memo", "type": { "option": "string" } }
+                    ],
+                    "admin": true
+                },
+                {
+                    "name": "deposit",
+                    "accounts": [],
+                    "args": [{ "name": "amount", "type": "u64" }]
+                }
+            ],
+            "metadata": { "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS" }
+        }))
+        .unwrap();
+        let cli = generate(&idl, None).unwrap();
+        let main_rs = syn::parse_file(&cli.main_rs).unwrap();
+        // Compares the code without whitespace, which the printing of tokens
+        // doesn't keep.
+        let squash = |code: String| code.split_whitespace().collect::<String>();
+        let main_rs = squash(quote! { #main_rs }.to_string());
+        let has = |tokens: TokenStream| main_rs.contains(&squash(tokens.to_string()));
+
+        assert!(cli.cargo_toml.contains("name = \"vault-admin\""));
+        assert!(has(quote! {
+            #[doc = " Sets the fee of the vault."]
+            SetFee {
+                #[clap(long)]
+                fee_bps: u16,
+                #[clap(long)]
+                memo: Option<String>,
+                #[doc = " Account `vault`."]
+                #[clap(long)]
+                vault: Pubkey,
+                #[doc = " Account `admin`, the signer of the transaction by default."]
+                #[clap(long)]
+                auth_admin: Option<Pubkey>,
+                #[doc = " Account `systemProgram`, its known address by default."]
+                #[clap(long)]
+                system_program: Option<Pubkey>
+            }
+        }));
+        assert!(has(quote! { pubkey: auth_admin.unwrap_or(signer), }));
+        assert!(!main_rs.contains("Deposit"));
+
+        let mut unsupported = idl.clone();
+        unsupported.instructions[0].args[0].ty = IdlType::Defined("Fees".to_string());
+        assert!(generate(&unsupported, None).is_err());
+        let mut no_admin = idl;
+        no_admin.instructions[0].admin = false;
+        assert!(generate(&no_admin, None).is_err());
+    }
+}
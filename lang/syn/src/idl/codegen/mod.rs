@@ -0,0 +1,7 @@
+// Client code generated from an IDL.
+
+pub mod admin_cli;
+pub mod python;
+pub mod rust_cpi;
+pub mod template;
+pub mod typescript;
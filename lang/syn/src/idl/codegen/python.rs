@@ -0,0 +1,831 @@
+use crate::codegen::program::common::sighash;
+use crate::idl::mangle::Target;
+use crate::idl::*;
+use anyhow::Result;
+use heck::{CamelCase, ShoutySnakeCase, SnakeCase};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+// Python bindings of a program, generated from its IDL, for use with anchorpy.
+//
+// The generated module has the `borsh_construct` layouts of the user defined
+// types, accounts, events and instruction arguments, along with a decoder per
+// account and one for the events, a builder of a `solders` `Instruction` per
+// instruction, and a helper per PDA with seeds in the IDL, finding its address
+// from the instruction arguments and accounts it's derived from.
+
+// The module of the program deployed at `program_id`, defaulting to the
+// address in the IDL's metadata. Without either, the builders and PDA helpers
+// take the program id as an argument.
+pub fn generate(idl: &Idl, program_id: Option<&str>) -> Result<String> {
+    let program_id = program_id.map(ToString::to_string).or_else(|| {
+        idl.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("address"))
+            .and_then(|address| address.as_str())
+            .map(ToString::to_string)
+    });
+    let program_id_arg = match program_id {
+        Some(_) => "program_id: Pubkey = PROGRAM_ID",
+        None => "program_id: Pubkey",
+    };
+
+    let mut py = String::new();
+    writeln!(
+        py,
+        r#""""Client of the `{}` program, generated from its IDL."""
+import typing{}
+
+import borsh_construct as borsh
+from anchorpy.borsh_extension import BorshPubkey
+from solders.instruction import AccountMeta, Instruction
+from solders.pubkey import Pubkey"#,
+        idl.name,
+        // Uses of instructions scheduled for removal with `#[sunset(..)]`
+        // raise a `DeprecationWarning`.
+        match idl.instructions.iter().any(|ix| ix.sunset.is_some()) {
+            true => "\nimport warnings",
+            false => "",
+        }
+    )?;
+    if let Some(program_id) = &program_id {
+        writeln!(py)?;
+        writeln!(py, r#"PROGRAM_ID = Pubkey.from_string("{}")"#, program_id)?;
+    }
+    if idl
+        .instructions
+        .iter()
+        .any(|ix| ix.compute_budget.is_some())
+    {
+        writeln!(py)?;
+        writeln!(
+            py,
+            r#"COMPUTE_BUDGET_PROGRAM_ID = Pubkey.from_string("ComputeBudget111111111111111111111111111111")"#
+        )?;
+    }
+
+    // The layouts of the types and accounts, each after the ones it refers to.
+    let ty_defs = idl
+        .types
+        .iter()
+        .chain(&idl.accounts)
+        .map(|ty_def| (ty_def.name.as_str(), ty_def))
+        .collect::<HashMap<_, _>>();
+    let mut written = BTreeSet::new();
+    for ty_def in idl.types.iter().chain(&idl.accounts) {
+        type_definition(&mut py, ty_def, &ty_defs, &mut written)?;
+    }
+
+    for acc in &idl.accounts {
+        let name = acc.name.to_shouty_snake_case();
+        let discriminator = acc.account_discriminator();
+        writeln!(py)?;
+        writeln!(py)?;
+        writeln!(py, "{}_DISCRIMINATOR = {}", name, bytes(&discriminator))?;
+        writeln!(py)?;
+        writeln!(py)?;
+        writeln!(
+            py,
+            "def decode_{}(data: bytes) -> typing.Any:",
+            acc.name.to_snake_case()
+        )?;
+        writeln!(
+            py,
+            r#"    """Decodes the data of a `{}` account.""""#,
+            acc.name
+        )?;
+        writeln!(
+            py,
+            "    if data[:{}] != {}_DISCRIMINATOR:",
+            discriminator.len(),
+            name
+        )?;
+        writeln!(
+            py,
+            r#"        raise ValueError("Invalid discriminator of a {} account")"#,
+            acc.name
+        )?;
+        writeln!(
+            py,
+            "    return {}_LAYOUT.parse(data[{}:])",
+            name,
+            discriminator.len()
+        )?;
+    }
+
+    if let Some(events) = idl.events.as_ref().filter(|events| !events.is_empty()) {
+        for event in events {
+            let name = event.name.to_shouty_snake_case();
+            writeln!(py)?;
+            writeln!(py)?;
+            writeln!(
+                py,
+                "{}_DISCRIMINATOR = {}",
+                name,
+                bytes(&sighash("event", &event.name))
+            )?;
+            let fields = event
+                .fields
+                .iter()
+                .map(|f| (f.name.as_str(), &f.ty))
+                .collect::<Vec<_>>();
+            writeln!(py, "{}_LAYOUT = {}", name, cstruct(&fields, 0))?;
+        }
+        writeln!(py)?;
+        writeln!(py, "EVENTS = {{")?;
+        for event in events {
+            let name = event.name.to_shouty_snake_case();
+            writeln!(
+                py,
+                r#"    {}_DISCRIMINATOR: ("{}", {}_LAYOUT),"#,
+                name, event.name, name
+            )?;
+        }
+        writeln!(py, "}}")?;
+        writeln!(py)?;
+        writeln!(py)?;
+        writeln!(
+            py,
+            "def decode_event(data: bytes) -> typing.Optional[typing.Tuple[str, typing.Any]]:"
+        )?;
+        writeln!(
+            py,
+            r#"    """Decodes the data of an event, i.e. the base64 decoded `Program data: `
+    log of `emit!`, as its name and value. Returns `None` for other data."""
+    event = EVENTS.get(data[:8])
+    if event is None:
+        return None
+    name, layout = event
+    return name, layout.parse(data[8:])"#
+        )?;
+    }
+
+    if let Some(errors) = idl.errors.as_ref().filter(|errors| !errors.is_empty()) {
+        writeln!(py)?;
+        writeln!(py)?;
+        writeln!(py, "ERRORS = {{")?;
+        for error in errors {
+            let msg = error.msg.as_ref().unwrap_or(&error.name);
+            writeln!(
+                py,
+                "    {}: ({}, {}),",
+                error.code,
+                serde_json::to_string(&error.name)?,
+                serde_json::to_string(msg)?
+            )?;
+        }
+        writeln!(py, "}}")?;
+    }
+
+    for ix in &idl.instructions {
+        instruction(&mut py, ix, program_id_arg)?;
+    }
+    for ix in &idl.instructions {
+        pdas(&mut py, ix, &ix.accounts, "", program_id_arg)?;
+    }
+    Ok(py)
+}
+
+fn type_definition<'a>(
+    py: &mut String,
+    ty_def: &'a IdlTypeDefinition,
+    ty_defs: &HashMap<&str, &'a IdlTypeDefinition>,
+    written: &mut BTreeSet<&'a str>,
+) -> Result<()> {
+    if !written.insert(&ty_def.name) {
+        return Ok(());
+    }
+    let mut dependencies = BTreeSet::new();
+    match &ty_def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            for field in fields {
+                defined(&field.ty, &mut dependencies);
+            }
+        }
+        IdlTypeDefinitionTy::Enum { variants } => {
+            for variant in variants {
+                match &variant.fields {
+                    None => {}
+                    Some(EnumFields::Named(fields)) => {
+                        for field in fields {
+                            defined(&field.ty, &mut dependencies);
+                        }
+                    }
+                    Some(EnumFields::Tuple(tys)) => {
+                        for ty in tys {
+                            defined(ty, &mut dependencies);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    for dependency in dependencies {
+        if let Some(ty_def) = ty_defs.get(dependency.as_str()) {
+            type_definition(py, ty_def, ty_defs, written)?;
+        }
+    }
+
+    let layout = match &ty_def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            let fields = fields
+                .iter()
+                .map(|f| (f.name.as_str(), &f.ty))
+                .collect::<Vec<_>>();
+            cstruct(&fields, 0)
+        }
+        IdlTypeDefinitionTy::Enum { variants } => {
+            let mut layout = "borsh.Enum(\n".to_string();
+            for variant in variants {
+                let name = serde_json::to_string(&variant.name)?;
+                match &variant.fields {
+                    None => writeln!(layout, "    {},", name)?,
+                    Some(EnumFields::Named(fields)) => {
+                        let fields = fields
+                            .iter()
+                            .map(|f| (f.name.as_str(), &f.ty))
+                            .collect::<Vec<_>>();
+                        writeln!(layout, "    {} / {},", name, cstruct(&fields, 1))?
+                    }
+                    Some(EnumFields::Tuple(tys)) => writeln!(
+                        layout,
+                        "    {} / borsh.TupleStruct({}),",
+                        name,
+                        tys.iter().map(construct).collect::<Vec<_>>().join(", ")
+                    )?,
+                }
+            }
+            writeln!(layout, r#"    enum_name="{}","#, ty_def.name)?;
+            layout.push(')');
+            layout
+        }
+    };
+    writeln!(py)?;
+    writeln!(
+        py,
+        "{}_LAYOUT = {}",
+        ty_def.name.to_shouty_snake_case(),
+        layout
+    )?;
+    Ok(())
+}
+
+// The names of the user defined types `ty` refers to.
+fn defined(ty: &IdlType, names: &mut BTreeSet<String>) {
+    match ty {
+        IdlType::Defined(name) => {
+            names.insert(name.clone());
+        }
+        IdlType::Option(ty) | IdlType::Vec(ty) | IdlType::Array(ty, _) => defined(ty, names),
+        _ => {}
+    }
+}
+
+fn instruction(py: &mut String, ix: &IdlInstruction, program_id_arg: &str) -> Result<()> {
+    let name = ix.name.to_camel_case();
+    let layout = ix.name.to_shouty_snake_case();
+    writeln!(py)?;
+    writeln!(py)?;
+    writeln!(
+        py,
+        "{}_DISCRIMINATOR = {}",
+        layout,
+        bytes(&ix.discriminator())
+    )?;
+    // The instructions of the compute budget program to send ahead of the
+    // instruction, `RequestHeapFrame` and `SetComputeUnitLimit`.
+    if let Some(budget) = &ix.compute_budget {
+        writeln!(py, "{}_COMPUTE_BUDGET = [", layout)?;
+        for (tag, value) in [(1u8, budget.heap), (2u8, budget.units)] {
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+            let mut data = vec![tag];
+            data.extend_from_slice(&value.to_le_bytes());
+            writeln!(
+                py,
+                "    Instruction(COMPUTE_BUDGET_PROGRAM_ID, {}, []),",
+                bytes(&data)
+            )?;
+        }
+        writeln!(py, "]")?;
+    }
+    if !ix.args.is_empty() {
+        let fields = ix
+            .args
+            .iter()
+            .map(|f| (f.name.as_str(), &f.ty))
+            .collect::<Vec<_>>();
+        writeln!(py, "{}_ARGS_LAYOUT = {}", layout, cstruct(&fields, 0))?;
+        writeln!(py)?;
+        writeln!(py)?;
+        writeln!(py, "class {}Args(typing.TypedDict):", name)?;
+        for arg in &ix.args {
+            writeln!(py, "    {}: {}", identifier(&arg.name), py_type(&arg.ty))?;
+        }
+    }
+    accounts_typed_dict(py, &format!("{}Accounts", name), &ix.accounts)?;
+
+    writeln!(py)?;
+    writeln!(py)?;
+    writeln!(py, "def {}(", identifier(&ix.name))?;
+    if !ix.args.is_empty() {
+        writeln!(py, "    args: {}Args,", name)?;
+    }
+    writeln!(py, "    accounts: {}Accounts,", name)?;
+    writeln!(py, "    {},", program_id_arg)?;
+    writeln!(
+        py,
+        "    remaining_accounts: typing.Optional[typing.List[AccountMeta]] = None,"
+    )?;
+    writeln!(py, ") -> Instruction:")?;
+    if let Some(docs) = ix.docs.as_ref().filter(|docs| !docs.is_empty()) {
+        writeln!(py, r#"    """{}""""#, docs.join("\n    "))?;
+    }
+    if let Some(sunset) = &ix.sunset {
+        writeln!(
+            py,
+            r#"    warnings.warn("`{}` fails from {} on", DeprecationWarning, stacklevel=2)"#,
+            identifier(&ix.name),
+            sunset
+        )?;
+    }
+    writeln!(py, "    keys: typing.List[AccountMeta] = [")?;
+    account_metas(py, &ix.accounts, "accounts")?;
+    writeln!(py, "    ]")?;
+    writeln!(py, "    if remaining_accounts is not None:")?;
+    writeln!(py, "        keys += remaining_accounts")?;
+    // Arguments may be given by their former names, from `#[idl_name]`.
+    let aliased = ix.args.iter().filter(|arg| !arg.aliases.is_empty());
+    for (i, arg) in aliased.enumerate() {
+        if i == 0 {
+            writeln!(py, "    args = typing.cast({}Args, dict(args))", name)?;
+        }
+        let aliases = arg
+            .aliases
+            .iter()
+            .map(|alias| format!(r#""{}""#, identifier(alias)))
+            .collect::<Vec<_>>();
+        writeln!(py, "    for alias in [{}]:", aliases.join(", "))?;
+        writeln!(
+            py,
+            r#"        if "{}" not in args and alias in args:"#,
+            identifier(&arg.name)
+        )?;
+        writeln!(
+            py,
+            r#"            args["{}"] = args.pop(alias)  # type: ignore"#,
+            identifier(&arg.name)
+        )?;
+    }
+    match ix.args.is_empty() {
+        true => writeln!(py, "    data = {}_DISCRIMINATOR", layout)?,
+        false => writeln!(
+            py,
+            "    data = {}_DISCRIMINATOR + {}_ARGS_LAYOUT.build(args)",
+            layout, layout
+        )?,
+    }
+    writeln!(py, "    return Instruction(program_id, data, keys)")?;
+    Ok(())
+}
+
+// The `TypedDict` of the accounts, preceded by the ones of its composite
+// accounts.
+fn accounts_typed_dict(py: &mut String, name: &str, accounts: &[IdlAccountItem]) -> Result<()> {
+    let mut fields = Vec::new();
+    for item in accounts {
+        match item {
+            IdlAccountItem::IdlAccount(acc) => {
+                let ty = match acc.is_optional {
+                    Some(true) => "typing.Optional[Pubkey]".to_string(),
+                    _ => "Pubkey".to_string(),
+                };
+                fields.push((identifier(&acc.name), ty));
+            }
+            IdlAccountItem::IdlAccounts(accs) => {
+                let nested = format!(
+                    "{}{}Accounts",
+                    name.trim_end_matches("Accounts"),
+                    accs.name.to_camel_case()
+                );
+                accounts_typed_dict(py, &nested, &accs.accounts)?;
+                fields.push((identifier(&accs.name), nested));
+            }
+        }
+    }
+    writeln!(py)?;
+    writeln!(py)?;
+    writeln!(py, "class {}(typing.TypedDict):", name)?;
+    if fields.is_empty() {
+        writeln!(py, "    pass")?;
+    }
+    for (field, ty) in fields {
+        writeln!(py, "    {}: {}", field, ty)?;
+    }
+    Ok(())
+}
+
+// The metas of the accounts, read from the dict `path`. Optional accounts that
+// aren't given are the program id.
+fn account_metas(py: &mut String, accounts: &[IdlAccountItem], path: &str) -> Result<()> {
+    let py_bool = |b: bool| if b { "True" } else { "False" };
+    for item in accounts {
+        match item {
+            IdlAccountItem::IdlAccount(acc) => {
+                let key = format!(r#"{}["{}"]"#, path, identifier(&acc.name));
+                match acc.is_optional {
+                    Some(true) => writeln!(
+                        py,
+                        "        AccountMeta(pubkey={}, is_signer={}, is_writable={})\n        if {} is not None\n        else AccountMeta(pubkey=program_id, is_signer=False, is_writable=False),",
+                        key,
+                        py_bool(acc.is_signer),
+                        py_bool(acc.is_mut),
+                        key
+                    )?,
+                    _ => writeln!(
+                        py,
+                        "        AccountMeta(pubkey={}, is_signer={}, is_writable={}),",
+                        key,
+                        py_bool(acc.is_signer),
+                        py_bool(acc.is_mut)
+                    )?,
+                }
+            }
+            IdlAccountItem::IdlAccounts(accs) => {
+                let path = format!(r#"{}["{}"]"#, path, identifier(&accs.name));
+                account_metas(py, &accs.accounts, &path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// The helpers finding the address of the PDAs among the accounts of `ix`,
+// e.g. `find_deposit_vault_address`.
+fn pdas(
+    py: &mut String,
+    ix: &IdlInstruction,
+    accounts: &[IdlAccountItem],
+    prefix: &str,
+    program_id_arg: &str,
+) -> Result<()> {
+    for item in accounts {
+        let acc = match item {
+            IdlAccountItem::IdlAccount(acc) => acc,
+            IdlAccountItem::IdlAccounts(accs) => {
+                let prefix = format!("{}{}_", prefix, accs.name.to_snake_case());
+                pdas(py, ix, &accs.accounts, &prefix, program_id_arg)?;
+                continue;
+            }
+        };
+        let pda = match &acc.pda {
+            Some(pda) => pda,
+            None => continue,
+        };
+        let mut params = Vec::new();
+        let seeds = pda
+            .seeds
+            .iter()
+            .map(|seed| seed_bytes(seed, &mut params))
+            .collect::<Option<Vec<_>>>();
+        let program_id = match &pda.program_id {
+            None => Some("program_id".to_string()),
+            Some(seed) => {
+                seed_bytes(seed, &mut params).map(|bytes| format!("Pubkey.from_bytes({})", bytes))
+            }
+        };
+        // Seeds of types without a known encoding get no helper.
+        let (seeds, program_id) = match (seeds, program_id) {
+            (Some(seeds), Some(program_id)) => (seeds, program_id),
+            _ => continue,
+        };
+        let mut args = params
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect::<Vec<_>>();
+        if pda.program_id.is_none() {
+            args.push(program_id_arg.to_string());
+        }
+        writeln!(py)?;
+        writeln!(py)?;
+        writeln!(
+            py,
+            "def find_{}_{}{}_address({}) -> typing.Tuple[Pubkey, int]:",
+            ix.name.to_snake_case(),
+            prefix,
+            acc.name.to_snake_case(),
+            args.join(", ")
+        )?;
+        writeln!(
+            py,
+            r#"    """Finds the address of the `{}` account of `{}`, and its bump.""""#,
+            acc.name, ix.name
+        )?;
+        writeln!(py, "    seeds = [{}]", seeds.join(", "))?;
+        writeln!(
+            py,
+            "    return Pubkey.find_program_address(seeds, {})",
+            program_id
+        )?;
+    }
+    Ok(())
+}
+
+// The Python expression of the bytes of a seed, adding the parameters it's
+// derived from to `params`, if its type has a known encoding.
+fn seed_bytes(seed: &IdlSeed, params: &mut Vec<(String, String)>) -> Option<String> {
+    let (path, ty) = match seed {
+        IdlSeed::Const(seed) => return const_bytes(&seed.value, &seed.ty),
+        IdlSeed::Arg(seed) => (&seed.path, &seed.ty),
+        IdlSeed::Account(seed) => (&seed.path, &seed.ty),
+    };
+    let name = identifier(&path.replace('.', "_"));
+    let bytes = value_bytes(&name, ty)?;
+    if !params.iter().any(|(param, _)| param == &name) {
+        params.push((name, py_type(ty)));
+    }
+    Some(bytes)
+}
+
+fn const_bytes(value: &serde_json::Value, ty: &IdlType) -> Option<String> {
+    let value = match (ty, value) {
+        (IdlType::String, serde_json::Value::String(_)) => {
+            return Some(format!("{}.encode()", value))
+        }
+        (IdlType::PublicKey, serde_json::Value::String(key)) => {
+            return Some(format!(r#"bytes(Pubkey.from_string("{}"))"#, key))
+        }
+        (_, serde_json::Value::String(value)) => value.clone(),
+        (_, value) => value.to_string(),
+    };
+    value_bytes(&value, ty)
+}
+
+// The Python expression of the bytes of `value`, if the encoding of `ty` is
+// known.
+fn value_bytes(value: &str, ty: &IdlType) -> Option<String> {
+    let int = |size: usize, signed: bool| {
+        let signed = if signed { "True" } else { "False" };
+        format!(
+            r#"({}).to_bytes({}, "little", signed={})"#,
+            value, size, signed
+        )
+    };
+    let bytes = match ty {
+        IdlType::Bool => format!("bytes([int({})])", value),
+        IdlType::U8 => int(1, false),
+        IdlType::I8 => int(1, true),
+        IdlType::U16 => int(2, false),
+        IdlType::I16 => int(2, true),
+        IdlType::U32 => int(4, false),
+        IdlType::I32 => int(4, true),
+        IdlType::U64 => int(8, false),
+        IdlType::I64 => int(8, true),
+        IdlType::U128 => int(16, false),
+        IdlType::I128 => int(16, true),
+        IdlType::String => format!("{}.encode()", value),
+        IdlType::PublicKey | IdlType::Bytes => format!("bytes({})", value),
+        IdlType::Array(ty, _) if **ty == IdlType::U8 => format!("bytes({})", value),
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+// A `borsh.CStruct` of the fields, at the given level of indentation.
+fn cstruct(fields: &[(&str, &IdlType)], indent: usize) -> String {
+    if fields.is_empty() {
+        return "borsh.CStruct()".to_string();
+    }
+    if indent > 0 {
+        let fields = fields
+            .iter()
+            .map(|(name, ty)| format!(r#""{}" / {}"#, identifier(name), construct(ty)))
+            .collect::<Vec<_>>();
+        return format!("borsh.CStruct({})", fields.join(", "));
+    }
+    let mut layout = "borsh.CStruct(\n".to_string();
+    for (name, ty) in fields {
+        writeln!(layout, r#"    "{}" / {},"#, identifier(name), construct(ty)).unwrap();
+    }
+    layout.push(')');
+    layout
+}
+
+// The `borsh_construct` layout of `ty`.
+fn construct(ty: &IdlType) -> String {
+    match ty {
+        IdlType::Bool => "borsh.Bool".to_string(),
+        IdlType::U8 => "borsh.U8".to_string(),
+        IdlType::I8 => "borsh.I8".to_string(),
+        IdlType::U16 => "borsh.U16".to_string(),
+        IdlType::I16 => "borsh.I16".to_string(),
+        IdlType::U32 => "borsh.U32".to_string(),
+        IdlType::I32 => "borsh.I32".to_string(),
+        IdlType::F32 => "borsh.F32".to_string(),
+        IdlType::U64 => "borsh.U64".to_string(),
+        IdlType::I64 => "borsh.I64".to_string(),
+        IdlType::F64 => "borsh.F64".to_string(),
+        IdlType::U128 => "borsh.U128".to_string(),
+        IdlType::I128 => "borsh.I128".to_string(),
+        IdlType::Float(float) => match float.bits {
+            32 => "borsh.F32".to_string(),
+            _ => "borsh.F64".to_string(),
+        },
+        IdlType::Bytes => "borsh.Bytes".to_string(),
+        IdlType::String => "borsh.String".to_string(),
+        IdlType::PublicKey => "BorshPubkey".to_string(),
+        IdlType::Defined(name) => format!("{}_LAYOUT", name.to_shouty_snake_case()),
+        IdlType::Option(ty) => format!("borsh.Option({})", construct(ty)),
+        IdlType::Vec(ty) => format!("borsh.Vec({})", construct(ty)),
+        IdlType::Array(ty, len) => format!("{}[{}]", construct(ty), len),
+    }
+}
+
+// The type hint of decoded values of `ty`.
+fn py_type(ty: &IdlType) -> String {
+    match ty {
+        IdlType::Bool => "bool".to_string(),
+        IdlType::U8
+        | IdlType::I8
+        | IdlType::U16
+        | IdlType::I16
+        | IdlType::U32
+        | IdlType::I32
+        | IdlType::U64
+        | IdlType::I64
+        | IdlType::U128
+        | IdlType::I128 => "int".to_string(),
+        IdlType::F32 | IdlType::F64 | IdlType::Float(_) => "float".to_string(),
+        IdlType::Bytes => "bytes".to_string(),
+        IdlType::String => "str".to_string(),
+        IdlType::PublicKey => "Pubkey".to_string(),
+        IdlType::Defined(_) => "typing.Any".to_string(),
+        IdlType::Option(ty) => format!("typing.Optional[{}]", py_type(ty)),
+        IdlType::Vec(ty) | IdlType::Array(ty, _) => format!("typing.List[{}]", py_type(ty)),
+    }
+}
+
+fn bytes(bytes: &[u8]) -> String {
+    format!("bytes({:?})", bytes)
+}
+
+// The snake case identifier of `name`, e.g. `from_` for `from`.
+fn identifier(name: &str) -> String {
+    Target::Python.mangle(&name.to_snake_case(), &BTreeMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn python() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "instructions": [{
+                "name": "deposit",
+                "docs": ["Deposits into the vault."],
+                "accounts": [
+                    {
+                        "name": "vault",
+                        "isMut": true,
+                        "isSigner": false,
+                        "pda": {
+                            "seeds": [
+                                { "kind": "const", "type": "string", "value": "vault" },
+                                { "kind": "account", "type": "publicKey", "path": "auth.authority" },
+                                { "kind": "arg", "type": "u64", "path": "from" }
+                            ]
+                        }
+                    },
+                    {
+                        "name": "auth",
+                        "accounts": [
+                            { "name": "authority", "isMut": false, "isSigner": true },
+                            { "name": "delegate", "isMut": false, "isSigner": true, "isOptional": true }
+                        ]
+                    }
+                ],
+                "args": [
+                    { "name": "from", "type": "u64" },
+                    { "name": "kind", "type": { "defined": "Kind" } }
+                ]
+            }],
+            "accounts": [{
+                "name": "Vault",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "balance", "type": "u64" },
+                        { "name": "kind", "type": { "defined": "Kind" } }
+                    ]
+                }
+            }],
+            "types": [{
+                "name": "Kind",
+                "type": {
+                    "kind": "enum",
+                    "variants": [
+                        { "name": "Spot" },
+                        { "name": "Locked", "fields": [{ "name": "until", "type": "i64" }] }
+                    ]
+                }
+            }],
+            "events": [{
+                "name": "Deposited",
+                "fields": [{ "name": "amount", "type": "u64", "index": false }]
+            }],
+            "errors": [{ "code": 6000, "name": "Paused", "msg": "The vault is paused" }],
+            "metadata": { "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS" }
+        }))
+        .unwrap();
+        let py = generate(&idl, None).unwrap();
+
+        assert!(py.contains(
+            r#"PROGRAM_ID = Pubkey.from_string("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS")"#
+        ));
+        assert!(py.contains(
+            r#"KIND_LAYOUT = borsh.Enum(
+    "Spot",
+    "Locked" / borsh.CStruct("until" / borsh.I64),
+    enum_name="Kind",
+)
+
+VAULT_LAYOUT = borsh.CStruct(
+    "balance" / borsh.U64,
+    "kind" / KIND_LAYOUT,
+)"#
+        ));
+        assert!(py.contains(
+            r#"def decode_vault(data: bytes) -> typing.Any:
+    """Decodes the data of a `Vault` account."""
+    if data[:8] != VAULT_DISCRIMINATOR:"#
+        ));
+        assert!(py.contains(r#"6000: ("Paused", "The vault is paused"),"#));
+        assert!(py.contains(
+            r#"class DepositAuthAccounts(typing.TypedDict):
+    authority: Pubkey
+    delegate: typing.Optional[Pubkey]
+
+
+class DepositAccounts(typing.TypedDict):
+    vault: Pubkey
+    auth: DepositAuthAccounts
+
+
+def deposit(
+    args: DepositArgs,
+    accounts: DepositAccounts,
+    program_id: Pubkey = PROGRAM_ID,
+    remaining_accounts: typing.Optional[typing.List[AccountMeta]] = None,
+) -> Instruction:
+    """Deposits into the vault."""
+    keys: typing.List[AccountMeta] = [
+        AccountMeta(pubkey=accounts["vault"], is_signer=False, is_writable=True),
+        AccountMeta(pubkey=accounts["auth"]["authority"], is_signer=True, is_writable=False),
+        AccountMeta(pubkey=accounts["auth"]["delegate"], is_signer=True, is_writable=False)
+        if accounts["auth"]["delegate"] is not None
+        else AccountMeta(pubkey=program_id, is_signer=False, is_writable=False),
+    ]"#
+        ));
+        assert!(py.contains("    from_: int\n"));
+        assert!(py.contains(
+            r#"def find_deposit_vault_address(auth_authority: Pubkey, from_: int, program_id: Pubkey = PROGRAM_ID) -> typing.Tuple[Pubkey, int]:
+    """Finds the address of the `vault` account of `deposit`, and its bump."""
+    seeds = ["vault".encode(), bytes(auth_authority), (from_).to_bytes(8, "little", signed=False)]
+    return Pubkey.find_program_address(seeds, program_id)"#
+        ));
+    }
+
+    #[test]
+    fn aliased_args() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "instructions": [{
+                "name": "setFee",
+                "accounts": [],
+                "args": [{ "name": "feeBps", "type": "u16", "aliases": ["fee", "feeRate"] }],
+                "computeBudget": { "units": 400000 }
+            }]
+        }))
+        .unwrap();
+        let py = generate(&idl, None).unwrap();
+
+        assert!(py.contains(
+            r#"    args = typing.cast(SetFeeArgs, dict(args))
+    for alias in ["fee", "fee_rate"]:
+        if "fee_bps" not in args and alias in args:
+            args["fee_bps"] = args.pop(alias)  # type: ignore
+    data = SET_FEE_DISCRIMINATOR + SET_FEE_ARGS_LAYOUT.build(args)"#
+        ));
+        assert!(py.contains(
+            r#"SET_FEE_COMPUTE_BUDGET = [
+    Instruction(COMPUTE_BUDGET_PROGRAM_ID, bytes([2, 128, 26, 6, 0]), []),
+]"#
+        ));
+    }
+}
@@ -0,0 +1,862 @@
+use crate::codegen::program::common::sighash;
+use crate::idl::*;
+use anyhow::{anyhow, Result};
+use heck::{CamelCase, KebabCase, SnakeCase};
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use quote::{format_ident, quote};
+use std::path::Path;
+
+// A crate for programs to CPI into a program with, generated from its IDL,
+// i.e. without depending on the program's crate.
+//
+// The generated crate mirrors the `cpi` feature of the program's crate: the
+// constants, user defined types, accounts and events are at its root, the instruction
+// data in `instruction`, and there's a function per instruction in `cpi`,
+// taking a `CpiContext` of its struct of `AccountInfo`s in `cpi::accounts`.
+// Composite accounts are nested structs, named after the instruction and the
+// field, e.g. `DepositAuth`. Errors are an `ErrorCode` with the program's
+// codes, and the events logged by the program are decoded with
+// `events::decode`. Instructions of the state aren't generated.
+//
+// The same items make up the module declared by `declare_program!`, for
+// programs to depend on the program at compile time.
+
+pub struct CpiCrate {
+    pub cargo_toml: String,
+    pub lib_rs: String,
+}
+
+impl CpiCrate {
+    // Writes the manifest and `src/lib.rs` of the crate to `dir`.
+    pub fn write(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir.join("src"))?;
+        std::fs::write(dir.join("Cargo.toml"), &self.cargo_toml)?;
+        std::fs::write(dir.join("src").join("lib.rs"), &self.lib_rs)?;
+        Ok(())
+    }
+}
+
+// The crate of the program deployed at `program_id`, defaulting to the address
+// in the IDL's metadata. The code of `lib_rs` isn't formatted.
+pub fn generate(idl: &Idl, program_id: Option<&str>) -> Result<CpiCrate> {
+    let doc = format!(
+        " CPI client of the `{}` program, generated from its IDL.",
+        idl.name
+    );
+    let items = module(idl, program_id, &quote! { crate })?;
+    let lib_rs = quote! {
+        #![doc = #doc]
+
+        #items
+    };
+
+    let cargo_toml = format!(
+        r#"[package]
+name = "{}-cpi"
+version = "{}"
+description = "CPI client of the {} program, generated from its IDL"
+edition = "2021"
+
+[dependencies]
+anchor-lang = "{}"
+"#,
+        idl.name.to_kebab_case(),
+        idl.version,
+        idl.name,
+        env!("CARGO_PKG_VERSION"),
+    );
+    Ok(CpiCrate {
+        cargo_toml,
+        lib_rs: lib_rs.to_string(),
+    })
+}
+
+// The items of the crate, at the path `root`, e.g. `crate` for the crate or
+// `crate::vault` for the module declared by `declare_program!`.
+pub fn module(idl: &Idl, program_id: Option<&str>, root: &TokenStream) -> Result<TokenStream> {
+    let program_id = match program_id {
+        Some(program_id) => program_id.to_string(),
+        None => idl
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("address"))
+            .and_then(|address| address.as_str())
+            .map(ToString::to_string)
+            .ok_or_else(|| anyhow!("The IDL has no address, the program id must be given"))?,
+    };
+    match bs58::decode(&program_id).into_vec() {
+        Ok(bytes) if bytes.len() == 32 => {}
+        _ => return Err(anyhow!("Invalid program id: {}", program_id)),
+    }
+    // The generated types implement `Discriminator`, of 8 bytes.
+    let discriminators = idl
+        .instructions
+        .iter()
+        .map(|ix| (&ix.name, ix.discriminator()))
+        .chain(
+            idl.accounts
+                .iter()
+                .map(|acc| (&acc.name, acc.account_discriminator())),
+        );
+    for (name, discriminator) in discriminators {
+        if discriminator.len() != 8 {
+            return Err(anyhow!(
+                "{} has a {} byte discriminator, only 8 byte discriminators are supported",
+                name,
+                discriminator.len()
+            ));
+        }
+    }
+
+    let constants = idl.constants.iter().filter_map(|c| constant(c, root));
+    let types = idl.types.iter().map(|ty_def| type_definition(ty_def, root));
+    let accounts = idl.accounts.iter().map(|acc| account(acc, root));
+    let events = events(idl, root);
+    let errors = errors(idl);
+    let ix_data = idl.instructions.iter().map(|ix| instruction_data(ix, root));
+    let cpi_fns = idl.instructions.iter().map(|ix| cpi_fn(ix, root));
+    let mut cpi_accounts = Vec::new();
+    for ix in &idl.instructions {
+        accounts_struct(
+            &ix.name.to_camel_case(),
+            &ix.docs,
+            &ix.accounts,
+            &mut cpi_accounts,
+            root,
+        );
+    }
+    Ok(quote! {
+        use anchor_lang::prelude::*;
+
+        declare_id!(#program_id);
+
+        #(#constants)*
+
+        #(#types)*
+
+        #(#accounts)*
+
+        #events
+
+        #errors
+
+        /// Data of the program's instructions.
+        pub mod instruction {
+            use super::*;
+
+            #(#ix_data)*
+        }
+
+        /// CPI into the program.
+        pub mod cpi {
+            use super::*;
+            use std::marker::PhantomData;
+
+            pub struct Return<T> {
+                phantom: PhantomData<T>,
+            }
+
+            impl<T: AnchorDeserialize> Return<T> {
+                pub fn get(&self) -> T {
+                    let (_key, data) = anchor_lang::solana_program::program::get_return_data().unwrap();
+                    T::try_from_slice(&data).unwrap()
+                }
+            }
+
+            #(#cpi_fns)*
+
+            /// Accounts of the program's instructions.
+            pub mod accounts {
+                use super::*;
+
+                #(#cpi_accounts)*
+            }
+        }
+    })
+}
+
+// The constant, if its value could be evaluated. Strings, bytes and vectors
+// are borrowed, e.g. `&str` for a `String`.
+fn constant(c: &IdlConst, root: &TokenStream) -> Option<TokenStream> {
+    let value = const_value(c.evaluated.as_ref()?)?;
+    let name = ident(&c.name);
+    let (ty, value) = match &c.ty {
+        IdlType::String => (quote! { &str }, value),
+        IdlType::Bytes => (quote! { &[u8] }, quote! { &#value }),
+        IdlType::Vec(ty) => {
+            let ty = rust_type(ty, root);
+            (quote! { &[#ty] }, quote! { &#value })
+        }
+        ty => (rust_type(ty, root), value),
+    };
+    Some(quote! {
+        pub const #name: #ty = #value;
+    })
+}
+
+fn const_value(value: &IdlConstValue) -> Option<TokenStream> {
+    Some(match value {
+        IdlConstValue::Bool(value) => quote! { #value },
+        IdlConstValue::Int(value) => value.parse().ok()?,
+        IdlConstValue::Float(value) => {
+            let value = Literal::f64_unsuffixed(*value);
+            quote! { #value }
+        }
+        IdlConstValue::String(value) => quote! { #value },
+        IdlConstValue::Bytes(bytes) => {
+            let bytes = bytes.iter().map(|byte| Literal::u8_unsuffixed(*byte));
+            quote! { [#(#bytes),*] }
+        }
+        IdlConstValue::PublicKey(key) => {
+            let bytes = bs58::decode(key).into_vec().ok()?;
+            let bytes = bytes.iter().map(|byte| Literal::u8_unsuffixed(*byte));
+            quote! { Pubkey::new_from_array([#(#bytes),*]) }
+        }
+        IdlConstValue::Array(values) => {
+            let values = values.iter().map(const_value).collect::<Option<Vec<_>>>()?;
+            quote! { [#(#values),*] }
+        }
+    })
+}
+
+fn type_definition(ty_def: &IdlTypeDefinition, root: &TokenStream) -> TokenStream {
+    let docs = docs(&ty_def.docs);
+    let name = ident(&ty_def.name);
+    let body = match &ty_def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            let fields = named_fields(fields, quote! { pub }, root);
+            quote! {
+                pub struct #name {
+                    #(#fields),*
+                }
+            }
+        }
+        IdlTypeDefinitionTy::Enum { variants } => {
+            let variants = variants.iter().map(|variant| {
+                let name = ident(&variant.name);
+                match &variant.fields {
+                    None => quote! { #name },
+                    Some(EnumFields::Named(fields)) => {
+                        let fields = named_fields(fields, quote! {}, root);
+                        quote! { #name { #(#fields),* } }
+                    }
+                    Some(EnumFields::Tuple(tys)) => {
+                        let tys = tys.iter().map(|ty| rust_type(ty, root));
+                        quote! { #name(#(#tys),*) }
+                    }
+                }
+            });
+            quote! {
+                pub enum #name {
+                    #(#variants),*
+                }
+            }
+        }
+    };
+    quote! {
+        #docs
+        #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+        #body
+    }
+}
+
+fn account(acc: &IdlTypeDefinition, root: &TokenStream) -> TokenStream {
+    let ty_def = type_definition(acc, root);
+    let name = ident(&acc.name);
+    let name_str = &acc.name;
+    let discriminator = discriminator(&acc.account_discriminator());
+    quote! {
+        #ty_def
+
+        impl anchor_lang::Discriminator for #name {
+            fn discriminator() -> [u8; 8] {
+                #discriminator
+            }
+        }
+
+        impl anchor_lang::Owner for #name {
+            fn owner() -> Pubkey {
+                #root::ID
+            }
+        }
+
+        impl anchor_lang::AccountSerialize for #name {
+            fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {
+                if writer.write_all(&#discriminator).is_err() {
+                    return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+                }
+                if AnchorSerialize::serialize(self, writer).is_err() {
+                    return Err(anchor_lang::error::ErrorCode::AccountDidNotSerialize.into());
+                }
+                Ok(())
+            }
+        }
+
+        impl anchor_lang::AccountDeserialize for #name {
+            fn try_deserialize(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+                if buf.len() < #discriminator.len() {
+                    return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorNotFound.into());
+                }
+                let given_disc = &buf[..8];
+                if #discriminator != given_disc {
+                    return Err(anchor_lang::error!(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch).with_account_name(#name_str));
+                }
+                Self::try_deserialize_unchecked(buf)
+            }
+
+            fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+                let mut data: &[u8] = &buf[8..];
+                AnchorDeserialize::deserialize(&mut data)
+                    .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
+            }
+        }
+    }
+}
+
+// The events, along with an `events::Event` of all of them, decoded from the
+// data logged by `emit!`.
+fn events(idl: &Idl, root: &TokenStream) -> TokenStream {
+    let events = match idl.events.as_deref() {
+        None | Some([]) => return quote! {},
+        Some(events) => events,
+    };
+    let structs = events.iter().map(|event| {
+        let name = ident(&event.name);
+        let fields = event.fields.iter().map(|field| {
+            let name = ident(&field.name.to_snake_case());
+            let ty = rust_type(&field.ty, root);
+            quote! { pub #name: #ty }
+        });
+        let discriminator = discriminator(&sighash("event", &event.name));
+        quote! {
+            #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+            pub struct #name {
+                #(#fields),*
+            }
+
+            impl anchor_lang::Discriminator for #name {
+                fn discriminator() -> [u8; 8] {
+                    #discriminator
+                }
+            }
+
+            impl anchor_lang::Event for #name {
+                fn data(&self) -> Vec<u8> {
+                    let mut d = #discriminator.to_vec();
+                    d.append(&mut self.try_to_vec().unwrap());
+                    d
+                }
+            }
+        }
+    });
+    let names = events
+        .iter()
+        .map(|event| ident(&event.name))
+        .collect::<Vec<_>>();
+    quote! {
+        #(#structs)*
+
+        /// Decoding of the program's events.
+        pub mod events {
+            use super::*;
+
+            #[derive(Clone, Debug, PartialEq)]
+            pub enum Event {
+                #(#names(#root::#names)),*
+            }
+
+            /// Decodes the data of an event, i.e. the base64 decoded
+            /// `Program data: ` log of `emit!`. Returns `None` for the data
+            /// of other events.
+            pub fn decode(data: &[u8]) -> Option<Event> {
+                if data.len() < 8 {
+                    return None;
+                }
+                let (discriminator, mut data) = data.split_at(8);
+                #(
+                    if discriminator == <#root::#names as anchor_lang::Discriminator>::discriminator() {
+                        return AnchorDeserialize::deserialize(&mut data).ok().map(Event::#names);
+                    }
+                )*
+                None
+            }
+        }
+    }
+}
+
+fn errors(idl: &Idl) -> TokenStream {
+    let errors = match idl.errors.as_deref() {
+        None | Some([]) => return quote! {},
+        Some(errors) => errors,
+    };
+    let variants = errors.iter().map(|error| {
+        let docs = docs(&error.docs);
+        let name = ident(&error.name);
+        let code = Literal::u32_unsuffixed(error.code);
+        quote! {
+            #docs
+            #name = #code
+        }
+    });
+    let from_code = errors.iter().map(|error| {
+        let name = ident(&error.name);
+        let code = Literal::u32_unsuffixed(error.code);
+        quote! { #code => Some(Self::#name), }
+    });
+    let msgs = errors.iter().map(|error| {
+        let name = ident(&error.name);
+        let msg = error.msg.as_ref().unwrap_or(&error.name);
+        quote! { Self::#name => #msg, }
+    });
+    quote! {
+        /// Errors of the program.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #[repr(u32)]
+        pub enum ErrorCode {
+            #(#variants),*
+        }
+
+        impl ErrorCode {
+            pub fn code(&self) -> u32 {
+                *self as u32
+            }
+
+            pub fn from_code(code: u32) -> Option<Self> {
+                match code {
+                    #(#from_code)*
+                    _ => None,
+                }
+            }
+
+            pub fn msg(&self) -> &'static str {
+                match self {
+                    #(#msgs)*
+                }
+            }
+        }
+    }
+}
+
+fn instruction_data(ix: &IdlInstruction, root: &TokenStream) -> TokenStream {
+    let docs = docs(&ix.docs);
+    let name = ident(&ix.name.to_camel_case());
+    let discriminator = discriminator(&ix.discriminator());
+    let strct = match ix.args.is_empty() {
+        true => quote! { pub struct #name; },
+        false => {
+            let fields = named_fields(&ix.args, quote! { pub }, root);
+            quote! {
+                pub struct #name {
+                    #(#fields),*
+                }
+            }
+        }
+    };
+    quote! {
+        #docs
+        #[derive(AnchorSerialize, AnchorDeserialize)]
+        #strct
+
+        impl anchor_lang::Discriminator for #name {
+            fn discriminator() -> [u8; 8] {
+                #discriminator
+            }
+        }
+
+        impl anchor_lang::InstructionData for #name {
+            fn data(&self) -> Vec<u8> {
+                let mut d = #discriminator.to_vec();
+                d.append(&mut self.try_to_vec().expect("Should always serialize"));
+                d
+            }
+        }
+    }
+}
+
+fn cpi_fn(ix: &IdlInstruction, root: &TokenStream) -> TokenStream {
+    let docs = docs(&ix.docs);
+    // Instructions scheduled for removal with `#[sunset(..)]` are deprecated,
+    // for the compiler to warn about their uses.
+    let deprecated = match &ix.sunset {
+        Some(sunset) => {
+            let note = format!("Fails from {} on", sunset);
+            quote! { #[deprecated(note = #note)] }
+        }
+        None => quote! {},
+    };
+    let method = ident(&ix.name.to_snake_case());
+    let name = ident(&ix.name.to_camel_case());
+    let accounts = match has_infos(&ix.accounts) {
+        true => quote! { #root::cpi::accounts::#name<'info> },
+        false => quote! { #root::cpi::accounts::#name },
+    };
+    let args = ix
+        .args
+        .iter()
+        .map(|arg| ident(&arg.name.to_snake_case()))
+        .collect::<Vec<_>>();
+    let arg_tys = ix.args.iter().map(|arg| rust_type(&arg.ty, root));
+    let ix_data = match ix.args.is_empty() {
+        true => quote! { instruction::#name },
+        false => quote! { instruction::#name { #(#args),* } },
+    };
+    let (ret, maybe_return) = match &ix.returns {
+        None => (quote! { () }, quote! { () }),
+        Some(ty) => {
+            let ty = rust_type(ty, root);
+            (
+                quote! { Return<#ty> },
+                quote! { Return { phantom: PhantomData } },
+            )
+        }
+    };
+    quote! {
+        #docs
+        #deprecated
+        pub fn #method<'a, 'b, 'c, 'info>(
+            ctx: anchor_lang::context::CpiContext<'a, 'b, 'c, 'info, #accounts>,
+            #(#args: #arg_tys),*
+        ) -> anchor_lang::Result<#ret> {
+            let ix = {
+                let ix = #ix_data;
+                let data = anchor_lang::InstructionData::data(&ix);
+                let accounts = ctx.to_account_metas(None);
+                anchor_lang::solana_program::instruction::Instruction {
+                    program_id: #root::ID,
+                    accounts,
+                    data,
+                }
+            };
+            let acc_infos = ctx.to_account_infos();
+            anchor_lang::solana_program::program::invoke_signed(
+                &ix,
+                &acc_infos,
+                ctx.signer_seeds,
+            )
+            .map(|_| #maybe_return)
+            .map_err(Into::into)
+        }
+    }
+}
+
+// The struct of `AccountInfo`s of the accounts `items`, followed by the ones
+// of its composite accounts.
+fn accounts_struct(
+    name: &str,
+    struct_docs: &Option<Vec<String>>,
+    items: &[IdlAccountItem],
+    structs: &mut Vec<TokenStream>,
+    root: &TokenStream,
+) {
+    let strct_docs = docs(struct_docs);
+    let struct_name = ident(name);
+    let generics = match has_infos(items) {
+        true => quote! { <'info> },
+        false => quote! {},
+    };
+    let mut fields = Vec::new();
+    let mut metas = Vec::new();
+    let mut infos = Vec::new();
+    let mut nested = Vec::new();
+    for item in items {
+        match item {
+            IdlAccountItem::IdlAccount(acc) => {
+                let docs = docs(&acc.docs);
+                let field = ident(&acc.name.to_snake_case());
+                let is_signer = acc.is_signer;
+                let meta = match acc.is_mut {
+                    false => {
+                        quote! { anchor_lang::solana_program::instruction::AccountMeta::new_readonly }
+                    }
+                    true => quote! { anchor_lang::solana_program::instruction::AccountMeta::new },
+                };
+                match acc.is_optional {
+                    Some(true) => {
+                        fields.push(quote! { #docs pub #field: Option<AccountInfo<'info>> });
+                        // Optional accounts that aren't given are the program id.
+                        metas.push(quote! {
+                            account_metas.push(match &self.#field {
+                                Some(account) => #meta(anchor_lang::Key::key(account), is_signer.unwrap_or(#is_signer)),
+                                None => anchor_lang::solana_program::instruction::AccountMeta::new_readonly(#root::ID, false),
+                            });
+                        });
+                        infos.push(quote! {
+                            account_infos.extend(self.#field.clone());
+                        });
+                    }
+                    _ => {
+                        fields.push(quote! { #docs pub #field: AccountInfo<'info> });
+                        metas.push(quote! {
+                            account_metas.push(#meta(anchor_lang::Key::key(&self.#field), is_signer.unwrap_or(#is_signer)));
+                        });
+                        infos.push(quote! {
+                            account_infos.push(anchor_lang::ToAccountInfo::to_account_info(&self.#field));
+                        });
+                    }
+                }
+            }
+            IdlAccountItem::IdlAccounts(accs) => {
+                let field = ident(&accs.name.to_snake_case());
+                let nested_name = format!("{}{}", name, accs.name.to_camel_case());
+                let ty = ident(&nested_name);
+                match has_infos(&accs.accounts) {
+                    true => fields.push(quote! { pub #field: #ty<'info> }),
+                    false => fields.push(quote! { pub #field: #ty }),
+                }
+                metas.push(quote! {
+                    account_metas.extend(self.#field.to_account_metas(is_signer));
+                });
+                infos.push(quote! {
+                    account_infos.extend(anchor_lang::ToAccountInfos::to_account_infos(&self.#field));
+                });
+                nested.push((nested_name, &accs.accounts));
+            }
+        }
+    }
+    structs.push(quote! {
+        #strct_docs
+        pub struct #struct_name #generics {
+            #(#fields),*
+        }
+
+        impl #generics anchor_lang::ToAccountMetas for #struct_name #generics {
+            fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<anchor_lang::solana_program::instruction::AccountMeta> {
+                let mut account_metas = vec![];
+                #(#metas)*
+                account_metas
+            }
+        }
+
+        impl<'info> anchor_lang::ToAccountInfos<'info> for #struct_name #generics {
+            fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+                let mut account_infos = vec![];
+                #(#infos)*
+                account_infos
+            }
+        }
+    });
+    for (name, items) in nested {
+        accounts_struct(&name, &None, items, structs, root);
+    }
+}
+
+// Whether there are any accounts, i.e. whether the struct of `items` has an
+// `'info` lifetime.
+fn has_infos(items: &[IdlAccountItem]) -> bool {
+    items.iter().any(|item| match item {
+        IdlAccountItem::IdlAccount(_) => true,
+        IdlAccountItem::IdlAccounts(accs) => has_infos(&accs.accounts),
+    })
+}
+
+// The fields, with the visibility `vis`, i.e. none for the ones of a variant.
+fn named_fields(fields: &[IdlField], vis: TokenStream, root: &TokenStream) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .map(|field| {
+            let docs = docs(&field.docs);
+            let name = ident(&field.name.to_snake_case());
+            let ty = rust_type(&field.ty, root);
+            quote! {
+                #docs
+                #vis #name: #ty
+            }
+        })
+        .collect()
+}
+
+fn rust_type(ty: &IdlType, root: &TokenStream) -> TokenStream {
+    match ty {
+        IdlType::Bool => quote! { bool },
+        IdlType::U8 => quote! { u8 },
+        IdlType::I8 => quote! { i8 },
+        IdlType::U16 => quote! { u16 },
+        IdlType::I16 => quote! { i16 },
+        IdlType::U32 => quote! { u32 },
+        IdlType::I32 => quote! { i32 },
+        IdlType::F32 => quote! { f32 },
+        IdlType::U64 => quote! { u64 },
+        IdlType::I64 => quote! { i64 },
+        IdlType::F64 => quote! { f64 },
+        IdlType::U128 => quote! { u128 },
+        IdlType::I128 => quote! { i128 },
+        IdlType::Float(float) => match float.bits {
+            32 => quote! { f32 },
+            _ => quote! { f64 },
+        },
+        IdlType::Bytes => quote! { Vec<u8> },
+        IdlType::String => quote! { String },
+        IdlType::PublicKey => quote! { Pubkey },
+        IdlType::Defined(name) => {
+            let name = ident(name);
+            quote! { #root::#name }
+        }
+        IdlType::Option(ty) => {
+            let ty = rust_type(ty, root);
+            quote! { Option<#ty> }
+        }
+        IdlType::Vec(ty) => {
+            let ty = rust_type(ty, root);
+            quote! { Vec<#ty> }
+        }
+        IdlType::Array(ty, len) => {
+            let ty = rust_type(ty, root);
+            let len = Literal::usize_unsuffixed(*len);
+            quote! { [#ty; #len] }
+        }
+    }
+}
+
+pub(super) fn docs(docs: &Option<Vec<String>>) -> TokenStream {
+    let lines = docs.iter().flatten().map(|line| format!(" {}", line));
+    quote! { #(#[doc = #lines])* }
+}
+
+pub(super) fn discriminator(discriminator: &[u8]) -> TokenStream {
+    format!("{:?}", discriminator).parse().unwrap()
+}
+
+// The identifier of `name`, escaping keywords, e.g. `r#type` for `type`.
+pub(super) fn ident(name: &str) -> Ident {
+    match name {
+        "self" | "Self" | "super" | "crate" => format_ident!("{}_", name),
+        _ if syn::parse_str::<Ident>(name).is_ok() => Ident::new(name, Span::call_site()),
+        _ => Ident::new_raw(name, Span::call_site()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rust_cpi() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "instructions": [{
+                "name": "deposit",
+                "docs": ["Deposits into the vault."],
+                "accounts": [
+                    { "name": "vault", "isMut": true, "isSigner": false },
+                    {
+                        "name": "auth",
+                        "accounts": [
+                            { "name": "authority", "isMut": false, "isSigner": true },
+                            { "name": "delegate", "isMut": false, "isSigner": true, "isOptional": true }
+                        ]
+                    }
+                ],
+                "args": [
+                    { "name": "amount", "type": "u64" },
+                    { "name": "type", "type": { "defined": "Kind" } }
+                ],
+                "returns": "u64"
+            }],
+            "accounts": [{
+                "name": "Vault",
+                "type": {
+                    "kind": "struct",
+                    "fields": [{ "name": "balance", "type": "u64" }]
+                }
+            }],
+            "types": [{
+                "name": "Kind",
+                "type": {
+                    "kind": "enum",
+                    "variants": [
+                        { "name": "Spot" },
+                        { "name": "Locked", "fields": [{ "name": "until", "type": "i64" }] }
+                    ]
+                }
+            }],
+            "events": [{
+                "name": "Deposited",
+                "fields": [{ "name": "amount", "type": "u64", "index": false }]
+            }],
+            "errors": [{ "code": 6000, "name": "Paused", "msg": "The vault is paused" }],
+            "metadata": { "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS" }
+        }))
+        .unwrap();
+        let cpi = generate(&idl, None).unwrap();
+        let lib_rs = syn::parse_file(&cpi.lib_rs).unwrap();
+        // Compares the code without whitespace, which the printing of tokens
+        // doesn't keep.
+        let squash = |code: String| code.split_whitespace().collect::<String>();
+        let lib_rs = squash(quote! { #lib_rs }.to_string());
+        let has = |tokens: TokenStream| lib_rs.contains(&squash(tokens.to_string()));
+
+        assert!(cpi.cargo_toml.contains("name = \"vault-cpi\""));
+        assert!(has(
+            quote! { declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"); }
+        ));
+        assert!(has(quote! {
+            pub struct Deposit {
+                pub amount: u64,
+                pub r#type: crate::Kind
+            }
+        }));
+        assert!(has(quote! {
+            pub fn deposit<'a, 'b, 'c, 'info>(
+                ctx: anchor_lang::context::CpiContext<'a, 'b, 'c, 'info, crate::cpi::accounts::Deposit<'info>>,
+                amount: u64,
+                r#type: crate::Kind
+            ) -> anchor_lang::Result<Return<u64>>
+        }));
+        assert!(has(quote! {
+            pub struct DepositAuth<'info> {
+                pub authority: AccountInfo<'info>,
+                pub delegate: Option<AccountInfo<'info>>
+            }
+        }));
+        assert!(has(quote! { Paused = 6000 }));
+        assert!(has(quote! { Self::Paused => "The vault is paused", }));
+        assert!(has(quote! { Deposited(crate::Deposited) }));
+
+        assert!(generate(
+            &Idl {
+                metadata: None,
+                ..idl.clone()
+            },
+            None
+        )
+        .is_err());
+        assert!(generate(&idl, Some("not a key")).is_err());
+    }
+
+    #[test]
+    fn declared_module() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "constants": [
+                { "name": "MAX_FEE", "type": "u64", "value": "100", "evaluated": { "int": "100" } },
+                { "name": "SEED", "type": "bytes", "value": "b\"ab\"", "evaluated": { "bytes": [97, 98] } },
+                { "name": "FEE", "type": "u64", "value": "MAX_FEE / 2" }
+            ],
+            "instructions": [{
+                "name": "deposit",
+                "accounts": [{ "name": "vault", "isMut": true, "isSigner": false }],
+                "args": [{ "name": "kind", "type": { "defined": "Kind" } }]
+            }],
+            "types": [{
+                "name": "Kind",
+                "type": { "kind": "enum", "variants": [{ "name": "Spot" }] }
+            }],
+            "metadata": { "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS" }
+        }))
+        .unwrap();
+        let module = module(&idl, None, &quote! { crate::vault }).unwrap();
+        let squash = |code: String| code.split_whitespace().collect::<String>();
+        let module = squash(module.to_string());
+        let has = |tokens: TokenStream| module.contains(&squash(tokens.to_string()));
+
+        assert!(has(quote! { pub const MAX_FEE: u64 = 100; }));
+        assert!(has(quote! { pub const SEED: &[u8] = &[97, 98]; }));
+        assert!(!module.contains("FEE:u64=MAX_FEE"));
+        assert!(has(quote! { pub kind: crate::vault::Kind }));
+        assert!(has(quote! { program_id: crate::vault::ID, }));
+        assert!(!module.contains("crate::Kind"));
+    }
+}
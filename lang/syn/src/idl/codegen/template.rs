@@ -0,0 +1,311 @@
+use crate::idl::*;
+use anyhow::{anyhow, Result};
+use heck::{CamelCase, MixedCase, ShoutySnakeCase, SnakeCase};
+use minijinja::{Environment, State};
+use serde::Serialize;
+use std::path::Path;
+
+// User-overridable templates of the generated clients.
+//
+// The module generated by each backend goes through a minijinja template
+// before it's written, with the context below. The default templates render
+// the module as it is, i.e. `{{ module }}`. A template of the same name in the
+// templates directory given to the generator replaces the default one, e.g. to
+// add a license header, imports of company-specific helpers, or wrappers of
+// the instructions, without forking the generator.
+//
+// The context structs are part of the interface of the templates: fields are
+// only ever added to them.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Typescript,
+    Python,
+    // The `src/lib.rs` of the CPI crate.
+    RustCpi,
+}
+
+impl Backend {
+    // The name of the template of the backend in the templates directory.
+    pub fn template_name(&self) -> &'static str {
+        match self {
+            Backend::Typescript => "typescript.ts.jinja",
+            Backend::Python => "python.py.jinja",
+            Backend::RustCpi => "rust_cpi.rs.jinja",
+        }
+    }
+}
+
+const DEFAULT_TEMPLATE: &str = "{{ module }}";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientContext {
+    // The module generated by the backend.
+    pub module: String,
+    pub program: ProgramContext,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgramContext {
+    pub name: String,
+    pub version: String,
+    // The address of the program, if known.
+    pub program_id: Option<String>,
+    pub docs: Vec<String>,
+    pub instructions: Vec<InstructionContext>,
+    pub accounts: Vec<TypeContext>,
+    pub types: Vec<TypeContext>,
+    pub events: Vec<TypeContext>,
+    pub errors: Vec<ErrorContext>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstructionContext {
+    pub name: String,
+    pub docs: Vec<String>,
+    pub args: Vec<FieldContext>,
+    // The accounts of the instruction, with the ones of composite accounts
+    // flattened into `<composite>.<account>` names.
+    pub accounts: Vec<AccountContext>,
+    pub returns: Option<IdlType>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountContext {
+    pub name: String,
+    pub is_mut: bool,
+    pub is_signer: bool,
+    pub is_optional: bool,
+    pub docs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeContext {
+    pub name: String,
+    pub docs: Vec<String>,
+    // The fields of structs and events, none for enums.
+    pub fields: Vec<FieldContext>,
+    // The variant names of enums.
+    pub variants: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldContext {
+    pub name: String,
+    pub docs: Vec<String>,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorContext {
+    pub code: u32,
+    pub name: String,
+    pub msg: Option<String>,
+}
+
+impl ProgramContext {
+    pub fn new(idl: &Idl, program_id: Option<&str>) -> Self {
+        let program_id = program_id.map(ToString::to_string).or_else(|| {
+            idl.metadata
+                .as_ref()
+                .and_then(|metadata| metadata.get("address"))
+                .and_then(|address| address.as_str())
+                .map(ToString::to_string)
+        });
+        ProgramContext {
+            name: idl.name.clone(),
+            version: idl.version.clone(),
+            program_id,
+            docs: idl.docs.clone().unwrap_or_default(),
+            instructions: idl
+                .instructions
+                .iter()
+                .map(|ix| InstructionContext {
+                    name: ix.name.clone(),
+                    docs: ix.docs.clone().unwrap_or_default(),
+                    args: ix.args.iter().map(field).collect(),
+                    accounts: accounts(&ix.accounts, ""),
+                    returns: ix.returns.clone(),
+                })
+                .collect(),
+            accounts: idl.accounts.iter().map(type_definition).collect(),
+            types: idl.types.iter().map(type_definition).collect(),
+            events: idl
+                .events
+                .iter()
+                .flatten()
+                .map(|event| TypeContext {
+                    name: event.name.clone(),
+                    docs: vec![],
+                    fields: event
+                        .fields
+                        .iter()
+                        .map(|f| FieldContext {
+                            name: f.name.clone(),
+                            docs: vec![],
+                            ty: f.ty.clone(),
+                        })
+                        .collect(),
+                    variants: vec![],
+                })
+                .collect(),
+            errors: idl
+                .errors
+                .iter()
+                .flatten()
+                .map(|err| ErrorContext {
+                    code: err.code,
+                    name: err.name.clone(),
+                    msg: err.msg.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn field(f: &IdlField) -> FieldContext {
+    FieldContext {
+        name: f.name.clone(),
+        docs: f.docs.clone().unwrap_or_default(),
+        ty: f.ty.clone(),
+    }
+}
+
+fn accounts(items: &[IdlAccountItem], prefix: &str) -> Vec<AccountContext> {
+    items
+        .iter()
+        .flat_map(|item| match item {
+            IdlAccountItem::IdlAccount(acc) => vec![AccountContext {
+                name: format!("{}{}", prefix, acc.name),
+                is_mut: acc.is_mut,
+                is_signer: acc.is_signer,
+                is_optional: acc.is_optional.unwrap_or(false),
+                docs: acc.docs.clone().unwrap_or_default(),
+            }],
+            IdlAccountItem::IdlAccounts(accs) => {
+                accounts(&accs.accounts, &format!("{}{}.", prefix, accs.name))
+            }
+        })
+        .collect()
+}
+
+fn type_definition(ty_def: &IdlTypeDefinition) -> TypeContext {
+    let (fields, variants) = match &ty_def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => (fields.iter().map(field).collect(), vec![]),
+        IdlTypeDefinitionTy::Enum { variants } => {
+            (vec![], variants.iter().map(|v| v.name.clone()).collect())
+        }
+    };
+    TypeContext {
+        name: ty_def.name.clone(),
+        docs: ty_def.docs.clone().unwrap_or_default(),
+        fields,
+        variants,
+    }
+}
+
+// Renders the module generated by `backend` through its template, the one in
+// `templates` if there's any, the default one otherwise.
+pub fn render(
+    backend: Backend,
+    idl: &Idl,
+    program_id: Option<&str>,
+    module: String,
+    templates: Option<&Path>,
+) -> Result<String> {
+    let template = match templates.map(|dir| dir.join(backend.template_name())) {
+        Some(path) if path.exists() => std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Unable to read template {}: {}", path.display(), e))?,
+        _ => DEFAULT_TEMPLATE.to_string(),
+    };
+    let context = ClientContext {
+        module,
+        program: ProgramContext::new(idl, program_id),
+    };
+    let mut env = Environment::new();
+    env.add_filter("camel_case", camel_case);
+    env.add_filter("mixed_case", mixed_case);
+    env.add_filter("snake_case", snake_case);
+    env.add_filter("shouty_snake_case", shouty_snake_case);
+    env.render_str(&template, context)
+        .map_err(|e| anyhow!("Unable to render {}: {}", backend.template_name(), e))
+}
+
+fn camel_case(_state: &State, value: String) -> Result<String, minijinja::Error> {
+    Ok(value.to_camel_case())
+}
+
+fn mixed_case(_state: &State, value: String) -> Result<String, minijinja::Error> {
+    Ok(value.to_mixed_case())
+}
+
+fn snake_case(_state: &State, value: String) -> Result<String, minijinja::Error> {
+    Ok(value.to_snake_case())
+}
+
+fn shouty_snake_case(_state: &State, value: String) -> Result<String, minijinja::Error> {
+    Ok(value.to_shouty_snake_case())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_templates() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "counter",
+            "instructions": [{
+                "name": "increment",
+                "accounts": [
+                    { "name": "counter", "isMut": true, "isSigner": false },
+                    {
+                        "name": "auth",
+                        "accounts": [{ "name": "authority", "isMut": false, "isSigner": true }]
+                    }
+                ],
+                "args": [{ "name": "by", "type": "u64" }]
+            }],
+            "metadata": { "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS" }
+        }))
+        .unwrap();
+        let module = "export const A = 1;\n".to_string();
+
+        // Without a template, the module is left as it is.
+        let ts = render(Backend::Typescript, &idl, None, module.clone(), None).unwrap();
+        assert_eq!(ts, module);
+
+        let dir = std::env::temp_dir().join(format!("anchor-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(Backend::Typescript.template_name()),
+            "// {{ program.name }} at {{ program.program_id }}\n{{ module }}\
+             {% for ix in program.instructions %}\
+             export const {{ ix.name | shouty_snake_case }}_ACCOUNTS = [\
+             {% for acc in ix.accounts %}\"{{ acc.name }}\"{% if not loop.last %}, {% endif %}{% endfor %}];\n\
+             {% endfor %}",
+        )
+        .unwrap();
+        let ts = render(Backend::Typescript, &idl, None, module, Some(&dir)).unwrap();
+        assert_eq!(
+            ts,
+            "// counter at Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS\n\
+             export const A = 1;\n\
+             export const INCREMENT_ACCOUNTS = [\"counter\", \"auth.authority\"];\n"
+        );
+
+        // Other backends keep their default template.
+        let py = render(
+            Backend::Python,
+            &idl,
+            None,
+            "A = 1\n".to_string(),
+            Some(&dir),
+        )
+        .unwrap();
+        assert_eq!(py, "A = 1\n");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
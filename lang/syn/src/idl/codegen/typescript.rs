@@ -0,0 +1,418 @@
+use crate::idl::mangle::Target;
+use crate::idl::*;
+use anyhow::Result;
+use heck::{CamelCase, MixedCase};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// TypeScript types of a program, generated from its IDL.
+//
+// The generated module has the IDL as a type, e.g. `Counter`, for the
+// `Program<Counter>` of `@project-serum/anchor`, along with the types of the
+// decoded user defined types, accounts and events, and of the arguments and
+// accounts of the instructions, in the `types`, `accounts`, `events` and
+// `instructions` namespaces. It also has a typed builder per instruction, and
+// a decoder per account.
+
+// The IDL as a type, along with its value as `IDL`.
+pub fn idl(idl: &Idl) -> Result<String> {
+    let mut idl = idl.clone();
+    for acc in idl.accounts.iter_mut() {
+        acc.name = acc.name.to_mixed_case();
+    }
+    let idl_json = serde_json::to_string_pretty(&idl)?;
+    Ok(format!(
+        r#"export type {} = {};
+
+export const IDL: {} = {};
+"#,
+        idl.name.to_camel_case(),
+        idl_json,
+        idl.name.to_camel_case(),
+        idl_json
+    ))
+}
+
+// The module of the program's types, builders and decoders.
+pub fn generate(idl: &Idl) -> Result<String> {
+    let name = idl.name.to_camel_case();
+    let program = format!("{}Program", name);
+    let mut ts = String::new();
+    writeln!(
+        ts,
+        r#"import {{ BN, Program, web3 }} from "@project-serum/anchor";"#
+    )?;
+    writeln!(ts)?;
+    ts.push_str(&self::idl(idl)?);
+    writeln!(ts)?;
+    writeln!(ts, "export type {} = Program<{}>;", program, name)?;
+
+    let ty_defs = |ty_defs: &[IdlTypeDefinition]| {
+        ty_defs
+            .iter()
+            .map(|ty_def| {
+                let ty = type_definition(idl, ty_def);
+                (ty_def.name.clone(), ty_def.docs.clone(), ty)
+            })
+            .collect::<Vec<_>>()
+    };
+    namespace(&mut ts, "types", ty_defs(&idl.types))?;
+    namespace(&mut ts, "accounts", ty_defs(&idl.accounts))?;
+    let events = idl
+        .events
+        .iter()
+        .flatten()
+        .map(|event| {
+            let fields = event.fields.iter().map(|f| (&f.name, &None, &f.ty));
+            (event.name.clone(), None, object(idl, fields, 1))
+        })
+        .collect();
+    namespace(&mut ts, "events", events)?;
+    let instructions = idl
+        .instructions
+        .iter()
+        .flat_map(|ix| {
+            let name = ix.name.to_camel_case();
+            [
+                (format!("{}Args", name), None, args_object(idl, &ix.args)),
+                (
+                    format!("{}Accounts", name),
+                    None,
+                    accounts_object(&ix.accounts, 1),
+                ),
+            ]
+        })
+        .collect();
+    namespace(&mut ts, "instructions", instructions)?;
+
+    for ix in &idl.instructions {
+        let name = ix.name.to_camel_case();
+        let args = ix.args.iter().map(arg_value).collect::<Vec<_>>().join(", ");
+        writeln!(ts)?;
+        docs(&mut ts, &with_sunset(&ix.docs, ix), 0)?;
+        writeln!(
+            ts,
+            "export function {}(\n  program: {},\n  args: instructions.{}Args,\n  accounts: instructions.{}Accounts\n) {{",
+            identifier(&ix.name),
+            program,
+            name,
+            name
+        )?;
+        writeln!(
+            ts,
+            "  return program.methods.{}({}).accounts(accounts);",
+            ix.name, args
+        )?;
+        writeln!(ts, "}}")?;
+    }
+    for acc in &idl.accounts {
+        writeln!(ts)?;
+        writeln!(
+            ts,
+            "export function decode{}(program: {}, data: Buffer): accounts.{} {{",
+            acc.name.to_camel_case(),
+            program,
+            acc.name
+        )?;
+        writeln!(
+            ts,
+            "  return program.coder.accounts.decode(\"{}\", data);",
+            acc.name.to_mixed_case()
+        )?;
+        writeln!(ts, "}}")?;
+    }
+    Ok(ts)
+}
+
+// Writes the types as the namespace `name`, unless there are none.
+fn namespace(
+    ts: &mut String,
+    name: &str,
+    types: Vec<(String, Option<Vec<String>>, String)>,
+) -> Result<()> {
+    if types.is_empty() {
+        return Ok(());
+    }
+    writeln!(ts)?;
+    writeln!(ts, "export namespace {} {{", name)?;
+    for (i, (name, ty_docs, ty)) in types.iter().enumerate() {
+        if i > 0 {
+            writeln!(ts)?;
+        }
+        docs(ts, ty_docs, 1)?;
+        writeln!(ts, "  export type {} = {};", name, ty)?;
+    }
+    writeln!(ts, "}}")?;
+    Ok(())
+}
+
+fn type_definition(idl: &Idl, ty_def: &IdlTypeDefinition) -> String {
+    match &ty_def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            object(idl, fields.iter().map(|f| (&f.name, &f.docs, &f.ty)), 1)
+        }
+        // Enums are decoded as an object with a single key, the variant name.
+        IdlTypeDefinitionTy::Enum { variants } => variants
+            .iter()
+            .map(|variant| {
+                let fields = match &variant.fields {
+                    None => "Record<string, never>".to_string(),
+                    Some(EnumFields::Named(fields)) => {
+                        object(idl, fields.iter().map(|f| (&f.name, &f.docs, &f.ty)), 2)
+                    }
+                    Some(EnumFields::Tuple(tys)) => format!(
+                        "[{}]",
+                        tys.iter()
+                            .map(|ty| ts_type(idl, ty))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                };
+                format!("{{ {}: {} }}", variant.name.to_mixed_case(), fields)
+            })
+            .collect::<Vec<_>>()
+            .join(" | "),
+    }
+}
+
+// An object type with the given fields, at the given level of indentation.
+fn object<'a>(
+    idl: &Idl,
+    fields: impl Iterator<Item = (&'a String, &'a Option<Vec<String>>, &'a IdlType)>,
+    indent: usize,
+) -> String {
+    let mut ts = String::new();
+    for (name, field_docs, ty) in fields {
+        docs(&mut ts, field_docs, indent + 1).unwrap();
+        writeln!(
+            ts,
+            "{}{}: {};",
+            "  ".repeat(indent + 1),
+            name,
+            ts_type(idl, ty)
+        )
+        .unwrap();
+    }
+    match ts.is_empty() {
+        true => "Record<string, never>".to_string(),
+        false => format!("{{\n{}{}}}", ts, "  ".repeat(indent)),
+    }
+}
+
+// The arguments of an instruction, given either by their name or by one of
+// their aliases, e.g. `{ amount: BN } & ({ feeBps: number } | { fee: number })`.
+fn args_object(idl: &Idl, args: &[IdlField]) -> String {
+    let (aliased, named): (Vec<_>, Vec<_>) = args.iter().partition(|f| !f.aliases.is_empty());
+    let mut ts = match named.is_empty() && !aliased.is_empty() {
+        true => vec![],
+        false => vec![object(
+            idl,
+            named.iter().map(|f| (&f.name, &f.docs, &f.ty)),
+            1,
+        )],
+    };
+    for arg in aliased {
+        let names = std::iter::once(&arg.name)
+            .chain(&arg.aliases)
+            .map(|name| format!("{{ {}: {} }}", name, ts_type(idl, &arg.ty)))
+            .collect::<Vec<_>>();
+        ts.push(format!("({})", names.join(" | ")));
+    }
+    ts.join(" & ")
+}
+
+// The value of an argument given to an instruction's builder, looked up by
+// its aliases too.
+fn arg_value(arg: &IdlField) -> String {
+    let mut value = format!("args.{}", arg.aliases.last().unwrap_or(&arg.name));
+    let names = std::iter::once(&arg.name).chain(&arg.aliases).rev().skip(1);
+    for name in names {
+        value = format!(r#""{}" in args ? args.{} : {}"#, name, name, value);
+    }
+    match arg.aliases.is_empty() {
+        true => value,
+        false => format!("({})", value),
+    }
+}
+
+// The accounts of an instruction, with the ones of composite accounts nested.
+fn accounts_object(accounts: &[IdlAccountItem], indent: usize) -> String {
+    let mut ts = String::new();
+    for item in accounts {
+        let (name, ty) = match item {
+            IdlAccountItem::IdlAccount(acc) => match acc.is_optional {
+                Some(true) => (&acc.name, "web3.PublicKey | null".to_string()),
+                _ => (&acc.name, "web3.PublicKey".to_string()),
+            },
+            IdlAccountItem::IdlAccounts(accs) => {
+                (&accs.name, accounts_object(&accs.accounts, indent + 1))
+            }
+        };
+        writeln!(ts, "{}{}: {};", "  ".repeat(indent + 1), name, ty).unwrap();
+    }
+    match ts.is_empty() {
+        true => "Record<string, never>".to_string(),
+        false => format!("{{\n{}{}}}", ts, "  ".repeat(indent)),
+    }
+}
+
+// The type of decoded values of `ty`.
+fn ts_type(idl: &Idl, ty: &IdlType) -> String {
+    match ty {
+        IdlType::Bool => "boolean".to_string(),
+        IdlType::U8
+        | IdlType::I8
+        | IdlType::U16
+        | IdlType::I16
+        | IdlType::U32
+        | IdlType::I32
+        | IdlType::F32
+        | IdlType::F64
+        | IdlType::Float(_) => "number".to_string(),
+        IdlType::U64 | IdlType::I64 | IdlType::U128 | IdlType::I128 => "BN".to_string(),
+        IdlType::Bytes => "Buffer".to_string(),
+        IdlType::String => "string".to_string(),
+        IdlType::PublicKey => "web3.PublicKey".to_string(),
+        IdlType::Defined(name) => {
+            if idl.types.iter().any(|ty_def| &ty_def.name == name) {
+                format!("types.{}", name)
+            } else if idl.accounts.iter().any(|acc| &acc.name == name) {
+                format!("accounts.{}", name)
+            } else {
+                name.clone()
+            }
+        }
+        IdlType::Option(ty) => format!("{} | null", ts_type(idl, ty)),
+        IdlType::Vec(ty) | IdlType::Array(ty, _) => format!("Array<{}>", ts_type(idl, ty)),
+    }
+}
+
+fn docs(ts: &mut String, docs: &Option<Vec<String>>, indent: usize) -> Result<()> {
+    let indent = "  ".repeat(indent);
+    match docs.as_deref() {
+        None | Some([]) => {}
+        Some([line]) => writeln!(ts, "{}/** {} */", indent, line)?,
+        Some(lines) => {
+            writeln!(ts, "{}/**", indent)?;
+            for line in lines {
+                writeln!(ts, "{} * {}", indent, line)?;
+            }
+            writeln!(ts, "{} */", indent)?;
+        }
+    }
+    Ok(())
+}
+
+// The docs of an instruction, followed by its deprecation if it's scheduled
+// for removal with `#[sunset(..)]`, for linters to flag its uses.
+fn with_sunset(docs: &Option<Vec<String>>, ix: &IdlInstruction) -> Option<Vec<String>> {
+    let sunset = match &ix.sunset {
+        Some(sunset) => sunset,
+        None => return docs.clone(),
+    };
+    let mut docs = docs.clone().unwrap_or_default();
+    docs.push(format!("@deprecated Fails from {} on.", sunset));
+    Some(docs)
+}
+
+// The name of the function of an instruction, e.g. `delete_` for `delete`.
+fn identifier(name: &str) -> String {
+    Target::Typescript.mangle(name, &BTreeMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typescript() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "counter",
+            "instructions": [{
+                "name": "delete",
+                "docs": ["Deletes the counter."],
+                "accounts": [
+                    { "name": "counter", "isMut": true, "isSigner": false },
+                    {
+                        "name": "auth",
+                        "accounts": [
+                            { "name": "authority", "isMut": false, "isSigner": true },
+                            { "name": "delegate", "isMut": false, "isSigner": true, "isOptional": true }
+                        ]
+                    }
+                ],
+                "args": [
+                    { "name": "refund", "type": "bool" },
+                    { "name": "feeBps", "type": "u16", "aliases": ["fee", "feeRate"] }
+                ]
+            }],
+            "accounts": [{
+                "name": "Counter",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "count", "type": "u64" },
+                        { "name": "mode", "type": { "option": { "defined": "Mode" } } }
+                    ]
+                }
+            }],
+            "types": [{
+                "name": "Mode",
+                "type": {
+                    "kind": "enum",
+                    "variants": [
+                        { "name": "Fast" },
+                        { "name": "Slow", "fields": [{ "name": "delay", "type": "u8" }] }
+                    ]
+                }
+            }]
+        }))
+        .unwrap();
+        let ts = generate(&idl).unwrap();
+
+        assert!(ts.contains("export type CounterProgram = Program<Counter>;"));
+        assert!(ts.contains(
+            r#"export namespace types {
+  export type Mode = { fast: Record<string, never> } | { slow: {
+      delay: number;
+    } };
+}"#
+        ));
+        assert!(ts.contains(
+            r#"  export type Counter = {
+    count: BN;
+    mode: types.Mode | null;
+  };"#
+        ));
+        assert!(ts.contains(
+            r#"  export type DeleteArgs = {
+    refund: boolean;
+  } & ({ feeBps: number } | { fee: number } | { feeRate: number });"#
+        ));
+        assert!(ts.contains(
+            r#"  export type DeleteAccounts = {
+    counter: web3.PublicKey;
+    auth: {
+      authority: web3.PublicKey;
+      delegate: web3.PublicKey | null;
+    };
+  };"#
+        ));
+        assert!(ts.contains(
+            r#"/** Deletes the counter. */
+export function delete_(
+  program: CounterProgram,
+  args: instructions.DeleteArgs,
+  accounts: instructions.DeleteAccounts
+) {
+  return program.methods.delete(args.refund, ("feeBps" in args ? args.feeBps : "fee" in args ? args.fee : args.feeRate)).accounts(accounts);
+}"#
+        ));
+        assert!(ts.contains(
+            r#"export function decodeCounter(program: CounterProgram, data: Buffer): accounts.Counter {
+  return program.coder.accounts.decode("counter", data);
+}"#
+        ));
+    }
+}
@@ -0,0 +1,354 @@
+use crate::codegen::program::common::sighash;
+use crate::idl::file::to_idl_type;
+use crate::idl::*;
+use crate::parser::{self, context::CrateContext};
+use anyhow::{anyhow, Result};
+use heck::MixedCase;
+use std::path::Path;
+
+// Conformance of a program to the interfaces it implements.
+//
+// An interface is either a set of `#[interface]` traits, whose instructions are
+// generic over their accounts, or the IDL of another program, e.g. of a
+// standard that several programs implement. Each instruction of the interface
+// is a test, which passes when the program has an instruction with the same
+// discriminator, the same args and return type, and accounts that callers of
+// the interface can pass, i.e. that don't need to be writable or signers when
+// the interface's aren't, and no more accounts than the interface's unless
+// they're optional.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interface {
+    pub name: String,
+    pub instructions: Vec<InterfaceInstruction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterfaceInstruction {
+    pub name: String,
+    pub discriminator: Vec<u8>,
+    pub args: Vec<IdlField>,
+    pub returns: Option<IdlType>,
+    // Unknown for `#[interface]` traits, which are generic over the accounts.
+    pub accounts: Option<Vec<(String, IdlAccount)>>,
+}
+
+impl Interface {
+    pub fn from_idl(idl: &Idl) -> Self {
+        Interface {
+            name: idl.name.clone(),
+            instructions: idl
+                .instructions
+                .iter()
+                .map(|ix| InterfaceInstruction {
+                    name: ix.name.clone(),
+                    discriminator: ix.discriminator(),
+                    args: ix.args.clone(),
+                    returns: ix.returns.clone(),
+                    accounts: Some(
+                        ix.account_names()
+                            .into_iter()
+                            .zip(flatten(&ix.accounts).into_iter().cloned())
+                            .collect(),
+                    ),
+                })
+                .collect(),
+        }
+    }
+
+    // The `#[interface]` traits of the crate whose root is `root`.
+    pub fn parse_traits(root: impl AsRef<Path>) -> Result<Vec<Self>> {
+        let ctx = CrateContext::parse(root)?;
+        let mut interfaces = vec![];
+        for module in ctx.modules() {
+            for item in module.items() {
+                let item_trait = match item {
+                    syn::Item::Trait(item_trait) if is_interface(item_trait) => item_trait,
+                    _ => continue,
+                };
+                let trait_name = item_trait.ident.to_string();
+                let instructions = item_trait
+                    .items
+                    .iter()
+                    .filter_map(|item| match item {
+                        syn::TraitItem::Method(method) => Some(&method.sig),
+                        _ => None,
+                    })
+                    .map(|sig| trait_instruction(&ctx, &module.path(), &trait_name, sig))
+                    .collect::<Result<_>>()?;
+                interfaces.push(Interface {
+                    name: trait_name,
+                    instructions,
+                });
+            }
+        }
+        Ok(interfaces)
+    }
+}
+
+// The instruction of a method of an `#[interface]` trait, taking the args of
+// the method besides its `Context`.
+fn trait_instruction(
+    ctx: &CrateContext,
+    module: &str,
+    trait_name: &str,
+    sig: &syn::Signature,
+) -> Result<InterfaceInstruction> {
+    let args = sig
+        .inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            syn::FnArg::Typed(pat_ty) => Some(pat_ty),
+            syn::FnArg::Receiver(_) => None,
+        })
+        .filter(|pat_ty| {
+            let mut ty = parser::tts_to_string(&pat_ty.ty);
+            ty.retain(|c| !c.is_whitespace());
+            !ty.starts_with("Context<")
+        })
+        .map(|pat_ty| {
+            Ok(IdlField {
+                name: parser::tts_to_string(&pat_ty.pat).to_mixed_case(),
+                docs: None,
+                ty: to_idl_type(ctx, module, &pat_ty.ty)?,
+                constraints: vec![],
+                aliases: vec![],
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(InterfaceInstruction {
+        name: sig.ident.to_string().to_mixed_case(),
+        discriminator: sighash(trait_name, &sig.ident.to_string()).to_vec(),
+        args,
+        returns: None,
+        accounts: None,
+    })
+}
+
+// Reads the interfaces at `path`, the root of a crate defining `#[interface]`
+// traits if it's a Rust file, and an IDL otherwise.
+pub fn read(path: impl AsRef<Path>) -> Result<Vec<Interface>> {
+    let path = path.as_ref();
+    if path.extension().map_or(false, |ext| ext == "rs") {
+        let interfaces = Interface::parse_traits(path)?;
+        if interfaces.is_empty() {
+            return Err(anyhow!("No #[interface] trait found in {}", path.display()));
+        }
+        return Ok(interfaces);
+    }
+    Ok(vec![Interface::from_idl(&io::read(path)?)])
+}
+
+// The outcome of the conformance test of an instruction of an interface.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConformanceTest {
+    pub interface: String,
+    pub instruction: String,
+    pub failures: Vec<String>,
+}
+
+impl ConformanceTest {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+// Tests that the program implements each instruction of the interface.
+pub fn check(program: &Idl, interface: &Interface) -> Vec<ConformanceTest> {
+    interface
+        .instructions
+        .iter()
+        .map(|expected| ConformanceTest {
+            interface: interface.name.clone(),
+            instruction: expected.name.clone(),
+            failures: check_instruction(program, expected),
+        })
+        .collect()
+}
+
+fn check_instruction(program: &Idl, expected: &InterfaceInstruction) -> Vec<String> {
+    let mut failures = vec![];
+    let ix = match program
+        .instructions
+        .iter()
+        .find(|ix| ix.discriminator() == expected.discriminator)
+    {
+        Some(ix) => ix,
+        None => match program
+            .instructions
+            .iter()
+            .find(|ix| ix.name == expected.name)
+        {
+            Some(ix) => {
+                failures.push(format!(
+                    "discriminator {:?} differs from the interface's {:?}",
+                    ix.discriminator(),
+                    expected.discriminator
+                ));
+                ix
+            }
+            None => return vec![format!("{} isn't implemented", expected.name)],
+        },
+    };
+
+    if ix.args.len() != expected.args.len() {
+        failures.push(format!(
+            "takes {} args but the interface passes {}",
+            ix.args.len(),
+            expected.args.len()
+        ));
+    }
+    for (arg, expected_arg) in ix.args.iter().zip(&expected.args) {
+        if arg.ty != expected_arg.ty {
+            failures.push(format!(
+                "arg {} is {} but the interface passes {} {}",
+                arg.name,
+                type_name(&arg.ty),
+                expected_arg.name,
+                type_name(&expected_arg.ty)
+            ));
+        }
+    }
+    if ix.returns != expected.returns {
+        failures.push(format!(
+            "returns {} but the interface returns {}",
+            ix.returns.as_ref().map_or("nothing".to_string(), type_name),
+            expected
+                .returns
+                .as_ref()
+                .map_or("nothing".to_string(), type_name)
+        ));
+    }
+
+    if let Some(expected_accounts) = &expected.accounts {
+        let accounts = flatten(&ix.accounts);
+        for (i, (name, acc)) in ix.account_names().iter().zip(accounts).enumerate() {
+            match expected_accounts.get(i) {
+                Some((expected_name, expected)) => {
+                    if acc.is_mut && !expected.is_mut {
+                        failures.push(format!(
+                            "account {} must be writable but the interface passes {} read-only",
+                            name, expected_name
+                        ));
+                    }
+                    if acc.is_signer && !expected.is_signer {
+                        failures.push(format!(
+                            "account {} must be a signer but the interface doesn't sign {}",
+                            name, expected_name
+                        ));
+                    }
+                }
+                None if acc.is_optional == Some(true) => {}
+                None => failures.push(format!("account {} isn't passed by the interface", name)),
+            }
+        }
+    }
+    failures
+}
+
+fn is_interface(item_trait: &syn::ItemTrait) -> bool {
+    item_trait.attrs.iter().any(|attr| {
+        attr.path
+            .segments
+            .last()
+            .map_or(false, |segment| segment.ident == "interface")
+    })
+}
+
+// The accounts of an instruction in the order of `IdlInstruction::account_names`.
+fn flatten(items: &[IdlAccountItem]) -> Vec<&IdlAccount> {
+    items
+        .iter()
+        .flat_map(|item| match item {
+            IdlAccountItem::IdlAccount(acc) => vec![acc.as_ref()],
+            IdlAccountItem::IdlAccounts(accs) => flatten(&accs.accounts),
+        })
+        .collect()
+}
+
+fn type_name(ty: &IdlType) -> String {
+    serde_json::to_string(ty).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn interface_conformance() {
+        let dir = std::env::temp_dir().join("anchor_syn_conformance");
+        std::fs::create_dir_all(&dir).unwrap();
+        let root = dir.join("lib.rs");
+        std::fs::write(
+            &root,
+            r#"
+            #[interface]
+            pub trait Auth<'info, T: Accounts<'info>> {
+                fn is_authorized(ctx: Context<T>, current: u64, new: u64) -> Result<()>;
+                fn revoke(ctx: Context<T>) -> Result<()>;
+            }
+            "#,
+        )
+        .unwrap();
+        let interfaces = read(&root).unwrap();
+        assert_eq!(interfaces.len(), 1);
+
+        let account = |name: &str, is_mut: bool, is_signer: bool| json!({ "name": name, "isMut": is_mut, "isSigner": is_signer });
+        let program: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "auth",
+            "instructions": [
+                {
+                    "name": "isAuthorized",
+                    "namespace": "Auth",
+                    "accounts": [account("authority", false, true)],
+                    "args": [
+                        { "name": "current", "type": "u64" },
+                        { "name": "new", "type": "u32" }
+                    ]
+                }
+            ]
+        }))
+        .unwrap();
+        let tests = check(&program, &interfaces[0]);
+        assert_eq!(
+            tests
+                .iter()
+                .map(|test| (test.instruction.as_str(), test.failures.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "isAuthorized",
+                    vec![r#"arg new is "u32" but the interface passes new "u64""#.to_string()]
+                ),
+                ("revoke", vec!["revoke isn't implemented".to_string()]),
+            ]
+        );
+
+        // Interfaces given by IDLs also describe the accounts.
+        let interface = Interface::from_idl(
+            &serde_json::from_value(json!({
+                "version": "0.1.0",
+                "name": "auth_standard",
+                "instructions": [
+                    {
+                        "name": "isAuthorized",
+                        "namespace": "Auth",
+                        "accounts": [],
+                        "args": [
+                            { "name": "current", "type": "u64" },
+                            { "name": "new", "type": "u32" }
+                        ]
+                    }
+                ]
+            }))
+            .unwrap(),
+        );
+        let tests = check(&program, &interface);
+        assert_eq!(
+            tests[0].failures,
+            vec!["account authority isn't passed by the interface"]
+        );
+    }
+}
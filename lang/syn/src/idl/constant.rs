@@ -0,0 +1,330 @@
+use crate::idl::*;
+use crate::parser::{self, context::CrateContext};
+use std::str::FromStr;
+
+// Maximum length of the arrays built by repeat expressions, e.g. `[0; N]`.
+const MAX_REPEAT_LEN: usize = 10 * 1024;
+
+// Parses a `#[constant]`, evaluating its value for clients to use without
+// parsing Rust expressions.
+//
+// Literals, references to other constants of the crate, casts, integer
+// arithmetic, arrays, byte strings and public keys (`pubkey!` and
+// `Pubkey::new_from_array`) are evaluated. Anything else is only available as
+// the raw `value`.
+pub fn parse(ctx: &CrateContext, item: &syn::ItemConst) -> IdlConst {
+    parse_with(Some(ctx), item)
+}
+
+// Parses a `#[constant]` without the context of its crate, i.e., without
+// evaluating references to other constants.
+pub fn parse_standalone(item: &syn::ItemConst) -> IdlConst {
+    parse_with(None, item)
+}
+
+fn parse_with(ctx: Option<&CrateContext>, item: &syn::ItemConst) -> IdlConst {
+    let mut evaluator = Evaluator {
+        ctx,
+        visiting: vec![item.ident.to_string()],
+    };
+    let ty = evaluator.ty(&item.ty);
+    let evaluated = evaluator
+        .eval(&item.expr)
+        .and_then(|value| value.into_idl(&ty));
+    IdlConst {
+        name: item.ident.to_string(),
+        ty,
+        value: parser::tts_to_string(&item.expr),
+        evaluated,
+        module: None,
+    }
+}
+
+// The IDL type of a Rust type, without the context of its crate, i.e., with
+// only literal array lengths.
+pub fn idl_type(ty: &syn::Type) -> IdlType {
+    Evaluator {
+        ctx: None,
+        visiting: vec![],
+    }
+    .ty(ty)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    PublicKey([u8; 32]),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn into_idl(self, ty: &IdlType) -> Option<IdlConstValue> {
+        Some(match (self, ty) {
+            (Value::Bool(b), _) => IdlConstValue::Bool(b),
+            (Value::Int(i), _) => IdlConstValue::Int(i.to_string()),
+            (Value::Float(f), _) => IdlConstValue::Float(f),
+            (Value::String(s), _) => IdlConstValue::String(s),
+            (Value::PublicKey(key), _) => IdlConstValue::PublicKey(bs58::encode(key).into_string()),
+            (Value::Bytes(bytes), IdlType::PublicKey) => IdlConstValue::PublicKey(
+                bs58::encode(<[u8; 32]>::try_from(bytes).ok()?).into_string(),
+            ),
+            (Value::Bytes(bytes), _) => IdlConstValue::Bytes(bytes),
+            (Value::Array(values), IdlType::Bytes | IdlType::PublicKey) => {
+                Value::Bytes(bytes(values)?).into_idl(ty)?
+            }
+            (Value::Array(values), IdlType::Array(elem, _)) if **elem == IdlType::U8 => {
+                IdlConstValue::Bytes(bytes(values)?)
+            }
+            (Value::Array(values), IdlType::Array(elem, _) | IdlType::Vec(elem)) => {
+                IdlConstValue::Array(
+                    values
+                        .into_iter()
+                        .map(|value| value.into_idl(elem))
+                        .collect::<Option<_>>()?,
+                )
+            }
+            (Value::Array(values), _) => IdlConstValue::Array(
+                values
+                    .into_iter()
+                    .map(|value| value.into_idl(ty))
+                    .collect::<Option<_>>()?,
+            ),
+        })
+    }
+
+    fn int(&self) -> Option<i128> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+fn bytes(values: Vec<Value>) -> Option<Vec<u8>> {
+    values
+        .iter()
+        .map(|value| u8::try_from(value.int()?).ok())
+        .collect()
+}
+
+struct Evaluator<'a> {
+    ctx: Option<&'a CrateContext>,
+    // Constants being evaluated, to not follow cyclic references.
+    visiting: Vec<String>,
+}
+
+impl<'a> Evaluator<'a> {
+    // The IDL type of a constant, e.g. `bytes` for `&[u8]`, with array
+    // lengths evaluated.
+    fn ty(&mut self, ty: &syn::Type) -> IdlType {
+        match ty {
+            syn::Type::Reference(reference) => match &*reference.elem {
+                syn::Type::Slice(slice) => match self.ty(&slice.elem) {
+                    IdlType::U8 => IdlType::Bytes,
+                    elem => IdlType::Vec(Box::new(elem)),
+                },
+                syn::Type::Path(path) if path.path.is_ident("str") => IdlType::String,
+                elem => self.ty(elem),
+            },
+            syn::Type::Array(array) => {
+                let elem = self.ty(&array.elem);
+                match self.eval(&array.len).and_then(|len| len.int()) {
+                    Some(len) if len >= 0 => IdlType::Array(Box::new(elem), len as usize),
+                    _ => IdlType::Defined(parser::tts_to_string(ty)),
+                }
+            }
+            syn::Type::Paren(paren) => self.ty(&paren.elem),
+            syn::Type::Path(path) if path.qself.is_none() => match generic_arg(&path.path) {
+                Some(("Box", elem)) => self.ty(elem),
+                Some(("Option", elem)) => IdlType::Option(Box::new(self.ty(elem))),
+                Some(("Vec", elem)) => match self.ty(elem) {
+                    IdlType::U8 => IdlType::Bytes,
+                    elem => IdlType::Vec(Box::new(elem)),
+                },
+                _ => {
+                    let ty = parser::tts_to_string(ty);
+                    IdlType::from_str(&ty).unwrap_or(IdlType::Defined(ty))
+                }
+            },
+            _ => {
+                let ty = parser::tts_to_string(ty);
+                IdlType::from_str(&ty).unwrap_or(IdlType::Defined(ty))
+            }
+        }
+    }
+
+    fn eval(&mut self, expr: &syn::Expr) -> Option<Value> {
+        match expr {
+            syn::Expr::Lit(lit) => lit_value(&lit.lit),
+            syn::Expr::Paren(paren) => self.eval(&paren.expr),
+            syn::Expr::Group(group) => self.eval(&group.expr),
+            syn::Expr::Reference(reference) => self.eval(&reference.expr),
+            syn::Expr::Unary(unary) => {
+                let value = self.eval(&unary.expr)?;
+                match (&unary.op, value) {
+                    (syn::UnOp::Neg(_), Value::Int(i)) => i.checked_neg().map(Value::Int),
+                    (syn::UnOp::Neg(_), Value::Float(f)) => Some(Value::Float(-f)),
+                    (syn::UnOp::Not(_), Value::Bool(b)) => Some(Value::Bool(!b)),
+                    // E.g. `*b"seed"`.
+                    (syn::UnOp::Deref(_), value) => Some(value),
+                    _ => None,
+                }
+            }
+            syn::Expr::Cast(cast) => {
+                let value = self.eval(&cast.expr)?;
+                match (value, self.ty(&cast.ty)) {
+                    (Value::Int(i), IdlType::F32 | IdlType::F64) => Some(Value::Float(i as f64)),
+                    (Value::Float(f), IdlType::F32 | IdlType::F64) => Some(Value::Float(f)),
+                    (Value::Int(i), ty) => {
+                        let (min, max) = int_range(&ty)?;
+                        (min..=max).contains(&i).then(|| Value::Int(i))
+                    }
+                    _ => None,
+                }
+            }
+            syn::Expr::Binary(binary) => {
+                let left = self.eval(&binary.left)?.int()?;
+                let right = self.eval(&binary.right)?.int()?;
+                let value = match binary.op {
+                    syn::BinOp::Add(_) => left.checked_add(right),
+                    syn::BinOp::Sub(_) => left.checked_sub(right),
+                    syn::BinOp::Mul(_) => left.checked_mul(right),
+                    syn::BinOp::Div(_) => left.checked_div(right),
+                    syn::BinOp::Rem(_) => left.checked_rem(right),
+                    syn::BinOp::Shl(_) => left.checked_shl(u32::try_from(right).ok()?),
+                    syn::BinOp::Shr(_) => left.checked_shr(u32::try_from(right).ok()?),
+                    syn::BinOp::BitAnd(_) => Some(left & right),
+                    syn::BinOp::BitOr(_) => Some(left | right),
+                    syn::BinOp::BitXor(_) => Some(left ^ right),
+                    _ => None,
+                };
+                value.map(Value::Int)
+            }
+            syn::Expr::Array(array) => array
+                .elems
+                .iter()
+                .map(|elem| self.eval(elem))
+                .collect::<Option<_>>()
+                .map(Value::Array),
+            syn::Expr::Repeat(repeat) => {
+                let value = self.eval(&repeat.expr)?;
+                let len = usize::try_from(self.eval(&repeat.len)?.int()?).ok()?;
+                (len <= MAX_REPEAT_LEN).then(|| Value::Array(vec![value; len]))
+            }
+            syn::Expr::Path(path) => self.path(&path.path),
+            // `pubkey!("..")`.
+            syn::Expr::Macro(mac) if mac.mac.path.segments.last()?.ident == "pubkey" => {
+                let key = mac.mac.parse_body::<syn::LitStr>().ok()?.value();
+                let key = bs58::decode(key).into_vec().ok()?;
+                Some(Value::PublicKey(key.try_into().ok()?))
+            }
+            // `Pubkey::new_from_array([..])`.
+            syn::Expr::Call(call) => {
+                let func = match &*call.func {
+                    syn::Expr::Path(func) => parser::tts_to_string(&func.path).replace(' ', ""),
+                    _ => return None,
+                };
+                if !func.ends_with("Pubkey::new_from_array") || call.args.len() != 1 {
+                    return None;
+                }
+                let key = match self.eval(&call.args[0])? {
+                    Value::Array(values) => bytes(values)?,
+                    Value::Bytes(bytes) => bytes,
+                    _ => return None,
+                };
+                Some(Value::PublicKey(key.try_into().ok()?))
+            }
+            // `"..".as_bytes()`.
+            syn::Expr::MethodCall(call) if call.method == "as_bytes" && call.args.is_empty() => {
+                match self.eval(&call.receiver)? {
+                    Value::String(s) => Some(Value::Bytes(s.into_bytes())),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // Other constants of the crate and the bounds of integer types.
+    fn path(&mut self, path: &syn::Path) -> Option<Value> {
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        if let [ty, bound] = &segments[..] {
+            let (min, max) = int_range(&IdlType::from_str(ty).ok()?)?;
+            return match bound.as_str() {
+                "MIN" => Some(Value::Int(min)),
+                "MAX" => Some(Value::Int(max)),
+                _ => None,
+            };
+        }
+        let name = path.get_ident()?.to_string();
+        if self.visiting.contains(&name) {
+            return None;
+        }
+        let mut consts = self.ctx?.consts().filter(|c| c.ident == name);
+        let item = consts.next()?;
+        // Constants of the same name in different modules are ambiguous.
+        if consts.any(|c| c.expr != item.expr) {
+            return None;
+        }
+        self.visiting.push(name);
+        let value = self.eval(&item.expr);
+        self.visiting.pop();
+        value
+    }
+}
+
+// The name and type argument of a generic type with a single one, e.g.
+// `Vec<T>`.
+fn generic_arg(path: &syn::Path) -> Option<(&str, &syn::Type)> {
+    let segment = path.segments.last()?;
+    let args = match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => args,
+        _ => return None,
+    };
+    let name = match segment.ident.to_string().as_str() {
+        "Box" => "Box",
+        "Option" => "Option",
+        "Vec" => "Vec",
+        _ => return None,
+    };
+    match &args.args[0] {
+        syn::GenericArgument::Type(ty) => Some((name, ty)),
+        _ => None,
+    }
+}
+
+fn lit_value(lit: &syn::Lit) -> Option<Value> {
+    match lit {
+        syn::Lit::Bool(b) => Some(Value::Bool(b.value)),
+        syn::Lit::Int(i) => i.base10_parse().ok().map(Value::Int),
+        syn::Lit::Float(f) => f.base10_parse().ok().map(Value::Float),
+        syn::Lit::Str(s) => Some(Value::String(s.value())),
+        syn::Lit::ByteStr(s) => Some(Value::Bytes(s.value())),
+        syn::Lit::Byte(b) => Some(Value::Int(b.value().into())),
+        syn::Lit::Char(c) => Some(Value::String(c.value().to_string())),
+        _ => None,
+    }
+}
+
+// Bounds of the integer types that fit in an `i128`.
+fn int_range(ty: &IdlType) -> Option<(i128, i128)> {
+    Some(match ty {
+        IdlType::U8 => (0, u8::MAX.into()),
+        IdlType::I8 => (i8::MIN.into(), i8::MAX.into()),
+        IdlType::U16 => (0, u16::MAX.into()),
+        IdlType::I16 => (i16::MIN.into(), i16::MAX.into()),
+        IdlType::U32 => (0, u32::MAX.into()),
+        IdlType::I32 => (i32::MIN.into(), i32::MAX.into()),
+        IdlType::U64 => (0, u64::MAX.into()),
+        IdlType::I64 => (i64::MIN.into(), i64::MAX.into()),
+        IdlType::U128 => (0, i128::MAX),
+        IdlType::I128 => (i128::MIN, i128::MAX),
+        IdlType::Defined(ty) if ty == "usize" => (0, u64::MAX.into()),
+        IdlType::Defined(ty) if ty == "isize" => (i64::MIN.into(), i64::MAX.into()),
+        _ => return None,
+    })
+}
@@ -0,0 +1,406 @@
+use crate::idl::build::Types;
+use crate::idl::*;
+use anyhow::{anyhow, Result};
+use heck::SnakeCase;
+use std::convert::TryInto;
+use std::fmt::Write;
+
+// Field-level diffs of two values of the same type, for `assert_accounts_eq!`
+// of `anchor-lang`.
+//
+// The values are decoded from their borsh encoding as described by their IDL
+// type, so that the diff points at the fields of structs and enum variants,
+// and at the index of the items of vecs and arrays, that differ, rather than
+// showing the whole `Debug` output of both values. Nested values that are
+// equal are collapsed. Values whose type isn't fully described, e.g. with
+// fields of types not defined with Anchor's macros, are compared by their
+// encoding instead.
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+// A decoded value.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    // A value shown on a single line, e.g. a number, a key or a unit variant.
+    Leaf(String),
+    // A struct, or an enum variant with named fields.
+    Struct(String, Vec<(String, Value)>),
+    // An enum variant with unnamed fields.
+    Tuple(String, Vec<Value>),
+    // A vec or an array.
+    Seq(Vec<Value>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Sign {
+    Same,
+    Expected,
+    Actual,
+}
+
+type Lines = Vec<(Sign, String)>;
+
+// The diff of the encodings of two values of `ty`, whose user defined types
+// are in `types`, with the expected lines in red and the actual ones in green
+// if `color` is set.
+pub fn diff(ty: &IdlType, types: &Types, expected: &[u8], actual: &[u8], color: bool) -> String {
+    let mut lines = vec![
+        (Sign::Expected, "expected".to_string()),
+        (Sign::Actual, "actual".to_string()),
+    ];
+    match (decode(ty, types, expected), decode(ty, types, actual)) {
+        (Ok(expected), Ok(actual)) => diff_value(&mut lines, 0, "", &expected, &actual),
+        (Err(e), _) | (_, Err(e)) => {
+            lines.push((Sign::Same, format!("unable to decode the values: {}", e)));
+            lines.push((Sign::Expected, hex(expected)));
+            lines.push((Sign::Actual, hex(actual)));
+        }
+    }
+
+    let mut out = String::new();
+    for (sign, line) in lines {
+        let (prefix, start) = match sign {
+            Sign::Same => (' ', ""),
+            Sign::Expected => ('-', RED),
+            Sign::Actual => ('+', GREEN),
+        };
+        match color && sign != Sign::Same {
+            true => writeln!(out, "{}{}{}{}", start, prefix, line, RESET).unwrap(),
+            false => writeln!(out, "{}{}", prefix, line).unwrap(),
+        }
+    }
+    out
+}
+
+// Decodes the encoding of a value of `ty`, all of which must be read.
+fn decode(ty: &IdlType, types: &Types, data: &[u8]) -> Result<Value> {
+    let mut decoder = Decoder { types, data };
+    let value = decoder.ty(ty)?;
+    match decoder.data.len() {
+        0 => Ok(value),
+        len => Err(anyhow!("{} bytes left after decoding", len)),
+    }
+}
+
+struct Decoder<'a> {
+    types: &'a Types,
+    data: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn ty(&mut self, ty: &IdlType) -> Result<Value> {
+        let leaf = |s: String| Ok(Value::Leaf(s));
+        match ty {
+            IdlType::Bool => match self.take(1)?[0] {
+                0 => leaf("false".to_string()),
+                1 => leaf("true".to_string()),
+                b => Err(anyhow!("Invalid bool: {}", b)),
+            },
+            IdlType::U8 => leaf(self.take(1)?[0].to_string()),
+            IdlType::I8 => leaf((self.take(1)?[0] as i8).to_string()),
+            IdlType::U16 => leaf(u16::from_le_bytes(self.array()?).to_string()),
+            IdlType::I16 => leaf(i16::from_le_bytes(self.array()?).to_string()),
+            IdlType::U32 => leaf(u32::from_le_bytes(self.array()?).to_string()),
+            IdlType::I32 => leaf(i32::from_le_bytes(self.array()?).to_string()),
+            IdlType::U64 => leaf(u64::from_le_bytes(self.array()?).to_string()),
+            IdlType::I64 => leaf(i64::from_le_bytes(self.array()?).to_string()),
+            IdlType::U128 => leaf(u128::from_le_bytes(self.array()?).to_string()),
+            IdlType::I128 => leaf(i128::from_le_bytes(self.array()?).to_string()),
+            IdlType::F32 => leaf(format!("{:?}", f32::from_le_bytes(self.array()?))),
+            IdlType::F64 => leaf(format!("{:?}", f64::from_le_bytes(self.array()?))),
+            IdlType::Float(float) => match float.bits {
+                32 => self.ty(&IdlType::F32),
+                _ => self.ty(&IdlType::F64),
+            },
+            IdlType::Bytes => {
+                let len = self.len()?;
+                let bytes = self.take(len)?;
+                Ok(Value::Seq(
+                    bytes.iter().map(|b| Value::Leaf(b.to_string())).collect(),
+                ))
+            }
+            IdlType::String => {
+                let len = self.len()?;
+                let s = std::str::from_utf8(self.take(len)?)?;
+                leaf(format!("{:?}", s))
+            }
+            IdlType::PublicKey => leaf(bs58::encode(self.take(32)?).into_string()),
+            IdlType::Defined(name) => self.defined(name),
+            IdlType::Option(ty) => match self.take(1)?[0] {
+                0 => leaf("None".to_string()),
+                1 => match self.ty(ty)? {
+                    Value::Leaf(s) => leaf(format!("Some({})", s)),
+                    value => Ok(value),
+                },
+                b => Err(anyhow!("Invalid option tag: {}", b)),
+            },
+            IdlType::Vec(ty) => {
+                let len = self.len()?;
+                let items = (0..len).map(|_| self.ty(ty)).collect::<Result<_>>()?;
+                Ok(Value::Seq(items))
+            }
+            IdlType::Array(ty, len) => {
+                let items = (0..*len).map(|_| self.ty(ty)).collect::<Result<_>>()?;
+                Ok(Value::Seq(items))
+            }
+        }
+    }
+
+    fn defined(&mut self, name: &str) -> Result<Value> {
+        let types = self.types;
+        let ty_def = types
+            .get(name)
+            .ok_or_else(|| anyhow!("Type {} is not described", name))?;
+        match &ty_def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                Ok(Value::Struct(name.to_string(), self.fields(fields)?))
+            }
+            IdlTypeDefinitionTy::Enum { variants } => {
+                let index = self.take(1)?[0];
+                let variant = variants
+                    .get(index as usize)
+                    .ok_or_else(|| anyhow!("Invalid variant of {}: {}", name, index))?;
+                let path = format!("{}::{}", name, variant.name);
+                match &variant.fields {
+                    None => Ok(Value::Leaf(path)),
+                    Some(EnumFields::Named(fields)) => {
+                        Ok(Value::Struct(path, self.fields(fields)?))
+                    }
+                    Some(EnumFields::Tuple(tys)) => {
+                        let items = tys.iter().map(|ty| self.ty(ty)).collect::<Result<_>>()?;
+                        Ok(Value::Tuple(path, items))
+                    }
+                }
+            }
+        }
+    }
+
+    fn fields(&mut self, fields: &[IdlField]) -> Result<Vec<(String, Value)>> {
+        fields
+            .iter()
+            .map(|f| Ok((f.name.to_snake_case(), self.ty(&f.ty)?)))
+            .collect()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        if self.data.len() < len {
+            return Err(anyhow!("Unexpected end of data"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+}
+
+// Diffs two values, labeled by the field or index they're at, e.g. `count: `.
+fn diff_value(lines: &mut Lines, indent: usize, label: &str, expected: &Value, actual: &Value) {
+    let pad = "    ".repeat(indent);
+    if expected == actual {
+        lines.push((
+            Sign::Same,
+            format!("{}{}{}", pad, label, collapsed(expected)),
+        ));
+        return;
+    }
+    match (expected, actual) {
+        (Value::Struct(name, fields), Value::Struct(actual_name, actual_fields))
+            if name == actual_name =>
+        {
+            lines.push((Sign::Same, format!("{}{}{} {{", pad, label, name)));
+            for ((field, expected), (_, actual)) in fields.iter().zip(actual_fields) {
+                diff_value(lines, indent + 1, &format!("{}: ", field), expected, actual);
+            }
+            lines.push((Sign::Same, format!("{}}}", pad)));
+        }
+        (Value::Tuple(name, items), Value::Tuple(actual_name, actual_items))
+            if name == actual_name =>
+        {
+            lines.push((Sign::Same, format!("{}{}{}(", pad, label, name)));
+            diff_items(lines, indent + 1, items, actual_items);
+            lines.push((Sign::Same, format!("{})", pad)));
+        }
+        (Value::Seq(items), Value::Seq(actual_items)) => {
+            lines.push((Sign::Same, format!("{}{}[", pad, label)));
+            diff_items(lines, indent + 1, items, actual_items);
+            lines.push((Sign::Same, format!("{}]", pad)));
+        }
+        _ => {
+            render(lines, Sign::Expected, indent, label, expected);
+            render(lines, Sign::Actual, indent, label, actual);
+        }
+    }
+}
+
+// Diffs the items of two sequences, labeled by their index. Runs of equal
+// items are collapsed.
+fn diff_items(lines: &mut Lines, indent: usize, expected: &[Value], actual: &[Value]) {
+    let pad = "    ".repeat(indent);
+    let mut equal = 0;
+    for i in 0..expected.len().max(actual.len()) {
+        let label = format!("[{}]: ", i);
+        match (expected.get(i), actual.get(i)) {
+            (Some(expected), Some(actual)) if expected == actual => {
+                equal += 1;
+                continue;
+            }
+            (Some(expected), Some(actual)) => {
+                flush_equal(lines, &pad, &mut equal);
+                diff_value(lines, indent, &label, expected, actual)
+            }
+            (Some(expected), None) => {
+                flush_equal(lines, &pad, &mut equal);
+                render(lines, Sign::Expected, indent, &label, expected)
+            }
+            (None, Some(actual)) => {
+                flush_equal(lines, &pad, &mut equal);
+                render(lines, Sign::Actual, indent, &label, actual)
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    flush_equal(lines, &pad, &mut equal);
+}
+
+fn flush_equal(lines: &mut Lines, pad: &str, equal: &mut usize) {
+    match *equal {
+        0 => {}
+        1 => lines.push((Sign::Same, format!("{}.. 1 equal item", pad))),
+        n => lines.push((Sign::Same, format!("{}.. {} equal items", pad, n))),
+    }
+    *equal = 0;
+}
+
+// Renders a whole value as the expected or actual one.
+fn render(lines: &mut Lines, sign: Sign, indent: usize, label: &str, value: &Value) {
+    let pad = "    ".repeat(indent);
+    match value {
+        Value::Leaf(s) => lines.push((sign, format!("{}{}{}", pad, label, s))),
+        Value::Struct(name, fields) => {
+            lines.push((sign, format!("{}{}{} {{", pad, label, name)));
+            for (field, value) in fields {
+                render(lines, sign, indent + 1, &format!("{}: ", field), value);
+            }
+            lines.push((sign, format!("{}}}", pad)));
+        }
+        Value::Tuple(name, items) => {
+            lines.push((sign, format!("{}{}{}(", pad, label, name)));
+            for (i, value) in items.iter().enumerate() {
+                render(lines, sign, indent + 1, &format!("[{}]: ", i), value);
+            }
+            lines.push((sign, format!("{})", pad)));
+        }
+        Value::Seq(items) => {
+            lines.push((sign, format!("{}{}[", pad, label)));
+            for (i, value) in items.iter().enumerate() {
+                render(lines, sign, indent + 1, &format!("[{}]: ", i), value);
+            }
+            lines.push((sign, format!("{}]", pad)));
+        }
+    }
+}
+
+// A value on a single line, with its nested values elided.
+fn collapsed(value: &Value) -> String {
+    match value {
+        Value::Leaf(s) => s.clone(),
+        Value::Struct(name, _) => format!("{} {{ .. }}", name),
+        Value::Tuple(name, _) => format!("{}(..)", name),
+        Value::Seq(items) if items.is_empty() => "[]".to_string(),
+        Value::Seq(items) => format!("[..] ({} items)", items.len()),
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_values() {
+        let types: Types = serde_json::from_value(serde_json::json!({
+            "Vault": {
+                "name": "Vault",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "authority", "type": "publicKey" },
+                        { "name": "balance", "type": "u64" },
+                        { "name": "history", "type": { "vec": "i16" } },
+                        { "name": "mode", "type": { "defined": "Mode" } },
+                        { "name": "label", "type": { "option": "string" } }
+                    ]
+                }
+            },
+            "Mode": {
+                "name": "Mode",
+                "type": {
+                    "kind": "enum",
+                    "variants": [
+                        { "name": "Open" },
+                        { "name": "Locked", "fields": [{ "name": "until", "type": "i64" }] }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+        let ty = IdlType::Defined("Vault".to_string());
+        let vault = |balance: u64, history: &[i16], until: i64, label: Option<&str>| {
+            let mut data = [7; 32].to_vec();
+            data.extend(balance.to_le_bytes());
+            data.extend((history.len() as u32).to_le_bytes());
+            for n in history {
+                data.extend(n.to_le_bytes());
+            }
+            data.push(1);
+            data.extend(until.to_le_bytes());
+            match label {
+                None => data.push(0),
+                Some(label) => {
+                    data.push(1);
+                    data.extend((label.len() as u32).to_le_bytes());
+                    data.extend(label.as_bytes());
+                }
+            }
+            data
+        };
+
+        let expected = vault(10, &[1, 2, 3, 4], 100, Some("main"));
+        let actual = vault(12, &[1, 2, -3, 4, 5], 100, None);
+        assert_eq!(
+            diff(&ty, &types, &expected, &actual, false),
+            r#"-expected
++actual
+ Vault {
+     authority: US517G5965aydkZ46HS38QLi7UQiSojurfbQfKCELFx
+-    balance: 10
++    balance: 12
+     history: [
+         .. 2 equal items
+-        [2]: 3
++        [2]: -3
+         .. 1 equal item
++        [4]: 5
+     ]
+     mode: Mode::Locked { .. }
+-    label: Some("main")
++    label: None
+ }
+"#
+        );
+        assert!(diff(&ty, &types, &expected, &actual[..40], true)
+            .contains("\x1b[31m-0707070707070707070707070707070707070707070707070707070707070707"));
+    }
+}
@@ -0,0 +1,222 @@
+use crate::codegen::program::common::sighash;
+use crate::idl::Idl;
+
+// Collisions between the discriminators of the programs of a workspace.
+//
+// Shared indexers decode accounts and events by their discriminator alone, so
+// two accounts, or two events, of any programs of the workspace must not start
+// with the same bytes. Instruction data always comes with the program it's
+// sent to, so instructions only collide within a program. Discriminators of
+// different lengths, e.g. custom ones, collide when one is a prefix of the
+// other.
+//
+// The same definition shared by several programs, e.g. an account defined in a
+// common crate, decodes the same way whichever program it comes from, and so
+// doesn't collide with itself.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemKind {
+    Account,
+    Event,
+    Instruction,
+}
+
+impl std::fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItemKind::Account => write!(f, "account"),
+            ItemKind::Event => write!(f, "event"),
+            ItemKind::Instruction => write!(f, "instruction"),
+        }
+    }
+}
+
+// An item of a program, along with its discriminator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub program: String,
+    pub kind: ItemKind,
+    pub name: String,
+    pub discriminator: Vec<u8>,
+    // The item's definition, to tell apart definitions shared by programs.
+    definition: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collision {
+    pub first: Item,
+    pub second: Item,
+}
+
+impl std::fmt::Display for Collision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}::{} {:?} collides with {} {}::{} {:?}",
+            self.first.kind,
+            self.first.program,
+            self.first.name,
+            self.first.discriminator,
+            self.second.kind,
+            self.second.program,
+            self.second.name,
+            self.second.discriminator,
+        )
+    }
+}
+
+// The discriminated items of an IDL, i.e. its accounts, events and
+// instructions. Accounts without a discriminator, e.g. of native programs
+// described by Shank, are left out.
+pub fn items(program: &str, idl: &Idl) -> Vec<Item> {
+    let item = |kind, name: &str, discriminator, definition: String| Item {
+        program: program.to_string(),
+        kind,
+        name: name.to_string(),
+        discriminator,
+        definition,
+    };
+    let accounts = idl.accounts.iter().map(|acc| {
+        item(
+            ItemKind::Account,
+            &acc.name,
+            acc.account_discriminator(),
+            serde_json::to_string(&acc.ty).unwrap_or_default(),
+        )
+    });
+    let events = idl.events.iter().flatten().map(|event| {
+        item(
+            ItemKind::Event,
+            &event.name,
+            sighash("event", &event.name).to_vec(),
+            serde_json::to_string(&event.fields).unwrap_or_default(),
+        )
+    });
+    let instructions = idl.instructions.iter().map(|ix| {
+        item(
+            ItemKind::Instruction,
+            &ix.name,
+            ix.discriminator(),
+            String::new(),
+        )
+    });
+    accounts
+        .chain(events)
+        .chain(instructions)
+        .filter(|item| !item.discriminator.is_empty())
+        .collect()
+}
+
+// The collisions between the items of the given programs, by the name of the
+// program, in the order of the programs and of their items.
+pub fn collisions<'a>(programs: impl IntoIterator<Item = (&'a str, &'a Idl)>) -> Vec<Collision> {
+    let items: Vec<Item> = programs
+        .into_iter()
+        .flat_map(|(program, idl)| items(program, idl))
+        .collect();
+    let mut collisions = vec![];
+    for (i, first) in items.iter().enumerate() {
+        for second in &items[i + 1..] {
+            if collide(first, second) {
+                collisions.push(Collision {
+                    first: first.clone(),
+                    second: second.clone(),
+                });
+            }
+        }
+    }
+    collisions
+}
+
+fn collide(first: &Item, second: &Item) -> bool {
+    if first.kind != second.kind {
+        return false;
+    }
+    if first.kind == ItemKind::Instruction && first.program != second.program {
+        return false;
+    }
+    if first.program != second.program
+        && first.name == second.name
+        && first.definition == second.definition
+    {
+        return false;
+    }
+    first.discriminator.starts_with(&second.discriminator)
+        || second.discriminator.starts_with(&first.discriminator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn idl(name: &str, accounts: serde_json::Value, instructions: serde_json::Value) -> Idl {
+        serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": name,
+            "instructions": instructions,
+            "accounts": accounts,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn workspace_collisions() {
+        let config = |fields: serde_json::Value| {
+            json!({
+                "name": "Config",
+                "type": { "kind": "struct", "fields": fields }
+            })
+        };
+        let ix = |name: &str, discriminator: &[u8]| {
+            json!({
+                "name": name,
+                "accounts": [],
+                "args": [],
+                "discriminator": discriminator
+            })
+        };
+        let pool = json!({
+            "name": "Pool",
+            "type": { "kind": "struct", "fields": [] },
+            "discriminator": sighash("account", "Config")[..4].to_vec()
+        });
+        let rate = json!([{ "name": "rate", "type": "u64" }]);
+        let vault = idl(
+            "vault",
+            json!([config(json!([]))]),
+            json!([ix("deposit", &[1]), ix("withdraw", &[1, 2])]),
+        );
+        let staking = idl(
+            "staking",
+            json!([config(json!([])), pool]),
+            json!([ix("stake", &[1])]),
+        );
+        let lending = idl("lending", json!([config(rate)]), json!([]));
+
+        let collisions: Vec<String> = collisions(vec![
+            ("vault", &vault),
+            ("staking", &staking),
+            ("lending", &lending),
+        ])
+        .iter()
+        .map(|c| {
+            format!(
+                "{} {}::{} {}::{}",
+                c.first.kind, c.first.program, c.first.name, c.second.program, c.second.name
+            )
+        })
+        .collect();
+        assert_eq!(
+            collisions,
+            vec![
+                "account vault::Config staking::Pool",
+                "account vault::Config lending::Config",
+                "instruction vault::deposit vault::withdraw",
+                "account staking::Config staking::Pool",
+                "account staking::Config lending::Config",
+                "account staking::Pool lending::Config",
+            ]
+        );
+    }
+}
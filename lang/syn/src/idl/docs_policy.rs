@@ -0,0 +1,183 @@
+use crate::idl::*;
+use crate::parser::docs::without_check_comments;
+
+impl Idl {
+    // Strips the docs left out by the policy.
+    pub fn apply_docs_policy(&mut self, policy: &DocsPolicy) {
+        let docs = Docs { policy };
+        docs.item(&mut self.docs);
+        for ix in &mut self.instructions {
+            docs.instruction(ix);
+        }
+        if let Some(fallback) = &mut self.fallback {
+            docs.item(&mut fallback.docs);
+        }
+        if let Some(state) = &mut self.state {
+            docs.ty_def(&mut state.strct);
+            for method in &mut state.methods {
+                docs.instruction(method);
+            }
+        }
+        for ty_def in self.accounts.iter_mut().chain(self.types.iter_mut()) {
+            docs.ty_def(ty_def);
+        }
+        for error in self.errors.iter_mut().flatten() {
+            docs.item(&mut error.docs);
+        }
+    }
+}
+
+struct Docs<'a> {
+    policy: &'a DocsPolicy,
+}
+
+impl Docs<'_> {
+    fn item(&self, docs: &mut Option<Vec<String>>) {
+        self.apply(docs, self.policy.items);
+    }
+
+    fn field(&self, docs: &mut Option<Vec<String>>) {
+        self.apply(docs, self.policy.fields);
+    }
+
+    fn instruction(&self, ix: &mut IdlInstruction) {
+        self.item(&mut ix.docs);
+        self.fields(&mut ix.args);
+        self.accounts(&mut ix.accounts);
+    }
+
+    fn accounts(&self, items: &mut [IdlAccountItem]) {
+        for item in items {
+            match item {
+                IdlAccountItem::IdlAccount(acc) => self.field(&mut acc.docs),
+                IdlAccountItem::IdlAccounts(accs) => self.accounts(&mut accs.accounts),
+            }
+        }
+    }
+
+    fn fields(&self, fields: &mut [IdlField]) {
+        for field in fields {
+            self.field(&mut field.docs);
+        }
+    }
+
+    fn ty_def(&self, ty_def: &mut IdlTypeDefinition) {
+        self.item(&mut ty_def.docs);
+        match &mut ty_def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => self.fields(fields),
+            IdlTypeDefinitionTy::Enum { variants } => {
+                for variant in variants {
+                    if let Some(EnumFields::Named(fields)) = &mut variant.fields {
+                        self.fields(fields);
+                    }
+                }
+            }
+        }
+    }
+
+    fn apply(&self, docs: &mut Option<Vec<String>>, keep: bool) {
+        let mut lines = match docs.take() {
+            Some(lines) if keep => lines,
+            _ => return,
+        };
+        if !self.policy.check_comments {
+            lines = match without_check_comments(&lines) {
+                Some(lines) => lines,
+                None => return,
+            };
+        }
+        if let Some(max_length) = self.policy.max_length {
+            lines = truncate(lines, max_length);
+        }
+        *docs = Some(lines);
+    }
+}
+
+// Cuts the docs to `max_length` characters, not counting line breaks.
+fn truncate(lines: Vec<String>, max_length: usize) -> Vec<String> {
+    let mut remaining = max_length;
+    let mut truncated = vec![];
+    for line in lines {
+        let len = line.chars().count();
+        if len > remaining {
+            let cut: String = line.chars().take(remaining).collect();
+            truncated.push(format!("{}...", cut.trim_end()));
+            break;
+        }
+        remaining -= len;
+        truncated.push(line);
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn docs_policy() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "docs": ["The vault program."],
+            "instructions": [{
+                "name": "withdraw",
+                "docs": ["Withdraws the fees.", "", "Only the authority can withdraw them."],
+                "accounts": [{
+                    "name": "vault",
+                    "isMut": true,
+                    "isSigner": false,
+                    "docs": ["The vault.", "", "CHECK: the seeds are", "checked by the program."]
+                }],
+                "args": [{ "name": "amount", "docs": ["In lamports."], "type": "u64" }]
+            }],
+            "errors": [{ "code": 6000, "name": "Paused", "docs": ["CHECK: internal."] }]
+        }))
+        .unwrap();
+        let docs = |policy: DocsPolicy| {
+            let mut idl = idl.clone();
+            idl.apply_docs_policy(&policy);
+            let ix = &idl.instructions[0];
+            let account = match &ix.accounts[0] {
+                IdlAccountItem::IdlAccount(account) => account.docs.clone(),
+                _ => unreachable!(),
+            };
+            (
+                ix.docs.clone(),
+                account,
+                ix.args[0].docs.clone(),
+                idl.errors.unwrap()[0].docs.clone(),
+            )
+        };
+        let lines = |lines: &[&str]| Some(lines.iter().map(|l| l.to_string()).collect());
+
+        assert_eq!(
+            docs(DocsPolicy::default()),
+            (
+                lines(&[
+                    "Withdraws the fees.",
+                    "",
+                    "Only the authority can withdraw them."
+                ]),
+                lines(&["The vault."]),
+                lines(&["In lamports."]),
+                None,
+            )
+        );
+        assert_eq!(
+            docs(DocsPolicy {
+                fields: false,
+                check_comments: true,
+                max_length: Some(24),
+                ..DocsPolicy::default()
+            }),
+            (
+                lines(&["Withdraws the fees.", "", "Only..."]),
+                None,
+                None,
+                lines(&["CHECK: internal."]),
+            )
+        );
+        assert_eq!(docs(DocsPolicy::none()), (None, None, None, None));
+    }
+}
@@ -1,26 +1,80 @@
+use crate::codegen::program::common::sighash;
 use crate::idl::*;
-use crate::parser::context::CrateContext;
-use crate::parser::{self, accounts, docs, error, program};
-use crate::Ty;
-use crate::{AccountField, AccountsStruct, StateIx};
-use anyhow::Result;
-use heck::MixedCase;
+use crate::parser::arg_constraints::ArgConstraint;
+use crate::parser::context::{CrateContext, ModuleContext};
+use crate::parser::zero_copy::{self, ZeroCopy};
+use crate::parser::{
+    self, accounts, checksum, discriminator, docs, error, idl_name, program, version,
+};
+use crate::{AccountField, AccountsStruct, ConstraintGroup, InitKind, ProgramArgs, State, StateIx};
+use crate::{SysvarTy, Ty};
+use anyhow::{anyhow, Result};
+use heck::{CamelCase, MixedCase, SnakeCase};
+use proc_macro2::{TokenStream, TokenTree};
 use quote::ToTokens;
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 const DERIVE_NAME: &str = "Accounts";
+const FRAGMENT_NAME: &str = "accounts_fragment";
 // TODO: share this with `anchor_lang` crate.
 const ERROR_CODE_OFFSET: u32 = 6000;
 
-// Parse an entire interface file.
+// The IDL of a program along with the issues found while generating it.
+#[derive(Debug)]
+pub struct ParseOutput {
+    pub idl: Idl,
+    pub warnings: Vec<IdlWarning>,
+}
+
+// Options of `parse`.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    // Version of the program, written to the IDL.
+    pub version: String,
+    // Whether the seeds of PDAs are written to the IDL.
+    pub seeds_feature: bool,
+    pub docs_policy: DocsPolicy,
+    // Whether the crate is checked against the safety rules before parsing.
+    pub safety_checks: bool,
+    pub float_policy: FloatPolicy,
+    // Whether the accounts of composite fields are listed in place of them,
+    // prefixed with the field's name, e.g. `marketBids` for the `bids` account
+    // of a `market` field, for clients that can't describe nested account
+    // groups.
+    pub flatten_accounts: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            version: "0.0.0".to_string(),
+            seeds_feature: false,
+            docs_policy: DocsPolicy::default(),
+            safety_checks: false,
+            float_policy: FloatPolicy::default(),
+            flatten_accounts: false,
+        }
+    }
+}
+
+// Parses the IDL of the program of the crate with the root file `filename`,
+// or `None` if the crate has no `#[program]` module. Composite accounts whose
+// `Accounts` struct isn't in the crate are resolved from `external_accounts`.
 pub fn parse(
     filename: impl AsRef<Path>,
-    version: String,
-    seeds_feature: bool,
-    no_docs: bool,
-    safety_checks: bool,
-) -> Result<Option<Idl>> {
+    options: &ParseOptions,
+    external_accounts: &ExternalAccounts,
+) -> Result<Option<ParseOutput>> {
+    let ParseOptions {
+        seeds_feature,
+        safety_checks,
+        float_policy,
+        flatten_accounts,
+        ..
+    } = *options;
+    let docs_policy = &options.docs_policy;
+    let no_docs = docs_policy.is_none();
     let ctx = CrateContext::parse(filename)?;
     if safety_checks {
         ctx.safety_checks()?;
@@ -30,7 +84,11 @@ pub fn parse(
         None => return Ok(None),
         Some(m) => m,
     };
+    let program_path = format!("crate::{}", program_mod.ident);
+    let args = program_args(&program_mod.attrs)?;
     let mut p = program::parse(program_mod)?;
+    p.discriminator_len = args.discriminator_len.unwrap_or(discriminator::MAX_LEN);
+    let mut warnings = vec![];
 
     if no_docs {
         p.docs = None;
@@ -41,7 +99,25 @@ pub fn parse(
 
     let accs = parse_account_derives(&ctx);
 
-    let state = match p.state {
+    let interface_ixs = p
+        .state
+        .as_ref()
+        .map(|state| {
+            interface_instructions(
+                &ctx,
+                &program_path,
+                state,
+                &accs,
+                external_accounts,
+                seeds_feature,
+                no_docs,
+                flatten_accounts,
+            )
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let mut state = match p.state {
         None => None,
         Some(state) => match state.ctor_and_anchor {
             None => None, // State struct defined but no implementation
@@ -69,6 +145,8 @@ pub fn parse(
                                             name: arg.name.to_string().to_mixed_case(),
                                             docs: doc,
                                             ty,
+                                            constraints: vec![],
+                                            aliases: vec![],
                                         }
                                     })
                                     .collect::<Vec<_>>();
@@ -78,8 +156,16 @@ pub fn parse(
                                     &ctx,
                                     accounts_strct,
                                     &accs,
+                                    external_accounts,
                                     seeds_feature,
                                     no_docs,
+                                    flatten_accounts,
+                                );
+                                let signers = instruction_signers(
+                                    &accounts,
+                                    accounts_strct,
+                                    &accs,
+                                    external_accounts,
                                 );
                                 IdlInstruction {
                                     name,
@@ -87,6 +173,16 @@ pub fn parse(
                                     accounts,
                                     args,
                                     returns: None,
+                                    namespace: None,
+                                    discriminator: None,
+                                    guards: vec![],
+                                    remaining_accounts: None,
+                                    sunset: None,
+                                    compute_budget: None,
+                                    distinct_accounts: vec![],
+                                    admin: false,
+                                    batch: vec![],
+                                    signers,
                                 }
                             })
                             .collect::<Vec<_>>()
@@ -121,20 +217,41 @@ pub fn parse(
                                     name: parser::tts_to_string(&arg_typed.pat).to_mixed_case(),
                                     docs: doc,
                                     ty,
+                                    constraints: vec![],
+                                    aliases: vec![],
                                 }
                             }
                             _ => panic!("Invalid syntax"),
                         })
                         .collect();
                     let accounts_strct = accs.get(&anchor_ident.to_string()).unwrap();
-                    let accounts =
-                        idl_accounts(&ctx, accounts_strct, &accs, seeds_feature, no_docs);
+                    let accounts = idl_accounts(
+                        &ctx,
+                        accounts_strct,
+                        &accs,
+                        external_accounts,
+                        seeds_feature,
+                        no_docs,
+                        flatten_accounts,
+                    );
+                    let signers =
+                        instruction_signers(&accounts, accounts_strct, &accs, external_accounts);
                     IdlInstruction {
                         name,
                         docs: None,
                         accounts,
                         args,
                         returns: None,
+                        namespace: None,
+                        discriminator: None,
+                        guards: vec![],
+                        remaining_accounts: None,
+                        sunset: None,
+                        compute_budget: None,
+                        distinct_accounts: vec![],
+                        admin: false,
+                        batch: vec![],
+                        signers,
                     }
                 };
 
@@ -158,6 +275,8 @@ pub fn parse(
                                     name: f.ident.as_ref().unwrap().to_string().to_mixed_case(),
                                     docs: doc,
                                     ty,
+                                    constraints: vec![],
+                                    aliases: vec![],
                                 }
                             })
                             .collect::<Vec<IdlField>>(),
@@ -167,6 +286,14 @@ pub fn parse(
                         name: state.name,
                         docs: None,
                         ty: IdlTypeDefinitionTy::Struct { fields },
+                        version: None,
+                        migrations: vec![],
+                        permissions: vec![],
+                        discriminator: None,
+                        state_machine: None,
+                        size: None,
+                        checksum: None,
+                        serialization: IdlSerialization::Borsh,
                     }
                 };
 
@@ -178,15 +305,21 @@ pub fn parse(
     let error_codes = error.as_ref().map(|e| {
         e.codes
             .iter()
-            .map(|code| IdlErrorCode {
+            .zip(&e.raw_enum.variants)
+            .map(|(code, variant)| IdlErrorCode {
                 code: ERROR_CODE_OFFSET + code.id,
                 name: code.ident.to_string(),
                 msg: code.msg.clone(),
+                docs: if !no_docs {
+                    docs::parse(&variant.attrs)
+                } else {
+                    None
+                },
             })
             .collect::<Vec<IdlErrorCode>>()
     });
 
-    let instructions = p
+    let mut instructions = p
         .ixs
         .iter()
         .map(|ix| {
@@ -199,34 +332,101 @@ pub fn parse(
                     } else {
                         None
                     };
-                    IdlField {
+                    Ok(IdlField {
                         name: arg.name.to_string().to_mixed_case(),
                         docs: doc,
-                        ty: to_idl_type(&ctx, &arg.raw_arg.ty),
-                    }
+                        ty: to_idl_type(&ctx, &program_path, &arg.raw_arg.ty)?,
+                        constraints: arg_constraints(&arg.constraints),
+                        aliases: arg.aliases.clone(),
+                    })
                 })
-                .collect::<Vec<_>>();
+                .collect::<Result<Vec<_>>>()?;
             // todo: don't unwrap
             let accounts_strct = accs.get(&ix.anchor_ident.to_string()).unwrap();
-            let accounts = idl_accounts(&ctx, accounts_strct, &accs, seeds_feature, no_docs);
+            let accounts = idl_accounts(
+                &ctx,
+                accounts_strct,
+                &accs,
+                external_accounts,
+                seeds_feature,
+                no_docs,
+                flatten_accounts,
+            );
             let ret_type_str = ix.returns.ty.to_token_stream().to_string();
             let returns = match ret_type_str.as_str() {
                 "()" => None,
                 _ => Some(ret_type_str.parse().unwrap()),
             };
-            IdlInstruction {
+            if !no_docs {
+                check_docs(&ix.ident.to_string(), &ix.raw_method.attrs, &mut warnings);
+            }
+            let signers = instruction_signers(&accounts, accounts_strct, &accs, external_accounts);
+            Ok(IdlInstruction {
                 name: ix.ident.to_string().to_mixed_case(),
                 docs: ix.docs.clone(),
                 accounts,
                 args,
                 returns,
-            }
+                namespace: ix.namespace.clone(),
+                discriminator: None,
+                guards: guards(&ix.raw_method.attrs)?,
+                remaining_accounts: remaining_accounts(accounts_strct),
+                sunset: sunset(&ix.raw_method.attrs)?,
+                compute_budget: compute_budget(&ix.raw_method.attrs)?,
+                distinct_accounts: distinct_accounts(accounts_strct),
+                admin: is_admin(&ix.raw_method.attrs),
+                batch: vec![],
+                signers,
+            })
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>>>()?;
+    let batch = batch_instruction(&p.ixs);
+    instructions.extend(batch.clone());
+    permissions::check_writes(&p.ixs, &accs, &parse_accounts(&ctx), &mut warnings)?;
+
+    // Methods without a receiver are plain instructions, the others take the
+    // state account like any other state method.
+    let (state_interface_ixs, interface_ixs): (Vec<_>, Vec<_>) = interface_ixs
+        .into_iter()
+        .partition(|(has_receiver, _)| *has_receiver);
+    instructions.extend(interface_ixs.into_iter().map(|(_, ix)| ix));
+    if let Some(state) = state.as_mut() {
+        state
+            .methods
+            .extend(state_interface_ixs.into_iter().map(|(_, ix)| ix));
+    }
+
+    let instruction_enum = if p.ixs.is_empty() {
+        None
+    } else {
+        Some(IdlInstructionEnum {
+            name: "ProgramInstruction".to_string(),
+            variants: p
+                .ixs
+                .iter()
+                .map(|ix| {
+                    let name = ix.ident.to_string();
+                    IdlInstructionEnumVariant {
+                        name: name.to_camel_case(),
+                        instruction: name.to_mixed_case(),
+                        discriminator: sighash(ix.sighash_namespace(), &name)
+                            [..p.discriminator_len]
+                            .to_vec(),
+                    }
+                })
+                .chain(
+                    batch
+                        .as_ref()
+                        .map(|ix| batch_enum_variant(ix, p.discriminator_len)),
+                )
+                .collect(),
+        })
+    };
 
+    let transports = event_transports(&ctx);
     let events = parse_events(&ctx)
         .iter()
-        .map(|e: &&syn::ItemStruct| {
+        .map(|(module, e)| {
             let fields = match &e.fields {
                 syn::Fields::Named(n) => n,
                 _ => panic!("Event fields must be named"),
@@ -235,34 +435,44 @@ pub fn parse(
                 .named
                 .iter()
                 .map(|f: &syn::Field| {
-                    let index = match f.attrs.get(0) {
-                        None => false,
-                        Some(i) => parser::tts_to_string(&i.path) == "index",
-                    };
-                    IdlEventField {
+                    let index = parser::event::index(&f.attrs)?;
+                    Ok(IdlEventField {
                         name: f.ident.clone().unwrap().to_string().to_mixed_case(),
-                        ty: to_idl_type(&ctx, &f.ty),
-                        index,
-                    }
+                        ty: to_idl_type(&ctx, &module.path(), &f.ty)?,
+                        index: index.is_some(),
+                        unique: index.map_or(false, |index| index.unique),
+                    })
                 })
-                .collect::<Vec<IdlEventField>>();
+                .collect::<Result<Vec<IdlEventField>>>()?;
 
-            IdlEvent {
-                name: e.ident.to_string(),
+            let name = e.ident.to_string();
+            let emitted = transports.get(&name);
+            Ok(IdlEvent {
+                transports: [IdlEventTransport::Log, IdlEventTransport::Cpi]
+                    .into_iter()
+                    .filter(|transport| emitted.map_or(false, |e| e.contains(transport)))
+                    .collect(),
+                name,
                 fields,
-            }
+            })
         })
-        .collect::<Vec<IdlEvent>>();
+        .collect::<Result<Vec<IdlEvent>>>()?;
+    let external_messages = parse_external_messages(&ctx, &instructions, no_docs)?;
 
     // All user defined types.
     let mut accounts = vec![];
     let mut types = vec![];
-    let ty_defs = parse_ty_defs(&ctx, no_docs)?;
+    let ty_defs = parse_ty_defs(&ctx, p.discriminator_len, no_docs, &mut warnings)?;
 
     let account_structs = parse_accounts(&ctx);
     let account_names: HashSet<String> = account_structs
         .iter()
         .map(|a| a.ident.to_string())
+        .chain(
+            parse_account_enums(&ctx)
+                .iter()
+                .map(|a| a.ident.to_string()),
+        )
         .collect::<HashSet<_>>();
 
     let error_name = error.map(|e| e.name).unwrap_or_else(|| "".to_string());
@@ -273,27 +483,39 @@ pub fn parse(
         if ty_def.name != error_name {
             if account_names.contains(&ty_def.name) {
                 accounts.push(ty_def);
-            } else if !events.iter().any(|e| e.name == ty_def.name) {
+            } else if !events.iter().any(|e| e.name == ty_def.name)
+                && !external_messages.iter().any(|m| m.name == ty_def.name)
+            {
                 types.push(ty_def);
             }
         }
     }
 
-    let constants = parse_consts(&ctx)
-        .iter()
-        .map(|c: &&syn::ItemConst| IdlConst {
-            name: c.ident.to_string(),
-            ty: c.ty.to_token_stream().to_string().parse().unwrap(),
-            value: c.expr.to_token_stream().to_string().parse().unwrap(),
+    let constants = parse_consts(&ctx, &mut warnings)?
+        .into_iter()
+        .map(|(module, c)| IdlConst {
+            module: module
+                .path()
+                .strip_prefix("crate::")
+                .map(ToString::to_string),
+            ..constant::parse(&ctx, c)
         })
         .collect::<Vec<IdlConst>>();
+    let mut const_paths = HashSet::new();
+    for c in constants.iter() {
+        if !const_paths.insert(c.path()) {
+            warnings.push(IdlWarning::DuplicateConst(c.path()));
+        }
+    }
 
-    Ok(Some(Idl {
-        version,
+    let mut idl = Idl {
+        version: options.version.clone(),
         name: p.name.to_string(),
         docs: p.docs.clone(),
         state,
         instructions,
+        instruction_enum,
+        fallback: p.fallback_fn.as_ref().map(IdlFallback::new),
         types,
         accounts,
         events: if events.is_empty() {
@@ -301,14 +523,392 @@ pub fn parse(
         } else {
             Some(events)
         },
+        external_messages,
         errors: error_codes,
         metadata: None,
         constants,
+    };
+    let state_methods = idl.state.iter_mut().flat_map(|state| &mut state.methods);
+    for ix in idl.instructions.iter_mut().chain(state_methods) {
+        ix.shorten_discriminator(p.discriminator_len);
+    }
+    instantiate_generics(&ctx, &mut idl, p.discriminator_len, no_docs, &mut warnings)?;
+    // The accounts of the whole crate, which `#[program]` doesn't see.
+    let mut account_discriminators: Vec<_> = idl
+        .accounts
+        .iter()
+        .map(|acc| (acc.name.clone(), acc.account_discriminator()))
+        .collect();
+    account_discriminators.sort();
+    discriminator::check_accounts(&account_discriminators).map_err(|err| anyhow!(err))?;
+    idl.apply_docs_policy(docs_policy);
+    apply_float_policy(&mut idl, float_policy, &mut warnings)?;
+
+    Ok(Some(ParseOutput { idl, warnings }))
+}
+
+// Instructions of the `#[interface]` trait implementations of the state
+// struct, namespaced by the trait, along with whether they take a receiver.
+#[allow(clippy::too_many_arguments)]
+fn interface_instructions(
+    ctx: &CrateContext,
+    program_path: &str,
+    state: &State,
+    accs: &HashMap<String, AccountsStruct>,
+    external_accounts: &ExternalAccounts,
+    seeds_feature: bool,
+    no_docs: bool,
+    flatten_accounts: bool,
+) -> Result<Vec<(bool, IdlInstruction)>> {
+    state
+        .interfaces
+        .iter()
+        .flatten()
+        .flat_map(|iface| {
+            iface.methods.iter().map(move |method| {
+                let args = method
+                    .args
+                    .iter()
+                    .map(|arg| {
+                        Ok(IdlField {
+                            name: arg.name.to_string().to_mixed_case(),
+                            docs: if !no_docs { arg.docs.clone() } else { None },
+                            ty: to_idl_type(ctx, program_path, &arg.raw_arg.ty)?,
+                            constraints: arg_constraints(&arg.constraints),
+                            aliases: arg.aliases.clone(),
+                        })
+                    })
+                    .collect::<Result<_>>()?;
+                let accounts_strct = accs.get(&method.anchor_ident.to_string()).unwrap();
+                let accounts = idl_accounts(
+                    ctx,
+                    accounts_strct,
+                    accs,
+                    external_accounts,
+                    seeds_feature,
+                    no_docs,
+                    flatten_accounts,
+                );
+                let signers =
+                    instruction_signers(&accounts, accounts_strct, accs, external_accounts);
+                let ix = IdlInstruction {
+                    name: method.ident.to_string().to_mixed_case(),
+                    docs: if !no_docs {
+                        docs::parse(&method.raw_method.attrs)
+                    } else {
+                        None
+                    },
+                    accounts,
+                    args,
+                    returns: None,
+                    namespace: Some(iface.trait_name.clone()),
+                    discriminator: None,
+                    guards: guards(&method.raw_method.attrs)?,
+                    remaining_accounts: remaining_accounts(accounts_strct),
+                    sunset: sunset(&method.raw_method.attrs)?,
+                    compute_budget: compute_budget(&method.raw_method.attrs)?,
+                    distinct_accounts: distinct_accounts(accounts_strct),
+                    admin: is_admin(&method.raw_method.attrs),
+                    batch: vec![],
+                    signers,
+                };
+                Ok((method.has_receiver, ix))
+            })
+        })
+        .collect()
+}
+
+// The remaining accounts of an instruction given by `#[remaining_accounts(..)]`
+// on its accounts struct, named after the last segment of their type.
+fn remaining_accounts(accounts: &AccountsStruct) -> Option<IdlRemainingAccounts> {
+    accounts
+        .remaining_accounts
+        .as_ref()
+        .map(|remaining| IdlRemainingAccounts {
+            account: match &remaining.ty {
+                syn::Type::Path(ty) => ty.path.segments.last().unwrap().ident.to_string(),
+                ty => parser::tts_to_string(ty),
+            },
+            is_mut: remaining.mutable,
+        })
+}
+
+// The pairs of accounts of an instruction checked by `#[distinct_mut]` on its
+// accounts struct.
+fn distinct_accounts(accounts: &AccountsStruct) -> Vec<(String, String)> {
+    accounts
+        .distinct_mut
+        .iter()
+        .map(|(a, b)| (a.to_string().to_mixed_case(), b.to_string().to_mixed_case()))
+        .collect()
+}
+
+// The constraints of an instruction argument given by `#[arg(..)]`.
+pub(crate) fn arg_constraints(constraints: &[ArgConstraint]) -> Vec<IdlArgConstraint> {
+    constraints
+        .iter()
+        .map(|constraint| match constraint {
+            ArgConstraint::Range { min, max, .. } => IdlArgConstraint::Range {
+                min: min.map(|min| min.to_string()),
+                max: max.map(|max| max.to_string()),
+            },
+            ArgConstraint::NonZero => IdlArgConstraint::NonZero,
+            ArgConstraint::MaxLen(max_len) => IdlArgConstraint::MaxLen(*max_len),
+        })
+        .collect()
+}
+
+// The signers of an instruction with the given accounts. PDAs given by
+// `seeds::program` are signed for by another program, and aren't listed.
+fn instruction_signers(
+    items: &[IdlAccountItem],
+    accounts: &AccountsStruct,
+    global_accs: &HashMap<String, AccountsStruct>,
+    external_accounts: &ExternalAccounts,
+) -> IdlSigners {
+    fn program_pdas(
+        accounts: &AccountsStruct,
+        global_accs: &HashMap<String, AccountsStruct>,
+        external_accounts: &ExternalAccounts,
+        pdas: &mut Vec<bool>,
+    ) {
+        for field in &accounts.fields {
+            match field {
+                AccountField::CompositeField(comp_f) => match global_accs.get(&comp_f.symbol) {
+                    Some(accs_strct) => {
+                        program_pdas(accs_strct, global_accs, external_accounts, pdas)
+                    }
+                    // The constraints of external accounts aren't known, only
+                    // the seeds they have in the IDL.
+                    None => {
+                        let items = external_accounts
+                            .resolve(&comp_f.symbol, true, true)
+                            .unwrap_or_default();
+                        pdas.extend(flat_accounts(&items).into_iter().map(
+                            |(_, acc)| matches!(&acc.pda, Some(pda) if pda.program_id.is_none()),
+                        ));
+                    }
+                },
+                AccountField::Field(acc) => pdas.push(matches!(
+                    &acc.constraints.seeds,
+                    Some(seeds) if seeds.program_seed.is_none()
+                )),
+            }
+        }
+    }
+    let mut pdas = vec![];
+    program_pdas(accounts, global_accs, external_accounts, &mut pdas);
+    let mut signers = IdlSigners::default();
+    for ((name, acc), is_pda) in flat_accounts(items).into_iter().zip(pdas) {
+        if acc.is_signer {
+            signers.accounts.push(name);
+        } else if is_pda {
+            signers.pdas.push(name);
+        }
+    }
+    signers
+}
+
+// Whether a handler is marked `#[admin]`.
+pub(crate) fn is_admin(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("admin"))
+}
+
+// The `batch` instruction generated for the handlers marked `#[batch]`, if any,
+// given the data of each instruction to run and the number of accounts each
+// one takes.
+pub(crate) fn batch_instruction(ixs: &[crate::Ix]) -> Option<IdlInstruction> {
+    let batched_ixs = parser::batch::batched_ixs(ixs);
+    if batched_ixs.is_empty() {
+        return None;
+    }
+    let arg = |name: &str, ty| IdlField {
+        name: name.to_string(),
+        docs: None,
+        ty,
+        constraints: vec![],
+        aliases: vec![],
+    };
+    Some(IdlInstruction {
+        name: parser::batch::BATCH_IX_NAME.to_string(),
+        docs: None,
+        accounts: vec![],
+        args: vec![
+            arg("instructions", IdlType::Vec(Box::new(IdlType::Bytes))),
+            arg("accountCounts", IdlType::Bytes),
+        ],
+        returns: None,
+        namespace: None,
+        discriminator: None,
+        guards: vec![],
+        remaining_accounts: None,
+        sunset: None,
+        compute_budget: None,
+        distinct_accounts: vec![],
+        admin: false,
+        batch: batched_ixs
+            .iter()
+            .map(|ix| ix.ident.to_string().to_mixed_case())
+            .collect(),
+        signers: IdlSigners::default(),
+    })
+}
+
+// The variant of the `batch` instruction in the `ProgramInstruction` enum.
+pub(crate) fn batch_enum_variant(batch: &IdlInstruction, len: usize) -> IdlInstructionEnumVariant {
+    IdlInstructionEnumVariant {
+        name: batch.name.to_camel_case(),
+        instruction: batch.name.clone(),
+        discriminator: batch.discriminator()[..len].to_vec(),
+    }
+}
+
+// The compute budget of a handler given by `#[compute_budget(..)]`.
+pub(crate) fn compute_budget(attrs: &[syn::Attribute]) -> Result<Option<IdlComputeBudget>> {
+    Ok(
+        parser::compute_budget::compute_budget(attrs)?.map(|budget| IdlComputeBudget {
+            units: budget.units,
+            heap: budget.heap,
+        }),
+    )
+}
+
+// The deadline of a handler given by `#[sunset(..)]`.
+pub(crate) fn sunset(attrs: &[syn::Attribute]) -> Result<Option<IdlSunset>> {
+    Ok(parser::sunset::sunset(attrs)?.map(|sunset| match sunset {
+        parser::sunset::Sunset::Slot(slot) => IdlSunset::Slot(slot),
+        parser::sunset::Sunset::Timestamp(timestamp) => IdlSunset::Timestamp(timestamp),
     }))
 }
 
+// The preconditions of a handler given by `#[access_control(..)]`.
+pub(crate) fn guards(attrs: &[syn::Attribute]) -> Result<Vec<IdlGuard>> {
+    Ok(parser::access_control::guards(attrs)?
+        .into_iter()
+        .map(|guard| IdlGuard {
+            name: guard.name,
+            args: guard.args,
+        })
+        .collect())
+}
+
+// Doc attributes that aren't string literals (e.g. `#[doc = include_str!(..)]`)
+// are skipped by `docs::parse`.
+fn check_docs(item: &str, attrs: &[syn::Attribute], warnings: &mut Vec<IdlWarning>) {
+    let unresolved = attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("doc") && attr.parse_meta().is_err());
+    if unresolved {
+        warnings.push(IdlWarning::UnresolvedDocs(item.to_string()));
+    }
+}
+
+// Checks (or annotates) all floating point types used by instructions,
+// accounts and user defined types.
+pub(crate) fn apply_float_policy(
+    idl: &mut Idl,
+    policy: FloatPolicy,
+    warnings: &mut Vec<IdlWarning>,
+) -> Result<()> {
+    fn visit(ty: &mut IdlType, location: &str, policy: FloatPolicy, found: &mut Vec<(u8, String)>) {
+        let bits = match ty {
+            IdlType::F32 => 32,
+            IdlType::F64 => 64,
+            IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+                return visit(inner, location, policy, found)
+            }
+            _ => return,
+        };
+        match policy {
+            FloatPolicy::Error | FloatPolicy::Warn => found.push((bits, location.to_string())),
+            FloatPolicy::AllowWithAnnotation => {
+                *ty = IdlType::Float(IdlFloat {
+                    bits,
+                    approximate: true,
+                })
+            }
+        }
+    }
+    fn visit_fields(
+        fields: &mut [IdlField],
+        location: &str,
+        policy: FloatPolicy,
+        found: &mut Vec<(u8, String)>,
+    ) {
+        for f in fields {
+            visit(
+                &mut f.ty,
+                &format!("{}.{}", location, f.name),
+                policy,
+                found,
+            );
+        }
+    }
+    fn visit_ty_def(
+        ty_def: &mut IdlTypeDefinition,
+        policy: FloatPolicy,
+        found: &mut Vec<(u8, String)>,
+    ) {
+        match &mut ty_def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                visit_fields(fields, &ty_def.name, policy, found)
+            }
+            IdlTypeDefinitionTy::Enum { variants } => {
+                for variant in variants {
+                    let location = format!("{}::{}", ty_def.name, variant.name);
+                    match &mut variant.fields {
+                        None => (),
+                        Some(EnumFields::Named(fields)) => {
+                            visit_fields(fields, &location, policy, found)
+                        }
+                        Some(EnumFields::Tuple(tys)) => {
+                            for (idx, ty) in tys.iter_mut().enumerate() {
+                                visit(ty, &format!("{}.{}", location, idx), policy, found);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut found = vec![];
+    for ix in idl.instructions.iter_mut() {
+        visit_fields(&mut ix.args, &ix.name, policy, &mut found);
+    }
+    if let Some(state) = idl.state.as_mut() {
+        visit_ty_def(&mut state.strct, policy, &mut found);
+        for method in state.methods.iter_mut() {
+            visit_fields(&mut method.args, &method.name, policy, &mut found);
+        }
+    }
+    for ty_def in idl.accounts.iter_mut().chain(idl.types.iter_mut()) {
+        visit_ty_def(ty_def, policy, &mut found);
+    }
+
+    match policy {
+        FloatPolicy::Error if !found.is_empty() => {
+            let errors: Vec<String> = found
+                .iter()
+                .map(|(bits, location)| format!("f{} used in {}", bits, location))
+                .collect();
+            return Err(anyhow::anyhow!(
+                "Floating point types are not allowed by the float policy:\n{}",
+                errors.join("\n")
+            ));
+        }
+        FloatPolicy::Warn => warnings.extend(
+            found
+                .into_iter()
+                .map(|(bits, location)| IdlWarning::Float { bits, location }),
+        ),
+        _ => (),
+    }
+    Ok(())
+}
+
 // Parse the main program mod.
-fn parse_program_mod(ctx: &CrateContext) -> Option<syn::ItemMod> {
+pub(crate) fn parse_program_mod(ctx: &CrateContext) -> Option<syn::ItemMod> {
     let root = ctx.root_module();
     let mods = root
         .items()
@@ -333,6 +933,17 @@ fn parse_program_mod(ctx: &CrateContext) -> Option<syn::ItemMod> {
     Some(mods[0].clone())
 }
 
+// Arguments of the `#[program(..)]` attribute of the program module.
+fn program_args(attrs: &[syn::Attribute]) -> Result<ProgramArgs> {
+    let attr = attrs
+        .iter()
+        .find(|attr| attr.path.segments.last().unwrap().ident == "program");
+    match attr {
+        Some(attr) if !attr.tokens.is_empty() => Ok(attr.parse_args()?),
+        _ => Ok(ProgramArgs::default()),
+    }
+}
+
 fn parse_error_enum(ctx: &CrateContext) -> Option<syn::ItemEnum> {
     ctx.enums()
         .filter_map(|item_enum| {
@@ -354,9 +965,9 @@ fn parse_error_enum(ctx: &CrateContext) -> Option<syn::ItemEnum> {
         .cloned()
 }
 
-fn parse_events(ctx: &CrateContext) -> Vec<&syn::ItemStruct> {
-    ctx.structs()
-        .filter_map(|item_strct| {
+fn parse_events(ctx: &CrateContext) -> Vec<(ModuleContext<'_>, &syn::ItemStruct)> {
+    module_structs(ctx)
+        .filter_map(|(module, item_strct)| {
             let attrs_count = item_strct
                 .attrs
                 .iter()
@@ -367,13 +978,110 @@ fn parse_events(ctx: &CrateContext) -> Vec<&syn::ItemStruct> {
                 .count();
             match attrs_count {
                 0 => None,
-                1 => Some(item_strct),
+                1 => Some((module, item_strct)),
                 _ => panic!("Invalid syntax: one event attribute allowed"),
             }
         })
         .collect()
 }
 
+// The payloads described by `#[external_message(..)]`, checking that the
+// instructions consuming them exist and have the account holding the message.
+fn parse_external_messages(
+    ctx: &CrateContext,
+    instructions: &[IdlInstruction],
+    no_docs: bool,
+) -> Result<Vec<IdlExternalMessage>> {
+    let mut messages = vec![];
+    for (module, strct) in module_structs(ctx) {
+        let args = match parser::external_message::args(&strct.attrs)? {
+            Some(args) => args,
+            None => continue,
+        };
+        let name = strct.ident.to_string();
+        let fields = match &strct.fields {
+            syn::Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(|f| {
+                    Ok(IdlField {
+                        name: f.ident.as_ref().unwrap().to_string().to_mixed_case(),
+                        docs: if !no_docs {
+                            docs::parse(&f.attrs)
+                        } else {
+                            None
+                        },
+                        ty: to_idl_type(ctx, &module.path(), &f.ty)?,
+                        constraints: vec![],
+                        aliases: vec![],
+                    })
+                })
+                .collect::<Result<Vec<IdlField>>>()?,
+            syn::Fields::Unit => vec![],
+            syn::Fields::Unnamed(_) => {
+                return Err(anyhow!("External message {} must have named fields", name))
+            }
+        };
+        let account = args.account.map(|account| account.to_mixed_case());
+        let consumers = args
+            .instructions
+            .iter()
+            .map(|ix| {
+                let ix_name = ix.to_mixed_case();
+                let ix = instructions
+                    .iter()
+                    .find(|ix| ix.name == ix_name)
+                    .ok_or_else(|| {
+                        anyhow!("External message {} names unknown instruction {}", name, ix)
+                    })?;
+                let has_account = |account: &String| {
+                    ix.accounts.iter().any(|item| match item {
+                        IdlAccountItem::IdlAccount(acc) => &acc.name == account,
+                        IdlAccountItem::IdlAccounts(accs) => &accs.name == account,
+                    })
+                };
+                match &account {
+                    Some(account) if !has_account(account) => Err(anyhow!(
+                        "Instruction {} has no account {} holding the message {}",
+                        ix_name,
+                        account,
+                        name
+                    )),
+                    _ => Ok(ix_name),
+                }
+            })
+            .collect::<Result<Vec<String>>>()?;
+        messages.push(IdlExternalMessage {
+            docs: if !no_docs {
+                docs::parse(&strct.attrs)
+            } else {
+                None
+            },
+            name,
+            format: args.format,
+            emitter: args.emitter.map(|address| IdlMessageEmitter {
+                chain: args.emitter_chain,
+                address,
+            }),
+            verification: account.map(|account| IdlMessageVerification {
+                account,
+                owner: args.owner,
+            }),
+            instructions: consumers,
+            fields,
+        });
+    }
+    Ok(messages)
+}
+
+// The structs of the crate, along with the module they're defined in.
+fn module_structs(
+    ctx: &CrateContext,
+) -> impl Iterator<Item = (ModuleContext<'_>, &syn::ItemStruct)> {
+    ctx.modules()
+        .flat_map(|module| module.structs().map(move |strct| (module, strct)))
+}
+
 fn parse_accounts(ctx: &CrateContext) -> Vec<&syn::ItemStruct> {
     ctx.structs()
         .filter_map(|item_strct| {
@@ -394,8 +1102,81 @@ fn parse_accounts(ctx: &CrateContext) -> Vec<&syn::ItemStruct> {
         .collect()
 }
 
+// The enums of the crate given `#[account]`, serialized as the variant index
+// followed by the fields of the variant.
+fn parse_account_enums(ctx: &CrateContext) -> Vec<&syn::ItemEnum> {
+    ctx.enums()
+        .filter(|item_enum| {
+            item_enum
+                .attrs
+                .iter()
+                .any(|attr| attr.path.segments.last().unwrap().ident == "account")
+        })
+        .collect()
+}
+
+// How each event is emitted, by its name, found from the `emit!` and
+// `emit_cpi!` invocations of the crate, e.g. `emit!(Deposit { .. })` or
+// `emit_cpi!(events::Deposit { .. })`. Events emitted from a variable, e.g.
+// `emit!(event)`, aren't found.
+fn event_transports(ctx: &CrateContext) -> HashMap<String, Vec<IdlEventTransport>> {
+    fn find_emits(
+        tokens: proc_macro2::TokenStream,
+        transports: &mut HashMap<String, Vec<IdlEventTransport>>,
+    ) {
+        let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+        for (i, token) in tokens.iter().enumerate() {
+            let ident = match token {
+                TokenTree::Group(group) => {
+                    find_emits(group.stream(), transports);
+                    continue;
+                }
+                TokenTree::Ident(ident) => ident,
+                _ => continue,
+            };
+            let transport = match ident.to_string().as_str() {
+                "emit" => IdlEventTransport::Log,
+                "emit_cpi" => IdlEventTransport::Cpi,
+                _ => continue,
+            };
+            let args = match (tokens.get(i + 1), tokens.get(i + 2)) {
+                (Some(TokenTree::Punct(bang)), Some(TokenTree::Group(args)))
+                    if bang.as_char() == '!' =>
+                {
+                    args
+                }
+                _ => continue,
+            };
+            // The last segment of the path of the event's struct expression.
+            let event = args
+                .stream()
+                .into_iter()
+                .take_while(|token| !matches!(token, TokenTree::Group(_)))
+                .filter_map(|token| match token {
+                    TokenTree::Ident(ident) => Some(ident.to_string()),
+                    _ => None,
+                })
+                .last();
+            if let Some(event) = event {
+                let emitted = transports.entry(event).or_default();
+                if !emitted.contains(&transport) {
+                    emitted.push(transport);
+                }
+            }
+        }
+    }
+
+    let mut transports = HashMap::new();
+    for module in ctx.modules() {
+        for item in module.items() {
+            find_emits(item.to_token_stream(), &mut transports);
+        }
+    }
+    transports
+}
+
 // Parse all structs implementing the `Accounts` trait.
-fn parse_account_derives(ctx: &CrateContext) -> HashMap<String, AccountsStruct> {
+pub(crate) fn parse_account_derives(ctx: &CrateContext) -> HashMap<String, AccountsStruct> {
     // TODO: parse manual implementations. Currently we only look
     //       for derives.
     ctx.structs()
@@ -405,88 +1186,71 @@ fn parse_account_derives(ctx: &CrateContext) -> HashMap<String, AccountsStruct>
                     let strct = accounts::parse(i_strct).expect("Code not parseable");
                     return Some((strct.ident.to_string(), strct));
                 }
+                // Accounts fragments resolve the fields they're embedded with,
+                // like composite accounts.
+                if attr.path.is_ident(FRAGMENT_NAME) {
+                    let strct = accounts::parse_fragment(i_strct).expect("Code not parseable");
+                    return Some((strct.ident.to_string(), strct));
+                }
             }
             None
         })
         .collect()
 }
 
-fn parse_consts(ctx: &CrateContext) -> Vec<&syn::ItemConst> {
-    ctx.consts()
-        .filter(|item_strct| {
-            for attr in &item_strct.attrs {
-                if attr.path.segments.last().unwrap().ident == "constant" {
-                    return true;
-                }
+// The `#[constant]`s of the crate, along with the module they're defined in.
+// Constants marked with `#[constant(skip_idl)]` are left out, as are the ones
+// that aren't public.
+fn parse_consts<'a>(
+    ctx: &'a CrateContext,
+    warnings: &mut Vec<IdlWarning>,
+) -> Result<Vec<(ModuleContext<'a>, &'a syn::ItemConst)>> {
+    let mut consts = vec![];
+    for module in ctx.modules() {
+        for item_const in module.consts() {
+            match parser::constant::args(&item_const.attrs)? {
+                Some(args) if !args.skip_idl => (),
+                _ => continue,
             }
-            false
-        })
-        .collect()
+            if !matches!(item_const.vis, syn::Visibility::Public(_)) {
+                warnings.push(IdlWarning::PrivateConst(item_const.ident.to_string()));
+                continue;
+            }
+            consts.push((module, item_const));
+        }
+    }
+    Ok(consts)
 }
 
 // Parse all user defined types in the file.
-fn parse_ty_defs(ctx: &CrateContext, no_docs: bool) -> Result<Vec<IdlTypeDefinition>> {
-    ctx.structs()
-        .filter_map(|item_strct| {
-            // Only take serializable types
-            let serializable = item_strct.attrs.iter().any(|attr| {
-                let attr_string = attr.tokens.to_string();
-                let attr_name = attr.path.segments.last().unwrap().ident.to_string();
-                let attr_serializable = ["account", "associated", "event", "zero_copy"];
-
-                let derived_serializable = attr_name == "derive"
-                    && attr_string.contains("AnchorSerialize")
-                    && attr_string.contains("AnchorDeserialize");
-
-                attr_serializable.iter().any(|a| *a == attr_name) || derived_serializable
-            });
-
-            if !serializable {
-                return None;
-            }
-
-            // Only take public types
-            match &item_strct.vis {
-                syn::Visibility::Public(_) => (),
-                _ => return None,
-            }
-
-            let name = item_strct.ident.to_string();
-            let doc = if !no_docs {
-                docs::parse(&item_strct.attrs)
-            } else {
-                None
-            };
-            let fields = match &item_strct.fields {
-                syn::Fields::Named(fields) => fields
-                    .named
-                    .iter()
-                    .map(|f: &syn::Field| {
-                        let doc = if !no_docs {
-                            docs::parse(&f.attrs)
-                        } else {
-                            None
-                        };
-                        Ok(IdlField {
-                            name: f.ident.as_ref().unwrap().to_string().to_mixed_case(),
-                            docs: doc,
-                            ty: to_idl_type(ctx, &f.ty),
-                        })
-                    })
-                    .collect::<Result<Vec<IdlField>>>(),
-                syn::Fields::Unnamed(_) => return None,
-                _ => panic!("Empty structs are allowed."),
-            };
-
-            Some(fields.map(|fields| IdlTypeDefinition {
-                name,
-                docs: doc,
-                ty: IdlTypeDefinitionTy::Struct { fields },
-            }))
+fn parse_ty_defs(
+    ctx: &CrateContext,
+    discriminator_len: usize,
+    no_docs: bool,
+    warnings: &mut Vec<IdlWarning>,
+) -> Result<Vec<IdlTypeDefinition>> {
+    let mut ty_defs = module_structs(ctx)
+        // Structs with const generic parameters are described per
+        // instantiation, see `instantiate_generics`.
+        .filter(|(_, item_strct)| item_strct.generics.const_params().next().is_none())
+        .filter_map(|(module, item_strct)| {
+            struct_ty_def(
+                ctx,
+                &module.path(),
+                item_strct,
+                discriminator_len,
+                no_docs,
+                warnings,
+            )
         })
-        .chain(ctx.enums().map(|enm| {
+        .collect::<Result<Vec<_>>>()?;
+    let enums = ctx
+        .modules()
+        .flat_map(|module| module.enums().map(move |enm| (module, enm)))
+        .map(|(module, enm)| {
             let name = enm.ident.to_string();
             let doc = if !no_docs {
+                check_docs(&name, &enm.attrs, warnings);
                 docs::parse(&enm.attrs)
             } else {
                 None
@@ -499,15 +1263,15 @@ fn parse_ty_defs(ctx: &CrateContext, no_docs: bool) -> Result<Vec<IdlTypeDefinit
                     let fields = match &variant.fields {
                         syn::Fields::Unit => None,
                         syn::Fields::Unnamed(fields) => {
-                            let fields: Vec<IdlType> = fields
+                            let fields = fields
                                 .unnamed
                                 .iter()
-                                .map(|f| to_idl_type(ctx, &f.ty))
-                                .collect();
+                                .map(|f| to_idl_type(ctx, &module.path(), &f.ty))
+                                .collect::<Result<Vec<IdlType>>>()?;
                             Some(EnumFields::Tuple(fields))
                         }
                         syn::Fields::Named(fields) => {
-                            let fields: Vec<IdlField> = fields
+                            let fields = fields
                                 .named
                                 .iter()
                                 .map(|f: &syn::Field| {
@@ -517,94 +1281,778 @@ fn parse_ty_defs(ctx: &CrateContext, no_docs: bool) -> Result<Vec<IdlTypeDefinit
                                     } else {
                                         None
                                     };
-                                    let ty = to_idl_type(ctx, &f.ty);
-                                    IdlField {
+                                    let ty = to_idl_type(ctx, &module.path(), &f.ty)?;
+                                    Ok(IdlField {
                                         name,
                                         docs: doc,
                                         ty,
-                                    }
+                                        constraints: vec![],
+                                        aliases: vec![],
+                                    })
                                 })
-                                .collect();
+                                .collect::<Result<Vec<IdlField>>>()?;
                             Some(EnumFields::Named(fields))
                         }
                     };
-                    IdlEnumVariant { name, fields }
+                    Ok(IdlEnumVariant { name, fields })
                 })
-                .collect::<Vec<IdlEnumVariant>>();
+                .collect::<Result<Vec<IdlEnumVariant>>>()?;
+            // Enums given `#[account]` are accounts.
+            let discriminator = layout_discriminator(&enm.ident, &enm.attrs, discriminator_len)?;
+            let checksum = layout_checksum(&enm.attrs)?;
+            let is_account = account_args(&enm.attrs)?.is_some();
+            let size = match is_account && parser::space::derives_init_space(&enm.attrs) {
+                true => {
+                    let overhead = discriminator.as_ref().map_or(8, Vec::len)
+                        + checksum.map_or(0, |c| c.size());
+                    let space = parser::space::enum_space(enm.variants.iter())?;
+                    Some((overhead + eval_space(ctx, &module.path(), &space)?) as u64)
+                }
+                false => None,
+            };
             Ok(IdlTypeDefinition {
                 name,
                 docs: doc,
                 ty: IdlTypeDefinitionTy::Enum { variants },
+                version: None,
+                migrations: vec![],
+                permissions: vec![],
+                discriminator,
+                state_machine: None,
+                size,
+                checksum,
+                serialization: IdlSerialization::Borsh,
             })
-        }))
-        .collect()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    ty_defs.extend(enums);
+    Ok(ty_defs)
 }
 
-// Replace variable array lengths with values
-fn resolve_variable_array_lengths(ctx: &CrateContext, mut tts_string: String) -> String {
-    for constant in ctx.consts().filter(|c| match *c.ty {
-        // Filter to only those consts that are of type usize or could be cast to usize
-        syn::Type::Path(ref p) => {
-            let segment = p.path.segments.last().unwrap();
-            matches!(
-                segment.ident.to_string().as_str(),
-                "usize"
-                    | "u8"
-                    | "u16"
-                    | "u32"
-                    | "u64"
-                    | "u128"
-                    | "isize"
-                    | "i8"
-                    | "i16"
-                    | "i32"
-                    | "i64"
-                    | "i128"
-            )
+// Describes each instantiation of the structs with const generic parameters
+// used by the IDL, e.g. `Buffer<32>` for `Buffer<const N: usize>`, as a type of
+// its own named after it, with the parameters replaced by their values in the
+// types of the fields, e.g. `[u8; N]`. Parameters without a value are
+// reported, e.g. for accounts, as the program doesn't instantiate them.
+fn instantiate_generics(
+    ctx: &CrateContext,
+    idl: &mut Idl,
+    discriminator_len: usize,
+    no_docs: bool,
+    warnings: &mut Vec<IdlWarning>,
+) -> Result<()> {
+    let generics: HashMap<String, (String, &syn::ItemStruct)> = module_structs(ctx)
+        .filter(|(_, strct)| strct.generics.const_params().next().is_some())
+        .map(|(module, strct)| (strct.ident.to_string(), (module.path(), strct)))
+        .collect();
+    if generics.is_empty() {
+        return Ok(());
+    }
+    for strct in parse_accounts(ctx) {
+        if generics.contains_key(&strct.ident.to_string()) {
+            return Err(unresolved_params(strct, &strct.ident.to_string()));
         }
-        _ => false,
-    }) {
-        let mut check_string = tts_string.clone();
-        // Strip whitespace to handle accidental double whitespaces
-        check_string.retain(|c| !c.is_whitespace());
-        let size_string = format!("{}]", &constant.ident.to_string());
-        let cast_size_string = format!("{}asusize]", &constant.ident.to_string());
-        // Check for something to replace
-        let mut replacement_string = None;
-        if check_string.contains(cast_size_string.as_str()) {
-            replacement_string = Some(cast_size_string);
-        } else if check_string.contains(size_string.as_str()) {
-            replacement_string = Some(size_string);
-        }
-        if let Some(replacement_string) = replacement_string {
-            // Check for the existence of consts existing elsewhere in the
-            // crate which have the same name, are usize, and have a
-            // different value. We can't know which was intended for the
-            // array size from ctx.
-            if ctx.consts().any(|c| {
-                c != constant
-                    && c.ident == constant.ident
-                    && c.ty == constant.ty
-                    && c.expr != constant.expr
-            }) {
-                panic!("Crate wide unique name required for array size const.");
-            }
-            // Replace the match, don't break because there might be multiple replacements to be
-            // made in the case of multidimensional arrays
-            tts_string = check_string.replace(
-                &replacement_string,
-                format!("{}]", &constant.expr.to_token_stream()).as_str(),
-            );
+    }
+
+    let mut skipped = HashSet::new();
+    loop {
+        let names: Vec<String> = build::undefined_types(idl)
+            .into_iter()
+            .filter(|name| !skipped.contains(name))
+            .collect();
+        if names.is_empty() {
+            return Ok(());
+        }
+        for name in names {
+            let (path, args) = match name.split_once('<') {
+                Some((path, args)) => (path, args.strip_suffix('>').unwrap_or(args)),
+                None => (name.as_str(), ""),
+            };
+            let (module, strct) = match generics.get(path.rsplit("::").next().unwrap()) {
+                Some(generic) => generic,
+                // Types defined elsewhere are reported as undefined.
+                None => {
+                    skipped.insert(name);
+                    continue;
+                }
+            };
+            let params: Vec<&syn::Ident> =
+                strct.generics.const_params().map(|p| &p.ident).collect();
+            let values = args
+                .split(',')
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| arg.parse::<usize>())
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .ok()
+                .filter(|values| values.len() == params.len())
+                .ok_or_else(|| unresolved_params(strct, &name))?;
+
+            let mut instance = (*strct).clone();
+            instance.generics = syn::Generics::default();
+            let values: HashMap<String, usize> = params
+                .iter()
+                .map(|param| param.to_string())
+                .zip(values)
+                .collect();
+            for field in instance.fields.iter_mut() {
+                substitute_params(&mut field.ty, &values);
+            }
+            match struct_ty_def(ctx, module, &instance, discriminator_len, no_docs, warnings) {
+                Some(ty_def) => idl.types.push(IdlTypeDefinition { name, ..ty_def? }),
+                None => {
+                    skipped.insert(name);
+                }
+            }
         }
     }
-    tts_string
 }
 
-fn to_idl_type(ctx: &CrateContext, ty: &syn::Type) -> IdlType {
-    let mut tts_string = parser::tts_to_string(&ty);
-    if tts_string.starts_with('[') {
-        tts_string = resolve_variable_array_lengths(ctx, tts_string);
+fn unresolved_params(strct: &syn::ItemStruct, name: &str) -> anyhow::Error {
+    let params: Vec<String> = strct
+        .generics
+        .const_params()
+        .map(|p| p.ident.to_string())
+        .collect();
+    anyhow!(
+        "Unable to resolve the const generic parameters of `{}`: {}. Only the \
+        instantiations of generic types with integer values are described",
+        name,
+        params.join(", ")
+    )
+}
+
+// Replaces the const generic parameters in `ty` with their value, e.g. in
+// array lengths.
+fn substitute_params(ty: &mut syn::Type, values: &HashMap<String, usize>) {
+    let value = |expr: &syn::Expr| -> Option<syn::Expr> {
+        let ident = match expr {
+            syn::Expr::Path(expr) => expr.path.get_ident()?,
+            _ => return None,
+        };
+        let value = proc_macro2::Literal::usize_unsuffixed(*values.get(&ident.to_string())?);
+        Some(syn::parse_quote!(#value))
+    };
+    match ty {
+        syn::Type::Array(array) => {
+            substitute_params(&mut array.elem, values);
+            if let Some(len) = value(&array.len) {
+                array.len = len;
+            }
+        }
+        syn::Type::Path(path) => {
+            for segment in path.path.segments.iter_mut() {
+                if let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                    for arg in args.args.iter_mut() {
+                        let param = match arg {
+                            syn::GenericArgument::Const(expr) => value(expr),
+                            // Parameters given by name are parsed as types.
+                            syn::GenericArgument::Type(syn::Type::Path(path)) => {
+                                value(&syn::Expr::Path(syn::ExprPath {
+                                    attrs: vec![],
+                                    qself: None,
+                                    path: path.path.clone(),
+                                }))
+                            }
+                            _ => None,
+                        };
+                        match (param, arg) {
+                            (Some(param), arg) => *arg = syn::GenericArgument::Const(param),
+                            (None, syn::GenericArgument::Type(ty)) => substitute_params(ty, values),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        syn::Type::Tuple(tuple) => {
+            for elem in tuple.elems.iter_mut() {
+                substitute_params(elem, values);
+            }
+        }
+        _ => {}
+    }
+}
+
+// The type definition of a struct of the module at `module`, if it's
+// serializable and public.
+fn struct_ty_def(
+    ctx: &CrateContext,
+    module: &str,
+    item_strct: &syn::ItemStruct,
+    discriminator_len: usize,
+    no_docs: bool,
+    warnings: &mut Vec<IdlWarning>,
+) -> Option<Result<IdlTypeDefinition>> {
+    // Only take serializable types
+    let serializable = item_strct.attrs.iter().any(|attr| {
+        let attr_string = attr.tokens.to_string();
+        let attr_name = attr.path.segments.last().unwrap().ident.to_string();
+        let attr_serializable = ["account", "associated", "event", "zero_copy"];
+
+        let derived_serializable = attr_name == "derive"
+            && attr_string.contains("AnchorSerialize")
+            && attr_string.contains("AnchorDeserialize");
+
+        attr_serializable.iter().any(|a| *a == attr_name) || derived_serializable
+    });
+
+    if !serializable {
+        return None;
+    }
+
+    let name = item_strct.ident.to_string();
+
+    // Only take public types
+    match &item_strct.vis {
+        syn::Visibility::Public(_) => (),
+        _ => {
+            warnings.push(IdlWarning::PrivateType(name));
+            return None;
+        }
+    }
+
+    let doc = if !no_docs {
+        check_docs(&name, &item_strct.attrs, warnings);
+        docs::parse(&item_strct.attrs)
+    } else {
+        None
+    };
+    let fields = match &item_strct.fields {
+        syn::Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|f: &syn::Field| {
+                let doc = if !no_docs {
+                    let field = format!("{}.{}", name, f.ident.as_ref().unwrap());
+                    check_docs(&field, &f.attrs, warnings);
+                    docs::parse(&f.attrs)
+                } else {
+                    None
+                };
+                Ok(IdlField {
+                    name: f.ident.as_ref().unwrap().to_string().to_mixed_case(),
+                    docs: doc,
+                    ty: to_idl_type(ctx, module, &f.ty)?,
+                    constraints: vec![],
+                    aliases: idl_name::parse(&f.attrs)?,
+                })
+            })
+            .collect::<Result<Vec<IdlField>>>(),
+        syn::Fields::Unnamed(_) => {
+            warnings.push(IdlWarning::TupleStruct(name));
+            return None;
+        }
+        // Unit structs are serialized as nothing at all, i.e., accounts
+        // consist of only their discriminator.
+        syn::Fields::Unit => Ok(vec![]),
+    };
+
+    let version = layout_version(&item_strct.attrs);
+    let migrations = layout_migrations(&item_strct.fields);
+    let permissions = field_permissions(&item_strct.fields);
+    let state_machine = state_machine::state_machine(&item_strct.attrs);
+    let discriminator =
+        layout_discriminator(&item_strct.ident, &item_strct.attrs, discriminator_len);
+    let checksum = layout_checksum(&item_strct.attrs);
+    let serialization = layout_serialization(&item_strct.attrs);
+    Some(fields.and_then(|fields| {
+        let discriminator = discriminator?;
+        let checksum = checksum?;
+        // The checksum follows the fields, taking space as well.
+        let overhead =
+            discriminator.as_ref().map_or(8, Vec::len) + checksum.map_or(0, |c| c.size());
+        Ok(IdlTypeDefinition {
+            name,
+            docs: doc,
+            ty: IdlTypeDefinitionTy::Struct { fields },
+            version: version?,
+            migrations: migrations?,
+            permissions: permissions?,
+            discriminator,
+            state_machine: state_machine?,
+            size: account_size(ctx, module, item_strct, overhead)?,
+            checksum,
+            serialization: serialization?,
+        })
+    }))
+}
+
+// Custom discriminator given by `#[account(discriminator = ..)]`, if any, or
+// the one shortened to the `discriminator_len` of the program. As for the
+// other accounts of the IDL, names are hashed without the namespace.
+fn layout_discriminator(
+    ident: &syn::Ident,
+    attrs: &[syn::Attribute],
+    discriminator_len: usize,
+) -> Result<Option<Vec<u8>>> {
+    let (args, zero_copy) = match account_args(attrs)? {
+        None => return Ok(None),
+        Some(args) => args,
+    };
+    // Zero copy accounts keep 8 bytes.
+    let len = match zero_copy {
+        Some(_) => discriminator::MAX_LEN,
+        None => discriminator_len,
+    };
+    let custom = discriminator::account_discriminator(args)?;
+    match (custom, len) {
+        (None, discriminator::MAX_LEN) => Ok(None),
+        (custom, len) => Ok(Some(discriminator::account_bytes(
+            ident,
+            "",
+            custom.as_ref(),
+            len,
+        ))),
+    }
+}
+
+// Checksum of the data given by `#[account(checksum)]`, if any.
+fn layout_checksum(attrs: &[syn::Attribute]) -> Result<Option<IdlChecksum>> {
+    match account_args(attrs)? {
+        Some((args, _)) => Ok(checksum::account_checksum(args)?.then(|| IdlChecksum::Crc32)),
+        None => Ok(None),
+    }
+}
+
+// Version of the layout given by `#[account(version = ..)]`, if any.
+fn layout_version(attrs: &[syn::Attribute]) -> Result<Option<u32>> {
+    match account_args(attrs)? {
+        Some((args, _)) => Ok(version::account_version(args)?),
+        None => Ok(None),
+    }
+}
+
+// Serialization of a struct given `#[zero_copy]` or `#[account(zero_copy)]`,
+// borsh otherwise.
+fn layout_serialization(attrs: &[syn::Attribute]) -> Result<IdlSerialization> {
+    let zero_copy = match attrs
+        .iter()
+        .find(|attr| attr.path.segments.last().unwrap().ident == "zero_copy")
+    {
+        Some(attr) if attr.tokens.is_empty() => Some(ZeroCopy::Safe),
+        Some(attr) => Some(zero_copy::parse(attr.parse_args()?)?),
+        None => account_args(attrs)?.and_then(|(_, zero_copy)| zero_copy),
+    };
+    Ok(zero_copy.into())
+}
+
+// The arguments of the `#[account(..)]` attribute, if any, without the
+// `zero_copy` argument, given separately.
+fn account_args(attrs: &[syn::Attribute]) -> Result<Option<(TokenStream, Option<ZeroCopy>)>> {
+    let attr = attrs
+        .iter()
+        .find(|attr| attr.path.segments.last().unwrap().ident == "account");
+    match attr {
+        None => Ok(None),
+        Some(attr) if attr.tokens.is_empty() => Ok(Some((TokenStream::new(), None))),
+        Some(attr) => Ok(Some(zero_copy::split_account_args(attr.parse_args()?)?)),
+    }
+}
+
+// Fields added or renamed by later versions of the layout, given by
+// `#[since(..)]`.
+pub(crate) fn layout_migrations(fields: &syn::Fields) -> Result<Vec<IdlMigration>> {
+    let mut migrations = vec![];
+    for field in fields.iter() {
+        if let Some(since) = version::since(&field.attrs)? {
+            migrations.push(IdlMigration {
+                field: field.ident.as_ref().unwrap().to_string().to_mixed_case(),
+                since: since.version,
+                renamed_from: since.renamed_from.map(|name| name.to_mixed_case()),
+            });
+        }
+    }
+    Ok(migrations)
+}
+
+// Signers allowed to write the fields, given by `#[writable_by = ".."]`.
+pub(crate) fn field_permissions(fields: &syn::Fields) -> Result<Vec<IdlPermission>> {
+    let mut permissions = vec![];
+    for field in fields.iter() {
+        if let Some(writable_by) = parser::permissions::writable_by(&field.attrs)? {
+            permissions.push(IdlPermission {
+                field: field.ident.as_ref().unwrap().to_string().to_mixed_case(),
+                writable_by: writable_by.to_mixed_case(),
+            });
+        }
+    }
+    Ok(permissions)
+}
+
+// Space of an account deriving `InitSpace`, discriminator and checksum
+// included, computed as its `INIT_SPACE` is.
+fn account_size(
+    ctx: &CrateContext,
+    module: &str,
+    strct: &syn::ItemStruct,
+    overhead: usize,
+) -> Result<Option<u64>> {
+    if !parser::space::derives_init_space(&strct.attrs) {
+        return Ok(None);
+    }
+    let space = parser::space::fields_space(&strct.fields)?;
+    Ok(Some((overhead + eval_space(ctx, module, &space)?) as u64))
+}
+
+fn eval_space(ctx: &CrateContext, module: &str, space: &parser::space::Space) -> Result<usize> {
+    space.eval(
+        &mut |ty: &syn::Type| defined_space(ctx, ty),
+        &mut |len: &syn::Expr| eval_len(ctx, module, len),
+    )
+}
+
+// `INIT_SPACE` of a type of the crate deriving `InitSpace`, found by name.
+fn defined_space(ctx: &CrateContext, ty: &syn::Type) -> Result<usize> {
+    let name = match ty {
+        syn::Type::Path(ty_path) => ty_path.path.segments.last().unwrap().ident.to_string(),
+        _ => parser::tts_to_string(ty),
+    };
+    for module in ctx.modules() {
+        let space = match module
+            .structs()
+            .find(|strct| strct.ident == name && parser::space::derives_init_space(&strct.attrs))
+        {
+            Some(strct) => Some(parser::space::fields_space(&strct.fields)?),
+            None => match module
+                .enums()
+                .find(|enm| enm.ident == name && parser::space::derives_init_space(&enm.attrs))
+            {
+                Some(enm) => Some(parser::space::enum_space(enm.variants.iter())?),
+                None => None,
+            },
+        };
+        if let Some(space) = space {
+            return eval_space(ctx, &module.path(), &space);
+        }
+    }
+    Err(anyhow!(
+        "Unable to compute the space of `{}`, which must derive InitSpace",
+        name
+    ))
+}
+
+// Value of the `space` of an `init` account, when given with integers,
+// constants of the crate and the `INIT_SPACE` of types, e.g.
+// `8 + Profile::INIT_SPACE`. Spaces depending on the instruction's arguments
+// aren't known.
+fn eval_space_expr(ctx: &CrateContext, expr: &syn::Expr) -> Option<u64> {
+    match expr {
+        syn::Expr::Paren(paren) => eval_space_expr(ctx, &paren.expr),
+        syn::Expr::Cast(cast) => eval_space_expr(ctx, &cast.expr),
+        syn::Expr::Binary(binary) => {
+            let left = eval_space_expr(ctx, &binary.left)?;
+            let right = eval_space_expr(ctx, &binary.right)?;
+            match binary.op {
+                syn::BinOp::Add(_) => left.checked_add(right),
+                syn::BinOp::Sub(_) => left.checked_sub(right),
+                syn::BinOp::Mul(_) => left.checked_mul(right),
+                _ => None,
+            }
+        }
+        syn::Expr::Path(path)
+            if path.qself.is_none()
+                && path.path.segments.len() > 1
+                && path.path.segments.last().unwrap().ident == "INIT_SPACE" =>
+        {
+            let mut ty = path.path.clone();
+            ty.segments.pop();
+            let ty = syn::Type::Path(syn::TypePath {
+                qself: None,
+                path: ty,
+            });
+            defined_space(ctx, &ty).ok().map(|space| space as u64)
+        }
+        _ => eval_len(ctx, "crate", expr).ok().map(|space| space as u64),
+    }
+}
+
+// Value of a `#[max_len(..)]` or array length, given as an integer or as a
+// constant of the crate.
+fn eval_len(ctx: &CrateContext, module: &str, len: &syn::Expr) -> Result<usize> {
+    match len {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(int),
+            ..
+        }) => Ok(int.base10_parse()?),
+        syn::Expr::Paren(paren) => eval_len(ctx, module, &paren.expr),
+        syn::Expr::Cast(cast) => eval_len(ctx, module, &cast.expr),
+        syn::Expr::Path(expr) => {
+            let segments = expr
+                .path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect::<Vec<_>>();
+            let constant = resolve_len_const(ctx, module, &segments)?.ok_or_else(|| {
+                anyhow!(
+                    "Unable to resolve the length `{}` used in `{}`",
+                    segments.join("::"),
+                    module
+                )
+            })?;
+            eval_len(ctx, module, &constant.expr)
+        }
+        _ => Err(anyhow!(
+            "Unable to compute the length `{}` used in `{}`",
+            parser::tts_to_string(len),
+            module
+        )),
+    }
+}
+
+// Replaces the constants used as array lengths in `ty`, e.g. `[u8; LEN]`,
+// with their value. Constants are resolved from the module at `module`, the
+// one the type is used in.
+fn resolve_array_lengths(ctx: &CrateContext, module: &str, ty: &mut syn::Type) -> Result<()> {
+    match ty {
+        syn::Type::Array(array) => {
+            resolve_array_lengths(ctx, module, &mut array.elem)?;
+            let path = match &array.len {
+                syn::Expr::Path(expr) => expr.path.clone(),
+                syn::Expr::Cast(cast) => match &*cast.expr {
+                    syn::Expr::Path(expr) => expr.path.clone(),
+                    _ => return Ok(()),
+                },
+                _ => return Ok(()),
+            };
+            let segments = path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect::<Vec<_>>();
+            let constant = resolve_len_const(ctx, module, &segments)?.ok_or_else(|| {
+                anyhow!(
+                    "Unable to resolve the array length `{}` used in `{}`",
+                    segments.join("::"),
+                    module
+                )
+            })?;
+            array.len = (*constant.expr).clone();
+        }
+        syn::Type::Path(path) => {
+            for segment in path.path.segments.iter_mut() {
+                if let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                    for arg in args.args.iter_mut() {
+                        resolve_generic_arg(ctx, module, arg)?;
+                    }
+                }
+            }
+        }
+        syn::Type::Tuple(tuple) => {
+            for elem in tuple.elems.iter_mut() {
+                resolve_array_lengths(ctx, module, elem)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Replaces a const generic argument, e.g. the `LEN` of `Buffer<LEN>`, with its
+// value, so that instantiations of a type are named after the values of their
+// parameters.
+fn resolve_generic_arg(
+    ctx: &CrateContext,
+    module: &str,
+    arg: &mut syn::GenericArgument,
+) -> Result<()> {
+    let value = match &*arg {
+        syn::GenericArgument::Const(expr) => Some(eval_len(ctx, module, expr)?),
+        // Constants given by name are parsed as types.
+        syn::GenericArgument::Type(syn::Type::Path(path)) if path.qself.is_none() => {
+            let segments = path
+                .path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect::<Vec<_>>();
+            match resolve_len_const(ctx, module, &segments)? {
+                Some(constant) => Some(eval_len(ctx, module, &constant.expr)?),
+                None => None,
+            }
+        }
+        _ => None,
+    };
+    match (value, arg) {
+        (Some(value), arg) => {
+            let value = proc_macro2::Literal::usize_unsuffixed(value);
+            *arg = syn::GenericArgument::Const(syn::parse_quote!(#value));
+        }
+        (None, syn::GenericArgument::Type(ty)) => resolve_array_lengths(ctx, module, ty)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+// Resolves the constant of an array length given by `segments`, a path
+// relative to `module`. Constants given by name are the ones defined in or
+// imported into the module, or failing that, the constant of the crate with
+// that name, as long as all of them have the same value.
+fn resolve_len_const<'a>(
+    ctx: &'a CrateContext,
+    module: &str,
+    segments: &[String],
+) -> Result<Option<&'a syn::ItemConst>> {
+    if let Some(constant) = lookup_const(ctx, module, segments, 0) {
+        return Ok(Some(constant));
+    }
+    let name = match segments {
+        [name] => name,
+        _ => return Ok(None),
+    };
+    let candidates = ctx
+        .modules()
+        .flat_map(|m| {
+            m.consts()
+                .filter(move |c| c.ident == name && is_len_const(c))
+                .map(move |c| (m.path(), c))
+        })
+        .collect::<Vec<_>>();
+    match candidates.split_first() {
+        None => Ok(None),
+        Some(((_, first), rest)) if rest.iter().all(|(_, c)| c.expr == first.expr) => {
+            Ok(Some(first))
+        }
+        Some(_) => Err(anyhow!(
+            "The array length `{}` used in `{}` is ambiguous, as constants with different \
+             values are defined in {}. Define or import the constant in `{}`, or use its path.",
+            name,
+            module,
+            candidates
+                .iter()
+                .map(|(path, _)| format!("`{}`", path))
+                .collect::<Vec<_>>()
+                .join(", "),
+            module
+        )),
+    }
+}
+
+// Imports are followed at most this many times when looking up a constant,
+// so that cyclic glob imports end.
+const MAX_IMPORT_DEPTH: usize = 8;
+
+// Looks up the constant at the path given by `segments` from the module at
+// `module`, following its imports.
+fn lookup_const<'a>(
+    ctx: &'a CrateContext,
+    module: &str,
+    segments: &[String],
+    depth: usize,
+) -> Option<&'a syn::ItemConst> {
+    if depth > MAX_IMPORT_DEPTH {
+        return None;
+    }
+    let (first, rest) = segments.split_first()?;
+    match first.as_str() {
+        "crate" => return lookup_const(ctx, "crate", rest, depth),
+        "self" => return lookup_const(ctx, module, rest, depth),
+        "super" => return lookup_const(ctx, module.rsplit_once("::")?.0, rest, depth),
+        _ => {}
+    }
+    let module_ctx = ctx.module(module)?;
+    if rest.is_empty() {
+        if let Some(constant) = module_ctx
+            .consts()
+            .find(|c| c.ident == first && is_len_const(c))
+        {
+            return Some(constant);
+        }
+    } else {
+        let submodule = format!("{}::{}", module, first);
+        if ctx.module(&submodule).is_some() {
+            return lookup_const(ctx, &submodule, rest, depth);
+        }
+    }
+    let mut imports = vec![];
+    for item in module_ctx.items() {
+        if let syn::Item::Use(item_use) = item {
+            collect_imports(&item_use.tree, &mut vec![], &mut imports);
+        }
+    }
+    imports.into_iter().find_map(|import| match import {
+        Import::Name { name, mut path } if name == *first => {
+            path.extend_from_slice(rest);
+            lookup_const(ctx, module, &path, depth + 1)
+        }
+        Import::Glob(mut path) => {
+            path.extend_from_slice(segments);
+            lookup_const(ctx, module, &path, depth + 1)
+        }
+        _ => None,
+    })
+}
+
+// An item imported by a `use` declaration.
+enum Import {
+    // An item imported under `name`, e.g. `use crate::consts::LEN as name`.
+    Name { name: String, path: Vec<String> },
+    // All items of a module, e.g. `use crate::consts::*`.
+    Glob(Vec<String>),
+}
+
+fn collect_imports(tree: &syn::UseTree, prefix: &mut Vec<String>, imports: &mut Vec<Import>) {
+    match tree {
+        syn::UseTree::Path(use_path) => {
+            prefix.push(use_path.ident.to_string());
+            collect_imports(&use_path.tree, prefix, imports);
+            prefix.pop();
+        }
+        syn::UseTree::Name(use_name) if use_name.ident == "self" => {
+            if let Some(name) = prefix.last() {
+                imports.push(Import::Name {
+                    name: name.clone(),
+                    path: prefix.clone(),
+                });
+            }
+        }
+        syn::UseTree::Name(use_name) => imports.push(Import::Name {
+            name: use_name.ident.to_string(),
+            path: [prefix.as_slice(), &[use_name.ident.to_string()]].concat(),
+        }),
+        syn::UseTree::Rename(use_rename) => imports.push(Import::Name {
+            name: use_rename.rename.to_string(),
+            path: [prefix.as_slice(), &[use_rename.ident.to_string()]].concat(),
+        }),
+        syn::UseTree::Glob(_) => imports.push(Import::Glob(prefix.clone())),
+        syn::UseTree::Group(group) => {
+            for tree in &group.items {
+                collect_imports(tree, prefix, imports);
+            }
+        }
+    }
+}
+
+// Returns true if the constant is of an integer type, i.e. can be an array
+// length.
+fn is_len_const(constant: &syn::ItemConst) -> bool {
+    match &*constant.ty {
+        syn::Type::Path(p) => {
+            let segment = p.path.segments.last().unwrap();
+            matches!(
+                segment.ident.to_string().as_str(),
+                "usize"
+                    | "u8"
+                    | "u16"
+                    | "u32"
+                    | "u64"
+                    | "u128"
+                    | "isize"
+                    | "i8"
+                    | "i16"
+                    | "i32"
+                    | "i64"
+                    | "i128"
+            )
+        }
+        _ => false,
     }
+}
+
+pub(crate) fn to_idl_type(ctx: &CrateContext, module: &str, ty: &syn::Type) -> Result<IdlType> {
+    let mut ty = ty.clone();
+    resolve_array_lengths(ctx, module, &mut ty)?;
+    let mut tts_string = parser::tts_to_string(&ty);
     // Box<FooType> -> FooType
     tts_string = tts_string
         .strip_prefix("Box < ")
@@ -612,40 +2060,3086 @@ fn to_idl_type(ctx: &CrateContext, ty: &syn::Type) -> IdlType {
         .unwrap_or(&tts_string)
         .into();
 
-    tts_string.parse().unwrap()
+    tts_string.parse()
 }
 
+// The accounts of an `Accounts` struct, with the ones of composite fields
+// either nested in a group, or flattened into the list with `flatten`.
 fn idl_accounts(
     ctx: &CrateContext,
     accounts: &AccountsStruct,
     global_accs: &HashMap<String, AccountsStruct>,
+    external_accounts: &ExternalAccounts,
     seeds_feature: bool,
     no_docs: bool,
+    flatten: bool,
 ) -> Vec<IdlAccountItem> {
-    accounts
+    let items = accounts
         .fields
         .iter()
-        .map(|acc: &AccountField| match acc {
+        .flat_map(|acc: &AccountField| match acc {
             AccountField::CompositeField(comp_f) => {
-                let accs_strct = global_accs.get(&comp_f.symbol).unwrap_or_else(|| {
-                    panic!("Could not resolve Accounts symbol {}", comp_f.symbol)
-                });
-                let accounts = idl_accounts(ctx, accs_strct, global_accs, seeds_feature, no_docs);
-                IdlAccountItem::IdlAccounts(IdlAccounts {
-                    name: comp_f.ident.to_string().to_mixed_case(),
-                    accounts,
-                })
+                let nested = match global_accs.get(&comp_f.symbol) {
+                    Some(accs_strct) => idl_accounts(
+                        ctx,
+                        accs_strct,
+                        global_accs,
+                        external_accounts,
+                        seeds_feature,
+                        no_docs,
+                        false,
+                    ),
+                    None => external_accounts
+                        .resolve(&comp_f.symbol, seeds_feature, no_docs)
+                        .unwrap_or_else(|| {
+                            panic!("Could not resolve Accounts symbol {}", comp_f.symbol)
+                        }),
+                };
+                match flatten {
+                    true => flatten_items(&comp_f.ident.to_string(), nested),
+                    false => vec![IdlAccountItem::IdlAccounts(IdlAccounts {
+                        name: comp_f.ident.to_string().to_mixed_case(),
+                        accounts: nested,
+                    })],
+                }
+            }
+            AccountField::Field(acc) => {
+                let (kind, address) = account_kind(Some(ctx), &acc.ty);
+                vec![IdlAccountItem::IdlAccount(Box::new(IdlAccount {
+                    name: acc.ident.to_string().to_mixed_case(),
+                    is_mut: acc.constraints.is_mutable(),
+                    is_signer: match acc.ty {
+                        Ty::Signer => true,
+                        _ => acc.constraints.is_signer(),
+                    },
+                    is_optional: acc.is_optional.then(|| true),
+                    is_init_if_needed: acc.constraints.is_init_if_needed().then(|| true),
+                    space: acc
+                        .constraints
+                        .init
+                        .as_ref()
+                        .and_then(|init| init.space.as_ref())
+                        .and_then(|space| eval_space_expr(ctx, space)),
+                    docs: if !no_docs { acc.docs.clone() } else { None },
+                    token: token_account(&acc.constraints),
+                    realloc: realloc(Some(ctx), &acc.constraints),
+                    pda: pda::parse(ctx, accounts, acc, seeds_feature)
+                        .or_else(|| metadata_pda(&acc.constraints)),
+                    kind,
+                    address,
+                    programs: account_programs(&acc.ty),
+                    close: close_destination(&acc.constraints),
+                }))]
+            }
+        })
+        .collect::<Vec<_>>();
+    if flatten {
+        let mut names = HashSet::new();
+        for item in &items {
+            if let IdlAccountItem::IdlAccount(acc) = item {
+                if !names.insert(&acc.name) {
+                    panic!(
+                        "Flattened account {} of {} is defined more than once",
+                        acc.name, accounts.ident
+                    );
+                }
+            }
+        }
+    }
+    items
+}
+
+// Lists the accounts of the composite field `prefix` in place of it, prefixing
+// their names and the accounts their seeds refer to with the field's name.
+fn flatten_items(prefix: &str, items: Vec<IdlAccountItem>) -> Vec<IdlAccountItem> {
+    items
+        .into_iter()
+        .flat_map(|item| match item {
+            IdlAccountItem::IdlAccounts(accs) => {
+                let prefix = format!("{}_{}", prefix, accs.name.to_snake_case());
+                flatten_items(&prefix, accs.accounts)
+            }
+            IdlAccountItem::IdlAccount(mut acc) => {
+                acc.name = format!("{}_{}", prefix, acc.name.to_snake_case()).to_mixed_case();
+                if let Some(close) = &mut acc.close {
+                    *close = format!("{}_{}", prefix, close.to_snake_case()).to_mixed_case();
+                }
+                if let Some(pda) = &mut acc.pda {
+                    for seed in pda.seeds.iter_mut().chain(pda.program_id.as_mut()) {
+                        if let IdlSeed::Account(seed) = seed {
+                            seed.path = format!("{}_{}", prefix, seed.path);
+                        }
+                    }
+                }
+                vec![IdlAccountItem::IdlAccount(acc)]
             }
-            AccountField::Field(acc) => IdlAccountItem::IdlAccount(IdlAccount {
-                name: acc.ident.to_string().to_mixed_case(),
-                is_mut: acc.constraints.is_mutable(),
-                is_signer: match acc.ty {
-                    Ty::Signer => true,
-                    _ => acc.constraints.is_signer(),
-                },
-                docs: if !no_docs { acc.docs.clone() } else { None },
-                pda: pda::parse(ctx, accounts, acc, seeds_feature),
-            }),
         })
+        .collect()
+}
+
+// The kind of `Sysvar` and `Program` accounts, along with their address if it
+// is known, so that clients can fill them in. Without the crate's context, the
+// address of the crate's own programs isn't known.
+pub(crate) fn account_kind(
+    ctx: Option<&CrateContext>,
+    ty: &Ty,
+) -> (Option<IdlAccountKind>, Option<String>) {
+    match ty {
+        Ty::Sysvar(sysvar) => {
+            let (name, address) = match sysvar {
+                SysvarTy::Clock => ("Clock", "SysvarC1ock11111111111111111111111111111111"),
+                SysvarTy::Rent => ("Rent", "SysvarRent111111111111111111111111111111111"),
+                SysvarTy::EpochSchedule => (
+                    "EpochSchedule",
+                    "SysvarEpochSchedu1e111111111111111111111111",
+                ),
+                SysvarTy::Fees => ("Fees", "SysvarFees111111111111111111111111111111111"),
+                SysvarTy::RecentBlockhashes => (
+                    "RecentBlockhashes",
+                    "SysvarRecentB1ockHashes11111111111111111111",
+                ),
+                SysvarTy::SlotHashes => {
+                    ("SlotHashes", "SysvarS1otHashes111111111111111111111111111")
+                }
+                SysvarTy::SlotHistory => {
+                    ("SlotHistory", "SysvarS1otHistory11111111111111111111111111")
+                }
+                SysvarTy::StakeHistory => (
+                    "StakeHistory",
+                    "SysvarStakeHistory1111111111111111111111111",
+                ),
+                SysvarTy::Instructions => (
+                    "Instructions",
+                    "Sysvar1nstructions1111111111111111111111111",
+                ),
+                SysvarTy::Rewards => ("Rewards", "SysvarRewards111111111111111111111111111111"),
+            };
+            (
+                Some(IdlAccountKind::Sysvar(name.to_string())),
+                Some(address.to_string()),
+            )
+        }
+        Ty::Program(program) => {
+            let segments: Vec<String> = program
+                .account_type_path
+                .path
+                .segments
+                .iter()
+                .map(|s| s.ident.to_string())
+                .collect();
+            let name = segments.last().cloned().unwrap_or_default();
+            let address = match name.as_str() {
+                "System" => Some("11111111111111111111111111111111".to_string()),
+                "Token" => Some("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA".to_string()),
+                "AssociatedToken" => {
+                    Some("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL".to_string())
+                }
+                "BpfLoaderUpgradeable" => {
+                    Some("BPFLoaderUpgradeab1e11111111111111111111111".to_string())
+                }
+                // Programs generated by `#[program]`, i.e.,
+                // `<module>::program::<Name>`, with the module declaring the id.
+                _ => match (ctx, segments.iter().position(|s| s == "program")) {
+                    (Some(ctx), Some(0)) => ctx.declared_id(None),
+                    (Some(ctx), Some(idx)) => {
+                        let module = &segments[idx - 1];
+                        match module.as_str() {
+                            "crate" | "self" | "super" => ctx.declared_id(None),
+                            module => ctx.declared_id(Some(module)),
+                        }
+                    }
+                    _ => None,
+                },
+            };
+            (Some(IdlAccountKind::Program(name)), address)
+        }
+        Ty::Interface(interface) => {
+            let name = interface
+                .account_type_path
+                .path
+                .segments
+                .last()
+                .map(|s| s.ident.to_string())
+                .unwrap_or_default();
+            (Some(IdlAccountKind::Interface(name)), None)
+        }
+        _ => (None, None),
+    }
+}
+
+// The programs an `Interface` may be, or an `InterfaceAccount` may be owned
+// by, for the interfaces of `anchor_spl`. Those of any other interface are only
+// known once compiled.
+pub(crate) fn account_programs(ty: &Ty) -> Option<Vec<String>> {
+    let path = match ty {
+        Ty::Interface(interface) => &interface.account_type_path,
+        Ty::InterfaceAccount(account) => &account.account_type_path,
+        _ => return None,
+    };
+    match path.path.segments.last()?.ident.to_string().as_str() {
+        "TokenInterface" | "TokenAccount" | "Mint" => Some(vec![
+            "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA".to_string(),
+            "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb".to_string(),
+        ]),
+        _ => None,
+    }
+}
+
+// The token account metadata given by the `token::*` and `associated_token::*`
+// constraints of an account, whether it's created by the instruction or not.
+pub(crate) fn token_account(constraints: &ConstraintGroup) -> Option<IdlTokenAccount> {
+    let (mint, authority, is_associated) = match (
+        constraints.init.as_ref().map(|init| &init.kind),
+        &constraints.token_account,
+        &constraints.associated_token,
+    ) {
+        (Some(InitKind::Token { owner, mint }), _, _) => (Some(mint), Some(owner), false),
+        (Some(InitKind::AssociatedToken { owner, mint }), _, _) => (Some(mint), Some(owner), true),
+        (_, _, Some(associated)) => (Some(&associated.mint), Some(&associated.wallet), true),
+        (_, Some(token), _) => (token.mint.as_ref(), token.authority.as_ref(), false),
+        _ => return None,
+    };
+    Some(IdlTokenAccount {
+        mint: mint.and_then(account_path),
+        authority: authority.and_then(account_path),
+        is_associated: is_associated.then(|| true),
+    })
+}
+
+// The address of the Metaplex metadata program.
+const METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+
+// The seeds of the metadata account of a mint given by `metadata::mint`, i.e.
+// `["metadata", <metadata program>, <mint>]` with the metadata program, for
+// clients to derive it. They're always known, so unlike those of `seeds`, they
+// don't depend on the `seeds` feature.
+pub(crate) fn metadata_pda(constraints: &ConstraintGroup) -> Option<IdlPda> {
+    let mint = constraints.metadata.as_ref()?.mint.as_ref()?;
+    let path = account_path(mint)?
+        .split('.')
+        .map(|component| component.to_snake_case())
         .collect::<Vec<_>>()
+        .join(".");
+    let program_id = IdlSeed::Const(IdlSeedConst {
+        ty: IdlType::PublicKey,
+        value: serde_json::Value::String(METADATA_PROGRAM_ID.to_string()),
+    });
+    Some(IdlPda {
+        seeds: vec![
+            IdlSeed::Const(IdlSeedConst {
+                ty: IdlType::String,
+                value: serde_json::Value::String("metadata".to_string()),
+            }),
+            program_id.clone(),
+            IdlSeed::Account(IdlSeedAccount {
+                ty: IdlType::PublicKey,
+                account: None,
+                path,
+            }),
+        ],
+        program_id: Some(program_id),
+        bump: None,
+    })
+}
+
+// The `realloc` constraints of an account, if any. Without the crate's context,
+// the new space isn't known.
+pub(crate) fn realloc(
+    ctx: Option<&CrateContext>,
+    constraints: &ConstraintGroup,
+) -> Option<IdlRealloc> {
+    let realloc = constraints.realloc.as_ref()?;
+    Some(IdlRealloc {
+        payer: account_path(&realloc.payer),
+        space: ctx.and_then(|ctx| eval_space_expr(ctx, &realloc.space)),
+        zero: match &realloc.zero {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Bool(zero),
+                ..
+            }) => Some(zero.value),
+            _ => None,
+        },
+    })
+}
+
+// The account receiving the lamports of a closed account, given by `close`.
+pub(crate) fn close_destination(constraints: &ConstraintGroup) -> Option<String> {
+    let close = constraints.close.as_ref()?;
+    Some(close.sol_dest.to_string().to_mixed_case())
+}
+
+// The path to an account, or to a field of an account, given by an
+// expression such as `mint`, `mint.key()` or `vault.mint`.
+fn account_path(expr: &syn::Expr) -> Option<String> {
+    match expr {
+        syn::Expr::Path(path) => path
+            .path
+            .get_ident()
+            .map(|ident| ident.to_string().to_mixed_case()),
+        syn::Expr::Field(field) => match &field.member {
+            syn::Member::Named(ident) => Some(format!(
+                "{}.{}",
+                account_path(&field.base)?,
+                ident.to_string().to_mixed_case()
+            )),
+            syn::Member::Unnamed(_) => None,
+        },
+        syn::Expr::MethodCall(call) if call.method == "key" && call.args.is_empty() => {
+            account_path(&call.receiver)
+        }
+        syn::Expr::Reference(reference) => account_path(&reference.expr),
+        syn::Expr::Paren(paren) => account_path(&paren.expr),
+        _ => None,
+    }
+}
+
+// `Accounts` structs defined outside of the program's crate, e.g. in a shared
+// CPI crate, used to resolve composite account fields.
+#[derive(Default)]
+pub struct ExternalAccounts {
+    crates: Vec<(CrateContext, HashMap<String, AccountsStruct>)>,
+    idl_accounts: HashMap<String, Vec<IdlAccountItem>>,
+}
+
+impl ExternalAccounts {
+    // Registers all the `Accounts` structs of the crate with the given root
+    // file, e.g. `shared/src/lib.rs`.
+    pub fn register_source(&mut self, filename: impl AsRef<Path>) -> Result<()> {
+        let ctx = CrateContext::parse(filename)?;
+        let accs = parse_account_derives(&ctx);
+        if !accs.is_empty() {
+            self.crates.push((ctx, accs));
+        }
+        Ok(())
+    }
+
+    // Registers the accounts of an instruction in another program's IDL as the
+    // `Accounts` struct with the given name.
+    pub fn register_idl(
+        &mut self,
+        symbol: impl Into<String>,
+        idl: &Idl,
+        instruction: &str,
+    ) -> Result<()> {
+        let ix = idl
+            .instructions
+            .iter()
+            .find(|ix| ix.name == instruction || ix.name == instruction.to_mixed_case())
+            .ok_or_else(|| {
+                anyhow::anyhow!("Instruction {} not found in IDL {}", instruction, idl.name)
+            })?;
+        self.idl_accounts.insert(symbol.into(), ix.accounts.clone());
+        Ok(())
+    }
+
+    fn resolve(
+        &self,
+        symbol: &str,
+        seeds_feature: bool,
+        no_docs: bool,
+    ) -> Option<Vec<IdlAccountItem>> {
+        if let Some(accounts) = self.idl_accounts.get(symbol) {
+            return Some(accounts.clone());
+        }
+        self.crates.iter().find_map(|(ctx, accs)| {
+            accs.get(symbol)
+                .map(|strct| idl_accounts(ctx, strct, accs, self, seeds_feature, no_docs, false))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::program::common::SIGHASH_GLOBAL_NAMESPACE;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Writes the root file of a crate to a path of its own, since tests run in
+    // parallel.
+    fn write_src(src: &str) -> std::path::PathBuf {
+        static CRATES: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "anchor_syn_file_{}_{}.rs",
+            std::process::id(),
+            CRATES.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, src).unwrap();
+        path
+    }
+
+    // The IDL of the crate with the root file `src`, parsed with the default
+    // options.
+    fn parse_src(src: &str) -> Idl {
+        parse(
+            write_src(src),
+            &ParseOptions::default(),
+            &ExternalAccounts::default(),
+        )
+        .unwrap()
+        .unwrap()
+        .idl
+    }
+
+    #[test]
+    fn sysvar_and_program_accounts() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod kinds {
+                use super::*;
+                pub fn init(ctx: Context<Init>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Init<'info> {
+                pub rent: Sysvar<'info, Rent>,
+                pub system_program: Program<'info, System>,
+                pub this_program: Program<'info, crate::program::Kinds>,
+                pub other_program: Program<'info, Other>,
+            }
+            "#,
+        );
+        let accounts: Vec<(Option<IdlAccountKind>, Option<String>)> = idl.instructions[0]
+            .accounts
+            .iter()
+            .map(|acc| match acc {
+                IdlAccountItem::IdlAccount(acc) => (acc.kind.clone(), acc.address.clone()),
+                IdlAccountItem::IdlAccounts(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            accounts,
+            vec![
+                (
+                    Some(IdlAccountKind::Sysvar("Rent".to_string())),
+                    Some("SysvarRent111111111111111111111111111111111".to_string())
+                ),
+                (
+                    Some(IdlAccountKind::Program("System".to_string())),
+                    Some("11111111111111111111111111111111".to_string())
+                ),
+                (
+                    Some(IdlAccountKind::Program("Kinds".to_string())),
+                    Some("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS".to_string())
+                ),
+                (Some(IdlAccountKind::Program("Other".to_string())), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn interface_accounts() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+            use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+            #[program]
+            pub mod vault {
+                use super::*;
+                pub fn deposit(ctx: Context<Deposit>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Deposit<'info> {
+                #[account(mut, token::mint = mint)]
+                pub vault: InterfaceAccount<'info, TokenAccount>,
+                pub mint: InterfaceAccount<'info, Mint>,
+                pub token_program: Interface<'info, TokenInterface>,
+            }
+            "#,
+        );
+        let token_programs = Some(vec![
+            "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA".to_string(),
+            "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb".to_string(),
+        ]);
+        let accounts: Vec<(Option<IdlAccountKind>, Option<Vec<String>>)> = idl.instructions[0]
+            .accounts
+            .iter()
+            .map(|acc| match acc {
+                IdlAccountItem::IdlAccount(acc) => (acc.kind.clone(), acc.programs.clone()),
+                IdlAccountItem::IdlAccounts(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            accounts,
+            vec![
+                (None, token_programs.clone()),
+                (None, token_programs.clone()),
+                (
+                    Some(IdlAccountKind::Interface("TokenInterface".to_string())),
+                    token_programs
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn account_init_space_size() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            pub const NAME_LEN: usize = 32;
+
+            #[program]
+            pub mod profiles {
+                use super::*;
+                pub fn init(ctx: Context<Init>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Init<'info> {
+                pub profile: Account<'info, Profile>,
+            }
+
+            #[account]
+            #[derive(InitSpace)]
+            pub struct Profile {
+                pub authority: Pubkey,
+                #[max_len(NAME_LEN)]
+                pub name: String,
+                #[max_len(3, 8)]
+                pub tags: Vec<String>,
+                pub status: Status,
+                pub bump: u8,
+                pub scores: [u16; 4],
+            }
+
+            #[account]
+            pub struct Unsized {
+                pub names: Vec<String>,
+            }
+
+            #[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+            pub enum Status {
+                Open,
+                Closed { at: Option<i64> },
+            }
+            "#,
+        );
+        let sizes: Vec<(&str, Option<u64>)> = idl
+            .accounts
+            .iter()
+            .map(|acc| (acc.name.as_str(), acc.size))
+            .collect();
+        // 8 + 32 + (4 + 32) + (4 + 3 * (4 + 8)) + (1 + 1 + 8) + 1 + 2 * 4
+        assert_eq!(sizes, vec![("Profile", Some(135)), ("Unsized", None)]);
+    }
+
+    #[test]
+    fn arg_constraints() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod vault {
+                use super::*;
+                pub fn deposit(
+                    ctx: Context<Deposit>,
+                    #[arg(non_zero)] amount: u64,
+                    #[arg(range = 1..10_001)] fee_bps: u16,
+                    #[arg(range = -5..)] offset: i8,
+                    #[arg(max_len = 32)] memo: String,
+                    note: String,
+                ) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Deposit {}
+            "#,
+        );
+        let constraints: Vec<Vec<IdlArgConstraint>> = idl.instructions[0]
+            .args
+            .iter()
+            .map(|arg| arg.constraints.clone())
+            .collect();
+        assert_eq!(
+            constraints,
+            vec![
+                vec![IdlArgConstraint::NonZero],
+                vec![IdlArgConstraint::Range {
+                    min: Some("1".to_string()),
+                    max: Some("10000".to_string()),
+                }],
+                vec![IdlArgConstraint::Range {
+                    min: Some("-5".to_string()),
+                    max: None,
+                }],
+                vec![IdlArgConstraint::MaxLen(32)],
+                vec![],
+            ]
+        );
+        let json = serde_json::to_value(&idl.instructions[0].args).unwrap();
+        assert_eq!(json[0]["constraints"], serde_json::json!(["nonZero"]));
+        assert_eq!(
+            json[1]["constraints"],
+            serde_json::json!([{ "range": { "min": "1", "max": "10000" } }])
+        );
+        assert!(json[4].get("constraints").is_none());
+    }
+
+    #[test]
+    fn account_checksums() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod treasury {
+                use super::*;
+            }
+
+            #[account(checksum)]
+            #[derive(InitSpace)]
+            pub struct Treasury {
+                pub authority: Pubkey,
+                pub balance: u64,
+            }
+
+            #[account]
+            #[derive(InitSpace)]
+            pub struct Position {
+                pub owner: Pubkey,
+            }
+            "#,
+        );
+        let accounts: Vec<(&str, Option<IdlChecksum>, Option<u64>)> = idl
+            .accounts
+            .iter()
+            .map(|acc| (acc.name.as_str(), acc.checksum, acc.size))
+            .collect();
+        // 8 + 32 + 8 + 4
+        assert_eq!(
+            accounts,
+            vec![
+                ("Treasury", Some(IdlChecksum::Crc32), Some(52)),
+                ("Position", None, Some(40)),
+            ]
+        );
+        let json = serde_json::to_value(&idl.accounts[0]).unwrap();
+        assert_eq!(json["checksum"], "crc32");
+    }
+
+    #[test]
+    fn zero_copy_serialization() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod books {
+                use super::*;
+            }
+
+            #[account(zero_copy)]
+            pub struct Book {
+                pub authority: Pubkey,
+                pub orders: [Order; 4],
+            }
+
+            #[account(zero_copy(unsafe))]
+            #[repr(packed)]
+            pub struct Ledger {
+                pub count: u8,
+                pub total: u64,
+            }
+
+            #[account]
+            pub struct Config {
+                pub admin: Pubkey,
+            }
+
+            #[zero_copy]
+            pub struct Order {
+                pub price: u64,
+                pub size: u64,
+            }
+            "#,
+        );
+        fn serialization(ty_defs: &[IdlTypeDefinition]) -> Vec<(&str, IdlSerialization)> {
+            ty_defs
+                .iter()
+                .map(|ty_def| (ty_def.name.as_str(), ty_def.serialization))
+                .collect()
+        }
+        assert_eq!(
+            serialization(&idl.accounts),
+            vec![
+                ("Book", IdlSerialization::Bytemuck),
+                ("Ledger", IdlSerialization::BytemuckUnsafe),
+                ("Config", IdlSerialization::Borsh),
+            ]
+        );
+        assert_eq!(
+            serialization(&idl.types),
+            vec![("Order", IdlSerialization::Bytemuck)]
+        );
+        let json = serde_json::to_value(&idl.accounts).unwrap();
+        assert_eq!(json[1]["serialization"], "bytemuckUnsafe");
+        assert!(json[2].get("serialization").is_none());
+    }
+
+    #[test]
+    fn custom_account_discriminators() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod pools {
+                use super::*;
+            }
+
+            #[account(discriminator = [1])]
+            #[derive(InitSpace)]
+            pub struct Pool {
+                pub authority: Pubkey,
+            }
+
+            #[account(discriminator = "Vault")]
+            pub struct Treasury {
+                pub authority: Pubkey,
+            }
+
+            #[account]
+            pub struct Position {
+                pub owner: Pubkey,
+            }
+            "#,
+        );
+        let accounts: Vec<_> = idl
+            .accounts
+            .iter()
+            .map(|acc| {
+                (
+                    acc.name.as_str(),
+                    acc.discriminator.clone(),
+                    acc.account_discriminator(),
+                    acc.size,
+                )
+            })
+            .collect();
+        let vault = sighash("account", "Vault").to_vec();
+        assert_eq!(
+            accounts,
+            vec![
+                ("Pool", Some(vec![1]), vec![1], Some(33)),
+                ("Treasury", Some(vault.clone()), vault, None),
+                (
+                    "Position",
+                    None,
+                    sighash("account", "Position").to_vec(),
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn shortened_discriminators() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program(discriminator_len = 4)]
+            pub mod short {
+                use super::*;
+                pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Initialize {}
+
+            #[account]
+            pub struct Counter {
+                pub count: u64,
+            }
+
+            #[account(discriminator = [1])]
+            pub struct Pool {
+                pub count: u64,
+            }
+
+            #[account(discriminator = "LegacyVault")]
+            pub struct Vault {
+                pub count: u64,
+            }
+
+            #[account(zero_copy)]
+            pub struct Book {
+                pub count: u64,
+            }
+            "#,
+        );
+        let initialize = sighash(SIGHASH_GLOBAL_NAMESPACE, "initialize")[..4].to_vec();
+        assert_eq!(idl.instructions[0].discriminator, Some(initialize.clone()));
+        assert_eq!(
+            idl.instruction_enum.unwrap().variants[0].discriminator,
+            initialize
+        );
+        let accounts: Vec<(&str, Vec<u8>)> = idl
+            .accounts
+            .iter()
+            .map(|acc| (acc.name.as_str(), acc.account_discriminator()))
+            .collect();
+        assert_eq!(
+            accounts,
+            vec![
+                ("Counter", sighash("account", "Counter")[..4].to_vec()),
+                ("Pool", vec![1]),
+                ("Vault", sighash("account", "LegacyVault")[..4].to_vec()),
+                ("Book", sighash("account", "Book").to_vec()),
+            ]
+        );
+
+        // Accounts collide wherever they are in the crate.
+        let program = write_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod short {
+                use super::*;
+                pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Initialize {}
+
+            #[account(discriminator = [1])]
+            pub struct Pool {
+                pub count: u64,
+            }
+
+            pub mod state {
+                use super::*;
+
+                #[account(discriminator = [1, 2])]
+                pub struct Position {
+                    pub count: u64,
+                }
+            }
+            "#,
+        );
+        let err = parse(
+            &program,
+            &ParseOptions::default(),
+            &ExternalAccounts::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Accounts `Pool` and `Position` have colliding discriminators, [1] and [1, 2]"
+        );
+    }
+
+    #[test]
+    fn access_control_guards() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod guarded {
+                use super::*;
+
+                #[access_control(is_admin(&ctx) not_paused(&ctx.accounts.config))]
+                pub fn set_fee(ctx: Context<Admin>, fee_bps: u16) -> Result<()> {
+                    Ok(())
+                }
+
+                pub fn initialize(ctx: Context<Admin>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Admin {}
+            "#,
+        );
+        assert_eq!(
+            idl.instructions[0].guards,
+            vec![
+                IdlGuard {
+                    name: "is_admin".to_string(),
+                    args: vec!["&ctx".to_string()],
+                },
+                IdlGuard {
+                    name: "not_paused".to_string(),
+                    args: vec!["&ctx.accounts.config".to_string()],
+                },
+            ]
+        );
+        assert!(idl.instructions[1].guards.is_empty());
+        let json = serde_json::to_value(&idl.instructions[0]).unwrap();
+        assert_eq!(json["guards"][0]["name"], "is_admin");
+        let json = serde_json::to_value(&idl.instructions[1]).unwrap();
+        assert!(json.get("guards").is_none());
+    }
+
+    #[test]
+    fn typed_remaining_accounts() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+            use anchor_spl::token::TokenAccount;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod vaults {
+                use super::*;
+                pub fn close_all(ctx: Context<CloseAll>) -> Result<()> {
+                    Ok(())
+                }
+                pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            #[remaining_accounts(ty = anchor_spl::token::TokenAccount, mutable)]
+            pub struct CloseAll {}
+
+            #[derive(Accounts)]
+            pub struct Initialize {}
+            "#,
+        );
+        assert_eq!(
+            idl.instructions[0].remaining_accounts,
+            Some(IdlRemainingAccounts {
+                account: "TokenAccount".to_string(),
+                is_mut: true,
+            })
+        );
+        assert_eq!(idl.instructions[1].remaining_accounts, None);
+    }
+
+    #[test]
+    fn distinct_mut_accounts() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod swap {
+                use super::*;
+                pub fn swap(ctx: Context<Swap>) -> Result<()> {
+                    Ok(())
+                }
+                pub fn transfer(ctx: Context<Transfer>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            #[distinct_mut]
+            pub struct Swap<'info> {
+                #[account(mut)]
+                pub vault_a: AccountInfo<'info>,
+                #[account(mut)]
+                pub vault_b: AccountInfo<'info>,
+                #[account(mut)]
+                pub fee_vault: AccountInfo<'info>,
+                pub authority: Signer<'info>,
+            }
+
+            #[derive(Accounts)]
+            #[distinct_mut(from, to)]
+            pub struct Transfer<'info> {
+                #[account(mut)]
+                pub from: AccountInfo<'info>,
+                #[account(mut)]
+                pub to: AccountInfo<'info>,
+                #[account(mut)]
+                pub payer: Signer<'info>,
+            }
+            "#,
+        );
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(
+            idl.instructions[0].distinct_accounts,
+            vec![
+                pair("vaultA", "vaultB"),
+                pair("vaultA", "feeVault"),
+                pair("vaultB", "feeVault"),
+            ]
+        );
+        assert_eq!(
+            idl.instructions[1].distinct_accounts,
+            vec![pair("from", "to")]
+        );
+        let json = serde_json::to_value(&idl.instructions[1]).unwrap();
+        assert_eq!(
+            json["distinctAccounts"],
+            serde_json::json!([["from", "to"]])
+        );
+    }
+
+    #[test]
+    fn admin_instructions() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod vault {
+                use super::*;
+                #[admin]
+                pub fn set_fee(ctx: Context<Empty>, fee_bps: u16) -> Result<()> {
+                    Ok(())
+                }
+                pub fn deposit(ctx: Context<Empty>, amount: u64) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Empty {}
+            "#,
+        );
+        assert!(idl.instructions[0].admin);
+        assert!(!idl.instructions[1].admin);
+        let json = serde_json::to_value(&idl.instructions).unwrap();
+        assert_eq!(json[0]["admin"], serde_json::json!(true));
+        assert!(json[1].get("admin").is_none());
+    }
+
+    #[test]
+    fn batch_instructions() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod vault {
+                use super::*;
+                #[batch]
+                pub fn deposit(ctx: Context<Empty>, amount: u64) -> Result<()> {
+                    Ok(())
+                }
+                #[batch]
+                pub fn withdraw_all(ctx: Context<Empty>) -> Result<()> {
+                    Ok(())
+                }
+                pub fn close(ctx: Context<Empty>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Empty {}
+            "#,
+        );
+        assert_eq!(idl.instructions.len(), 4);
+        let batch = &idl.instructions[3];
+        assert_eq!(batch.name, "batch");
+        assert_eq!(batch.batch, vec!["deposit", "withdrawAll"]);
+        assert!(batch.accounts.is_empty());
+        assert_eq!(
+            batch.args.iter().map(|arg| &arg.ty).collect::<Vec<_>>(),
+            vec![&IdlType::Vec(Box::new(IdlType::Bytes)), &IdlType::Bytes]
+        );
+        assert!(idl.instructions[0].batch.is_empty());
+        let variants = idl.instruction_enum.unwrap().variants;
+        assert_eq!(variants[3].name, "Batch");
+        assert_eq!(
+            variants[3].discriminator,
+            sighash("global", "batch").to_vec()
+        );
+
+        let json = serde_json::to_value(&idl.instructions).unwrap();
+        assert_eq!(
+            json[3]["batch"],
+            serde_json::json!(["deposit", "withdrawAll"])
+        );
+        assert!(json[0].get("batch").is_none());
+    }
+
+    #[test]
+    fn instruction_signers() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod vault {
+                use super::*;
+                pub fn withdraw(ctx: Context<Withdraw>) -> Result<()> {
+                    Ok(())
+                }
+                pub fn poke(ctx: Context<Poke>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Withdraw<'info> {
+                pub owner: Signer<'info>,
+                #[account(mut, seeds = [b"vault", owner.key().as_ref()], bump)]
+                pub vault: AccountInfo<'info>,
+                #[account(seeds = [b"other"], bump, seeds::program = other.key())]
+                pub other_pda: AccountInfo<'info>,
+                pub other: AccountInfo<'info>,
+                pub auth: Auth<'info>,
+            }
+
+            #[derive(Accounts)]
+            pub struct Auth<'info> {
+                #[account(mut)]
+                pub payer: Signer<'info>,
+                #[account(seeds = [b"authority"], bump)]
+                pub authority: AccountInfo<'info>,
+            }
+
+            #[derive(Accounts)]
+            pub struct Poke<'info> {
+                pub clock: Sysvar<'info, Clock>,
+            }
+            "#,
+        );
+        assert_eq!(
+            idl.instructions[0].signers,
+            IdlSigners {
+                accounts: vec!["owner".to_string(), "auth.payer".to_string()],
+                pdas: vec!["vault".to_string(), "auth.authority".to_string()],
+            }
+        );
+        let json = serde_json::to_value(&idl.instructions).unwrap();
+        assert_eq!(
+            json[0]["signers"],
+            serde_json::json!({
+                "accounts": ["owner", "auth.payer"],
+                "pdas": ["vault", "auth.authority"],
+            })
+        );
+        assert!(json[1].get("signers").is_none());
+    }
+
+    #[test]
+    fn stored_bumps() {
+        let program = write_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod vault {
+                use super::*;
+                pub fn withdraw(ctx: Context<Withdraw>, bump: u8) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            #[instruction(bump: u8)]
+            pub struct Withdraw<'info> {
+                #[account(seeds = [b"vault"], bump = vault.bump)]
+                pub vault: Account<'info, Vault>,
+                #[account(seeds = [b"escrow"], bump = bump)]
+                pub escrow: AccountInfo<'info>,
+                #[account(seeds = [b"fees"], bump)]
+                pub fees: AccountInfo<'info>,
+            }
+
+            #[account]
+            pub struct Vault {
+                pub bump: u8,
+            }
+            "#,
+        );
+        let idl = parse(
+            &program,
+            &ParseOptions {
+                seeds_feature: true,
+                docs_policy: DocsPolicy::none(),
+                ..Default::default()
+            },
+            &ExternalAccounts::default(),
+        )
+        .unwrap()
+        .unwrap()
+        .idl;
+        let bump = |idx: usize| match &idl.instructions[0].accounts[idx] {
+            IdlAccountItem::IdlAccount(acc) => acc.pda.as_ref().unwrap().bump.clone(),
+            IdlAccountItem::IdlAccounts(_) => panic!("expected an account"),
+        };
+        assert_eq!(
+            bump(0),
+            Some(IdlSeed::Account(IdlSeedAccount {
+                ty: IdlType::U8,
+                account: Some("Vault".to_string()),
+                path: "vault.bump".to_string(),
+            }))
+        );
+        assert_eq!(bump(1), None);
+        assert_eq!(bump(2), None);
+    }
+
+    #[test]
+    fn close_destinations() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod vault {
+                use super::*;
+                pub fn close(ctx: Context<Close>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Close<'info> {
+                #[account(mut)]
+                pub owner: Signer<'info>,
+                #[account(mut, close = owner)]
+                pub vault: Account<'info, Vault>,
+                pub inner: CloseInner<'info>,
+            }
+
+            #[derive(Accounts)]
+            pub struct CloseInner<'info> {
+                #[account(mut)]
+                pub rent_receiver: AccountInfo<'info>,
+                #[account(mut, close = rent_receiver)]
+                pub escrow: Account<'info, Vault>,
+            }
+
+            #[account]
+            pub struct Vault {}
+            "#,
+        );
+        let closes: Vec<(String, Option<String>)> = flat_accounts(&idl.instructions[0].accounts)
+            .into_iter()
+            .map(|(name, acc)| (name, acc.close.clone()))
+            .collect();
+        assert_eq!(
+            closes,
+            vec![
+                ("owner".to_string(), None),
+                ("vault".to_string(), Some("owner".to_string())),
+                ("inner.rentReceiver".to_string(), None),
+                ("inner.escrow".to_string(), Some("rentReceiver".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn instruction_namespaces() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod vault {
+                use super::*;
+
+                #[instruction(namespace = "admin")]
+                pub fn set_fee(ctx: Context<SetFee>, fee: u16) -> Result<()> {
+                    Ok(())
+                }
+
+                pub fn deposit(ctx: Context<SetFee>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct SetFee {}
+            "#,
+        );
+        assert_eq!(idl.instructions[0].namespace, Some("admin".to_string()));
+        assert_eq!(
+            idl.instructions[0].discriminator(),
+            sighash("admin", "set_fee").to_vec()
+        );
+        assert_eq!(idl.instructions[1].namespace, None);
+        assert_eq!(
+            idl.instructions[1].discriminator(),
+            sighash(SIGHASH_GLOBAL_NAMESPACE, "deposit").to_vec()
+        );
+        let variants = idl.instruction_enum.unwrap().variants;
+        assert_eq!(
+            variants[0].discriminator,
+            idl.instructions[0].discriminator()
+        );
+
+        let program = write_src(
+            r#"
+            #[program]
+            pub mod vault {
+                use super::*;
+
+                #[instruction(namespace = "state")]
+                pub fn set_fee(ctx: Context<SetFee>) -> Result<()> {
+                    Ok(())
+                }
+            }
+            "#,
+        );
+        assert!(parse(
+            &program,
+            &ParseOptions::default(),
+            &ExternalAccounts::default(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn fallback_function() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod proxy {
+                use super::*;
+
+                pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+                    Ok(())
+                }
+
+                /// Forwards the instruction to the target program.
+                pub fn forward_raw(
+                    program_id: &Pubkey,
+                    accounts: &[AccountInfo],
+                    data: &[u8],
+                ) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Initialize {}
+            "#,
+        );
+        assert_eq!(idl.instructions.len(), 1);
+        assert_eq!(
+            idl.fallback,
+            Some(IdlFallback {
+                name: "forwardRaw".to_string(),
+                docs: Some(vec![
+                    "Forwards the instruction to the target program.".to_string()
+                ]),
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&idl).unwrap()["fallback"]["name"],
+            "forwardRaw"
+        );
+    }
+
+    #[test]
+    fn field_aliases() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod vault {
+                use super::*;
+
+                pub fn set_fee(
+                    ctx: Context<SetFee>,
+                    #[idl_name("fee", "feeRate")] fee_bps: u16,
+                ) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct SetFee {}
+
+            #[account]
+            pub struct Vault {
+                pub authority: Pubkey,
+                #[idl_name("feeRate")]
+                pub fee_bps: u16,
+            }
+            "#,
+        );
+        let args = &idl.instructions[0].args;
+        assert_eq!(args[0].name, "feeBps");
+        assert_eq!(args[0].aliases, vec!["fee", "feeRate"]);
+        let fields = match &idl.accounts[0].ty {
+            IdlTypeDefinitionTy::Struct { fields } => fields,
+            _ => panic!("Expected a struct"),
+        };
+        assert!(fields[0].aliases.is_empty());
+        assert_eq!(fields[1].aliases, vec!["feeRate"]);
+        let json = serde_json::to_value(&fields[1]).unwrap();
+        assert_eq!(json["aliases"], serde_json::json!(["feeRate"]));
+        assert!(serde_json::to_value(&fields[0])
+            .unwrap()
+            .get("aliases")
+            .is_none());
+    }
+
+    #[test]
+    fn compute_budgets() {
+        let source = |budget: &str| {
+            format!(
+                r#"
+                use anchor_lang::prelude::*;
+
+                declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+                #[program]
+                pub mod vault {{
+                    use super::*;
+
+                    {}
+                    pub fn rebalance(ctx: Context<Rebalance>) -> Result<()> {{
+                        Ok(())
+                    }}
+
+                    pub fn deposit(ctx: Context<Rebalance>) -> Result<()> {{
+                        Ok(())
+                    }}
+                }}
+
+                #[derive(Accounts)]
+                pub struct Rebalance {{}}
+                "#,
+                budget
+            )
+        };
+        let parse_idl = |budget: &str| {
+            let program = write_src(&source(budget));
+            parse(
+                &program,
+                &ParseOptions::default(),
+                &ExternalAccounts::default(),
+            )
+        };
+
+        let idl = parse_idl("#[compute_budget(units = 400_000, heap = 256_000)]")
+            .unwrap()
+            .unwrap()
+            .idl;
+        assert_eq!(
+            idl.instructions[0].compute_budget,
+            Some(IdlComputeBudget {
+                units: Some(400_000),
+                heap: Some(256_000),
+            })
+        );
+        assert_eq!(idl.instructions[1].compute_budget, None);
+        let json = serde_json::to_value(&idl.instructions[0]).unwrap();
+        assert_eq!(
+            json["computeBudget"],
+            serde_json::json!({ "units": 400000, "heap": 256000 })
+        );
+
+        let err = parse_idl("#[compute_budget(heap = 1000)]")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("multiple of 1024"), "{}", err);
+    }
+
+    #[test]
+    fn metadata_accounts() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+            use anchor_spl::metadata::MetadataAccount;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod collection {
+                use super::*;
+
+                pub fn verify(ctx: Context<Verify>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Verify<'info> {
+                pub nft_mint: Account<'info, Mint>,
+                #[account(
+                    metadata::mint = nft_mint,
+                    metadata::symbol = "APE",
+                    metadata::update_authority = authority,
+                )]
+                pub nft_metadata: Account<'info, MetadataAccount>,
+                #[account(metadata::name = "Ape")]
+                pub other_metadata: Account<'info, MetadataAccount>,
+                pub authority: Signer<'info>,
+            }
+            "#,
+        );
+        let accounts = &idl.instructions[0].accounts;
+        let pda = match &accounts[1] {
+            IdlAccountItem::IdlAccount(acc) => serde_json::to_value(&acc.pda).unwrap(),
+            _ => panic!("Expected an account"),
+        };
+        assert_eq!(
+            pda,
+            serde_json::json!({
+                "seeds": [
+                    { "kind": "const", "type": "string", "value": "metadata" },
+                    {
+                        "kind": "const",
+                        "type": "publicKey",
+                        "value": "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"
+                    },
+                    { "kind": "account", "type": "publicKey", "path": "nft_mint" }
+                ],
+                "programId": {
+                    "kind": "const",
+                    "type": "publicKey",
+                    "value": "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"
+                }
+            })
+        );
+        match &accounts[2] {
+            IdlAccountItem::IdlAccount(acc) => assert_eq!(acc.pda, None),
+            _ => panic!("Expected an account"),
+        }
+    }
+
+    #[test]
+    fn enum_accounts() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod escrow {
+                use super::*;
+
+                pub fn settle(ctx: Context<Settle>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Settle<'info> {
+                #[account(mut)]
+                pub escrow: Account<'info, Escrow>,
+            }
+
+            #[account(discriminator = [7])]
+            #[derive(InitSpace)]
+            pub enum Escrow {
+                Open { maker: Pubkey, amount: u64 },
+                Settled(Pubkey),
+                Canceled,
+            }
+
+            #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+            pub enum Side {
+                Bid,
+                Ask,
+            }
+            "#,
+        );
+        assert_eq!(idl.accounts.len(), 1);
+        let escrow = &idl.accounts[0];
+        assert_eq!(escrow.name, "Escrow");
+        assert_eq!(escrow.discriminator, Some(vec![7]));
+        // The discriminator, the variant index and the largest variant.
+        assert_eq!(escrow.size, Some(1 + 1 + 40));
+        match &escrow.ty {
+            IdlTypeDefinitionTy::Enum { variants } => {
+                let names: Vec<&str> = variants.iter().map(|v| v.name.as_str()).collect();
+                assert_eq!(names, ["Open", "Settled", "Canceled"]);
+            }
+            _ => panic!("Expected an enum"),
+        }
+        assert_eq!(idl.types.len(), 1);
+        assert_eq!(idl.types[0].name, "Side");
+        assert_eq!(idl.types[0].size, None);
+    }
+
+    #[test]
+    fn sunset_instructions() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod vault {
+                use super::*;
+
+                #[sunset(slot = 200_000_000)]
+                pub fn withdraw(ctx: Context<Withdraw>) -> Result<()> {
+                    Ok(())
+                }
+
+                #[sunset(timestamp = 1735689600)]
+                pub fn deposit(ctx: Context<Withdraw>) -> Result<()> {
+                    Ok(())
+                }
+
+                pub fn withdraw_v2(ctx: Context<Withdraw>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Withdraw {}
+            "#,
+        );
+        let sunsets: Vec<Option<IdlSunset>> = idl.instructions.iter().map(|ix| ix.sunset).collect();
+        assert_eq!(
+            sunsets,
+            vec![
+                Some(IdlSunset::Slot(200_000_000)),
+                Some(IdlSunset::Timestamp(1735689600)),
+                None,
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&idl.instructions[0]).unwrap()["sunset"],
+            serde_json::json!({ "slot": 200_000_000 })
+        );
+        assert_eq!(IdlSunset::Slot(1).is_past(i64::MAX), None);
+        assert_eq!(
+            IdlSunset::Timestamp(1735689600).is_past(1735689600),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn external_messages() {
+        let source = |instruction: &str| {
+            format!(
+                r#"
+                use anchor_lang::prelude::*;
+
+                declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+                #[program]
+                pub mod bridge {{
+                    use super::*;
+                    pub fn complete_transfer(ctx: Context<CompleteTransfer>) -> Result<()> {{
+                        Ok(())
+                    }}
+                }}
+
+                #[derive(Accounts)]
+                pub struct CompleteTransfer<'info> {{
+                    pub posted_vaa: UncheckedAccount<'info>,
+                }}
+
+                #[external_message(
+                    format = "wormhole_vaa",
+                    emitter_chain = 2,
+                    emitter = "0x3ee18b2214aff97000d974cf647e7c347e8fa585",
+                    account = posted_vaa,
+                    owner = "worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth",
+                    instructions = [{}],
+                )]
+                #[derive(AnchorSerialize, AnchorDeserialize)]
+                pub struct TransferPayload {{
+                    pub amount: u64,
+                    pub recipient: Pubkey,
+                }}
+                "#,
+                instruction
+            )
+        };
+        let parse_source = |instruction: &str| {
+            parse(
+                write_src(&source(instruction)),
+                &ParseOptions::default(),
+                &ExternalAccounts::default(),
+            )
+        };
+
+        let idl = parse_source("complete_transfer").unwrap().unwrap().idl;
+        assert_eq!(
+            idl.external_messages,
+            vec![IdlExternalMessage {
+                name: "TransferPayload".to_string(),
+                docs: None,
+                format: Some("wormhole_vaa".to_string()),
+                emitter: Some(IdlMessageEmitter {
+                    chain: Some(2),
+                    address: "0x3ee18b2214aff97000d974cf647e7c347e8fa585".to_string(),
+                }),
+                verification: Some(IdlMessageVerification {
+                    account: "postedVaa".to_string(),
+                    owner: Some("worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth".to_string()),
+                }),
+                instructions: vec!["completeTransfer".to_string()],
+                fields: vec![
+                    IdlField {
+                        name: "amount".to_string(),
+                        docs: None,
+                        ty: IdlType::U64,
+                        constraints: vec![],
+                        aliases: vec![],
+                    },
+                    IdlField {
+                        name: "recipient".to_string(),
+                        docs: None,
+                        ty: IdlType::PublicKey,
+                        constraints: vec![],
+                        aliases: vec![],
+                    },
+                ],
+            }]
+        );
+        // The payload isn't a type of the program.
+        assert!(idl.types.is_empty());
+
+        assert!(parse_source("redeem").is_err());
+    }
+
+    #[test]
+    fn accounts_fragments() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+            use anchor_spl::token::{Mint, Token, TokenAccount};
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            #[program]
+            pub mod swap {
+                use super::*;
+                pub fn swap(ctx: Context<Swap>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[accounts_fragment]
+            pub struct TokenPair<'info> {
+                pub mint: Account<'info, Mint>,
+                #[account(mut, token::mint = mint, token::authority = authority)]
+                pub vault: Account<'info, TokenAccount>,
+                pub token_program: Program<'info, Token>,
+            }
+
+            #[with_fragments]
+            #[derive(Accounts)]
+            pub struct Swap<'info> {
+                pub authority: Signer<'info>,
+                #[fragment]
+                pub base: TokenPair<'info>,
+                #[fragment(prefix = "q")]
+                pub quote: TokenPair<'info>,
+            }
+            "#,
+        );
+        let names = |items: &[IdlAccountItem]| -> Vec<String> {
+            items
+                .iter()
+                .map(|item| match item {
+                    IdlAccountItem::IdlAccount(acc) => acc.name.clone(),
+                    IdlAccountItem::IdlAccounts(accs) => accs.name.clone(),
+                })
+                .collect()
+        };
+        let accounts = &idl.instructions[0].accounts;
+        assert_eq!(names(accounts), vec!["authority", "base", "quote"]);
+        for item in &accounts[1..] {
+            match item {
+                IdlAccountItem::IdlAccounts(accs) => {
+                    assert_eq!(names(&accs.accounts), vec!["mint", "vault", "tokenProgram"])
+                }
+                IdlAccountItem::IdlAccount(_) => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn const_generic_instantiations() {
+        let src = |account: &str| {
+            format!(
+                r#"
+                use anchor_lang::prelude::*;
+
+                pub const SLOTS: usize = 4;
+
+                #[program]
+                pub mod ring {{
+                    use super::*;
+                    pub fn push(ctx: Context<Push>, entry: Buffer<8>) -> Result<()> {{
+                        Ok(())
+                    }}
+                }}
+
+                #[derive(Accounts)]
+                pub struct Push<'info> {{
+                    pub ring: Account<'info, Ring>,
+                }}
+
+                #[account]
+                pub struct Ring {{
+                    pub entries: [Buffer<32>; SLOTS],
+                    pub last: Grid<2, SLOTS>,
+                }}
+
+                #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+                pub struct Buffer<const N: usize> {{
+                    pub data: [u8; N],
+                }}
+
+                #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+                pub struct Grid<const W: usize, const H: usize> {{
+                    pub rows: [Buffer<W>; H],
+                }}
+
+                {}
+                "#,
+                account
+            )
+        };
+        let try_parse_src = |src: String| {
+            parse(
+                write_src(&src),
+                &ParseOptions::default(),
+                &ExternalAccounts::default(),
+            )
+        };
+
+        let idl = try_parse_src(src("")).unwrap().unwrap().idl;
+        let mut types: Vec<(&str, IdlType)> = idl
+            .types
+            .iter()
+            .map(|ty_def| match &ty_def.ty {
+                IdlTypeDefinitionTy::Struct { fields } => {
+                    (ty_def.name.as_str(), fields[0].ty.clone())
+                }
+                IdlTypeDefinitionTy::Enum { .. } => unreachable!(),
+            })
+            .collect();
+        types.sort_by(|a, b| a.0.cmp(b.0));
+        let array = |ty: IdlType, len: usize| IdlType::Array(Box::new(ty), len);
+        let defined = |name: &str| IdlType::Defined(name.to_string());
+        assert_eq!(
+            types,
+            vec![
+                ("Buffer<2>", array(IdlType::U8, 2)),
+                ("Buffer<32>", array(IdlType::U8, 32)),
+                ("Buffer<8>", array(IdlType::U8, 8)),
+                ("Grid<2,4>", array(defined("Buffer<2>"), 4)),
+            ]
+        );
+
+        let err = try_parse_src(src(
+            "#[account] pub struct Window<const N: usize> { pub data: [u8; N] }",
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("`Window`: N"));
+    }
+
+    #[test]
+    fn init_accounts() {
+        // `init_if_needed` is rejected without the `init-if-needed` feature.
+        let init_if_needed = match cfg!(feature = "init-if-needed") {
+            true => "init_if_needed",
+            false => "init",
+        };
+        let src = r#"
+            use anchor_lang::prelude::*;
+
+            pub const HEADER: usize = 16;
+
+            #[program]
+            pub mod profiles {
+                use super::*;
+                pub fn init(ctx: Context<Init>, len: u32) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            #[instruction(len: u32)]
+            pub struct Init<'info> {
+                #[account(INIT_IF_NEEDED, payer = payer, space = 8 + Profile::INIT_SPACE)]
+                pub profile: Account<'info, Profile>,
+                #[account(init, payer = payer, space = 8 + HEADER * 2)]
+                pub log: Account<'info, Log>,
+                #[account(init, payer = payer, space = 8 + len as usize)]
+                pub blob: Account<'info, Log>,
+                #[account(mut)]
+                pub payer: Signer<'info>,
+                pub system_program: Program<'info, System>,
+            }
+
+            #[account]
+            #[derive(InitSpace)]
+            pub struct Profile {
+                pub authority: Pubkey,
+            }
+
+            #[account]
+            pub struct Log {}
+            "#;
+        let idl = parse_src(&src.replace("INIT_IF_NEEDED", init_if_needed));
+        let accounts: Vec<(Option<bool>, Option<u64>)> = idl.instructions[0]
+            .accounts
+            .iter()
+            .map(|acc| match acc {
+                IdlAccountItem::IdlAccount(acc) => (acc.is_init_if_needed, acc.space),
+                IdlAccountItem::IdlAccounts(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            accounts,
+            vec![
+                (cfg!(feature = "init-if-needed").then(|| true), Some(40)),
+                (None, Some(40)),
+                (None, None),
+                (None, None),
+                (None, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn token_accounts() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+            use anchor_spl::associated_token::AssociatedToken;
+            use anchor_spl::token::{Mint, Token, TokenAccount};
+
+            #[program]
+            pub mod vaults {
+                use super::*;
+                pub fn deposit(ctx: Context<Deposit>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Deposit<'info> {
+                #[account(init, payer = owner, associated_token::mint = mint, associated_token::authority = owner)]
+                pub owner_tokens: Account<'info, TokenAccount>,
+                #[account(mut, token::mint = vault.mint, token::authority = vault_authority.key())]
+                pub vault_tokens: Account<'info, TokenAccount>,
+                pub vault: Account<'info, Vault>,
+                pub vault_authority: UncheckedAccount<'info>,
+                pub mint: Account<'info, Mint>,
+                #[account(mut)]
+                pub owner: Signer<'info>,
+                pub system_program: Program<'info, System>,
+                pub token_program: Program<'info, Token>,
+                pub associated_token_program: Program<'info, AssociatedToken>,
+            }
+
+            #[account]
+            pub struct Vault {
+                pub mint: Pubkey,
+            }
+            "#,
+        );
+        let tokens: Vec<Option<IdlTokenAccount>> = idl.instructions[0]
+            .accounts
+            .iter()
+            .take(2)
+            .map(|acc| match acc {
+                IdlAccountItem::IdlAccount(acc) => acc.token.clone(),
+                IdlAccountItem::IdlAccounts(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Some(IdlTokenAccount {
+                    mint: Some("mint".to_string()),
+                    authority: Some("owner".to_string()),
+                    is_associated: Some(true),
+                }),
+                Some(IdlTokenAccount {
+                    mint: Some("vault.mint".to_string()),
+                    authority: Some("vaultAuthority".to_string()),
+                    is_associated: None,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn realloc_accounts() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            pub const ENTRY: usize = 32;
+
+            #[program]
+            pub mod lists {
+                use super::*;
+                pub fn grow(ctx: Context<Grow>, len: u16, zero: bool) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            #[instruction(len: u16, zero: bool)]
+            pub struct Grow<'info> {
+                #[account(mut, realloc = 8 + ENTRY * 4, realloc::payer = payer, realloc::zero = false)]
+                pub list: Account<'info, List>,
+                #[account(mut, realloc = 8 + len as usize, realloc::payer = payer, realloc::zero = zero)]
+                pub other: Account<'info, List>,
+                #[account(mut)]
+                pub payer: Signer<'info>,
+                pub system_program: Program<'info, System>,
+            }
+
+            #[account]
+            pub struct List {}
+            "#,
+        );
+        let reallocs: Vec<Option<IdlRealloc>> = idl.instructions[0]
+            .accounts
+            .iter()
+            .map(|acc| match acc {
+                IdlAccountItem::IdlAccount(acc) => acc.realloc.clone(),
+                IdlAccountItem::IdlAccounts(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            reallocs,
+            vec![
+                Some(IdlRealloc {
+                    payer: Some("payer".to_string()),
+                    space: Some(136),
+                    zero: Some(false),
+                }),
+                Some(IdlRealloc {
+                    payer: Some("payer".to_string()),
+                    space: None,
+                    zero: None,
+                }),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn event_cpi() {
+        let program = write_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod vaults {
+                use super::*;
+                pub fn deposit(ctx: Context<Deposit>) -> Result<()> {
+                    emit!(Deposited { amount: 1 });
+                    emit_cpi!(Deposited { amount: 1 });
+                    emit_cpi!(events::Withdrawn {});
+                    Ok(())
+                }
+            }
+
+            #[event_cpi]
+            #[derive(Accounts)]
+            pub struct Deposit<'info> {
+                pub owner: Signer<'info>,
+            }
+
+            #[event]
+            pub struct Deposited {
+                pub amount: u64,
+            }
+
+            pub mod events {
+                use super::*;
+
+                #[event]
+                pub struct Withdrawn {}
+            }
+            "#,
+        );
+        let idl = parse(
+            &program,
+            &ParseOptions {
+                seeds_feature: true,
+                docs_policy: DocsPolicy::none(),
+                ..Default::default()
+            },
+            &ExternalAccounts::default(),
+        )
+        .unwrap()
+        .unwrap()
+        .idl;
+        assert_eq!(
+            idl.instructions[0].account_names(),
+            vec!["owner", "eventAuthority", "program"]
+        );
+        let transports: Vec<(String, Vec<IdlEventTransport>)> = idl
+            .events
+            .unwrap()
+            .into_iter()
+            .map(|event| (event.name, event.transports))
+            .collect();
+        assert_eq!(
+            transports,
+            vec![
+                ("Withdrawn".to_string(), vec![IdlEventTransport::Cpi]),
+                (
+                    "Deposited".to_string(),
+                    vec![IdlEventTransport::Log, IdlEventTransport::Cpi]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn interface_instructions() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod counter_auth {
+                use super::*;
+
+                #[state]
+                pub struct CounterAuth {
+                    pub calls: u64,
+                }
+
+                impl CounterAuth {
+                    pub fn new(_ctx: Context<Empty>) -> Result<Self> {
+                        Ok(Self { calls: 0 })
+                    }
+                }
+
+                impl<'info> Auth<'info, Empty> for CounterAuth {
+                    fn is_authorized(_ctx: Context<Empty>, current: u64, new: u64) -> Result<()> {
+                        Ok(())
+                    }
+
+                    fn record(&mut self, _ctx: Context<Empty>) -> Result<()> {
+                        Ok(())
+                    }
+                }
+
+                pub fn reset(_ctx: Context<Empty>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Empty {}
+            "#,
+        );
+        let instructions: Vec<(&str, Option<&str>, usize)> = idl
+            .instructions
+            .iter()
+            .map(|ix| (ix.name.as_str(), ix.namespace.as_deref(), ix.args.len()))
+            .collect();
+        assert_eq!(
+            instructions,
+            vec![("reset", None, 0), ("isAuthorized", Some("Auth"), 2)]
+        );
+        let methods: Vec<(&str, Option<&str>)> = idl
+            .state
+            .as_ref()
+            .unwrap()
+            .methods
+            .iter()
+            .map(|ix| (ix.name.as_str(), ix.namespace.as_deref()))
+            .collect();
+        assert_eq!(methods, vec![("new", None), ("record", Some("Auth"))]);
+    }
+
+    #[test]
+    fn warnings() {
+        let program = write_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod warnings {
+                use super::*;
+                pub fn init(ctx: Context<Init>, price: f64) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Init {}
+
+            #[derive(AnchorSerialize, AnchorDeserialize)]
+            struct Private {
+                pub value: u8,
+            }
+
+            #[derive(AnchorSerialize, AnchorDeserialize)]
+            pub struct Tuple(pub u8);
+
+            #[doc = include_str!("../README.md")]
+            #[account]
+            pub struct Documented {}
+
+            #[constant]
+            pub const SEED: &str = "a";
+
+            pub mod other {
+                use super::*;
+
+                #[cfg(feature = "devnet")]
+                #[constant]
+                pub const SEED: &str = "b";
+                #[cfg(not(feature = "devnet"))]
+                #[constant]
+                pub const SEED: &str = "c";
+            }
+            "#,
+        );
+        let output = parse(
+            &program,
+            &ParseOptions::default(),
+            &ExternalAccounts::default(),
+        )
+        .unwrap()
+        .unwrap();
+        let mut warnings = output.warnings;
+        warnings.sort_by_key(|w| w.to_string());
+        assert_eq!(
+            warnings,
+            vec![
+                IdlWarning::PrivateType("Private".to_string()),
+                IdlWarning::TupleStruct("Tuple".to_string()),
+                IdlWarning::DuplicateConst("other::SEED".to_string()),
+                IdlWarning::Float {
+                    bits: 64,
+                    location: "init.price".to_string()
+                },
+                IdlWarning::UnresolvedDocs("Documented".to_string()),
+            ]
+        );
+        assert_eq!(output.idl.accounts[0].name, "Documented");
+        assert_eq!(output.idl.accounts[0].docs, None);
+    }
+
+    #[test]
+    fn unit_struct_accounts() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod markers {
+                use super::*;
+                pub fn init(ctx: Context<Init>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Init {}
+
+            #[account]
+            pub struct Marker;
+
+            #[account]
+            pub struct Empty {}
+            "#,
+        );
+        let mut accounts: Vec<_> = idl.accounts.iter().map(|acc| &acc.name).collect();
+        accounts.sort();
+        assert_eq!(accounts, vec!["Empty", "Marker"]);
+        for acc in &idl.accounts {
+            assert_eq!(acc.ty, IdlTypeDefinitionTy::Struct { fields: vec![] });
+        }
+    }
+
+    #[test]
+    fn optional_and_nested_loaders() {
+        let program = write_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod markets {
+                use super::*;
+                pub fn trade(ctx: Context<Trade>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Markets<'info> {
+                #[account(mut)]
+                pub market: AccountLoader<'info, Market>,
+                #[account(seeds = [market.key().as_ref()], bump)]
+                pub fallback: Option<AccountLoader<'info, Market>>,
+            }
+
+            #[derive(Accounts)]
+            pub struct Trade<'info> {
+                pub markets: Markets<'info>,
+                pub oracle: Option<UncheckedAccount<'info>>,
+            }
+
+            #[account(zero_copy)]
+            pub struct Market {
+                pub fee: u64,
+            }
+            "#,
+        );
+        let idl = parse(
+            &program,
+            &ParseOptions {
+                seeds_feature: true,
+                docs_policy: DocsPolicy::none(),
+                ..Default::default()
+            },
+            &ExternalAccounts::default(),
+        )
+        .unwrap()
+        .unwrap()
+        .idl;
+        let account = |item: &IdlAccountItem| match item {
+            IdlAccountItem::IdlAccount(acc) => acc.clone(),
+            IdlAccountItem::IdlAccounts(_) => panic!("expected an account"),
+        };
+        let markets = match &idl.instructions[0].accounts[0] {
+            IdlAccountItem::IdlAccounts(accs) => accs.accounts.clone(),
+            IdlAccountItem::IdlAccount(_) => panic!("expected composite accounts"),
+        };
+        assert_eq!(account(&markets[0]).is_optional, None);
+        let fallback = account(&markets[1]);
+        assert_eq!(fallback.is_optional, Some(true));
+        assert_eq!(
+            fallback.pda.unwrap().seeds,
+            vec![IdlSeed::Account(IdlSeedAccount {
+                ty: IdlType::PublicKey,
+                account: Some("Market".to_string()),
+                path: "market".to_string(),
+            })]
+        );
+        assert_eq!(
+            account(&idl.instructions[0].accounts[1]).is_optional,
+            Some(true)
+        );
+        assert_eq!(idl.accounts[0].name, "Market");
+    }
+
+    #[test]
+    fn versioned_accounts() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod vaults {
+                use super::*;
+                pub fn init(ctx: Context<Init>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Init {}
+
+            #[account(version = 3)]
+            pub struct Vault {
+                pub authority: Pubkey,
+                #[since(2)]
+                pub fee_bps: u16,
+                #[since(3, renamed_from = "total")]
+                pub total_deposits: u64,
+            }
+
+            #[account(zero_copy)]
+            pub struct Pool {
+                pub authority: Pubkey,
+            }
+            "#,
+        );
+        let vault = idl.accounts.iter().find(|acc| acc.name == "Vault").unwrap();
+        assert_eq!(vault.version, Some(3));
+        assert_eq!(
+            vault.migrations,
+            vec![
+                IdlMigration {
+                    field: "feeBps".to_string(),
+                    since: 2,
+                    renamed_from: None,
+                },
+                IdlMigration {
+                    field: "totalDeposits".to_string(),
+                    since: 3,
+                    renamed_from: Some("total".to_string()),
+                },
+            ]
+        );
+        let pool = idl.accounts.iter().find(|acc| acc.name == "Pool").unwrap();
+        assert_eq!(pool.version, None);
+        assert!(pool.migrations.is_empty());
+    }
+
+    #[test]
+    fn zero_copy_repr_lint() {
+        let parse_checked = |items: &str| {
+            let program = write_src(&format!(
+                r#"
+                    use anchor_lang::prelude::*;
+
+                    #[program]
+                    pub mod pools {{
+                        use super::*;
+                        pub fn init(ctx: Context<Init>) -> Result<()> {{
+                            Ok(())
+                        }}
+                    }}
+
+                    #[derive(Accounts)]
+                    pub struct Init {{}}
+
+                    {}
+                    "#,
+                items
+            ));
+            parse(
+                &program,
+                &ParseOptions {
+                    docs_policy: DocsPolicy::none(),
+                    safety_checks: true,
+                    ..Default::default()
+                },
+                &ExternalAccounts::default(),
+            )
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+        };
+
+        let ok = r#"
+            #[account(zero_copy)]
+            #[repr(packed)]
+            pub struct Pool {
+                pub slots: [Slot; 4],
+                pub authority: Pubkey,
+            }
+
+            #[zero_copy]
+            pub struct Slot {
+                pub amount: u64,
+            }
+        "#;
+        assert_eq!(parse_checked(ok), Ok(()));
+
+        let align = r#"
+            #[account(zero_copy)]
+            #[repr(align(8))]
+            pub struct Pool {
+                pub amount: u64,
+            }
+        "#;
+        let err = parse_checked(align).unwrap_err();
+        assert!(
+            err.contains(r#"Zero copy struct "Pool" has a #[repr]"#),
+            "{}",
+            err
+        );
+
+        let nested = r#"
+            #[account(zero_copy)]
+            pub struct Pool {
+                pub slots: [Slot; 4],
+            }
+
+            #[derive(Clone, Copy)]
+            pub struct Slot {
+                pub amount: u64,
+            }
+        "#;
+        let err = parse_checked(nested).unwrap_err();
+        assert!(err.contains(r#"has type "Slot""#), "{}", err);
+    }
+
+    #[test]
+    fn typed_constants() {
+        let idl = parse_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod consts {
+                use super::*;
+                pub fn init(ctx: Context<Init>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Init {}
+
+            pub const SEED_LEN: usize = 4;
+
+            #[constant]
+            pub const SEED: [u8; SEED_LEN] = *b"seed";
+            #[constant]
+            pub const PREFIX: &[u8] = b"prefix";
+            #[constant]
+            pub const NAME: &str = "vault";
+            #[constant]
+            pub const ADMIN: Pubkey = pubkey!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+            #[constant]
+            pub const MAX_SUPPLY: u64 = u64::MAX - 1_000 * (SEED_LEN as u64);
+            #[constant]
+            pub const BIG: u128 = 1 << 100;
+            #[constant]
+            pub const MIN_PRICE: i32 = -5;
+            #[constant]
+            pub const RATES: [u16; 3] = [1, 2, 3];
+            #[constant]
+            pub const UNKNOWN: u64 = compute();
+            "#,
+        );
+        let constant = |name: &str| idl.constants.iter().find(|c| c.name == name).unwrap();
+
+        let seed = constant("SEED");
+        assert_eq!(seed.ty, IdlType::Array(Box::new(IdlType::U8), 4));
+        assert_eq!(seed.evaluated, Some(IdlConstValue::Bytes(b"seed".to_vec())));
+        let prefix = constant("PREFIX");
+        assert_eq!(prefix.ty, IdlType::Bytes);
+        assert_eq!(
+            prefix.evaluated,
+            Some(IdlConstValue::Bytes(b"prefix".to_vec()))
+        );
+        let name = constant("NAME");
+        assert_eq!(name.ty, IdlType::String);
+        assert_eq!(
+            name.evaluated,
+            Some(IdlConstValue::String("vault".to_string()))
+        );
+        let admin = constant("ADMIN");
+        assert_eq!(admin.ty, IdlType::PublicKey);
+        assert_eq!(
+            admin.evaluated,
+            Some(IdlConstValue::PublicKey(
+                "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS".to_string()
+            ))
+        );
+        assert_eq!(
+            constant("MAX_SUPPLY").evaluated,
+            Some(IdlConstValue::Int((u64::MAX - 4_000).to_string()))
+        );
+        assert_eq!(
+            constant("BIG").evaluated,
+            Some(IdlConstValue::Int((1u128 << 100).to_string()))
+        );
+        assert_eq!(
+            constant("MIN_PRICE").evaluated,
+            Some(IdlConstValue::Int("-5".to_string()))
+        );
+        assert_eq!(
+            constant("RATES").evaluated,
+            Some(IdlConstValue::Array(vec![
+                IdlConstValue::Int("1".to_string()),
+                IdlConstValue::Int("2".to_string()),
+                IdlConstValue::Int("3".to_string()),
+            ]))
+        );
+        let unknown = constant("UNKNOWN");
+        assert_eq!(unknown.value, "compute ()");
+        assert_eq!(unknown.evaluated, None);
+    }
+
+    #[test]
+    fn module_constants() {
+        let program = write_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod consts {
+                use super::*;
+                pub fn init(ctx: Context<Init>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Init {}
+
+            #[constant]
+            pub const VERSION: u8 = 1;
+
+            pub mod fees {
+                #[constant]
+                pub const MAX_FEE: u64 = 100;
+                #[constant(skip_idl)]
+                pub const FEE_SEED: &[u8] = b"fee";
+                #[constant]
+                const DENOMINATOR: u64 = 10_000;
+
+                pub mod tiers {
+                    #[constant]
+                    pub const MAX_FEE: u64 = 50;
+                }
+            }
+            "#,
+        );
+        let output = parse(
+            &program,
+            &ParseOptions::default(),
+            &ExternalAccounts::default(),
+        )
+        .unwrap()
+        .unwrap();
+
+        let constants = output
+            .idl
+            .constants
+            .iter()
+            .map(|c| (c.module.as_deref(), c.name.as_str(), c.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            constants,
+            vec![
+                (Some("fees::tiers"), "MAX_FEE", "50"),
+                (Some("fees"), "MAX_FEE", "100"),
+                (None, "VERSION", "1"),
+            ]
+        );
+        assert_eq!(
+            output.warnings,
+            vec![IdlWarning::PrivateConst("DENOMINATOR".to_string())]
+        );
+    }
+
+    #[test]
+    fn writable_by() {
+        let program = write_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod vault {
+                use super::*;
+
+                pub fn set_fee(ctx: Context<SetFee>, fee_bps: u16) -> Result<()> {
+                    ctx.accounts.vault.fee_bps = fee_bps;
+                    Ok(())
+                }
+
+                pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+                    let vault = &mut ctx.accounts.vault;
+                    vault.total_deposits += amount;
+                    vault.fee_bps = 0;
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct SetFee<'info> {
+                #[account(mut, has_one = authority)]
+                pub vault: Account<'info, Vault>,
+                pub authority: Signer<'info>,
+            }
+
+            #[derive(Accounts)]
+            pub struct Deposit<'info> {
+                #[account(mut)]
+                pub vault: Account<'info, Vault>,
+                pub depositor: Signer<'info>,
+            }
+
+            #[account]
+            pub struct Vault {
+                pub authority: Pubkey,
+                #[writable_by = "authority"]
+                pub fee_bps: u16,
+                pub total_deposits: u64,
+            }
+            "#,
+        );
+        let output = parse(
+            &program,
+            &ParseOptions::default(),
+            &ExternalAccounts::default(),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            output.idl.accounts[0].permissions,
+            vec![IdlPermission {
+                field: "feeBps".to_string(),
+                writable_by: "authority".to_string(),
+            }]
+        );
+        assert_eq!(
+            output.warnings,
+            vec![IdlWarning::UnauthorizedWrite {
+                instruction: "deposit".to_string(),
+                field: "Vault.fee_bps".to_string(),
+                writable_by: "authority".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn event_indexes() {
+        let src = |attrs: &str| {
+            format!(
+                r#"
+                use anchor_lang::prelude::*;
+
+                #[program]
+                pub mod orders {{
+                    use super::*;
+                    pub fn init(ctx: Context<Init>) -> Result<()> {{
+                        Ok(())
+                    }}
+                }}
+
+                #[derive(Accounts)]
+                pub struct Init {{}}
+
+                #[event]
+                pub struct OrderFilled {{
+                    /// The order.
+                    {}
+                    pub order: u64,
+                    #[index]
+                    pub market: Pubkey,
+                    pub amount: u64,
+                }}
+                "#,
+                attrs
+            )
+        };
+        let try_parse_src = |attrs: &str| {
+            parse(
+                write_src(&src(attrs)),
+                &ParseOptions::default(),
+                &ExternalAccounts::default(),
+            )
+        };
+
+        let output = try_parse_src("#[index(unique)]").unwrap().unwrap();
+        let fields = output.idl.events.unwrap().remove(0).fields;
+        let indexes = fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.index, f.unique))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            indexes,
+            vec![
+                ("order", true, true),
+                ("market", true, false),
+                ("amount", false, false),
+            ]
+        );
+        let json = serde_json::to_value(&fields).unwrap();
+        assert_eq!(json[0]["unique"], true);
+        assert!(json[1].get("unique").is_none());
+
+        let err = try_parse_src("#[index(sorted)]").unwrap_err();
+        assert_eq!(err.to_string(), "Unknown index argument, expected `unique`");
+    }
+
+    #[test]
+    fn array_length_constants() {
+        let src = |extra: &str| {
+            format!(
+                r#"
+                use anchor_lang::prelude::*;
+
+                #[program]
+                pub mod lengths {{
+                    use super::*;
+                    pub fn init(ctx: Context<Init>, name: [u8; names::LEN]) -> Result<()> {{
+                        Ok(())
+                    }}
+                }}
+
+                #[derive(Accounts)]
+                pub struct Init {{}}
+
+                pub mod names {{
+                    use super::*;
+                    pub const LEN: usize = 32;
+
+                    #[derive(AnchorSerialize, AnchorDeserialize)]
+                    pub struct Name {{
+                        pub value: [u8; LEN],
+                    }}
+                }}
+
+                pub mod prices {{
+                    use super::*;
+                    pub const LEN: usize = 4;
+
+                    #[derive(AnchorSerialize, AnchorDeserialize)]
+                    pub struct Prices {{
+                        pub values: Option<[[u64; LEN]; crate::names::LEN as usize]>,
+                    }}
+                }}
+
+                pub mod book {{
+                    use super::*;
+                    use crate::prices::{{LEN as PRICES, *}};
+
+                    #[derive(AnchorSerialize, AnchorDeserialize)]
+                    pub struct Book {{
+                        pub prices: [u64; PRICES],
+                        pub sides: [u8; SIDES],
+                    }}
+                }}
+
+                pub const SIDES: usize = 2;
+                {}
+                "#,
+                extra
+            )
+        };
+        let try_parse_src = |extra: &str| {
+            parse(
+                write_src(&src(extra)),
+                &ParseOptions::default(),
+                &ExternalAccounts::default(),
+            )
+        };
+        let idl = try_parse_src("").unwrap().unwrap().idl;
+        let array = |ty: IdlType, len: usize| IdlType::Array(Box::new(ty), len);
+        let fields = |name: &str| match &idl.types.iter().find(|ty| ty.name == name).unwrap().ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                fields.iter().map(|f| f.ty.clone()).collect::<Vec<_>>()
+            }
+            _ => panic!("Not a struct"),
+        };
+
+        assert_eq!(idl.instructions[0].args[0].ty, array(IdlType::U8, 32));
+        assert_eq!(fields("Name"), vec![array(IdlType::U8, 32)]);
+        assert_eq!(
+            fields("Prices"),
+            vec![IdlType::Option(Box::new(array(array(IdlType::U64, 4), 32)))]
+        );
+        assert_eq!(
+            fields("Book"),
+            vec![array(IdlType::U64, 4), array(IdlType::U8, 2)]
+        );
+
+        // A length used where none of the constants of that name is in scope
+        // is ambiguous.
+        let err = try_parse_src(
+            r#"
+            #[derive(AnchorSerialize, AnchorDeserialize)]
+            pub struct Ambiguous {
+                pub values: [u8; LEN],
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The array length `LEN` used in `crate` is ambiguous, as constants with different \
+             values are defined in `crate::names`, `crate::prices`. Define or import the \
+             constant in `crate`, or use its path."
+        );
+    }
+
+    #[test]
+    fn external_composite_accounts() {
+        let shared = write_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[derive(Accounts)]
+            pub struct Shared<'info> {
+                #[account(mut)]
+                pub vault: AccountInfo<'info>,
+                pub authority: Signer<'info>,
+            }
+            "#,
+        );
+        let program = write_src(
+            r#"
+            use anchor_lang::prelude::*;
+            use shared::Shared;
+
+            #[program]
+            pub mod external {
+                use super::*;
+                pub fn init(ctx: Context<Init>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Init<'info> {
+                pub shared: Shared<'info>,
+                pub other: Other<'info>,
+            }
+            "#,
+        );
+
+        let mut external_accounts = ExternalAccounts::default();
+        external_accounts.register_source(&shared).unwrap();
+        let other_idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "other",
+            "instructions": [{
+                "name": "doThing",
+                "accounts": [{ "name": "payer", "isMut": true, "isSigner": true }],
+                "args": []
+            }]
+        }))
+        .unwrap();
+        external_accounts
+            .register_idl("Other", &other_idl, "do_thing")
+            .unwrap();
+
+        let idl = parse(&program, &ParseOptions::default(), &external_accounts)
+            .unwrap()
+            .unwrap()
+            .idl;
+        let names: Vec<Vec<String>> = idl.instructions[0]
+            .accounts
+            .iter()
+            .map(|acc| match acc {
+                IdlAccountItem::IdlAccounts(accs) => accs
+                    .accounts
+                    .iter()
+                    .map(|acc| match acc {
+                        IdlAccountItem::IdlAccount(acc) => acc.name.clone(),
+                        _ => panic!("expected an account"),
+                    })
+                    .collect(),
+                _ => panic!("expected composite accounts"),
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                vec!["vault".to_string(), "authority".to_string()],
+                vec!["payer".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn flattened_accounts() {
+        let program = write_src(
+            r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod dex {
+                use super::*;
+                pub fn place_order(ctx: Context<PlaceOrder>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct PlaceOrder<'info> {
+                pub market: Market<'info>,
+                pub authority: Signer<'info>,
+            }
+
+            #[derive(Accounts)]
+            pub struct Market<'info> {
+                #[account(mut)]
+                pub bids: AccountInfo<'info>,
+                #[account(mut)]
+                pub asks: AccountInfo<'info>,
+                #[account(seeds = [b"queue", bids.key().as_ref()], bump)]
+                pub event_queue: AccountInfo<'info>,
+            }
+            "#,
+        );
+        let accounts = |flatten_accounts| {
+            parse(
+                &program,
+                &ParseOptions {
+                    seeds_feature: true,
+                    docs_policy: DocsPolicy::none(),
+                    flatten_accounts,
+                    ..Default::default()
+                },
+                &ExternalAccounts::default(),
+            )
+            .unwrap()
+            .unwrap()
+            .idl
+            .instructions
+            .remove(0)
+            .accounts
+        };
+
+        assert!(matches!(
+            &accounts(false)[0],
+            IdlAccountItem::IdlAccounts(accs) if accs.name == "market"
+        ));
+        let flattened = accounts(true)
+            .into_iter()
+            .map(|acc| match acc {
+                IdlAccountItem::IdlAccount(acc) => acc,
+                _ => panic!("expected an account"),
+            })
+            .collect::<Vec<_>>();
+        let names = flattened
+            .iter()
+            .map(|acc| acc.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["marketBids", "marketAsks", "marketEventQueue", "authority"]
+        );
+        assert!(flattened[0].is_mut);
+        let seeds = &flattened[2].pda.as_ref().unwrap().seeds;
+        match &seeds[1] {
+            IdlSeed::Account(seed) => assert_eq!(seed.path, "market_bids"),
+            _ => panic!("expected an account seed"),
+        }
+    }
 }
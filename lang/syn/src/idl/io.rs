@@ -0,0 +1,197 @@
+use crate::idl::{shank, Idl};
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+// Reading and writing of IDL files.
+//
+// IDLs are serialized straight to their writer, without building the JSON in
+// memory first. Large IDLs can also be split into a file per section, e.g.
+// `instructions.json` and `types.json`, next to an `idl.json` index listing
+// them in its `sections`:
+//
+// ```json
+// {
+//   "version": "0.1.0",
+//   "name": "vault",
+//   "sections": {
+//     "instructions": "instructions.json",
+//     "types": "types.json"
+//   }
+// }
+// ```
+
+// The name of the index of a split IDL.
+pub const INDEX_FILE: &str = "idl.json";
+
+// Sections of the IDL written to their own file when splitting it.
+const SECTIONS: [&str; 6] = [
+    "constants",
+    "instructions",
+    "accounts",
+    "types",
+    "events",
+    "errors",
+];
+
+// Writes the IDL as pretty printed JSON.
+pub fn write(idl: &Idl, writer: impl Write) -> Result<()> {
+    write_json(writer, idl)
+}
+
+// Writes the IDL to the directory `dir`, as its index along with a file per
+// section. Empty sections are left out.
+pub fn write_split(idl: &Idl, dir: impl AsRef<Path>) -> Result<()> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+
+    let mut sections = BTreeMap::new();
+    if !idl.constants.is_empty() {
+        write_section(dir, "constants", &idl.constants, &mut sections)?;
+    }
+    write_section(dir, "instructions", &idl.instructions, &mut sections)?;
+    if !idl.accounts.is_empty() {
+        write_section(dir, "accounts", &idl.accounts, &mut sections)?;
+    }
+    if !idl.types.is_empty() {
+        write_section(dir, "types", &idl.types, &mut sections)?;
+    }
+    if let Some(events) = &idl.events {
+        write_section(dir, "events", events, &mut sections)?;
+    }
+    if let Some(errors) = &idl.errors {
+        write_section(dir, "errors", errors, &mut sections)?;
+    }
+
+    // The index has everything else, e.g. the metadata.
+    let index = Idl {
+        version: idl.version.clone(),
+        name: idl.name.clone(),
+        docs: idl.docs.clone(),
+        constants: vec![],
+        instructions: vec![],
+        instruction_enum: idl.instruction_enum.clone(),
+        fallback: idl.fallback.clone(),
+        state: idl.state.clone(),
+        accounts: vec![],
+        types: vec![],
+        events: None,
+        external_messages: idl.external_messages.clone(),
+        errors: None,
+        metadata: idl.metadata.clone(),
+    };
+    let mut index = serde_json::to_value(&index)?;
+    let fields = index.as_object_mut().unwrap();
+    for section in SECTIONS {
+        fields.remove(section);
+    }
+    fields.insert("sections".to_string(), serde_json::to_value(sections)?);
+    write_json(File::create(dir.join(INDEX_FILE))?, &index)
+}
+
+fn write_section<T: Serialize>(
+    dir: &Path,
+    name: &str,
+    section: &T,
+    sections: &mut BTreeMap<String, String>,
+) -> Result<()> {
+    let file = format!("{}.json", name);
+    write_json(File::create(dir.join(&file))?, section)?;
+    sections.insert(name.to_string(), file);
+    Ok(())
+}
+
+fn write_json<T: Serialize + ?Sized>(writer: impl Write, value: &T) -> Result<()> {
+    let mut writer = BufWriter::new(writer);
+    serde_json::to_writer_pretty(&mut writer, value)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// Reads an IDL written either as a single file, or split into sections. The
+// path of a split IDL is the one of its index, or of the directory it's in.
+// Shank IDLs are read as well, converted to Anchor IDLs.
+pub fn read(path: impl AsRef<Path>) -> Result<Idl> {
+    let mut path = path.as_ref().to_path_buf();
+    if path.is_dir() {
+        path = path.join(INDEX_FILE);
+    }
+    let mut idl = read_json(&path)?;
+    let sections = idl
+        .as_object_mut()
+        .and_then(|fields| fields.remove("sections"));
+    if let Some(sections) = sections {
+        let sections: BTreeMap<String, String> = serde_json::from_value(sections)
+            .with_context(|| format!("Invalid sections in {}", path.display()))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for (name, file) in sections {
+            if !SECTIONS.contains(&name.as_str()) {
+                return Err(anyhow!(
+                    "Unknown IDL section {} in {}",
+                    name,
+                    path.display()
+                ));
+            }
+            let section = read_json(&dir.join(file))?;
+            idl.as_object_mut().unwrap().insert(name, section);
+        }
+    }
+    // Shank IDLs of native programs are converted, to generate their clients
+    // the same way.
+    if shank::is_shank(&idl) {
+        let idl = serde_json::from_value(idl)
+            .with_context(|| format!("Invalid Shank IDL {}", path.display()))?;
+        return shank::import(idl);
+    }
+    serde_json::from_value(idl).with_context(|| format!("Invalid IDL {}", path.display()))
+}
+
+fn read_json(path: &Path) -> Result<JsonValue> {
+    let file = File::open(path).with_context(|| format!("Unable to open {}", path.display()))?;
+    serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("Unable to parse {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_round_trip() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "instructions": [{ "name": "init", "accounts": [], "args": [] }],
+            "types": [{ "name": "Fee", "type": { "kind": "struct", "fields": [] } }],
+            "errors": [{ "code": 6000, "name": "Paused" }],
+            "metadata": { "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS" }
+        }))
+        .unwrap();
+        let dir = std::env::temp_dir().join("anchor_syn_io");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        write_split(&idl, dir.join("split")).unwrap();
+        let mut files = std::fs::read_dir(dir.join("split"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(
+            files,
+            vec!["errors.json", "idl.json", "instructions.json", "types.json"]
+        );
+        let index = read_json(&dir.join("split/idl.json")).unwrap();
+        assert_eq!(index["sections"]["types"], "types.json");
+        assert_eq!(index["metadata"], idl.metadata.clone().unwrap());
+        assert!(index.get("instructions").is_none());
+        assert_eq!(read(dir.join("split")).unwrap(), idl);
+        assert_eq!(read(dir.join("split/idl.json")).unwrap(), idl);
+
+        write(&idl, File::create(dir.join("vault.json")).unwrap()).unwrap();
+        assert_eq!(read(dir.join("vault.json")).unwrap(), idl);
+    }
+}
@@ -0,0 +1,438 @@
+use crate::idl::*;
+use anyhow::anyhow;
+use std::collections::BTreeMap;
+
+// A language clients are generated in, whose reserved words can't be used as
+// identifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    Typescript,
+    Python,
+    Kotlin,
+}
+
+const TYPESCRIPT_RESERVED: &[&str] = &[
+    "as",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "constructor",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+const PYTHON_RESERVED: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "type",
+    "while", "with", "yield",
+];
+
+const KOTLIN_RESERVED: &[&str] = &[
+    "as",
+    "break",
+    "class",
+    "continue",
+    "do",
+    "else",
+    "false",
+    "for",
+    "fun",
+    "if",
+    "in",
+    "interface",
+    "is",
+    "null",
+    "object",
+    "package",
+    "return",
+    "super",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typealias",
+    "typeof",
+    "val",
+    "var",
+    "when",
+    "while",
+];
+
+impl Target {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Target::Typescript => "typescript",
+            Target::Python => "python",
+            Target::Kotlin => "kotlin",
+        }
+    }
+
+    pub fn reserved_words(&self) -> &'static [&'static str] {
+        match self {
+            Target::Typescript => TYPESCRIPT_RESERVED,
+            Target::Python => PYTHON_RESERVED,
+            Target::Kotlin => KOTLIN_RESERVED,
+        }
+    }
+
+    pub fn is_reserved(&self, name: &str) -> bool {
+        self.reserved_words().contains(&name)
+    }
+
+    // The identifier generated code uses for a name: its override, if any,
+    // else the name with a `_` suffix if it's a reserved word, e.g. `type_`.
+    pub fn mangle(&self, name: &str, overrides: &BTreeMap<String, String>) -> String {
+        match overrides.get(name) {
+            Some(renamed) => renamed.clone(),
+            None if self.is_reserved(name) => format!("{}_", name),
+            None => name.to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for Target {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "typescript" | "ts" => Ok(Target::Typescript),
+            "python" | "py" => Ok(Target::Python),
+            "kotlin" | "kt" => Ok(Target::Kotlin),
+            _ => Err(anyhow!("Invalid codegen target: {}", s)),
+        }
+    }
+}
+
+// Renames names in place, keeping track of the new name of each renamed one.
+struct Renamer<'a> {
+    target: Target,
+    overrides: &'a BTreeMap<String, String>,
+    renames: BTreeMap<String, String>,
+}
+
+impl Renamer<'_> {
+    fn name(&mut self, name: &mut String) {
+        let renamed = self.target.mangle(name, self.overrides);
+        if renamed != *name {
+            self.renames.insert(name.clone(), renamed.clone());
+            *name = renamed;
+        }
+    }
+
+    // Renames the first component of a path, e.g. `type.key` of a seed.
+    fn path(&mut self, path: &mut String) {
+        let mut components = path.splitn(2, '.');
+        let mut first = components.next().unwrap_or_default().to_string();
+        let rest = components.next().map(str::to_string);
+        self.name(&mut first);
+        *path = match rest {
+            Some(rest) => format!("{}.{}", first, rest),
+            None => first,
+        };
+    }
+
+    fn ty(&mut self, ty: &mut IdlType) {
+        match ty {
+            IdlType::Defined(name) => self.name(name),
+            IdlType::Option(ty) | IdlType::Vec(ty) | IdlType::Array(ty, _) => self.ty(ty),
+            _ => {}
+        }
+    }
+
+    fn fields(&mut self, fields: &mut [IdlField]) {
+        for field in fields {
+            self.name(&mut field.name);
+            for alias in &mut field.aliases {
+                self.name(alias);
+            }
+            self.ty(&mut field.ty);
+        }
+    }
+
+    fn type_definition(&mut self, ty_def: &mut IdlTypeDefinition) {
+        self.name(&mut ty_def.name);
+        match &mut ty_def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => self.fields(fields),
+            IdlTypeDefinitionTy::Enum { variants } => {
+                for variant in variants {
+                    self.name(&mut variant.name);
+                    match &mut variant.fields {
+                        Some(EnumFields::Named(fields)) => self.fields(fields),
+                        Some(EnumFields::Tuple(tys)) => tys.iter_mut().for_each(|ty| self.ty(ty)),
+                        None => {}
+                    }
+                }
+            }
+        }
+        for migration in &mut ty_def.migrations {
+            self.name(&mut migration.field);
+            if let Some(renamed_from) = &mut migration.renamed_from {
+                self.name(renamed_from);
+            }
+        }
+        for permission in &mut ty_def.permissions {
+            self.name(&mut permission.field);
+            self.name(&mut permission.writable_by);
+        }
+    }
+
+    fn seed(&mut self, seed: &mut IdlSeed) {
+        match seed {
+            IdlSeed::Const(seed) => self.ty(&mut seed.ty),
+            IdlSeed::Arg(seed) => {
+                self.ty(&mut seed.ty);
+                self.path(&mut seed.path);
+            }
+            IdlSeed::Account(seed) => {
+                self.ty(&mut seed.ty);
+                if let Some(account) = &mut seed.account {
+                    self.name(account);
+                }
+                self.path(&mut seed.path);
+            }
+        }
+    }
+
+    fn accounts(&mut self, items: &mut [IdlAccountItem]) {
+        for item in items {
+            match item {
+                IdlAccountItem::IdlAccount(acc) => {
+                    self.name(&mut acc.name);
+                    if let Some(pda) = &mut acc.pda {
+                        pda.seeds.iter_mut().for_each(|seed| self.seed(seed));
+                        if let Some(program_id) = &mut pda.program_id {
+                            self.seed(program_id);
+                        }
+                    }
+                }
+                IdlAccountItem::IdlAccounts(accs) => {
+                    self.name(&mut accs.name);
+                    self.accounts(&mut accs.accounts);
+                }
+            }
+        }
+    }
+
+    fn instruction(&mut self, ix: &mut IdlInstruction) {
+        self.name(&mut ix.name);
+        self.accounts(&mut ix.accounts);
+        self.fields(&mut ix.args);
+        if let Some(returns) = &mut ix.returns {
+            self.ty(returns);
+        }
+        // The instructions of a batch, named as the instructions themselves.
+        for name in &mut ix.batch {
+            self.name(name);
+        }
+    }
+}
+
+impl Idl {
+    // Returns the IDL with the names that can't be identifiers in the target
+    // language renamed, by the given overrides or with a `_` suffix, for
+    // clients generated from it.
+    //
+    // Each rename is recorded in `metadata.renames.<target>`, from the
+    // original name to the new one, as the original names are still needed,
+    // e.g. for discriminators.
+    pub fn mangled(&self, target: Target, overrides: &BTreeMap<String, String>) -> Idl {
+        let mut idl = self.clone();
+        let mut renamer = Renamer {
+            target,
+            overrides,
+            renames: BTreeMap::new(),
+        };
+        for constant in &mut idl.constants {
+            renamer.name(&mut constant.name);
+            renamer.ty(&mut constant.ty);
+        }
+        for ix in &mut idl.instructions {
+            renamer.instruction(ix);
+        }
+        if let Some(instruction_enum) = &mut idl.instruction_enum {
+            renamer.name(&mut instruction_enum.name);
+            for variant in &mut instruction_enum.variants {
+                renamer.name(&mut variant.name);
+                renamer.name(&mut variant.instruction);
+            }
+        }
+        if let Some(state) = &mut idl.state {
+            renamer.type_definition(&mut state.strct);
+            for method in &mut state.methods {
+                renamer.instruction(method);
+            }
+        }
+        for ty_def in idl.accounts.iter_mut().chain(idl.types.iter_mut()) {
+            renamer.type_definition(ty_def);
+        }
+        for event in idl.events.iter_mut().flatten() {
+            renamer.name(&mut event.name);
+            for field in &mut event.fields {
+                renamer.name(&mut field.name);
+                renamer.ty(&mut field.ty);
+            }
+        }
+        for error in idl.errors.iter_mut().flatten() {
+            renamer.name(&mut error.name);
+        }
+
+        if !renamer.renames.is_empty() {
+            let mut metadata = match idl.metadata.take() {
+                Some(JsonValue::Object(metadata)) => metadata,
+                _ => serde_json::Map::new(),
+            };
+            let renames = metadata
+                .entry("renames")
+                .or_insert_with(|| JsonValue::Object(serde_json::Map::new()));
+            if let JsonValue::Object(renames) = renames {
+                renames.insert(
+                    target.as_str().to_string(),
+                    serde_json::to_value(&renamer.renames).unwrap(),
+                );
+            }
+            idl.metadata = Some(JsonValue::Object(metadata));
+        }
+        idl
+    }
+
+    // The names renamed by `mangled` for the target, from the original name
+    // to the new one.
+    pub fn renames(&self, target: Target) -> BTreeMap<String, String> {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("renames")?.get(target.as_str()))
+            .and_then(|renames| serde_json::from_value(renames.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mangle_reserved_words() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "version": "0.1.0",
+            "name": "shapes",
+            "instructions": [{
+                "name": "new",
+                "accounts": [{
+                    "name": "class",
+                    "isMut": true,
+                    "isSigner": false,
+                    "pda": {
+                        "seeds": [{ "kind": "arg", "type": "u8", "path": "type" }]
+                    }
+                }],
+                "args": [{ "name": "type", "type": { "defined": "Kind" } }]
+            }],
+            "types": [{
+                "name": "Kind",
+                "type": {
+                    "kind": "enum",
+                    "variants": [{ "name": "None" }, { "name": "Square" }]
+                }
+            }],
+            "metadata": { "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS" }
+        }))
+        .unwrap();
+
+        let overrides = vec![("Kind".to_string(), "ShapeKind".to_string())]
+            .into_iter()
+            .collect();
+        let python = idl.mangled(Target::Python, &overrides);
+        let ix = &python.instructions[0];
+        assert_eq!(ix.name, "new");
+        assert_eq!(ix.args[0].name, "type_");
+        assert_eq!(ix.args[0].ty, IdlType::Defined("ShapeKind".to_string()));
+        assert_eq!(python.types[0].name, "ShapeKind");
+        assert_eq!(
+            python.types[0].ty,
+            IdlTypeDefinitionTy::Enum {
+                variants: vec![
+                    IdlEnumVariant {
+                        name: "None_".to_string(),
+                        fields: None,
+                    },
+                    IdlEnumVariant {
+                        name: "Square".to_string(),
+                        fields: None,
+                    },
+                ]
+            }
+        );
+        let renames = python.renames(Target::Python);
+        assert_eq!(renames.len(), 4);
+        assert_eq!(renames["class"], "class_");
+        assert_eq!(renames["type"], "type_");
+        // Other metadata is kept.
+        assert!(python.metadata.unwrap().get("address").is_some());
+
+        let typescript = idl.mangled(Target::Typescript, &BTreeMap::new());
+        assert_eq!(typescript.instructions[0].name, "new_");
+        match &typescript.instructions[0].accounts[0] {
+            IdlAccountItem::IdlAccount(acc) => {
+                assert_eq!(acc.name, "class_");
+                assert_eq!(
+                    acc.pda.as_ref().unwrap().seeds[0],
+                    IdlSeed::Arg(IdlSeedArg {
+                        ty: IdlType::U8,
+                        path: "type_".to_string(),
+                    })
+                );
+            }
+            IdlAccountItem::IdlAccounts(_) => panic!("expected an account"),
+        }
+        assert!(typescript.renames(Target::Python).is_empty());
+
+        let kotlin = idl.mangled(Target::Kotlin, &BTreeMap::new());
+        assert_eq!(kotlin.renames(Target::Kotlin).len(), 1);
+        // Nothing to rename leaves the IDL as is.
+        assert_eq!(kotlin.mangled(Target::Kotlin, &BTreeMap::new()), kotlin);
+    }
+}
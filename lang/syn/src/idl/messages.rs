@@ -0,0 +1,194 @@
+use crate::idl::*;
+use heck::SnakeCase;
+use std::collections::BTreeMap;
+
+// Locale of the messages written in the program.
+pub const DEFAULT_LOCALE: &str = "en";
+
+// The messages of a program's errors, keyed by error code, to be translated
+// and displayed by clients.
+//
+// Messages may contain named placeholders, e.g. `#[msg("Maximum is {max}")]`,
+// filled in by the client with `format`. `{{` and `}}` are literal braces.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlErrorMessages {
+    pub program: String,
+    pub locale: String,
+    pub messages: BTreeMap<u32, IdlErrorMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlErrorMessage {
+    pub name: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub placeholders: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+}
+
+impl Idl {
+    // Returns the message bundle of the program's errors.
+    //
+    // The message of an error is its `#[msg]`, or the first line of its docs,
+    // or else its name in words. Its docs are the description.
+    pub fn error_messages(&self) -> IdlErrorMessages {
+        let messages = self
+            .errors
+            .iter()
+            .flatten()
+            .map(|error| {
+                let docs = error.docs.as_deref().unwrap_or_default();
+                let message = error
+                    .msg
+                    .clone()
+                    .or_else(|| docs.first().cloned())
+                    .unwrap_or_else(|| sentence(&error.name));
+                let message = IdlErrorMessage {
+                    name: error.name.clone(),
+                    placeholders: placeholders(&message),
+                    message,
+                    description: (!docs.is_empty()).then(|| docs.join("\n")),
+                };
+                (error.code, message)
+            })
+            .collect();
+        IdlErrorMessages {
+            program: self.name.clone(),
+            locale: DEFAULT_LOCALE.to_string(),
+            messages,
+        }
+    }
+}
+
+impl IdlErrorMessages {
+    // Formats the message of the error `code`, replacing its placeholders by
+    // the given values. Placeholders without a value are left as is.
+    pub fn format(&self, code: u32, values: &BTreeMap<String, String>) -> Option<String> {
+        let message = self.messages.get(&code)?;
+        Some(format(&message.message, |name| values.get(name).cloned()))
+    }
+}
+
+// Names of the placeholders of a message, in order of first use.
+pub fn placeholders(message: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for segment in segments(message) {
+        if let Segment::Placeholder(name) = segment {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+// Replaces the placeholders of a message by their `value`, and unescapes
+// braces.
+pub fn format(message: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    segments(message)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text.to_string(),
+            Segment::Placeholder(name) => value(name).unwrap_or_else(|| format!("{{{}}}", name)),
+        })
+        .collect()
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+fn segments(message: &str) -> Vec<Segment<'_>> {
+    let mut segments = vec![];
+    let mut rest = message;
+    while let Some(idx) = rest.find(|c| c == '{' || c == '}') {
+        let (text, tail) = rest.split_at(idx);
+        segments.push(Segment::Text(text));
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            segments.push(Segment::Text(&tail[..1]));
+            rest = &tail[2..];
+            continue;
+        }
+        let name = tail[1..].find('}').map(|end| &tail[1..end + 1]);
+        match name {
+            Some(name) if is_placeholder(name) => {
+                segments.push(Segment::Placeholder(name));
+                rest = &tail[name.len() + 2..];
+            }
+            _ => {
+                segments.push(Segment::Text(&tail[..1]));
+                rest = &tail[1..];
+            }
+        }
+    }
+    segments.push(Segment::Text(rest));
+    segments
+}
+
+fn is_placeholder(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// E.g. "Insufficient funds" for `InsufficientFunds`.
+fn sentence(name: &str) -> String {
+    let words = name.to_snake_case().replace('_', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn error_messages() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "instructions": [],
+            "errors": [
+                {
+                    "code": 6000,
+                    "name": "AmountTooLarge",
+                    "msg": "Amount {amount} exceeds the maximum of {max} ({{max}})",
+                    "docs": ["Raised by deposits above the cap."]
+                },
+                { "code": 6001, "name": "Locked", "docs": ["The vault is locked", "until its unlock time."] },
+                { "code": 6002, "name": "InsufficientFunds" }
+            ]
+        }))
+        .unwrap();
+        let messages = idl.error_messages();
+        assert_eq!(messages.locale, DEFAULT_LOCALE);
+
+        let too_large = &messages.messages[&6000];
+        assert_eq!(too_large.placeholders, vec!["amount", "max"]);
+        assert_eq!(
+            too_large.description.as_deref(),
+            Some("Raised by deposits above the cap.")
+        );
+        assert_eq!(messages.messages[&6001].message, "The vault is locked");
+        assert_eq!(messages.messages[&6002].message, "Insufficient funds");
+        assert_eq!(messages.messages[&6002].description, None);
+
+        let values = [("amount".to_string(), "10".to_string())]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            messages.format(6000, &values).unwrap(),
+            "Amount 10 exceeds the maximum of {max} ({max})"
+        );
+        assert_eq!(messages.format(6003, &values), None);
+
+        let json = serde_json::to_value(&messages).unwrap();
+        assert_eq!(json["messages"]["6002"]["name"], "InsufficientFunds");
+    }
+}
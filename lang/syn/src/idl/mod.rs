@@ -1,8 +1,33 @@
+use crate::codegen::program::common::{sighash, SIGHASH_GLOBAL_NAMESPACE};
+use crate::parser::zero_copy::ZeroCopy;
+use heck::{MixedCase, SnakeCase};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 
+pub mod budget;
+pub mod build;
+pub mod codama;
+pub mod codegen;
+pub mod conformance;
+pub mod constant;
+pub mod diff;
+pub mod discriminators;
+pub mod docs_policy;
 pub mod file;
+pub mod io;
+pub mod mangle;
+pub mod messages;
+pub mod onchain;
 pub mod pda;
+pub mod permissions;
+pub mod profile;
+pub mod schema;
+pub mod shank;
+pub mod state_machine;
+pub mod vectors;
+pub mod workspace;
+
+pub use workspace::{parse_workspace, WorkspaceOptions};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Idl {
@@ -13,6 +38,16 @@ pub struct Idl {
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub constants: Vec<IdlConst>,
     pub instructions: Vec<IdlInstruction>,
+    #[serde(
+        rename = "instructionEnum",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub instruction_enum: Option<IdlInstructionEnum>,
+    // Function of the `#[program]` module handling the instruction data that
+    // matches no instruction.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fallback: Option<IdlFallback>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub state: Option<IdlState>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
@@ -21,6 +56,12 @@ pub struct Idl {
     pub types: Vec<IdlTypeDefinition>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub events: Option<Vec<IdlEvent>>,
+    #[serde(
+        rename = "externalMessages",
+        skip_serializing_if = "Vec::is_empty",
+        default
+    )]
+    pub external_messages: Vec<IdlExternalMessage>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub errors: Option<Vec<IdlErrorCode>>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
@@ -32,7 +73,41 @@ pub struct IdlConst {
     pub name: String,
     #[serde(rename = "type")]
     pub ty: IdlType,
+    // The expression of the constant, as written.
     pub value: String,
+    // The value of the constant, if it could be evaluated.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub evaluated: Option<IdlConstValue>,
+    // The path of the module the constant is defined in, relative to the
+    // crate root, e.g. `state::fees`, unless it's the crate root.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub module: Option<String>,
+}
+
+impl IdlConst {
+    // The path of the constant relative to the crate root, e.g.
+    // `state::fees::MAX_FEE`.
+    pub fn path(&self) -> String {
+        match &self.module {
+            Some(module) => format!("{}::{}", module, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum IdlConstValue {
+    Bool(bool),
+    // Decimal string, so that 64 and 128 bit integers keep their precision in
+    // JSON.
+    Int(String),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    // Base58 encoded.
+    PublicKey(String),
+    Array(Vec<IdlConstValue>),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -51,6 +126,246 @@ pub struct IdlInstruction {
     pub args: Vec<IdlField>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub returns: Option<IdlType>,
+    // Namespace of the instruction's discriminator, i.e., the trait name for
+    // `#[interface]` implementations. Defaults to `global` for instructions
+    // and `state` for state methods.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub namespace: Option<String>,
+    // Discriminator of the instruction data, when it isn't the sighash of the
+    // instruction's name, e.g. the index of an instruction of a native program
+    // described by Shank.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub discriminator: Option<Vec<u8>>,
+    // Preconditions checked by `#[access_control(..)]` before the handler
+    // runs, e.g. that the signer is an admin.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub guards: Vec<IdlGuard>,
+    // Accounts expected after the instruction's, given by
+    // `#[remaining_accounts(..)]`.
+    #[serde(
+        rename = "remainingAccounts",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub remaining_accounts: Option<IdlRemainingAccounts>,
+    // Deadline from which the instruction fails, given by `#[sunset(..)]`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sunset: Option<IdlSunset>,
+    // Compute units and heap the instruction needs, given by
+    // `#[compute_budget(..)]`, for clients to request them.
+    #[serde(
+        rename = "computeBudget",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub compute_budget: Option<IdlComputeBudget>,
+    // Pairs of accounts that can't be given the same key, checked by
+    // `#[distinct_mut]`.
+    #[serde(
+        rename = "distinctAccounts",
+        skip_serializing_if = "Vec::is_empty",
+        default
+    )]
+    pub distinct_accounts: Vec<(String, String)>,
+    // Whether the instruction is administrative, given by `#[admin]`.
+    #[serde(skip_serializing_if = "is_false", default)]
+    pub admin: bool,
+    // Instructions the generated `batch` instruction can run, given by
+    // `#[batch]` on their handlers. Only set on the `batch` instruction.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub batch: Vec<String>,
+    // Accounts the transaction must be signed with, and PDAs the program signs
+    // for itself.
+    #[serde(skip_serializing_if = "IdlSigners::is_empty", default)]
+    pub signers: IdlSigners,
+}
+
+// The signers of an instruction, for e.g. multisig UIs to build proposals
+// from without guessing: the accounts the transaction must be signed with,
+// and the PDAs of the program, given by seeds constraints, which it signs for
+// with `invoke_signed`. Accounts are named as by `account_names`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlSigners {
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub accounts: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub pdas: Vec<String>,
+}
+
+impl IdlSigners {
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.pdas.is_empty()
+    }
+}
+
+// The compute budget an instruction needs, e.g.
+// `{ "units": 400000, "heap": 256000 }`, heap in bytes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct IdlComputeBudget {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub units: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub heap: Option<u32>,
+}
+
+// The slot, or unix timestamp, from which an instruction fails, e.g.
+// `{ "slot": 200000000 }`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum IdlSunset {
+    Slot(u64),
+    Timestamp(i64),
+}
+
+impl IdlSunset {
+    // Whether the deadline is past at the given unix timestamp, unknown for
+    // slots.
+    pub fn is_past(&self, unix_timestamp: i64) -> Option<bool> {
+        match self {
+            IdlSunset::Slot(_) => None,
+            IdlSunset::Timestamp(timestamp) => Some(unix_timestamp >= *timestamp),
+        }
+    }
+}
+
+impl std::fmt::Display for IdlSunset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdlSunset::Slot(slot) => write!(f, "slot {}", slot),
+            IdlSunset::Timestamp(timestamp) => write!(f, "unix timestamp {}", timestamp),
+        }
+    }
+}
+
+// The type of the remaining accounts of an instruction, any number of which
+// can be appended to its accounts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlRemainingAccounts {
+    // Name of the account type, e.g. `TokenAccount`.
+    pub account: String,
+    pub is_mut: bool,
+}
+
+// A call made by `#[access_control(..)]`, e.g. `is_admin(&ctx)`, named after
+// the called function.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlGuard {
+    pub name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub args: Vec<String>,
+}
+
+impl IdlInstruction {
+    // Records the discriminator of the instruction if it's shortened to `len`
+    // bytes with `#[program(discriminator_len = ..)]`.
+    pub fn shorten_discriminator(&mut self, len: usize) {
+        if self.discriminator.is_none() && len < 8 {
+            self.discriminator = Some(self.discriminator()[..len].to_vec());
+        }
+    }
+
+    // The bytes the instruction data starts with.
+    pub fn discriminator(&self) -> Vec<u8> {
+        match &self.discriminator {
+            Some(discriminator) => discriminator.clone(),
+            None => {
+                let namespace = self
+                    .namespace
+                    .as_deref()
+                    .unwrap_or(SIGHASH_GLOBAL_NAMESPACE);
+                sighash(namespace, &self.name.to_snake_case()).to_vec()
+            }
+        }
+    }
+
+    // Flattened names of all accounts, in the order the instruction expects
+    // them. Accounts nested in composite structs are joined with a `.`.
+    pub fn account_names(&self) -> Vec<String> {
+        flat_accounts(&self.accounts)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    // Maps a flat list of account metas back to their account names. Any
+    // accounts beyond the instruction's own are named `remainingAccounts[i]`.
+    pub fn name_accounts<T>(&self, metas: impl IntoIterator<Item = T>) -> Vec<(String, T)> {
+        let names = self.account_names();
+        metas
+            .into_iter()
+            .enumerate()
+            .map(|(idx, meta)| match names.get(idx) {
+                Some(name) => (name.clone(), meta),
+                None => (format!("remainingAccounts[{}]", idx - names.len()), meta),
+            })
+            .collect()
+    }
+}
+
+// All accounts of `items` along with their flattened names, in order.
+pub fn flat_accounts(items: &[IdlAccountItem]) -> Vec<(String, &IdlAccount)> {
+    fn flatten<'a>(
+        prefix: Option<&str>,
+        items: &'a [IdlAccountItem],
+        accounts: &mut Vec<(String, &'a IdlAccount)>,
+    ) {
+        for item in items {
+            let name = match item {
+                IdlAccountItem::IdlAccount(acc) => &acc.name,
+                IdlAccountItem::IdlAccounts(accs) => &accs.name,
+            };
+            let name = match prefix {
+                None => name.clone(),
+                Some(prefix) => format!("{}.{}", prefix, name),
+            };
+            match item {
+                IdlAccountItem::IdlAccount(acc) => accounts.push((name, acc)),
+                IdlAccountItem::IdlAccounts(accs) => flatten(Some(&name), &accs.accounts, accounts),
+            }
+        }
+    }
+    let mut accounts = vec![];
+    flatten(None, items, &mut accounts);
+    accounts
+}
+
+// Describes the generated `instruction::ProgramInstruction` enum, where each
+// variant is serialized as its discriminator followed by the borsh encoded
+// instruction arguments.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlInstructionEnum {
+    pub name: String,
+    pub variants: Vec<IdlInstructionEnumVariant>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlInstructionEnumVariant {
+    pub name: String,
+    // Name of the entry in the "instructions" section.
+    pub instruction: String,
+    pub discriminator: Vec<u8>,
+}
+
+// The fallback function of a program, i.e. a function of the `#[program]`
+// module without a `Context`, invoked with the accounts and the whole data of
+// the instructions whose discriminator matches no instruction, or that are
+// shorter than a discriminator, e.g. to forward them to another program.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlFallback {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub docs: Option<Vec<String>>,
+}
+
+impl IdlFallback {
+    pub(crate) fn new(fallback_fn: &crate::FallbackFn) -> Self {
+        Self {
+            name: fallback_fn.raw_method.sig.ident.to_string().to_mixed_case(),
+            docs: crate::parser::docs::parse(&fallback_fn.raw_method.attrs),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -63,7 +378,7 @@ pub struct IdlAccounts {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum IdlAccountItem {
-    IdlAccount(IdlAccount),
+    IdlAccount(Box<IdlAccount>),
     IdlAccounts(IdlAccounts),
 }
 
@@ -73,10 +388,84 @@ pub struct IdlAccount {
     pub name: String,
     pub is_mut: bool,
     pub is_signer: bool,
+    // Whether the account can be omitted, by passing the program id instead.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub is_optional: Option<bool>,
+    // Whether the account is created by the instruction unless it exists,
+    // given by `init_if_needed`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub is_init_if_needed: Option<bool>,
+    // Space allocated to the account when it's created by the instruction,
+    // when it's known without running the program.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub space: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub docs: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub token: Option<IdlTokenAccount>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub realloc: Option<IdlRealloc>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
     pub pda: Option<IdlPda>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub kind: Option<IdlAccountKind>,
+    // Base58 address, for accounts that can only ever be a single account,
+    // e.g. well known programs and sysvars.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub address: Option<String>,
+    // Base58 addresses of the programs an `Interface` may be, or an
+    // `InterfaceAccount` may be owned by.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub programs: Option<Vec<String>>,
+    // Account of the same struct receiving the lamports of the account when
+    // the instruction closes it, given by `close = <destination>`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub close: Option<String>,
+}
+
+// The mint and authority of a token account, given by its `token::*` or
+// `associated_token::*` constraints, as paths to other accounts of the
+// instruction, e.g. `mint` or `vault.mint`, so that clients derive associated
+// token accounts. Paths to anything else, e.g. instruction arguments, are
+// omitted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlTokenAccount {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub authority: Option<String>,
+    // Whether it's the associated token account of the authority for the mint.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub is_associated: Option<bool>,
+}
+
+// The resizing of the data of an account by the instruction, given by its
+// `realloc` constraints. The space and whether new bytes are zeroed are only
+// given when they're known without running the program.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlRealloc {
+    // The account paying for the rent of the new space, and refunded when the
+    // account shrinks, as a path like those of `IdlTokenAccount`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub payer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub space: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub zero: Option<bool>,
+}
+
+// The concrete type of an account, e.g. `{ "sysvar": "Rent" }` for a
+// `Sysvar<'info, Rent>` or `{ "program": "System" }` for a
+// `Program<'info, System>` or `{ "interface": "TokenInterface" }` for an
+// `Interface<'info, TokenInterface>`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum IdlAccountKind {
+    Sysvar(String),
+    Program(String),
+    Interface(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -85,6 +474,11 @@ pub struct IdlPda {
     pub seeds: Vec<IdlSeed>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub program_id: Option<IdlSeed>,
+    // The `u8` field of an account the bump is stored in, given by e.g.
+    // `bump = vault.bump`, for clients to derive the address with instead of
+    // searching for the canonical bump.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub bump: Option<IdlSeed>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -130,12 +524,50 @@ pub struct IdlField {
     pub docs: Option<Vec<String>>,
     #[serde(rename = "type")]
     pub ty: IdlType,
+    // Constraints of an instruction argument given by `#[arg(..)]`, for
+    // clients to validate it before sending the instruction.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub constraints: Vec<IdlArgConstraint>,
+    // Former names given by `#[idl_name(..)]`, which clients accept as well.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub aliases: Vec<String>,
+}
+
+// A constraint of an instruction argument, e.g. `{ "range": { "min": "1",
+// "max": "100" } }`. Range bounds are inclusive, and encoded as decimal
+// strings, as the 128 bit values of `IdlType::is_string_encoded`, since they
+// can be out of the range JSON numbers are read in.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum IdlArgConstraint {
+    Range {
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        min: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        max: Option<String>,
+    },
+    NonZero,
+    MaxLen(u64),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct IdlEvent {
     pub name: String,
     pub fields: Vec<IdlEventField>,
+    // How the program emits the event, found from its `emit!` and `emit_cpi!`
+    // invocations, so that indexers know where to read it from. Empty when
+    // it isn't known, e.g. for IDLs generated with `idl-build`.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub transports: Vec<IdlEventTransport>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum IdlEventTransport {
+    // In the program's logs, with `emit!`.
+    Log,
+    // In the data of a self CPI, with `emit_cpi!`.
+    Cpi,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -144,6 +576,53 @@ pub struct IdlEventField {
     #[serde(rename = "type")]
     pub ty: IdlType,
     pub index: bool,
+    // Whether the indexed field identifies a single event, i.e. the field is
+    // marked with `#[index(unique)]`.
+    #[serde(skip_serializing_if = "is_false", default)]
+    pub unique: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+// A payload defined outside of the program and consumed by its instructions,
+// e.g. the payload of a bridge's message, given by `#[external_message(..)]`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlExternalMessage {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub docs: Option<Vec<String>>,
+    // Format of the message carrying the payload, e.g. `wormhole_vaa`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub emitter: Option<IdlMessageEmitter>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub verification: Option<IdlMessageVerification>,
+    // Instructions consuming the message.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub instructions: Vec<String>,
+    // Layout of the payload.
+    pub fields: Vec<IdlField>,
+}
+
+// The sender the program expects messages from, e.g. the address of a contract
+// on another chain.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlMessageEmitter {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub chain: Option<u16>,
+    pub address: String,
+}
+
+// The account of the instructions holding the verified message, and the
+// program it must be owned by, i.e. the one that verified it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlMessageVerification {
+    pub account: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub owner: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -153,6 +632,133 @@ pub struct IdlTypeDefinition {
     pub docs: Option<Vec<String>>,
     #[serde(rename = "type")]
     pub ty: IdlTypeDefinitionTy,
+    // Version of the layout, given by `#[account(version = ..)]`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version: Option<u32>,
+    // Fields that changed since the first version of the layout, to decode
+    // data written with older versions.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub migrations: Vec<IdlMigration>,
+    // Signers allowed to write the fields, given by `#[writable_by = ".."]`.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub permissions: Vec<IdlPermission>,
+    // Discriminator of the data of an account, when it isn't the sighash of
+    // the account's name, e.g. empty for accounts of native programs described
+    // by Shank, which have none.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub discriminator: Option<Vec<u8>>,
+    // Lifecycle of an account, given by `#[state_machine(..)]`.
+    #[serde(
+        rename = "stateMachine",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub state_machine: Option<IdlStateMachine>,
+    // Space of an account deriving `InitSpace`, discriminator and checksum
+    // included, i.e. `8 + INIT_SPACE`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub size: Option<u64>,
+    // Checksum following the data of an account, given by
+    // `#[account(checksum)]`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub checksum: Option<IdlChecksum>,
+    // Serialization of the data, borsh unless given by `#[zero_copy]`.
+    #[serde(skip_serializing_if = "IdlSerialization::is_borsh", default)]
+    pub serialization: IdlSerialization,
+}
+
+// How the data of a type is serialized. Zero copy types are their `#[repr(C)]`
+// memory layout, or `#[repr(packed)]` if given. That of `zero_copy(unsafe)`
+// types may have padding bytes, which borsh decoders don't expect.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum IdlSerialization {
+    Borsh,
+    Bytemuck,
+    BytemuckUnsafe,
+}
+
+impl Default for IdlSerialization {
+    fn default() -> Self {
+        IdlSerialization::Borsh
+    }
+}
+
+impl IdlSerialization {
+    pub fn is_borsh(&self) -> bool {
+        *self == IdlSerialization::Borsh
+    }
+}
+
+impl From<Option<ZeroCopy>> for IdlSerialization {
+    fn from(zero_copy: Option<ZeroCopy>) -> Self {
+        match zero_copy {
+            None => IdlSerialization::Borsh,
+            Some(ZeroCopy::Safe) => IdlSerialization::Bytemuck,
+            Some(ZeroCopy::Unsafe) => IdlSerialization::BytemuckUnsafe,
+        }
+    }
+}
+
+// Algorithm of the checksum of an account's data, computed over its fields and
+// appended to them as little endian bytes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum IdlChecksum {
+    Crc32,
+}
+
+impl IdlChecksum {
+    // Number of bytes of the checksum.
+    pub fn size(&self) -> usize {
+        match self {
+            IdlChecksum::Crc32 => 4,
+        }
+    }
+}
+
+impl IdlTypeDefinition {
+    // The bytes the data of an account of this type starts with.
+    pub fn account_discriminator(&self) -> Vec<u8> {
+        match &self.discriminator {
+            Some(discriminator) => discriminator.clone(),
+            None => sighash("account", &self.name).to_vec(),
+        }
+    }
+}
+
+// A field added by a version of a layout, given by `#[since(..)]`. Older
+// versions don't have it, unless it was renamed, in which case they have it
+// under its previous name.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlMigration {
+    pub field: String,
+    pub since: u32,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub renamed_from: Option<String>,
+}
+
+// A field only the given signer may write, e.g. the `authority` of a vault.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlPermission {
+    pub field: String,
+    pub writable_by: String,
+}
+
+// The states of an account, the variants of the enum of its `field`, and the
+// transitions allowed between them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlStateMachine {
+    pub field: String,
+    pub transitions: Vec<IdlTransition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlTransition {
+    pub from: String,
+    pub to: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -199,6 +805,183 @@ pub enum IdlType {
     Option(Box<IdlType>),
     Vec(Box<IdlType>),
     Array(Box<IdlType>, usize),
+    Float(IdlFloat),
+}
+
+impl IdlType {
+    // Values of 128 bit integers don't fit in a JSON number without silently
+    // losing precision in most consumers (e.g. JavaScript, or `serde_json`
+    // without `arbitrary_precision`), so wherever the IDL embeds a value of
+    // one of these types, it's encoded as a decimal string instead.
+    pub fn is_string_encoded(&self) -> bool {
+        matches!(self, IdlType::U128 | IdlType::I128)
+    }
+}
+
+// Annotated floating point type, emitted in place of `f32`/`f64` when using
+// `FloatPolicy::AllowWithAnnotation`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IdlFloat {
+    pub bits: u8,
+    // Floats can't be relied on to round trip exactly across clients.
+    pub approximate: bool,
+}
+
+// How floating point types in accounts, instructions and user defined types
+// are treated when generating the IDL. Floats don't have a deterministic
+// encoding across all clients (e.g. NaN payloads, or precision lost in
+// JavaScript numbers), so programs can opt into rejecting them.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FloatPolicy {
+    // Fail IDL generation.
+    Error,
+    // Print a warning and emit `f32`/`f64` as is.
+    Warn,
+    // Emit an explicit `float` type marked as approximate.
+    AllowWithAnnotation,
+}
+
+impl Default for FloatPolicy {
+    fn default() -> Self {
+        FloatPolicy::Warn
+    }
+}
+
+impl std::str::FromStr for FloatPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(FloatPolicy::Error),
+            "warn" => Ok(FloatPolicy::Warn),
+            "allow-with-annotation" => Ok(FloatPolicy::AllowWithAnnotation),
+            _ => Err(anyhow::anyhow!("Invalid float policy: {}", s)),
+        }
+    }
+}
+
+// Which doc comments are kept when generating the IDL, so that published IDLs
+// can keep the docs useful to clients while dropping internal commentary.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct DocsPolicy {
+    // Docs of the program, of its instructions and state methods, and of the
+    // accounts, types and errors it defines.
+    pub items: bool,
+    // Docs of instruction arguments and accounts, and of the fields of types.
+    pub fields: bool,
+    // `CHECK:` comments justifying the safety of unchecked accounts, up to the
+    // end of their paragraph.
+    pub check_comments: bool,
+    // Maximum length of each doc, in characters not counting line breaks.
+    // Longer docs are cut, ending with `...`.
+    pub max_length: Option<usize>,
+}
+
+impl DocsPolicy {
+    // Leaves out all docs.
+    pub fn none() -> Self {
+        Self {
+            items: false,
+            fields: false,
+            ..Self::default()
+        }
+    }
+
+    // Returns true if no docs are kept at all.
+    pub fn is_none(&self) -> bool {
+        !self.items && !self.fields
+    }
+}
+
+impl Default for DocsPolicy {
+    fn default() -> Self {
+        Self {
+            items: true,
+            fields: true,
+            check_comments: false,
+            max_length: None,
+        }
+    }
+}
+
+// A non-fatal issue found while generating an IDL. These don't fail the
+// generation, but usually mean that the IDL is incomplete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdlWarning {
+    // A serializable type that isn't public, and so is left out.
+    PrivateType(String),
+    // A serializable tuple struct, which the IDL can't describe, and so is
+    // left out.
+    TupleStruct(String),
+    // An item with doc attributes that can't be resolved without expanding
+    // macros, e.g. `#[doc = include_str!("..")]`, which are left out.
+    UnresolvedDocs(String),
+    // A constant defined more than once.
+    DuplicateConst(String),
+    // A `#[constant]` that isn't public, and so is left out.
+    PrivateConst(String),
+    // A floating point type, when using `FloatPolicy::Warn`.
+    Float {
+        bits: u8,
+        location: String,
+    },
+    // A type used but not defined, when generating the IDL with `idl-build`,
+    // e.g. a type not defined with Anchor's macros.
+    UndefinedType(String),
+    // An instruction writing a field given `#[writable_by = ".."]`, without
+    // the account allowed to write it as a signer.
+    UnauthorizedWrite {
+        instruction: String,
+        field: String,
+        writable_by: String,
+    },
+}
+
+impl std::fmt::Display for IdlWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdlWarning::PrivateType(name) => {
+                write!(f, "{} is serializable but not public, skipping", name)
+            }
+            IdlWarning::TupleStruct(name) => {
+                write!(
+                    f,
+                    "{} is a tuple struct, which isn't supported, skipping",
+                    name
+                )
+            }
+            IdlWarning::UnresolvedDocs(name) => {
+                write!(f, "unable to resolve the docs of {}", name)
+            }
+            IdlWarning::DuplicateConst(name) => {
+                write!(f, "constant {} is defined more than once", name)
+            }
+            IdlWarning::PrivateConst(name) => {
+                write!(f, "constant {} is not public, skipping", name)
+            }
+            IdlWarning::Float { bits, location } => write!(
+                f,
+                "f{} used in {} doesn't serialize deterministically across clients",
+                bits, location
+            ),
+            IdlWarning::UndefinedType(name) => write!(
+                f,
+                "{} isn't defined with Anchor's macros, so its definition is missing",
+                name
+            ),
+            IdlWarning::UnauthorizedWrite {
+                instruction,
+                field,
+                writable_by,
+            } => write!(
+                f,
+                "instruction {} writes {}, which is writable by {}, without {} as a signer",
+                instruction, field, writable_by, writable_by
+            ),
+        }
+    }
 }
 
 impl std::str::FromStr for IdlType {
@@ -274,11 +1057,15 @@ pub struct IdlErrorCode {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub msg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub docs: Option<Vec<String>>,
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::idl::IdlType;
+    use crate::idl::{
+        IdlAccount, IdlAccountItem, IdlAccounts, IdlInstruction, IdlSigners, IdlType,
+    };
     use std::str::FromStr;
 
     #[test]
@@ -313,6 +1100,61 @@ mod tests {
         )
     }
 
+    #[test]
+    fn name_accounts() {
+        let account = |name: &str| {
+            IdlAccountItem::IdlAccount(Box::new(IdlAccount {
+                name: name.to_string(),
+                is_mut: false,
+                is_signer: false,
+                is_optional: None,
+                is_init_if_needed: None,
+                space: None,
+                docs: None,
+                token: None,
+                realloc: None,
+                pda: None,
+                kind: None,
+                address: None,
+                programs: None,
+                close: None,
+            }))
+        };
+        let ix = IdlInstruction {
+            name: "swap".to_string(),
+            docs: None,
+            accounts: vec![
+                account("authority"),
+                IdlAccountItem::IdlAccounts(IdlAccounts {
+                    name: "market".to_string(),
+                    accounts: vec![account("bids"), account("asks")],
+                }),
+            ],
+            args: vec![],
+            returns: None,
+            namespace: None,
+            discriminator: None,
+            guards: vec![],
+            remaining_accounts: None,
+            sunset: None,
+            compute_budget: None,
+            distinct_accounts: vec![],
+            admin: false,
+            batch: vec![],
+            signers: IdlSigners::default(),
+        };
+        assert_eq!(
+            ix.name_accounts(0..5),
+            vec![
+                ("authority".to_string(), 0),
+                ("market.bids".to_string(), 1),
+                ("market.asks".to_string(), 2),
+                ("remainingAccounts[0]".to_string(), 3),
+                ("remainingAccounts[1]".to_string(), 4),
+            ]
+        );
+    }
+
     #[test]
     fn vector() {
         assert_eq!(
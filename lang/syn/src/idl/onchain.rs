@@ -0,0 +1,282 @@
+use crate::idl::Idl;
+use anyhow::{anyhow, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde_json::{Map, Number, Value as JsonValue};
+use std::io::{Read, Write};
+
+// Binary encoding of the IDL, and the format of the IDL stored on chain.
+//
+// `Idl::to_borsh` encodes the JSON tree of the IDL with borsh, after a version
+// byte. Objects keep their keys, so that the encoding doesn't change as fields
+// are added to the IDL, and fields unknown to the reader are ignored when
+// decoding, as with JSON.
+//
+// The IDL account of a program holds the IDL's JSON compressed with zlib, as
+// written by `anchor idl init`, or with zstd, which is told apart by its
+// header when decompressing:
+//
+// | discriminator | authority | data length | data     |
+// | ------------- | --------- | ----------- | -------- |
+// | 8 bytes       | 32 bytes  | u32         | variable |
+
+// The version of the binary encoding written by `Idl::to_borsh`.
+pub const BORSH_VERSION: u8 = 0;
+
+// The header of zstd frames.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+// The offset of the data in the IDL account, after its discriminator and
+// authority.
+const ACCOUNT_DATA_OFFSET: usize = 8 + 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Zlib,
+    Zstd,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::Zlib
+    }
+}
+
+impl std::str::FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "zlib" => Ok(Compression::Zlib),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(anyhow!("Unknown compression {}, expected zlib or zstd", s)),
+        }
+    }
+}
+
+// Encoded as borsh derives it, i.e. the variant index followed by its fields,
+// implemented by hand since the derives can't handle the recursion.
+enum Node {
+    Null,
+    Bool(bool),
+    UInt(u64),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+impl BorshSerialize for Node {
+    fn serialize<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        match self {
+            Node::Null => 0u8.serialize(writer),
+            Node::Bool(b) => {
+                1u8.serialize(writer)?;
+                b.serialize(writer)
+            }
+            Node::UInt(n) => {
+                2u8.serialize(writer)?;
+                n.serialize(writer)
+            }
+            Node::Int(n) => {
+                3u8.serialize(writer)?;
+                n.serialize(writer)
+            }
+            Node::Float(n) => {
+                4u8.serialize(writer)?;
+                n.serialize(writer)
+            }
+            Node::String(s) => {
+                5u8.serialize(writer)?;
+                s.serialize(writer)
+            }
+            Node::Array(nodes) => {
+                6u8.serialize(writer)?;
+                (nodes.len() as u32).serialize(writer)?;
+                nodes.iter().try_for_each(|node| node.serialize(writer))
+            }
+            Node::Object(entries) => {
+                7u8.serialize(writer)?;
+                (entries.len() as u32).serialize(writer)?;
+                entries.iter().try_for_each(|(key, node)| {
+                    key.serialize(writer)?;
+                    node.serialize(writer)
+                })
+            }
+        }
+    }
+}
+
+impl BorshDeserialize for Node {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        Ok(match u8::deserialize(buf)? {
+            0 => Node::Null,
+            1 => Node::Bool(bool::deserialize(buf)?),
+            2 => Node::UInt(u64::deserialize(buf)?),
+            3 => Node::Int(i64::deserialize(buf)?),
+            4 => Node::Float(f64::deserialize(buf)?),
+            5 => Node::String(String::deserialize(buf)?),
+            6 => {
+                let len = u32::deserialize(buf)?;
+                Node::Array(
+                    (0..len)
+                        .map(|_| Node::deserialize(buf))
+                        .collect::<std::io::Result<_>>()?,
+                )
+            }
+            7 => {
+                let len = u32::deserialize(buf)?;
+                Node::Object(
+                    (0..len)
+                        .map(|_| Ok((String::deserialize(buf)?, Node::deserialize(buf)?)))
+                        .collect::<std::io::Result<_>>()?,
+                )
+            }
+            variant => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Unexpected IDL node variant {}", variant),
+                ))
+            }
+        })
+    }
+}
+
+impl From<&JsonValue> for Node {
+    fn from(value: &JsonValue) -> Self {
+        match value {
+            JsonValue::Null => Node::Null,
+            JsonValue::Bool(b) => Node::Bool(*b),
+            JsonValue::Number(n) => match (n.as_u64(), n.as_i64()) {
+                (Some(n), _) => Node::UInt(n),
+                (_, Some(n)) => Node::Int(n),
+                _ => Node::Float(n.as_f64().unwrap_or_default()),
+            },
+            JsonValue::String(s) => Node::String(s.clone()),
+            JsonValue::Array(values) => Node::Array(values.iter().map(Into::into).collect()),
+            JsonValue::Object(map) => Node::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<Node> for JsonValue {
+    fn from(node: Node) -> Self {
+        match node {
+            Node::Null => JsonValue::Null,
+            Node::Bool(b) => JsonValue::Bool(b),
+            Node::UInt(n) => JsonValue::Number(n.into()),
+            Node::Int(n) => JsonValue::Number(n.into()),
+            Node::Float(n) => Number::from_f64(n).map_or(JsonValue::Null, JsonValue::Number),
+            Node::String(s) => JsonValue::String(s),
+            Node::Array(nodes) => JsonValue::Array(nodes.into_iter().map(Into::into).collect()),
+            Node::Object(entries) => JsonValue::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, node)| (key, node.into()))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+impl Idl {
+    // Encodes the IDL with borsh.
+    pub fn to_borsh(&self) -> Result<Vec<u8>> {
+        let node = Node::from(&serde_json::to_value(self)?);
+        let mut data = vec![BORSH_VERSION];
+        node.serialize(&mut data)?;
+        Ok(data)
+    }
+
+    // Decodes an IDL encoded by `Idl::to_borsh`.
+    pub fn from_borsh(data: &[u8]) -> Result<Self> {
+        let (version, mut data) = data
+            .split_first()
+            .ok_or_else(|| anyhow!("Empty IDL data"))?;
+        if *version != BORSH_VERSION {
+            return Err(anyhow!("Unsupported IDL encoding version {}", version));
+        }
+        let node = Node::deserialize(&mut data)?;
+        if !data.is_empty() {
+            return Err(anyhow!("Unexpected {} bytes after the IDL", data.len()));
+        }
+        serde_json::from_value(node.into()).map_err(Into::into)
+    }
+}
+
+// Compresses the IDL's JSON, as stored in the data of its account.
+pub fn compress(idl: &Idl, compression: Compression) -> Result<Vec<u8>> {
+    let json_bytes = serde_json::to_vec(idl)?;
+    match compression {
+        Compression::Zlib => {
+            let mut e = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            e.write_all(&json_bytes)?;
+            e.finish().map_err(Into::into)
+        }
+        Compression::Zstd => zstd::stream::encode_all(&json_bytes[..], 0).map_err(Into::into),
+    }
+}
+
+// Decompresses the data of an IDL account, whichever its compression.
+pub fn decompress(data: &[u8]) -> Result<Idl> {
+    let mut json_bytes = Vec::new();
+    match data.starts_with(&ZSTD_MAGIC) {
+        true => zstd::stream::copy_decode(data, &mut json_bytes)?,
+        false => {
+            flate2::read::ZlibDecoder::new(data).read_to_end(&mut json_bytes)?;
+        }
+    }
+    serde_json::from_slice(&json_bytes).map_err(Into::into)
+}
+
+// Decodes the IDL from the raw data of its account, discriminator included.
+pub fn decode_account(account_data: &[u8]) -> Result<Idl> {
+    let mut data = account_data
+        .get(ACCOUNT_DATA_OFFSET..)
+        .ok_or_else(|| anyhow!("IDL account data too short"))?;
+    let idl_data = Vec::<u8>::deserialize(&mut data)?;
+    decompress(&idl_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn onchain_round_trip() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "counter",
+            "instructions": [{
+                "name": "increment",
+                "accounts": [{ "name": "counter", "isMut": true, "isSigner": false }],
+                "args": [{ "name": "by", "type": { "array": ["u8", 4] } }]
+            }],
+            "accounts": [{
+                "name": "Counter",
+                "type": { "kind": "struct", "fields": [{ "name": "count", "type": "i64" }] }
+            }],
+            "metadata": { "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS", "ratio": -0.5 }
+        }))
+        .unwrap();
+
+        let data = idl.to_borsh().unwrap();
+        assert_eq!(data[0], BORSH_VERSION);
+        assert_eq!(Idl::from_borsh(&data).unwrap(), idl);
+        assert!(Idl::from_borsh(&data[..data.len() - 1]).is_err());
+
+        for compression in [Compression::Zlib, Compression::Zstd] {
+            let idl_data = compress(&idl, compression).unwrap();
+            assert_eq!(decompress(&idl_data).unwrap(), idl);
+
+            let mut account_data = vec![0; ACCOUNT_DATA_OFFSET];
+            idl_data.serialize(&mut account_data).unwrap();
+            assert_eq!(decode_account(&account_data).unwrap(), idl);
+        }
+    }
+}
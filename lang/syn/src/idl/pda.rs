@@ -88,11 +88,71 @@ impl<'a> PdaParser<'a> {
         let program_id = seeds_grp
             .program_seed
             .as_ref()
-            .map(|pid| self.parse_seed(pid))
+            .map(|pid| self.parse_program_seed(pid))
             .unwrap_or_default();
 
+        // Parse the bump, if it's stored in an account.
+        let bump = seeds_grp
+            .bump
+            .as_ref()
+            .and_then(|bump| self.parse_bump(bump));
+
         // Done.
-        Some(IdlPda { seeds, program_id })
+        Some(IdlPda {
+            seeds,
+            program_id,
+            bump,
+        })
+    }
+
+    // Parses a bump stored in a `u8` field of an account of the accounts
+    // context, e.g. `bump = vault.bump`. Other bumps, e.g. instruction
+    // arguments, aren't described.
+    fn parse_bump(&self, bump: &Expr) -> Option<IdlSeed> {
+        let seed_path = match bump {
+            Expr::Field(_) => parse_seed_path(bump)?,
+            _ => return None,
+        };
+        if !self.is_account(&seed_path) || seed_path.components().len() != 1 {
+            return None;
+        }
+        let account_field = self
+            .accounts
+            .fields
+            .iter()
+            .find(|field| *field.ident() == seed_path.name())
+            .unwrap();
+        let account = account_field.ty_name()?;
+        let field_ty = self
+            .ctx
+            .structs()
+            .find(|s| s.ident == account)
+            .and_then(|strct| {
+                strct.fields.iter().find(|f| match &f.ident {
+                    Some(ident) => *ident == seed_path.components()[0],
+                    None => false,
+                })
+            })
+            .map(|field| parser::tts_to_string(&field.ty));
+        match field_ty.as_deref() {
+            Some("u8") => Some(IdlSeed::Account(IdlSeedAccount {
+                ty: IdlType::U8,
+                account: Some(account),
+                path: seed_path.path(),
+            })),
+            Some(ty) => {
+                println!(
+                    "WARNING: bump {} must be a u8, found {}",
+                    seed_path.path(),
+                    ty
+                );
+                None
+            }
+            None => {
+                println!("WARNING: unable to find bump field: {}", seed_path.path());
+                None
+            }
+        }
     }
 
     fn parse_seed(&self, seed: &Expr) -> Option<IdlSeed> {
@@ -114,6 +174,15 @@ impl<'a> PdaParser<'a> {
                 }
             }
             Expr::Reference(expr_reference) => self.parse_seed(&expr_reference.expr),
+            Expr::Lit(_) => {
+                let seed_path = SeedPath(parser::tts_to_string(seed), Vec::new());
+                if self.is_str_literal(&seed_path) {
+                    self.parse_str_literal(&seed_path)
+                } else {
+                    println!("WARNING: unexpected seed literal: {:?}", seed_path);
+                    None
+                }
+            }
             Expr::Index(_) => {
                 println!("WARNING: auto pda derivation not currently supported for slice literals");
                 None
@@ -126,8 +195,80 @@ impl<'a> PdaParser<'a> {
         }
     }
 
+    // Parses the `seeds::program` constraint, which is either an account in
+    // the accounts context (e.g. `other_program.key()`) or a constant program
+    // id (e.g. `other_program::ID`, `other_program::id()` or a `Pubkey` const).
+    fn parse_program_seed(&self, program_seed: &Expr) -> Option<IdlSeed> {
+        let path = match program_seed {
+            Expr::Reference(expr_reference) => {
+                return self.parse_program_seed(&expr_reference.expr)
+            }
+            Expr::Path(expr_path) => &expr_path.path,
+            Expr::Call(expr_call) if expr_call.args.is_empty() => match &*expr_call.func {
+                Expr::Path(expr_path) => &expr_path.path,
+                _ => return self.parse_seed(program_seed),
+            },
+            _ => return self.parse_seed(program_seed),
+        };
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        let (name, module) = match segments.split_last() {
+            None => return None,
+            Some((name, modules)) => (
+                name,
+                modules
+                    .last()
+                    .filter(|m| *m != "crate" && *m != "self" && *m != "super"),
+            ),
+        };
+        let address = if name == "ID" || name == "id" {
+            self.ctx.declared_id(module.map(|m| m.as_str()))
+        } else {
+            self.ctx
+                .consts()
+                .find(|c| c.ident == name)
+                .and_then(|c| match &*c.expr {
+                    Expr::Macro(expr_macro) => expr_macro
+                        .mac
+                        .parse_body::<syn::LitStr>()
+                        .ok()
+                        .map(|lit| lit.value()),
+                    _ => None,
+                })
+        };
+        match address {
+            None => {
+                println!(
+                    "WARNING: unable to resolve seeds::program: {}",
+                    parser::tts_to_string(program_seed)
+                );
+                None
+            }
+            Some(address) => Some(IdlSeed::Const(IdlSeedConst {
+                ty: IdlType::PublicKey,
+                value: serde_json::Value::String(address),
+            })),
+        }
+    }
+
     fn parse_instruction(&self, seed_path: &SeedPath) -> Option<IdlSeed> {
-        let idl_ty = IdlType::from_str(self.ix_args.get(&seed_path.name()).unwrap()).ok()?;
+        let arg_ty = self.ix_args.get(&seed_path.name()).unwrap();
+        let idl_ty = match seed_path.components().is_empty() {
+            true => IdlType::from_str(arg_ty).ok()?,
+            // Field access on a struct argument, e.g. `params.amount`.
+            false => {
+                let mut arg_ty = arg_ty.clone();
+                arg_ty.retain(|c| !c.is_whitespace());
+                let strct = match self.ctx.structs().find(|s| s.ident == arg_ty) {
+                    None => {
+                        println!("WARNING: unable to find argument type: {}", arg_ty);
+                        return None;
+                    }
+                    Some(strct) => strct,
+                };
+                let mut path = seed_path.components();
+                parse_field_path(self.ctx, strct, &mut path)
+            }
+        };
         Some(IdlSeed::Arg(IdlSeedArg {
             ty: idl_ty,
             path: seed_path.path(),
@@ -156,10 +297,12 @@ impl<'a> PdaParser<'a> {
             }
         }
 
-        Some(IdlSeed::Const(IdlSeedConst {
-            ty: idl_ty,
-            value: serde_json::from_str(&idl_ty_value).unwrap(),
-        }))
+        let value = match idl_ty.is_string_encoded() {
+            true => serde_json::Value::String(parse_int_literal(&idl_ty_value)?),
+            false => serde_json::from_str(&idl_ty_value).unwrap(),
+        };
+
+        Some(IdlSeed::Const(IdlSeedConst { ty: idl_ty, value }))
     }
 
     fn parse_account(&self, seed_path: &SeedPath) -> Option<IdlSeed> {
@@ -320,3 +463,220 @@ fn parse_field_path(ctx: &CrateContext, strct: &syn::ItemStruct, path: &mut &[St
 
     parse_field_path(ctx, strct, path)
 }
+
+// Returns the decimal digits of an integer literal, e.g. `"-1000"` for
+// `-1_000i128`.
+fn parse_int_literal(lit: &str) -> Option<String> {
+    let (sign, lit) = match lit.trim().strip_prefix('-') {
+        Some(lit) => ("-", lit.trim()),
+        None => ("", lit.trim()),
+    };
+    let lit = syn::parse_str::<syn::LitInt>(lit).ok()?;
+    Some(format!("{}{}", sign, lit.base10_digits()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::idl::file;
+    use crate::idl::*;
+
+    #[test]
+    fn instruction_arg_seeds() {
+        let src = r#"
+            use anchor_lang::prelude::*;
+
+            #[program]
+            pub mod vault {
+                use super::*;
+                pub fn deposit(ctx: Context<Deposit>, amount: u64, params: Params) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(AnchorSerialize, AnchorDeserialize)]
+            pub struct Params {
+                pub nonce: u16,
+            }
+
+            #[derive(Accounts)]
+            #[instruction(amount: u64, params: Params)]
+            pub struct Deposit<'info> {
+                #[account(
+                    seeds = [b"vault", amount.to_le_bytes().as_ref(), params.nonce.to_le_bytes().as_ref()],
+                    bump,
+                )]
+                pub vault: AccountInfo<'info>,
+            }
+        "#;
+        let path = std::env::temp_dir().join("anchor_syn_pda_instruction_arg_seeds.rs");
+        std::fs::write(&path, src).unwrap();
+        let idl = file::parse(
+            &path,
+            &file::ParseOptions {
+                seeds_feature: true,
+                docs_policy: DocsPolicy::none(),
+                ..Default::default()
+            },
+            &file::ExternalAccounts::default(),
+        )
+        .unwrap()
+        .unwrap()
+        .idl;
+        let pda = match &idl.instructions[0].accounts[0] {
+            IdlAccountItem::IdlAccount(acc) => acc.pda.clone().unwrap(),
+            _ => panic!("expected an account"),
+        };
+        assert_eq!(
+            pda.seeds,
+            vec![
+                IdlSeed::Const(IdlSeedConst {
+                    ty: IdlType::String,
+                    value: serde_json::Value::String("vault".to_string()),
+                }),
+                IdlSeed::Arg(IdlSeedArg {
+                    ty: IdlType::U64,
+                    path: "amount".to_string(),
+                }),
+                IdlSeed::Arg(IdlSeedArg {
+                    ty: IdlType::U16,
+                    path: "params.nonce".to_string(),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn program_seeds() {
+        let src = r#"
+            use anchor_lang::prelude::*;
+
+            declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+            pub const METADATA_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+            pub mod other {
+                declare_id!("9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin");
+            }
+
+            #[program]
+            pub mod pdas {
+                use super::*;
+                pub fn init(ctx: Context<Init>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Init<'info> {
+                #[account(seeds = [b"a"], bump, seeds::program = other::ID)]
+                pub a: AccountInfo<'info>,
+                #[account(seeds = [b"b"], bump, seeds::program = METADATA_ID)]
+                pub b: AccountInfo<'info>,
+                #[account(seeds = [b"c"], bump, seeds::program = other_program.key())]
+                pub c: AccountInfo<'info>,
+                pub other_program: AccountInfo<'info>,
+            }
+        "#;
+        let path = std::env::temp_dir().join("anchor_syn_pda_program_seeds.rs");
+        std::fs::write(&path, src).unwrap();
+        let idl = file::parse(
+            &path,
+            &file::ParseOptions {
+                seeds_feature: true,
+                docs_policy: DocsPolicy::none(),
+                ..Default::default()
+            },
+            &file::ExternalAccounts::default(),
+        )
+        .unwrap()
+        .unwrap()
+        .idl;
+        let program_ids: Vec<Option<IdlSeed>> = idl.instructions[0]
+            .accounts
+            .iter()
+            .map(|acc| match acc {
+                IdlAccountItem::IdlAccount(acc) => {
+                    acc.pda.as_ref().and_then(|p| p.program_id.clone())
+                }
+                _ => panic!("expected an account"),
+            })
+            .collect();
+        let const_id = |id: &str| {
+            Some(IdlSeed::Const(IdlSeedConst {
+                ty: IdlType::PublicKey,
+                value: serde_json::Value::String(id.to_string()),
+            }))
+        };
+        assert_eq!(
+            program_ids,
+            vec![
+                const_id("9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"),
+                const_id("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"),
+                Some(IdlSeed::Account(IdlSeedAccount {
+                    ty: IdlType::PublicKey,
+                    account: None,
+                    path: "other_program".to_string(),
+                })),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn int128_const_seeds() {
+        let src = r#"
+            use anchor_lang::prelude::*;
+
+            pub const MAX: u128 = 340_282_366_920_938_463_463_374_607_431_768_211_455u128;
+            pub const MIN: i128 = -170141183460469231731687303715884105728;
+
+            #[program]
+            pub mod pdas {
+                use super::*;
+                pub fn init(ctx: Context<Init>) -> Result<()> {
+                    Ok(())
+                }
+            }
+
+            #[derive(Accounts)]
+            pub struct Init<'info> {
+                #[account(seeds = [MAX.to_le_bytes().as_ref(), MIN.to_le_bytes().as_ref()], bump)]
+                pub pda: AccountInfo<'info>,
+            }
+        "#;
+        let path = std::env::temp_dir().join("anchor_syn_pda_int128_const_seeds.rs");
+        std::fs::write(&path, src).unwrap();
+        let idl = file::parse(
+            &path,
+            &file::ParseOptions {
+                seeds_feature: true,
+                docs_policy: DocsPolicy::none(),
+                ..Default::default()
+            },
+            &file::ExternalAccounts::default(),
+        )
+        .unwrap()
+        .unwrap()
+        .idl;
+
+        // Round trip through JSON without losing precision.
+        let idl: Idl = serde_json::from_str(&serde_json::to_string(&idl).unwrap()).unwrap();
+        let pda = match &idl.instructions[0].accounts[0] {
+            IdlAccountItem::IdlAccount(acc) => acc.pda.clone().unwrap(),
+            _ => panic!("expected an account"),
+        };
+        let values: Vec<String> = pda
+            .seeds
+            .iter()
+            .map(|seed| match seed {
+                IdlSeed::Const(c) => {
+                    assert!(c.ty.is_string_encoded());
+                    c.value.as_str().unwrap().to_string()
+                }
+                _ => panic!("expected a const seed"),
+            })
+            .collect();
+        assert_eq!(values[0].parse::<u128>().unwrap(), u128::MAX);
+        assert_eq!(values[1].parse::<i128>().unwrap(), i128::MIN);
+    }
+}
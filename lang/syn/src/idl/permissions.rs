@@ -0,0 +1,193 @@
+use crate::idl::IdlWarning;
+use crate::parser::permissions;
+use crate::{AccountField, AccountsStruct, Ix, Ty};
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+use syn::visit::{self, Visit};
+
+// Best effort check of the instructions writing fields given
+// `#[writable_by = ".."]`, warning about the ones without the account allowed
+// to write them as a signer.
+//
+// Only the writes made directly by the handlers are found, i.e. assignments
+// to a field of `ctx.accounts.<account>`, or of a local bound to it, e.g.
+// `let vault = &mut ctx.accounts.vault;`. Writes made by other functions, or
+// through composite accounts, aren't checked.
+pub(crate) fn check_writes(
+    ixs: &[Ix],
+    accs: &HashMap<String, AccountsStruct>,
+    accounts: &[&syn::ItemStruct],
+    warnings: &mut Vec<IdlWarning>,
+) -> Result<()> {
+    // The signer allowed to write each field, by account and field name.
+    let mut writable_by = HashMap::new();
+    for strct in accounts {
+        for field in strct.fields.iter() {
+            if let Some(authority) = permissions::writable_by(&field.attrs)? {
+                let field = field.ident.as_ref().unwrap().to_string();
+                writable_by.insert((strct.ident.to_string(), field), authority);
+            }
+        }
+    }
+    if writable_by.is_empty() {
+        return Ok(());
+    }
+
+    for ix in ixs {
+        let accounts_strct = match accs.get(&ix.anchor_ident.to_string()) {
+            Some(accounts_strct) => accounts_strct,
+            None => continue,
+        };
+        let mut writes = Writes::new(&ix.raw_method);
+        writes.visit_block(&ix.raw_method.block);
+        for (account, field) in writes.writes {
+            let ty = match account_type(accounts_strct, &account) {
+                Some(ty) => ty,
+                None => continue,
+            };
+            let authority = match writable_by.get(&(ty.clone(), field.clone())) {
+                Some(authority) => authority,
+                None => continue,
+            };
+            if !is_signer(accounts_strct, authority) {
+                warnings.push(IdlWarning::UnauthorizedWrite {
+                    instruction: ix.ident.to_string(),
+                    field: format!("{}.{}", ty, field),
+                    writable_by: authority.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+// The name of the account type of the field `name` of an accounts struct.
+fn account_type(accounts_strct: &AccountsStruct, name: &str) -> Option<String> {
+    let field = accounts_strct.fields.iter().find_map(|f| match f {
+        AccountField::Field(f) if f.ident == name => Some(f),
+        _ => None,
+    })?;
+    let path = match &field.ty {
+        Ty::Account(ty) => &ty.account_type_path,
+        Ty::AccountLoader(ty) => &ty.account_type_path,
+        Ty::ProgramAccount(ty) => &ty.account_type_path,
+        Ty::Loader(ty) => &ty.account_type_path,
+        _ => return None,
+    };
+    Some(path.path.segments.last()?.ident.to_string())
+}
+
+fn is_signer(accounts_strct: &AccountsStruct, name: &str) -> bool {
+    accounts_strct.fields.iter().any(|f| match f {
+        AccountField::Field(f) => {
+            f.ident == name && (matches!(f.ty, Ty::Signer) || f.constraints.is_signer())
+        }
+        AccountField::CompositeField(_) => false,
+    })
+}
+
+// The fields of the accounts written by a handler, as account and field names.
+struct Writes {
+    // The name of the handler's `Context` argument.
+    ctx: Option<String>,
+    // Locals bound to an account of the context.
+    aliases: HashMap<String, String>,
+    writes: BTreeSet<(String, String)>,
+}
+
+impl Writes {
+    fn new(handler: &syn::ItemFn) -> Self {
+        let ctx = handler.sig.inputs.iter().find_map(|arg| match arg {
+            syn::FnArg::Typed(arg) => match &*arg.pat {
+                syn::Pat::Ident(pat) => Some(pat.ident.to_string()),
+                _ => None,
+            },
+            syn::FnArg::Receiver(_) => None,
+        });
+        Self {
+            ctx,
+            aliases: HashMap::new(),
+            writes: BTreeSet::new(),
+        }
+    }
+
+    // The account `expr` refers to, along with the fields accessed on it, e.g.
+    // `vault` and `["fee"]` for `ctx.accounts.vault.fee`.
+    fn account(&self, expr: &syn::Expr) -> Option<(String, Vec<String>)> {
+        let members = members(expr)?;
+        let (first, rest) = members.split_first()?;
+        if Some(first) == self.ctx.as_ref() {
+            match rest {
+                [accounts, account, fields @ ..] if accounts == "accounts" => {
+                    Some((account.clone(), fields.to_vec()))
+                }
+                _ => None,
+            }
+        } else {
+            let account = self.aliases.get(first)?;
+            Some((account.clone(), rest.to_vec()))
+        }
+    }
+
+    fn write(&mut self, expr: &syn::Expr) {
+        if let Some((account, fields)) = self.account(expr) {
+            if let Some(field) = fields.into_iter().next() {
+                self.writes.insert((account, field));
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Writes {
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        if let (syn::Pat::Ident(pat), Some((_, init))) = (&local.pat, &local.init) {
+            match self.account(init) {
+                Some((account, fields)) if fields.is_empty() => {
+                    self.aliases.insert(pat.ident.to_string(), account);
+                }
+                _ => {
+                    self.aliases.remove(&pat.ident.to_string());
+                }
+            }
+        }
+        visit::visit_local(self, local);
+    }
+
+    fn visit_expr_assign(&mut self, assign: &'ast syn::ExprAssign) {
+        self.write(&assign.left);
+        visit::visit_expr_assign(self, assign);
+    }
+
+    fn visit_expr_assign_op(&mut self, assign: &'ast syn::ExprAssignOp) {
+        self.write(&assign.left);
+        visit::visit_expr_assign_op(self, assign);
+    }
+}
+
+// The names making up a place expression, e.g. `["ctx", "accounts", "vault"]`
+// for `&mut ctx.accounts.vault`, or `ctx.accounts.vault.load_mut()?`.
+fn members(expr: &syn::Expr) -> Option<Vec<String>> {
+    match expr {
+        syn::Expr::Path(path) => Some(vec![path.path.get_ident()?.to_string()]),
+        syn::Expr::Field(field) => {
+            let mut members = members(&field.base)?;
+            match &field.member {
+                syn::Member::Named(ident) => members.push(ident.to_string()),
+                syn::Member::Unnamed(index) => members.push(index.index.to_string()),
+            }
+            Some(members)
+        }
+        syn::Expr::Reference(reference) => members(&reference.expr),
+        syn::Expr::Paren(paren) => members(&paren.expr),
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Deref(_),
+            expr,
+            ..
+        }) => members(expr),
+        syn::Expr::Try(try_expr) => members(&try_expr.expr),
+        syn::Expr::MethodCall(call) if call.method == "load_mut" || call.method == "load_init" => {
+            members(&call.receiver)
+        }
+        _ => None,
+    }
+}
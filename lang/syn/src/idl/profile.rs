@@ -0,0 +1,172 @@
+use crate::idl::*;
+use anyhow::{anyhow, Result};
+use heck::MixedCase;
+use std::collections::HashSet;
+
+impl Idl {
+    // Returns the IDL of a client profile, i.e., with only the allowlisted
+    // instructions, given by their name in the program (e.g. `set_fee`) or in
+    // the IDL (e.g. `setFee`).
+    //
+    // Types only used by the removed instructions are removed as well, while
+    // accounts, events and errors are kept, as clients may still decode them.
+    pub fn with_instructions(&self, allowlist: &[String]) -> Result<Idl> {
+        let allowed: HashSet<String> = allowlist.iter().map(|name| name.to_mixed_case()).collect();
+        let known: HashSet<String> = self
+            .instructions
+            .iter()
+            .chain(self.state.iter().flat_map(|state| &state.methods))
+            .map(|ix| ix.name.to_mixed_case())
+            .collect();
+        let mut unknown: Vec<&String> = allowlist
+            .iter()
+            .filter(|name| !known.contains(&name.to_mixed_case()))
+            .collect();
+        if !unknown.is_empty() {
+            unknown.sort();
+            return Err(anyhow!(
+                "Unknown instructions in the allowlist of {}: {}",
+                self.name,
+                unknown
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        let mut idl = self.clone();
+        let is_allowed = |ix: &IdlInstruction| allowed.contains(&ix.name.to_mixed_case());
+        idl.instructions.retain(is_allowed);
+        if let Some(state) = &mut idl.state {
+            state.methods.retain(is_allowed);
+        }
+        if let Some(instruction_enum) = &mut idl.instruction_enum {
+            let names: HashSet<&String> = idl.instructions.iter().map(|ix| &ix.name).collect();
+            instruction_enum
+                .variants
+                .retain(|variant| names.contains(&variant.instruction));
+        }
+
+        // Keep the types reachable from what's left.
+        let mut used = HashSet::new();
+        let args = idl
+            .instructions
+            .iter()
+            .chain(idl.state.iter().flat_map(|state| &state.methods))
+            .flat_map(|ix| ix.args.iter().map(|arg| &arg.ty));
+        let fields = idl
+            .accounts
+            .iter()
+            .chain(idl.state.iter().map(|state| &state.strct))
+            .flat_map(type_definition_types);
+        let events = idl
+            .events
+            .iter()
+            .flatten()
+            .flat_map(|event| event.fields.iter().map(|field| &field.ty));
+        let constants = idl.constants.iter().map(|c| &c.ty);
+        let mut queue: Vec<&IdlType> = args.chain(fields).chain(events).chain(constants).collect();
+        while let Some(ty) = queue.pop() {
+            match ty {
+                IdlType::Defined(name) if used.insert(name.clone()) => {
+                    if let Some(ty_def) = self.types.iter().find(|t| &t.name == name) {
+                        queue.extend(type_definition_types(ty_def));
+                    }
+                }
+                IdlType::Option(ty) | IdlType::Vec(ty) | IdlType::Array(ty, _) => queue.push(ty),
+                _ => {}
+            }
+        }
+        idl.types.retain(|ty_def| used.contains(&ty_def.name));
+        Ok(idl)
+    }
+}
+
+fn type_definition_types(ty_def: &IdlTypeDefinition) -> Vec<&IdlType> {
+    match &ty_def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => fields.iter().map(|f| &f.ty).collect(),
+        IdlTypeDefinitionTy::Enum { variants } => variants
+            .iter()
+            .flat_map(|variant| match &variant.fields {
+                Some(EnumFields::Named(fields)) => fields.iter().map(|f| &f.ty).collect(),
+                Some(EnumFields::Tuple(tys)) => tys.iter().collect(),
+                None => vec![],
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn with_instructions() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "vault",
+            "instructions": [
+                {
+                    "name": "deposit",
+                    "accounts": [],
+                    "args": [{ "name": "args", "type": { "defined": "DepositArgs" } }]
+                },
+                {
+                    "name": "setFee",
+                    "accounts": [],
+                    "args": [{ "name": "fee", "type": { "defined": "FeeConfig" } }]
+                }
+            ],
+            "accounts": [{
+                "name": "Vault",
+                "type": {
+                    "kind": "struct",
+                    "fields": [{ "name": "fee", "type": { "option": { "defined": "Fee" } } }]
+                }
+            }],
+            "types": [
+                {
+                    "name": "DepositArgs",
+                    "type": { "kind": "struct", "fields": [{ "name": "amount", "type": "u64" }] }
+                },
+                {
+                    "name": "FeeConfig",
+                    "type": { "kind": "struct", "fields": [{ "name": "fee", "type": { "defined": "Fee" } }] }
+                },
+                {
+                    "name": "Fee",
+                    "type": { "kind": "struct", "fields": [{ "name": "bps", "type": "u16" }] }
+                }
+            ]
+        }))
+        .unwrap();
+
+        let frontend = idl.with_instructions(&["deposit".to_string()]).unwrap();
+        assert_eq!(
+            frontend
+                .instructions
+                .iter()
+                .map(|ix| ix.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["deposit"]
+        );
+        // `Fee` is still used by the `Vault` account.
+        assert_eq!(
+            frontend
+                .types
+                .iter()
+                .map(|ty| ty.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["DepositArgs", "Fee"]
+        );
+        assert_eq!(frontend.accounts, idl.accounts);
+
+        let keeper = idl.with_instructions(&["set_fee".to_string()]).unwrap();
+        assert_eq!(keeper.instructions[0].name, "setFee");
+        assert_eq!(keeper.types.len(), 2);
+
+        assert!(idl.with_instructions(&["withdraw".to_string()]).is_err());
+    }
+}
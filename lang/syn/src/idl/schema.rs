@@ -0,0 +1,212 @@
+use crate::idl::*;
+use serde_json::{json, Map};
+
+const JSON_SCHEMA_DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+impl Idl {
+    // Returns a JSON Schema (draft 2020-12) describing the decoded instruction
+    // arguments and account data of the program.
+    //
+    // Each instruction's args are at `#/properties/instructions/properties/<name>`,
+    // each account at `#/properties/accounts/properties/<name>` and user
+    // defined types at `#/$defs/<name>`. Field names are the same as in the
+    // IDL, and 128 bit integers are expected as decimal strings.
+    pub fn json_schema(&self) -> JsonValue {
+        let instructions: Map<String, JsonValue> = self
+            .instructions
+            .iter()
+            .map(|ix| {
+                let mut schema = fields_schema(&ix.args);
+                with_docs(&mut schema, &ix.docs);
+                (ix.name.clone(), schema)
+            })
+            .collect();
+        let accounts: Map<String, JsonValue> = self
+            .accounts
+            .iter()
+            .map(|acc| (acc.name.clone(), type_definition_schema(acc)))
+            .collect();
+        let defs: Map<String, JsonValue> = self
+            .types
+            .iter()
+            .map(|ty| (ty.name.clone(), type_definition_schema(ty)))
+            .collect();
+
+        let mut schema = json!({
+            "$schema": JSON_SCHEMA_DRAFT,
+            "title": self.name,
+            "type": "object",
+            "properties": {
+                "instructions": {
+                    "type": "object",
+                    "properties": instructions,
+                    "additionalProperties": false,
+                },
+                "accounts": {
+                    "type": "object",
+                    "properties": accounts,
+                    "additionalProperties": false,
+                },
+            },
+            "$defs": defs,
+        });
+        with_docs(&mut schema, &self.docs);
+        schema
+    }
+}
+
+fn type_definition_schema(ty_def: &IdlTypeDefinition) -> JsonValue {
+    let mut schema = match &ty_def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => fields_schema(fields),
+        IdlTypeDefinitionTy::Enum { variants } => {
+            // Enums are decoded as an object with a single key, the variant
+            // name, e.g. `{ "Variant": { .. } }`.
+            let variants: Vec<JsonValue> = variants
+                .iter()
+                .map(|variant| {
+                    let fields = match &variant.fields {
+                        None => json!({ "type": "object", "maxProperties": 0 }),
+                        Some(EnumFields::Named(fields)) => fields_schema(fields),
+                        Some(EnumFields::Tuple(tys)) => tuple_schema(tys),
+                    };
+                    json!({
+                        "type": "object",
+                        "properties": { variant.name.clone(): fields },
+                        "required": [variant.name],
+                        "additionalProperties": false,
+                    })
+                })
+                .collect();
+            json!({ "oneOf": variants })
+        }
+    };
+    with_docs(&mut schema, &ty_def.docs);
+    schema
+}
+
+fn fields_schema(fields: &[IdlField]) -> JsonValue {
+    let properties: Map<String, JsonValue> = fields
+        .iter()
+        .map(|f| {
+            let mut schema = type_schema(&f.ty);
+            with_docs(&mut schema, &f.docs);
+            (f.name.clone(), schema)
+        })
+        .collect();
+    let required: Vec<&String> = fields.iter().map(|f| &f.name).collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+fn tuple_schema(tys: &[IdlType]) -> JsonValue {
+    let items: Vec<JsonValue> = tys.iter().map(type_schema).collect();
+    json!({
+        "type": "array",
+        "prefixItems": items,
+        "items": false,
+        "minItems": tys.len(),
+    })
+}
+
+fn type_schema(ty: &IdlType) -> JsonValue {
+    match ty {
+        IdlType::Bool => json!({ "type": "boolean" }),
+        IdlType::U8 => int_schema(u8::MIN as i64, u8::MAX as u64),
+        IdlType::I8 => int_schema(i8::MIN as i64, i8::MAX as u64),
+        IdlType::U16 => int_schema(u16::MIN as i64, u16::MAX as u64),
+        IdlType::I16 => int_schema(i16::MIN as i64, i16::MAX as u64),
+        IdlType::U32 => int_schema(u32::MIN as i64, u32::MAX as u64),
+        IdlType::I32 => int_schema(i32::MIN as i64, i32::MAX as u64),
+        IdlType::U64 => int_schema(u64::MIN as i64, u64::MAX),
+        IdlType::I64 => int_schema(i64::MIN, i64::MAX as u64),
+        IdlType::U128 => json!({ "type": "string", "pattern": "^[0-9]+$" }),
+        IdlType::I128 => json!({ "type": "string", "pattern": "^-?[0-9]+$" }),
+        IdlType::F32 | IdlType::F64 | IdlType::Float(_) => json!({ "type": "number" }),
+        IdlType::Bytes => json!({ "type": "array", "items": type_schema(&IdlType::U8) }),
+        IdlType::String => json!({ "type": "string" }),
+        IdlType::PublicKey => json!({
+            "type": "string",
+            "pattern": "^[1-9A-HJ-NP-Za-km-z]{32,44}$",
+        }),
+        IdlType::Defined(name) => json!({ "$ref": format!("#/$defs/{}", name) }),
+        IdlType::Option(ty) => json!({ "anyOf": [type_schema(ty), { "type": "null" }] }),
+        IdlType::Vec(ty) => json!({ "type": "array", "items": type_schema(ty) }),
+        IdlType::Array(ty, len) => json!({
+            "type": "array",
+            "items": type_schema(ty),
+            "minItems": len,
+            "maxItems": len,
+        }),
+    }
+}
+
+fn int_schema(min: i64, max: u64) -> JsonValue {
+    json!({ "type": "integer", "minimum": min, "maximum": max })
+}
+
+fn with_docs(schema: &mut JsonValue, docs: &Option<Vec<String>>) {
+    if let Some(docs) = docs {
+        schema["description"] = JsonValue::String(docs.join("\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_schema() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "counter",
+            "instructions": [{
+                "name": "increment",
+                "accounts": [],
+                "args": [{ "name": "by", "type": "u64" }]
+            }],
+            "accounts": [{
+                "name": "Counter",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "authority", "type": "publicKey" },
+                        { "name": "count", "type": "u128" },
+                        { "name": "mode", "type": { "option": { "defined": "Mode" } } }
+                    ]
+                }
+            }],
+            "types": [{
+                "name": "Mode",
+                "type": {
+                    "kind": "enum",
+                    "variants": [{ "name": "Fast" }, { "name": "Slow", "fields": ["u8"] }]
+                }
+            }]
+        }))
+        .unwrap();
+        let schema = idl.json_schema();
+
+        assert_eq!(schema["$schema"], JSON_SCHEMA_DRAFT);
+        assert_eq!(
+            schema["properties"]["instructions"]["properties"]["increment"]["properties"]["by"],
+            json!({ "type": "integer", "minimum": 0, "maximum": u64::MAX })
+        );
+        let counter = &schema["properties"]["accounts"]["properties"]["Counter"];
+        assert_eq!(counter["required"], json!(["authority", "count", "mode"]));
+        assert_eq!(counter["properties"]["count"]["type"], "string");
+        assert_eq!(
+            counter["properties"]["mode"]["anyOf"][0],
+            json!({ "$ref": "#/$defs/Mode" })
+        );
+        let variants = schema["$defs"]["Mode"]["oneOf"].as_array().unwrap();
+        assert_eq!(variants.len(), 2);
+        assert_eq!(
+            variants[1]["properties"]["Slow"]["prefixItems"][0]["maximum"],
+            u8::MAX
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use crate::idl::*;
+use anyhow::{anyhow, Result};
+use heck::{CamelCase, MixedCase};
+use serde_json::json;
+
+// Conversion between Anchor IDLs and the IDLs Shank generates for native
+// programs, so that clients of both are generated the same way.
+//
+// Shank instructions are discriminated by a `u8`, their index in the
+// program's instruction enum, and Shank accounts have no discriminator, which
+// the converted IDL records in the `discriminator` of its instructions and
+// accounts. Instruction names are PascalCase in Shank IDLs, and camelCase in
+// Anchor ones.
+//
+// Composite accounts are flattened when exporting, e.g. `marketBids` for the
+// `bids` of the `market` accounts, and what Shank doesn't describe, e.g. PDAs,
+// events and constants, is left out. Types Anchor doesn't describe, e.g.
+// tuples and maps, can't be imported.
+
+// The `origin` of the metadata of Shank IDLs.
+pub const SHANK_ORIGIN: &str = "shank";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ShankIdl {
+    pub version: String,
+    pub name: String,
+    pub instructions: Vec<ShankInstruction>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub accounts: Vec<IdlTypeDefinition>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub types: Vec<IdlTypeDefinition>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub errors: Vec<IdlErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub metadata: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ShankInstruction {
+    pub name: String,
+    pub accounts: Vec<ShankAccount>,
+    pub args: Vec<IdlField>,
+    pub discriminant: ShankDiscriminant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShankAccount {
+    pub name: String,
+    pub is_mut: bool,
+    pub is_signer: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub desc: Option<String>,
+    #[serde(skip_serializing_if = "is_false", default, alias = "isOptional")]
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ShankDiscriminant {
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+    pub value: u8,
+}
+
+// Whether the JSON of an IDL is a Shank IDL rather than an Anchor one.
+pub fn is_shank(idl: &JsonValue) -> bool {
+    idl["metadata"]["origin"] == SHANK_ORIGIN
+        && idl["instructions"].as_array().map_or(false, |ixs| {
+            ixs.iter().all(|ix| ix.get("discriminant").is_some())
+        })
+}
+
+// Converts a Shank IDL to an Anchor IDL.
+pub fn import(shank: ShankIdl) -> Result<Idl> {
+    let instructions = shank
+        .instructions
+        .into_iter()
+        .map(|ix| {
+            if ix.discriminant.ty != IdlType::U8 {
+                return Err(anyhow!(
+                    "Unsupported discriminant type of instruction {}",
+                    ix.name
+                ));
+            }
+            let accounts = ix
+                .accounts
+                .into_iter()
+                .map(|acc| {
+                    IdlAccountItem::IdlAccount(Box::new(IdlAccount {
+                        name: acc.name,
+                        is_mut: acc.is_mut,
+                        is_signer: acc.is_signer,
+                        is_optional: acc.optional.then(|| true),
+                        is_init_if_needed: None,
+                        space: None,
+                        docs: acc.desc.map(|desc| vec![desc]),
+                        token: None,
+                        realloc: None,
+                        pda: None,
+                        kind: None,
+                        address: None,
+                        programs: None,
+                        close: None,
+                    }))
+                })
+                .collect();
+            Ok(IdlInstruction {
+                name: ix.name.to_mixed_case(),
+                docs: None,
+                accounts,
+                args: ix.args,
+                returns: None,
+                namespace: None,
+                discriminator: Some(vec![ix.discriminant.value]),
+                guards: vec![],
+                remaining_accounts: None,
+                sunset: None,
+                compute_budget: None,
+                distinct_accounts: vec![],
+                admin: false,
+                batch: vec![],
+                signers: IdlSigners::default(),
+            })
+        })
+        .collect::<Result<_>>()?;
+    let accounts = shank
+        .accounts
+        .into_iter()
+        .map(|acc| IdlTypeDefinition {
+            discriminator: Some(vec![]),
+            ..acc
+        })
+        .collect();
+
+    Ok(Idl {
+        version: shank.version,
+        name: shank.name,
+        docs: None,
+        constants: vec![],
+        instructions,
+        instruction_enum: None,
+        fallback: None,
+        state: None,
+        accounts,
+        types: shank.types,
+        events: None,
+        external_messages: vec![],
+        errors: Some(shank.errors).filter(|errors| !errors.is_empty()),
+        metadata: shank.metadata,
+    })
+}
+
+// Converts an Anchor IDL to a Shank IDL. Fails unless the discriminators of
+// its instructions are a single byte and its accounts have none, as for IDLs
+// imported from Shank.
+pub fn export(idl: &Idl) -> Result<ShankIdl> {
+    let instructions = idl
+        .instructions
+        .iter()
+        .map(|ix| {
+            let value = match ix.discriminator()[..] {
+                [value] => value,
+                _ => {
+                    return Err(anyhow!(
+                        "Instruction {} isn't discriminated by a u8 as in Shank IDLs",
+                        ix.name
+                    ))
+                }
+            };
+            let mut accounts = vec![];
+            flatten_accounts(&ix.accounts, "", &mut accounts);
+            Ok(ShankInstruction {
+                name: ix.name.to_camel_case(),
+                accounts,
+                args: fields(&ix.args),
+                discriminant: ShankDiscriminant {
+                    ty: IdlType::U8,
+                    value,
+                },
+            })
+        })
+        .collect::<Result<_>>()?;
+    let accounts = idl
+        .accounts
+        .iter()
+        .map(|acc| match acc.account_discriminator().is_empty() {
+            true => Ok(type_definition(acc)),
+            false => Err(anyhow!(
+                "Account {} has a discriminator, which Shank IDLs don't describe",
+                acc.name
+            )),
+        })
+        .collect::<Result<_>>()?;
+
+    let mut metadata = idl.metadata.clone().unwrap_or_else(|| json!({}));
+    metadata["origin"] = json!(SHANK_ORIGIN);
+    Ok(ShankIdl {
+        version: idl.version.clone(),
+        name: idl.name.clone(),
+        instructions,
+        accounts,
+        types: idl.types.iter().map(type_definition).collect(),
+        errors: idl.errors.clone().unwrap_or_default(),
+        metadata: Some(metadata),
+    })
+}
+
+fn flatten_accounts(items: &[IdlAccountItem], prefix: &str, accounts: &mut Vec<ShankAccount>) {
+    for item in items {
+        match item {
+            IdlAccountItem::IdlAccount(acc) => accounts.push(ShankAccount {
+                name: match prefix {
+                    "" => acc.name.clone(),
+                    _ => format!("{}_{}", prefix, acc.name).to_mixed_case(),
+                },
+                is_mut: acc.is_mut,
+                is_signer: acc.is_signer,
+                desc: acc.docs.as_ref().map(|docs| docs.join("\n")),
+                optional: acc.is_optional.unwrap_or(false),
+            }),
+            IdlAccountItem::IdlAccounts(accs) => {
+                let prefix = match prefix {
+                    "" => accs.name.clone(),
+                    _ => format!("{}_{}", prefix, accs.name),
+                };
+                flatten_accounts(&accs.accounts, &prefix, accounts)
+            }
+        }
+    }
+}
+
+// The type definition without what Shank doesn't describe.
+fn type_definition(ty_def: &IdlTypeDefinition) -> IdlTypeDefinition {
+    let ty = match &ty_def.ty {
+        IdlTypeDefinitionTy::Struct {
+            fields: struct_fields,
+        } => IdlTypeDefinitionTy::Struct {
+            fields: fields(struct_fields),
+        },
+        IdlTypeDefinitionTy::Enum { variants } => IdlTypeDefinitionTy::Enum {
+            variants: variants
+                .iter()
+                .map(|variant| IdlEnumVariant {
+                    name: variant.name.clone(),
+                    fields: variant
+                        .fields
+                        .as_ref()
+                        .map(|variant_fields| match variant_fields {
+                            EnumFields::Named(named) => EnumFields::Named(fields(named)),
+                            EnumFields::Tuple(tys) => {
+                                EnumFields::Tuple(tys.iter().map(plain_type).collect())
+                            }
+                        }),
+                })
+                .collect(),
+        },
+    };
+    IdlTypeDefinition {
+        name: ty_def.name.clone(),
+        docs: ty_def.docs.clone(),
+        ty,
+        version: None,
+        migrations: vec![],
+        permissions: vec![],
+        discriminator: None,
+        state_machine: None,
+        size: None,
+        checksum: None,
+        serialization: IdlSerialization::Borsh,
+    }
+}
+
+fn fields(fields: &[IdlField]) -> Vec<IdlField> {
+    fields
+        .iter()
+        .map(|f| IdlField {
+            name: f.name.clone(),
+            docs: f.docs.clone(),
+            ty: plain_type(&f.ty),
+            constraints: vec![],
+            aliases: vec![],
+        })
+        .collect()
+}
+
+// The type with annotated floats as plain ones.
+fn plain_type(ty: &IdlType) -> IdlType {
+    match ty {
+        IdlType::Float(float) if float.bits == 32 => IdlType::F32,
+        IdlType::Float(_) => IdlType::F64,
+        IdlType::Option(ty) => IdlType::Option(Box::new(plain_type(ty))),
+        IdlType::Vec(ty) => IdlType::Vec(Box::new(plain_type(ty))),
+        IdlType::Array(ty, len) => IdlType::Array(Box::new(plain_type(ty)), *len),
+        _ => ty.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shank_round_trip() {
+        let shank_json = json!({
+            "version": "0.1.0",
+            "name": "token_vault",
+            "instructions": [
+                {
+                    "name": "InitVault",
+                    "accounts": [
+                        { "name": "vault", "isMut": true, "isSigner": false, "desc": "The vault" },
+                        { "name": "authority", "isMut": false, "isSigner": true, "optional": true }
+                    ],
+                    "args": [{ "name": "initVaultArgs", "type": { "defined": "InitVaultArgs" } }],
+                    "discriminant": { "type": "u8", "value": 0 }
+                },
+                {
+                    "name": "Withdraw",
+                    "accounts": [],
+                    "args": [],
+                    "discriminant": { "type": "u8", "value": 1 }
+                }
+            ],
+            "accounts": [{
+                "name": "Vault",
+                "type": {
+                    "kind": "struct",
+                    "fields": [{ "name": "authority", "type": "publicKey" }]
+                }
+            }],
+            "types": [{
+                "name": "InitVaultArgs",
+                "type": { "kind": "struct", "fields": [{ "name": "amount", "type": "u64" }] }
+            }],
+            "errors": [{ "code": 0, "name": "InvalidAuthority", "msg": "Invalid authority" }],
+            "metadata": {
+                "origin": "shank",
+                "address": "vau1zxA2LbssAUEF7Gpw91zMM1LvXrvpzJtmZ58rPsn"
+            }
+        });
+        assert!(is_shank(&shank_json));
+        let shank: ShankIdl = serde_json::from_value(shank_json.clone()).unwrap();
+
+        let idl = import(shank.clone()).unwrap();
+        assert!(!is_shank(&serde_json::to_value(&idl).unwrap()));
+        assert_eq!(idl.instructions[1].name, "withdraw");
+        assert_eq!(idl.instructions[1].discriminator(), vec![1]);
+        assert!(idl.accounts[0].account_discriminator().is_empty());
+        assert_eq!(
+            idl.instructions[0].accounts[0],
+            IdlAccountItem::IdlAccount(Box::new(IdlAccount {
+                name: "vault".to_string(),
+                is_mut: true,
+                is_signer: false,
+                is_optional: None,
+                is_init_if_needed: None,
+                space: None,
+                docs: Some(vec!["The vault".to_string()]),
+                token: None,
+                realloc: None,
+                pda: None,
+                kind: None,
+                address: None,
+                programs: None,
+                close: None,
+            }))
+        );
+
+        assert_eq!(export(&idl).unwrap(), shank);
+
+        let anchor_idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "counter",
+            "instructions": [{ "name": "increment", "accounts": [], "args": [] }]
+        }))
+        .unwrap();
+        assert!(export(&anchor_idl).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use crate::idl::{Idl, IdlStateMachine, IdlTransition};
+use crate::parser;
+use anyhow::Result;
+use heck::MixedCase;
+
+// Lifecycles of accounts, given by `#[state_machine(..)]`, and their rendering
+// as Mermaid state diagrams for the program's docs.
+
+// The state machine of an `#[account]` struct, if any.
+pub(crate) fn state_machine(attrs: &[syn::Attribute]) -> Result<Option<IdlStateMachine>> {
+    let machine = match parser::state_machine::state_machine(attrs)? {
+        Some(machine) => machine,
+        None => return Ok(None),
+    };
+    Ok(Some(IdlStateMachine {
+        field: machine.field.to_string().to_mixed_case(),
+        transitions: machine
+            .transitions
+            .iter()
+            .map(|t| IdlTransition {
+                from: t.from.to_string(),
+                to: t.to.to_string(),
+            })
+            .collect(),
+    }))
+}
+
+// A `stateDiagram-v2` per account of the IDL with a state machine, titled
+// with the name of the account. The first state of the machine is the
+// initial one.
+pub fn mermaid(idl: &Idl) -> String {
+    idl.accounts
+        .iter()
+        .filter_map(|acc| acc.state_machine.as_ref().map(|m| (&acc.name, m)))
+        .map(|(name, machine)| {
+            let mut diagram = format!("---\ntitle: {}\n---\nstateDiagram-v2\n", name);
+            if let Some(initial) = machine.transitions.first() {
+                diagram.push_str(&format!("    [*] --> {}\n", initial.from));
+            }
+            for t in &machine.transitions {
+                diagram.push_str(&format!("    {} --> {}\n", t.from, t.to));
+            }
+            diagram
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn state_machine_diagram() {
+        let strct: syn::ItemStruct = syn::parse_quote! {
+            #[state_machine(field = order_status, transitions(Open -> Filled, Open -> Canceled))]
+            pub struct Order {
+                pub order_status: OrderStatus,
+            }
+        };
+        let machine = state_machine(&strct.attrs).unwrap().unwrap();
+        assert_eq!(machine.field, "orderStatus");
+
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "orders",
+            "instructions": [],
+            "accounts": [
+                {
+                    "name": "Order",
+                    "type": { "kind": "struct", "fields": [] },
+                    "stateMachine": serde_json::to_value(&machine).unwrap()
+                },
+                { "name": "Market", "type": { "kind": "struct", "fields": [] } }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            mermaid(&idl),
+            "---\ntitle: Order\n---\nstateDiagram-v2\n    [*] --> Open\n    Open --> Filled\n    Open --> Canceled\n"
+        );
+    }
+}
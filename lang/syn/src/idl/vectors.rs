@@ -0,0 +1,358 @@
+use crate::codegen::program::common::sighash;
+use crate::idl::*;
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map};
+
+// Encode/decode test vectors, for clients to check that they serialize the
+// program's instructions, accounts and events byte for byte like the program.
+//
+// Each vector is a value of the instruction's args, or of the account or
+// event, decoded the same way as described by `Idl::json_schema`, along with
+// its borsh encoding prefixed with the discriminator, as a hex string. Values
+// are generated from a seed, so that the same vectors are generated for the
+// same IDL and seed.
+
+// The seed used by `anchor build` and when none is given.
+pub const DEFAULT_SEED: u64 = 0;
+
+// The number of vectors generated per instruction, account and event.
+const VECTORS_PER_ITEM: usize = 3;
+
+// The nesting of user defined types after which options are `None` and vecs
+// are empty, to bound the values of recursive types.
+const MAX_DEPTH: usize = 4;
+
+// The maximum length of generated vecs, strings and bytes.
+const MAX_LEN: u64 = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestVectors {
+    pub seed: u64,
+    pub instructions: Vec<TestVector>,
+    pub accounts: Vec<TestVector>,
+    pub events: Vec<TestVector>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestVector {
+    pub name: String,
+    pub value: JsonValue,
+    pub data: String,
+}
+
+impl Idl {
+    // Generates the test vectors of the program's instructions, accounts and
+    // events from `seed`. Instructions of the state are left out.
+    pub fn test_vectors(&self, seed: u64) -> Result<TestVectors> {
+        let mut gen = Generator {
+            idl: self,
+            rng: SplitMix64(seed),
+        };
+        let mut instructions = vec![];
+        for ix in &self.instructions {
+            let discriminator = ix.discriminator();
+            let fields: Vec<_> = ix.args.iter().map(|f| (&f.name, &f.ty)).collect();
+            for _ in 0..VECTORS_PER_ITEM {
+                instructions.push(gen.vector(&ix.name, &discriminator, &fields, 0)?);
+            }
+        }
+        let mut accounts = vec![];
+        for acc in &self.accounts {
+            let fields = match &acc.ty {
+                IdlTypeDefinitionTy::Struct { fields } => fields,
+                IdlTypeDefinitionTy::Enum { .. } => continue,
+            };
+            let discriminator = acc.account_discriminator();
+            let fields: Vec<_> = fields.iter().map(|f| (&f.name, &f.ty)).collect();
+            for _ in 0..VECTORS_PER_ITEM {
+                accounts.push(gen.vector(&acc.name, &discriminator, &fields, 1)?);
+            }
+        }
+        let mut events = vec![];
+        for event in self.events.iter().flatten() {
+            let discriminator = sighash("event", &event.name);
+            let fields: Vec<_> = event.fields.iter().map(|f| (&f.name, &f.ty)).collect();
+            for _ in 0..VECTORS_PER_ITEM {
+                events.push(gen.vector(&event.name, &discriminator, &fields, 1)?);
+            }
+        }
+        Ok(TestVectors {
+            seed,
+            instructions,
+            accounts,
+            events,
+        })
+    }
+}
+
+struct Generator<'a> {
+    idl: &'a Idl,
+    rng: SplitMix64,
+}
+
+impl Generator<'_> {
+    fn vector(
+        &mut self,
+        name: &str,
+        discriminator: &[u8],
+        fields: &[(&String, &IdlType)],
+        depth: usize,
+    ) -> Result<TestVector> {
+        let mut data = discriminator.to_vec();
+        let value = self.fields(fields, depth, &mut data)?;
+        Ok(TestVector {
+            name: name.to_string(),
+            value,
+            data: data.iter().map(|b| format!("{:02x}", b)).collect(),
+        })
+    }
+
+    fn fields(
+        &mut self,
+        fields: &[(&String, &IdlType)],
+        depth: usize,
+        data: &mut Vec<u8>,
+    ) -> Result<JsonValue> {
+        let mut value = Map::new();
+        for (name, ty) in fields {
+            value.insert(name.to_string(), self.ty(ty, depth, data)?);
+        }
+        Ok(JsonValue::Object(value))
+    }
+
+    // Generates a value of `ty`, appending its encoding to `data`.
+    fn ty(&mut self, ty: &IdlType, depth: usize, data: &mut Vec<u8>) -> Result<JsonValue> {
+        let value = match ty {
+            IdlType::Bool => {
+                let b = self.rng.next() % 2 == 1;
+                data.push(b as u8);
+                json!(b)
+            }
+            IdlType::U8 => self.int(1, data, |n| json!(n as u8)),
+            IdlType::I8 => self.int(1, data, |n| json!(n as u8 as i8)),
+            IdlType::U16 => self.int(2, data, |n| json!(n as u16)),
+            IdlType::I16 => self.int(2, data, |n| json!(n as u16 as i16)),
+            IdlType::U32 => self.int(4, data, |n| json!(n as u32)),
+            IdlType::I32 => self.int(4, data, |n| json!(n as u32 as i32)),
+            IdlType::U64 => self.int(8, data, |n| json!(n as u64)),
+            IdlType::I64 => self.int(8, data, |n| json!(n as u64 as i64)),
+            IdlType::U128 => self.int(16, data, |n| json!(n.to_string())),
+            IdlType::I128 => self.int(16, data, |n| json!((n as i128).to_string())),
+            IdlType::F32 => self.float(32, data),
+            IdlType::F64 => self.float(64, data),
+            IdlType::Float(float) => self.float(float.bits, data),
+            IdlType::Bytes => {
+                let len = self.len(depth);
+                let bytes = self.bytes(len as usize);
+                data.extend((bytes.len() as u32).to_le_bytes());
+                data.extend(&bytes);
+                json!(bytes)
+            }
+            IdlType::String => {
+                const CHARS: &[u8] =
+                    b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+                let s: String = (0..self.len(depth))
+                    .map(|_| CHARS[(self.rng.next() % CHARS.len() as u64) as usize] as char)
+                    .collect();
+                data.extend((s.len() as u32).to_le_bytes());
+                data.extend(s.as_bytes());
+                json!(s)
+            }
+            IdlType::PublicKey => {
+                let key = self.bytes(32);
+                data.extend(&key);
+                json!(bs58::encode(key).into_string())
+            }
+            IdlType::Defined(name) => self.defined(name, depth + 1, data)?,
+            IdlType::Option(ty) => {
+                if depth >= MAX_DEPTH || self.rng.next() % 2 == 0 {
+                    data.push(0);
+                    JsonValue::Null
+                } else {
+                    data.push(1);
+                    self.ty(ty, depth, data)?
+                }
+            }
+            IdlType::Vec(ty) => {
+                let len = self.len(depth);
+                data.extend((len as u32).to_le_bytes());
+                let items = (0..len)
+                    .map(|_| self.ty(ty, depth, data))
+                    .collect::<Result<Vec<_>>>()?;
+                JsonValue::Array(items)
+            }
+            IdlType::Array(ty, len) => {
+                let items = (0..*len)
+                    .map(|_| self.ty(ty, depth, data))
+                    .collect::<Result<Vec<_>>>()?;
+                JsonValue::Array(items)
+            }
+        };
+        Ok(value)
+    }
+
+    fn defined(&mut self, name: &str, depth: usize, data: &mut Vec<u8>) -> Result<JsonValue> {
+        let idl = self.idl;
+        let ty_def = idl
+            .types
+            .iter()
+            .chain(idl.accounts.iter())
+            .find(|ty_def| ty_def.name == name)
+            .ok_or_else(|| anyhow!("Type {} is not defined in the IDL", name))?;
+        match &ty_def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                let fields: Vec<_> = fields.iter().map(|f| (&f.name, &f.ty)).collect();
+                self.fields(&fields, depth, data)
+            }
+            IdlTypeDefinitionTy::Enum { variants } => {
+                if variants.is_empty() {
+                    return Err(anyhow!("Enum {} has no variants", name));
+                }
+                let index = (self.rng.next() % variants.len() as u64) as usize;
+                let variant = &variants[index];
+                data.push(index as u8);
+                let fields = match &variant.fields {
+                    None => json!({}),
+                    Some(EnumFields::Named(fields)) => {
+                        let fields: Vec<_> = fields.iter().map(|f| (&f.name, &f.ty)).collect();
+                        self.fields(&fields, depth, data)?
+                    }
+                    Some(EnumFields::Tuple(tys)) => JsonValue::Array(
+                        tys.iter()
+                            .map(|ty| self.ty(ty, depth, data))
+                            .collect::<Result<_>>()?,
+                    ),
+                };
+                Ok(json!({ variant.name.clone(): fields }))
+            }
+        }
+    }
+
+    // Generates an integer of `size` bytes, given as a `u128` to `to_json`.
+    fn int(
+        &mut self,
+        size: usize,
+        data: &mut Vec<u8>,
+        to_json: impl Fn(u128) -> JsonValue,
+    ) -> JsonValue {
+        let n = (self.rng.next() as u128) << 64 | self.rng.next() as u128;
+        data.extend(&n.to_le_bytes()[..size]);
+        to_json(n)
+    }
+
+    // Floats are small multiples of 1/8, which every client represents
+    // exactly, rather than arbitrary bits, e.g. NaNs.
+    fn float(&mut self, bits: u8, data: &mut Vec<u8>) -> JsonValue {
+        let n = (self.rng.next() % 2_000_001) as f64 - 1_000_000.0;
+        let float = n / 8.0;
+        match bits {
+            32 => data.extend((float as f32).to_le_bytes()),
+            _ => data.extend(float.to_le_bytes()),
+        }
+        json!(float)
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.rng.next() as u8).collect()
+    }
+
+    fn len(&mut self, depth: usize) -> u64 {
+        match depth >= MAX_DEPTH {
+            true => 0,
+            false => self.rng.next() % (MAX_LEN + 1),
+        }
+    }
+}
+
+// The SplitMix64 generator, which is simple enough for clients to reproduce
+// the values when needed.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::program::common::SIGHASH_GLOBAL_NAMESPACE;
+
+    #[test]
+    fn test_vectors() {
+        let idl: Idl = serde_json::from_value(json!({
+            "version": "0.1.0",
+            "name": "counter",
+            "instructions": [{
+                "name": "incrementBy",
+                "accounts": [],
+                "args": [{ "name": "by", "type": "u64" }]
+            }],
+            "accounts": [{
+                "name": "Counter",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "authority", "type": "publicKey" },
+                        { "name": "count", "type": "u128" },
+                        { "name": "mode", "type": { "defined": "Mode" } },
+                        { "name": "label", "type": "string" }
+                    ]
+                }
+            }],
+            "types": [{
+                "name": "Mode",
+                "type": {
+                    "kind": "enum",
+                    "variants": [{ "name": "Fast" }, { "name": "Slow", "fields": ["u8"] }]
+                }
+            }]
+        }))
+        .unwrap();
+        let vectors = idl.test_vectors(DEFAULT_SEED).unwrap();
+
+        assert_eq!(vectors, idl.test_vectors(DEFAULT_SEED).unwrap());
+        assert_ne!(vectors, idl.test_vectors(1).unwrap());
+        assert_eq!(vectors.instructions.len(), VECTORS_PER_ITEM);
+        assert!(vectors.events.is_empty());
+
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+        let ix = &vectors.instructions[0];
+        let by = ix.value["by"].as_u64().unwrap();
+        let discriminator = sighash(SIGHASH_GLOBAL_NAMESPACE, "increment_by");
+        assert_eq!(ix.data, hex(&discriminator) + &hex(&by.to_le_bytes()));
+
+        for account in &vectors.accounts {
+            let value = &account.value;
+            let mut data = sighash("account", "Counter").to_vec();
+            data.extend(
+                bs58::decode(value["authority"].as_str().unwrap())
+                    .into_vec()
+                    .unwrap(),
+            );
+            let count: u128 = value["count"].as_str().unwrap().parse().unwrap();
+            data.extend(count.to_le_bytes());
+            match value["mode"].as_object().unwrap().iter().next().unwrap() {
+                (name, fields) if name == "Fast" => {
+                    assert_eq!(fields, &json!({}));
+                    data.push(0);
+                }
+                (_, fields) => data.extend([1, fields[0].as_u64().unwrap() as u8]),
+            }
+            let label = value["label"].as_str().unwrap();
+            data.extend((label.len() as u32).to_le_bytes());
+            data.extend(label.as_bytes());
+            assert_eq!(account.data, hex(&data));
+        }
+    }
+}
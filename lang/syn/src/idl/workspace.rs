@@ -0,0 +1,190 @@
+use crate::idl::file::{self, ExternalAccounts};
+use crate::idl::{DocsPolicy, FloatPolicy, Idl};
+use anyhow::{anyhow, Context, Result};
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Options of `parse_workspace`, applied to all programs.
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceOptions {
+    pub seeds_feature: bool,
+    pub docs_policy: DocsPolicy,
+    pub safety_checks: bool,
+    pub float_policy: FloatPolicy,
+    pub flatten_accounts: bool,
+}
+
+// The subset of `cargo metadata` used to discover programs.
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+    version: String,
+    targets: Vec<Target>,
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Target {
+    name: String,
+    kind: Vec<String>,
+    src_path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct Dependency {
+    path: Option<PathBuf>,
+}
+
+// A program crate of the workspace, i.e. with a `cdylib` target.
+#[derive(Debug, PartialEq)]
+struct WorkspaceProgram {
+    lib_name: String,
+    version: String,
+    src_path: PathBuf,
+}
+
+// Parses the IDLs of all programs of the Cargo workspace at `root`, by the
+// name of their library, in parallel.
+//
+// Composite accounts defined in crates the programs depend on by path, e.g. a
+// shared CPI crate, resolve as with `ExternalAccounts`. These crates are only
+// parsed once per thread, however many programs depend on them.
+pub fn parse_workspace(root: &Path, opts: &WorkspaceOptions) -> Result<HashMap<String, Idl>> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .current_dir(root)
+        .output()
+        .context("Unable to run cargo metadata")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    let metadata: Metadata = serde_json::from_slice(&output.stdout)?;
+    let (programs, shared) = workspace_programs(&metadata);
+
+    programs
+        .par_iter()
+        .map_init(
+            || {
+                // Syntax trees can't be sent across threads, so each thread
+                // has its own context.
+                let mut external_accounts = ExternalAccounts::default();
+                for lib in &shared {
+                    // Crates that can't be parsed simply aren't available for
+                    // resolution.
+                    let _ = external_accounts.register_source(lib);
+                }
+                external_accounts
+            },
+            |external_accounts, program| {
+                let options = file::ParseOptions {
+                    version: program.version.clone(),
+                    seeds_feature: opts.seeds_feature,
+                    docs_policy: opts.docs_policy.clone(),
+                    safety_checks: opts.safety_checks,
+                    float_policy: opts.float_policy,
+                    flatten_accounts: opts.flatten_accounts,
+                };
+                let idl = file::parse(&program.src_path, &options, external_accounts)
+                    .with_context(|| format!("Unable to parse the IDL of {}", program.lib_name))?;
+                Ok(idl.map(|output| (program.lib_name.clone(), output.idl)))
+            },
+        )
+        .filter_map(Result::transpose)
+        .collect()
+}
+
+// The programs of the workspace, and the library roots of the crates they
+// depend on by path.
+fn workspace_programs(metadata: &Metadata) -> (Vec<WorkspaceProgram>, BTreeSet<PathBuf>) {
+    let mut programs = Vec::new();
+    let mut shared = BTreeSet::new();
+    for package in &metadata.packages {
+        let target = match package
+            .targets
+            .iter()
+            .find(|target| target.kind.iter().any(|kind| kind == "cdylib"))
+        {
+            Some(target) => target,
+            None => continue,
+        };
+        programs.push(WorkspaceProgram {
+            lib_name: target.name.clone(),
+            version: package.version.clone(),
+            src_path: target.src_path.clone(),
+        });
+        shared.extend(
+            package
+                .dependencies
+                .iter()
+                .filter_map(|dep| dep.path.as_ref())
+                .map(|path| path.join("src/lib.rs"))
+                .filter(|lib| lib.exists()),
+        );
+    }
+    (programs, shared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discover_programs() {
+        let dir = std::env::temp_dir().join("anchor_syn_workspace");
+        std::fs::create_dir_all(dir.join("shared/src")).unwrap();
+        std::fs::write(dir.join("shared/src/lib.rs"), "").unwrap();
+        let metadata: Metadata = serde_json::from_value(serde_json::json!({
+            "packages": [
+                {
+                    "name": "vault",
+                    "version": "0.1.0",
+                    "targets": [{
+                        "name": "vault",
+                        "kind": ["cdylib", "lib"],
+                        "src_path": "/ws/programs/vault/src/lib.rs"
+                    }],
+                    "dependencies": [
+                        { "name": "anchor-lang" },
+                        { "name": "shared", "path": dir.join("shared") },
+                        { "name": "missing", "path": dir.join("missing") }
+                    ]
+                },
+                {
+                    "name": "shared",
+                    "version": "0.2.0",
+                    "targets": [{
+                        "name": "shared",
+                        "kind": ["lib"],
+                        "src_path": dir.join("shared/src/lib.rs")
+                    }],
+                    "dependencies": []
+                }
+            ]
+        }))
+        .unwrap();
+
+        let (programs, shared) = workspace_programs(&metadata);
+        assert_eq!(
+            programs,
+            vec![WorkspaceProgram {
+                lib_name: "vault".to_string(),
+                version: "0.1.0".to_string(),
+                src_path: PathBuf::from("/ws/programs/vault/src/lib.rs"),
+            }]
+        );
+        assert_eq!(
+            shared.into_iter().collect::<Vec<_>>(),
+            vec![dir.join("shared/src/lib.rs")]
+        );
+    }
+}
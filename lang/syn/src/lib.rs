@@ -34,6 +34,10 @@ pub struct Program {
     pub docs: Option<Vec<String>>,
     pub program_mod: ItemMod,
     pub fallback_fn: Option<FallbackFn>,
+    pub args: Option<ProgramArgs>,
+    // Number of bytes of the sighash the instruction data starts with, given
+    // by `#[program(discriminator_len = ..)]`.
+    pub discriminator_len: usize,
 }
 
 impl Parse for Program {
@@ -90,12 +94,29 @@ pub struct Ix {
     pub returns: IxReturn,
     // The ident for the struct deriving Accounts.
     pub anchor_ident: Ident,
+    // Namespace of the instruction's sighash, given by
+    // `#[instruction(namespace = "..")]`.
+    pub namespace: Option<String>,
+}
+
+impl Ix {
+    // The namespace the instruction's name is hashed under, `global` unless
+    // given.
+    pub fn sighash_namespace(&self) -> &str {
+        self.namespace
+            .as_deref()
+            .unwrap_or(codegen::program::common::SIGHASH_GLOBAL_NAMESPACE)
+    }
 }
 
 #[derive(Debug)]
 pub struct IxArg {
     pub name: Ident,
     pub docs: Option<Vec<String>>,
+    // Constraints given by `#[arg(..)]`, stripped from `raw_arg`.
+    pub constraints: Vec<parser::arg_constraints::ArgConstraint>,
+    // Former names given by `#[idl_name(..)]`, stripped from `raw_arg`.
+    pub aliases: Vec<String>,
     pub raw_arg: PatType,
 }
 
@@ -119,6 +140,18 @@ pub struct AccountsStruct {
     pub fields: Vec<AccountField>,
     // Instruction data api expression.
     instruction_api: Option<Punctuated<Expr, Comma>>,
+    // Type of the remaining accounts, given by `#[remaining_accounts(..)]`.
+    pub remaining_accounts: Option<RemainingAccounts>,
+    // Pairs of accounts that must have different keys, given by
+    // `#[distinct_mut]`.
+    pub distinct_mut: Vec<(Ident, Ident)>,
+}
+
+// `#[remaining_accounts(ty = TokenAccount, mutable)]`.
+#[derive(Debug)]
+pub struct RemainingAccounts {
+    pub ty: Type,
+    pub mutable: bool,
 }
 
 impl Parse for AccountsStruct {
@@ -153,6 +186,8 @@ impl AccountsStruct {
             generics,
             fields,
             instruction_api,
+            remaining_accounts: None,
+            distinct_mut: Vec::new(),
         }
     }
 
@@ -203,6 +238,14 @@ impl AccountField {
                 Ty::ProgramAccount(account) => {
                     Some(parser::tts_to_string(&account.account_type_path))
                 }
+                Ty::AccountLoader(account) => {
+                    Some(parser::tts_to_string(&account.account_type_path))
+                }
+                Ty::Loader(account) => Some(parser::tts_to_string(&account.account_type_path)),
+                Ty::LazyAccount(account) => Some(parser::tts_to_string(&account.account_type_path)),
+                Ty::InterfaceAccount(account) => {
+                    Some(parser::tts_to_string(&account.account_type_path))
+                }
                 _ => None,
             },
             AccountField::CompositeField(field) => Some(field.symbol.clone()),
@@ -217,6 +260,11 @@ pub struct Field {
     pub ty: Ty,
     /// IDL Doc comment
     pub docs: Option<Vec<String>>,
+    /// Whether the account is an `Option`, omitted by passing the program id.
+    pub is_optional: bool,
+    /// The field of the accounts fragment embedded into the struct, and the
+    /// name of the account in the fragment, for accounts of fragments.
+    pub fragment: Option<(String, String)>,
 }
 
 impl Field {
@@ -229,6 +277,17 @@ impl Field {
     }
 
     pub fn ty_decl(&self) -> proc_macro2::TokenStream {
+        let ty_decl = self.account_ty_decl();
+        match self.is_optional {
+            true => quote! {
+                Option<#ty_decl>
+            },
+            false => ty_decl,
+        }
+    }
+
+    // The type of the account, without the `Option` of optional accounts.
+    fn account_ty_decl(&self) -> proc_macro2::TokenStream {
         let account_ty = self.account_ty();
         let container_ty = self.container_ty();
         match &self.ty {
@@ -342,6 +401,26 @@ impl Field {
                     }
                 }
             }
+            Ty::LazyAccount(_) | Ty::Interface(_) => quote! {
+                #container_ty::try_from(
+                    &#field,
+                ).map_err(|e| e.with_account_name(#field_str))?
+            },
+            Ty::InterfaceAccount(_) => {
+                if checked {
+                    quote! {
+                        #container_ty::try_from(
+                            &#field,
+                        ).map_err(|e| e.with_account_name(#field_str))?
+                    }
+                } else {
+                    quote! {
+                        #container_ty::try_from_unchecked(
+                            &#field,
+                        ).map_err(|e| e.with_account_name(#field_str))?
+                    }
+                }
+            }
             Ty::AccountLoader(_) => {
                 if checked {
                     quote! {
@@ -389,6 +468,15 @@ impl Field {
             Ty::AccountLoader(_) => quote! {
                 anchor_lang::accounts::account_loader::AccountLoader
             },
+            Ty::LazyAccount(_) => quote! {
+                anchor_lang::accounts::lazy_account::LazyAccount
+            },
+            Ty::InterfaceAccount(_) => quote! {
+                anchor_lang::accounts::interface_account::InterfaceAccount
+            },
+            Ty::Interface(_) => quote! {
+                anchor_lang::accounts::interface::Interface
+            },
             Ty::Loader(_) => quote! {
                 anchor_lang::accounts::loader::Loader
             },
@@ -443,6 +531,24 @@ impl Field {
                     #ident
                 }
             }
+            Ty::LazyAccount(ty) => {
+                let ident = &ty.account_type_path;
+                quote! {
+                    #ident
+                }
+            }
+            Ty::InterfaceAccount(ty) => {
+                let ident = &ty.account_type_path;
+                quote! {
+                    #ident
+                }
+            }
+            Ty::Interface(ty) => {
+                let interface = &ty.account_type_path;
+                quote! {
+                    #interface
+                }
+            }
             Ty::Loader(ty) => {
                 let ident = &ty.account_type_path;
                 quote! {
@@ -512,7 +618,10 @@ pub enum Ty {
     CpiAccount(CpiAccountTy),
     Sysvar(SysvarTy),
     Account(AccountTy),
+    LazyAccount(LazyAccountTy),
+    InterfaceAccount(InterfaceAccountTy),
     Program(ProgramTy),
+    Interface(InterfaceTy),
     Signer,
     SystemAccount,
     ProgramData,
@@ -560,6 +669,24 @@ pub struct AccountLoaderTy {
     pub account_type_path: TypePath,
 }
 
+#[derive(Debug, PartialEq)]
+pub struct LazyAccountTy {
+    // The struct type of the account.
+    pub account_type_path: TypePath,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InterfaceAccountTy {
+    // The struct type of the account.
+    pub account_type_path: TypePath,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InterfaceTy {
+    // The type implementing `Ids`, e.g. `TokenInterface`.
+    pub account_type_path: TypePath,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct LoaderTy {
     // The struct type of the account.
@@ -608,6 +735,56 @@ impl Parse for ErrorArgs {
     }
 }
 
+// Arguments to the `#[program]` attribute.
+#[derive(Debug, Default)]
+pub struct ProgramArgs {
+    // Path to a `macro_rules!` macro every instruction data type generated by
+    // `#[program]` is passed through, e.g. to add derives or impls for
+    // off-chain use.
+    pub prelude: Option<syn::Path>,
+    // True if the arithmetic of the handlers is rewritten into checked
+    // operations, failing the instruction on overflow.
+    pub checked_math: bool,
+    // Number of bytes of the sighash the instruction data starts with, from 1
+    // to 8, given by `discriminator_len = ..`, and of the discriminators of
+    // the accounts of the crate.
+    pub discriminator_len: Option<usize>,
+}
+
+impl Parse for ProgramArgs {
+    fn parse(stream: ParseStream) -> ParseResult<Self> {
+        let mut args = ProgramArgs::default();
+        while !stream.is_empty() {
+            let arg = stream.call(Ident::parse_any)?;
+            match arg.to_string().as_str() {
+                "prelude" if args.prelude.is_none() => {
+                    stream.parse::<Token![=]>()?;
+                    args.prelude = Some(stream.parse()?);
+                }
+                "checked_math" if !args.checked_math => args.checked_math = true,
+                "discriminator_len" if args.discriminator_len.is_none() => {
+                    stream.parse::<Token![=]>()?;
+                    args.discriminator_len =
+                        Some(parser::discriminator::parse_len(&stream.parse()?)?);
+                }
+                "prelude" | "checked_math" | "discriminator_len" => {
+                    return Err(ParseError::new(arg.span(), "duplicate program argument"))
+                }
+                _ => {
+                    return Err(ParseError::new(
+                        arg.span(),
+                        "expected keyword prelude, checked_math or discriminator_len",
+                    ))
+                }
+            }
+            if !stream.is_empty() {
+                stream.parse::<Token![,]>()?;
+            }
+        }
+        Ok(args)
+    }
+}
+
 #[derive(Debug)]
 pub struct ErrorCode {
     pub id: u32,
@@ -636,6 +813,8 @@ pub struct ConstraintGroup {
     token_account: Option<ConstraintTokenAccountGroup>,
     mint: Option<ConstraintTokenMintGroup>,
     realloc: Option<ConstraintReallocGroup>,
+    state_machine: Option<ConstraintStateMachineGroup>,
+    metadata: Option<ConstraintMetadataGroup>,
 }
 
 impl ConstraintGroup {
@@ -654,6 +833,10 @@ impl ConstraintGroup {
     pub fn is_close(&self) -> bool {
         self.close.is_some()
     }
+
+    pub fn is_init_if_needed(&self) -> bool {
+        self.init.as_ref().map_or(false, |init| init.if_needed)
+    }
 }
 
 // A single account constraint *after* merging all tokens into a well formed
@@ -680,6 +863,8 @@ pub enum Constraint {
     TokenAccount(ConstraintTokenAccountGroup),
     Mint(ConstraintTokenMintGroup),
     Realloc(ConstraintReallocGroup),
+    StateMachine(ConstraintStateMachineGroup),
+    Metadata(ConstraintMetadataGroup),
 }
 
 // Constraint token is a single keyword in a `#[account(<TOKEN>)]` attribute.
@@ -709,11 +894,17 @@ pub enum ConstraintToken {
     MintAuthority(Context<ConstraintMintAuthority>),
     MintFreezeAuthority(Context<ConstraintMintFreezeAuthority>),
     MintDecimals(Context<ConstraintMintDecimals>),
+    MetadataMint(Context<ConstraintMetadataMint>),
+    MetadataName(Context<ConstraintMetadataName>),
+    MetadataSymbol(Context<ConstraintMetadataSymbol>),
+    MetadataUpdateAuthority(Context<ConstraintMetadataUpdateAuthority>),
     Bump(Context<ConstraintTokenBump>),
     ProgramSeed(Context<ConstraintProgramSeed>),
     Realloc(Context<ConstraintRealloc>),
     ReallocPayer(Context<ConstraintReallocPayer>),
     ReallocZero(Context<ConstraintReallocZero>),
+    InState(Context<ConstraintInState>),
+    TransitionTo(Context<ConstraintTransitionTo>),
 }
 
 impl Parse for ConstraintToken {
@@ -760,6 +951,22 @@ pub struct ConstraintReallocZero {
     pub zero: Expr,
 }
 
+#[derive(Debug, Clone)]
+pub struct ConstraintStateMachineGroup {
+    pub states: Vec<Ident>,
+    pub transition_to: Option<Ident>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstraintInState {
+    pub states: Vec<Ident>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstraintTransitionTo {
+    pub state: Ident,
+}
+
 #[derive(Debug, Clone)]
 pub struct ConstraintSigner {
     pub error: Option<Expr>,
@@ -893,6 +1100,26 @@ pub struct ConstraintMintDecimals {
     decimals: Expr,
 }
 
+#[derive(Debug, Clone)]
+pub struct ConstraintMetadataMint {
+    mint: Expr,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstraintMetadataName {
+    name: Expr,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstraintMetadataSymbol {
+    symbol: Expr,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstraintMetadataUpdateAuthority {
+    update_authority: Expr,
+}
+
 #[derive(Debug, Clone)]
 pub struct ConstraintTokenBump {
     bump: Option<Expr>,
@@ -922,6 +1149,16 @@ pub struct ConstraintTokenMintGroup {
     pub freeze_authority: Option<Expr>,
 }
 
+// The Metaplex metadata account of a mint, given by the `metadata::*`
+// constraints.
+#[derive(Debug, Clone)]
+pub struct ConstraintMetadataGroup {
+    pub mint: Option<Expr>,
+    pub name: Option<Expr>,
+    pub symbol: Option<Expr>,
+    pub update_authority: Option<Expr>,
+}
+
 // Syntaxt context object for preserving metadata about the inner item.
 #[derive(Debug, Clone)]
 pub struct Context<T> {
@@ -0,0 +1,89 @@
+use syn::parse::ParseStream;
+use syn::{Expr, Token};
+
+// Preconditions of an instruction given by `#[access_control(..)]`, the calls
+// run before its handler, e.g. `#[access_control(is_admin(&ctx))]`.
+
+pub struct Guard {
+    // Path of the called function, or the whole expression if it isn't a
+    // function call, e.g. a method call.
+    pub name: String,
+    // Arguments of the call, as written.
+    pub args: Vec<String>,
+}
+
+// Parses the guards of all the `#[access_control(..)]` attributes of a
+// handler, in the order they run.
+pub fn guards(attrs: &[syn::Attribute]) -> syn::Result<Vec<Guard>> {
+    let mut guards = vec![];
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("access_control"))
+    {
+        let exprs = attr.parse_args_with(|input: ParseStream| {
+            let mut exprs = vec![];
+            while !input.is_empty() {
+                exprs.push(input.parse::<Expr>()?);
+                if input.peek(Token![,]) {
+                    input.parse::<Token![,]>()?;
+                }
+            }
+            Ok(exprs)
+        })?;
+        guards.extend(exprs.iter().map(guard));
+    }
+    Ok(guards)
+}
+
+fn guard(expr: &Expr) -> Guard {
+    match expr {
+        Expr::Call(call) if matches!(*call.func, Expr::Path(_)) => Guard {
+            name: compact(&call.func),
+            args: call.args.iter().map(compact).collect(),
+        },
+        expr => Guard {
+            name: compact(expr),
+            args: vec![],
+        },
+    }
+}
+
+// The tokens of an expression without the spaces added between them, e.g.
+// `&ctx` rather than `& ctx`.
+fn compact(expr: &Expr) -> String {
+    super::tts_to_string(expr)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_guards() {
+        let handler: syn::ItemFn = syn::parse_str(
+            "#[access_control(is_admin(&ctx) Create::accounts(&ctx, bump))]
+            #[access_control(ctx.accounts.validate())]
+            pub fn create(ctx: Context<Create>, bump: u8) -> Result<()> { Ok(()) }",
+        )
+        .unwrap();
+        let guards: Vec<(String, Vec<String>)> = guards(&handler.attrs)
+            .unwrap()
+            .into_iter()
+            .map(|guard| (guard.name, guard.args))
+            .collect();
+        assert_eq!(
+            guards,
+            vec![
+                ("is_admin".to_string(), vec!["&ctx".to_string()]),
+                (
+                    "Create::accounts".to_string(),
+                    vec!["&ctx".to_string(), "bump".to_string()]
+                ),
+                ("ctx.accounts.validate()".to_string(), vec![]),
+            ]
+        );
+    }
+}
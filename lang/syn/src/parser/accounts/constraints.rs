@@ -121,6 +121,45 @@ pub fn parse_token(stream: ParseStream) -> ParseResult<ConstraintToken> {
                 _ => return Err(ParseError::new(ident.span(), "Invalid attribute")),
             }
         }
+        "metadata" => {
+            stream.parse::<Token![:]>()?;
+            stream.parse::<Token![:]>()?;
+            let kw = stream.call(Ident::parse_any)?.to_string();
+            stream.parse::<Token![=]>()?;
+
+            let span = ident
+                .span()
+                .join(stream.span())
+                .unwrap_or_else(|| ident.span());
+
+            match kw.as_str() {
+                "mint" => ConstraintToken::MetadataMint(Context::new(
+                    span,
+                    ConstraintMetadataMint {
+                        mint: stream.parse()?,
+                    },
+                )),
+                "name" => ConstraintToken::MetadataName(Context::new(
+                    span,
+                    ConstraintMetadataName {
+                        name: stream.parse()?,
+                    },
+                )),
+                "symbol" => ConstraintToken::MetadataSymbol(Context::new(
+                    span,
+                    ConstraintMetadataSymbol {
+                        symbol: stream.parse()?,
+                    },
+                )),
+                "update_authority" => ConstraintToken::MetadataUpdateAuthority(Context::new(
+                    span,
+                    ConstraintMetadataUpdateAuthority {
+                        update_authority: stream.parse()?,
+                    },
+                )),
+                _ => return Err(ParseError::new(ident.span(), "Invalid attribute")),
+            }
+        }
         "associated_token" => {
             stream.parse::<Token![:]>()?;
             stream.parse::<Token![:]>()?;
@@ -296,6 +335,20 @@ pub fn parse_token(stream: ParseStream) -> ParseResult<ConstraintToken> {
                         error: parse_optional_custom_error(&stream)?,
                     },
                 )),
+                "in_state" => ConstraintToken::InState(Context::new(
+                    span,
+                    ConstraintInState {
+                        states: Punctuated::<Ident, Token![|]>::parse_separated_nonempty(stream)?
+                            .into_iter()
+                            .collect(),
+                    },
+                )),
+                "transition_to" => ConstraintToken::TransitionTo(Context::new(
+                    span,
+                    ConstraintTransitionTo {
+                        state: stream.parse()?,
+                    },
+                )),
                 "close" => ConstraintToken::Close(Context::new(
                     span,
                     ConstraintClose {
@@ -352,11 +405,17 @@ pub struct ConstraintGroupBuilder<'ty> {
     pub mint_authority: Option<Context<ConstraintMintAuthority>>,
     pub mint_freeze_authority: Option<Context<ConstraintMintFreezeAuthority>>,
     pub mint_decimals: Option<Context<ConstraintMintDecimals>>,
+    pub metadata_mint: Option<Context<ConstraintMetadataMint>>,
+    pub metadata_name: Option<Context<ConstraintMetadataName>>,
+    pub metadata_symbol: Option<Context<ConstraintMetadataSymbol>>,
+    pub metadata_update_authority: Option<Context<ConstraintMetadataUpdateAuthority>>,
     pub bump: Option<Context<ConstraintTokenBump>>,
     pub program_seed: Option<Context<ConstraintProgramSeed>>,
     pub realloc: Option<Context<ConstraintRealloc>>,
     pub realloc_payer: Option<Context<ConstraintReallocPayer>>,
     pub realloc_zero: Option<Context<ConstraintReallocZero>>,
+    pub in_state: Option<Context<ConstraintInState>>,
+    pub transition_to: Option<Context<ConstraintTransitionTo>>,
 }
 
 impl<'ty> ConstraintGroupBuilder<'ty> {
@@ -386,11 +445,17 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
             mint_authority: None,
             mint_freeze_authority: None,
             mint_decimals: None,
+            metadata_mint: None,
+            metadata_name: None,
+            metadata_symbol: None,
+            metadata_update_authority: None,
             bump: None,
             program_seed: None,
             realloc: None,
             realloc_payer: None,
             realloc_zero: None,
+            in_state: None,
+            transition_to: None,
         }
     }
 
@@ -486,6 +551,28 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
             }
         }
 
+        // Metadata.
+        if let Some(i) = &self.init {
+            if self.metadata_mint.is_some()
+                || self.metadata_name.is_some()
+                || self.metadata_symbol.is_some()
+                || self.metadata_update_authority.is_some()
+            {
+                return Err(ParseError::new(
+                    i.span(),
+                    "metadata constraints cannot be used with init, metadata accounts are created by the metadata program",
+                ));
+            }
+        }
+        if let Some(m) = &self.metadata_mint {
+            if self.seeds.is_some() {
+                return Err(ParseError::new(
+                    m.span(),
+                    "'metadata::mint' cannot be used with the 'seeds' constraint",
+                ));
+            }
+        }
+
         // Realloc.
         if let Some(r) = &self.realloc {
             if self.realloc_payer.is_none() {
@@ -587,11 +674,17 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
             mint_authority,
             mint_freeze_authority,
             mint_decimals,
+            metadata_mint,
+            metadata_name,
+            metadata_symbol,
+            metadata_update_authority,
             bump,
             program_seed,
             realloc,
             realloc_payer,
             realloc_zero,
+            in_state,
+            transition_to,
         } = self;
 
         // Converts Option<Context<T>> -> Option<T>.
@@ -670,6 +763,22 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
             }),
         };
 
+        let metadata = match (
+            &metadata_mint,
+            &metadata_name,
+            &metadata_symbol,
+            &metadata_update_authority,
+        ) {
+            (None, None, None, None) => None,
+            _ => Some(ConstraintMetadataGroup {
+                mint: into_inner!(metadata_mint).map(|c| c.mint),
+                name: into_inner!(metadata_name).map(|c| c.name),
+                symbol: into_inner!(metadata_symbol).map(|c| c.symbol),
+                update_authority: into_inner!(metadata_update_authority)
+                    .map(|c| c.update_authority),
+            }),
+        };
+
         Ok(ConstraintGroup {
             init: init.as_ref().map(|i| Ok(ConstraintInitGroup {
                 if_needed: i.if_needed,
@@ -715,6 +824,13 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
                 space: r.space.clone(),
                 zero: into_inner!(realloc_zero).unwrap().zero,
             }),
+            state_machine: match (in_state.is_some(), transition_to.is_some()) {
+                (false, false) => None,
+                _ => Some(ConstraintStateMachineGroup {
+                    states: into_inner!(in_state).map_or_else(Vec::new, |c| c.states),
+                    transition_to: into_inner!(transition_to).map(|c| c.state),
+                }),
+            },
             zeroed: into_inner!(zeroed),
             mutable: into_inner!(mutable),
             signer: into_inner!(signer),
@@ -731,6 +847,7 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
             seeds,
             token_account: if !is_init {token_account} else {None},
             mint: if !is_init {mint} else {None},
+            metadata,
         })
     }
 
@@ -759,15 +876,27 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
             ConstraintToken::MintAuthority(c) => self.add_mint_authority(c),
             ConstraintToken::MintFreezeAuthority(c) => self.add_mint_freeze_authority(c),
             ConstraintToken::MintDecimals(c) => self.add_mint_decimals(c),
+            ConstraintToken::MetadataMint(c) => self.add_metadata_mint(c),
+            ConstraintToken::MetadataName(c) => self.add_metadata_name(c),
+            ConstraintToken::MetadataSymbol(c) => self.add_metadata_symbol(c),
+            ConstraintToken::MetadataUpdateAuthority(c) => self.add_metadata_update_authority(c),
             ConstraintToken::Bump(c) => self.add_bump(c),
             ConstraintToken::ProgramSeed(c) => self.add_program_seed(c),
             ConstraintToken::Realloc(c) => self.add_realloc(c),
             ConstraintToken::ReallocPayer(c) => self.add_realloc_payer(c),
             ConstraintToken::ReallocZero(c) => self.add_realloc_zero(c),
+            ConstraintToken::InState(c) => self.add_in_state(c),
+            ConstraintToken::TransitionTo(c) => self.add_transition_to(c),
         }
     }
 
     fn add_init(&mut self, c: Context<ConstraintInit>) -> ParseResult<()> {
+        if matches!(self.f_ty, Some(Ty::LazyAccount(_))) {
+            return Err(ParseError::new(
+                c.span(),
+                "init can't be on a LazyAccount, which is read-only",
+            ));
+        }
         if self.init.is_some() {
             return Err(ParseError::new(c.span(), "init already provided"));
         }
@@ -821,6 +950,12 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
     }
 
     fn add_zeroed(&mut self, c: Context<ConstraintZeroed>) -> ParseResult<()> {
+        if matches!(self.f_ty, Some(Ty::LazyAccount(_))) {
+            return Err(ParseError::new(
+                c.span(),
+                "zero can't be on a LazyAccount, which is read-only",
+            ));
+        }
         if self.zeroed.is_some() {
             return Err(ParseError::new(c.span(), "zeroed already provided"));
         }
@@ -834,10 +969,11 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
     fn add_realloc(&mut self, c: Context<ConstraintRealloc>) -> ParseResult<()> {
         if !matches!(self.f_ty, Some(Ty::Account(_)))
             && !matches!(self.f_ty, Some(Ty::AccountLoader(_)))
+            && !matches!(self.f_ty, Some(Ty::InterfaceAccount(_)))
         {
             return Err(ParseError::new(
                 c.span(),
-                "realloc must be on an Account or AccountLoader",
+                "realloc must be on an Account, InterfaceAccount or AccountLoader",
             ));
         }
         if self.mutable.is_none() {
@@ -881,15 +1017,47 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
         Ok(())
     }
 
+    fn add_in_state(&mut self, c: Context<ConstraintInState>) -> ParseResult<()> {
+        if !matches!(self.f_ty, Some(Ty::Account(_))) {
+            return Err(ParseError::new(c.span(), "in_state must be on an Account"));
+        }
+        if self.in_state.is_some() {
+            return Err(ParseError::new(c.span(), "in_state already provided"));
+        }
+        self.in_state.replace(c);
+        Ok(())
+    }
+
+    fn add_transition_to(&mut self, c: Context<ConstraintTransitionTo>) -> ParseResult<()> {
+        if !matches!(self.f_ty, Some(Ty::Account(_))) {
+            return Err(ParseError::new(
+                c.span(),
+                "transition_to must be on an Account",
+            ));
+        }
+        if self.mutable.is_none() {
+            return Err(ParseError::new(
+                c.span(),
+                "mut must be provided before transition_to",
+            ));
+        }
+        if self.transition_to.is_some() {
+            return Err(ParseError::new(c.span(), "transition_to already provided"));
+        }
+        self.transition_to.replace(c);
+        Ok(())
+    }
+
     fn add_close(&mut self, c: Context<ConstraintClose>) -> ParseResult<()> {
         if !matches!(self.f_ty, Some(Ty::ProgramAccount(_)))
             && !matches!(self.f_ty, Some(Ty::Account(_)))
             && !matches!(self.f_ty, Some(Ty::Loader(_)))
             && !matches!(self.f_ty, Some(Ty::AccountLoader(_)))
+            && !matches!(self.f_ty, Some(Ty::InterfaceAccount(_)))
         {
             return Err(ParseError::new(
                 c.span(),
-                "close must be on an Account, ProgramAccount, or Loader",
+                "close must be on an Account, InterfaceAccount, ProgramAccount, or Loader",
             ));
         }
         if self.mutable.is_none() {
@@ -1013,6 +1181,67 @@ impl<'ty> ConstraintGroupBuilder<'ty> {
         Ok(())
     }
 
+    fn add_metadata_mint(&mut self, c: Context<ConstraintMetadataMint>) -> ParseResult<()> {
+        if self.metadata_mint.is_some() {
+            return Err(ParseError::new(c.span(), "metadata mint already provided"));
+        }
+        self.metadata_mint.replace(c);
+        Ok(())
+    }
+
+    // The name, symbol and update authority are read from the data of the
+    // account, so it must be deserialized, e.g. as a `MetadataAccount`.
+    fn add_metadata_name(&mut self, c: Context<ConstraintMetadataName>) -> ParseResult<()> {
+        if !matches!(self.f_ty, Some(Ty::Account(_))) {
+            return Err(ParseError::new(
+                c.span(),
+                "metadata::name must be on an Account",
+            ));
+        }
+        if self.metadata_name.is_some() {
+            return Err(ParseError::new(c.span(), "metadata name already provided"));
+        }
+        self.metadata_name.replace(c);
+        Ok(())
+    }
+
+    fn add_metadata_symbol(&mut self, c: Context<ConstraintMetadataSymbol>) -> ParseResult<()> {
+        if !matches!(self.f_ty, Some(Ty::Account(_))) {
+            return Err(ParseError::new(
+                c.span(),
+                "metadata::symbol must be on an Account",
+            ));
+        }
+        if self.metadata_symbol.is_some() {
+            return Err(ParseError::new(
+                c.span(),
+                "metadata symbol already provided",
+            ));
+        }
+        self.metadata_symbol.replace(c);
+        Ok(())
+    }
+
+    fn add_metadata_update_authority(
+        &mut self,
+        c: Context<ConstraintMetadataUpdateAuthority>,
+    ) -> ParseResult<()> {
+        if !matches!(self.f_ty, Some(Ty::Account(_))) {
+            return Err(ParseError::new(
+                c.span(),
+                "metadata::update_authority must be on an Account",
+            ));
+        }
+        if self.metadata_update_authority.is_some() {
+            return Err(ParseError::new(
+                c.span(),
+                "metadata update authority already provided",
+            ));
+        }
+        self.metadata_update_authority.replace(c);
+        Ok(())
+    }
+
     fn add_mint_authority(&mut self, c: Context<ConstraintMintAuthority>) -> ParseResult<()> {
         if self.mint_authority.is_some() {
             return Err(ParseError::new(c.span(), "mint authority already provided"));
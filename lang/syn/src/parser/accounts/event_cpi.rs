@@ -0,0 +1,69 @@
+use quote::quote;
+use syn::parse::Parser;
+
+// `#[event_cpi]` adds the accounts `emit_cpi!` needs to an `Accounts` struct,
+// i.e. the PDA signing the self CPIs the events are emitted with, and the
+// program itself, which is invoked by them.
+
+// Seed of the PDA signing the self CPIs emitting events, as
+// `anchor_lang::event::EVENT_AUTHORITY_SEED`.
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+// Whether the struct is marked with `#[event_cpi]`.
+pub fn is_event_cpi(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(is_event_cpi_attr)
+}
+
+fn is_event_cpi_attr(attr: &syn::Attribute) -> bool {
+    attr.path
+        .segments
+        .last()
+        .map_or(false, |segment| segment.ident == "event_cpi")
+}
+
+// Appends the `event_authority` and `program` accounts to the struct, removing
+// its `#[event_cpi]` attribute.
+pub fn add_event_cpi_accounts(strct: &mut syn::ItemStruct) -> syn::Result<()> {
+    strct
+        .attrs
+        .retain(|attr| !is_event_cpi(std::slice::from_ref(attr)));
+    let info_lifetime = match strct.generics.lifetimes().next() {
+        Some(lifetime) => lifetime.lifetime.clone(),
+        None => {
+            return Err(syn::Error::new_spanned(
+                &strct.ident,
+                "#[event_cpi] requires the struct to have a lifetime, e.g. `'info`",
+            ))
+        }
+    };
+    let fields = match &mut strct.fields {
+        syn::Fields::Named(fields) => fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &strct.fields,
+                "fields must be named",
+            ))
+        }
+    };
+    if let Some(field) = fields.named.iter().find(|f| {
+        f.ident.as_ref().map_or(false, |ident| {
+            ident == "event_authority" || ident == "program"
+        })
+    }) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "#[event_cpi] adds the `event_authority` and `program` accounts itself",
+        ));
+    }
+    let seed = syn::LitByteStr::new(EVENT_AUTHORITY_SEED, proc_macro2::Span::call_site());
+    fields.named.push(syn::Field::parse_named.parse2(quote! {
+        /// CHECK: Only the event authority signs the self CPIs emitting events.
+        #[account(seeds = [#seed], bump)]
+        pub event_authority: AccountInfo<#info_lifetime>
+    })?);
+    fields.named.push(syn::Field::parse_named.parse2(quote! {
+        /// CHECK: Self CPIs emitting events fail unless it's the program itself.
+        pub program: AccountInfo<#info_lifetime>
+    })?);
+    Ok(())
+}
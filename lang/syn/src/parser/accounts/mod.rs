@@ -7,8 +7,16 @@ use syn::token::Comma;
 use syn::Expr;
 
 pub mod constraints;
+pub mod event_cpi;
 
 pub fn parse(strct: &syn::ItemStruct) -> ParseResult<AccountsStruct> {
+    // Structs parsed from source still have their `#[event_cpi]` attribute,
+    // e.g. when generating the IDL from files.
+    if event_cpi::is_event_cpi(&strct.attrs) {
+        let mut strct = strct.clone();
+        event_cpi::add_event_cpi_accounts(&mut strct)?;
+        return parse(&strct);
+    }
     let instruction_api: Option<Punctuated<Expr, Comma>> = strct
         .attrs
         .iter()
@@ -35,7 +43,117 @@ pub fn parse(strct: &syn::ItemStruct) -> ParseResult<AccountsStruct> {
 
     let _ = constraints_cross_checks(&fields)?;
 
-    Ok(AccountsStruct::new(strct.clone(), fields, instruction_api))
+    let mut accounts = AccountsStruct::new(strct.clone(), fields, instruction_api);
+    accounts.remaining_accounts = strct
+        .attrs
+        .iter()
+        .find(|a| a.path.is_ident("remaining_accounts"))
+        .map(|attr| attr.parse_args_with(parse_remaining_accounts))
+        .transpose()?;
+    accounts.distinct_mut = strct
+        .attrs
+        .iter()
+        .find(|a| a.path.is_ident("distinct_mut"))
+        .map(|attr| parse_distinct_mut(attr, &accounts.fields))
+        .transpose()?
+        .unwrap_or_default();
+    Ok(accounts)
+}
+
+// Each pair of the accounts listed by `#[distinct_mut(a, b, ..)]`, or of the
+// mutable accounts of the struct for a bare `#[distinct_mut]`.
+fn parse_distinct_mut(
+    attr: &syn::Attribute,
+    fields: &[AccountField],
+) -> ParseResult<Vec<(Ident, Ident)>> {
+    let accounts: Vec<&Field> = if attr.tokens.is_empty() {
+        fields
+            .iter()
+            .filter_map(|af| match af {
+                AccountField::Field(f) if f.constraints.is_mutable() => Some(f),
+                _ => None,
+            })
+            .collect()
+    } else {
+        attr.parse_args_with(Punctuated::<Ident, Comma>::parse_terminated)?
+            .iter()
+            .map(|name| {
+                fields
+                    .iter()
+                    .find_map(|af| match af {
+                        AccountField::Field(f) if &f.ident == name => Some(f),
+                        _ => None,
+                    })
+                    .ok_or_else(|| {
+                        ParseError::new(
+                            name.span(),
+                            format!("`{}` isn't an account of the struct", name),
+                        )
+                    })
+            })
+            .collect::<ParseResult<_>>()?
+    };
+    if accounts.len() < 2 {
+        return Err(ParseError::new_spanned(
+            attr,
+            "`distinct_mut` needs at least two accounts to compare",
+        ));
+    }
+    Ok(accounts
+        .iter()
+        .enumerate()
+        .flat_map(|(i, a)| {
+            accounts[i + 1..]
+                .iter()
+                .map(move |b| (a.ident.clone(), b.ident.clone()))
+        })
+        .collect())
+}
+
+// `ty = <type>`, followed by `mutable` if the accounts must be writable.
+fn parse_remaining_accounts(input: syn::parse::ParseStream) -> ParseResult<RemainingAccounts> {
+    let key: syn::Ident = input.parse()?;
+    if key != "ty" {
+        return Err(ParseError::new(
+            key.span(),
+            "Expected the type of the remaining accounts, e.g. `ty = TokenAccount`",
+        ));
+    }
+    input.parse::<syn::Token![=]>()?;
+    let ty = input.parse()?;
+    let mut mutable = false;
+    while !input.is_empty() {
+        input.parse::<syn::Token![,]>()?;
+        if input.is_empty() {
+            break;
+        }
+        let flag: syn::Ident = input.parse()?;
+        match flag.to_string().as_str() {
+            "mutable" => mutable = true,
+            _ => return Err(ParseError::new(flag.span(), "Expected `mutable`")),
+        }
+    }
+    Ok(RemainingAccounts { ty, mutable })
+}
+
+// Parses an `#[accounts_fragment]` struct. Its constraints are only checked
+// once embedded, as they may refer to the accounts of the struct it's embedded
+// into.
+pub fn parse_fragment(strct: &syn::ItemStruct) -> ParseResult<AccountsStruct> {
+    let fields = match &strct.fields {
+        syn::Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(parse_account_field)
+            .collect::<ParseResult<Vec<AccountField>>>()?,
+        _ => {
+            return Err(ParseError::new_spanned(
+                &strct.fields,
+                "fields must be named",
+            ))
+        }
+    };
+    Ok(AccountsStruct::new(strct.clone(), fields, None))
 }
 
 fn constraints_cross_checks(fields: &[AccountField]) -> ParseResult<()> {
@@ -196,21 +314,66 @@ fn constraints_cross_checks(fields: &[AccountField]) -> ParseResult<()> {
         }
     }
 
+    // CLOSE
+    for field in fields {
+        if let AccountField::Field(field) = field {
+            if let Some(close) = &field.constraints.close {
+                if close.sol_dest == field.ident {
+                    return Err(ParseError::new(
+                        close.sol_dest.span(),
+                        "the close destination must differ from the closed account.",
+                    ));
+                }
+            }
+        }
+    }
+
     Ok(())
 }
 
 pub fn parse_account_field(f: &syn::Field) -> ParseResult<AccountField> {
     let ident = f.ident.clone().unwrap();
     let docs = docs::parse(&f.attrs);
+    let fragment = parse_from_fragment(f)?;
+    // Optional accounts are parsed as the account they wrap.
+    let (f, is_optional) = match option_inner(&f.ty) {
+        Some(ty) => {
+            let inner = syn::Field {
+                ty: ty.clone(),
+                ..f.clone()
+            };
+            if !is_field_primitive(&inner)? {
+                return Err(ParseError::new(
+                    f.ty.span(),
+                    "optional composite accounts are not supported",
+                ));
+            }
+            (inner, true)
+        }
+        None => (f.clone(), false),
+    };
+    let f = &f;
     let account_field = match is_field_primitive(f)? {
         true => {
             let ty = parse_ty(f)?;
             let account_constraints = constraints::parse(f, Some(&ty))?;
+            if is_optional
+                && (account_constraints.init.is_some()
+                    || account_constraints.zeroed.is_some()
+                    || account_constraints.realloc.is_some())
+            {
+                return Err(ParseError::new(
+                    ident.span(),
+                    "init, zero and realloc constraints are not supported on optional accounts",
+                ));
+            }
             AccountField::Field(Field {
                 ident,
                 ty,
                 constraints: account_constraints,
                 docs,
+                is_optional,
+                fragment,
             })
         }
         false => {
@@ -227,6 +390,42 @@ pub fn parse_account_field(f: &syn::Field) -> ParseResult<AccountField> {
     Ok(account_field)
 }
 
+// The `#[from_fragment(<field>, <name>)]` attribute of the accounts embedded
+// from an accounts fragment.
+fn parse_from_fragment(f: &syn::Field) -> ParseResult<Option<(String, String)>> {
+    let attr = match f.attrs.iter().find(|a| a.path.is_ident("from_fragment")) {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+    let idents = attr.parse_args_with(Punctuated::<Ident, Comma>::parse_terminated)?;
+    match (idents.first(), idents.last(), idents.len()) {
+        (Some(field), Some(name), 2) => Ok(Some((field.to_string(), name.to_string()))),
+        _ => Err(ParseError::new(
+            attr.span(),
+            "expected the field of the fragment and the name of the account",
+        )),
+    }
+}
+
+// The account wrapped by an `Option`, if any.
+fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let path = match ty {
+        syn::Type::Path(ty_path) if ty_path.qself.is_none() => &ty_path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if path.segments.len() != 1 || segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 fn is_field_primitive(f: &syn::Field) -> ParseResult<bool> {
     let r = matches!(
         ident_string(f)?.as_str(),
@@ -240,6 +439,9 @@ fn is_field_primitive(f: &syn::Field) -> ParseResult<bool> {
             | "Loader"
             | "AccountLoader"
             | "Account"
+            | "LazyAccount"
+            | "InterfaceAccount"
+            | "Interface"
             | "Program"
             | "Signer"
             | "SystemAccount"
@@ -264,6 +466,9 @@ fn parse_ty(f: &syn::Field) -> ParseResult<Ty> {
         "Loader" => Ty::Loader(parse_program_account_zero_copy(&path)?),
         "AccountLoader" => Ty::AccountLoader(parse_program_account_loader(&path)?),
         "Account" => Ty::Account(parse_account_ty(&path)?),
+        "LazyAccount" => Ty::LazyAccount(parse_lazy_account_ty(&path)?),
+        "InterfaceAccount" => Ty::InterfaceAccount(parse_interface_account_ty(&path)?),
+        "Interface" => Ty::Interface(parse_interface_ty(&path)?),
         "Program" => Ty::Program(parse_program_ty(&path)?),
         "Signer" => Ty::Signer,
         "SystemAccount" => Ty::SystemAccount,
@@ -349,6 +554,21 @@ fn parse_account_ty(path: &syn::Path) -> ParseResult<AccountTy> {
     })
 }
 
+fn parse_lazy_account_ty(path: &syn::Path) -> ParseResult<LazyAccountTy> {
+    let account_type_path = parse_account(path)?;
+    Ok(LazyAccountTy { account_type_path })
+}
+
+fn parse_interface_account_ty(path: &syn::Path) -> ParseResult<InterfaceAccountTy> {
+    let account_type_path = parse_account(path)?;
+    Ok(InterfaceAccountTy { account_type_path })
+}
+
+fn parse_interface_ty(path: &syn::Path) -> ParseResult<InterfaceTy> {
+    let account_type_path = parse_account(path)?;
+    Ok(InterfaceTy { account_type_path })
+}
+
 fn parse_program_ty(path: &syn::Path) -> ParseResult<ProgramTy> {
     let account_type_path = parse_account(path)?;
     Ok(ProgramTy { account_type_path })
@@ -0,0 +1,99 @@
+use syn::punctuated::Punctuated;
+use syn::{Expr, Lit, Token};
+
+// Constraints on the arguments of instruction handlers, checked before the
+// handler runs:
+//
+// pub fn deposit(
+//     ctx: Context<Deposit>,
+//     #[arg(range = 1..=100)] fee_bps: u16,
+//     #[arg(non_zero)] amount: u64,
+//     #[arg(max_len = 32)] memo: String,
+// ) -> Result<()>
+//
+// Range bounds must be integer literals, for the IDL to describe them.
+
+#[derive(Debug, Clone)]
+pub enum ArgConstraint {
+    // The range as written, along with its inclusive bounds.
+    Range {
+        range: syn::ExprRange,
+        min: Option<i128>,
+        max: Option<i128>,
+    },
+    NonZero,
+    MaxLen(u64),
+}
+
+// Parses the `#[arg(..)]` attributes of an argument.
+pub fn parse(attrs: &[syn::Attribute]) -> syn::Result<Vec<ArgConstraint>> {
+    let mut constraints = vec![];
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("arg")) {
+        let args = attr.parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)?;
+        for arg in args {
+            constraints.push(parse_constraint(&arg)?);
+        }
+    }
+    Ok(constraints)
+}
+
+// Removes the `#[arg(..)]` attributes of an argument, which aren't valid Rust
+// once the program is expanded.
+pub fn strip(attrs: &mut Vec<syn::Attribute>) {
+    attrs.retain(|attr| !attr.path.is_ident("arg"));
+}
+
+fn parse_constraint(arg: &Expr) -> syn::Result<ArgConstraint> {
+    match arg {
+        Expr::Path(path) if path.path.is_ident("non_zero") => Ok(ArgConstraint::NonZero),
+        Expr::Assign(assign) => match &*assign.left {
+            Expr::Path(path) if path.path.is_ident("range") => match &*assign.right {
+                Expr::Range(range) => {
+                    let min = range.from.as_deref().map(int).transpose()?;
+                    let max = match (&range.to, &range.limits) {
+                        (Some(to), syn::RangeLimits::Closed(_)) => Some(int(to)?),
+                        (Some(to), syn::RangeLimits::HalfOpen(_)) => Some(int(to)? - 1),
+                        (None, _) => None,
+                    };
+                    Ok(ArgConstraint::Range {
+                        range: range.clone(),
+                        min,
+                        max,
+                    })
+                }
+                right => Err(syn::Error::new_spanned(
+                    right,
+                    "Expected a range, e.g. `range = 1..=100`",
+                )),
+            },
+            Expr::Path(path) if path.path.is_ident("max_len") => {
+                let max_len = int(&assign.right)?;
+                u64::try_from(max_len)
+                    .map(ArgConstraint::MaxLen)
+                    .map_err(|_| syn::Error::new_spanned(&assign.right, "Expected a length"))
+            }
+            left => Err(syn::Error::new_spanned(
+                left,
+                "Expected `range` or `max_len`",
+            )),
+        },
+        _ => Err(syn::Error::new_spanned(
+            arg,
+            "Unknown arg constraint, expected `range = ..`, `non_zero` or `max_len = ..`",
+        )),
+    }
+}
+
+fn int(expr: &Expr) -> syn::Result<i128> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse(),
+        Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        }) => int(expr).map(|int| -int),
+        _ => Err(syn::Error::new_spanned(expr, "Expected an integer literal")),
+    }
+}
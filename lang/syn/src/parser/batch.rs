@@ -0,0 +1,79 @@
+use crate::Ix;
+
+// Handlers marked `#[batch]` can be run by the `batch` instruction generated
+// for the program, given the data of each instruction to run and the number
+// of accounts each one takes out of the batch's, in order.
+
+// Name of the generated instruction.
+pub const BATCH_IX_NAME: &str = "batch";
+
+// Whether a handler is marked `#[batch]`.
+pub fn is_batched(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("batch"))
+}
+
+// The handlers the `batch` instruction can run, none if it isn't generated.
+pub fn batched_ixs(ixs: &[Ix]) -> Vec<&Ix> {
+    ixs.iter()
+        .filter(|ix| is_batched(&ix.raw_method.attrs))
+        .collect()
+}
+
+// Errors if the generated instruction would collide with a handler.
+pub fn check(ixs: &[Ix]) -> syn::Result<()> {
+    if batched_ixs(ixs).is_empty() {
+        return Ok(());
+    }
+    match ixs.iter().find(|ix| ix.ident == BATCH_IX_NAME) {
+        Some(ix) => Err(syn::Error::new(
+            ix.raw_method.sig.ident.span(),
+            "The `batch` instruction is generated for the `#[batch]` handlers",
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Program;
+
+    #[test]
+    fn batch_name_collision() {
+        let program: Program = syn::parse_quote! {
+            pub mod vault {
+                #[batch]
+                pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+                    Ok(())
+                }
+
+                pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+                    Ok(())
+                }
+            }
+        };
+        let names: Vec<String> = batched_ixs(&program.ixs)
+            .iter()
+            .map(|ix| ix.ident.to_string())
+            .collect();
+        assert_eq!(names, vec!["deposit"]);
+
+        let err = syn::parse2::<Program>(quote::quote! {
+            pub mod vault {
+                #[batch]
+                pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+                    Ok(())
+                }
+
+                pub fn batch(ctx: Context<Batch>) -> Result<()> {
+                    Ok(())
+                }
+            }
+        })
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The `batch` instruction is generated for the `#[batch]` handlers"
+        );
+    }
+}
@@ -0,0 +1,16 @@
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, Token};
+
+// Accounts given `#[account(checksum)]` have their data followed by the CRC-32
+// of their serialized fields, verified when they're deserialized.
+
+// Whether the `#[account(..)]` attribute has the `checksum` argument.
+pub fn account_checksum(args: proc_macro2::TokenStream) -> syn::Result<bool> {
+    let args = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(args)?;
+    let is_checksummed = args.iter().any(|arg| match arg {
+        Expr::Path(path) => path.path.is_ident("checksum"),
+        _ => false,
+    });
+    Ok(is_checksummed)
+}
@@ -0,0 +1,92 @@
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, Lit, Token};
+
+// Compute budget an instruction needs, given by
+// `#[compute_budget(units = 400_000, heap = 256_000)]` on its handler, in
+// compute units and bytes of heap. The program can't raise its own budget, so
+// it's only recorded in the IDL, for clients to request it with the
+// instructions of the compute budget program ahead of the instruction.
+
+// Most compute units a transaction can request.
+pub const MAX_UNITS: u32 = 1_400_000;
+// Bounds of the heap a transaction can request, in multiples of 1 KiB.
+pub const MIN_HEAP: u32 = 32 * 1024;
+pub const MAX_HEAP: u32 = 256 * 1024;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeBudget {
+    pub units: Option<u32>,
+    pub heap: Option<u32>,
+}
+
+pub fn parse_args(args: proc_macro2::TokenStream) -> syn::Result<ComputeBudget> {
+    let args = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(args)?;
+    let mut budget = ComputeBudget::default();
+    for arg in args.iter() {
+        let assign = match arg {
+            Expr::Assign(assign) => assign,
+            arg => {
+                return Err(syn::Error::new_spanned(
+                    arg,
+                    "Expected `units = ..` or `heap = ..`",
+                ))
+            }
+        };
+        let value = int(&assign.right)?;
+        match &*assign.left {
+            Expr::Path(path) if path.path.is_ident("units") && budget.units.is_none() => {
+                let units = value.base10_parse()?;
+                if units == 0 || units > MAX_UNITS {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        format!("The compute units must be between 1 and {}", MAX_UNITS),
+                    ));
+                }
+                budget.units = Some(units);
+            }
+            Expr::Path(path) if path.path.is_ident("heap") && budget.heap.is_none() => {
+                let heap = value.base10_parse()?;
+                if !(MIN_HEAP..=MAX_HEAP).contains(&heap) || heap % 1024 != 0 {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        format!(
+                            "The heap must be a multiple of 1024 bytes between {} and {}",
+                            MIN_HEAP, MAX_HEAP
+                        ),
+                    ));
+                }
+                budget.heap = Some(heap);
+            }
+            Expr::Path(path) if path.path.is_ident("units") || path.path.is_ident("heap") => {
+                return Err(syn::Error::new_spanned(arg, "Duplicate compute budget"))
+            }
+            left => return Err(syn::Error::new_spanned(left, "Expected `units` or `heap`")),
+        }
+    }
+    if budget == ComputeBudget::default() {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "Expected the budget, e.g. `#[compute_budget(units = 400_000, heap = 256_000)]`",
+        ));
+    }
+    Ok(budget)
+}
+
+// Parses the `#[compute_budget(..)]` attribute of a handler, if any.
+pub fn compute_budget(attrs: &[syn::Attribute]) -> syn::Result<Option<ComputeBudget>> {
+    attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("compute_budget"))
+        .map(|attr| parse_args(attr.parse_args()?))
+        .transpose()
+}
+
+fn int(expr: &Expr) -> syn::Result<&syn::LitInt> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(int), ..
+        }) => Ok(int),
+        _ => Err(syn::Error::new_spanned(expr, "Expected an integer")),
+    }
+}
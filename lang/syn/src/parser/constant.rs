@@ -0,0 +1,44 @@
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::Token;
+
+// Arguments of the `#[constant]` attribute.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ConstantArgs {
+    // Leaves the constant out of the IDL, e.g. `#[constant(skip_idl)]` for a
+    // constant only the program uses.
+    pub skip_idl: bool,
+}
+
+// Parses the arguments given to `#[constant(..)]`.
+pub fn parse_args(args: proc_macro2::TokenStream) -> syn::Result<ConstantArgs> {
+    let idents = Punctuated::<syn::Ident, Token![,]>::parse_terminated.parse2(args)?;
+    let mut parsed = ConstantArgs::default();
+    for ident in idents {
+        match ident.to_string().as_str() {
+            "skip_idl" => parsed.skip_idl = true,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "Unknown constant argument, expected `skip_idl`",
+                ))
+            }
+        }
+    }
+    Ok(parsed)
+}
+
+// Parses the `#[constant]` attribute of an item, if any.
+pub fn args(attrs: &[syn::Attribute]) -> syn::Result<Option<ConstantArgs>> {
+    let attr = match attrs
+        .iter()
+        .find(|attr| attr.path.segments.last().unwrap().ident == "constant")
+    {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+    if attr.tokens.is_empty() {
+        return Ok(Some(ConstantArgs::default()));
+    }
+    parse_args(attr.parse_args()?).map(Some)
+}
@@ -1,7 +1,12 @@
+use crate::parser::safety::{self, Diagnostic, SafetyConfig, Severity};
 use anyhow::anyhow;
-use std::collections::BTreeMap;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use syn::parse::{Error as ParseError, Result as ParseResult};
+use syn::punctuated::Punctuated;
+use syn::Token;
 
 /// Crate parse context
 ///
@@ -23,57 +28,131 @@ impl CrateContext {
         self.modules.iter().flat_map(|(_, ctx)| ctx.enums())
     }
 
+    // Returns the program id given to `declare_id!`, either in the crate root
+    // or, if given, in the module with the given name.
+    pub fn declared_id(&self, module: Option<&str>) -> Option<String> {
+        match module {
+            None => self.modules.get("crate")?.declared_id(),
+            Some(module) => self
+                .modules
+                .values()
+                .filter(|ctx| ctx.name == module)
+                .find_map(|ctx| ctx.declared_id()),
+        }
+    }
+
     pub fn modules(&self) -> impl Iterator<Item = ModuleContext> {
         self.modules
             .iter()
             .map(move |(_, detail)| ModuleContext { detail })
     }
 
+    // Returns the module at the given path, e.g. `crate::state`.
+    pub fn module(&self, path: &str) -> Option<ModuleContext<'_>> {
+        self.modules().find(|module| module.path() == path)
+    }
+
     pub fn root_module(&self) -> ModuleContext {
         ModuleContext {
             detail: self.modules.get("crate").unwrap(),
         }
     }
 
+    // Parses the crate, reusing the files of the shared parse cache whose
+    // content didn't change.
     pub fn parse(root: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        ParseCache::shared(|cache| Self::parse_with_cache(root, cache))
+    }
+
+    pub fn parse_with_cache(
+        root: impl AsRef<Path>,
+        cache: &mut ParseCache,
+    ) -> Result<Self, anyhow::Error> {
         Ok(CrateContext {
-            modules: ParsedModule::parse_recursive(root.as_ref())?,
+            modules: ParsedModule::parse_recursive(root.as_ref(), cache)?,
         })
     }
 
-    // Perform Anchor safety checks on the parsed create
+    // Perform Anchor safety checks on the parsed create, failing on the first
+    // rule violation which is an error by default.
     pub fn safety_checks(&self) -> Result<(), anyhow::Error> {
-        // Check all structs for unsafe field types, i.e. AccountInfo and UncheckedAccount.
-        for (_, ctx) in self.modules.iter() {
-            for unsafe_field in ctx.unsafe_struct_fields() {
-                // Check if unsafe field type has been documented with a /// SAFETY: doc string.
-                let is_documented = unsafe_field.attrs.iter().any(|attr| {
-                    attr.tokens.clone().into_iter().any(|token| match token {
-                        // Check for doc comments containing CHECK
-                        proc_macro2::TokenTree::Literal(s) => s.to_string().contains("CHECK"),
-                        _ => false,
-                    })
-                });
-                if !is_documented {
-                    let ident = unsafe_field.ident.as_ref().unwrap();
-                    let span = ident.span();
-                    // Error if undocumented.
-                    return Err(anyhow!(
-                        r#"
-        {}:{}:{}
-        Struct field "{}" is unsafe, but is not documented.
-        Please add a `/// CHECK:` doc comment explaining why no checks through types are necessary.
-        See https://book.anchor-lang.com/anchor_in_depth/the_accounts_struct.html#safety-checks for more information.
-                    "#,
-                        ctx.file.canonicalize().unwrap().display(),
-                        span.start().line,
-                        span.start().column,
-                        ident.to_string()
-                    ));
-                };
+        self.safety_diagnostics(&SafetyConfig::default())
+            .into_iter()
+            .find(|diagnostic| diagnostic.severity == Severity::Error)
+            .map_or(Ok(()), |diagnostic| Err(anyhow!("{}", diagnostic)))
+    }
+
+    // Checks the parsed crate against the rules of the config which aren't
+    // allowed, in the order of modules.
+    pub fn safety_diagnostics(&self, config: &SafetyConfig) -> Vec<Diagnostic> {
+        safety::check(self, config)
+    }
+}
+
+/// Parsed files, by path
+///
+/// Files are only parsed again when their content changes, which is checked
+/// with a hash of their content.
+#[derive(Default)]
+pub struct ParseCache {
+    files: HashMap<PathBuf, CachedFile>,
+}
+
+struct CachedFile {
+    hash: [u8; 32],
+    file: syn::File,
+}
+
+thread_local! {
+    // Syntax trees can't be sent across threads, so each thread has its own.
+    static SHARED_PARSE_CACHE: RefCell<ParseCache> = RefCell::new(ParseCache::default());
+}
+
+impl ParseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `f` with the cache used by `CrateContext::parse` on this thread,
+    /// e.g. to invalidate files.
+    pub fn shared<R>(f: impl FnOnce(&mut ParseCache) -> R) -> R {
+        SHARED_PARSE_CACHE.with(|cache| f(&mut cache.borrow_mut()))
+    }
+
+    /// Removes a file from the cache, returning true if it was cached.
+    pub fn invalidate(&mut self, path: impl AsRef<Path>) -> bool {
+        self.files.remove(path.as_ref()).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    // Parses the content of the file at `path`, unless it was already parsed.
+    fn parse(&mut self, path: &Path, content: &str) -> ParseResult<syn::File> {
+        let hash: [u8; 32] = Sha256::digest(content.as_bytes()).into();
+        if let Some(cached) = self.files.get(path) {
+            if cached.hash == hash {
+                return Ok(cached.file.clone());
             }
         }
-        Ok(())
+        let file = syn::parse_file(content)?;
+        self.files.insert(
+            path.to_owned(),
+            CachedFile {
+                hash,
+                file: file.clone(),
+            },
+        );
+        Ok(file)
     }
 }
 
@@ -86,9 +165,31 @@ pub struct ModuleContext<'krate> {
 }
 
 impl<'krate> ModuleContext<'krate> {
-    pub fn items(&self) -> impl Iterator<Item = &syn::Item> {
+    pub fn items(&self) -> impl Iterator<Item = &'krate syn::Item> {
         self.detail.items.iter()
     }
+
+    // The file the module is defined in.
+    pub fn file(&self) -> &'krate Path {
+        &self.detail.file
+    }
+
+    // The path of the module, e.g. `crate::state`.
+    pub fn path(&self) -> String {
+        format!("crate{}", self.detail.path)
+    }
+
+    pub fn consts(&self) -> impl Iterator<Item = &'krate syn::ItemConst> {
+        self.detail.consts()
+    }
+
+    pub fn structs(&self) -> impl Iterator<Item = &'krate syn::ItemStruct> {
+        self.detail.structs()
+    }
+
+    pub fn enums(&self) -> impl Iterator<Item = &'krate syn::ItemEnum> {
+        self.detail.enums()
+    }
 }
 struct ParsedModule {
     name: String,
@@ -97,17 +198,147 @@ struct ParsedModule {
     items: Vec<syn::Item>,
 }
 
+// The value of a `#[path = ".."]` attribute.
+fn path_attr(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("path"))
+        .find_map(|attr| match attr.parse_meta().ok()? {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(path),
+                ..
+            }) => Some(path.value()),
+            _ => None,
+        })
+}
+
+// Returns true if the attributes only compile the item in tests, e.g.
+// `#[cfg(test)]` or `#[cfg(all(test, feature = "x"))]`.
+fn is_test_only(attrs: &[syn::Attribute]) -> bool {
+    fn requires_test(meta: &syn::NestedMeta) -> bool {
+        match meta {
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) => path.is_ident("test"),
+            syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("all") => {
+                list.nested.iter().any(requires_test)
+            }
+            syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("any") => {
+                !list.nested.is_empty() && list.nested.iter().all(requires_test)
+            }
+            _ => false,
+        }
+    }
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("cfg"))
+        .any(|attr| match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list.nested.iter().any(requires_test),
+            _ => false,
+        })
+}
+
+fn item_attrs(item: &syn::Item) -> &[syn::Attribute] {
+    match item {
+        syn::Item::Const(item) => &item.attrs,
+        syn::Item::Enum(item) => &item.attrs,
+        syn::Item::ExternCrate(item) => &item.attrs,
+        syn::Item::Fn(item) => &item.attrs,
+        syn::Item::ForeignMod(item) => &item.attrs,
+        syn::Item::Impl(item) => &item.attrs,
+        syn::Item::Macro(item) => &item.attrs,
+        syn::Item::Macro2(item) => &item.attrs,
+        syn::Item::Mod(item) => &item.attrs,
+        syn::Item::Static(item) => &item.attrs,
+        syn::Item::Struct(item) => &item.attrs,
+        syn::Item::Trait(item) => &item.attrs,
+        syn::Item::TraitAlias(item) => &item.attrs,
+        syn::Item::Type(item) => &item.attrs,
+        syn::Item::Union(item) => &item.attrs,
+        syn::Item::Use(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+// Replaces the `include!` items of a file by the items of the included files,
+// and strips the items only compiled in tests, e.g. `#[cfg(test)] mod tests`,
+// so that test types don't end up in the IDL.
+//
+// Only paths that are string literals, or `concat!` of string literals and
+// `env!("CARGO_MANIFEST_DIR")`, can be resolved. Other includes, e.g. of
+// files generated in `OUT_DIR`, are left as is.
+fn expand_includes(
+    file: &Path,
+    items: Vec<syn::Item>,
+    cache: &mut ParseCache,
+) -> ParseResult<Vec<syn::Item>> {
+    let mut expanded = Vec::with_capacity(items.len());
+    for item in items {
+        if is_test_only(item_attrs(&item)) {
+            continue;
+        }
+        let path = match &item {
+            syn::Item::Macro(item) if item.mac.path.is_ident("include") => item
+                .mac
+                .parse_body::<syn::Expr>()
+                .ok()
+                .and_then(|expr| include_path(file, &expr)),
+            _ => None,
+        };
+        let path = match path {
+            Some(path) => file.parent().unwrap().join(path),
+            None => {
+                expanded.push(item);
+                continue;
+            }
+        };
+        let content = std::fs::read_to_string(&path)
+            .map_err(|_| ParseError::new_spanned(&item, "could not read included file"))?;
+        let included = cache.parse(&path, &content)?;
+        expanded.extend(expand_includes(&path, included.items, cache)?);
+    }
+    Ok(expanded)
+}
+
+fn include_path(file: &Path, expr: &syn::Expr) -> Option<String> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit),
+            ..
+        }) => Some(lit.value()),
+        syn::Expr::Macro(expr) if expr.mac.path.is_ident("concat") => expr
+            .mac
+            .parse_body_with(Punctuated::<syn::Expr, Token![,]>::parse_terminated)
+            .ok()?
+            .iter()
+            .map(|expr| include_path(file, expr))
+            .collect(),
+        syn::Expr::Macro(expr) if expr.mac.path.is_ident("env") => {
+            match expr.mac.parse_body::<syn::LitStr>().ok()?.value().as_str() {
+                "CARGO_MANIFEST_DIR" => file
+                    .ancestors()
+                    .skip(1)
+                    .find(|dir| dir.join("Cargo.toml").exists())
+                    .map(|dir| dir.display().to_string()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
 impl ParsedModule {
-    fn parse_recursive(root: &Path) -> Result<BTreeMap<String, ParsedModule>, anyhow::Error> {
+    fn parse_recursive(
+        root: &Path,
+        cache: &mut ParseCache,
+    ) -> Result<BTreeMap<String, ParsedModule>, anyhow::Error> {
         let mut modules = BTreeMap::new();
 
         let root_content = std::fs::read_to_string(root)?;
-        let root_file = syn::parse_file(&root_content)?;
+        let root_file = cache.parse(root, &root_content)?;
         let root_mod = Self::new(
             String::new(),
             root.to_owned(),
             "crate".to_owned(),
-            root_file.items,
+            expand_includes(root, root_file.items, cache)?,
         );
 
         struct UnparsedModule {
@@ -130,7 +361,7 @@ impl ParsedModule {
         while let Some(to_parse) = unparsed.pop() {
             let path = format!("{}::{}", to_parse.path, to_parse.name);
             let name = to_parse.name;
-            let module = Self::from_item_mod(&to_parse.file, &path, to_parse.item)?;
+            let module = Self::from_item_mod(&to_parse.file, &path, to_parse.item, cache)?;
 
             unparsed.extend(module.submodules().map(|item| UnparsedModule {
                 item: item.clone(),
@@ -150,6 +381,7 @@ impl ParsedModule {
         parent_file: &Path,
         parent_path: &str,
         item: syn::ItemMod,
+        cache: &mut ParseCache,
     ) -> ParseResult<Self> {
         Ok(match item.content {
             Some((_, items)) => {
@@ -158,7 +390,7 @@ impl ParsedModule {
                     parent_path.to_owned(),
                     parent_file.to_owned(),
                     item.ident.to_string(),
-                    items,
+                    expand_includes(parent_file, items, cache)?,
                 )
             }
             None => {
@@ -168,12 +400,17 @@ impl ParsedModule {
                 let parent_filename = parent_file.file_stem().unwrap().to_str().unwrap();
                 let parent_mod_dir = parent_dir.join(parent_filename);
 
-                let possible_file_paths = vec![
-                    parent_dir.join(format!("{}.rs", item.ident)),
-                    parent_dir.join(format!("{}/mod.rs", item.ident)),
-                    parent_mod_dir.join(format!("{}.rs", item.ident)),
-                    parent_mod_dir.join(format!("{}/mod.rs", item.ident)),
-                ];
+                // A `#[path]` attribute gives the file relative to the
+                // directory of the parent file.
+                let possible_file_paths = match path_attr(&item.attrs) {
+                    Some(path) => vec![parent_dir.join(path)],
+                    None => vec![
+                        parent_dir.join(format!("{}.rs", item.ident)),
+                        parent_dir.join(format!("{}/mod.rs", item.ident)),
+                        parent_mod_dir.join(format!("{}.rs", item.ident)),
+                        parent_mod_dir.join(format!("{}/mod.rs", item.ident)),
+                    ],
+                };
 
                 let mod_file_path = possible_file_paths
                     .into_iter()
@@ -181,13 +418,14 @@ impl ParsedModule {
                     .ok_or_else(|| ParseError::new_spanned(&item, "could not find file"))?;
                 let mod_file_content = std::fs::read_to_string(&mod_file_path)
                     .map_err(|_| ParseError::new_spanned(&item, "could not read file"))?;
-                let mod_file = syn::parse_file(&mod_file_content)?;
+                let mod_file = cache.parse(&mod_file_path, &mod_file_content)?;
+                let items = expand_includes(&mod_file_path, mod_file.items, cache)?;
 
                 Self::new(
                     parent_path.to_owned(),
                     mod_file_path,
                     item.ident.to_string(),
-                    mod_file.items,
+                    items,
                 )
             }
         })
@@ -216,21 +454,6 @@ impl ParsedModule {
         })
     }
 
-    fn unsafe_struct_fields(&self) -> impl Iterator<Item = &syn::Field> {
-        self.structs()
-            .flat_map(|s| &s.fields)
-            .filter(|f| match &f.ty {
-                syn::Type::Path(syn::TypePath {
-                    path: syn::Path { segments, .. },
-                    ..
-                }) => {
-                    segments.len() == 1 && segments[0].ident == "UncheckedAccount"
-                        || segments[0].ident == "AccountInfo"
-                }
-                _ => false,
-            })
-    }
-
     fn enums(&self) -> impl Iterator<Item = &syn::ItemEnum> {
         self.items.iter().filter_map(|i| match i {
             syn::Item::Enum(item) => Some(item),
@@ -238,6 +461,20 @@ impl ParsedModule {
         })
     }
 
+    fn declared_id(&self) -> Option<String> {
+        self.items.iter().find_map(|i| match i {
+            syn::Item::Macro(item)
+                if item.mac.path.segments.last().unwrap().ident == "declare_id" =>
+            {
+                item.mac
+                    .parse_body::<syn::LitStr>()
+                    .ok()
+                    .map(|lit| lit.value())
+            }
+            _ => None,
+        })
+    }
+
     fn consts(&self) -> impl Iterator<Item = &syn::ItemConst> {
         self.items.iter().filter_map(|i| match i {
             syn::Item::Const(item) => Some(item),
@@ -245,3 +482,101 @@ impl ParsedModule {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_attributes_and_includes() {
+        let dir = std::env::temp_dir().join("anchor_syn_context_includes");
+        std::fs::create_dir_all(dir.join("src/other")).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+        std::fs::write(
+            dir.join("src/lib.rs"),
+            r#"
+            #[path = "other/dir.rs"]
+            mod state;
+            include!("types.rs");
+            include!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/other/", "events.rs"));
+            include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+            "#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("src/other/dir.rs"), "pub struct Vault {}").unwrap();
+        std::fs::write(
+            dir.join("src/types.rs"),
+            "pub struct Fee {} include!(\"other/nested.rs\");",
+        )
+        .unwrap();
+        std::fs::write(dir.join("src/other/nested.rs"), "pub enum Kind {}").unwrap();
+        std::fs::write(dir.join("src/other/events.rs"), "pub struct Deposited {}").unwrap();
+
+        let ctx = CrateContext::parse(dir.join("src/lib.rs")).unwrap();
+        let mut structs: Vec<String> = ctx.structs().map(|s| s.ident.to_string()).collect();
+        structs.sort();
+        assert_eq!(structs, vec!["Deposited", "Fee", "Vault"]);
+        assert_eq!(ctx.enums().next().unwrap().ident, "Kind");
+        // Unresolved includes are left as is.
+        assert_eq!(ctx.root_module().items().count(), 5);
+    }
+
+    #[test]
+    fn strip_test_items() {
+        let dir = std::env::temp_dir().join("anchor_syn_context_tests");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("lib.rs"),
+            r#"
+            pub const MAX: usize = 8;
+            pub struct Vault {}
+            #[cfg(not(test))]
+            pub struct Pool {}
+            #[cfg(any(test, feature = "mock"))]
+            pub struct Oracle {}
+            #[cfg(all(test, feature = "mock"))]
+            pub struct MockOracle {}
+
+            #[cfg(test)]
+            mod tests {
+                pub const MAX: usize = 2;
+                pub struct Fixture {}
+            }
+
+            // Not on disk.
+            #[cfg(test)]
+            mod fuzz;
+            "#,
+        )
+        .unwrap();
+
+        let ctx = CrateContext::parse(dir.join("lib.rs")).unwrap();
+        let structs: Vec<String> = ctx.structs().map(|s| s.ident.to_string()).collect();
+        assert_eq!(structs, vec!["Vault", "Pool", "Oracle"]);
+        assert_eq!(ctx.consts().count(), 1);
+        assert_eq!(ctx.modules().count(), 1);
+    }
+
+    #[test]
+    fn parse_cache() {
+        let dir = std::env::temp_dir().join("anchor_syn_context_cache");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lib.rs"), "mod state;").unwrap();
+        std::fs::write(dir.join("state.rs"), "pub struct Vault {}").unwrap();
+
+        let mut cache = ParseCache::new();
+        let ctx = CrateContext::parse_with_cache(dir.join("lib.rs"), &mut cache).unwrap();
+        assert_eq!(ctx.structs().next().unwrap().ident, "Vault");
+        assert_eq!(cache.len(), 2);
+
+        // Changed files are parsed again.
+        std::fs::write(dir.join("state.rs"), "pub struct Pool {}").unwrap();
+        let ctx = CrateContext::parse_with_cache(dir.join("lib.rs"), &mut cache).unwrap();
+        assert_eq!(ctx.structs().next().unwrap().ident, "Pool");
+        assert_eq!(cache.len(), 2);
+
+        assert!(cache.invalidate(dir.join("state.rs")));
+        assert!(!cache.invalidate(dir.join("state.rs")));
+        assert_eq!(cache.len(), 1);
+    }
+}
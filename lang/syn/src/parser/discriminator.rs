@@ -0,0 +1,337 @@
+use crate::codegen::program::common::{sighash, SIGHASH_GLOBAL_NAMESPACE, SIGHASH_STATE_NAMESPACE};
+use crate::Program;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, Lit, Token};
+
+// Custom account discriminators.
+//
+// `#[account(discriminator = [1, 2, 3, 4])]` gives the bytes the data of an
+// account starts with, from 1 to 8 of them, e.g. to keep those of the accounts
+// of a program migrated to Anchor. `#[account(discriminator = "legacy_name")]`
+// derives the discriminator from another name than the struct's, e.g. to keep
+// the discriminator of a renamed account.
+//
+// `#[program(discriminator_len = 4)]` shortens the discriminators of the
+// instructions of a program, and those of all the accounts of its crate, to
+// their first bytes. `#[program]` declares the length as a constant at the
+// root of the crate, which the accounts take theirs from at compile time,
+// and the prelude of `anchor_lang` declares the default one for crates
+// without a program. Custom discriminator bytes, and those of zero copy
+// accounts, keep their length.
+
+// Maximum length of a discriminator.
+pub const MAX_LEN: usize = 8;
+
+// Parses the length of shortened discriminators, e.g. the `4` of
+// `discriminator_len = 4`.
+pub fn parse_len(expr: &Expr) -> syn::Result<usize> {
+    let len = match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse::<usize>()?,
+        _ => return Err(syn::Error::new_spanned(expr, "Expected a length")),
+    };
+    match (1..=MAX_LEN).contains(&len) {
+        true => Ok(len),
+        false => Err(syn::Error::new_spanned(
+            expr,
+            "The discriminator length must be from 1 to 8",
+        )),
+    }
+}
+
+pub enum AccountDiscriminator {
+    Bytes(Vec<u8>),
+    Name(String),
+}
+
+// Name of the constant holding the length of the discriminators of the
+// accounts of a crate, declared by `#[program]` at the root of the crate.
+pub const LEN_CONST: &str = "__DISCRIMINATOR_LEN";
+
+// Errors if the `#[account(..)]` attribute is given a discriminator length,
+// which is set for the whole program instead.
+pub fn reject_account_len(args: proc_macro2::TokenStream) -> syn::Result<()> {
+    let args = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(args)?;
+    for arg in args {
+        match arg {
+            Expr::Assign(assign) if matches!(&*assign.left, Expr::Path(path) if path.path.is_ident("discriminator_len")) => {
+                return Err(syn::Error::new_spanned(
+                    assign,
+                    "The discriminator length is set for the whole program, with `#[program(discriminator_len = ..)]`",
+                ))
+            }
+            _ => continue,
+        }
+    }
+    Ok(())
+}
+
+// Returns the `discriminator` argument of the `#[account(..)]` attribute.
+pub fn account_discriminator(
+    args: proc_macro2::TokenStream,
+) -> syn::Result<Option<AccountDiscriminator>> {
+    let args = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(args)?;
+    for arg in args {
+        let assign = match arg {
+            Expr::Assign(assign) => assign,
+            _ => continue,
+        };
+        if !matches!(&*assign.left, Expr::Path(path) if path.path.is_ident("discriminator")) {
+            continue;
+        }
+        return match &*assign.right {
+            Expr::Lit(syn::ExprLit {
+                lit: Lit::Str(name),
+                ..
+            }) => Ok(Some(AccountDiscriminator::Name(name.value()))),
+            Expr::Array(array) => {
+                let bytes = array
+                    .elems
+                    .iter()
+                    .map(|elem| match elem {
+                        Expr::Lit(syn::ExprLit {
+                            lit: Lit::Int(int), ..
+                        }) => int.base10_parse::<u8>(),
+                        _ => Err(syn::Error::new_spanned(elem, "Expected a byte")),
+                    })
+                    .collect::<syn::Result<Vec<u8>>>()?;
+                if bytes.is_empty() || bytes.len() > MAX_LEN {
+                    return Err(syn::Error::new_spanned(
+                        array,
+                        "A discriminator must be from 1 to 8 bytes",
+                    ));
+                }
+                Ok(Some(AccountDiscriminator::Bytes(bytes)))
+            }
+            right => Err(syn::Error::new_spanned(
+                right,
+                "Expected the bytes of the discriminator, e.g. `[1, 2, 3, 4]`, or the name to derive it from",
+            )),
+        };
+    }
+    Ok(None)
+}
+
+// Returns the namespace given to the `#[account(..)]` attribute, empty if
+// none, i.e. its argument that isn't named.
+pub fn account_namespace(args: proc_macro2::TokenStream) -> syn::Result<String> {
+    let args = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(args)?;
+    let mut namespace = String::new();
+    for arg in args {
+        match arg {
+            Expr::Assign(_) => {}
+            Expr::Path(path) if path.path.is_ident("checksum") => {}
+            Expr::Lit(syn::ExprLit {
+                lit: Lit::Str(ns), ..
+            }) => namespace = ns.value(),
+            arg => {
+                namespace = crate::parser::tts_to_string(&arg)
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect()
+            }
+        }
+    }
+    Ok(namespace)
+}
+
+// The discriminator of an account, the custom bytes if given, or else the
+// first `len` bytes of the hash of its namespaced name, or of the custom name
+// it's derived from.
+pub fn account_bytes(
+    ident: &syn::Ident,
+    namespace: &str,
+    custom: Option<&AccountDiscriminator>,
+    len: usize,
+) -> Vec<u8> {
+    // Namespace the discriminator to prevent collisions.
+    let namespace = match namespace {
+        "" => "account",
+        namespace => namespace,
+    };
+    match custom {
+        Some(AccountDiscriminator::Bytes(bytes)) => bytes.clone(),
+        Some(AccountDiscriminator::Name(name)) => sighash(namespace, name)[..len].to_vec(),
+        None => sighash(namespace, &ident.to_string())[..len].to_vec(),
+    }
+}
+
+// The 8 bytes tags the dispatcher tests the instruction data for before the
+// discriminators of the program, i.e. `anchor_lang::idl::IDL_IX_TAG` and
+// `anchor_lang::event::EVENT_IX_TAG`, in little endian.
+fn reserved_tags() -> [(&'static str, [u8; 8]); 2] {
+    [
+        ("IDL instructions", 0x0a69e9a778bcf440u64.to_le_bytes()),
+        (
+            "events emitted with `emit_cpi!`",
+            0x1d9acb512ea545e4u64.to_le_bytes(),
+        ),
+    ]
+}
+
+// Errors if two instructions of a program have the same discriminator, or if
+// the data of one could start with the tag of the instructions the dispatcher
+// handles first, which shortened discriminators make likely. The accounts,
+// which `#[program]` doesn't see outside of its module, are checked over the
+// whole crate with the IDL, see `check_accounts`.
+pub fn check_collisions(program: &Program) -> syn::Result<()> {
+    let mut ixs = vec![];
+    if let Some(state) = &program.state {
+        if let Some((ctor, _)) = &state.ctor_and_anchor {
+            ixs.push((
+                ctor.sig.ident.clone(),
+                sighash(SIGHASH_STATE_NAMESPACE, "new"),
+            ));
+        }
+        if let Some((_, methods)) = &state.impl_block_and_methods {
+            for method in methods {
+                ixs.push((
+                    method.ident.clone(),
+                    sighash(SIGHASH_STATE_NAMESPACE, &method.ident.to_string()),
+                ));
+            }
+        }
+        for iface in state.interfaces.iter().flatten() {
+            for method in &iface.methods {
+                ixs.push((
+                    method.ident.clone(),
+                    sighash(&iface.trait_name, &method.ident.to_string()),
+                ));
+            }
+        }
+    }
+    for ix in &program.ixs {
+        ixs.push((
+            ix.raw_method.sig.ident.clone(),
+            sighash(ix.sighash_namespace(), &ix.ident.to_string()),
+        ));
+    }
+    if !crate::parser::batch::batched_ixs(&program.ixs).is_empty() {
+        ixs.push((
+            syn::Ident::new(crate::parser::batch::BATCH_IX_NAME, program.name.span()),
+            sighash(
+                SIGHASH_GLOBAL_NAMESPACE,
+                crate::parser::batch::BATCH_IX_NAME,
+            ),
+        ));
+    }
+    let ixs: Vec<_> = ixs
+        .into_iter()
+        .map(|(ident, sighash)| (ident, sighash[..program.discriminator_len].to_vec()))
+        .collect();
+    check_unique("Instructions", &ixs).map_err(|(i, msg)| syn::Error::new(ixs[i].0.span(), msg))?;
+    for (ident, bytes) in &ixs {
+        for (handled, tag) in reserved_tags() {
+            if tag.starts_with(bytes) {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!(
+                        "The discriminator of instruction `{}`, {:?}, is the start of the tag of {}, {:?}",
+                        ident, bytes, handled, tag
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Errors if the discriminator of an account of a crate starts with another's,
+// given the accounts of the whole crate along with their discriminators.
+pub fn check_accounts(accounts: &[(String, Vec<u8>)]) -> Result<(), String> {
+    check_unique("Accounts", accounts).map_err(|(_, msg)| msg)
+}
+
+// Errors with the index of the first discriminator starting with a previous
+// one, or being the start of it.
+fn check_unique<T: std::fmt::Display>(
+    kind: &str,
+    discriminators: &[(T, Vec<u8>)],
+) -> Result<(), (usize, String)> {
+    for (i, (name, bytes)) in discriminators.iter().enumerate() {
+        let other = discriminators[..i]
+            .iter()
+            .find(|(_, other)| other.starts_with(bytes) || bytes.starts_with(other));
+        if let Some((other, other_bytes)) = other {
+            return Err((
+                i,
+                format!(
+                    "{} `{}` and `{}` have colliding discriminators, {:?} and {:?}",
+                    kind, other, name, other_bytes, bytes
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colliding_discriminators() {
+        // Two instructions whose discriminators share their first byte.
+        let names: Vec<String> = (0..).map(|i| format!("ix_{}", i)).take(64).collect();
+        let (first, second) = names
+            .iter()
+            .enumerate()
+            .find_map(|(i, name)| {
+                let byte = sighash(SIGHASH_GLOBAL_NAMESPACE, name)[0];
+                names[..i]
+                    .iter()
+                    .find(|other| sighash(SIGHASH_GLOBAL_NAMESPACE, other)[0] == byte)
+                    .map(|other| (other, name))
+            })
+            .unwrap();
+        let handlers = format!(
+            "pub mod vault {{
+                pub fn {}(ctx: Context<Ix>) -> Result<()> {{ Ok(()) }}
+                pub fn {}(ctx: Context<Ix>) -> Result<()> {{ Ok(()) }}
+            }}",
+            first, second
+        );
+        let mut program: Program = syn::parse_str(&handlers).unwrap();
+        check_collisions(&program).unwrap();
+        program.discriminator_len = 1;
+        let err = check_collisions(&program).unwrap_err().to_string();
+        assert!(err.starts_with(&format!(
+            "Instructions `{}` and `{}` have colliding discriminators",
+            first, second
+        )));
+
+        // An instruction whose discriminator is the start of the IDL tag.
+        let name = (0..)
+            .map(|i| format!("ix_{}", i))
+            .find(|name| sighash(SIGHASH_GLOBAL_NAMESPACE, name)[0] == reserved_tags()[0].1[0])
+            .unwrap();
+        let handler = format!(
+            "pub mod vault {{
+                pub fn {}(ctx: Context<Ix>) -> Result<()> {{ Ok(()) }}
+            }}",
+            name
+        );
+        let mut program: Program = syn::parse_str(&handler).unwrap();
+        check_collisions(&program).unwrap();
+        program.discriminator_len = 1;
+        let err = check_collisions(&program).unwrap_err().to_string();
+        assert!(err.starts_with(&format!(
+            "The discriminator of instruction `{}`, {:?}, is the start of the tag of IDL instructions",
+            name,
+            &reserved_tags()[0].1[..1]
+        )));
+
+        let accounts = vec![
+            ("Pool".to_string(), vec![1]),
+            ("Counter".to_string(), vec![2, 3, 4, 5]),
+            ("Position".to_string(), vec![1, 2]),
+        ];
+        assert_eq!(
+            check_accounts(&accounts).unwrap_err(),
+            "Accounts `Pool` and `Position` have colliding discriminators, [1] and [1, 2]"
+        );
+        check_accounts(&accounts[..2]).unwrap();
+    }
+}
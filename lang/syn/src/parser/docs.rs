@@ -1,28 +1,271 @@
 use syn::{Lit::Str, Meta::NameValue};
 
 // returns vec of doc strings
+//
+// Each element is a single line of markdown. Paragraphs are separated by an
+// empty line, indentation inside code fences is preserved and intra-doc links
+// are rewritten to plain names, since they can't be resolved outside rustdoc.
 pub fn parse(attrs: &[syn::Attribute]) -> Option<Vec<String>> {
-    let doc_strings: Vec<String> = attrs
+    let raw_lines: Vec<String> = attrs
         .iter()
         .filter_map(|attr| match attr.parse_meta() {
             Ok(NameValue(meta)) => {
                 if meta.path.is_ident("doc") {
                     if let Str(doc) = meta.lit {
-                        let val = doc.value().trim().to_string();
-                        if val.starts_with("CHECK:") {
-                            return None;
-                        }
-                        return Some(val);
+                        return Some(doc.value());
                     }
                 }
                 None
             }
             _ => None,
         })
+        // Block comments (`/** */`) are a single attribute with many lines.
+        .flat_map(|doc| {
+            doc.split('\n')
+                .map(|line| line.trim_end().to_string())
+                .collect::<Vec<_>>()
+        })
         .collect();
+
+    let doc_strings = format(&unindent(&raw_lines));
     if doc_strings.is_empty() {
         None
     } else {
         Some(doc_strings)
     }
 }
+
+// Removes the `CHECK:` comments justifying the safety of unchecked accounts,
+// each up to the end of its paragraph, from parsed docs. Returns `None` if
+// nothing else is left.
+pub fn without_check_comments(lines: &[String]) -> Option<Vec<String>> {
+    let mut doc_strings: Vec<String> = vec![];
+    let mut in_check = false;
+    for line in lines {
+        if line.is_empty() {
+            in_check = false;
+            if doc_strings.last().map(|l| l.is_empty()).unwrap_or(true) {
+                continue;
+            }
+        } else if line.trim_start().starts_with("CHECK:") {
+            in_check = true;
+        }
+        if !in_check {
+            doc_strings.push(line.clone());
+        }
+    }
+    while doc_strings.last().map(|l| l.is_empty()).unwrap_or(false) {
+        doc_strings.pop();
+    }
+    (!doc_strings.is_empty()).then(|| doc_strings)
+}
+
+// Removes the indentation common to all non empty lines, e.g. the space
+// following `///`.
+fn unindent(lines: &[String]) -> Vec<String> {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_default().to_string())
+        .collect()
+}
+
+// Collapses runs of empty lines into a single paragraph break and rewrites
+// intra-doc links outside of code fences.
+fn format(lines: &[String]) -> Vec<String> {
+    let mut doc_strings: Vec<String> = vec![];
+    let mut in_code_block = false;
+    for line in lines {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            doc_strings.push(line.clone());
+            continue;
+        }
+        if in_code_block {
+            doc_strings.push(line.clone());
+            continue;
+        }
+        if line.is_empty() {
+            if doc_strings.last().map(|l| l.is_empty()).unwrap_or(true) {
+                continue;
+            }
+            doc_strings.push(String::new());
+            continue;
+        }
+        // Link reference definitions, e.g. `[Foo]: crate::Foo`, only make
+        // sense to rustdoc when pointing to an item.
+        if let Some((_, target)) = parse_link_definition(line) {
+            if is_item_path(target) {
+                continue;
+            }
+        }
+        doc_strings.push(rewrite_links(line));
+    }
+    while doc_strings.last().map(|l| l.is_empty()).unwrap_or(false) {
+        doc_strings.pop();
+    }
+    doc_strings
+}
+
+fn parse_link_definition(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start().strip_prefix('[')?;
+    let (label, rest) = line.split_once("]:")?;
+    Some((label, rest.trim()))
+}
+
+// Rewrites intra-doc links to their plain names:
+//
+// * `` [`Foo`] `` and `` [`crate::Foo`] `` become `` `Foo` ``.
+// * `[text](crate::Foo)` and `[text][crate::Foo]` become `text`.
+//
+// Links to URLs and anything that isn't an item path (e.g. `[u8; 32]`) are
+// left untouched.
+fn rewrite_links(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        out.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+        match rewrite_link(candidate) {
+            Some((plain, consumed)) => {
+                out.push_str(&plain);
+                rest = &candidate[consumed..];
+            }
+            None => {
+                out.push('[');
+                rest = &candidate[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Given a string starting with `[`, returns the plain text of the link and the
+// number of bytes it spans, if it is an intra-doc link.
+fn rewrite_link(s: &str) -> Option<(String, usize)> {
+    let end = s.find(']')?;
+    let text = &s[1..end];
+    let after = &s[end + 1..];
+
+    // `[text](target)` and `[text][target]`.
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        if let Some(target) = after.strip_prefix(open) {
+            let target_end = target.find(close)?;
+            return is_item_path(&target[..target_end])
+                .then(|| (text.to_string(), end + 1 + target_end + 2));
+        }
+    }
+
+    // `[Foo]` and `` [`Foo`] ``.
+    let (ticks, path) = match text.strip_prefix('`').and_then(|t| t.strip_suffix('`')) {
+        Some(path) => ("`", path),
+        None => ("", text),
+    };
+    is_item_path(path).then(|| (format!("{0}{1}{0}", ticks, item_name(path)), end + 1))
+}
+
+fn is_item_path(target: &str) -> bool {
+    let path = strip_disambiguator(target.trim());
+    let path = path
+        .strip_suffix("()")
+        .or_else(|| path.strip_suffix('!'))
+        .unwrap_or(path);
+    !path.is_empty()
+        && path.split("::").all(|segment| {
+            let mut chars = segment.chars();
+            matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+                && chars.all(|c| c.is_alphanumeric() || c == '_')
+        })
+}
+
+// The last segment of the path, e.g. `Foo` for `crate::state::Foo`.
+fn item_name(path: &str) -> &str {
+    let path = strip_disambiguator(path.trim());
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+// Strips rustdoc disambiguators, e.g. `struct@Foo`.
+fn strip_disambiguator(path: &str) -> &str {
+    path.split_once('@').map(|(_, path)| path).unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn docs(src: &str) -> Option<Vec<String>> {
+        let item: syn::ItemStruct = syn::parse_str(src).unwrap();
+        parse(&item.attrs)
+    }
+
+    #[test]
+    fn preserves_markdown() {
+        let src = r#"
+            /// Creates a [`Vault`] for the [owner](crate::state::Owner).
+            ///
+            ///
+            /// See [`crate::instructions::deposit`] and the [docs](https://www.anchor-lang.com).
+            ///
+            /// ```
+            /// let x = [1u8; 32];
+            ///     indented();
+            /// ```
+            ///
+            /// [`Vault`]: crate::state::Vault
+            struct Foo;
+        "#;
+        assert_eq!(
+            docs(src).unwrap(),
+            vec![
+                "Creates a `Vault` for the owner.",
+                "",
+                "See `deposit` and the [docs](https://www.anchor-lang.com).",
+                "",
+                "```",
+                "let x = [1u8; 32];",
+                "    indented();",
+                "```",
+            ]
+        );
+    }
+
+    #[test]
+    fn block_comments() {
+        let src = r#"
+            /**
+             * Not a list.
+             */
+            struct Foo;
+        "#;
+        assert_eq!(docs(src).unwrap(), vec!["* Not a list."]);
+    }
+
+    #[test]
+    fn check_comments() {
+        let src = r#"
+            /// The vault.
+            ///
+            /// CHECK: the owner is checked by the
+            /// token program.
+            ///
+            /// Holds the fees.
+            struct Foo;
+        "#;
+        let docs = docs(src).unwrap();
+        assert_eq!(docs[2], "CHECK: the owner is checked by the");
+        assert_eq!(
+            without_check_comments(&docs).unwrap(),
+            vec!["The vault.", "", "Holds the fees."]
+        );
+        assert_eq!(
+            without_check_comments(&["CHECK: unchecked".to_string()]),
+            None
+        );
+    }
+}
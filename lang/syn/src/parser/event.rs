@@ -0,0 +1,43 @@
+use syn::punctuated::Punctuated;
+use syn::Token;
+
+// Arguments of the `#[index]` attribute of an event field.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct IndexArgs {
+    // The field identifies a single event, e.g. `#[index(unique)]` for an
+    // order id, instead of grouping them.
+    pub unique: bool,
+}
+
+// Parses the `#[index]` attribute of an event field, if any. It can be given
+// anywhere in the attribute list, e.g. after the docs.
+pub fn index(attrs: &[syn::Attribute]) -> syn::Result<Option<IndexArgs>> {
+    let mut indexes = attrs.iter().filter(|attr| attr.path.is_ident("index"));
+    let attr = match indexes.next() {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+    if let Some(duplicate) = indexes.next() {
+        return Err(syn::Error::new_spanned(
+            duplicate,
+            "Duplicate `#[index]` attribute",
+        ));
+    }
+    if attr.tokens.is_empty() {
+        return Ok(Some(IndexArgs::default()));
+    }
+    let idents = attr.parse_args_with(Punctuated::<syn::Ident, Token![,]>::parse_terminated)?;
+    let mut parsed = IndexArgs::default();
+    for ident in idents {
+        match ident.to_string().as_str() {
+            "unique" => parsed.unique = true,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "Unknown index argument, expected `unique`",
+                ))
+            }
+        }
+    }
+    Ok(Some(parsed))
+}
@@ -0,0 +1,123 @@
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, Lit, Token};
+
+// Payloads defined outside of the program and consumed by its instructions,
+// e.g. the payload of a Wormhole VAA, described by `#[external_message(..)]`:
+//
+// #[external_message(
+//     format = "wormhole_vaa",
+//     emitter_chain = 2,
+//     emitter = "0x3ee18b2214aff97000d974cf647e7c347e8fa585",
+//     account = posted_vaa,
+//     owner = "worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth",
+//     instructions = [complete_transfer],
+// )]
+//
+// `account` names the account of the instructions holding the verified
+// message, and `owner` the program it must be owned by, e.g. the bridge's.
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ExternalMessageArgs {
+    pub format: Option<String>,
+    pub emitter_chain: Option<u16>,
+    pub emitter: Option<String>,
+    pub account: Option<String>,
+    pub owner: Option<String>,
+    pub instructions: Vec<String>,
+}
+
+pub fn parse_args(args: proc_macro2::TokenStream) -> syn::Result<ExternalMessageArgs> {
+    let args = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(args)?;
+    let mut parsed = ExternalMessageArgs::default();
+    for arg in args {
+        let assign = match &arg {
+            Expr::Assign(assign) => assign,
+            _ => return Err(syn::Error::new_spanned(arg, "Expected `<name> = <value>`")),
+        };
+        let value = &*assign.right;
+        match ident(&assign.left).as_deref() {
+            Some("format") => parsed.format = Some(string(value)?),
+            Some("emitter_chain") => parsed.emitter_chain = Some(int(value)?),
+            Some("emitter") => parsed.emitter = Some(string(value)?),
+            Some("account") => parsed.account = Some(path_ident(value)?),
+            Some("owner") => parsed.owner = Some(string(value)?),
+            Some("instructions") => {
+                parsed.instructions = match value {
+                    Expr::Array(array) => array
+                        .elems
+                        .iter()
+                        .map(path_ident)
+                        .collect::<syn::Result<_>>()?,
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            value,
+                            "Expected the instructions, e.g. `[redeem]`",
+                        ))
+                    }
+                }
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &assign.left,
+                    "Unknown external message argument, expected `format`, `emitter_chain`, \
+                     `emitter`, `account`, `owner` or `instructions`",
+                ))
+            }
+        }
+    }
+    if parsed.owner.is_some() && parsed.account.is_none() {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "`owner` is the owner of the message `account`, which must be given",
+        ));
+    }
+    if parsed.emitter_chain.is_some() && parsed.emitter.is_none() {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "`emitter_chain` is the chain of the `emitter`, which must be given",
+        ));
+    }
+    Ok(parsed)
+}
+
+// Parses the `#[external_message(..)]` attribute of a struct, if any.
+pub fn args(attrs: &[syn::Attribute]) -> syn::Result<Option<ExternalMessageArgs>> {
+    match attrs
+        .iter()
+        .find(|attr| attr.path.segments.last().unwrap().ident == "external_message")
+    {
+        Some(attr) if attr.tokens.is_empty() => Ok(Some(ExternalMessageArgs::default())),
+        Some(attr) => parse_args(attr.parse_args()?).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn ident(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Path(path) => path.path.get_ident().map(ToString::to_string),
+        _ => None,
+    }
+}
+
+fn path_ident(expr: &Expr) -> syn::Result<String> {
+    ident(expr).ok_or_else(|| syn::Error::new_spanned(expr, "Expected a name"))
+}
+
+fn int(expr: &Expr) -> syn::Result<u16> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse(),
+        _ => Err(syn::Error::new_spanned(expr, "Expected an integer")),
+    }
+}
+
+fn string(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Str(s), ..
+        }) => Ok(s.value()),
+        _ => Err(syn::Error::new_spanned(expr, "Expected a string")),
+    }
+}
@@ -0,0 +1,49 @@
+use syn::punctuated::Punctuated;
+use syn::{LitStr, Token};
+
+// Former names of struct fields and instruction arguments, recorded in the
+// IDL as their aliases, for clients written against the former names to keep
+// working after a rename:
+//
+// #[account]
+// pub struct Pool {
+//     #[idl_name("feeBps")]
+//     pub fee_rate_bps: u16,
+// }
+//
+// pub fn set_fee(ctx: Context<SetFee>, #[idl_name("fee")] fee_bps: u16) -> Result<()>
+//
+// The names are the ones of the IDL, i.e. camel case.
+
+// Parses the `#[idl_name(..)]` attributes of a field or an argument.
+pub fn parse(attrs: &[syn::Attribute]) -> syn::Result<Vec<String>> {
+    let mut aliases: Vec<String> = vec![];
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("idl_name")) {
+        let names = attr.parse_args_with(Punctuated::<LitStr, Token![,]>::parse_terminated)?;
+        if names.is_empty() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "Expected the former name, e.g. `#[idl_name(\"oldName\")]`",
+            ));
+        }
+        for name in names {
+            if syn::parse_str::<syn::Ident>(&name.value()).is_err() {
+                return Err(syn::Error::new_spanned(
+                    &name,
+                    "#[idl_name] must be an identifier",
+                ));
+            }
+            if aliases.contains(&name.value()) {
+                return Err(syn::Error::new_spanned(&name, "Duplicate #[idl_name]"));
+            }
+            aliases.push(name.value());
+        }
+    }
+    Ok(aliases)
+}
+
+// Removes the `#[idl_name(..)]` attributes, which aren't valid Rust once the
+// item is expanded.
+pub fn strip(attrs: &mut Vec<syn::Attribute>) {
+    attrs.retain(|attr| !attr.path.is_ident("idl_name"));
+}
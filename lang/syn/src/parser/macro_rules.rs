@@ -0,0 +1,572 @@
+use proc_macro2::{token_stream, Delimiter, Group, Ident, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use syn::ext::IdentExt;
+use syn::parse::discouraged::Speculative;
+use syn::parse::{ParseStream, Parser};
+use syn::Token;
+
+// Expansion of the macros invoked among the items of a module, so that the
+// items they generate can be parsed before the compiler expands them, e.g.
+// the handlers of the `#[program]` module.
+//
+// Only the macros defined with `macro_rules!` in the module, before being
+// invoked, are expanded, e.g.
+//
+// macro_rules! deposit {
+//     ($name:ident, $accounts:ident) => {
+//         pub fn $name(ctx: Context<$accounts>, amount: u64) -> Result<()> {
+//             ...
+//         }
+//     };
+// }
+// deposit!(deposit_usdc, DepositUsdc);
+//
+// Other macros, e.g. `paste!` or `cfg_if!`, or the ones defined outside of
+// the module, can't be expanded here, and are reported as errors since the
+// items they generate would silently be missing.
+
+// Maximum depth of macros expanding to invocations of macros.
+const RECURSION_LIMIT: usize = 64;
+
+const FRAGMENTS: &[&str] = &[
+    "block",
+    "expr",
+    "ident",
+    "item",
+    "lifetime",
+    "literal",
+    "meta",
+    "pat",
+    "pat_param",
+    "path",
+    "stmt",
+    "tt",
+    "ty",
+    "vis",
+];
+
+// The items of a module, with the invocations of its `macro_rules!` macros
+// replaced by the items they expand to.
+pub fn expand(items: &[syn::Item]) -> syn::Result<Vec<syn::Item>> {
+    let mut macros = HashMap::new();
+    let mut expanded = Vec::new();
+    let mut errors = Vec::new();
+    expand_items(items, &mut macros, &mut expanded, &mut errors, 0);
+    match errors.into_iter().reduce(|mut errors, error| {
+        errors.combine(error);
+        errors
+    }) {
+        Some(errors) => Err(errors),
+        None => Ok(expanded),
+    }
+}
+
+fn expand_items(
+    items: &[syn::Item],
+    macros: &mut HashMap<String, MacroRules>,
+    expanded: &mut Vec<syn::Item>,
+    errors: &mut Vec<syn::Error>,
+    depth: usize,
+) {
+    for item in items {
+        let item_macro = match item {
+            syn::Item::Macro(item_macro) => item_macro,
+            _ => {
+                expanded.push(item.clone());
+                continue;
+            }
+        };
+        if let (true, Some(ident)) = (
+            item_macro.mac.path.is_ident("macro_rules"),
+            &item_macro.ident,
+        ) {
+            match MacroRules::parse(item_macro.mac.tokens.clone()) {
+                Ok(rules) => {
+                    macros.insert(ident.to_string(), rules);
+                }
+                Err(e) => errors.push(e),
+            }
+            expanded.push(item.clone());
+            continue;
+        }
+
+        let path = item_macro
+            .mac
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>()
+            .join("::");
+        let rules = match macros.get(&path) {
+            Some(rules) => rules,
+            None => {
+                errors.push(syn::Error::new_spanned(
+                    &item_macro.mac,
+                    format!(
+                        "Unable to expand `{}!`, the items it generates would be missing. Only \
+                         the macros defined with `macro_rules!` in this module, before their \
+                         invocation, are expanded, so define it there or write out its items",
+                        path
+                    ),
+                ));
+                continue;
+            }
+        };
+        if depth == RECURSION_LIMIT {
+            errors.push(syn::Error::new_spanned(
+                &item_macro.mac,
+                format!("Recursion limit reached while expanding `{}!`", path),
+            ));
+            continue;
+        }
+        let items = rules
+            .expand(&item_macro.mac)
+            .and_then(|tokens| Parser::parse2(parse_items, tokens));
+        match items {
+            Ok(items) => expand_items(&items, macros, expanded, errors, depth + 1),
+            Err(e) => errors.push(e),
+        }
+    }
+}
+
+fn parse_items(input: ParseStream) -> syn::Result<Vec<syn::Item>> {
+    let mut items = Vec::new();
+    while !input.is_empty() {
+        items.push(input.parse()?);
+    }
+    Ok(items)
+}
+
+#[derive(Clone)]
+struct MacroRules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone)]
+struct Rule {
+    matcher: Vec<Matcher>,
+    transcriber: TokenStream,
+}
+
+#[derive(Clone)]
+enum Matcher {
+    Token(TokenTree),
+    Group(Delimiter, Vec<Matcher>),
+    // `$name:kind`.
+    Fragment(Ident, Ident),
+    // `$(..) sep op`.
+    Repetition(Vec<Matcher>, Option<TokenTree>, char),
+}
+
+#[derive(Clone)]
+enum Binding {
+    One(TokenStream),
+    // The bindings of each repetition.
+    Seq(Vec<Binding>),
+}
+
+type Bindings = HashMap<String, Binding>;
+
+impl MacroRules {
+    fn parse(tokens: TokenStream) -> syn::Result<Self> {
+        let parse_rules = |input: ParseStream| {
+            let mut rules = Vec::new();
+            while !input.is_empty() {
+                let matcher: Group = input.parse()?;
+                input.parse::<Token![=>]>()?;
+                let transcriber: Group = input.parse()?;
+                rules.push(Rule {
+                    matcher: parse_matcher(matcher.stream())?,
+                    transcriber: transcriber.stream(),
+                });
+                if !input.is_empty() {
+                    input.parse::<Token![;]>()?;
+                }
+            }
+            Ok(rules)
+        };
+        Ok(Self {
+            rules: Parser::parse2(parse_rules, tokens)?,
+        })
+    }
+
+    // The tokens the invocation `mac` expands to, with the first rule it
+    // matches.
+    fn expand(&self, mac: &syn::Macro) -> syn::Result<TokenStream> {
+        for rule in &self.rules {
+            let mut bindings = Bindings::new();
+            let matched = Parser::parse2(
+                |input: ParseStream| match_seq(input, &rule.matcher, &mut bindings),
+                mac.tokens.clone(),
+            );
+            if matched.is_ok() {
+                return transcribe(rule.transcriber.clone(), &bindings);
+            }
+        }
+        Err(syn::Error::new_spanned(
+            mac,
+            "No rule of the macro matches this invocation",
+        ))
+    }
+}
+
+fn parse_matcher(tokens: TokenStream) -> syn::Result<Vec<Matcher>> {
+    let mut matchers = Vec::new();
+    let mut tts = tokens.into_iter().peekable();
+    while let Some(tt) = tts.next() {
+        match tt {
+            TokenTree::Punct(dollar) if dollar.as_char() == '$' => match tts.next() {
+                Some(TokenTree::Ident(name)) => match (tts.next(), tts.next()) {
+                    (Some(TokenTree::Punct(colon)), Some(TokenTree::Ident(kind)))
+                        if colon.as_char() == ':' =>
+                    {
+                        if !FRAGMENTS.contains(&kind.to_string().as_str()) {
+                            return Err(syn::Error::new(
+                                kind.span(),
+                                format!("Unknown fragment specifier `{}`", kind),
+                            ));
+                        }
+                        matchers.push(Matcher::Fragment(name, kind));
+                    }
+                    _ => {
+                        return Err(syn::Error::new(
+                            name.span(),
+                            "Expected a fragment specifier, e.g. `$name:ident`",
+                        ))
+                    }
+                },
+                Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                    let inner = parse_matcher(group.stream())?;
+                    if inner.is_empty() {
+                        return Err(syn::Error::new(
+                            group.span(),
+                            "Repetition matches an empty token tree",
+                        ));
+                    }
+                    let (separator, op) = repetition(&mut tts, &group)?;
+                    matchers.push(Matcher::Repetition(inner, separator, op));
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        dollar.span(),
+                        "Expected a fragment or a repetition after `$`",
+                    ))
+                }
+            },
+            TokenTree::Group(group) => matchers.push(Matcher::Group(
+                group.delimiter(),
+                parse_matcher(group.stream())?,
+            )),
+            tt => matchers.push(Matcher::Token(tt)),
+        }
+    }
+    Ok(matchers)
+}
+
+// The separator and operator following the repetition `group`, e.g. `,` and
+// `*` for `$($name:ident),*`.
+fn repetition(
+    tts: &mut Peekable<token_stream::IntoIter>,
+    group: &Group,
+) -> syn::Result<(Option<TokenTree>, char)> {
+    let op = |tt: Option<&TokenTree>| match tt {
+        Some(TokenTree::Punct(punct)) if matches!(punct.as_char(), '*' | '+' | '?') => {
+            Some(punct.as_char())
+        }
+        _ => None,
+    };
+    if let Some(op) = op(tts.peek()) {
+        tts.next();
+        return Ok((None, op));
+    }
+    let separator = tts.next();
+    match op(tts.peek()) {
+        Some(op) if separator.is_some() => {
+            tts.next();
+            Ok((separator, op))
+        }
+        _ => Err(syn::Error::new(
+            group.span(),
+            "Expected a repetition operator, `*`, `+` or `?`",
+        )),
+    }
+}
+
+fn match_seq(input: ParseStream, matchers: &[Matcher], bindings: &mut Bindings) -> syn::Result<()> {
+    for matcher in matchers {
+        match matcher {
+            Matcher::Token(expected) => {
+                let tt: TokenTree = input.parse()?;
+                if tt.to_string() != expected.to_string() {
+                    return Err(syn::Error::new(
+                        tt.span(),
+                        format!("Expected `{}`", expected),
+                    ));
+                }
+            }
+            Matcher::Group(delimiter, inner) => {
+                let group: Group = input.parse()?;
+                if group.delimiter() != *delimiter {
+                    return Err(syn::Error::new(group.span(), "Unexpected delimiter"));
+                }
+                Parser::parse2(
+                    |input: ParseStream| match_seq(input, inner, bindings),
+                    group.stream(),
+                )?;
+            }
+            Matcher::Fragment(name, kind) => {
+                let tokens = fragment(input, kind)?;
+                bindings.insert(name.to_string(), Binding::One(tokens));
+            }
+            Matcher::Repetition(inner, separator, op) => {
+                let mut repetitions: Vec<Bindings> = Vec::new();
+                while !input.is_empty() && (*op != '?' || repetitions.is_empty()) {
+                    let fork = input.fork();
+                    if let (false, Some(separator)) = (repetitions.is_empty(), separator) {
+                        match fork.parse::<TokenTree>() {
+                            Ok(tt) if tt.to_string() == separator.to_string() => {}
+                            _ => break,
+                        }
+                    }
+                    let mut repetition = Bindings::new();
+                    if match_seq(&fork, inner, &mut repetition).is_err() {
+                        break;
+                    }
+                    input.advance_to(&fork);
+                    repetitions.push(repetition);
+                }
+                if *op == '+' && repetitions.is_empty() {
+                    return Err(input.error("Expected at least one repetition"));
+                }
+                for name in fragment_names(inner) {
+                    let seq = repetitions
+                        .iter()
+                        .map(|repetition| {
+                            repetition
+                                .get(&name)
+                                .cloned()
+                                .unwrap_or(Binding::Seq(Vec::new()))
+                        })
+                        .collect();
+                    bindings.insert(name, Binding::Seq(seq));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn fragment(input: ParseStream, kind: &Ident) -> syn::Result<TokenStream> {
+    let tokens = match kind.to_string().as_str() {
+        "block" => input.parse::<syn::Block>()?.into_token_stream(),
+        // Expressions are kept grouped, for their precedence to be the same
+        // wherever they're substituted.
+        "expr" => {
+            let expr = input.parse::<syn::Expr>()?;
+            Group::new(Delimiter::None, expr.into_token_stream()).into_token_stream()
+        }
+        "ident" => input.call(Ident::parse_any)?.into_token_stream(),
+        "item" => input.parse::<syn::Item>()?.into_token_stream(),
+        "lifetime" => input.parse::<syn::Lifetime>()?.into_token_stream(),
+        "literal" => input.parse::<syn::Lit>()?.into_token_stream(),
+        "meta" => input.parse::<syn::Meta>()?.into_token_stream(),
+        "pat" | "pat_param" => input.parse::<syn::Pat>()?.into_token_stream(),
+        "path" => input.parse::<syn::Path>()?.into_token_stream(),
+        "stmt" => input.parse::<syn::Stmt>()?.into_token_stream(),
+        "tt" => input.parse::<TokenTree>()?.into_token_stream(),
+        "ty" => input.parse::<syn::Type>()?.into_token_stream(),
+        "vis" => input.parse::<syn::Visibility>()?.into_token_stream(),
+        _ => unreachable!("Fragment specifiers are checked when parsing the macro"),
+    };
+    Ok(tokens)
+}
+
+fn fragment_names(matchers: &[Matcher]) -> Vec<String> {
+    matchers
+        .iter()
+        .flat_map(|matcher| match matcher {
+            Matcher::Token(_) => Vec::new(),
+            Matcher::Group(_, inner) | Matcher::Repetition(inner, _, _) => fragment_names(inner),
+            Matcher::Fragment(name, _) => vec![name.to_string()],
+        })
+        .collect()
+}
+
+fn transcribe(tokens: TokenStream, bindings: &Bindings) -> syn::Result<TokenStream> {
+    let mut transcribed = TokenStream::new();
+    let mut tts = tokens.into_iter().peekable();
+    while let Some(tt) = tts.next() {
+        let dollar = match tt {
+            TokenTree::Punct(dollar) if dollar.as_char() == '$' => dollar,
+            TokenTree::Group(group) => {
+                let mut transcribed_group =
+                    Group::new(group.delimiter(), transcribe(group.stream(), bindings)?);
+                transcribed_group.set_span(group.span());
+                transcribed.extend(Some(TokenTree::Group(transcribed_group)));
+                continue;
+            }
+            tt => {
+                transcribed.extend(Some(tt));
+                continue;
+            }
+        };
+        match tts.next() {
+            Some(TokenTree::Ident(ident)) if ident == "crate" => {
+                transcribed.extend(Some(TokenTree::Ident(Ident::new("crate", ident.span()))));
+            }
+            Some(TokenTree::Ident(ident)) => match bindings.get(&ident.to_string()) {
+                Some(Binding::One(tokens)) => transcribed.extend(tokens.clone()),
+                Some(Binding::Seq(_)) => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!("Variable `{}` is still repeating at this depth", ident),
+                    ))
+                }
+                None => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        format!("Unknown macro variable `{}`", ident),
+                    ))
+                }
+            },
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                let (separator, _) = repetition(&mut tts, &group)?;
+                let names = variables(group.stream());
+                let mut len = None;
+                for name in &names {
+                    if let Some(Binding::Seq(seq)) = bindings.get(name) {
+                        match len {
+                            Some(len) if len != seq.len() => {
+                                return Err(syn::Error::new(
+                                    group.span(),
+                                    "Variables repeat a different number of times",
+                                ))
+                            }
+                            _ => len = Some(seq.len()),
+                        }
+                    }
+                }
+                let len = len.ok_or_else(|| {
+                    syn::Error::new(
+                        group.span(),
+                        "Repetition without any variable repeating at this depth",
+                    )
+                })?;
+                for i in 0..len {
+                    if let (true, Some(separator)) = (i > 0, &separator) {
+                        transcribed.extend(Some(separator.clone()));
+                    }
+                    let mut repetition = bindings.clone();
+                    for name in &names {
+                        if let Some(Binding::Seq(seq)) = bindings.get(name) {
+                            repetition.insert(name.clone(), seq[i].clone());
+                        }
+                    }
+                    transcribed.extend(transcribe(group.stream(), &repetition)?);
+                }
+            }
+            tt => {
+                transcribed.extend(Some(TokenTree::Punct(dollar)));
+                transcribed.extend(tt);
+            }
+        }
+    }
+    Ok(transcribed)
+}
+
+// The names of the variables substituted in `tokens`.
+fn variables(tokens: TokenStream) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut tts = tokens.into_iter().peekable();
+    while let Some(tt) = tts.next() {
+        match tt {
+            TokenTree::Punct(dollar) if dollar.as_char() == '$' => {
+                if let Some(TokenTree::Ident(ident)) = tts.peek() {
+                    names.push(ident.to_string());
+                }
+            }
+            TokenTree::Group(group) => names.extend(variables(group.stream())),
+            _ => {}
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_macro_rules() {
+        let file: syn::File = syn::parse_quote! {
+            macro_rules! deposit {
+                ($($name:ident => $accounts:ident),* $(,)?) => {
+                    $(
+                        pub fn $name(ctx: Context<$accounts>, amount: u64) -> Result<()> {
+                            ctx.accounts.deposit(amount * 2)
+                        }
+                    )*
+                };
+            }
+            macro_rules! handlers {
+                ($($tt:tt)*) => { deposit!($($tt)*); };
+            }
+            handlers!(deposit_usdc => DepositUsdc, deposit_sol => DepositSol,);
+            pub fn withdraw(ctx: Context<Withdraw>) -> Result<()> {
+                Ok(())
+            }
+        };
+        let items = expand(&file.items).unwrap();
+        let fns = items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Fn(item_fn) => Some(item_fn.sig.ident.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(fns, ["deposit_usdc", "deposit_sol", "withdraw"]);
+        let deposit_usdc = items
+            .iter()
+            .find_map(|item| match item {
+                syn::Item::Fn(item_fn) if item_fn.sig.ident == "deposit_usdc" => Some(item_fn),
+                _ => None,
+            })
+            .unwrap();
+        let expected: syn::ItemFn = syn::parse_quote! {
+            pub fn deposit_usdc(ctx: Context<DepositUsdc>, amount: u64) -> Result<()> {
+                ctx.accounts.deposit(amount * 2)
+            }
+        };
+        assert_eq!(
+            deposit_usdc.to_token_stream().to_string(),
+            expected.to_token_stream().to_string()
+        );
+    }
+
+    #[test]
+    fn unexpandable_macros() {
+        let file: syn::File = syn::parse_quote! {
+            paste::paste! {
+                pub fn [<deposit_ usdc>](ctx: Context<Deposit>) -> Result<()> {
+                    Ok(())
+                }
+            }
+            handlers!();
+            macro_rules! handlers {
+                () => {};
+            }
+        };
+        let errors = expand(&file.items)
+            .err()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("Unable to expand `paste::paste!`"));
+        assert!(errors[1].starts_with("Unable to expand `handlers!`"));
+    }
+}
@@ -1,8 +1,26 @@
+pub mod access_control;
 pub mod accounts;
+pub mod arg_constraints;
+pub mod batch;
+pub mod checksum;
+pub mod compute_budget;
+pub mod constant;
 pub mod context;
+pub mod discriminator;
 pub mod docs;
 pub mod error;
+pub mod event;
+pub mod external_message;
+pub mod idl_name;
+pub mod macro_rules;
+pub mod permissions;
 pub mod program;
+pub mod safety;
+pub mod space;
+pub mod state_machine;
+pub mod sunset;
+pub mod version;
+pub mod zero_copy;
 
 pub fn tts_to_string<T: quote::ToTokens>(item: T) -> String {
     let mut tts = proc_macro2::TokenStream::new();
@@ -0,0 +1,31 @@
+use syn::{Lit, Meta};
+
+// Field level access control of accounts.
+//
+// `#[writable_by = "authority"]` on a field of an `#[account]` gives the
+// signer that may change it, i.e. the account every instruction writing the
+// field is expected to have signing it.
+
+// Parses the `#[writable_by = ".."]` attribute of a field, if any.
+pub fn writable_by(attrs: &[syn::Attribute]) -> syn::Result<Option<String>> {
+    let attr = match attrs.iter().find(|attr| attr.path.is_ident("writable_by")) {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+    let authority = match attr.parse_meta()? {
+        Meta::NameValue(syn::MetaNameValue {
+            lit: Lit::Str(s), ..
+        }) => s,
+        _ => return Err(syn::Error::new_spanned(
+            attr,
+            "Expected the signer that may write the field, e.g. `#[writable_by = \"authority\"]`",
+        )),
+    };
+    if syn::parse_str::<syn::Ident>(&authority.value()).is_err() {
+        return Err(syn::Error::new_spanned(
+            authority,
+            "#[writable_by] must name an account, e.g. \"authority\"",
+        ));
+    }
+    Ok(Some(authority.value()))
+}
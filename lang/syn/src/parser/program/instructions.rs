@@ -1,16 +1,23 @@
+use crate::codegen::program::common::SIGHASH_STATE_NAMESPACE;
+use crate::parser::arg_constraints;
 use crate::parser::docs;
+use crate::parser::idl_name;
+use crate::parser::macro_rules;
 use crate::parser::program::ctx_accounts_ident;
 use crate::{FallbackFn, Ix, IxArg, IxReturn};
+use heck::CamelCase;
 use syn::parse::{Error as ParseError, Result as ParseResult};
 use syn::spanned::Spanned;
 
-// Parse all non-state ix handlers from the program mod definition.
+// Parse all non-state ix handlers from the program mod definition, including
+// the ones generated by its `macro_rules!` macros.
 pub fn parse(program_mod: &syn::ItemMod) -> ParseResult<(Vec<Ix>, Option<FallbackFn>)> {
     let mod_content = &program_mod
         .content
         .as_ref()
         .ok_or_else(|| ParseError::new(program_mod.span(), "program content not provided"))?
         .1;
+    let mod_content = macro_rules::expand(mod_content)?;
 
     let ixs = mod_content
         .iter()
@@ -23,10 +30,18 @@ pub fn parse(program_mod: &syn::ItemMod) -> ParseResult<(Vec<Ix>, Option<Fallbac
             _ => None,
         })
         .map(|method: &syn::ItemFn| {
-            let (ctx, args) = parse_args(method)?;
+            let (ctx, mut args) = parse_args(method)?;
+            // Handlers' arguments follow the context.
+            for (arg, input) in args.iter_mut().zip(method.sig.inputs.iter().skip(1)) {
+                if let syn::FnArg::Typed(input) = input {
+                    arg.constraints = arg_constraints::parse(&input.attrs)?;
+                    arg.aliases = idl_name::parse(&input.attrs)?;
+                }
+            }
             let docs = docs::parse(&method.attrs);
             let returns = parse_return(method)?;
             let anchor_ident = ctx_accounts_ident(&ctx.raw_arg)?;
+            let namespace = parse_namespace(&method.attrs)?;
             Ok(Ix {
                 raw_method: method.clone(),
                 ident: method.sig.ident.clone(),
@@ -34,9 +49,11 @@ pub fn parse(program_mod: &syn::ItemMod) -> ParseResult<(Vec<Ix>, Option<Fallbac
                 args,
                 anchor_ident,
                 returns,
+                namespace,
             })
         })
         .collect::<ParseResult<Vec<Ix>>>()?;
+    check_names(&ixs)?;
 
     let fallback_fn = {
         let fallback_fns = mod_content
@@ -68,6 +85,71 @@ pub fn parse(program_mod: &syn::ItemMod) -> ParseResult<(Vec<Ix>, Option<Fallbac
     Ok((ixs, fallback_fn))
 }
 
+// Name of the generated enum over all the instructions.
+const PROGRAM_INSTRUCTION_NAME: &str = "ProgramInstruction";
+
+// Errors if the arguments struct of a handler, named after it in camel case,
+// would collide with the generated `ProgramInstruction` enum.
+fn check_names(ixs: &[Ix]) -> ParseResult<()> {
+    match ixs
+        .iter()
+        .find(|ix| ix.ident.to_string().to_camel_case() == PROGRAM_INSTRUCTION_NAME)
+    {
+        Some(ix) => Err(ParseError::new(
+            ix.ident.span(),
+            "The `ProgramInstruction` enum is generated over all the instructions, rename the handler",
+        )),
+        None => Ok(()),
+    }
+}
+
+// Parses the namespace of a handler's sighash, given by
+// `#[instruction(namespace = "admin")]`, hashing its name as `admin:<name>`
+// rather than `global:<name>`, e.g. to implement the instructions of an
+// interface whose names collide with the program's.
+fn parse_namespace(attrs: &[syn::Attribute]) -> ParseResult<Option<String>> {
+    let mut namespace = None;
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("instruction"))
+    {
+        let arg: syn::MetaNameValue = attr.parse_args()?;
+        if !arg.path.is_ident("namespace") {
+            return Err(ParseError::new(arg.path.span(), "Expected `namespace`"));
+        }
+        let value = match &arg.lit {
+            syn::Lit::Str(value) => value,
+            lit => return Err(ParseError::new(lit.span(), "Expected a string")),
+        };
+        let name = value.value();
+        if name.is_empty() || name.contains(':') {
+            return Err(ParseError::new(
+                value.span(),
+                "A namespace must be non empty, without `:`",
+            ));
+        }
+        if name == SIGHASH_STATE_NAMESPACE {
+            return Err(ParseError::new(
+                value.span(),
+                "The `state` namespace is reserved for the methods of the state",
+            ));
+        }
+        if namespace.replace(name).is_some() {
+            return Err(ParseError::new(
+                attr.span(),
+                "Only one namespace can be given",
+            ));
+        }
+    }
+    Ok(namespace)
+}
+
+// Removes the `#[instruction(..)]` attributes of a handler, parsed above,
+// which aren't valid Rust on functions.
+pub fn strip_namespace(attrs: &mut Vec<syn::Attribute>) {
+    attrs.retain(|attr| !attr.path.is_ident("instruction"));
+}
+
 pub fn parse_args(method: &syn::ItemFn) -> ParseResult<(IxArg, Vec<IxArg>)> {
     let mut args: Vec<IxArg> = method
         .sig
@@ -80,10 +162,15 @@ pub fn parse_args(method: &syn::ItemFn) -> ParseResult<(IxArg, Vec<IxArg>)> {
                     syn::Pat::Ident(ident) => &ident.ident,
                     _ => return Err(ParseError::new(arg.pat.span(), "expected argument name")),
                 };
+                let mut raw_arg = arg.clone();
+                arg_constraints::strip(&mut raw_arg.attrs);
+                idl_name::strip(&mut raw_arg.attrs);
                 Ok(IxArg {
                     name: ident.clone(),
                     docs,
-                    raw_arg: arg.clone(),
+                    constraints: vec![],
+                    aliases: vec![],
+                    raw_arg,
                 })
             }
             syn::FnArg::Receiver(_) => Err(ParseError::new(
@@ -6,10 +6,12 @@ use syn::spanned::Spanned;
 mod instructions;
 mod state;
 
-pub fn parse(program_mod: syn::ItemMod) -> ParseResult<Program> {
+pub fn parse(mut program_mod: syn::ItemMod) -> ParseResult<Program> {
     let state = state::parse(&program_mod)?;
     let docs = docs::parse(&program_mod.attrs);
     let (ixs, fallback_fn) = instructions::parse(&program_mod)?;
+    crate::parser::batch::check(&ixs)?;
+    strip_handler_attrs(&mut program_mod);
     Ok(Program {
         state,
         ixs,
@@ -17,9 +19,28 @@ pub fn parse(program_mod: syn::ItemMod) -> ParseResult<Program> {
         docs,
         program_mod,
         fallback_fn,
+        args: None,
+        discriminator_len: crate::parser::discriminator::MAX_LEN,
     })
 }
 
+// The `#[instruction(..)]` attributes of the handlers, and `#[arg(..)]` and
+// `#[idl_name(..)]` ones of their arguments, parsed above, aren't valid Rust.
+fn strip_handler_attrs(program_mod: &mut syn::ItemMod) {
+    let items = program_mod.content.iter_mut().flat_map(|(_, items)| items);
+    for item in items {
+        if let syn::Item::Fn(item_fn) = item {
+            instructions::strip_namespace(&mut item_fn.attrs);
+            for arg in item_fn.sig.inputs.iter_mut() {
+                if let syn::FnArg::Typed(arg) = arg {
+                    crate::parser::arg_constraints::strip(&mut arg.attrs);
+                    crate::parser::idl_name::strip(&mut arg.attrs);
+                }
+            }
+        }
+    }
+}
+
 fn ctx_accounts_ident(path_ty: &syn::PatType) -> ParseResult<proc_macro2::Ident> {
     let p = match &*path_ty.ty {
         syn::Type::Path(p) => &p.path,
@@ -189,6 +189,8 @@ pub fn parse(program_mod: &syn::ItemMod) -> ParseResult<Option<State>> {
                             Ok(IxArg {
                                 name: ident.clone(),
                                 docs,
+                                constraints: vec![],
+                                aliases: vec![],
                                 raw_arg: raw_arg.clone(),
                             })
                         })
@@ -267,6 +269,8 @@ pub fn parse(program_mod: &syn::ItemMod) -> ParseResult<Option<State>> {
                                             IxArg {
                                                 name: ident.clone(),
                                                 docs,
+                                                constraints: vec![],
+                                                aliases: vec![],
                                                 raw_arg: raw_arg.clone(),
                                             }
                                         })
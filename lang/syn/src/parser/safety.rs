@@ -0,0 +1,571 @@
+use crate::codegen::program::checked_math::{checked_method, macro_args};
+use crate::parser::context::{CrateContext, ModuleContext};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+
+/// A safety check of a program's source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    // `AccountInfo` and `UncheckedAccount` fields without a `/// CHECK:` doc
+    // comment explaining why they're safe.
+    MissingCheckComment,
+    // `UncheckedAccount` fields of `Accounts` structs.
+    UncheckedAccount,
+    // `AccountInfo` fields of `Accounts` structs.
+    AccountInfo,
+    // `AccountInfo` and `UncheckedAccount` fields of `Accounts` structs whose
+    // owner or address isn't constrained.
+    MissingOwnerCheck,
+    // Zero copy structs with a layout account data can't be reinterpreted as.
+    ZeroCopyLayout,
+    // `+ - * /` operations of instruction handlers, which may overflow, unless
+    // the program is `#[program(checked_math)]`.
+    UncheckedMath,
+}
+
+impl Rule {
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            Rule::MissingCheckComment | Rule::ZeroCopyLayout => Severity::Error,
+            Rule::UncheckedAccount
+            | Rule::AccountInfo
+            | Rule::MissingOwnerCheck
+            | Rule::UncheckedMath => Severity::Allow,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    Allow,
+    Warn,
+    Error,
+}
+
+/// The severity of each rule, overriding their default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SafetyConfig {
+    pub rules: BTreeMap<Rule, Severity>,
+}
+
+impl SafetyConfig {
+    pub fn severity(&self, rule: Rule) -> Severity {
+        self.rules
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+/// A violation of a rule.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            r#"
+        {}:{}:{}
+        {}
+                    "#,
+            self.file.display(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+/// Checks the crate against the rules that aren't allowed.
+pub fn check(ctx: &CrateContext, config: &SafetyConfig) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut report =
+        |module: &ModuleContext, rule: Rule, span: proc_macro2::Span, message: String| {
+            let severity = config.severity(rule);
+            if severity == Severity::Allow {
+                return;
+            }
+            let start = span.start();
+            diagnostics.push(Diagnostic {
+                rule,
+                severity,
+                file: module
+                    .file()
+                    .canonicalize()
+                    .unwrap_or_else(|_| module.file().to_owned()),
+                line: start.line,
+                column: start.column,
+                message,
+            });
+        };
+
+    // Structs with a `#[repr]` giving them a defined layout.
+    let repr_structs: HashSet<String> = ctx
+        .structs()
+        .filter(|strct| is_zero_copy(strct) || has_defined_repr(strct))
+        .map(|strct| strct.ident.to_string())
+        .collect();
+
+    for module in ctx.modules() {
+        for strct in structs(&module) {
+            let is_accounts = is_accounts_struct(strct);
+            for field in &strct.fields {
+                let ident = match &field.ident {
+                    Some(ident) => ident,
+                    None => continue,
+                };
+                if is_unsafe_field(field) && !is_documented(field) {
+                    report(
+                        &module,
+                        Rule::MissingCheckComment,
+                        ident.span(),
+                        format!(
+                            r#"Struct field "{}" is unsafe, but is not documented.
+        Please add a `/// CHECK:` doc comment explaining why no checks through types are necessary.
+        See https://book.anchor-lang.com/anchor_in_depth/the_accounts_struct.html#safety-checks for more information."#,
+                            ident
+                        ),
+                    );
+                }
+                if !is_accounts {
+                    continue;
+                }
+                let ty = match field_type_name(&field.ty) {
+                    Some(ty) if ty == "UncheckedAccount" || ty == "AccountInfo" => ty,
+                    _ => continue,
+                };
+                let rule = match ty.as_str() {
+                    "UncheckedAccount" => Rule::UncheckedAccount,
+                    _ => Rule::AccountInfo,
+                };
+                report(
+                    &module,
+                    rule,
+                    ident.span(),
+                    format!(r#"Struct field "{}" is an {}."#, ident, ty),
+                );
+                if !has_owner_constraint(field) {
+                    report(
+                        &module,
+                        Rule::MissingOwnerCheck,
+                        ident.span(),
+                        format!(
+                            r#"Struct field "{}" is an {}, but neither its owner nor its address is checked.
+        Please add an `owner`, `address` or `seeds` constraint."#,
+                            ident, ty
+                        ),
+                    );
+                }
+            }
+
+            if is_zero_copy(strct) {
+                if let Some((ident, message)) = zero_copy_mismatch(ctx, strct, &repr_structs) {
+                    report(&module, Rule::ZeroCopyLayout, ident.span(), message);
+                }
+            }
+        }
+
+        for handler in unchecked_handlers(&module) {
+            let mut ops = UncheckedOps::default();
+            ops.visit_block(&handler.block);
+            for (op, span) in ops.0 {
+                report(
+                    &module,
+                    Rule::UncheckedMath,
+                    span,
+                    format!(
+                        r#"Handler "{}" has an unchecked `{}` operation, which may overflow.
+        Please use a checked operation, or add `checked_math` to the #[program] attribute."#,
+                        handler.sig.ident, op
+                    ),
+                );
+            }
+        }
+    }
+    diagnostics
+}
+
+// The instruction handlers, i.e. the functions taking a `Context`, of the
+// `#[program]` modules of a module, unless their arithmetic is checked by `#[program(checked_math)]`.
+fn unchecked_handlers<'a>(module: &ModuleContext<'a>) -> impl Iterator<Item = &'a syn::ItemFn> {
+    module
+        .items()
+        .filter_map(|item| match item {
+            syn::Item::Mod(item_mod) => item_mod
+                .attrs
+                .iter()
+                .find(|attr| attr.path.segments.last().unwrap().ident == "program")
+                .filter(|attr| !is_checked_math(attr))
+                .and(item_mod.content.as_ref()),
+            _ => None,
+        })
+        .flat_map(|(_, items)| items)
+        .filter_map(|item| match item {
+            syn::Item::Fn(item_fn) if takes_context(item_fn) => Some(item_fn),
+            _ => None,
+        })
+}
+
+// Whether `#[program(..)]` is given `checked_math`.
+fn is_checked_math(attr: &syn::Attribute) -> bool {
+    !attr.tokens.is_empty()
+        && attr
+            .parse_args::<crate::ProgramArgs>()
+            .map_or(false, |args| args.checked_math)
+}
+
+fn takes_context(item_fn: &syn::ItemFn) -> bool {
+    match item_fn.sig.inputs.first() {
+        Some(syn::FnArg::Typed(arg)) => field_type_name(&arg.ty).as_deref() == Some("Context"),
+        _ => false,
+    }
+}
+
+// The arithmetic operations which may overflow, skipping the closures, nested
+// items and types `#[program(checked_math)]` doesn't rewrite, and looking into
+// the arguments of macro invocations it rewrites.
+#[derive(Default)]
+struct UncheckedOps(Vec<(String, proc_macro2::Span)>);
+
+impl<'ast> Visit<'ast> for UncheckedOps {
+    fn visit_bin_op(&mut self, op: &'ast syn::BinOp) {
+        if checked_method(op).is_some() {
+            let token = quote::quote! { #op };
+            self.0.push((token.to_string(), op.span()));
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        if let Some(args) = macro_args(mac) {
+            let mut ops = UncheckedOps::default();
+            for arg in &args {
+                ops.visit_expr(arg);
+            }
+            self.0.extend(ops.0);
+        }
+    }
+
+    fn visit_expr_closure(&mut self, _closure: &'ast syn::ExprClosure) {}
+
+    fn visit_expr_async(&mut self, _async_block: &'ast syn::ExprAsync) {}
+
+    fn visit_expr_repeat(&mut self, repeat: &'ast syn::ExprRepeat) {
+        self.visit_expr(&repeat.expr);
+    }
+
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        if let syn::Item::Macro(item) = item {
+            if item.ident.is_none() {
+                self.visit_macro(&item.mac);
+            }
+        }
+    }
+
+    fn visit_type(&mut self, _ty: &'ast syn::Type) {}
+}
+
+fn structs<'a>(module: &ModuleContext<'a>) -> impl Iterator<Item = &'a syn::ItemStruct> {
+    module.items().filter_map(|item| match item {
+        syn::Item::Struct(strct) => Some(strct),
+        _ => None,
+    })
+}
+
+// Returns true if the field is an `AccountInfo` or `UncheckedAccount`,
+// optional or not.
+fn is_unsafe_field(field: &syn::Field) -> bool {
+    match without_option(&field.ty) {
+        syn::Type::Path(syn::TypePath {
+            path: syn::Path { segments, .. },
+            ..
+        }) => {
+            segments.len() == 1 && segments[0].ident == "UncheckedAccount"
+                || segments[0].ident == "AccountInfo"
+        }
+        _ => false,
+    }
+}
+
+// Returns true if the field has a doc comment containing CHECK.
+fn is_documented(field: &syn::Field) -> bool {
+    field.attrs.iter().any(|attr| {
+        attr.tokens.clone().into_iter().any(|token| match token {
+            proc_macro2::TokenTree::Literal(s) => s.to_string().contains("CHECK"),
+            _ => false,
+        })
+    })
+}
+
+fn is_accounts_struct(strct: &syn::ItemStruct) -> bool {
+    strct
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("derive"))
+        .any(|attr| attr.tokens.to_string().contains("Accounts"))
+}
+
+fn field_type_name(ty: &syn::Type) -> Option<String> {
+    match without_option(ty) {
+        syn::Type::Path(path) => Some(path.path.segments.last()?.ident.to_string()),
+        _ => None,
+    }
+}
+
+// The type of an optional account, i.e. without its `Option`.
+fn without_option(ty: &syn::Type) -> &syn::Type {
+    let segment = match ty {
+        syn::Type::Path(path) if path.path.segments.len() == 1 => &path.path.segments[0],
+        _ => return ty,
+    };
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if segment.ident == "Option" => {
+            match args.args.first() {
+                Some(syn::GenericArgument::Type(inner)) => inner,
+                _ => ty,
+            }
+        }
+        _ => ty,
+    }
+}
+
+// Returns true if an `#[account]` constraint of the field checks its owner,
+// or its address, which implies the owner.
+fn has_owner_constraint(field: &syn::Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("account"))
+        .any(|attr| {
+            let group = match attr.tokens.clone().into_iter().next() {
+                Some(proc_macro2::TokenTree::Group(group)) => group,
+                _ => return false,
+            };
+            let tokens: Vec<proc_macro2::TokenTree> = group.stream().into_iter().collect();
+            tokens.iter().enumerate().any(|(index, token)| match token {
+                proc_macro2::TokenTree::Ident(ident) => {
+                    let is_constraint = index == 0
+                        || matches!(&tokens[index - 1], proc_macro2::TokenTree::Punct(p) if p.as_char() == ',');
+                    let is_assigned = matches!(tokens.get(index + 1), Some(proc_macro2::TokenTree::Punct(p)) if p.as_char() == '=');
+                    is_constraint
+                        && ((ident == "init" || ident == "zero")
+                            || (is_assigned
+                                && (ident == "owner" || ident == "address" || ident == "seeds")))
+                }
+                _ => false,
+            })
+        })
+}
+
+// The field of a zero copy struct with a layout account data can't be
+// reinterpreted as, if any, and why.
+fn zero_copy_mismatch(
+    ctx: &CrateContext,
+    strct: &syn::ItemStruct,
+    repr_structs: &HashSet<String>,
+) -> Option<(syn::Ident, String)> {
+    if !has_defined_repr(strct) && has_repr(strct) {
+        return Some((
+            strct.ident.clone(),
+            format!(
+                r#"Zero copy struct "{}" has a #[repr] replacing the default #[repr(C)], so its layout is unspecified.
+        Please add `C`, `packed` or `transparent` to it."#,
+                strct.ident
+            ),
+        ));
+    }
+    strct.fields.iter().find_map(|field| {
+        let name = field_struct_name(&field.ty)?;
+        let is_local = ctx.structs().any(|strct| strct.ident == name);
+        (is_local && !repr_structs.contains(&name)).then(|| {
+            (
+                field.ident.clone().unwrap_or_else(|| strct.ident.clone()),
+                format!(
+                    r#"Field of zero copy struct "{}" has type "{}", whose layout is unspecified.
+        Please mark it with #[zero_copy]."#,
+                    strct.ident, name
+                ),
+            )
+        })
+    })
+}
+
+// Returns true if the struct is `#[account(zero_copy)]` or `#[zero_copy]`.
+fn is_zero_copy(strct: &syn::ItemStruct) -> bool {
+    strct.attrs.iter().any(|attr| {
+        let name = attr.path.segments.last().unwrap().ident.to_string();
+        name == "zero_copy" || (name == "account" && attr.tokens.to_string().contains("zero_copy"))
+    })
+}
+
+fn has_repr(strct: &syn::ItemStruct) -> bool {
+    strct.attrs.iter().any(|attr| attr.path.is_ident("repr"))
+}
+
+// Returns true if the struct has a `#[repr]` giving it a defined layout.
+fn has_defined_repr(strct: &syn::ItemStruct) -> bool {
+    strct
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("repr"))
+        .any(|attr| {
+            attr.tokens.clone().into_iter().any(|token| match token {
+                proc_macro2::TokenTree::Group(group) => group.stream().into_iter().any(|token| {
+                    matches!(token, proc_macro2::TokenTree::Ident(ident)
+                        if ident == "C" || ident == "packed" || ident == "transparent")
+                }),
+                _ => false,
+            })
+        })
+}
+
+// The name of the struct a field is made of, if any, e.g. `Foo` for
+// `[Foo; 8]`.
+fn field_struct_name(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Array(array) => field_struct_name(&array.elem),
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let segment = path.path.segments.last()?;
+            matches!(segment.arguments, syn::PathArguments::None).then(|| segment.ident.to_string())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules() {
+        let path = std::env::temp_dir().join("anchor_syn_safety_rules.rs");
+        std::fs::write(
+            &path,
+            r#"
+            #[derive(Accounts)]
+            pub struct Swap<'info> {
+                pub undocumented: UncheckedAccount<'info>,
+                /// CHECK: Only read.
+                pub unowned: AccountInfo<'info>,
+                /// CHECK: The pool's vault.
+                #[account(mut, has_one = owner, owner = token::ID)]
+                pub vault: UncheckedAccount<'info>,
+                /// CHECK: Derived.
+                #[account(seeds = [b"authority"], bump)]
+                pub authority: UncheckedAccount<'info>,
+                pub oracle: Option<AccountInfo<'info>>,
+            }
+
+            #[zero_copy]
+            #[repr(align(8))]
+            pub struct Pool {}
+            "#,
+        )
+        .unwrap();
+        let ctx = CrateContext::parse(&path).unwrap();
+
+        let summary = |config: &SafetyConfig| -> Vec<(Rule, Severity, usize)> {
+            check(&ctx, config)
+                .into_iter()
+                .map(|d| (d.rule, d.severity, d.line))
+                .collect()
+        };
+        assert_eq!(
+            summary(&SafetyConfig::default()),
+            vec![
+                (Rule::MissingCheckComment, Severity::Error, 4),
+                (Rule::MissingCheckComment, Severity::Error, 13),
+                (Rule::ZeroCopyLayout, Severity::Error, 18),
+            ]
+        );
+
+        let config = SafetyConfig {
+            rules: vec![
+                (Rule::MissingCheckComment, Severity::Warn),
+                (Rule::MissingOwnerCheck, Severity::Error),
+                (Rule::ZeroCopyLayout, Severity::Allow),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        assert_eq!(
+            summary(&config),
+            vec![
+                (Rule::MissingCheckComment, Severity::Warn, 4),
+                (Rule::MissingOwnerCheck, Severity::Error, 4),
+                (Rule::MissingOwnerCheck, Severity::Error, 6),
+                (Rule::MissingCheckComment, Severity::Warn, 13),
+                (Rule::MissingOwnerCheck, Severity::Error, 13),
+            ]
+        );
+    }
+
+    #[test]
+    fn unchecked_math() {
+        let path = std::env::temp_dir().join("anchor_syn_safety_unchecked_math.rs");
+        std::fs::write(
+            &path,
+            r#"
+            #[program]
+            pub mod vault {
+                pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+                    ctx.accounts.vault.amount += amount * 2;
+                    let fee = amount.checked_div(100).unwrap();
+                    let total = |a: u64| a - fee;
+                    let buffer = [0u8; 8 + 32];
+                    msg!("Fee {}", fee * 2);
+                    Ok(())
+                }
+
+                fn helper(a: u64) -> u64 {
+                    a + 1
+                }
+            }
+
+            #[program(prelude = crate::checked_math_prelude)]
+            pub mod prelude {
+                pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+                    ctx.accounts.vault.amount -= amount;
+                    Ok(())
+                }
+            }
+
+            #[program(checked_math)]
+            pub mod checked {
+                pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+                    ctx.accounts.vault.amount += amount;
+                    Ok(())
+                }
+            }
+            "#,
+        )
+        .unwrap();
+        let ctx = CrateContext::parse(&path).unwrap();
+        let config = SafetyConfig {
+            rules: vec![(Rule::UncheckedMath, Severity::Warn)]
+                .into_iter()
+                .collect(),
+        };
+        let diagnostics: Vec<(usize, usize)> = check(&ctx, &config)
+            .into_iter()
+            .filter(|d| d.rule == Rule::UncheckedMath)
+            .map(|d| (d.line, d.column))
+            .collect();
+        assert_eq!(diagnostics, vec![(5, 46), (5, 56), (9, 39), (21, 46)]);
+        assert!(check(&ctx, &SafetyConfig::default()).is_empty());
+    }
+}
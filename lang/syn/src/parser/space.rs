@@ -0,0 +1,165 @@
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::Token;
+
+// Space taken by the serialization of a type at most, as computed by
+// `#[derive(InitSpace)]` for `INIT_SPACE`, and by the IDL for the `size` of
+// accounts, so that both always agree.
+//
+// `String` and `Vec` fields are bounded by `#[max_len(..)]`, with one length
+// per level of nesting, e.g. `#[max_len(10, 32)]` for a `Vec<String>`.
+pub enum Space {
+    // A fixed number of bytes.
+    Bytes(usize),
+    // The `INIT_SPACE` of another type.
+    Defined(syn::Type),
+    // The given number of times the inner space.
+    Repeat(Box<Space>, syn::Expr),
+    Sum(Vec<Space>),
+    // The largest of the spaces, i.e. the one of the largest enum variant.
+    Max(Vec<Space>),
+}
+
+impl Space {
+    // Evaluates the space, given the `INIT_SPACE` of other types and the value
+    // of lengths.
+    pub fn eval<E>(
+        &self,
+        defined: &mut impl FnMut(&syn::Type) -> Result<usize, E>,
+        len: &mut impl FnMut(&syn::Expr) -> Result<usize, E>,
+    ) -> Result<usize, E> {
+        Ok(match self {
+            Space::Bytes(bytes) => *bytes,
+            Space::Defined(ty) => defined(ty)?,
+            Space::Repeat(inner, n) => len(n)? * inner.eval(defined, len)?,
+            Space::Sum(spaces) => {
+                let mut sum: usize = 0;
+                for space in spaces {
+                    sum += space.eval(defined, len)?;
+                }
+                sum
+            }
+            Space::Max(spaces) => {
+                let mut max: usize = 0;
+                for space in spaces {
+                    max = max.max(space.eval(defined, len)?);
+                }
+                max
+            }
+        })
+    }
+}
+
+// Whether the item derives `InitSpace`.
+pub fn derives_init_space(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path.is_ident("derive")
+            && attr
+                .parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated)
+                .map_or(false, |paths| {
+                    paths.iter().any(|path| {
+                        path.segments
+                            .last()
+                            .map_or(false, |segment| segment.ident == "InitSpace")
+                    })
+                })
+    })
+}
+
+// The lengths given by the `#[max_len(..)]` attribute, if any.
+pub fn max_len(attrs: &[syn::Attribute]) -> syn::Result<Vec<syn::Expr>> {
+    match attrs.iter().find(|attr| attr.path.is_ident("max_len")) {
+        Some(attr) => Ok(attr
+            .parse_args_with(Punctuated::<syn::Expr, Token![,]>::parse_terminated)?
+            .into_iter()
+            .collect()),
+        None => Ok(vec![]),
+    }
+}
+
+// Space of a struct, or of an enum variant.
+pub fn fields_space(fields: &syn::Fields) -> syn::Result<Space> {
+    fields
+        .iter()
+        .map(|f| ty_space(&f.ty, &max_len(&f.attrs)?))
+        .collect::<syn::Result<_>>()
+        .map(Space::Sum)
+}
+
+// Space of an enum, its variant index followed by its largest variant.
+pub fn enum_space<'a>(variants: impl Iterator<Item = &'a syn::Variant>) -> syn::Result<Space> {
+    let variants = variants
+        .map(|variant| fields_space(&variant.fields))
+        .collect::<syn::Result<_>>()?;
+    Ok(Space::Sum(vec![Space::Bytes(1), Space::Max(variants)]))
+}
+
+// Space of a type, given the lengths of its `#[max_len(..)]`.
+pub fn ty_space(ty: &syn::Type, lens: &[syn::Expr]) -> syn::Result<Space> {
+    let (max_len, inner_lens) = match lens.split_first() {
+        Some((max_len, rest)) => (Some(max_len), rest),
+        None => (None, lens),
+    };
+    let missing_len = || {
+        syn::Error::new(
+            ty.span(),
+            "Expected the length of this type with #[max_len(..)]",
+        )
+    };
+    match ty {
+        syn::Type::Array(array) => Ok(Space::Repeat(
+            Box::new(ty_space(&array.elem, lens)?),
+            array.len.clone(),
+        )),
+        syn::Type::Tuple(tuple) => tuple
+            .elems
+            .iter()
+            .map(|elem| ty_space(elem, &[]))
+            .collect::<syn::Result<_>>()
+            .map(Space::Sum),
+        syn::Type::Path(ty_path) => {
+            let segment = ty_path.path.segments.last().unwrap();
+            let generic_arg = match &segment.arguments {
+                syn::PathArguments::AngleBracketed(args) => {
+                    args.args.iter().find_map(|arg| match arg {
+                        syn::GenericArgument::Type(ty) => Some(ty),
+                        _ => None,
+                    })
+                }
+                _ => None,
+            };
+            Ok(match (segment.ident.to_string().as_str(), generic_arg) {
+                ("bool" | "u8" | "i8", None) => Space::Bytes(1),
+                ("u16" | "i16", None) => Space::Bytes(2),
+                ("u32" | "i32" | "f32", None) => Space::Bytes(4),
+                ("u64" | "i64" | "f64", None) => Space::Bytes(8),
+                ("u128" | "i128", None) => Space::Bytes(16),
+                ("Pubkey", None) => Space::Bytes(32),
+                ("String", None) => Space::Sum(vec![
+                    Space::Bytes(4),
+                    Space::Repeat(
+                        Box::new(Space::Bytes(1)),
+                        max_len.ok_or_else(missing_len)?.clone(),
+                    ),
+                ]),
+                ("Vec", Some(inner)) => Space::Sum(vec![
+                    Space::Bytes(4),
+                    Space::Repeat(
+                        Box::new(ty_space(inner, inner_lens)?),
+                        max_len.ok_or_else(missing_len)?.clone(),
+                    ),
+                ]),
+                // Options and boxes don't have a length of their own.
+                ("Option", Some(inner)) => {
+                    Space::Sum(vec![Space::Bytes(1), ty_space(inner, lens)?])
+                }
+                ("Box", Some(inner)) => ty_space(inner, lens)?,
+                _ => Space::Defined(ty.clone()),
+            })
+        }
+        _ => Err(syn::Error::new(
+            ty.span(),
+            "Unable to compute the space of this type",
+        )),
+    }
+}
@@ -0,0 +1,98 @@
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parenthesized, Ident, Token};
+
+// Lifecycle of accounts.
+//
+// `#[state_machine(field = status, transitions(Open -> Filled, Open -> Canceled))]`
+// on an `#[account]` struct declares the enum field holding the account's
+// state, along with the transitions allowed between the variants of the enum.
+// Instructions then require the account to be in given states with the
+// `in_state` constraint, and move it to another one with `transition_to`.
+
+pub struct StateMachine {
+    pub field: Ident,
+    pub transitions: Vec<Transition>,
+}
+
+pub struct Transition {
+    pub from: Ident,
+    pub to: Ident,
+}
+
+impl StateMachine {
+    // The states of the machine, in the order of the transitions.
+    pub fn states(&self) -> Vec<&Ident> {
+        let mut states: Vec<&Ident> = vec![];
+        for transition in &self.transitions {
+            for state in [&transition.from, &transition.to] {
+                if !states.contains(&state) {
+                    states.push(state);
+                }
+            }
+        }
+        states
+    }
+}
+
+impl Parse for StateMachine {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut field = None;
+        let mut transitions = None;
+        while !input.is_empty() {
+            let arg: Ident = input.parse()?;
+            match arg.to_string().as_str() {
+                "field" => {
+                    input.parse::<Token![=]>()?;
+                    field = Some(input.parse()?);
+                }
+                "transitions" => {
+                    let content;
+                    parenthesized!(content in input);
+                    let parsed = Punctuated::<Transition, Token![,]>::parse_terminated(&content)?;
+                    transitions = Some(parsed.into_iter().collect::<Vec<_>>());
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        arg,
+                        "Unknown state_machine argument",
+                    ))
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        let field = field.ok_or_else(|| {
+            input.error("Expected the field holding the state, e.g. `field = status`")
+        })?;
+        let transitions = match transitions {
+            Some(transitions) if !transitions.is_empty() => transitions,
+            _ => {
+                return Err(input
+                    .error("Expected the allowed transitions, e.g. `transitions(Open -> Filled)`"))
+            }
+        };
+        Ok(StateMachine { field, transitions })
+    }
+}
+
+impl Parse for Transition {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let from = input.parse()?;
+        input.parse::<Token![->]>()?;
+        let to = input.parse()?;
+        Ok(Transition { from, to })
+    }
+}
+
+// Parses the `#[state_machine(..)]` attribute of a struct, if any.
+pub fn state_machine(attrs: &[syn::Attribute]) -> syn::Result<Option<StateMachine>> {
+    match attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("state_machine"))
+    {
+        Some(attr) => attr.parse_args().map(Some),
+        None => Ok(None),
+    }
+}
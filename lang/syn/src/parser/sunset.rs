@@ -0,0 +1,60 @@
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, Lit, Token};
+
+// Deadlines of instructions scheduled for removal, given by
+// `#[sunset(slot = ..)]` or `#[sunset(timestamp = ..)]`, the latter a unix
+// timestamp. The instruction fails from the deadline on.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sunset {
+    Slot(u64),
+    Timestamp(i64),
+}
+
+pub fn parse_args(args: proc_macro2::TokenStream) -> syn::Result<Sunset> {
+    let args = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(args)?;
+    let mut args = args.iter();
+    let sunset = match (args.next(), args.next()) {
+        (Some(Expr::Assign(assign)), None) => match &*assign.left {
+            Expr::Path(path) if path.path.is_ident("slot") => {
+                Sunset::Slot(int(&assign.right)?.base10_parse()?)
+            }
+            Expr::Path(path) if path.path.is_ident("timestamp") => {
+                Sunset::Timestamp(int(&assign.right)?.base10_parse()?)
+            }
+            left => {
+                return Err(syn::Error::new_spanned(
+                    left,
+                    "Expected `slot` or `timestamp`",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                "Expected the deadline, e.g. `#[sunset(slot = 200_000_000)]` or \
+                 `#[sunset(timestamp = 1735689600)]`",
+            ))
+        }
+    };
+    Ok(sunset)
+}
+
+// Parses the `#[sunset(..)]` attribute of a handler, if any.
+pub fn sunset(attrs: &[syn::Attribute]) -> syn::Result<Option<Sunset>> {
+    attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("sunset"))
+        .map(|attr| parse_args(attr.parse_args()?))
+        .transpose()
+}
+
+fn int(expr: &Expr) -> syn::Result<&syn::LitInt> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(int), ..
+        }) => Ok(int),
+        _ => Err(syn::Error::new_spanned(expr, "Expected an integer")),
+    }
+}
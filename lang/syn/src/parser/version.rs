@@ -0,0 +1,82 @@
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, Lit, Token};
+
+// Versioning of account layouts.
+//
+// `#[account(version = 2)]` gives the current version of an account's layout,
+// and the fields added by a later version than the first are marked with
+// `#[since(2)]`, or `#[since(2, renamed_from = "old_name")]` if they were
+// renamed, so that accounts written with older layouts can still be decoded.
+
+pub struct Since {
+    pub version: u32,
+    pub renamed_from: Option<String>,
+}
+
+// Returns the `version` argument of the `#[account(..)]` attribute.
+pub fn account_version(args: proc_macro2::TokenStream) -> syn::Result<Option<u32>> {
+    let args = Punctuated::<Expr, Token![,]>::parse_terminated.parse2(args)?;
+    for arg in args {
+        if let Expr::Assign(assign) = arg {
+            if is_ident(&assign.left, "version") {
+                return int(&assign.right).map(Some);
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Parses the `#[since(..)]` attribute of a field, if any.
+pub fn since(attrs: &[syn::Attribute]) -> syn::Result<Option<Since>> {
+    let attr = match attrs.iter().find(|attr| attr.path.is_ident("since")) {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+    let args = attr.parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)?;
+    let mut args = args.iter();
+    let version = match args.next() {
+        Some(version) => int(version)?,
+        None => {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "Expected the version that added the field, e.g. `#[since(2)]`",
+            ))
+        }
+    };
+    let mut renamed_from = None;
+    for arg in args {
+        match arg {
+            Expr::Assign(assign) if is_ident(&assign.left, "renamed_from") => {
+                renamed_from = Some(string(&assign.right)?);
+            }
+            _ => return Err(syn::Error::new_spanned(arg, "Unknown since argument")),
+        }
+    }
+    Ok(Some(Since {
+        version,
+        renamed_from,
+    }))
+}
+
+fn is_ident(expr: &Expr, ident: &str) -> bool {
+    matches!(expr, Expr::Path(path) if path.path.is_ident(ident))
+}
+
+fn int(expr: &Expr) -> syn::Result<u32> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse(),
+        _ => Err(syn::Error::new_spanned(expr, "Expected an integer version")),
+    }
+}
+
+fn string(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Str(s), ..
+        }) => Ok(s.value()),
+        _ => Err(syn::Error::new_spanned(expr, "Expected a string")),
+    }
+}
@@ -0,0 +1,80 @@
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+
+// Zero copy structs, given `#[zero_copy]` or `#[account(zero_copy)]`, derive
+// bytemuck's `Pod` and `Zeroable`, which fails to compile for structs with
+// padding bytes or with fields that aren't `Pod` themselves. With
+// `zero_copy(unsafe)`, the traits are implemented without these checks
+// instead, e.g. for generic structs, which can't derive them.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZeroCopy {
+    Safe,
+    Unsafe,
+}
+
+// Parses the arguments of `#[zero_copy(..)]`.
+pub fn parse(args: TokenStream) -> syn::Result<ZeroCopy> {
+    let mut args = args.into_iter();
+    match (args.next(), args.next()) {
+        (None, _) => Ok(ZeroCopy::Safe),
+        (Some(TokenTree::Ident(ident)), None) if ident == "unsafe" => Ok(ZeroCopy::Unsafe),
+        (Some(arg), _) => Err(syn::Error::new(arg.span(), "Expected `unsafe`")),
+    }
+}
+
+// Removes the `zero_copy` argument of `#[account(..)]`, which isn't an
+// expression like the others when given as `zero_copy(unsafe)`. Returns the
+// other arguments, along with the kind of zero copy account if it's one.
+pub fn split_account_args(args: TokenStream) -> syn::Result<(TokenStream, Option<ZeroCopy>)> {
+    let mut others = vec![];
+    let mut zero_copy = None;
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        match arg {
+            TokenTree::Ident(ident) if ident == "zero_copy" => {
+                zero_copy = Some(match args.peek() {
+                    Some(TokenTree::Group(group))
+                        if group.delimiter() == Delimiter::Parenthesis =>
+                    {
+                        let kind = parse(group.stream())?;
+                        args.next();
+                        kind
+                    }
+                    _ => ZeroCopy::Safe,
+                });
+                if matches!(args.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == ',') {
+                    args.next();
+                }
+            }
+            arg => others.push(arg),
+        }
+    }
+    Ok((others.into_iter().collect(), zero_copy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn split_account_args_zero_copy() {
+        let split = |args: TokenStream| {
+            let (others, zero_copy) = split_account_args(args).unwrap();
+            (others.to_string(), zero_copy)
+        };
+        assert_eq!(split(quote! { checksum }), ("checksum".to_string(), None));
+        assert_eq!(
+            split(quote! { zero_copy }),
+            (String::new(), Some(ZeroCopy::Safe))
+        );
+        assert_eq!(
+            split(quote! { "state", zero_copy(unsafe), version = 2 }),
+            (
+                quote! { "state", version = 2 }.to_string(),
+                Some(ZeroCopy::Unsafe)
+            )
+        );
+        assert!(split_account_args(quote! { zero_copy(safe) }).is_err());
+    }
+}
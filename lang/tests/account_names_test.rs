@@ -0,0 +1,55 @@
+#![cfg(feature = "idl-build")]
+
+use anchor_lang::idl::build::{instruction, IdlBuildAccounts};
+use anchor_lang::prelude::*;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[derive(Accounts)]
+pub struct Market<'info> {
+    /// CHECK: Only named.
+    pub order_book: UncheckedAccount<'info>,
+    /// CHECK: Only named.
+    pub event_queue: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceOrder<'info> {
+    /// CHECK: Only named.
+    pub open_orders: UncheckedAccount<'info>,
+    pub market: Market<'info>,
+    pub owner_authority: Signer<'info>,
+}
+
+#[test]
+fn named_account_metas_match_idl() {
+    let accounts = __client_accounts_place_order::PlaceOrder {
+        open_orders: Pubkey::new_unique(),
+        market: __client_accounts_market::Market {
+            order_book: Pubkey::new_unique(),
+            event_queue: Pubkey::new_unique(),
+        },
+        owner_authority: Pubkey::new_unique(),
+    };
+    let ix = instruction(
+        r#"{"name":"placeOrder","accounts":[],"args":[]}"#,
+        PlaceOrder::__anchor_private_gen_idl_accounts(),
+        vec![],
+        None,
+    );
+
+    let named = accounts.to_account_metas_named();
+    assert_eq!(ix.name_accounts(accounts.to_account_metas(None)), named);
+    assert_eq!(
+        named
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "openOrders",
+            "market.orderBook",
+            "market.eventQueue",
+            "ownerAuthority"
+        ]
+    );
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "arbitrary")]
+
+use anchor_lang::__private::arbitrary::{Arbitrary, Unstructured};
+use anchor_lang::prelude::*;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[account]
+pub struct Profile {
+    pub authority: Pubkey,
+    pub delegate: Option<Pubkey>,
+    #[max_len(16)]
+    pub name: String,
+    #[max_len(4, 8)]
+    pub tags: Vec<String>,
+    #[max_len(3)]
+    pub friends: Vec<Pubkey>,
+}
+
+#[account(zero_copy)]
+pub struct Counter {
+    pub authority: Pubkey,
+    pub count: u64,
+}
+
+#[event]
+pub struct Renamed {
+    pub profile: Pubkey,
+    #[max_len(16)]
+    pub name: String,
+}
+
+#[test]
+fn respects_max_len() {
+    let data: Vec<u8> = (0..4096).map(|i| (i * 7 + 3) as u8).collect();
+    let mut u = Unstructured::new(&data);
+    for _ in 0..16 {
+        let profile = Profile::arbitrary(&mut u).unwrap();
+        assert!(profile.name.len() <= 16);
+        assert!(profile.tags.len() <= 4);
+        assert!(profile.tags.iter().all(|tag| tag.len() <= 8));
+        assert!(profile.friends.len() <= 3);
+
+        let event = Renamed::arbitrary(&mut u).unwrap();
+        assert!(event.name.len() <= 16);
+    }
+}
+
+#[test]
+fn zero_copy_account() {
+    let data = [1u8; 64];
+    let counter = Counter::arbitrary(&mut Unstructured::new(&data)).unwrap();
+    assert_eq!(counter.authority, Pubkey::new_from_array([1; 32]));
+}
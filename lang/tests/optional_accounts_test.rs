@@ -0,0 +1,181 @@
+#![allow(dead_code)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use std::collections::{BTreeMap, BTreeSet};
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[account(zero_copy)]
+pub struct Market {
+    pub authority: Pubkey,
+    pub fee: u64,
+}
+
+#[derive(Accounts)]
+pub struct Markets<'info> {
+    #[account(mut)]
+    pub market: AccountLoader<'info, Market>,
+    #[account(constraint = fallback.load()?.fee < 100)]
+    pub fallback: Option<AccountLoader<'info, Market>>,
+}
+
+#[derive(Accounts)]
+pub struct Trade<'info> {
+    pub markets: Markets<'info>,
+    pub authority: Signer<'info>,
+    pub oracle: Option<AccountLoader<'info, Market>>,
+}
+
+struct TestAccount {
+    key: Pubkey,
+    lamports: u64,
+    data: Vec<u64>,
+    owner: Pubkey,
+    is_signer: bool,
+}
+
+impl TestAccount {
+    fn market(fee: u64) -> Self {
+        // Account data as laid out by the runtime, i.e., aligned to 8 bytes.
+        let mut data = vec![0u64; (8 + 40) / 8];
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut data);
+        bytes[..8].copy_from_slice(&Market::discriminator());
+        bytes[40..48].copy_from_slice(&fee.to_le_bytes());
+        Self {
+            key: Pubkey::new_unique(),
+            lamports: 1,
+            data,
+            owner: ID,
+            is_signer: false,
+        }
+    }
+
+    fn signer() -> Self {
+        Self {
+            key: Pubkey::new_unique(),
+            lamports: 1,
+            data: vec![],
+            owner: Pubkey::default(),
+            is_signer: true,
+        }
+    }
+
+    // The program id, given for optional accounts that are omitted.
+    fn program() -> Self {
+        Self {
+            key: ID,
+            lamports: 1,
+            data: vec![],
+            owner: Pubkey::default(),
+            is_signer: false,
+        }
+    }
+
+    fn info(&mut self) -> AccountInfo<'_> {
+        AccountInfo::new(
+            &self.key,
+            self.is_signer,
+            true,
+            &mut self.lamports,
+            bytemuck::cast_slice_mut(&mut self.data),
+            &self.owner,
+            false,
+            0,
+        )
+    }
+}
+
+fn try_trade<'info>(infos: &[AccountInfo<'info>]) -> Result<Trade<'info>> {
+    let mut accounts = infos;
+    Trade::try_accounts(
+        &ID,
+        &mut accounts,
+        &[],
+        &mut BTreeMap::new(),
+        &mut BTreeSet::new(),
+    )
+}
+
+#[test]
+fn omitted_optional_accounts() {
+    let mut market = TestAccount::market(1);
+    let mut fallback = TestAccount::program();
+    let mut authority = TestAccount::signer();
+    let mut oracle = TestAccount::program();
+    let infos = vec![
+        market.info(),
+        fallback.info(),
+        authority.info(),
+        oracle.info(),
+    ];
+
+    let trade = try_trade(&infos).unwrap();
+    assert!(trade.markets.fallback.is_none());
+    assert!(trade.oracle.is_none());
+    assert_eq!(trade.markets.market.load().unwrap().fee, 1);
+    assert_eq!(trade.to_account_infos().len(), 2);
+
+    // Omitted accounts keep their position as the program id.
+    let metas = trade.to_account_metas(None);
+    assert_eq!(metas.len(), 4);
+    assert_eq!(metas[1], AccountMeta::new_readonly(ID, false));
+    assert_eq!(metas[3], AccountMeta::new_readonly(ID, false));
+}
+
+#[test]
+fn given_optional_accounts() {
+    let mut market = TestAccount::market(1);
+    let mut fallback = TestAccount::market(2);
+    let mut authority = TestAccount::signer();
+    let mut oracle = TestAccount::market(3);
+    let infos = vec![
+        market.info(),
+        fallback.info(),
+        authority.info(),
+        oracle.info(),
+    ];
+
+    let trade = try_trade(&infos).unwrap();
+    assert_eq!(
+        trade.markets.fallback.as_ref().unwrap().load().unwrap().fee,
+        2
+    );
+    assert_eq!(trade.oracle.as_ref().unwrap().load().unwrap().fee, 3);
+    assert_eq!(trade.to_account_metas(None)[3].pubkey, oracle.key);
+}
+
+#[test]
+fn optional_account_constraints() {
+    let mut market = TestAccount::market(1);
+    let mut fallback = TestAccount::market(100);
+    let mut authority = TestAccount::signer();
+    let mut oracle = TestAccount::program();
+    let infos = vec![
+        market.info(),
+        fallback.info(),
+        authority.info(),
+        oracle.info(),
+    ];
+
+    let err = try_trade(&infos).err().unwrap();
+    assert_eq!(err, ErrorCode::ConstraintRaw.into());
+}
+
+#[test]
+fn optional_account_owner() {
+    let mut market = TestAccount::market(1);
+    let mut fallback = TestAccount::program();
+    let mut authority = TestAccount::signer();
+    let mut oracle = TestAccount::market(3);
+    oracle.owner = Pubkey::new_unique();
+    let infos = vec![
+        market.info(),
+        fallback.info(),
+        authority.info(),
+        oracle.info(),
+    ];
+
+    let err = try_trade(&infos).err().unwrap();
+    assert_eq!(err, ErrorCode::AccountOwnedByWrongProgram.into());
+}
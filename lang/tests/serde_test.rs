@@ -0,0 +1,89 @@
+#![cfg(feature = "serde")]
+
+use anchor_lang::prelude::*;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[account]
+#[derive(Debug, PartialEq)]
+pub struct Vault {
+    pub authority: Pubkey,
+    pub delegate: Option<Pubkey>,
+    pub data: Vec<u8>,
+    pub seed: [u8; 4],
+    pub amount: u64,
+}
+
+#[account(zero_copy)]
+pub struct Book {
+    pub authority: Pubkey,
+    pub seed: [u8; 4],
+}
+
+#[event]
+pub struct Deposited {
+    pub vault: Pubkey,
+    pub memo: Vec<u8>,
+}
+
+#[test]
+fn account_json_roundtrip() {
+    let vault = Vault {
+        authority: ID,
+        delegate: None,
+        data: vec![1, 2, 3],
+        seed: [4, 5, 6, 7],
+        amount: 42,
+    };
+    let json = serde_json::to_value(&vault).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "authority": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "delegate": null,
+            "data": "AQID",
+            "seed": "BAUGBw==",
+            "amount": 42,
+        })
+    );
+    assert_eq!(serde_json::from_value::<Vault>(json).unwrap(), vault);
+}
+
+#[test]
+fn zero_copy_account_json() {
+    let book = Book {
+        authority: ID,
+        seed: [0; 4],
+    };
+    assert_eq!(
+        serde_json::to_value(book).unwrap(),
+        serde_json::json!({
+            "authority": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "seed": "AAAAAA==",
+        })
+    );
+}
+
+#[test]
+fn event_json() {
+    let event = Deposited {
+        vault: ID,
+        memo: b"hi".to_vec(),
+    };
+    assert_eq!(
+        serde_json::to_value(&event).unwrap(),
+        serde_json::json!({
+            "vault": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "memo": "aGk=",
+        })
+    );
+}
+
+#[test]
+fn invalid_byte_array_length() {
+    let json = serde_json::json!({
+        "authority": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+        "seed": "AQID",
+    });
+    assert!(serde_json::from_value::<Book>(json).is_err());
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "anchor-debug")]
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[account(zero_copy)]
+pub struct Counter {
+    pub authority: Pubkey,
+    pub count: u64,
+}
+
+// Account data as laid out by the runtime, i.e., aligned to 8 bytes.
+fn account_data(len: usize) -> Vec<u64> {
+    vec![0; (len + 7) / 8]
+}
+
+fn with_loader(data: &mut [u8], f: impl FnOnce(&AccountLoader<Counter>)) {
+    data[..8].copy_from_slice(&Counter::discriminator());
+    let key = Pubkey::new_unique();
+    let mut lamports = 0;
+    let info = AccountInfo::new(&key, false, true, &mut lamports, data, &ID, false, 0);
+    f(&AccountLoader::try_from(&info).unwrap());
+}
+
+#[test]
+fn loads_valid_data() {
+    let mut data = account_data(8 + 40);
+    let data = bytemuck::cast_slice_mut(&mut data);
+    with_loader(data, |loader| {
+        loader.load_mut().unwrap().count = 7;
+        assert_eq!(loader.load().unwrap().count, 7);
+    });
+}
+
+#[test]
+#[should_panic(expected = "has 16 bytes of data, but 48 are needed")]
+fn too_small() {
+    let mut data = account_data(16);
+    with_loader(bytemuck::cast_slice_mut(&mut data), |loader| {
+        let _ = loader.load();
+    });
+}
+
+#[test]
+#[should_panic(expected = "load_mut of account")]
+fn overlapping_borrows() {
+    let mut data = account_data(8 + 40);
+    with_loader(bytemuck::cast_slice_mut(&mut data), |loader| {
+        let _counter = loader.load().unwrap();
+        let _ = loader.load_mut();
+    });
+}
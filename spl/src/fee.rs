@@ -0,0 +1,180 @@
+use crate::token::{self, Transfer};
+use anchor_lang::context::CpiContext;
+use anchor_lang::solana_program::account_info::AccountInfo;
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::{Accounts, Result};
+
+/// Basis points of the whole amount.
+pub const MAX_FEE_BPS: u16 = 10_000;
+
+/// How fees of a fraction of a token are rounded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    /// In favor of the payer.
+    Down,
+    /// In favor of the fee collector.
+    Up,
+    /// To the nearest amount, halves rounded up.
+    Nearest,
+}
+
+/// The fee of `fee_bps` basis points on `amount`. Never more than `amount`.
+pub fn fee_amount(amount: u64, fee_bps: u16, rounding: Rounding) -> Result<u64> {
+    if fee_bps > MAX_FEE_BPS {
+        return Err(ProgramError::InvalidArgument.into());
+    }
+    let max = MAX_FEE_BPS as u128;
+    let scaled = amount as u128 * fee_bps as u128;
+    let fee = match rounding {
+        Rounding::Down => scaled / max,
+        Rounding::Up => (scaled + max - 1) / max,
+        Rounding::Nearest => (scaled + max / 2) / max,
+    };
+    // `fee <= amount` since `fee_bps <= MAX_FEE_BPS`.
+    Ok(fee as u64)
+}
+
+/// Splits `amount` into the fee of `fee_bps` basis points and the rest, as
+/// `(fee, net)`.
+pub fn split_fee(amount: u64, fee_bps: u16, rounding: Rounding) -> Result<(u64, u64)> {
+    let fee = fee_amount(amount, fee_bps, rounding)?;
+    Ok((fee, amount - fee))
+}
+
+/// Transfers the fee of `fee_bps` basis points on `amount` from `from` to
+/// `fee_vault`, returning the fee. Nothing is transferred for a zero fee.
+///
+/// When `from` is owned by a PDA of the program, give its seeds with
+/// `CpiContext::new_with_signer`.
+pub fn collect_fee<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, CollectFee<'info>>,
+    amount: u64,
+    fee_bps: u16,
+    rounding: Rounding,
+) -> Result<u64> {
+    let fee = fee_amount(amount, fee_bps, rounding)?;
+    if fee > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.program,
+                Transfer {
+                    from: ctx.accounts.from,
+                    to: ctx.accounts.fee_vault,
+                    authority: ctx.accounts.authority,
+                },
+                ctx.signer_seeds,
+            ),
+            fee,
+        )?;
+    }
+    Ok(fee)
+}
+
+#[derive(Accounts)]
+pub struct CollectFee<'info> {
+    pub from: AccountInfo<'info>,
+    pub fee_vault: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+}
+
+/// Declares the `FeeConfig` account and the `FeeCollected` event in the
+/// program crate, so that they're owned by the program and described by its
+/// IDL, with the same layout in every program using them.
+///
+/// `FeeConfig::collect` collects the fee of a config with
+/// [`collect_fee`](fee/fn.collect_fee.html), checking that the fee vault is
+/// the config's, and emits `FeeCollected`.
+///
+/// ```ignore
+/// use anchor_spl::fee::{CollectFee, Rounding};
+///
+/// anchor_spl::declare_fee_config!();
+///
+/// pub fn swap(ctx: Context<Swap>, amount: u64) -> Result<()> {
+///     let seeds: &[&[u8]] = &[b"pool", &[ctx.accounts.pool.bump]];
+///     let fee = FeeConfig::collect(
+///         &ctx.accounts.fee_config,
+///         CpiContext::new_with_signer(
+///             ctx.accounts.token_program.to_account_info(),
+///             CollectFee {
+///                 from: ctx.accounts.pool_vault.to_account_info(),
+///                 fee_vault: ctx.accounts.fee_vault.to_account_info(),
+///                 authority: ctx.accounts.pool.to_account_info(),
+///             },
+///             &[seeds],
+///         ),
+///         amount,
+///         Rounding::Up,
+///     )?;
+///     // ...
+/// }
+/// ```
+#[macro_export]
+macro_rules! declare_fee_config {
+    () => {
+        /// Fee charged by the program, in basis points, and the token
+        /// account collecting it.
+        #[account]
+        #[derive(InitSpace)]
+        pub struct FeeConfig {
+            pub authority: Pubkey,
+            pub fee_vault: Pubkey,
+            pub fee_bps: u16,
+            pub bump: u8,
+        }
+
+        /// A fee collected with `FeeConfig::collect`.
+        #[event]
+        pub struct FeeCollected {
+            pub fee_config: Pubkey,
+            pub from: Pubkey,
+            pub fee_vault: Pubkey,
+            pub amount: u64,
+            pub fee: u64,
+            pub fee_bps: u16,
+        }
+
+        impl FeeConfig {
+            /// The fee on `amount`.
+            pub fn fee(
+                &self,
+                amount: u64,
+                rounding: $crate::fee::Rounding,
+            ) -> anchor_lang::Result<u64> {
+                $crate::fee::fee_amount(amount, self.fee_bps, rounding)
+            }
+
+            /// Collects the fee on `amount` into the config's fee vault and
+            /// emits `FeeCollected`, returning the fee.
+            pub fn collect<'a, 'b, 'c, 'info>(
+                config: &anchor_lang::prelude::Account<'info, FeeConfig>,
+                ctx: anchor_lang::context::CpiContext<
+                    'a,
+                    'b,
+                    'c,
+                    'info,
+                    $crate::fee::CollectFee<'info>,
+                >,
+                amount: u64,
+                rounding: $crate::fee::Rounding,
+            ) -> anchor_lang::Result<u64> {
+                anchor_lang::require_keys_eq!(
+                    *ctx.accounts.fee_vault.key,
+                    config.fee_vault,
+                    anchor_lang::error::ErrorCode::ConstraintAddress
+                );
+                let from = *ctx.accounts.from.key;
+                let fee = $crate::fee::collect_fee(ctx, amount, config.fee_bps, rounding)?;
+                anchor_lang::emit!(FeeCollected {
+                    fee_config: anchor_lang::Key::key(config),
+                    from,
+                    fee_vault: config.fee_vault,
+                    amount,
+                    fee,
+                    fee_bps: config.fee_bps,
+                });
+                Ok(fee)
+            }
+        }
+    };
+}
@@ -7,9 +7,15 @@ pub mod mint;
 #[cfg(feature = "token")]
 pub mod token;
 
+#[cfg(feature = "token_interface")]
+pub mod token_interface;
+
 #[cfg(feature = "dex")]
 pub mod dex;
 
+#[cfg(feature = "fee")]
+pub mod fee;
+
 #[cfg(feature = "governance")]
 pub mod governance;
 
@@ -4,6 +4,7 @@ use mpl_token_metadata::state::DataV2;
 use mpl_token_metadata::ID;
 use solana_program::account_info::AccountInfo;
 use solana_program::pubkey::Pubkey;
+use std::ops::Deref;
 
 #[derive(Clone)]
 pub struct Metadata;
@@ -14,6 +15,44 @@ impl anchor_lang::Id for Metadata {
     }
 }
 
+/// The address of the metadata account of `mint`, the program derived address
+/// of the metadata program with the seeds `["metadata", ID, mint]`.
+pub fn find_metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"metadata", ID.as_ref(), mint.as_ref()], &ID).0
+}
+
+/// Anchor wrapper for the metadata program's `Metadata` account.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetadataAccount(mpl_token_metadata::state::Metadata);
+
+impl anchor_lang::AccountDeserialize for MetadataAccount {
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        let metadata: mpl_token_metadata::state::Metadata =
+            anchor_lang::AnchorDeserialize::deserialize(buf)
+                .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize)?;
+        if metadata.key != mpl_token_metadata::state::Key::MetadataV1 {
+            return Err(anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into());
+        }
+        Ok(MetadataAccount(metadata))
+    }
+}
+
+impl anchor_lang::AccountSerialize for MetadataAccount {}
+
+impl anchor_lang::Owner for MetadataAccount {
+    fn owner() -> Pubkey {
+        ID
+    }
+}
+
+impl Deref for MetadataAccount {
+    type Target = mpl_token_metadata::state::Metadata;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 pub fn create_metadata_accounts_v2<'info>(
     ctx: CpiContext<'_, '_, '_, 'info, CreateMetadataAccountsV2<'info>>,
     data: DataV2,
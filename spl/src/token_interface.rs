@@ -0,0 +1,122 @@
+//! Accounts of the token interface, implemented by both the token program and
+//! token-2022, for use with `InterfaceAccount` and `Interface`.
+//!
+//! Token-2022 accounts and mints start with the layout of those of the token
+//! program, followed by their extensions, if any. Only the base layout is
+//! deserialized.
+
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use std::ops::Deref;
+
+/// The token-2022 program.
+pub mod token_2022 {
+    anchor_lang::solana_program::declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+}
+
+// `spl_token::ID` and `token_2022::ID`.
+static IDS: [Pubkey; 2] = [
+    Pubkey::new_from_array([
+        6, 221, 246, 225, 215, 101, 161, 147, 217, 203, 225, 70, 206, 235, 121, 172, 28, 180, 133,
+        237, 95, 91, 55, 145, 58, 140, 245, 133, 126, 255, 0, 169,
+    ]),
+    Pubkey::new_from_array([
+        6, 221, 246, 225, 238, 117, 143, 222, 24, 66, 93, 188, 228, 108, 205, 218, 182, 26, 252,
+        77, 131, 185, 13, 39, 254, 189, 249, 40, 216, 161, 139, 252,
+    ]),
+];
+
+// Offset of the `AccountType` of token-2022 accounts with extensions, right
+// after the base layout of a token account, up to which mints are padded.
+const ACCOUNT_TYPE_OFFSET: usize = spl_token::state::Account::LEN;
+
+// `AccountType::Mint` and `AccountType::Account` of token-2022.
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+// Unpacks the base layout of an account of either program.
+//
+// Accounts of the token program, and those of token-2022 without extensions,
+// have the exact length of the base layout. Only token-2022 accounts with
+// extensions are longer, and their `AccountType` must match. The token
+// program's only longer accounts are multisigs, whose length token-2022 never
+// gives to an account with extensions, so they are left to the exact length
+// check.
+fn unpack<T: Pack>(buf: &[u8], account_type: u8) -> anchor_lang::Result<T> {
+    if buf.len() <= ACCOUNT_TYPE_OFFSET || buf.len() == spl_token::state::Multisig::LEN {
+        return T::unpack(buf).map_err(Into::into);
+    }
+    if buf[ACCOUNT_TYPE_OFFSET] != account_type {
+        return Err(ProgramError::InvalidAccountData.into());
+    }
+    T::unpack(&buf[..T::LEN]).map_err(Into::into)
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TokenAccount(spl_token::state::Account);
+
+impl TokenAccount {
+    pub const LEN: usize = spl_token::state::Account::LEN;
+}
+
+impl anchor_lang::AccountDeserialize for TokenAccount {
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        unpack(buf, ACCOUNT_TYPE_ACCOUNT).map(TokenAccount)
+    }
+}
+
+impl anchor_lang::AccountSerialize for TokenAccount {}
+
+impl anchor_lang::Owners for TokenAccount {
+    fn owners() -> &'static [Pubkey] {
+        &IDS
+    }
+}
+
+impl Deref for TokenAccount {
+    type Target = spl_token::state::Account;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mint(spl_token::state::Mint);
+
+impl Mint {
+    pub const LEN: usize = spl_token::state::Mint::LEN;
+}
+
+impl anchor_lang::AccountDeserialize for Mint {
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+        unpack(buf, ACCOUNT_TYPE_MINT).map(Mint)
+    }
+}
+
+impl anchor_lang::AccountSerialize for Mint {}
+
+impl anchor_lang::Owners for Mint {
+    fn owners() -> &'static [Pubkey] {
+        &IDS
+    }
+}
+
+impl Deref for Mint {
+    type Target = spl_token::state::Mint;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Either the token program or token-2022.
+#[derive(Clone)]
+pub struct TokenInterface;
+
+impl anchor_lang::Ids for TokenInterface {
+    fn ids() -> &'static [Pubkey] {
+        &IDS
+    }
+}
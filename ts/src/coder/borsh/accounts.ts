@@ -13,6 +13,11 @@ import { accountSize } from "../common.js";
  */
 export const ACCOUNT_DISCRIMINATOR_SIZE = 8;
 
+/**
+ * Number of bytes of the checksum of `#[account(checksum)]` accounts.
+ */
+export const ACCOUNT_CHECKSUM_SIZE = 4;
+
 /**
  * Encodes and decodes account objects.
  */
@@ -50,14 +55,19 @@ export class BorshAccountsCoder<A extends string = string>
     }
     const len = layout.encode(account, buffer);
     let accountData = buffer.slice(0, len);
-    let discriminator = BorshAccountsCoder.accountDiscriminator(accountName);
+    let discriminator = this.discriminator(accountName);
+    if (this.idlAccount(accountName)?.checksum) {
+      const checksum = Buffer.alloc(ACCOUNT_CHECKSUM_SIZE);
+      checksum.writeUInt32LE(crc32(accountData), 0);
+      return Buffer.concat([discriminator, accountData, checksum]);
+    }
     return Buffer.concat([discriminator, accountData]);
   }
 
   public decode<T = any>(accountName: A, data: Buffer): T {
     // Assert the account discriminator is correct.
-    const discriminator = BorshAccountsCoder.accountDiscriminator(accountName);
-    if (discriminator.compare(data.slice(0, 8))) {
+    const discriminator = this.discriminator(accountName);
+    if (discriminator.compare(data.slice(0, discriminator.length))) {
       throw new Error("Invalid account discriminator");
     }
     return this.decodeUnchecked(accountName, data);
@@ -65,7 +75,7 @@ export class BorshAccountsCoder<A extends string = string>
 
   public decodeUnchecked<T = any>(accountName: A, ix: Buffer): T {
     // Chop off the discriminator before decoding.
-    const data = ix.slice(ACCOUNT_DISCRIMINATOR_SIZE);
+    const data = ix.slice(this.discriminator(accountName).length);
     const layout = this.accountLayouts.get(accountName);
     if (!layout) {
       throw new Error(`Unknown account: ${accountName}`);
@@ -74,7 +84,7 @@ export class BorshAccountsCoder<A extends string = string>
   }
 
   public memcmp(accountName: A, appendData?: Buffer): any {
-    const discriminator = BorshAccountsCoder.accountDiscriminator(accountName);
+    const discriminator = this.discriminator(accountName);
     return {
       offset: 0,
       bytes: bs58.encode(
@@ -85,10 +95,27 @@ export class BorshAccountsCoder<A extends string = string>
 
   public size(idlAccount: IdlTypeDef): number {
     return (
-      ACCOUNT_DISCRIMINATOR_SIZE + (accountSize(this.idl, idlAccount) ?? 0)
+      (idlAccount.discriminator?.length ?? ACCOUNT_DISCRIMINATOR_SIZE) +
+      (accountSize(this.idl, idlAccount) ?? 0) +
+      (idlAccount.checksum ? ACCOUNT_CHECKSUM_SIZE : 0)
     );
   }
 
+  private idlAccount(accountName: A): IdlTypeDef | undefined {
+    return this.idl?.accounts?.find((acc) => acc.name === accountName);
+  }
+
+  /**
+   * The discriminator of an account of the IDL, the custom one given by
+   * `#[account(discriminator = ..)]` if any.
+   */
+  private discriminator(accountName: A): Buffer {
+    const custom = this.idlAccount(accountName)?.discriminator;
+    return custom
+      ? Buffer.from(custom)
+      : BorshAccountsCoder.accountDiscriminator(accountName);
+  }
+
   /**
    * Calculates and returns a unique 8 byte discriminator prepended to all anchor accounts.
    *
@@ -100,3 +127,27 @@ export class BorshAccountsCoder<A extends string = string>
     ).slice(0, ACCOUNT_DISCRIMINATOR_SIZE);
   }
 }
+
+const CRC32_TABLE = (() => {
+  const table = new Uint32Array(256);
+  for (let i = 0; i < 256; i++) {
+    let crc = i;
+    for (let bit = 0; bit < 8; bit++) {
+      crc = crc & 1 ? (crc >>> 1) ^ 0xedb88320 : crc >>> 1;
+    }
+    table[i] = crc;
+  }
+  return table;
+})();
+
+/**
+ * The CRC-32 (IEEE 802.3) of the given bytes, the checksum of
+ * `#[account(checksum)]` accounts.
+ */
+function crc32(data: Buffer): number {
+  let crc = 0xffffffff;
+  for (const byte of data) {
+    crc = CRC32_TABLE[(crc ^ byte) & 0xff] ^ (crc >>> 8);
+  }
+  return (crc ^ 0xffffffff) >>> 0;
+}
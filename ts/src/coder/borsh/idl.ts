@@ -1,4 +1,5 @@
 import camelCase from "camelcase";
+import { Buffer } from "buffer";
 import { Layout } from "buffer-layout";
 import * as borsh from "@project-serum/borsh";
 import { IdlField, IdlTypeDef, IdlEnumVariant, IdlType } from "../../idl.js";
@@ -105,6 +106,10 @@ export class IdlCoder {
             types
           );
           return borsh.array(innerLayout, arrayLen, fieldName);
+        } else if ("float" in field.type) {
+          return field.type.float.bits === 32
+            ? borsh.f32(fieldName)
+            : borsh.f64(fieldName);
         } else {
           throw new Error(`Not yet implemented: ${field}`);
         }
@@ -122,7 +127,10 @@ export class IdlCoder {
         const x = IdlCoder.fieldLayout(field, types);
         return x;
       });
-      return borsh.struct(fieldLayouts, name);
+      return IdlCoder.withAliases(
+        borsh.struct(fieldLayouts, name),
+        typeDef.type.fields
+      );
     } else if (typeDef.type.kind === "enum") {
       let variants = typeDef.type.variants.map((variant: IdlEnumVariant) => {
         const name = camelCase(variant.name);
@@ -153,4 +161,33 @@ export class IdlCoder {
       throw new Error(`Unknown type kint: ${typeDef}`);
     }
   }
+
+  /**
+   * Makes a struct layout accept the aliases of its fields, i.e. their former
+   * names, in place of their names when encoding.
+   */
+  public static withAliases(layout: Layout, fields: IdlField[]): Layout {
+    const aliased = fields.filter((field) => field.aliases?.length);
+    if (aliased.length === 0) {
+      return layout;
+    }
+    const encode = layout.encode.bind(layout);
+    layout.encode = (src: any, b: Buffer, offset?: number) => {
+      const value = { ...src };
+      aliased.forEach((field) => {
+        const name = camelCase(field.name);
+        if (value[name] !== undefined) {
+          return;
+        }
+        const alias = (field.aliases ?? [])
+          .map((alias) => camelCase(alias))
+          .find((alias) => src[alias] !== undefined);
+        if (alias !== undefined) {
+          value[name] = src[alias];
+        }
+      });
+      return encode(value, b, offset);
+    };
+    return layout;
+  }
 }
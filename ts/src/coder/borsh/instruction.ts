@@ -43,12 +43,17 @@ export class BorshInstructionCoder implements InstructionCoder {
   // Base58 encoded sighash to instruction layout.
   private sighashLayouts: Map<string, { layout: Layout; name: string }>;
 
+  // Lengths of the discriminators of the instructions, longest first.
+  private discriminatorLens: number[];
+
   public constructor(private idl: Idl) {
     this.ixLayout = BorshInstructionCoder.parseIxLayout(idl);
 
     const sighashLayouts = new Map();
+    const discriminatorLens = new Set<number>();
     idl.instructions.forEach((ix) => {
-      const sh = sighash(SIGHASH_GLOBAL_NAMESPACE, ix.name);
+      const sh = discriminator(ix, SIGHASH_GLOBAL_NAMESPACE);
+      discriminatorLens.add(sh.length);
       sighashLayouts.set(bs58.encode(sh), {
         layout: this.ixLayout.get(ix.name),
         name: ix.name,
@@ -57,7 +62,8 @@ export class BorshInstructionCoder implements InstructionCoder {
 
     if (idl.state) {
       idl.state.methods.map((ix) => {
-        const sh = sighash(SIGHASH_STATE_NAMESPACE, ix.name);
+        const sh = discriminator(ix, SIGHASH_STATE_NAMESPACE);
+        discriminatorLens.add(sh.length);
         sighashLayouts.set(bs58.encode(sh), {
           layout: this.ixLayout.get(ix.name) as Layout,
           name: ix.name,
@@ -66,23 +72,42 @@ export class BorshInstructionCoder implements InstructionCoder {
     }
 
     this.sighashLayouts = sighashLayouts;
+    this.discriminatorLens = [...discriminatorLens].sort((a, b) => b - a);
   }
 
   /**
    * Encodes a program instruction.
    */
   public encode(ixName: string, ix: any): Buffer {
-    return this._encode(SIGHASH_GLOBAL_NAMESPACE, ixName, ix);
+    const idlIx = this.idl.instructions.find(
+      (ix) => camelCase(ix.name) === camelCase(ixName)
+    );
+    return this._encode(
+      idlIx
+        ? discriminator(idlIx, SIGHASH_GLOBAL_NAMESPACE)
+        : sighash(SIGHASH_GLOBAL_NAMESPACE, ixName),
+      ixName,
+      ix
+    );
   }
 
   /**
    * Encodes a program state instruction.
    */
   public encodeState(ixName: string, ix: any): Buffer {
-    return this._encode(SIGHASH_STATE_NAMESPACE, ixName, ix);
+    const idlIx = this.idl.state?.methods.find(
+      (ix) => camelCase(ix.name) === camelCase(ixName)
+    );
+    return this._encode(
+      idlIx
+        ? discriminator(idlIx, SIGHASH_STATE_NAMESPACE)
+        : sighash(SIGHASH_STATE_NAMESPACE, ixName),
+      ixName,
+      ix
+    );
   }
 
-  private _encode(nameSpace: string, ixName: string, ix: any): Buffer {
+  private _encode(disc: Buffer, ixName: string, ix: any): Buffer {
     const buffer = Buffer.alloc(1000); // TODO: use a tighter buffer.
     const methodName = camelCase(ixName);
     const layout = this.ixLayout.get(methodName);
@@ -91,7 +116,7 @@ export class BorshInstructionCoder implements InstructionCoder {
     }
     const len = layout.encode(ix, buffer);
     const data = buffer.slice(0, len);
-    return Buffer.concat([sighash(nameSpace, ixName), data]);
+    return Buffer.concat([disc, data]);
   }
 
   private static parseIxLayout(idl: Idl): Map<string, Layout> {
@@ -106,7 +131,10 @@ export class BorshInstructionCoder implements InstructionCoder {
           );
         });
         const name = camelCase(m.name);
-        return [name, borsh.struct(fieldLayouts, name)];
+        return [
+          name,
+          IdlCoder.withAliases(borsh.struct(fieldLayouts, name), m.args),
+        ];
       })
       .concat(
         idl.instructions.map((ix) => {
@@ -117,7 +145,10 @@ export class BorshInstructionCoder implements InstructionCoder {
             )
           );
           const name = camelCase(ix.name);
-          return [name, borsh.struct(fieldLayouts, name)];
+          return [
+            name,
+            IdlCoder.withAliases(borsh.struct(fieldLayouts, name), ix.args),
+          ];
         })
       );
     return new Map(ixLayouts);
@@ -133,16 +164,16 @@ export class BorshInstructionCoder implements InstructionCoder {
     if (typeof ix === "string") {
       ix = encoding === "hex" ? Buffer.from(ix, "hex") : bs58.decode(ix);
     }
-    let sighash = bs58.encode(ix.slice(0, 8));
-    let data = ix.slice(8);
-    const decoder = this.sighashLayouts.get(sighash);
-    if (!decoder) {
-      return null;
+    for (const len of this.discriminatorLens) {
+      const decoder = this.sighashLayouts.get(bs58.encode(ix.slice(0, len)));
+      if (decoder) {
+        return {
+          data: decoder.layout.decode(ix.slice(len)),
+          name: decoder.name,
+        };
+      }
     }
-    return {
-      data: decoder.layout.decode(data),
-      name: decoder.name,
-    };
+    return null;
   }
 
   /**
@@ -390,3 +421,15 @@ function sighash(nameSpace: string, ixName: string): Buffer {
   let preimage = `${nameSpace}:${name}`;
   return Buffer.from(sha256.digest(preimage)).slice(0, 8);
 }
+
+// The bytes the data of an instruction starts with, its sighash unless the
+// IDL gives another discriminator.
+function discriminator(
+  ix: { name: string; namespace?: string; discriminator?: number[] },
+  defaultNamespace: string
+): Buffer {
+  if (ix.discriminator) {
+    return Buffer.from(ix.discriminator);
+  }
+  return sighash(ix.namespace ?? defaultNamespace, ix.name);
+}
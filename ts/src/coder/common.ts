@@ -88,6 +88,9 @@ function typeSize(idl: Idl, ty: IdlType): number {
         let arraySize = ty.array[1];
         return typeSize(idl, arrayTy) * arraySize;
       }
+      if ("float" in ty) {
+        return ty.float.bits / 8;
+      }
       throw new Error(`Invalid type ${JSON.stringify(ty)}`);
   }
 }
@@ -305,12 +305,89 @@ export function translateError(err: any, idlErrors: Map<number, string>) {
   return err;
 }
 
+// A message bundle of a program's errors, keyed by error code, as generated
+// by `anchor idl messages`.
+export type ErrorMessageBundle = {
+  program: string;
+  locale: string;
+  messages: { [code: string]: ErrorMessageEntry };
+};
+
+export type ErrorMessageEntry = {
+  name: string;
+  message: string;
+  placeholders?: string[];
+  description?: string;
+};
+
+export type ErrorMessageValues = { [placeholder: string]: unknown };
+
+// Replaces the named placeholders of a message, e.g. `{amount}`, by their
+// value. Placeholders without a value are left as is, and `{{` and `}}` are
+// literal braces.
+export function formatErrorMessage(
+  message: string,
+  values: ErrorMessageValues = {}
+): string {
+  return message.replace(
+    /\{\{|\}\}|\{([A-Za-z_][A-Za-z0-9_]*)\}/g,
+    (match, name?: string) => {
+      if (name === undefined) {
+        return match[0];
+      }
+      return values[name] === undefined ? match : String(values[name]);
+    }
+  );
+}
+
+// Localized messages of program errors.
+//
+// Bundles are given in order of preference, e.g. the bundle translated to the
+// user's locale followed by the one generated from the program, so that
+// untranslated messages fall back to the next bundle.
+export class ErrorMessages {
+  constructor(readonly bundles: ErrorMessageBundle[]) {}
+
+  public message(
+    code: number,
+    values: ErrorMessageValues = {}
+  ): string | undefined {
+    for (const bundle of this.bundles) {
+      const entry = bundle.messages[code.toString()];
+      if (entry !== undefined) {
+        return formatErrorMessage(entry.message, values);
+      }
+    }
+    return undefined;
+  }
+
+  // The message of an `AnchorError` or `ProgramError`, if it's in a bundle.
+  public fromError(
+    err: unknown,
+    values: ErrorMessageValues = {}
+  ): string | undefined {
+    if (err instanceof AnchorError) {
+      return this.message(err.error.errorCode.number, values);
+    }
+    if (err instanceof ProgramError) {
+      return this.message(err.code, values);
+    }
+    return undefined;
+  }
+}
+
 export const LangErrorCode = {
   // Instructions.
   InstructionMissing: 100,
   InstructionFallbackNotFound: 101,
   InstructionDidNotDeserialize: 102,
   InstructionDidNotSerialize: 103,
+  InstructionSunset: 104,
+  InstructionArgOutOfRange: 105,
+  InstructionArgZero: 106,
+  InstructionArgTooLong: 107,
+  InstructionNotBatchable: 108,
+  InstructionBatchMismatch: 109,
 
   // IDL instructions.
   IdlInstructionStub: 1000,
@@ -337,6 +414,10 @@ export const LangErrorCode = {
   ConstraintMintFreezeAuthority: 2017,
   ConstraintMintDecimals: 2018,
   ConstraintSpace: 2019,
+  ConstraintStateMachine: 2020,
+  ConstraintDuplicateMutableAccount: 2021,
+  ConstraintMetadataAddress: 2022,
+  ConstraintMetadata: 2023,
 
   // Require.
   RequireViolated: 2500,
@@ -366,12 +447,15 @@ export const LangErrorCode = {
   AccountSysvarMismatch: 3015,
   AccountReallocExceedsLimit: 3016,
   AccountDuplicateReallocs: 3017,
+  AccountChecksumMismatch: 3018,
 
   // State.
   StateInvalidAddress: 4000,
 
   // Miscellaneous
   DeclaredProgramIdMismatch: 4100,
+  StateMachineTransition: 4101,
+  ArithmeticOverflow: 4102,
 
   // Used for APIs that shouldn't be used anymore.
   Deprecated: 5000,
@@ -395,6 +479,30 @@ export const LangErrorMessage = new Map([
     LangErrorCode.InstructionDidNotSerialize,
     "The program could not serialize the given instruction",
   ],
+  [
+    LangErrorCode.InstructionSunset,
+    "The instruction is past its sunset",
+  ],
+  [
+    LangErrorCode.InstructionArgOutOfRange,
+    "An instruction argument is out of its range",
+  ],
+  [
+    LangErrorCode.InstructionArgZero,
+    "An instruction argument must not be zero",
+  ],
+  [
+    LangErrorCode.InstructionArgTooLong,
+    "An instruction argument exceeds its maximum length",
+  ],
+  [
+    LangErrorCode.InstructionNotBatchable,
+    "The instruction isn't allowed in batches",
+  ],
+  [
+    LangErrorCode.InstructionBatchMismatch,
+    "The batch gives more or fewer account counts than instructions",
+  ],
 
   // Idl instructions.
   [
@@ -442,6 +550,19 @@ export const LangErrorMessage = new Map([
     "A mint decimals constraint was violated",
   ],
   [LangErrorCode.ConstraintSpace, "A space constraint was violated"],
+  [
+    LangErrorCode.ConstraintStateMachine,
+    "A state machine constraint was violated",
+  ],
+  [
+    LangErrorCode.ConstraintDuplicateMutableAccount,
+    "A duplicate mutable account constraint was violated",
+  ],
+  [
+    LangErrorCode.ConstraintMetadataAddress,
+    "A metadata address constraint was violated",
+  ],
+  [LangErrorCode.ConstraintMetadata, "A metadata constraint was violated"],
 
   // Require.
   [LangErrorCode.RequireViolated, "A require expression was violated"],
@@ -513,6 +634,10 @@ export const LangErrorMessage = new Map([
     LangErrorCode.AccountDuplicateReallocs,
     "The account was duplicated for more than one reallocation",
   ],
+  [
+    LangErrorCode.AccountChecksumMismatch,
+    "The checksum of the account data doesn't match its content",
+  ],
 
   // State.
   [
@@ -525,6 +650,14 @@ export const LangErrorMessage = new Map([
     LangErrorCode.DeclaredProgramIdMismatch,
     "The declared program id does not match the actual program id",
   ],
+  [
+    LangErrorCode.StateMachineTransition,
+    "The state machine of the account doesn't allow the transition",
+  ],
+  [
+    LangErrorCode.ArithmeticOverflow,
+    "An arithmetic operation overflowed or divided by zero",
+  ],
 
   // Deprecated
   [
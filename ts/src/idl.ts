@@ -7,10 +7,13 @@ export type Idl = {
   name: string;
   docs?: string[];
   instructions: IdlInstruction[];
+  instructionEnum?: IdlInstructionEnum;
+  fallback?: IdlFallback;
   state?: IdlState;
   accounts?: IdlAccountDef[];
   types?: IdlTypeDef[];
   events?: IdlEvent[];
+  externalMessages?: IdlExternalMessage[];
   errors?: IdlErrorCode[];
   constants?: IdlConstant[];
   metadata?: IdlMetadata;
@@ -22,17 +25,46 @@ export type IdlConstant = {
   name: string;
   type: IdlType;
   value: string;
+  evaluated?: IdlConstValue;
+  module?: string;
 };
 
+export type IdlConstValue =
+  | { bool: boolean }
+  | { int: string }
+  | { float: number }
+  | { string: string }
+  | { bytes: number[] }
+  | { publicKey: string }
+  | { array: IdlConstValue[] };
+
 export type IdlEvent = {
   name: string;
   fields: IdlEventField[];
+  transports?: IdlEventTransport[];
+};
+
+export type IdlEventTransport = "log" | "cpi";
+
+// A payload defined outside of the program and consumed by its instructions,
+// given by `#[external_message(..)]`.
+export type IdlExternalMessage = {
+  name: string;
+  docs?: string[];
+  format?: string;
+  emitter?: { chain?: number; address: string };
+  // The account holding the verified message, and the program owning it.
+  verification?: { account: string; owner?: string };
+  instructions?: string[];
+  fields: IdlField[];
 };
 
 export type IdlEventField = {
   name: string;
   type: IdlType;
   index: boolean;
+  // Set for fields marked with `#[index(unique)]`.
+  unique?: boolean;
 };
 
 export type IdlInstruction = {
@@ -41,6 +73,74 @@ export type IdlInstruction = {
   accounts: IdlAccountItem[];
   args: IdlField[];
   returns?: IdlType;
+  // Namespace of the discriminator, e.g. the trait name for `#[interface]`
+  // implementations. Defaults to "global" ("state" for state methods).
+  namespace?: string;
+  // Discriminator of the instruction data, when it isn't the 8 byte sighash,
+  // e.g. when shortened with `discriminator_len`.
+  discriminator?: number[];
+  // Preconditions checked by `#[access_control(..)]` before the handler runs.
+  guards?: IdlGuard[];
+  // Type of the accounts that can be appended to the instruction's, given by
+  // `#[remaining_accounts(..)]`.
+  remainingAccounts?: IdlRemainingAccounts;
+  // Slot, or unix timestamp, from which the instruction fails, given by
+  // `#[sunset(..)]`.
+  sunset?: IdlSunset;
+  // Compute units and bytes of heap the instruction needs, given by
+  // `#[compute_budget(..)]`.
+  computeBudget?: IdlComputeBudget;
+  // Pairs of accounts that can't be given the same key, checked by
+  // `#[distinct_mut]`.
+  distinctAccounts?: [string, string][];
+  // Whether the instruction is administrative, given by `#[admin]`.
+  admin?: boolean;
+  // Instructions the generated `batch` instruction can run, given by
+  // `#[batch]` on their handlers. Only set on the `batch` instruction.
+  batch?: string[];
+  // Accounts the transaction must be signed with, and PDAs the program signs
+  // for itself.
+  signers?: IdlSigners;
+};
+
+// Accounts are named as in the flattened account list, e.g. "market.bids".
+export type IdlSigners = {
+  accounts?: string[];
+  pdas?: string[];
+};
+
+export type IdlSunset = { slot: number } | { timestamp: number };
+
+export type IdlComputeBudget = {
+  units?: number;
+  heap?: number;
+};
+
+export type IdlRemainingAccounts = {
+  account: string;
+  isMut: boolean;
+};
+
+// A call made by `#[access_control(..)]`, e.g. `is_admin(&ctx)`.
+export type IdlGuard = {
+  name: string;
+  args?: string[];
+};
+
+export type IdlInstructionEnum = {
+  name: string;
+  variants: IdlInstructionEnumVariant[];
+};
+
+export type IdlInstructionEnumVariant = {
+  name: string;
+  instruction: string;
+  discriminator: number[];
+};
+
+export type IdlFallback = {
+  name: string;
+  docs?: string[];
 };
 
 export type IdlState = {
@@ -56,13 +156,41 @@ export type IdlAccount = {
   name: string;
   isMut: boolean;
   isSigner: boolean;
+  isOptional?: boolean;
+  isInitIfNeeded?: boolean;
+  space?: number;
   docs?: string[];
+  token?: IdlTokenAccount;
+  realloc?: IdlRealloc;
   pda?: IdlPda;
+  kind?: IdlAccountKind;
+  address?: string;
+  programs?: string[];
+  close?: string;
 };
 
+export type IdlTokenAccount = {
+  mint?: string;
+  authority?: string;
+  isAssociated?: boolean;
+};
+
+export type IdlRealloc = {
+  payer?: string;
+  space?: number;
+  zero?: boolean;
+};
+
+export type IdlAccountKind =
+  | { sysvar: string }
+  | { program: string }
+  | { interface: string };
+
 export type IdlPda = {
   seeds: IdlSeed[];
   programId?: IdlSeed;
+  // Account field the bump is stored in, e.g. `bump = vault.bump`.
+  bump?: IdlSeed;
 };
 
 export type IdlSeed = any; // TODO
@@ -78,18 +206,64 @@ export type IdlField = {
   name: string;
   docs?: string[];
   type: IdlType;
+  // Constraints of an instruction argument, given by `#[arg(..)]`.
+  constraints?: IdlArgConstraint[];
+  // Former names, given by `#[idl_name(..)]`, accepted in place of the name.
+  aliases?: string[];
 };
 
+// Range bounds are inclusive, and encoded as decimal strings.
+export type IdlArgConstraint =
+  | { range: { min?: string; max?: string } }
+  | "nonZero"
+  | { maxLen: number };
+
 export type IdlTypeDef = {
   name: string;
   docs?: string[];
   type: IdlTypeDefTy;
+  version?: number;
+  migrations?: IdlMigration[];
+  permissions?: IdlPermission[];
+  // Custom discriminator of an account, given by
+  // `#[account(discriminator = ..)]`.
+  discriminator?: number[];
+  // Checksum following the data of an account, given by
+  // `#[account(checksum)]`.
+  checksum?: IdlChecksum;
+  // Serialization of the data, "borsh" if not given.
+  serialization?: IdlSerialization;
+};
+
+// Zero copy types are serialized as their memory layout, which has no padding
+// bytes, and thus matches borsh, unless given `#[zero_copy(unsafe)]`.
+export type IdlSerialization = "borsh" | "bytemuck" | "bytemuckUnsafe";
+
+// CRC-32 of the serialized fields of an account, appended to them as 4 little
+// endian bytes.
+export type IdlChecksum = "crc32";
+
+// A field added by a version of a layout. Older versions don't have it, unless
+// it was renamed, in which case they have it under its previous name.
+export type IdlMigration = {
+  field: string;
+  since: number;
+  renamedFrom?: string;
+};
+
+// A field only the given signer may write.
+export type IdlPermission = {
+  field: string;
+  writableBy: string;
 };
 
 export type IdlAccountDef = {
   name: string;
   docs?: string[];
-  type: IdlTypeDefTyStruct;
+  type: IdlTypeDefTy;
+  version?: number;
+  migrations?: IdlMigration[];
+  permissions?: IdlPermission[];
 };
 
 export type IdlTypeDefTyStruct = {
@@ -127,7 +301,8 @@ export type IdlType =
   | IdlTypeOption
   | IdlTypeCOption
   | IdlTypeVec
-  | IdlTypeArray;
+  | IdlTypeArray
+  | IdlTypeFloat;
 
 // User defined type.
 export type IdlTypeDefined = {
@@ -150,6 +325,11 @@ export type IdlTypeArray = {
   array: [idlType: IdlType, size: number];
 };
 
+// Floating point type emitted with the `allow-with-annotation` float policy.
+export type IdlTypeFloat = {
+  float: { bits: 32 | 64; approximate: boolean };
+};
+
 export type IdlEnumVariant = {
   name: string;
   fields?: IdlEnumFields;
@@ -165,6 +345,7 @@ export type IdlErrorCode = {
   code: number;
   name: string;
   msg?: string;
+  docs?: string[];
 };
 
 // Deterministic IDL address as a function of the program id.
@@ -0,0 +1,164 @@
+import camelCase from "camelcase";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { AnchorError } from "../error.js";
+import { IdlAccount, IdlAccountItem, IdlInstruction } from "../idl.js";
+
+// Constraint errors logging the address an account was expected to have, as
+// the right hand side of the compared pubkeys.
+const EXPECTED_ADDRESS_ERRORS = [
+  "ConstraintSeeds",
+  "ConstraintAddress",
+  "ConstraintAssociated",
+];
+
+// Constraint errors of token accounts logging the address of their mint or
+// authority, as the left hand side of the compared pubkeys, along with the
+// field of the IDL token metadata naming the account that should have it.
+const TOKEN_ERRORS: { [code: string]: "mint" | "authority" } = {
+  ConstraintTokenMint: "mint",
+  ConstraintTokenOwner: "authority",
+};
+
+export type DiscoveryOptions = {
+  // Maximum number of simulations, 10 by default.
+  maxSimulations?: number;
+};
+
+export type DiscoveryResult = {
+  // Accounts whose address was found in the errors of the simulations.
+  discovered: { [name: string]: PublicKey };
+  // Accounts still unknown, given placeholder addresses in the last
+  // simulation. Nested accounts are named `<group>.<account>`.
+  unresolved: string[];
+  // Number of simulations run.
+  simulations: number;
+  // Error of the last simulation, unless it succeeded.
+  error?: unknown;
+};
+
+/**
+ * Finds the accounts of an instruction neither given nor resolved from the
+ * IDL by simulating it, with placeholder addresses for the missing accounts.
+ * The address of an account is then taken from the error of the constraint
+ * it fails, e.g. the address of a PDA from its `ConstraintSeeds` error, and
+ * the accounts depending on it are resolved again before the next
+ * simulation, until it succeeds or no more accounts are found.
+ *
+ * Only top level accounts are discovered, and only from the errors of the
+ * constraints checked after deserializing the account, e.g. the seeds of
+ * `init` accounts.
+ */
+export class AccountsDiscovery {
+  constructor(
+    private _idlIx: IdlInstruction,
+    private _accounts: { [name: string]: any },
+    private _resolve: () => Promise<void>,
+    private _simulate: (accounts: { [name: string]: any }) => Promise<unknown>
+  ) {}
+
+  public async discover(options?: DiscoveryOptions): Promise<DiscoveryResult> {
+    const maxSimulations = options?.maxSimulations ?? 10;
+    const discovered: { [name: string]: PublicKey } = {};
+    let error: unknown = undefined;
+    let unresolved: string[] = [];
+    for (
+      let simulations = 1;
+      simulations <= maxSimulations;
+      simulations += 1
+    ) {
+      await this._resolve();
+      unresolved = [];
+      const placeholders = placeholderAccounts(
+        this._idlIx.accounts,
+        this._accounts,
+        "",
+        unresolved
+      );
+      try {
+        await this._simulate({ ...this._accounts, ...placeholders });
+        return { discovered, unresolved, simulations };
+      } catch (err) {
+        error = err;
+      }
+      if (!(error instanceof AnchorError)) {
+        return { discovered, unresolved, simulations, error };
+      }
+      // Only the accounts given placeholders are filled in.
+      const found = Object.entries(errorAccounts(this._idlIx, error)).filter(
+        ([name]) => unresolved.includes(name)
+      );
+      if (found.length === 0) {
+        return { discovered, unresolved, simulations, error };
+      }
+      for (const [name, address] of found) {
+        this._accounts[name] = address;
+        discovered[name] = address;
+      }
+    }
+    return { discovered, unresolved, simulations: maxSimulations, error };
+  }
+}
+
+/**
+ * The addresses of accounts of the instruction given by a constraint error,
+ * by name.
+ */
+export function errorAccounts(
+  idlIx: IdlInstruction,
+  error: AnchorError
+): { [name: string]: PublicKey } {
+  const { errorCode, origin, comparedValues } = error.error;
+  if (typeof origin !== "string" || !comparedValues) {
+    return {};
+  }
+  const [left, right] = comparedValues;
+  if (!(left instanceof PublicKey) || !(right instanceof PublicKey)) {
+    return {};
+  }
+  const name = camelCase(origin);
+  if (EXPECTED_ADDRESS_ERRORS.includes(errorCode.code)) {
+    return { [name]: right };
+  }
+  const tokenField = TOKEN_ERRORS[errorCode.code];
+  if (tokenField) {
+    const account = idlIx.accounts.find(
+      (acc) => camelCase(acc.name) === name
+    ) as IdlAccount | undefined;
+    const target = account?.token?.[tokenField];
+    // Only paths to the accounts themselves name an account.
+    if (target && !target.includes(".")) {
+      return { [camelCase(target)]: left };
+    }
+  }
+  return {};
+}
+
+// Unique addresses for the missing accounts, so that the instruction can be
+// built, recording their names in `unresolved`. Optional accounts are left
+// out.
+function placeholderAccounts(
+  items: IdlAccountItem[],
+  accounts: { [name: string]: any },
+  prefix: string,
+  unresolved: string[]
+): { [name: string]: any } {
+  const placeholders: { [name: string]: any } = {};
+  for (const item of items) {
+    const name = camelCase(item.name);
+    if ("accounts" in item) {
+      const nested = placeholderAccounts(
+        item.accounts,
+        accounts[name] ?? {},
+        `${prefix}${name}.`,
+        unresolved
+      );
+      if (Object.keys(nested).length > 0) {
+        placeholders[name] = { ...nested, ...accounts[name] };
+      }
+    } else if (accounts[name] === undefined && !item.isOptional) {
+      placeholders[name] = Keypair.generate().publicKey;
+      unresolved.push(`${prefix}${name}`);
+    }
+  }
+  return placeholders;
+}
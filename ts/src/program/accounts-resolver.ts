@@ -2,7 +2,11 @@ import camelCase from "camelcase";
 import { PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
 import { Idl, IdlSeed, IdlAccount } from "../idl.js";
 import * as utf8 from "../utils/bytes/utf8.js";
-import { TOKEN_PROGRAM_ID, ASSOCIATED_PROGRAM_ID } from "../utils/token.js";
+import {
+  TOKEN_PROGRAM_ID,
+  ASSOCIATED_PROGRAM_ID,
+  associatedAddress,
+} from "../utils/token.js";
 import { AllInstructions } from "./namespace/types.js";
 import Provider from "../provider.js";
 import { AccountNamespace } from "./namespace/account.js";
@@ -59,6 +63,13 @@ export class AccountsResolver<IDL extends Idl, I extends AllInstructions<IDL>> {
         continue;
       }
 
+      // Sysvars and well known programs are auto populated with their
+      // address.
+      if (accountDesc.address && !this._accounts[accountDescName]) {
+        this._accounts[accountDescName] = new PublicKey(accountDesc.address);
+        continue;
+      }
+
       // Common accounts are auto populated with magic names by convention.
       if (
         Reflect.has(AccountsResolver.CONST_ACCOUNTS, accountDescName) &&
@@ -84,9 +95,41 @@ export class AccountsResolver<IDL extends Idl, I extends AllInstructions<IDL>> {
         await this.autoPopulatePda(accountDesc);
         continue;
       }
+
+      // Associated token account of accounts already known.
+      if (
+        accountDesc.token?.isAssociated &&
+        !this._accounts[accountDescName]
+      ) {
+        await this.autoPopulateAssociatedToken(accountDesc);
+        continue;
+      }
     }
   }
 
+  private async autoPopulateAssociatedToken(accountDesc: IdlAccount) {
+    const { mint, authority } = accountDesc.token ?? {};
+    // Only paths to the accounts themselves, rather than their data, are
+    // derived.
+    if (
+      !mint ||
+      !authority ||
+      mint.includes(".") ||
+      authority.includes(".")
+    ) {
+      return;
+    }
+    const mintPubkey = this._accounts[camelCase(mint)];
+    const authorityPubkey = this._accounts[camelCase(authority)];
+    if (!mintPubkey || !authorityPubkey) {
+      return;
+    }
+    this._accounts[camelCase(accountDesc.name)] = await associatedAddress({
+      mint: mintPubkey,
+      owner: authorityPubkey,
+    });
+  }
+
   private async autoPopulatePda(accountDesc: IdlAccount) {
     if (!accountDesc.pda || !accountDesc.pda.seeds)
       throw new Error("Must have seeds");
@@ -107,9 +150,7 @@ export class AccountsResolver<IDL extends Idl, I extends AllInstructions<IDL>> {
     }
     switch (accountDesc.pda.programId.kind) {
       case "const":
-        return new PublicKey(
-          this.toBufferConst(accountDesc.pda.programId.value)
-        );
+        return new PublicKey(accountDesc.pda.programId.value);
       case "arg":
         return this.argValue(accountDesc.pda.programId);
       case "account":
@@ -135,6 +176,11 @@ export class AccountsResolver<IDL extends Idl, I extends AllInstructions<IDL>> {
   }
 
   private toBufferConst(seedDesc: IdlSeed): Buffer {
+    // Constant public keys, e.g. the metadata program in the seeds of
+    // metadata accounts, are base58 encoded in the IDL.
+    if (seedDesc.type === "publicKey") {
+      return new PublicKey(seedDesc.value).toBuffer();
+    }
     return this.toBufferValue(seedDesc.type, seedDesc.value);
   }
 
@@ -215,6 +261,15 @@ export class AccountsResolver<IDL extends Idl, I extends AllInstructions<IDL>> {
         let bU64 = Buffer.alloc(8);
         bU64.writeBigUInt64LE(BigInt(value));
         return bU64;
+      case "u128":
+      case "i128":
+        // 128 bit values are string encoded in the IDL, so go through BigInt
+        // rather than a JavaScript number to avoid losing precision.
+        let bU128 = Buffer.alloc(16);
+        const u128 = BigInt.asUintN(128, BigInt(value.toString()));
+        bU128.writeBigUInt64LE(BigInt.asUintN(64, u128));
+        bU128.writeBigUInt64LE(u128 >> BigInt(64), 8);
+        return bU128;
       case "string":
         return Buffer.from(utf8.encode(value));
       case "publicKey":
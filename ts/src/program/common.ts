@@ -46,7 +46,7 @@ export function validateAccounts(
     if ("accounts" in acc) {
       validateAccounts(acc.accounts, accounts[acc.name] as Accounts);
     } else {
-      if (accounts[acc.name] === undefined) {
+      if (accounts[acc.name] === undefined && !acc.isOptional) {
         throw new Error(`Invalid arguments: ${acc.name} not provided.`);
       }
     }
@@ -67,6 +67,8 @@ export type Accounts<A extends IdlAccountItem = IdlAccountItem> = {
 
 type Account<A extends IdlAccountItem> = A extends IdlAccounts
   ? Accounts<A["accounts"][number]>
+  : A extends { isOptional: true }
+  ? Address | null
   : Address;
 
 export function splitArgsAndCtx(
@@ -21,6 +21,10 @@ export * from "./common.js";
 export * from "./context.js";
 export * from "./event.js";
 export * from "./namespace/index.js";
+export type {
+  DiscoveryOptions,
+  DiscoveryResult,
+} from "./accounts-discovery.js";
 
 /**
  * ## Program
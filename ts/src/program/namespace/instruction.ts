@@ -3,6 +3,8 @@ import {
   PublicKey,
   TransactionInstruction,
 } from "@solana/web3.js";
+import BN from "bn.js";
+import { Buffer } from "buffer";
 import {
   Idl,
   IdlAccount,
@@ -42,7 +44,7 @@ export default class InstructionNamespaceFactory {
     ): TransactionInstruction => {
       const [ixArgs, ctx] = splitArgsAndCtx(idlIx, [...args]);
       validateAccounts(idlIx.accounts, ctx.accounts);
-      validateInstruction(idlIx, ...args);
+      validateInstruction(idlIx, ...ixArgs);
 
       const keys = ix.accounts(ctx.accounts);
 
@@ -66,7 +68,8 @@ export default class InstructionNamespaceFactory {
       return InstructionNamespaceFactory.accountsArray(
         accs,
         idlIx.accounts,
-        idlIx.name
+        idlIx.name,
+        programId
       );
     };
 
@@ -76,7 +79,8 @@ export default class InstructionNamespaceFactory {
   public static accountsArray(
     ctx: Accounts | undefined,
     accounts: readonly IdlAccountItem[],
-    ixName?: string
+    ixName?: string,
+    programId?: PublicKey
   ): AccountMeta[] {
     if (!ctx) {
       return [];
@@ -92,10 +96,20 @@ export default class InstructionNamespaceFactory {
           return InstructionNamespaceFactory.accountsArray(
             rpcAccs,
             (acc as IdlAccounts).accounts,
-            ixName
+            ixName,
+            programId
           ).flat();
         } else {
           const account: IdlAccount = acc as IdlAccount;
+          // Omitted optional accounts are given as the program id.
+          if (account.isOptional && ctx[acc.name] == null) {
+            if (programId === undefined) {
+              throw new Error(
+                `Missing program id to omit optional account "${acc.name}"`
+              );
+            }
+            return { pubkey: programId, isWritable: false, isSigner: false };
+          }
           let pubkey;
           try {
             pubkey = translateAddress(ctx[acc.name] as Address);
@@ -187,7 +201,38 @@ export type InstructionEncodeFn<I extends IdlInstruction = IdlInstruction> = (
   ix: any
 ) => Buffer;
 
-// Throws error if any argument required for the `ix` is not given.
+// Throws error if an argument of the `ix` doesn't satisfy its `#[arg(..)]`
+// constraints, which the program would reject it for.
 function validateInstruction(ix: IdlInstruction, ...args: any[]) {
-  // todo
+  ix.args.forEach((ixArg, idx) => {
+    const arg = args[idx];
+    const fail = (reason: string) => {
+      throw new Error(
+        `Argument "${ixArg.name}" of instruction "${ix.name}" ${reason}`
+      );
+    };
+    ixArg.constraints?.forEach((constraint) => {
+      if (constraint === "nonZero") {
+        if (new BN(arg).isZero()) {
+          fail("must not be zero");
+        }
+      } else if ("range" in constraint) {
+        const { min, max } = constraint.range;
+        const value = new BN(arg);
+        if (
+          (min !== undefined && value.lt(new BN(min))) ||
+          (max !== undefined && value.gt(new BN(max)))
+        ) {
+          fail(`must be in the range ${min ?? ""}..=${max ?? ""}`);
+        }
+      } else if ("maxLen" in constraint) {
+        // Strings are limited in bytes, as by the program.
+        const len =
+          typeof arg === "string" ? Buffer.byteLength(arg) : arg.length;
+        if (len > constraint.maxLen) {
+          fail(`must be at most ${constraint.maxLen} long`);
+        }
+      }
+    });
+  });
 }
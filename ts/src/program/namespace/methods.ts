@@ -23,6 +23,11 @@ import { ViewFn } from "./views.js";
 import Provider from "../../provider.js";
 import { AccountNamespace } from "./account.js";
 import { AccountsResolver } from "../accounts-resolver.js";
+import {
+  AccountsDiscovery,
+  DiscoveryOptions,
+  DiscoveryResult,
+} from "../accounts-discovery.js";
 import { Accounts } from "../context.js";
 
 export type MethodsNamespace<
@@ -76,7 +81,7 @@ export class MethodsBuilder<IDL extends Idl, I extends AllInstructions<IDL>> {
     private _viewFn: ViewFn<IDL> | undefined,
     _provider: Provider,
     _programId: PublicKey,
-    _idlIx: AllInstructions<IDL>,
+    private _idlIx: AllInstructions<IDL>,
     _accountNamespace: AccountNamespace<IDL>
   ) {
     this._accountsResolver = new AccountsResolver(
@@ -114,6 +119,39 @@ export class MethodsBuilder<IDL extends Idl, I extends AllInstructions<IDL>> {
     return this;
   }
 
+  /**
+   * Finds the accounts that are neither given nor resolved from the IDL by
+   * simulating the instruction, taking their address from the errors of the
+   * constraints they fail, e.g. the address of a PDA whose seeds aren't in
+   * the IDL. The accounts found are added to the builder.
+   *
+   * ```javascript
+   * const { unresolved } = await program.methods
+   *   .swap(amount)
+   *   .accounts({ pool, user })
+   *   .discoverAccounts();
+   * ```
+   */
+  public async discoverAccounts(
+    options?: DiscoveryOptions
+  ): Promise<DiscoveryResult> {
+    const discovery = new AccountsDiscovery(
+      this._idlIx,
+      this._accounts,
+      () => this._accountsResolver.resolve(),
+      (accounts) =>
+        // @ts-ignore
+        this._simulateFn(...this._args, {
+          accounts,
+          signers: this._signers,
+          remainingAccounts: this._remainingAccounts,
+          preInstructions: this._preInstructions,
+          postInstructions: this._postInstructions,
+        })
+    );
+    return discovery.discover(options);
+  }
+
   public signers(signers: Array<Signer>): MethodsBuilder<IDL, I> {
     this._signers = this._signers.concat(signers);
     return this;
@@ -118,7 +118,8 @@ export class StateClient<IDL extends Idl> {
               InstructionNamespaceFactory.accountsArray(
                 accounts,
                 m.accounts,
-                m.name
+                m.name,
+                programId
               )
             );
           };
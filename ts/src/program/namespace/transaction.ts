@@ -1,4 +1,9 @@
-import { Transaction } from "@solana/web3.js";
+import { Buffer } from "buffer";
+import {
+  PublicKey,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
 import { Idl, IdlInstruction } from "../../idl.js";
 import { splitArgsAndCtx } from "../context.js";
 import { InstructionFn } from "./instruction.js";
@@ -19,8 +24,19 @@ export default class TransactionFactory {
       if (ctx.preInstructions && ctx.instructions) {
         throw new Error("instructions is deprecated, use preInstructions");
       }
-      ctx.preInstructions?.forEach((ix) => tx.add(ix));
-      ctx.instructions?.forEach((ix) => tx.add(ix));
+      const preInstructions = [
+        ...(ctx.preInstructions ?? []),
+        ...(ctx.instructions ?? []),
+      ];
+      // The budget given by the caller takes precedence.
+      if (
+        !preInstructions.some((ix) =>
+          ix.programId.equals(COMPUTE_BUDGET_PROGRAM_ID)
+        )
+      ) {
+        computeBudgetInstructions(idlIx).forEach((ix) => tx.add(ix));
+      }
+      preInstructions.forEach((ix) => tx.add(ix));
       tx.add(ixFn(...args));
       ctx.postInstructions?.forEach((ix) => tx.add(ix));
       return tx;
@@ -30,6 +46,46 @@ export default class TransactionFactory {
   }
 }
 
+/**
+ * Address of the compute budget program.
+ */
+export const COMPUTE_BUDGET_PROGRAM_ID = new PublicKey(
+  "ComputeBudget111111111111111111111111111111"
+);
+
+/**
+ * The instructions of the compute budget program requesting the compute
+ * units and heap an instruction needs, given by `#[compute_budget(..)]`.
+ */
+export function computeBudgetInstructions(
+  idlIx: IdlInstruction
+): TransactionInstruction[] {
+  const budget = idlIx.computeBudget;
+  const ixs: TransactionInstruction[] = [];
+  // `RequestHeapFrame` and `SetComputeUnitLimit`, each with a u32.
+  if (budget?.heap !== undefined) {
+    ixs.push(computeBudgetInstruction(1, budget.heap));
+  }
+  if (budget?.units !== undefined) {
+    ixs.push(computeBudgetInstruction(2, budget.units));
+  }
+  return ixs;
+}
+
+function computeBudgetInstruction(
+  tag: number,
+  value: number
+): TransactionInstruction {
+  const data = Buffer.alloc(5);
+  data.writeUInt8(tag, 0);
+  data.writeUInt32LE(value, 1);
+  return new TransactionInstruction({
+    keys: [],
+    programId: COMPUTE_BUDGET_PROGRAM_ID,
+    data,
+  });
+}
+
 /**
  * The namespace provides functions to build [[Transaction]] objects for each
  * method of a program.
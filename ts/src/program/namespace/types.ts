@@ -127,6 +127,8 @@ export type DecodeType<T extends IdlType, Defined> = T extends keyof TypeMap
   ? TypeMap[T["vec"]][]
   : T extends { array: [defined: keyof TypeMap, size: number] }
   ? TypeMap[T["array"][0]][]
+  : T extends { float: { bits: number } }
+  ? number
   : unknown;
 
 /**
@@ -0,0 +1,124 @@
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { AnchorError } from "../src/error";
+import { IdlInstruction } from "../src/idl";
+import {
+  AccountsDiscovery,
+  errorAccounts,
+} from "../src/program/accounts-discovery";
+
+describe("program/accounts-discovery", () => {
+  const idlIx: IdlInstruction = {
+    name: "deposit",
+    accounts: [
+      { name: "user", isMut: false, isSigner: true },
+      { name: "mint", isMut: false, isSigner: false },
+      {
+        name: "vault",
+        isMut: true,
+        isSigner: false,
+        token: { mint: "mint", authority: "vaultAuthority" },
+      },
+      { name: "vaultAuthority", isMut: false, isSigner: false },
+    ],
+    args: [],
+  };
+
+  const constraintError = (
+    code: string,
+    origin: string,
+    left: PublicKey,
+    right: PublicKey
+  ) =>
+    new AnchorError(
+      { code, number: 0 },
+      "A constraint was violated",
+      [],
+      [],
+      origin,
+      [left, right]
+    );
+
+  it("takes the expected address of PDAs", () => {
+    const [given, expected] = [Keypair.generate(), Keypair.generate()];
+    const error = constraintError(
+      "ConstraintSeeds",
+      "vault_authority",
+      given.publicKey,
+      expected.publicKey
+    );
+    expect(errorAccounts(idlIx, error)).toEqual({
+      vaultAuthority: expected.publicKey,
+    });
+  });
+
+  it("takes the mint of token accounts", () => {
+    const [actual, given] = [Keypair.generate(), Keypair.generate()];
+    const error = constraintError(
+      "ConstraintTokenMint",
+      "vault",
+      actual.publicKey,
+      given.publicKey
+    );
+    expect(errorAccounts(idlIx, error)).toEqual({ mint: actual.publicKey });
+  });
+
+  it("simulates until all accounts are found", async () => {
+    const user = Keypair.generate().publicKey;
+    const vault = Keypair.generate().publicKey;
+    const mint = Keypair.generate().publicKey;
+    const vaultAuthority = Keypair.generate().publicKey;
+    const accounts: { [name: string]: any } = { user, vault };
+    const simulated: { [name: string]: any }[] = [];
+    const discovery = new AccountsDiscovery(
+      idlIx,
+      accounts,
+      async () => {},
+      async (given) => {
+        simulated.push(given);
+        if (!given.mint.equals(mint)) {
+          throw constraintError(
+            "ConstraintTokenMint",
+            "vault",
+            mint,
+            given.mint
+          );
+        }
+        if (!given.vaultAuthority.equals(vaultAuthority)) {
+          throw constraintError(
+            "ConstraintSeeds",
+            "vault_authority",
+            given.vaultAuthority,
+            vaultAuthority
+          );
+        }
+      }
+    );
+    const result = await discovery.discover();
+    expect(result.simulations).toBe(3);
+    expect(result.discovered).toEqual({ mint, vaultAuthority });
+    expect(result.unresolved).toEqual([]);
+    expect(result.error).toBeUndefined();
+    expect(accounts).toEqual({ user, vault, mint, vaultAuthority });
+    expect(simulated[0].user).toBe(user);
+  });
+
+  it("stops when an account can't be found", async () => {
+    const discovery = new AccountsDiscovery(
+      idlIx,
+      {},
+      async () => {},
+      async () => {
+        throw new Error("Blockhash not found");
+      }
+    );
+    const result = await discovery.discover();
+    expect(result.simulations).toBe(1);
+    expect(result.unresolved).toEqual([
+      "user",
+      "mint",
+      "vault",
+      "vaultAuthority",
+    ]);
+    expect(result.error).toBeInstanceOf(Error);
+  });
+});
@@ -0,0 +1,125 @@
+import * as assert from "assert";
+import { BorshCoder } from "../src";
+import { ACCOUNT_DISCRIMINATOR_SIZE } from "../src/coder/borsh/accounts";
+
+describe("coder.accounts", () => {
+  test("Can encode and decode accounts without fields", async () => {
+    const idl = {
+      version: "0.0.0",
+      name: "markers",
+      instructions: [
+        {
+          name: "initialize",
+          accounts: [],
+          args: [],
+        },
+      ],
+      accounts: [
+        {
+          name: "Marker",
+          type: {
+            kind: "struct" as const,
+            fields: [],
+          },
+        },
+      ],
+    };
+    const coder = new BorshCoder(idl);
+
+    const encoded = await coder.accounts.encode("Marker", {});
+
+    assert.strictEqual(encoded.length, ACCOUNT_DISCRIMINATOR_SIZE);
+    assert.strictEqual(
+      coder.accounts.size(idl.accounts[0]),
+      ACCOUNT_DISCRIMINATOR_SIZE
+    );
+    assert.deepEqual(coder.accounts.decode("Marker", encoded), {});
+  });
+
+  test("Can encode accounts with the aliases of their fields", async () => {
+    const idl = {
+      version: "0.0.0",
+      name: "vault",
+      instructions: [
+        {
+          name: "setFee",
+          accounts: [],
+          args: [{ name: "feeBps", type: "u16" as const, aliases: ["fee"] }],
+        },
+      ],
+      accounts: [
+        {
+          name: "Vault",
+          type: {
+            kind: "struct" as const,
+            fields: [
+              {
+                name: "feeBps",
+                type: "u16" as const,
+                aliases: ["feeRate"],
+              },
+            ],
+          },
+        },
+      ],
+    };
+    const coder = new BorshCoder(idl);
+
+    const encoded = await coder.accounts.encode("Vault", { feeRate: 30 });
+
+    assert.deepEqual(
+      encoded,
+      await coder.accounts.encode("Vault", { feeBps: 30 })
+    );
+    assert.deepEqual(coder.accounts.decode("Vault", encoded), { feeBps: 30 });
+    assert.deepEqual(
+      coder.instruction.encode("setFee", { fee: 30 }),
+      coder.instruction.encode("setFee", { feeBps: 30 })
+    );
+  });
+
+  test("Can encode and decode enum accounts", async () => {
+    const idl = {
+      version: "0.0.0",
+      name: "escrow",
+      instructions: [
+        {
+          name: "settle",
+          accounts: [],
+          args: [],
+        },
+      ],
+      accounts: [
+        {
+          name: "Escrow",
+          type: {
+            kind: "enum" as const,
+            variants: [
+              {
+                name: "Open",
+                fields: [{ name: "amount", type: "u8" as const }],
+              },
+              { name: "Canceled" },
+            ],
+          },
+        },
+      ],
+    };
+    const coder = new BorshCoder(idl);
+
+    const open = await coder.accounts.encode("Escrow", {
+      open: { amount: 5 },
+    });
+    const canceled = await coder.accounts.encode("Escrow", { canceled: {} });
+
+    assert.strictEqual(open.length, ACCOUNT_DISCRIMINATOR_SIZE + 2);
+    assert.strictEqual(open[ACCOUNT_DISCRIMINATOR_SIZE], 0);
+    assert.strictEqual(canceled[ACCOUNT_DISCRIMINATOR_SIZE], 1);
+    assert.deepEqual(coder.accounts.decode("Escrow", open), {
+      open: { amount: 5 },
+    });
+    assert.deepEqual(coder.accounts.decode("Escrow", canceled), {
+      canceled: {},
+    });
+  });
+});
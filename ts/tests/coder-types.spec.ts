@@ -1,4 +1,5 @@
 import * as assert from "assert";
+import BN from "bn.js";
 import { BorshCoder } from "../src";
 
 describe("coder.types", () => {
@@ -42,4 +43,49 @@ describe("coder.types", () => {
 
     assert.deepEqual(coder.types.decode("MintInfo", encoded), mintInfo);
   });
+
+  test("Can encode and decode 128 bit integers without losing precision", () => {
+    const idl = {
+      version: "0.0.0",
+      name: "basic_0",
+      instructions: [
+        {
+          name: "initialize",
+          accounts: [],
+          args: [],
+        },
+      ],
+      types: [
+        {
+          name: "Bounds",
+          type: {
+            kind: "struct" as const,
+            fields: [
+              {
+                name: "max",
+                type: "u128" as const,
+              },
+              {
+                name: "min",
+                type: "i128" as const,
+              },
+            ],
+          },
+        },
+      ],
+    };
+    const coder = new BorshCoder(idl);
+
+    const bounds = {
+      max: new BN("340282366920938463463374607431768211455"),
+      min: new BN("-170141183460469231731687303715884105728"),
+    };
+    const decoded = coder.types.decode(
+      "Bounds",
+      coder.types.encode("Bounds", bounds)
+    );
+
+    assert.strictEqual(decoded.max.toString(), bounds.max.toString());
+    assert.strictEqual(decoded.min.toString(), bounds.min.toString());
+  });
 });
@@ -1,4 +1,10 @@
-import { ProgramErrorStack, AnchorError } from "../src/error";
+import {
+  ProgramErrorStack,
+  AnchorError,
+  ProgramError,
+  ErrorMessages,
+  formatErrorMessage,
+} from "../src/error";
 
 describe("ProgramErrorStack", () => {
   test("basic", () => {
@@ -328,3 +334,47 @@ describe("AnchorError", () => {
     ]);
   });
 });
+
+describe("ErrorMessages", () => {
+  const en = {
+    program: "vault",
+    locale: "en",
+    messages: {
+      "6000": {
+        name: "AmountTooLarge",
+        message: "Amount {amount} exceeds {max}",
+        placeholders: ["amount", "max"],
+      },
+      "6001": { name: "Locked", message: "The vault is locked" },
+    },
+  };
+  const fr = {
+    program: "vault",
+    locale: "fr",
+    messages: {
+      "6000": {
+        name: "AmountTooLarge",
+        message: "Le montant {amount} dépasse {max}",
+        placeholders: ["amount", "max"],
+      },
+    },
+  };
+
+  it("formats placeholders", () => {
+    expect(formatErrorMessage("{a} {{a}} {b}", { a: 1 })).toEqual(
+      "1 {a} {b}"
+    );
+  });
+
+  it("falls back to the next bundle", () => {
+    const messages = new ErrorMessages([fr, en]);
+    expect(messages.message(6000, { amount: 5, max: 3 })).toEqual(
+      "Le montant 5 dépasse 3"
+    );
+    expect(messages.message(6001)).toEqual("The vault is locked");
+    expect(messages.message(6002)).toBeUndefined();
+    expect(
+      messages.fromError(new ProgramError(6001, "The vault is locked"))
+    ).toEqual("The vault is locked");
+  });
+});
@@ -1,4 +1,6 @@
-import TransactionFactory from "../src/program/namespace/transaction";
+import TransactionFactory, {
+  COMPUTE_BUDGET_PROGRAM_ID,
+} from "../src/program/namespace/transaction";
 import InstructionFactory from "../src/program/namespace/instruction";
 import { BorshCoder } from "../src";
 import { PublicKey, TransactionInstruction } from "@solana/web3.js";
@@ -68,4 +70,42 @@ describe("Transaction", () => {
       txItem({ accounts: {}, preInstructions: [preIx], instructions: [preIx] })
     ).toThrow(new Error("instructions is deprecated, use preInstructions"));
   });
+
+  it("should add the compute budget of the method ix before it", async () => {
+    const budgetIdl = {
+      ...idl,
+      instructions: [
+        {
+          ...idl.instructions[0],
+          computeBudget: { units: 400000, heap: 65536 },
+        },
+      ],
+    };
+    const coder = new BorshCoder(budgetIdl);
+    const programId = PublicKey.default;
+    const ixItem = InstructionFactory.build(
+      budgetIdl.instructions[0],
+      (ixName, ix) => coder.instruction.encode(ixName, ix),
+      programId
+    );
+    const txItem = TransactionFactory.build(
+      budgetIdl.instructions[0],
+      ixItem
+    );
+
+    const tx = txItem({ accounts: {}, preInstructions: [preIx] });
+    expect(tx.instructions.length).toBe(4);
+    expect(tx.instructions[0].programId).toEqual(COMPUTE_BUDGET_PROGRAM_ID);
+    expect(tx.instructions[0].data).toEqual(
+      Buffer.from([1, 0x00, 0x00, 0x01, 0x00])
+    );
+    expect(tx.instructions[1].data).toEqual(
+      Buffer.from([2, 0x80, 0x1a, 0x06, 0x00])
+    );
+    expect(tx.instructions[2]).toMatchObject(preIx);
+
+    const budgetIx = tx.instructions[1];
+    const ownBudgetTx = txItem({ accounts: {}, preInstructions: [budgetIx] });
+    expect(ownBudgetTx.instructions.length).toBe(2);
+  });
 });